@@ -2,6 +2,8 @@
 //!
 //! Uses hypersdk's `approve_agent()` method for reliable EIP-712 signing.
 
+use std::path::Path;
+
 use alloy::signers::local::PrivateKeySigner;
 use anyhow::Result;
 use hypersdk::Address;
@@ -33,15 +35,27 @@ pub fn agent_name_today() -> String {
     chrono::Utc::now().format("hlmcp-%m%d%y").to_string()
 }
 
-pub fn save_agent_key_to_env(agent_key_hex: &str) -> Result<std::path::PathBuf> {
+/// Write `var=0x<key>` to the top of the env file, replacing any earlier line
+/// for `var`. `var` is `HYPERLIQUID_AGENT_PRIVATE_KEY` or a profile's
+/// `HYPERLIQUID_PROFILE_<NAME>_AGENT_PRIVATE_KEY`.
+pub fn save_agent_key_to_env(
+    var: &str,
+    agent_key_hex: &str,
+    env_path: Option<&Path>,
+) -> Result<std::path::PathBuf> {
     use std::io::Write;
 
-    let home =
-        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
-    let env_dir = home.join(".config/hyperliquid-mcp");
-    std::fs::create_dir_all(&env_dir)?;
+    let env_path = match env_path {
+        Some(path) => path.to_path_buf(),
+        None => config::default_env_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?,
+    };
+    let env_dir = env_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(env_dir)?;
 
-    let env_path = home.join(config::ENV_FILE_PATH);
     let tmp_path = env_dir.join(".env.tmp");
 
     let existing = std::fs::read_to_string(&env_path).unwrap_or_default();
@@ -49,15 +63,14 @@ pub fn save_agent_key_to_env(agent_key_hex: &str) -> Result<std::path::PathBuf>
         .lines()
         .filter(|line| {
             let trimmed = line.trim();
-            !trimmed.starts_with("HYPERLIQUID_AGENT_PRIVATE_KEY=")
+            !trimmed
+                .strip_prefix(var)
+                .is_some_and(|rest| rest.starts_with('='))
         })
         .map(|l| l.to_string())
         .collect();
 
-    lines.insert(
-        0,
-        format!("HYPERLIQUID_AGENT_PRIVATE_KEY=0x{agent_key_hex}"),
-    );
+    lines.insert(0, format!("{var}=0x{agent_key_hex}"));
 
     // Write atomically
     {
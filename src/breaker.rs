@@ -0,0 +1,246 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::tools::timestamps::fmt_clock;
+
+/// Failures count as consecutive only while each lands within this long of
+/// the previous one.
+pub const FAILURE_WINDOW: Duration = Duration::from_secs(120);
+
+/// How often an open breaker probes the exchange to see if it recovered.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Actions go through.
+    Closed,
+    /// Repeated failures; actions are refused until a probe succeeds.
+    Open,
+    /// A probe is in flight; actions are still refused.
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn label(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half-open (probing)",
+        }
+    }
+}
+
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub opened_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+struct Inner {
+    state: BreakerState,
+    consecutive: u32,
+    last_failure: Option<Instant>,
+    opened_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// Stops signed actions after repeated exchange failures (maintenance, rate
+/// limiting) so each tool call doesn't fail on its own with a raw error.
+/// Shared by all profiles, since they talk to the same exchange.
+pub struct CircuitBreaker {
+    /// Consecutive failures that open the breaker; zero disables it.
+    threshold: u32,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                consecutive: 0,
+                last_failure: None,
+                opened_at: None,
+                last_error: None,
+            }),
+        }
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The exchange answered; close the breaker and reset the count.
+    pub fn record_success(&self) {
+        let mut inner = self.lock();
+        if inner.state != BreakerState::Closed {
+            tracing::info!("Exchange responding again; resuming signed actions");
+        }
+        inner.state = BreakerState::Closed;
+        inner.consecutive = 0;
+        inner.last_failure = None;
+        inner.opened_at = None;
+    }
+
+    /// Count a failed exchange call. Returns true when this failure opened
+    /// the breaker, in which case the caller should start probing.
+    pub fn record_failure(&self, message: &str) -> bool {
+        let mut inner = self.lock();
+        let now = Instant::now();
+        if inner
+            .last_failure
+            .is_some_and(|last| now.duration_since(last) > FAILURE_WINDOW)
+        {
+            inner.consecutive = 0;
+        }
+        inner.consecutive += 1;
+        inner.last_failure = Some(now);
+        inner.last_error = Some(message.to_string());
+
+        let trips = self.threshold > 0
+            && inner.state == BreakerState::Closed
+            && inner.consecutive >= self.threshold;
+        if trips {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(Utc::now());
+            tracing::warn!(
+                failures = inner.consecutive,
+                error = message,
+                "Exchange appears degraded; pausing signed actions"
+            );
+        }
+        trips
+    }
+
+    /// Move an open breaker to half-open before a probe. False once the
+    /// breaker has closed, which ends probing.
+    pub fn begin_probe(&self) -> bool {
+        let mut inner = self.lock();
+        match inner.state {
+            BreakerState::Closed => false,
+            BreakerState::Open | BreakerState::HalfOpen => {
+                inner.state = BreakerState::HalfOpen;
+                true
+            }
+        }
+    }
+
+    /// A probe failed; stay open until the next one.
+    pub fn probe_failed(&self, message: &str) {
+        let mut inner = self.lock();
+        if inner.state == BreakerState::HalfOpen {
+            inner.state = BreakerState::Open;
+            inner.last_error = Some(message.to_string());
+        }
+    }
+
+    /// Refusal message while the breaker is open or probing.
+    pub fn check(&self) -> Result<(), String> {
+        let inner = self.lock();
+        if inner.state == BreakerState::Closed {
+            return Ok(());
+        }
+        let since = inner
+            .opened_at
+            .map(|t| format!(" since {}", fmt_clock(t)))
+            .unwrap_or_default();
+        let last_error = inner
+            .last_error
+            .as_deref()
+            .map(|e| format!(" Last error: {e}."))
+            .unwrap_or_default();
+        Err(format!(
+            "Exchange appears degraded{since} ({} consecutive failures); read-only tools, cancels and \
+             closes still work. New orders and transfers resume automatically once the exchange \
+             responds to a probe (every {} s).{last_error}",
+            inner.consecutive,
+            PROBE_INTERVAL.as_secs(),
+        ))
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let inner = self.lock();
+        BreakerStatus {
+            state: inner.state,
+            consecutive_failures: inner.consecutive,
+            opened_at: inner.opened_at,
+            last_error: inner.last_error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_at_threshold_and_refuses_actions() {
+        let breaker = CircuitBreaker::new(3);
+        assert!(!breaker.record_failure("timeout"));
+        assert!(!breaker.record_failure("timeout"));
+        assert_eq!(breaker.check(), Ok(()));
+        assert!(breaker.record_failure("429 Too Many Requests"));
+        assert_eq!(breaker.status().state, BreakerState::Open);
+
+        let refusal = breaker.check().unwrap_err();
+        assert!(refusal.contains("(3 consecutive failures)"), "{refusal}");
+        assert!(refusal.ends_with("Last error: 429 Too Many Requests."));
+        // Later failures don't trip it again, so only one probe loop starts.
+        assert!(!breaker.record_failure("timeout"));
+    }
+
+    #[test]
+    fn failures_outside_the_window_start_a_new_count() {
+        let breaker = CircuitBreaker::new(2);
+        assert!(!breaker.record_failure("timeout"));
+        breaker.lock().last_failure = Instant::now().checked_sub(FAILURE_WINDOW * 2);
+        assert!(!breaker.record_failure("timeout"));
+        assert_eq!(breaker.status().consecutive_failures, 1);
+        assert!(breaker.record_failure("timeout"));
+    }
+
+    #[test]
+    fn probes_move_through_half_open_until_recovery() {
+        let breaker = CircuitBreaker::new(1);
+        assert!(!breaker.begin_probe());
+        assert!(breaker.record_failure("502 Bad Gateway"));
+
+        assert!(breaker.begin_probe());
+        assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+        assert!(breaker.check().is_err());
+        breaker.probe_failed("still down");
+        assert_eq!(breaker.status().state, BreakerState::Open);
+        assert!(
+            breaker
+                .check()
+                .unwrap_err()
+                .ends_with("Last error: still down.")
+        );
+
+        assert!(breaker.begin_probe());
+        breaker.record_success();
+        let status = breaker.status();
+        assert_eq!(status.state, BreakerState::Closed);
+        assert_eq!(status.consecutive_failures, 0);
+        assert!(status.opened_at.is_none());
+        assert_eq!(breaker.check(), Ok(()));
+        assert!(!breaker.begin_probe());
+    }
+
+    #[test]
+    fn zero_threshold_never_opens() {
+        let breaker = CircuitBreaker::new(0);
+        for _ in 0..10 {
+            assert!(!breaker.record_failure("timeout"));
+        }
+        assert_eq!(breaker.check(), Ok(()));
+    }
+}
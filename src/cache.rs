@@ -1,12 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 
-use hypersdk::hypercore::ClearinghouseState;
-use hypersdk::hypercore::types::BasicOrder;
+use hypersdk::hypercore::types::{
+    BasicOrder, Candle, Fill, FundingRate, L2Book, OrderStatus, OrderUpdate, Subscription,
+};
+use hypersdk::hypercore::ws::ConnectionHandle;
+use hypersdk::hypercore::{ClearinghouseState, Side};
 use rust_decimal::Decimal;
-use serde_json::Value;
+use rust_decimal::prelude::ToPrimitive;
 use tokio::sync::RwLock;
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
+
+use crate::events::{EventKind, EventLog};
+use crate::liquidity::LiquidityAlerts;
+use crate::meta::{AssetCtx, MarketMeta};
 
 pub struct CachedValue<T> {
     pub value: T,
@@ -26,22 +35,276 @@ impl<T> CachedValue<T> {
     }
 }
 
+/// Finished watched orders are kept this long before being dropped.
+const WATCH_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Upper bound on cached candle series. The oldest is evicted beyond it.
+const MAX_CANDLE_SERIES: usize = 64;
+
+/// The most recent candles for one (coin, interval), as fetched. `count` is
+/// the number of periods requested, which can exceed `candles.len()` for
+/// recently listed markets.
+pub struct CandleSeries {
+    pub count: u64,
+    pub candles: Vec<Candle>,
+}
+
+impl CandleSeries {
+    /// The most recent `count` candles, if this series spans that many periods.
+    pub fn tail(&self, count: u64) -> Option<Vec<Candle>> {
+        if count > self.count {
+            return None;
+        }
+        let skip = self.candles.len().saturating_sub(count as usize);
+        Some(self.candles[skip..].to_vec())
+    }
+}
+
+/// The next funding settlement of one perp, from `predictedFundings`.
+#[derive(Debug, Clone, Copy)]
+pub struct PredictedFunding {
+    /// Hourly rate the settlement is expected to apply.
+    pub rate: Decimal,
+    /// When it settles, in milliseconds.
+    pub next_time_ms: u64,
+}
+
+/// Funding history for one perp from `start_ms` onward, oldest first.
+pub struct FundingHistory {
+    pub start_ms: u64,
+    pub rates: Vec<FundingRate>,
+}
+
+/// Fills kept in the recent-fills buffer.
+pub const MAX_RECENT_FILLS: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FillSource {
+    WebSocket,
+    /// Backfilled from `userFills` at startup.
+    Rest,
+}
+
+#[derive(Clone)]
+pub struct RecentFill {
+    pub fill: Fill,
+    pub source: FillSource,
+    /// When this server received the fill, in milliseconds.
+    pub received_at: u64,
+}
+
+/// The account's latest fills, oldest first, fed by the WebSocket feed and a
+/// one-off REST backfill so it starts populated.
+#[derive(Default)]
+pub struct RecentFills {
+    pub fills: VecDeque<RecentFill>,
+    /// Whether the startup backfill has landed. Until then fills from before
+    /// the feed connected may be missing.
+    pub backfilled: bool,
+}
+
+impl RecentFills {
+    /// Fills at or after `since`, if the buffer is known to hold all of them.
+    pub fn covering(&self, since: u64) -> Option<Vec<&Fill>> {
+        let complete = self.fills.len() < MAX_RECENT_FILLS
+            || self.fills.front().is_some_and(|f| f.fill.time <= since);
+        (self.backfilled && complete).then(|| {
+            self.fills
+                .iter()
+                .filter(|f| f.fill.time >= since)
+                .map(|f| &f.fill)
+                .collect()
+        })
+    }
+}
+
+/// Samples kept per coin: a day at the default 30 second interval.
+pub const MAX_BOOK_SAMPLES: usize = 2880;
+
+/// Top of book for one coin at one moment. Depth is USD notional resting
+/// within 0.5% of the mid on each side.
+#[derive(Clone, Copy)]
+pub struct BookSample {
+    pub time: u64,
+    pub bid: f64,
+    pub ask: f64,
+    pub bid_depth: f64,
+    pub ask_depth: f64,
+}
+
+impl BookSample {
+    pub fn spread_bps(&self) -> f64 {
+        (self.ask - self.bid) / ((self.ask + self.bid) / 2.0) * 10_000.0
+    }
+}
+
+/// Perp context snapshots are taken at most this often.
+pub const META_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Snapshots kept: four hours at one a minute, plus a few spare so the
+/// four-hour lookback still finds one.
+pub const MAX_META_SNAPSHOTS: usize = 4 * 60 + 5;
+
+/// The fields of one perp's asset context that deltas are computed from.
+#[derive(Clone, Copy)]
+pub struct CtxPoint {
+    /// Open interest in coins, so price moves don't register as OI change.
+    pub open_interest: f64,
+    /// Hourly funding rate.
+    pub funding: f64,
+    /// Rolling 24h notional volume.
+    pub day_volume: f64,
+}
+
+impl CtxPoint {
+    pub fn from_ctx(ctx: &AssetCtx) -> Option<Self> {
+        Some(Self {
+            open_interest: ctx.open_interest?.to_f64()?,
+            funding: ctx.funding?.to_f64()?,
+            day_volume: ctx.day_ntl_vlm?.to_f64()?,
+        })
+    }
+}
+
+/// Perp contexts from one `metaAndAssetCtxs` poll, by coin.
+pub struct MetaSnapshot {
+    pub time: u64,
+    pub ctxs: HashMap<String, CtxPoint>,
+}
+
+impl MetaSnapshot {
+    fn from_meta(meta: &MarketMeta, time: u64) -> Option<Self> {
+        if meta.ctxs.is_empty() {
+            return None;
+        }
+        let ctxs = meta
+            .universe
+            .iter()
+            .zip(&meta.ctxs)
+            .filter_map(|(asset, ctx)| Some((asset.name.clone(), CtxPoint::from_ctx(ctx)?)))
+            .collect();
+        Some(Self { time, ctxs })
+    }
+}
+
+pub struct WatchedFill {
+    pub tid: u64,
+    pub px: Decimal,
+    pub sz: Decimal,
+    pub time: u64,
+}
+
+/// An order registered with `watch_order`. Fills and status changes are
+/// recorded from the user WebSocket stream.
+pub struct WatchedOrder {
+    pub oid: u64,
+    pub coin: String,
+    pub side: Side,
+    pub limit_px: Decimal,
+    pub orig_sz: Decimal,
+    pub status: OrderStatus,
+    pub fills: Vec<WatchedFill>,
+    pub finished_at: Option<Instant>,
+}
+
+impl WatchedOrder {
+    pub fn filled_sz(&self) -> Decimal {
+        self.fills.iter().map(|f| f.sz).sum()
+    }
+
+    /// Volume-weighted average fill price, if anything has filled.
+    pub fn vwap(&self) -> Option<Decimal> {
+        let filled = self.filled_sz();
+        if filled.is_zero() {
+            return None;
+        }
+        Some(self.fills.iter().map(|f| f.px * f.sz).sum::<Decimal>() / filled)
+    }
+
+    /// Record a fill, ignoring ones already seen (the stream replays a
+    /// snapshot of recent fills on reconnect).
+    pub fn record_fill(&mut self, fill: &Fill) {
+        if self.fills.iter().any(|f| f.tid == fill.tid) {
+            return;
+        }
+        self.fills.push(WatchedFill {
+            tid: fill.tid,
+            px: fill.px,
+            sz: fill.sz,
+            time: fill.time,
+        });
+    }
+
+    pub fn set_status(&mut self, status: OrderStatus) {
+        self.status = status;
+        if status.is_finished() && self.finished_at.is_none() {
+            self.finished_at = Some(Instant::now());
+        }
+    }
+}
+
 pub struct WsCache {
+    /// Whether a WebSocket feed is updating this cache.
+    pub live: bool,
     pub all_mids: watch::Receiver<HashMap<String, Decimal>>,
-    pub meta_cache: RwLock<Option<CachedValue<Value>>>,
-    pub spot_meta_cache: RwLock<Option<CachedValue<Value>>>,
+    pub meta_cache: RwLock<Option<CachedValue<Arc<MarketMeta>>>>,
+    pub spot_meta_cache: RwLock<Option<CachedValue<Arc<MarketMeta>>>>,
     pub clearinghouse_cache: RwLock<Option<CachedValue<ClearinghouseState>>>,
     pub open_orders_cache: RwLock<Option<CachedValue<Vec<BasicOrder>>>>,
+    pub watched_orders: RwLock<HashMap<u64, WatchedOrder>>,
+    /// Candle series keyed by (coin, interval).
+    pub candle_cache: RwLock<HashMap<(String, String), CachedValue<CandleSeries>>>,
+    pub funding_cache: RwLock<HashMap<String, CachedValue<FundingHistory>>>,
+    /// Perps currently at their open interest cap.
+    pub oi_cap_cache: RwLock<Option<CachedValue<Vec<String>>>>,
+    /// Hyperliquid's predicted funding, keyed by coin.
+    pub predicted_funding_cache: RwLock<Option<CachedValue<HashMap<String, PredictedFunding>>>>,
+    /// Ring buffers of sampled top-of-book, keyed by coin.
+    pub book_samples: RwLock<HashMap<String, VecDeque<BookSample>>>,
+    /// Perp contexts about once a minute, oldest first, for OI, volume and
+    /// funding deltas.
+    pub meta_history: RwLock<VecDeque<MetaSnapshot>>,
+    pub recent_fills: RwLock<RecentFills>,
+    /// Fills, triggers and liquidations not yet shown to the user.
+    pub events: EventLog,
+    /// When the last WebSocket message arrived, in milliseconds.
+    pub last_message_ms: AtomicU64,
+    /// Coins where a fill from the feed closed (part of) a perp position.
+    pub position_closes: broadcast::Sender<String>,
+    pub liquidity_alerts: LiquidityAlerts,
+    /// Account value at server start, or before the first destructive tool
+    /// when the startup lookup failed.
+    pub session_equity: OnceLock<Decimal>,
+    /// Subscriptions on the feed, for books watched by liquidity alerts.
+    feed: Option<ConnectionHandle>,
 }
 
 impl WsCache {
-    pub fn new(mids_rx: watch::Receiver<HashMap<String, Decimal>>) -> Self {
+    pub fn new(
+        mids_rx: watch::Receiver<HashMap<String, Decimal>>,
+        feed: Option<ConnectionHandle>,
+    ) -> Self {
         Self {
+            live: feed.is_some(),
             all_mids: mids_rx,
             meta_cache: RwLock::new(None),
             spot_meta_cache: RwLock::new(None),
             clearinghouse_cache: RwLock::new(None),
             open_orders_cache: RwLock::new(None),
+            watched_orders: RwLock::new(HashMap::new()),
+            candle_cache: RwLock::new(HashMap::new()),
+            funding_cache: RwLock::new(HashMap::new()),
+            oi_cap_cache: RwLock::new(None),
+            predicted_funding_cache: RwLock::new(None),
+            book_samples: RwLock::new(HashMap::new()),
+            meta_history: RwLock::new(VecDeque::new()),
+            recent_fills: RwLock::new(RecentFills::default()),
+            events: EventLog::default(),
+            last_message_ms: AtomicU64::new(0),
+            position_closes: broadcast::channel(64).0,
+            liquidity_alerts: LiquidityAlerts::default(),
+            session_equity: OnceLock::new(),
+            feed,
         }
     }
 
@@ -49,4 +312,310 @@ impl WsCache {
         *self.clearinghouse_cache.write().await = None;
         *self.open_orders_cache.write().await = None;
     }
+
+    pub async fn record_order_updates(&self, updates: &[OrderUpdate]) {
+        let mut watched = self.watched_orders.write().await;
+        for update in updates {
+            if let Some(order) = watched.get_mut(&update.order.oid) {
+                order.set_status(update.status);
+            }
+        }
+        self.events.record_order_updates(updates);
+    }
+
+    pub async fn record_fills(&self, fills: &[Fill]) {
+        {
+            let mut watched = self.watched_orders.write().await;
+            for fill in fills {
+                if let Some(order) = watched.get_mut(&fill.oid) {
+                    order.record_fill(fill);
+                }
+            }
+        }
+        let new = self.record_recent_fills(fills, FillSource::WebSocket).await;
+        self.events.record_fills(&new);
+
+        let mut closed: Vec<&str> = fills
+            .iter()
+            .filter(|f| f.dir.starts_with("Close"))
+            .map(|f| f.coin.as_str())
+            .collect();
+        closed.dedup();
+        for coin in closed {
+            // No receivers unless the orphan watch is running.
+            let _ = self.position_closes.send(coin.to_string());
+        }
+    }
+
+    /// Add fills to the recent-fills buffer, skipping trade ids already held
+    /// and keeping it sorted by fill time. Returns the fills that were new.
+    pub async fn record_recent_fills(&self, fills: &[Fill], source: FillSource) -> Vec<Fill> {
+        let mut new = Vec::new();
+        let received_at = chrono::Utc::now().timestamp_millis() as u64;
+        let mut recent = self.recent_fills.write().await;
+        for fill in fills {
+            if recent.fills.iter().any(|f| f.fill.tid == fill.tid) {
+                continue;
+            }
+            new.push(fill.clone());
+            let at = recent.fills.partition_point(|f| f.fill.time <= fill.time);
+            recent.fills.insert(
+                at,
+                RecentFill {
+                    fill: fill.clone(),
+                    source,
+                    received_at,
+                },
+            );
+        }
+        while recent.fills.len() > MAX_RECENT_FILLS {
+            recent.fills.pop_front();
+        }
+        if source == FillSource::Rest {
+            recent.backfilled = true;
+        }
+        new
+    }
+
+    /// The most recent `count` candles from a cached series younger than `ttl`.
+    pub async fn cached_candles(
+        &self,
+        coin: &str,
+        interval: &str,
+        count: u64,
+        ttl: Duration,
+    ) -> Option<Vec<Candle>> {
+        let cache = self.candle_cache.read().await;
+        let entry = cache.get(&(coin.to_string(), interval.to_string()))?;
+        if !entry.is_fresh(ttl) {
+            return None;
+        }
+        entry.value.tail(count)
+    }
+
+    /// Candles from `start_ms` onward, from a cached series younger than `ttl`
+    /// that reaches back that far.
+    pub async fn cached_candles_since(
+        &self,
+        coin: &str,
+        interval: &str,
+        start_ms: u64,
+        ttl: Duration,
+    ) -> Option<Vec<Candle>> {
+        let cache = self.candle_cache.read().await;
+        let entry = cache.get(&(coin.to_string(), interval.to_string()))?;
+        if !entry.is_fresh(ttl) {
+            return None;
+        }
+        let candles = &entry.value.candles;
+        if candles.first()?.open_time > start_ms {
+            return None;
+        }
+        let from = candles.partition_point(|c| c.close_time < start_ms);
+        Some(candles[from..].to_vec())
+    }
+
+    /// Cache a freshly fetched series, evicting the oldest entry when full.
+    pub async fn store_candles(&self, coin: &str, interval: &str, series: CandleSeries) {
+        let key = (coin.to_string(), interval.to_string());
+        let mut cache = self.candle_cache.write().await;
+        if cache.len() >= MAX_CANDLE_SERIES && !cache.contains_key(&key) {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, v)| v.inserted_at)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, CachedValue::new(series));
+    }
+
+    pub async fn record_book_sample(&self, coin: &str, sample: BookSample) {
+        let mut samples = self.book_samples.write().await;
+        let ring = samples.entry(coin.to_string()).or_default();
+        if ring.len() >= MAX_BOOK_SAMPLES {
+            ring.pop_front();
+        }
+        ring.push_back(sample);
+    }
+
+    /// Keep the perp contexts from a `metaAndAssetCtxs` response, unless the
+    /// last snapshot is younger than `META_SNAPSHOT_INTERVAL`.
+    pub async fn record_meta_snapshot(&self, meta: &MarketMeta) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let mut history = self.meta_history.write().await;
+        if history
+            .back()
+            .is_some_and(|s| now - s.time < META_SNAPSHOT_INTERVAL.as_millis() as u64)
+        {
+            return;
+        }
+        let Some(snapshot) = MetaSnapshot::from_meta(meta, now) else {
+            return;
+        };
+        if history.len() >= MAX_META_SNAPSHOTS {
+            history.pop_front();
+        }
+        history.push_back(snapshot);
+    }
+
+    /// Perp contexts from about `ago` ago: the snapshot closest to that time,
+    /// if one is within a tenth of `ago` (at least 90 seconds) of it.
+    pub async fn meta_ago(&self, ago: Duration) -> Option<(u64, HashMap<String, CtxPoint>)> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let target = now.saturating_sub(ago.as_millis() as u64);
+        let tolerance = (ago.as_millis() as u64 / 10).max(90_000);
+        let history = self.meta_history.read().await;
+        history
+            .iter()
+            .min_by_key(|s| s.time.abs_diff(target))
+            .filter(|s| s.time.abs_diff(target) <= tolerance)
+            .map(|s| (s.time, s.ctxs.clone()))
+    }
+
+    /// When the oldest snapshot was taken.
+    pub async fn meta_history_start(&self) -> Option<u64> {
+        self.meta_history.read().await.front().map(|s| s.time)
+    }
+
+    /// Start receiving `coin`'s book for liquidity alerts.
+    pub fn watch_book(&self, coin: &str) {
+        if let Some(feed) = &self.feed {
+            feed.subscribe(Subscription::L2Book { coin: coin.into() });
+        }
+    }
+
+    /// Drop expired liquidity alerts and the books only they needed.
+    pub fn prune_liquidity_alerts(&self) {
+        for coin in self.liquidity_alerts.prune(chrono::Utc::now()) {
+            if let Some(feed) = &self.feed {
+                feed.unsubscribe(Subscription::L2Book { coin });
+            }
+        }
+    }
+
+    /// Scan a book from the feed for liquidity alerts, queueing an event for
+    /// each that fires.
+    pub fn record_book(&self, book: &L2Book) {
+        self.prune_liquidity_alerts();
+        let side = |i: usize| -> Vec<(Decimal, Decimal)> {
+            book.levels[i].iter().map(|l| (l.px, l.sz)).collect()
+        };
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        for text in self
+            .liquidity_alerts
+            .scan(&book.coin, &side(0), &side(1), now)
+        {
+            self.events.record(EventKind::Liquidity, text);
+        }
+    }
+
+    /// Drop watched orders that finished more than an hour ago.
+    pub async fn prune_watched_orders(&self) {
+        self.watched_orders
+            .write()
+            .await
+            .retain(|_, o| o.finished_at.is_none_or(|t| t.elapsed() < WATCH_RETENTION));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000;
+
+    /// One-minute candles opening at each of `minutes`.
+    fn candles(minutes: &[u64]) -> Vec<Candle> {
+        minutes
+            .iter()
+            .map(|&m| Candle {
+                open_time: m * MINUTE,
+                close_time: (m + 1) * MINUTE - 1,
+                coin: "BTC".into(),
+                interval: "1m".into(),
+                open: Decimal::ONE,
+                high: Decimal::ONE,
+                low: Decimal::ONE,
+                close: Decimal::from(m),
+                volume: Decimal::ZERO,
+                num_trades: 0,
+            })
+            .collect()
+    }
+
+    fn opens(candles: &[Candle]) -> Vec<u64> {
+        candles.iter().map(|c| c.open_time / MINUTE).collect()
+    }
+
+    #[test]
+    fn tail_serves_any_count_the_series_spans() {
+        let series = CandleSeries {
+            count: 5,
+            candles: candles(&[10, 11, 12, 13, 14]),
+        };
+        assert_eq!(series.tail(3).as_deref().map(opens), Some(vec![12, 13, 14]));
+        assert_eq!(
+            series.tail(5).as_deref().map(opens),
+            Some(vec![10, 11, 12, 13, 14])
+        );
+        assert!(series.tail(6).is_none());
+
+        // A recently listed market returns fewer candles than requested; the
+        // series still covers every count up to the one it was fetched for.
+        let listed = CandleSeries {
+            count: 10,
+            candles: candles(&[13, 14]),
+        };
+        assert_eq!(listed.tail(8).as_deref().map(opens), Some(vec![13, 14]));
+        assert_eq!(listed.tail(1).as_deref().map(opens), Some(vec![14]));
+        assert!(listed.tail(11).is_none());
+    }
+
+    #[tokio::test]
+    async fn cached_candles_subsume_narrower_ranges() {
+        let (_, mids) = watch::channel(HashMap::new());
+        let cache = WsCache::new(mids, None);
+        let ttl = Duration::from_secs(60);
+        cache
+            .store_candles(
+                "BTC",
+                "1m",
+                CandleSeries {
+                    count: 5,
+                    candles: candles(&[10, 11, 12, 13, 14]),
+                },
+            )
+            .await;
+
+        let by_count = |count| cache.cached_candles("BTC", "1m", count, ttl);
+        assert_eq!(by_count(2).await.as_deref().map(opens), Some(vec![13, 14]));
+        assert!(by_count(6).await.is_none());
+        assert!(cache.cached_candles("BTC", "5m", 2, ttl).await.is_none());
+        assert!(cache.cached_candles("ETH", "1m", 2, ttl).await.is_none());
+        assert!(
+            cache
+                .cached_candles("BTC", "1m", 2, Duration::ZERO)
+                .await
+                .is_none()
+        );
+
+        let since = |start_ms| cache.cached_candles_since("BTC", "1m", start_ms, ttl);
+        assert_eq!(
+            since(12 * MINUTE).await.as_deref().map(opens),
+            Some(vec![12, 13, 14])
+        );
+        // A start inside a candle includes that candle.
+        assert_eq!(
+            since(12 * MINUTE + 30_000).await.as_deref().map(opens),
+            Some(vec![12, 13, 14])
+        );
+        assert_eq!(
+            since(10 * MINUTE).await.as_deref().map(opens),
+            Some(vec![10, 11, 12, 13, 14])
+        );
+        // Earlier than the first cached candle needs a fetch.
+        assert!(since(10 * MINUTE - 1).await.is_none());
+    }
 }
@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+
+/// Failed tool calls remembered for `get_recent_errors`.
+const MAX_FAILED_CALLS: usize = 100;
+
+/// Error details are cut to this many characters.
+const MAX_DETAIL_CHARS: usize = 500;
+
+/// Hex runs at least this long are redacted from error details: private
+/// keys, signatures and raw payloads, but not 40-digit addresses.
+const REDACT_HEX_DIGITS: usize = 64;
+
+/// A short id for one tool call, shown in its error and on its log lines.
+pub fn request_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..8].to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The call failed with a protocol error.
+    Error,
+    /// The tool answered with an error result, e.g. invalid input or a
+    /// refused action.
+    Refused,
+}
+
+impl Failure {
+    pub fn label(self) -> &'static str {
+        match self {
+            Failure::Error => "error",
+            Failure::Refused => "refused",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FailedCall {
+    pub request_id: String,
+    pub tool: String,
+    pub profile: Option<String>,
+    pub at: DateTime<Utc>,
+    pub elapsed_ms: u64,
+    pub failure: Failure,
+    /// Sanitized error text.
+    pub detail: String,
+}
+
+/// The latest failed tool calls, so an error seen in a chat client can be
+/// matched to the server log by its request id. Shared by all profiles.
+#[derive(Default)]
+pub struct CallLog {
+    failed: Mutex<VecDeque<FailedCall>>,
+}
+
+impl CallLog {
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<FailedCall>> {
+        self.failed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record(&self, mut call: FailedCall) {
+        call.detail = sanitize(&call.detail);
+        let mut failed = self.lock();
+        failed.push_back(call);
+        while failed.len() > MAX_FAILED_CALLS {
+            failed.pop_front();
+        }
+    }
+
+    /// The latest `limit` failures, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<FailedCall> {
+        self.lock().iter().rev().take(limit).cloned().collect()
+    }
+}
+
+/// Redact long hex runs, flatten to one line and cap the length.
+fn sanitize(detail: &str) -> String {
+    let mut out = String::with_capacity(detail.len());
+    let mut chars = detail.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_hexdigit() {
+            let mut run = String::from(c);
+            while let Some(&next) = chars.peek().filter(|c| c.is_ascii_hexdigit()) {
+                run.push(next);
+                chars.next();
+            }
+            if run.len() >= REDACT_HEX_DIGITS {
+                out.push_str("[redacted]");
+            } else {
+                out.push_str(&run);
+            }
+        } else if c.is_whitespace() {
+            if !out.ends_with(' ') {
+                out.push(' ');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    let out = out.trim();
+    match out.char_indices().nth(MAX_DETAIL_CHARS) {
+        Some((cut, _)) => format!("{}…", &out[..cut]),
+        None => out.to_string(),
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use hypersdk::hypercore::types::OrderStatus;
+use hypersdk::hypercore::{Cloid, OrderResponseStatus};
+use serde_json::Value;
+
+use crate::tools::timestamps::fmt_instant;
+
+/// Issued cloids remembered for lookups; the oldest are dropped beyond this.
+const MAX_RECORDS: usize = 10_000;
+
+/// A failed attempt can be retried under the same intent for this long.
+const RETRY_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// What happened to the order sent under a cloid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Submission {
+    /// Issued; the exchange has not answered yet.
+    Pending,
+    /// Issued but never sent, e.g. a duplicate or a refused batch.
+    NotSent,
+    /// Accepted without further detail, e.g. a modify.
+    Accepted,
+    Resting {
+        oid: u64,
+    },
+    Filled {
+        oid: u64,
+    },
+    Rejected(String),
+    /// The request failed without an answer, so the order may or may not
+    /// have reached the exchange. The cloid must never be sent again.
+    Unknown(String),
+}
+
+impl Submission {
+    pub fn describe(&self) -> String {
+        match self {
+            Submission::Pending => "pending".to_string(),
+            Submission::NotSent => "not sent".to_string(),
+            Submission::Accepted => "accepted".to_string(),
+            Submission::Resting { oid } => format!("resting (oid {oid})"),
+            Submission::Filled { oid } => format!("filled (oid {oid})"),
+            Submission::Rejected(e) => format!("rejected: {e}"),
+            Submission::Unknown(e) => format!("unknown, request failed: {e}"),
+        }
+    }
+
+    /// Whether the exchange took the order, so a repeat is a new order
+    /// rather than a retry.
+    fn accepted(&self) -> bool {
+        matches!(
+            self,
+            Submission::Pending
+                | Submission::Accepted
+                | Submission::Resting { .. }
+                | Submission::Filled { .. }
+        )
+    }
+}
+
+impl Submission {
+    /// The outcome an `orderStatus` lookup shows for order `oid`. Orders that
+    /// rested and were later cancelled or triggered were still accepted.
+    pub fn from_order_status(status: OrderStatus, oid: u64) -> Self {
+        match status {
+            OrderStatus::Open => Submission::Resting { oid },
+            OrderStatus::Filled => Submission::Filled { oid },
+            status if status.is_rejected() => Submission::Rejected(status.to_string()),
+            _ => Submission::Accepted,
+        }
+    }
+}
+
+impl From<&OrderResponseStatus> for Submission {
+    fn from(status: &OrderResponseStatus) -> Self {
+        match status {
+            OrderResponseStatus::Success => Submission::Accepted,
+            OrderResponseStatus::Resting { oid, .. } => Submission::Resting { oid: *oid },
+            OrderResponseStatus::Filled { oid, .. } => Submission::Filled { oid: *oid },
+            OrderResponseStatus::Error(e) => Submission::Rejected(e.clone()),
+        }
+    }
+}
+
+/// One logical order. Every attempt at it gets its own cloid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Intent(u64);
+
+impl Intent {
+    pub fn id(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CloidRecord {
+    pub intent: Intent,
+    /// What the order was for, e.g. "place_order: buy 0.1 BTC".
+    pub purpose: String,
+    /// 1 for the first attempt at the intent, 2 for the first retry, ...
+    pub attempt: u32,
+    pub issued_at: DateTime<Utc>,
+    pub outcome: Submission,
+}
+
+struct KeyedIntent {
+    intent: Intent,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_intent: u64,
+    records: HashMap<Cloid, CloidRecord>,
+    /// Issue order, for evicting the oldest records.
+    issued: VecDeque<Cloid>,
+    keyed: HashMap<u64, KeyedIntent>,
+}
+
+impl Inner {
+    fn attempts(&self, intent: Intent) -> u32 {
+        self.records.values().filter(|r| r.intent == intent).count() as u32
+    }
+}
+
+/// Every cloid this server issues, with the intent behind it and what the
+/// exchange made of it. Issuing goes through here so a cloid is never
+/// reused, even when a request failed in a way that may have delivered it;
+/// a retry gets a fresh cloid linked to the same intent. Shared by all
+/// profiles.
+#[derive(Default)]
+pub struct CloidRegistry {
+    inner: Mutex<Inner>,
+}
+
+impl CloidRegistry {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start a new logical order.
+    pub fn intent(&self) -> Intent {
+        let mut inner = self.lock();
+        inner.next_intent += 1;
+        Intent(inner.next_intent)
+    }
+
+    /// Intent for an order identified by `key` (a parameter fingerprint).
+    /// Reuses the previous intent for the key when its last attempt was not
+    /// accepted within the retry window, so retries stay linked to it.
+    pub fn intent_for(&self, key: u64) -> Intent {
+        let mut inner = self.lock();
+        inner
+            .keyed
+            .retain(|_, k| k.last_used.elapsed() < RETRY_WINDOW);
+        if let Some(keyed) = inner.keyed.get(&key) {
+            let intent = keyed.intent;
+            let last = inner
+                .records
+                .values()
+                .filter(|r| r.intent == intent)
+                .max_by_key(|r| r.attempt);
+            if last.is_none_or(|r| !r.outcome.accepted()) {
+                if let Some(keyed) = inner.keyed.get_mut(&key) {
+                    keyed.last_used = Instant::now();
+                }
+                return intent;
+            }
+        }
+        inner.next_intent += 1;
+        let intent = Intent(inner.next_intent);
+        inner.keyed.insert(
+            key,
+            KeyedIntent {
+                intent,
+                last_used: Instant::now(),
+            },
+        );
+        intent
+    }
+
+    /// A new cloid for the next attempt at `intent`, distinct from every
+    /// cloid issued before.
+    pub fn issue(&self, intent: Intent, purpose: impl Into<String>) -> Cloid {
+        let mut inner = self.lock();
+        let cloid = loop {
+            let candidate = Cloid::from_slice(uuid::Uuid::new_v4().as_bytes());
+            if !inner.records.contains_key(&candidate) {
+                break candidate;
+            }
+        };
+        let attempt = inner.attempts(intent) + 1;
+        inner.records.insert(
+            cloid,
+            CloidRecord {
+                intent,
+                purpose: purpose.into(),
+                attempt,
+                issued_at: Utc::now(),
+                outcome: Submission::Pending,
+            },
+        );
+        inner.issued.push_back(cloid);
+        while inner.issued.len() > MAX_RECORDS {
+            if let Some(oldest) = inner.issued.pop_front() {
+                inner.records.remove(&oldest);
+            }
+        }
+        cloid
+    }
+
+    /// Shorthand for a one-off order: a new intent and its first cloid.
+    pub fn issue_new(&self, purpose: impl Into<String>) -> Cloid {
+        let intent = self.intent();
+        self.issue(intent, purpose)
+    }
+
+    pub fn record(&self, cloid: Cloid, outcome: Submission) {
+        if let Some(record) = self.lock().records.get_mut(&cloid) {
+            record.outcome = outcome;
+        }
+    }
+
+    /// Record the outcome of an order action sent with `cloids`, in order.
+    /// An error means no answer arrived, so each cloid is marked unknown.
+    pub fn record_response(&self, cloids: &[Cloid], response: &anyhow::Result<Value>) {
+        let outcomes: Vec<Submission> = match response {
+            Err(e) => vec![Submission::Unknown(e.to_string()); cloids.len()],
+            Ok(body) if body.get("status").and_then(Value::as_str) != Some("ok") => {
+                let message = body
+                    .get("response")
+                    .and_then(Value::as_str)
+                    .map(String::from)
+                    .unwrap_or_else(|| body.to_string());
+                vec![Submission::Rejected(message); cloids.len()]
+            }
+            Ok(body) => {
+                let statuses = body
+                    .pointer("/response/data/statuses")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                cloids
+                    .iter()
+                    .enumerate()
+                    .map(|(i, _)| match statuses.get(i) {
+                        Some(status) => parse_status(status),
+                        None => Submission::Unknown("no status returned".to_string()),
+                    })
+                    .collect()
+            }
+        };
+        let mut inner = self.lock();
+        for (cloid, outcome) in cloids.iter().zip(outcomes) {
+            if let Some(record) = inner.records.get_mut(cloid) {
+                record.outcome = outcome;
+            }
+        }
+    }
+
+    pub fn lookup(&self, cloid: &Cloid) -> Option<CloidRecord> {
+        self.lock().records.get(cloid).cloned()
+    }
+
+    /// All remembered attempts at `intent`, oldest first.
+    pub fn attempts(&self, intent: Intent) -> Vec<(Cloid, CloidRecord)> {
+        let inner = self.lock();
+        let mut attempts: Vec<(Cloid, CloidRecord)> = inner
+            .records
+            .iter()
+            .filter(|(_, r)| r.intent == intent)
+            .map(|(c, r)| (*c, r.clone()))
+            .collect();
+        attempts.sort_by_key(|(_, r)| r.attempt);
+        attempts
+    }
+
+    /// "attempt 2 of intent #14 (place_order: buy 0.1 BTC) at 14:05:32 UTC,
+    /// filled (oid 123)".
+    pub fn describe(&self, cloid: &Cloid) -> Option<String> {
+        let record = self.lookup(cloid)?;
+        Some(format!(
+            "attempt {} of intent #{} ({}) at {}, {}",
+            record.attempt,
+            record.intent.0,
+            record.purpose,
+            fmt_instant(record.issued_at),
+            record.outcome.describe()
+        ))
+    }
+}
+
+fn parse_status(status: &Value) -> Submission {
+    let oid = |kind: &str| {
+        status
+            .pointer(&format!("/{kind}/oid"))
+            .and_then(Value::as_u64)
+    };
+    if let Some(oid) = oid("resting") {
+        Submission::Resting { oid }
+    } else if let Some(oid) = oid("filled") {
+        Submission::Filled { oid }
+    } else if let Some(error) = status.get("error").and_then(Value::as_str) {
+        Submission::Rejected(error.to_string())
+    } else {
+        Submission::Unknown(status.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_issue_never_collides() {
+        let registry = Arc::new(CloidRegistry::default());
+        let shared = registry.intent();
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let registry = Arc::clone(&registry);
+                std::thread::spawn(move || {
+                    (0..250)
+                        .map(|i| {
+                            if i % 2 == 0 {
+                                registry.issue(shared, "shared")
+                            } else {
+                                registry.issue_new("one-off")
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let cloids: Vec<Cloid> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+
+        assert_eq!(cloids.iter().collect::<HashSet<_>>().len(), 2000);
+        // Attempts at the shared intent are numbered without gaps or repeats.
+        let attempts: Vec<u32> = registry
+            .attempts(shared)
+            .iter()
+            .map(|(_, r)| r.attempt)
+            .collect();
+        assert_eq!(attempts, (1..=1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn retries_get_fresh_cloids_under_the_same_intent() {
+        let registry = CloidRegistry::default();
+        let key = 0x5eed;
+
+        let intent = registry.intent_for(key);
+        let first = registry.issue(intent, "place_order: buy 0.1 BTC");
+        registry.record_response(&[first], &Err(anyhow::anyhow!("connection reset")));
+
+        assert_eq!(registry.intent_for(key), intent);
+        let second = registry.issue(intent, "place_order: buy 0.1 BTC");
+        registry.record_response(
+            &[second],
+            &Ok(json!({
+                "status": "ok",
+                "response": { "type": "order", "data": { "statuses": [
+                    { "error": "Insufficient margin to place order." }
+                ] } }
+            })),
+        );
+
+        assert_eq!(registry.intent_for(key), intent);
+        let third = registry.issue(intent, "place_order: buy 0.1 BTC");
+        registry.record(third, Submission::Resting { oid: 77 });
+
+        let attempts = registry.attempts(intent);
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|(c, r)| (*c, r.attempt, r.outcome.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    first,
+                    1,
+                    Submission::Unknown("connection reset".to_string())
+                ),
+                (
+                    second,
+                    2,
+                    Submission::Rejected("Insufficient margin to place order.".to_string())
+                ),
+                (third, 3, Submission::Resting { oid: 77 }),
+            ]
+        );
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|(c, _)| c)
+                .collect::<HashSet<_>>()
+                .len(),
+            3
+        );
+
+        // Once an attempt is accepted, the same parameters are a new order.
+        assert_ne!(registry.intent_for(key), intent);
+    }
+}
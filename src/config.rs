@@ -1,11 +1,22 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use alloy::signers::local::PrivateKeySigner;
 use anyhow::{Context, Result};
 use hypersdk::Address;
 use hypersdk::hypercore::Chain;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::Cli;
+use crate::trading_hours::TradingHours;
 
 pub const BUILDER_ADDRESS: &str = "0xdadcB94d61D4A14e8aD1b94Acf888120b7E807aE";
-// 10 = 0.01%
+// Per-order builder fee in tenths of a basis point: 10 = 0.01%
 pub const BUILDER_FEE: u64 = 10;
+// Highest builder fee the exchange accepts on perps: 100 = 0.1%
+pub const MAX_BUILDER_FEE: u64 = 100;
 
 pub struct Config {
     pub wallet: Option<PrivateKeySigner>,
@@ -15,26 +26,310 @@ pub struct Config {
     pub vault_address: Option<Address>,
     pub chain: Chain,
     pub realtime: bool,
+    pub profiles: Vec<ProfileConfig>,
+    pub read_only: bool,
+    pub dry_run: bool,
+    /// Dry-run paper account: starting USDC and taker slippage from mid.
+    pub paper_balance: Decimal,
+    pub paper_slippage_bps: u32,
+    pub env_file: Option<PathBuf>,
+    /// Max builder fee to approve, in tenths of a basis point.
+    pub builder_max_fee: u64,
+    /// EIP-712 domain chain id for user-signed actions such as
+    /// `approveBuilderFee`, sent as the action's `signatureChainId`.
+    pub signature_chain_id: u64,
+    /// Market orders are limited this many basis points beyond the book level
+    /// needed to fill them.
+    pub market_buffer_bps: u32,
+    /// `place_order` fallbacks when the request omits them.
+    pub default_order_type: String,
+    pub default_tif: String,
+    /// Soft limits on resting orders, checked before placement.
+    pub max_open_orders: usize,
+    pub max_open_orders_per_coin: Option<usize>,
+    /// Caps on a perp position's notional after a fill, in USD. Per-coin
+    /// overrides are keyed by the uppercased coin.
+    pub max_position_notional: Option<Decimal>,
+    pub max_position_notional_by_coin: BTreeMap<String, Decimal>,
+    /// Coins whose top of book is sampled for `get_spread_history`.
+    pub sample_books: Vec<String>,
+    pub sample_interval_secs: u64,
+    pub position_warn_multiple: Decimal,
+    pub impact_guard_multiple: Decimal,
+    /// Limit prices further than this percentage from the mark are refused
+    /// before sending, as the exchange would reject them.
+    pub price_band_pct: Decimal,
+    pub funding_alert_usd: Decimal,
+    /// How long `place_order` waits for the exchange before answering with
+    /// a pending confirmation. None waits as long as the request takes.
+    pub order_latency_budget: Option<Duration>,
+    pub hide_builder_nudge: bool,
+    /// Set by HYPERLIQUID_HIDE_EQUITY_FOOTER: no equity change line after
+    /// destructive tools.
+    pub hide_equity_footer: bool,
+    /// Set by HYPERLIQUID_PLAIN_NUMBERS: no thousands separators or "$12.4M".
+    pub plain_numbers: bool,
+    /// HYPERLIQUID_DISPLAY_TZ: zone for times in tool output instead of UTC.
+    pub display_tz: Option<chrono_tz::Tz>,
+    pub withdrawals_enabled: bool,
+    pub raw_queries_enabled: bool,
+    pub raw_query_types: Vec<String>,
+    pub auto_cancel_orphan_stops: bool,
+    pub emergency_phrase: Option<String>,
+    /// Consecutive exchange failures that pause signed actions; 0 disables.
+    pub breaker_failures: u32,
+    pub trading_hours: Option<TradingHours>,
+    /// HYPERLIQUID_HTTP_TOKEN: bearer token the HTTP transport requires.
+    pub http_token: Option<String>,
 }
 
+/// An additional account defined via `HYPERLIQUID_PROFILE_<NAME>_*` variables.
+pub struct ProfileConfig {
+    pub name: String,
+    pub wallet: Option<PrivateKeySigner>,
+    pub main_address: Option<Address>,
+    pub agent_address: Option<Address>,
+}
+
+pub const DEFAULT_MARKET_BUFFER_BPS: u32 = 50;
+pub const DEFAULT_ORDER_TYPE: &str = "limit";
+pub const DEFAULT_TIF: &str = "gtc";
+/// Hyperliquid's base account-wide open order limit. It grows with trading
+/// volume, so accounts with a higher limit can raise it via env.
+pub const DEFAULT_MAX_OPEN_ORDERS: usize = 1000;
+pub const DEFAULT_SAMPLE_INTERVAL_SECS: u64 = 30;
+pub const DEFAULT_BREAKER_FAILURES: u32 = 5;
+/// USDC the dry-run paper account starts with.
+pub const DEFAULT_PAPER_BALANCE: Decimal = Decimal::from_parts(10_000, 0, 0, false, 0);
+/// Paper taker fills are priced this many basis points through the mid.
+pub const DEFAULT_PAPER_SLIPPAGE_BPS: u32 = 5;
+/// Hyperliquid's base perp fee rates (0.045% taker, 0.015% maker), before
+/// volume tiers, staking and referral discounts.
+pub const BASE_TAKER_FEE: Decimal = Decimal::from_parts(45, 0, 0, false, 5);
+pub const BASE_MAKER_FEE: Decimal = Decimal::from_parts(15, 0, 0, false, 5);
+/// `place_order` warns when an order grows a position past this multiple of
+/// its current size.
+pub const DEFAULT_POSITION_WARN_MULTIPLE: Decimal = Decimal::from_parts(15, 0, 0, false, 1);
+/// Market orders are refused when their estimated fill is further from mid
+/// than this multiple of the market's impact spread.
+pub const DEFAULT_IMPACT_GUARD_MULTIPLE: Decimal = Decimal::from_parts(3, 0, 0, false, 0);
+/// The exchange rejects limit prices more than this percentage away from the
+/// reference price.
+pub const DEFAULT_PRICE_BAND_PCT: Decimal = Decimal::from_parts(95, 0, 0, false, 0);
+/// `get_positions` flags positions whose next funding payment is at least
+/// this many USD.
+pub const DEFAULT_FUNDING_ALERT_USD: Decimal = Decimal::from_parts(10, 0, 0, false, 0);
+/// `place_order` answers with a pending confirmation once the exchange has
+/// taken this long, well inside common client tool-call timeouts.
+pub const DEFAULT_ORDER_LATENCY_BUDGET_SECS: u64 = 10;
+
+/// Info request types `raw_info_query` forwards by default. All are plain
+/// reads; `HYPERLIQUID_RAW_QUERY_TYPES` can add newer ones.
+pub const DEFAULT_RAW_QUERY_TYPES: &[&str] = &[
+    "activeAssetData",
+    "allMids",
+    "candleSnapshot",
+    "clearinghouseState",
+    "delegations",
+    "delegatorHistory",
+    "delegatorRewards",
+    "delegatorSummary",
+    "exchangeStatus",
+    "extraAgents",
+    "frontendOpenOrders",
+    "fundingHistory",
+    "historicalOrders",
+    "l2Book",
+    "maxBuilderFee",
+    "meta",
+    "metaAndAssetCtxs",
+    "openOrders",
+    "orderStatus",
+    "perpDexs",
+    "perpsAtOpenInterestCap",
+    "portfolio",
+    "predictedFundings",
+    "referral",
+    "spotClearinghouseState",
+    "spotDeployState",
+    "spotMeta",
+    "spotMetaAndAssetCtxs",
+    "subAccounts",
+    "tokenDetails",
+    "twapHistory",
+    "userFees",
+    "userFills",
+    "userFillsByTime",
+    "userFunding",
+    "userNonFundingLedgerUpdates",
+    "userRateLimit",
+    "userRole",
+    "userTwapSliceFills",
+    "userVaultEquities",
+    "validatorSummaries",
+    "vaultDetails",
+];
+
 pub const ENV_FILE_PATH: &str = ".config/hyperliquid-mcp/.env";
 
-impl Config {
-    pub fn from_env() -> Result<Self> {
-        if let Some(home) = dirs::home_dir() {
-            let env_path = home.join(ENV_FILE_PATH);
-            match dotenvy::from_path(&env_path) {
-                Ok(()) => tracing::info!(path = %env_path.display(), "Loaded .env file"),
-                Err(dotenvy::Error::Io(_)) => {
-                    // File doesn't exist
-                }
-                Err(e) => {
-                    tracing::warn!(path = %env_path.display(), error = %e, "Failed to parse .env file")
-                }
-            }
+/// Records when the builder fee nudge was last shown, so restarts don't
+/// repeat it more than once per `NUDGE_INTERVAL`.
+pub const NUDGE_STATE_PATH: &str = ".config/hyperliquid-mcp/builder_nudge_shown";
+const NUDGE_INTERVAL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Tracking for background constructs, restored at startup. See `persist`.
+pub const STATE_FILE_PATH: &str = ".config/hyperliquid-mcp/state.json";
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+pub const MAX_POSITION_NOTIONAL_VAR: &str = "HYPERLIQUID_MAX_POSITION_NOTIONAL";
+
+pub const AGENT_KEY_VAR: &str = "HYPERLIQUID_AGENT_PRIVATE_KEY";
+
+pub const PROFILE_PREFIX: &str = "HYPERLIQUID_PROFILE_";
+pub const PROFILE_AGENT_KEY_SUFFIX: &str = "_AGENT_PRIVATE_KEY";
+pub const PROFILE_WALLET_ADDRESS_SUFFIX: &str = "_WALLET_ADDRESS";
+
+pub fn default_env_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(ENV_FILE_PATH))
+}
+
+/// Whether the builder fee nudge was shown within the last week, by any
+/// server process.
+pub fn nudge_shown_recently() -> bool {
+    let Some(path) = dirs::home_dir().map(|home| home.join(NUDGE_STATE_PATH)) else {
+        return false;
+    };
+    let Some(shown_at) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+    else {
+        return false;
+    };
+    let now = chrono::Utc::now().timestamp() as u64;
+    now.saturating_sub(shown_at) < NUDGE_INTERVAL_SECS
+}
+
+pub fn record_nudge_shown() -> Result<()> {
+    let path = dirs::home_dir()
+        .map(|home| home.join(NUDGE_STATE_PATH))
+        .context("No home directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, chrono::Utc::now().timestamp().to_string())?;
+    Ok(())
+}
+
+pub fn parse_chain(network: &str) -> Chain {
+    match network.trim().to_lowercase().as_str() {
+        "testnet" | "test" => Chain::Testnet,
+        _ => Chain::Mainnet,
+    }
+}
+
+/// Arbitrum One for mainnet, Arbitrum Sepolia for testnet: the chains the
+/// Hyperliquid app signs user actions on.
+pub fn default_signature_chain_id(chain: Chain) -> u64 {
+    match chain {
+        Chain::Mainnet => 42161,
+        Chain::Testnet => 421614,
+    }
+}
+
+/// A chain id in decimal ("42161") or hex ("0xa4b1").
+pub fn parse_chain_id(value: &str) -> Option<u64> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+    .filter(|id| *id > 0)
+}
+
+/// Format a fee in tenths of a basis point as the percent string used by
+/// `approveBuilderFee`, e.g. 10 -> "0.01%".
+pub fn fee_to_percent(fee: u64) -> String {
+    format!("{}%", Decimal::new(fee as i64, 3).normalize())
+}
+
+/// Parse a percent such as "0.01%" or "0.01" into tenths of a basis point.
+/// Returns `None` for negative values or finer precision than 0.001%.
+pub fn percent_to_fee(percent: &str) -> Option<u64> {
+    let value: Decimal = percent.trim().trim_end_matches('%').trim().parse().ok()?;
+    let fee = value * Decimal::ONE_THOUSAND;
+    if fee.is_sign_negative() || !fee.fract().is_zero() {
+        return None;
+    }
+    fee.to_u64()
+}
+
+/// Parse a `maxBuilderFee` info response into tenths of a basis point. The API
+/// returns a number; percent strings are accepted too.
+pub fn parse_max_builder_fee(value: &serde_json::Value) -> u64 {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(percent_to_fee))
+        .unwrap_or(0)
+}
+
+/// Outcome of loading the env file. Loading happens before logging is set up
+/// (the file may configure logging), so the result is reported afterwards.
+pub struct EnvFile {
+    pub path: Option<PathBuf>,
+    pub loaded: bool,
+    pub error: Option<String>,
+}
+
+impl EnvFile {
+    pub fn log(&self) {
+        let Some(path) = &self.path else { return };
+        if self.loaded {
+            tracing::info!(path = %path.display(), "Loaded .env file");
+        } else if let Some(error) = &self.error {
+            tracing::warn!(path = %path.display(), error = %error, "Failed to parse .env file");
+        }
+    }
+}
+
+/// Load `explicit` (from `--env-file`) or the default env file into the
+/// process environment. A missing default file is fine; a missing explicit one is not.
+pub fn load_env_file(explicit: Option<&Path>) -> Result<EnvFile> {
+    let path = explicit.map(Path::to_path_buf).or_else(default_env_path);
+    let mut env_file = EnvFile {
+        path,
+        loaded: false,
+        error: None,
+    };
+    let Some(env_path) = &env_file.path else {
+        return Ok(env_file);
+    };
+
+    match dotenvy::from_path(env_path) {
+        Ok(()) => env_file.loaded = true,
+        Err(dotenvy::Error::Io(e)) if explicit.is_some() => {
+            return Err(e)
+                .with_context(|| format!("Failed to read env file {}", env_path.display()));
+        }
+        Err(dotenvy::Error::Io(_)) => {
+            // File doesn't exist
         }
+        Err(e) => env_file.error = Some(e.to_string()),
+    }
+
+    Ok(env_file)
+}
+
+impl Config {
+    /// Load configuration from the environment (after `load_env_file`).
+    /// Command-line arguments take precedence.
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let env_file = cli.env_file.clone().or_else(default_env_path);
 
-        let wallet = match std::env::var("HYPERLIQUID_AGENT_PRIVATE_KEY") {
+        let wallet = match std::env::var(AGENT_KEY_VAR) {
             Ok(key) => {
                 let key = key.trim().trim_start_matches("0x");
                 let signer: PrivateKeySigner = key.parse().context(
@@ -85,13 +380,11 @@ impl Config {
             );
         }
 
-        let chain = match std::env::var("HYPERLIQUID_NETWORK")
-            .unwrap_or_else(|_| "mainnet".to_string())
-            .to_lowercase()
-            .as_str()
-        {
-            "testnet" | "test" => Chain::Testnet,
-            _ => Chain::Mainnet,
+        let chain = match &cli.network {
+            Some(network) => parse_chain(network),
+            None => parse_chain(
+                &std::env::var("HYPERLIQUID_NETWORK").unwrap_or_else(|_| "mainnet".to_string()),
+            ),
         };
         let network_name = match chain {
             Chain::Testnet => "testnet",
@@ -123,6 +416,250 @@ impl Config {
             tracing::info!(vault = %vault, "Vault mode enabled — trading as vault leader");
         }
 
+        let builder_max_fee = match std::env::var("HYPERLIQUID_BUILDER_MAX_FEE") {
+            Ok(value) => percent_to_fee(&value).with_context(|| {
+                format!(
+                    "Invalid HYPERLIQUID_BUILDER_MAX_FEE '{value}', expected a percent like 0.01%"
+                )
+            })?,
+            Err(_) => BUILDER_FEE,
+        };
+        if builder_max_fee < BUILDER_FEE {
+            tracing::warn!(
+                max_fee = %fee_to_percent(builder_max_fee),
+                order_fee = %fee_to_percent(BUILDER_FEE),
+                "HYPERLIQUID_BUILDER_MAX_FEE is below the per-order builder fee — orders will be rejected once this is approved"
+            );
+        }
+
+        let signature_chain_id = match std::env::var("HYPERLIQUID_SIGNATURE_CHAIN_ID") {
+            Ok(value) => parse_chain_id(&value).with_context(|| {
+                format!(
+                    "Invalid HYPERLIQUID_SIGNATURE_CHAIN_ID '{value}', expected a chain id like 42161 or 0xa4b1"
+                )
+            })?,
+            Err(_) => default_signature_chain_id(chain),
+        };
+
+        let market_buffer_bps = match std::env::var("HYPERLIQUID_MARKET_BUFFER_BPS") {
+            Ok(value) => value.trim().parse::<u32>().with_context(|| {
+                format!("Invalid HYPERLIQUID_MARKET_BUFFER_BPS '{value}', expected basis points")
+            })?,
+            Err(_) => DEFAULT_MARKET_BUFFER_BPS,
+        };
+
+        let paper_balance = match std::env::var("HYPERLIQUID_PAPER_BALANCE") {
+            Ok(value) => match value.trim().parse::<Decimal>() {
+                Ok(balance) if balance > Decimal::ZERO => balance,
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_PAPER_BALANCE '{value}', expected a positive USDC amount"
+                ),
+            },
+            Err(_) => DEFAULT_PAPER_BALANCE,
+        };
+
+        let paper_slippage_bps = match std::env::var("HYPERLIQUID_PAPER_SLIPPAGE_BPS") {
+            Ok(value) => value.trim().parse::<u32>().with_context(|| {
+                format!("Invalid HYPERLIQUID_PAPER_SLIPPAGE_BPS '{value}', expected basis points")
+            })?,
+            Err(_) => DEFAULT_PAPER_SLIPPAGE_BPS,
+        };
+
+        let default_order_type = match std::env::var("HYPERLIQUID_DEFAULT_ORDER_TYPE") {
+            Ok(value) => match value.trim().to_lowercase().as_str() {
+                kind @ ("limit" | "market") => kind.to_string(),
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_DEFAULT_ORDER_TYPE '{value}', expected \"limit\" or \"market\""
+                ),
+            },
+            Err(_) => DEFAULT_ORDER_TYPE.to_string(),
+        };
+
+        let default_tif = match std::env::var("HYPERLIQUID_DEFAULT_TIF") {
+            Ok(value) => match value.trim().to_lowercase().as_str() {
+                tif @ ("gtc" | "ioc" | "alo") => tif.to_string(),
+                "post_only" | "post-only" => "alo".to_string(),
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_DEFAULT_TIF '{value}', expected \"gtc\", \"ioc\" or \"alo\""
+                ),
+            },
+            Err(_) => DEFAULT_TIF.to_string(),
+        };
+
+        let max_open_orders = match std::env::var("HYPERLIQUID_MAX_OPEN_ORDERS") {
+            Ok(value) => value.trim().parse::<usize>().with_context(|| {
+                format!("Invalid HYPERLIQUID_MAX_OPEN_ORDERS '{value}', expected a count")
+            })?,
+            Err(_) => DEFAULT_MAX_OPEN_ORDERS,
+        };
+
+        let max_open_orders_per_coin = match std::env::var("HYPERLIQUID_MAX_OPEN_ORDERS_PER_COIN") {
+            Ok(value) => Some(value.trim().parse::<usize>().with_context(|| {
+                format!("Invalid HYPERLIQUID_MAX_OPEN_ORDERS_PER_COIN '{value}', expected a count")
+            })?),
+            Err(_) => None,
+        };
+
+        let max_position_notional = match std::env::var(MAX_POSITION_NOTIONAL_VAR) {
+            Ok(value) => Some(parse_notional_limit(MAX_POSITION_NOTIONAL_VAR, &value)?),
+            Err(_) => None,
+        };
+        let max_position_notional_by_coin = parse_position_notional_overrides()?;
+
+        let sample_books: Vec<String> = std::env::var("HYPERLIQUID_SAMPLE_BOOKS")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let sample_interval_secs = match std::env::var("HYPERLIQUID_SAMPLE_INTERVAL_SECS") {
+            Ok(value) => match value.trim().parse::<u64>() {
+                Ok(secs) if secs > 0 => secs,
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_SAMPLE_INTERVAL_SECS '{value}', expected a positive number of seconds"
+                ),
+            },
+            Err(_) => DEFAULT_SAMPLE_INTERVAL_SECS,
+        };
+        let position_warn_multiple = match std::env::var("HYPERLIQUID_POSITION_WARN_MULTIPLE") {
+            Ok(value) => match value.trim().parse::<Decimal>() {
+                Ok(multiple) if multiple >= Decimal::ONE => multiple,
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_POSITION_WARN_MULTIPLE '{value}', expected a number of at least 1"
+                ),
+            },
+            Err(_) => DEFAULT_POSITION_WARN_MULTIPLE,
+        };
+        let impact_guard_multiple = match std::env::var("HYPERLIQUID_IMPACT_GUARD_MULTIPLE") {
+            Ok(value) => match value.trim().parse::<Decimal>() {
+                Ok(multiple) if multiple > Decimal::ZERO => multiple,
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_IMPACT_GUARD_MULTIPLE '{value}', expected a positive number"
+                ),
+            },
+            Err(_) => DEFAULT_IMPACT_GUARD_MULTIPLE,
+        };
+        let price_band_pct = match std::env::var("HYPERLIQUID_PRICE_BAND_PCT") {
+            Ok(value) => match value.trim().parse::<Decimal>() {
+                Ok(pct) if pct > Decimal::ZERO && pct < Decimal::ONE_HUNDRED => pct,
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_PRICE_BAND_PCT '{value}', expected a percentage between 0 and 100"
+                ),
+            },
+            Err(_) => DEFAULT_PRICE_BAND_PCT,
+        };
+        let funding_alert_usd = match std::env::var("HYPERLIQUID_FUNDING_ALERT_USD") {
+            Ok(value) => match value.trim().parse::<Decimal>() {
+                Ok(usd) if usd > Decimal::ZERO => usd,
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_FUNDING_ALERT_USD '{value}', expected a positive USD amount"
+                ),
+            },
+            Err(_) => DEFAULT_FUNDING_ALERT_USD,
+        };
+        let order_latency_budget = match std::env::var("HYPERLIQUID_ORDER_LATENCY_BUDGET_SECS") {
+            Ok(value) => match value.trim().parse::<u64>() {
+                Ok(0) => None,
+                Ok(secs) => Some(Duration::from_secs(secs)),
+                _ => anyhow::bail!(
+                    "Invalid HYPERLIQUID_ORDER_LATENCY_BUDGET_SECS '{value}', expected a number of seconds (0 to disable)"
+                ),
+            },
+            Err(_) => Some(Duration::from_secs(DEFAULT_ORDER_LATENCY_BUDGET_SECS)),
+        };
+
+        let hide_builder_nudge = std::env::var("HYPERLIQUID_HIDE_BUILDER_NUDGE")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+            .unwrap_or(false);
+        let hide_equity_footer = std::env::var("HYPERLIQUID_HIDE_EQUITY_FOOTER")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+            .unwrap_or(false);
+        let plain_numbers = std::env::var("HYPERLIQUID_PLAIN_NUMBERS")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+            .unwrap_or(false);
+        let display_tz = match std::env::var("HYPERLIQUID_DISPLAY_TZ") {
+            Ok(value) if !value.trim().is_empty() => {
+                Some(value.trim().parse::<chrono_tz::Tz>().map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid HYPERLIQUID_DISPLAY_TZ '{value}', expected an IANA time zone such as \"Europe/Berlin\""
+                    )
+                })?)
+            }
+            _ => None,
+        };
+        let withdrawals_enabled = std::env::var("HYPERLIQUID_ENABLE_WITHDRAWALS")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+            .unwrap_or(false);
+        let auto_cancel_orphan_stops = std::env::var("HYPERLIQUID_AUTO_CANCEL_ORPHAN_STOPS")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+            .unwrap_or(false);
+        let emergency_phrase = std::env::var("HYPERLIQUID_EMERGENCY_PHRASE")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let http_token = std::env::var("HYPERLIQUID_HTTP_TOKEN")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let breaker_failures = match std::env::var("HYPERLIQUID_BREAKER_FAILURES") {
+            Ok(value) => value.trim().parse::<u32>().with_context(|| {
+                format!("Invalid HYPERLIQUID_BREAKER_FAILURES '{value}', expected a count")
+            })?,
+            Err(_) => DEFAULT_BREAKER_FAILURES,
+        };
+        let trading_hours = match std::env::var("HYPERLIQUID_TRADING_HOURS") {
+            Ok(value) if !value.trim().is_empty() => {
+                let hours = TradingHours::parse(&value).map_err(|e| {
+                    anyhow::anyhow!("Invalid HYPERLIQUID_TRADING_HOURS '{value}': {e}")
+                })?;
+                tracing::info!(schedule = %hours.describe(), "Trading restricted to a schedule");
+                Some(hours)
+            }
+            _ => None,
+        };
+        let raw_queries_enabled = std::env::var("HYPERLIQUID_ENABLE_RAW_QUERIES")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+            .unwrap_or(false);
+        let mut raw_query_types: Vec<String> = DEFAULT_RAW_QUERY_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        if let Ok(extra) = std::env::var("HYPERLIQUID_RAW_QUERY_TYPES") {
+            for t in extra.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                if !raw_query_types.iter().any(|known| known == t) {
+                    raw_query_types.push(t.to_string());
+                }
+            }
+        }
+
+        if !sample_books.is_empty() && !realtime {
+            tracing::warn!(
+                "HYPERLIQUID_SAMPLE_BOOKS is set but REALTIME_ENABLED is off; order books will not be sampled"
+            );
+        }
+
+        let mut profiles = parse_profiles()?;
+
+        let (wallet, main_wallet) = if cli.read_only {
+            tracing::info!(
+                "Read-only mode (--read-only) — private keys will not be used for signing"
+            );
+            for profile in &mut profiles {
+                profile.wallet = None;
+            }
+            (None, None)
+        } else {
+            (wallet, main_wallet)
+        };
+
+        if cli.dry_run {
+            tracing::info!("Dry-run mode (--dry-run) — write actions will not be submitted");
+        }
+
         Ok(Config {
             wallet,
             main_wallet,
@@ -131,6 +668,169 @@ impl Config {
             vault_address,
             chain,
             realtime,
+            profiles,
+            read_only: cli.read_only,
+            dry_run: cli.dry_run,
+            paper_balance,
+            paper_slippage_bps,
+            env_file,
+            builder_max_fee,
+            signature_chain_id,
+            market_buffer_bps,
+            default_order_type,
+            default_tif,
+            max_open_orders,
+            max_open_orders_per_coin,
+            max_position_notional,
+            max_position_notional_by_coin,
+            sample_books,
+            sample_interval_secs,
+            position_warn_multiple,
+            impact_guard_multiple,
+            price_band_pct,
+            funding_alert_usd,
+            order_latency_budget,
+            hide_builder_nudge,
+            hide_equity_footer,
+            plain_numbers,
+            display_tz,
+            withdrawals_enabled,
+            raw_queries_enabled,
+            raw_query_types,
+            auto_cancel_orphan_stops,
+            emergency_phrase,
+            breaker_failures,
+            trading_hours,
+            http_token,
         })
     }
 }
+
+fn parse_notional_limit(key: &str, value: &str) -> Result<Decimal> {
+    match value.trim().parse::<Decimal>() {
+        Ok(limit) if limit > Decimal::ZERO => Ok(limit),
+        _ => anyhow::bail!("Invalid {key} '{value}', expected a positive USD amount"),
+    }
+}
+
+/// Collect `HYPERLIQUID_MAX_POSITION_NOTIONAL_<COIN>` overrides, keyed by the
+/// uppercased coin. HIP-3 coins use `_` for the `:` that env names can't hold.
+fn parse_position_notional_overrides() -> Result<BTreeMap<String, Decimal>> {
+    let prefix = format!("{MAX_POSITION_NOTIONAL_VAR}_");
+    let mut limits = BTreeMap::new();
+    for (key, value) in std::env::vars() {
+        let Some(coin) = key.strip_prefix(&prefix).filter(|c| !c.is_empty()) else {
+            continue;
+        };
+        limits.insert(coin.to_uppercase(), parse_notional_limit(&key, &value)?);
+    }
+    Ok(limits)
+}
+
+/// Key a coin the way `HYPERLIQUID_MAX_POSITION_NOTIONAL_<COIN>` names it.
+pub fn notional_limit_key(coin: &str) -> String {
+    coin.to_uppercase().replace(':', "_")
+}
+
+/// Collect `HYPERLIQUID_PROFILE_<NAME>_AGENT_PRIVATE_KEY` / `..._WALLET_ADDRESS`
+/// pairs into named profiles. Names are lowercased.
+fn parse_profiles() -> Result<Vec<ProfileConfig>> {
+    let mut profiles: BTreeMap<String, ProfileConfig> = BTreeMap::new();
+
+    for (key, value) in std::env::vars() {
+        let Some(rest) = key.strip_prefix(PROFILE_PREFIX) else {
+            continue;
+        };
+
+        if let Some(name) = rest.strip_suffix(PROFILE_AGENT_KEY_SUFFIX) {
+            let name = profile_name(name)?;
+            let signer: PrivateKeySigner = value
+                .trim()
+                .trim_start_matches("0x")
+                .parse()
+                .with_context(|| format!("Failed to parse {key} as a valid hex private key"))?;
+            let entry = profiles
+                .entry(name.clone())
+                .or_insert_with(|| empty_profile(name));
+            entry.agent_address = Some(signer.address());
+            entry.wallet = Some(signer);
+        } else if let Some(name) = rest.strip_suffix(PROFILE_WALLET_ADDRESS_SUFFIX) {
+            let name = profile_name(name)?;
+            let address: Address = value
+                .trim()
+                .parse()
+                .with_context(|| format!("Failed to parse {key} as an address"))?;
+            profiles
+                .entry(name.clone())
+                .or_insert_with(|| empty_profile(name))
+                .main_address = Some(address);
+        }
+    }
+
+    for profile in profiles.values() {
+        tracing::info!(
+            profile = %profile.name,
+            account = ?profile.main_address.or(profile.agent_address),
+            trading = profile.wallet.is_some(),
+            "Loaded account profile"
+        );
+    }
+
+    Ok(profiles.into_values().collect())
+}
+
+fn profile_name(raw: &str) -> Result<String> {
+    let name = raw.to_lowercase();
+    anyhow::ensure!(
+        !name.is_empty() && name != DEFAULT_PROFILE,
+        "Invalid profile name in {PROFILE_PREFIX}{raw}: '{DEFAULT_PROFILE}' is reserved for the top-level HYPERLIQUID_* settings"
+    );
+    Ok(name)
+}
+
+fn empty_profile(name: String) -> ProfileConfig {
+    ProfileConfig {
+        name,
+        wallet: None,
+        main_address: None,
+        agent_address: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_to_percent_formats_tenths_of_a_basis_point() {
+        assert_eq!(fee_to_percent(0), "0%");
+        assert_eq!(fee_to_percent(1), "0.001%");
+        assert_eq!(fee_to_percent(10), "0.01%");
+        assert_eq!(fee_to_percent(100), "0.1%");
+        assert_eq!(fee_to_percent(1000), "1%");
+    }
+
+    #[test]
+    fn percent_to_fee_parses_and_rejects() {
+        assert_eq!(percent_to_fee("0.01%"), Some(10));
+        assert_eq!(percent_to_fee(" 0.01 % "), Some(10));
+        assert_eq!(percent_to_fee("0.01"), Some(10));
+        assert_eq!(percent_to_fee("0.100%"), Some(100));
+        assert_eq!(percent_to_fee("0%"), Some(0));
+        assert_eq!(percent_to_fee("0.0005%"), None);
+        assert_eq!(percent_to_fee("-0.01%"), None);
+        assert_eq!(percent_to_fee("ten"), None);
+        assert_eq!(percent_to_fee(""), None);
+
+        for fee in [0, 1, 10, 25, 100, 1000] {
+            assert_eq!(percent_to_fee(&fee_to_percent(fee)), Some(fee));
+        }
+    }
+
+    #[test]
+    fn max_builder_fee_accepts_numbers_and_percents() {
+        assert_eq!(parse_max_builder_fee(&serde_json::json!(10)), 10);
+        assert_eq!(parse_max_builder_fee(&serde_json::json!("0.1%")), 100);
+        assert_eq!(parse_max_builder_fee(&serde_json::json!(null)), 0);
+    }
+}
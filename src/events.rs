@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+use hypersdk::Decimal;
+use hypersdk::hypercore::types::{Fill, OrderStatus, OrderUpdate};
+
+use crate::tools::format::{fmt_price, fmt_qty, fmt_usd, round_sig};
+
+/// Events kept for `get_events`; the oldest are dropped beyond this.
+const MAX_EVENTS: usize = 200;
+
+/// Triggered order ids remembered so their fills can be labelled.
+const MAX_TRIGGERED: usize = 100;
+
+/// Events shown in the digest before the rest are summarised as a count.
+const MAX_DIGEST_EVENTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// A resting order filled.
+    Fill,
+    /// A stop or take-profit triggered.
+    Trigger,
+    /// A liquidation fill, or orders cancelled for margin or liquidation.
+    Liquidation,
+    /// The exchange's answer to an order that outlasted the latency budget.
+    Order,
+    /// A large resting level appeared near mid on a watched book.
+    Liquidity,
+}
+
+impl EventKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::Fill => "Fill",
+            EventKind::Trigger => "Trigger",
+            EventKind::Liquidation => "Liquidation",
+            EventKind::Order => "Order",
+            EventKind::Liquidity => "Liquidity",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AccountEvent {
+    pub id: u64,
+    pub kind: EventKind,
+    /// When it happened on the exchange, in milliseconds.
+    pub time: u64,
+    pub text: String,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    events: VecDeque<AccountEvent>,
+    /// Events with an id at or below this have been shown.
+    read_upto: u64,
+    triggered: VecDeque<u64>,
+}
+
+/// Account events from the WebSocket feed that happened between tool calls:
+/// fills of resting orders, stops triggering, liquidations. The stdio
+/// transport can't push them, so the next tool response carries a digest of
+/// the unread ones. Fills the account took as a taker are left out, since
+/// the tool call that sent the order already reported them, unless the
+/// order was a triggered stop.
+pub struct EventLog {
+    /// Fills from before this (the WebSocket snapshot on connect) are not
+    /// news.
+    started_ms: u64,
+    inner: Mutex<Inner>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self {
+            started_ms: chrono::Utc::now().timestamp_millis() as u64,
+            inner: Mutex::default(),
+        }
+    }
+}
+
+impl EventLog {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(inner: &mut Inner, kind: EventKind, time: u64, text: String) {
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.events.push_back(AccountEvent {
+            id,
+            kind,
+            time,
+            text,
+        });
+        while inner.events.len() > MAX_EVENTS {
+            inner.events.pop_front();
+        }
+    }
+
+    pub fn record_order_updates(&self, updates: &[OrderUpdate]) {
+        let mut inner = self.lock();
+        for update in updates {
+            let o = &update.order;
+            let side = match o.side {
+                hypersdk::hypercore::Side::Bid => "buy",
+                hypersdk::hypercore::Side::Ask => "sell",
+            };
+            match update.status {
+                OrderStatus::Triggered if !inner.triggered.contains(&o.oid) => {
+                    inner.triggered.push_back(o.oid);
+                    while inner.triggered.len() > MAX_TRIGGERED {
+                        inner.triggered.pop_front();
+                    }
+                    let text = format!(
+                        "trigger order on {} fired ({side} {})",
+                        o.coin,
+                        fmt_qty(o.orig_sz)
+                    );
+                    Self::push(
+                        &mut inner,
+                        EventKind::Trigger,
+                        update.status_timestamp,
+                        text,
+                    );
+                }
+                OrderStatus::MarginCanceled | OrderStatus::LiquidatedCanceled => {
+                    let text = format!(
+                        "{} {side} {} @ {} cancelled ({})",
+                        o.coin,
+                        fmt_qty(o.sz),
+                        fmt_price(o.limit_px),
+                        update.status
+                    );
+                    Self::push(
+                        &mut inner,
+                        EventKind::Liquidation,
+                        update.status_timestamp,
+                        text,
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Record fills new to this server, one event per order.
+    pub fn record_fills(&self, fills: &[Fill]) {
+        let mut inner = self.lock();
+        let mut by_order: BTreeMap<u64, Vec<&Fill>> = BTreeMap::new();
+        for fill in fills.iter().filter(|f| f.time >= self.started_ms) {
+            let triggered = inner.triggered.contains(&fill.oid);
+            if !fill.crossed || triggered || fill.liquidation.is_some() {
+                by_order.entry(fill.oid).or_default().push(fill);
+            }
+        }
+        for (oid, fills) in by_order {
+            let first = fills[0];
+            let sz: Decimal = fills.iter().map(|f| f.sz).sum();
+            if sz.is_zero() {
+                continue;
+            }
+            let px = fills.iter().map(|f| f.px * f.sz).sum::<Decimal>() / sz;
+            let pnl: Decimal = fills.iter().map(|f| f.closed_pnl).sum();
+            let time = fills.iter().map(|f| f.time).max().unwrap_or(first.time);
+            let (kind, prefix) = if first.liquidation.is_some() {
+                (EventKind::Liquidation, "liquidation: ")
+            } else if inner.triggered.contains(&oid) {
+                (EventKind::Trigger, "trigger order ")
+            } else {
+                (EventKind::Fill, "")
+            };
+            let text = format!(
+                "{prefix}{} {} filled {} @ {}{}",
+                first.coin,
+                first.dir.to_lowercase(),
+                fmt_qty(sz),
+                fmt_price(round_sig(px, 6)),
+                if pnl.is_zero() {
+                    String::new()
+                } else {
+                    format!(" ({})", fmt_usd(pnl))
+                },
+            );
+            Self::push(&mut inner, kind, time, text);
+        }
+    }
+
+    /// Record an event this server noticed itself, such as a late order
+    /// confirmation, as happening now.
+    pub fn record(&self, kind: EventKind, text: String) {
+        let mut inner = self.lock();
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        Self::push(&mut inner, kind, now, text);
+    }
+
+    /// Unread events, marking them read.
+    pub fn take_unread(&self) -> Vec<AccountEvent> {
+        let mut inner = self.lock();
+        let read_upto = inner.read_upto;
+        inner.read_upto = inner.next_id;
+        inner
+            .events
+            .iter()
+            .filter(|e| e.id > read_upto)
+            .cloned()
+            .collect()
+    }
+
+    /// The latest `limit` events, newest first, each with whether it was
+    /// unread. Marks everything read.
+    pub fn recent(&self, limit: usize) -> Vec<(AccountEvent, bool)> {
+        let mut inner = self.lock();
+        let read_upto = inner.read_upto;
+        inner.read_upto = inner.next_id;
+        inner
+            .events
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|e| (e.clone(), e.id > read_upto))
+            .collect()
+    }
+
+    /// "Since your last message: ..." for the unread events, or None when
+    /// there are none. Liquidations come first; beyond `MAX_DIGEST_EVENTS`
+    /// the rest are counted.
+    pub fn digest(&self) -> Option<String> {
+        let mut events = self.take_unread();
+        if events.is_empty() {
+            return None;
+        }
+        events.sort_by_key(|e| (e.kind != EventKind::Liquidation, e.time));
+        let more = events.len().saturating_sub(MAX_DIGEST_EVENTS);
+        let shown: Vec<&str> = events
+            .iter()
+            .take(MAX_DIGEST_EVENTS)
+            .map(|e| e.text.as_str())
+            .collect();
+        Some(format!(
+            "> **Since your last message:** {}{}\n\n",
+            shown.join("; "),
+            if more > 0 {
+                format!(" (+{more} more, see get_events)")
+            } else {
+                String::new()
+            }
+        ))
+    }
+}
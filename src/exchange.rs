@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use either::Either;
+use futures::future::BoxFuture;
+use hypersdk::hypercore::types::{BasicOrder, Candle, Fill, FundingRate, OrderUpdate, UserBalance};
+use hypersdk::hypercore::{
+    CandleInterval, CandleSnapshotRequest, Chain, ClearinghouseState, HttpClient, OidOrCloid,
+};
+use hypersdk::{Address, Decimal};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::hyperliquid;
+
+pub type ApiFuture<'a, T> = BoxFuture<'a, anyhow::Result<T>>;
+
+/// Everything the tools ask of the exchange. `info` and `exchange` post raw
+/// requests; the typed queries are built on `info` unless an implementation
+/// has a better way to answer them.
+pub trait ExchangeApi: Send + Sync {
+    /// POST an `/info` request.
+    fn info(&self, request: Value) -> ApiFuture<'_, Value>;
+
+    /// POST a signed `/exchange` request.
+    fn exchange(&self, request: Value) -> ApiFuture<'_, Value>;
+
+    fn clearinghouse_state(&self, user: Address) -> ApiFuture<'_, ClearinghouseState> {
+        Box::pin(async move {
+            let body = self
+                .info(json!({ "type": "clearinghouseState", "user": format!("{user:#x}") }))
+                .await?;
+            parse(body, "clearinghouseState")
+        })
+    }
+
+    fn open_orders(&self, user: Address) -> ApiFuture<'_, Vec<BasicOrder>> {
+        Box::pin(async move {
+            let body = self
+                .info(json!({ "type": "frontendOpenOrders", "user": format!("{user:#x}") }))
+                .await?;
+            parse(body, "frontendOpenOrders")
+        })
+    }
+
+    fn user_fills(&self, user: Address) -> ApiFuture<'_, Vec<Fill>> {
+        Box::pin(async move {
+            let body = self
+                .info(json!({ "type": "userFills", "user": format!("{user:#x}") }))
+                .await?;
+            parse(body, "userFills")
+        })
+    }
+
+    /// None when the exchange doesn't know the order.
+    fn order_status(&self, user: Address, id: OidOrCloid) -> ApiFuture<'_, Option<OrderUpdate>> {
+        Box::pin(async move {
+            let oid = match id {
+                Either::Left(oid) => json!(oid),
+                Either::Right(cloid) => json!(format!("{cloid:#x}")),
+            };
+            let body = self
+                .info(json!({ "type": "orderStatus", "user": format!("{user:#x}"), "oid": oid }))
+                .await?;
+            match body.get("status").and_then(Value::as_str) {
+                Some("order") => parse(body["order"].clone(), "orderStatus").map(Some),
+                Some("unknownOid") => Ok(None),
+                _ => anyhow::bail!("Unexpected orderStatus response: {body}"),
+            }
+        })
+    }
+
+    /// Spot token balances.
+    fn user_balances(&self, user: Address) -> ApiFuture<'_, Vec<UserBalance>> {
+        Box::pin(async move {
+            let body = self
+                .info(json!({ "type": "spotClearinghouseState", "user": format!("{user:#x}") }))
+                .await?;
+            parse(body["balances"].clone(), "spotClearinghouseState")
+        })
+    }
+
+    fn all_mids(&self) -> ApiFuture<'_, HashMap<String, Decimal>> {
+        Box::pin(async move {
+            let body = self.info(json!({ "type": "allMids" })).await?;
+            parse(body, "allMids")
+        })
+    }
+
+    fn candle_snapshot(
+        &self,
+        coin: String,
+        interval: CandleInterval,
+        start_time: u64,
+        end_time: u64,
+    ) -> ApiFuture<'_, Vec<Candle>> {
+        Box::pin(async move {
+            let req = CandleSnapshotRequest {
+                coin,
+                interval,
+                start_time,
+                end_time,
+            };
+            let body = self
+                .info(json!({ "type": "candleSnapshot", "req": req }))
+                .await?;
+            parse(body, "candleSnapshot")
+        })
+    }
+
+    fn funding_history(
+        &self,
+        coin: String,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> ApiFuture<'_, Vec<FundingRate>> {
+        Box::pin(async move {
+            let mut request =
+                json!({ "type": "fundingHistory", "coin": coin, "startTime": start_time });
+            if let Some(end_time) = end_time {
+                request["endTime"] = json!(end_time);
+            }
+            let body = self.info(request).await?;
+            parse(body, "fundingHistory")
+        })
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: Value, what: &str) -> anyhow::Result<T> {
+    serde_json::from_value(body).with_context(|| format!("Malformed {what} response"))
+}
+
+/// The live API. Typed queries go through hypersdk's client.
+pub struct HttpExchange {
+    client: Arc<HttpClient>,
+    http: reqwest::Client,
+    chain: Chain,
+}
+
+impl HttpExchange {
+    pub fn new(client: Arc<HttpClient>, http: reqwest::Client, chain: Chain) -> Self {
+        Self {
+            client,
+            http,
+            chain,
+        }
+    }
+}
+
+impl ExchangeApi for HttpExchange {
+    fn info(&self, request: Value) -> ApiFuture<'_, Value> {
+        Box::pin(hyperliquid::raw_info_request(
+            &self.http, self.chain, request,
+        ))
+    }
+
+    fn exchange(&self, request: Value) -> ApiFuture<'_, Value> {
+        Box::pin(hyperliquid::raw_exchange_request(
+            &self.http, self.chain, request,
+        ))
+    }
+
+    fn clearinghouse_state(&self, user: Address) -> ApiFuture<'_, ClearinghouseState> {
+        Box::pin(self.client.clearinghouse_state(user, None))
+    }
+
+    fn open_orders(&self, user: Address) -> ApiFuture<'_, Vec<BasicOrder>> {
+        Box::pin(self.client.open_orders(user, None))
+    }
+
+    fn user_fills(&self, user: Address) -> ApiFuture<'_, Vec<Fill>> {
+        Box::pin(self.client.user_fills(user))
+    }
+
+    fn order_status(&self, user: Address, id: OidOrCloid) -> ApiFuture<'_, Option<OrderUpdate>> {
+        Box::pin(self.client.order_status(user, id))
+    }
+
+    fn user_balances(&self, user: Address) -> ApiFuture<'_, Vec<UserBalance>> {
+        Box::pin(self.client.user_balances(user))
+    }
+
+    fn all_mids(&self) -> ApiFuture<'_, HashMap<String, Decimal>> {
+        Box::pin(self.client.all_mids(None))
+    }
+
+    fn candle_snapshot(
+        &self,
+        coin: String,
+        interval: CandleInterval,
+        start_time: u64,
+        end_time: u64,
+    ) -> ApiFuture<'_, Vec<Candle>> {
+        Box::pin(
+            self.client
+                .candle_snapshot(coin, interval, start_time, end_time),
+        )
+    }
+
+    fn funding_history(
+        &self,
+        coin: String,
+        start_time: u64,
+        end_time: Option<u64>,
+    ) -> ApiFuture<'_, Vec<FundingRate>> {
+        Box::pin(self.client.funding_history(coin, start_time, end_time))
+    }
+}
+
+/// Canned responses for tests, keyed by info request type. Signed requests
+/// answer with the "exchange" entry. Every request is recorded.
+#[cfg(test)]
+pub mod mock {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// The account the fixtures describe.
+    pub const MOCK_USER: Address =
+        alloy::primitives::address!("0x00000000000000000000000000000000000000aa");
+
+    pub struct MockExchange {
+        responses: Mutex<HashMap<String, Value>>,
+        requests: Mutex<Vec<Value>>,
+        stalled: Mutex<Vec<String>>,
+        handlers: Mutex<HashMap<String, Handler>>,
+    }
+
+    type Handler = Arc<dyn Fn(&Value) -> Value + Send + Sync>;
+
+    impl MockExchange {
+        /// A mock answering from `fixtures()`.
+        pub fn new() -> Self {
+            Self {
+                responses: Mutex::new(fixtures()),
+                requests: Mutex::default(),
+                stalled: Mutex::default(),
+                handlers: Mutex::default(),
+            }
+        }
+
+        /// Answer `kind` requests with whatever `handler` builds from each
+        /// request, for endpoints whose answer depends on its parameters.
+        pub fn respond_with(
+            &self,
+            kind: &str,
+            handler: impl Fn(&Value) -> Value + Send + Sync + 'static,
+        ) {
+            self.handlers
+                .lock()
+                .unwrap()
+                .insert(kind.to_string(), Arc::new(handler));
+        }
+
+        /// Never answer `kind` requests, as if the exchange stopped
+        /// responding.
+        pub fn stall(&self, kind: &str) {
+            self.stalled.lock().unwrap().push(kind.to_string());
+        }
+
+        /// Answer `kind` requests with `response` from now on.
+        pub fn respond(&self, kind: &str, response: Value) {
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(kind.to_string(), response);
+        }
+
+        /// Requests seen so far, oldest first.
+        pub fn requests(&self) -> Vec<Value> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        async fn answer(&self, kind: &str, request: Value) -> anyhow::Result<Value> {
+            self.requests.lock().unwrap().push(request.clone());
+            if self.stalled.lock().unwrap().iter().any(|k| k == kind) {
+                std::future::pending::<()>().await;
+            }
+            let handler = self.handlers.lock().unwrap().get(kind).cloned();
+            if let Some(handler) = handler {
+                return Ok(handler(&request));
+            }
+            self.responses
+                .lock()
+                .unwrap()
+                .get(kind)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No fixture for {kind}"))
+        }
+    }
+
+    impl ExchangeApi for MockExchange {
+        fn info(&self, request: Value) -> ApiFuture<'_, Value> {
+            let kind = request["type"].as_str().unwrap_or_default().to_string();
+            Box::pin(async move { self.answer(&kind, request).await })
+        }
+
+        fn exchange(&self, request: Value) -> ApiFuture<'_, Value> {
+            Box::pin(async move { self.answer("exchange", request).await })
+        }
+    }
+
+    /// The text of a tool result, joined across content blocks.
+    pub fn text(result: &rmcp::model::CallToolResult) -> String {
+        result
+            .content
+            .iter()
+            .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+            .collect()
+    }
+
+    /// The test account: long 0.5 BTC and short 10 ETH, with 1,000 USDC and
+    /// 12.5 HYPE (2.5 held) on spot. Markets match `ServerState::mock`.
+    fn fixtures() -> HashMap<String, Value> {
+        let position = |coin: &str,
+                        szi: &str,
+                        entry: &str,
+                        value: &str,
+                        pnl: &str,
+                        roe: &str,
+                        liq: &str,
+                        margin: &str| {
+            json!({
+                "type": "oneWay",
+                "position": {
+                    "coin": coin,
+                    "szi": szi,
+                    "leverage": { "type": "cross", "value": 10 },
+                    "entryPx": entry,
+                    "positionValue": value,
+                    "unrealizedPnl": pnl,
+                    "returnOnEquity": roe,
+                    "liquidationPx": liq,
+                    "marginUsed": margin,
+                    "maxLeverage": 40,
+                    "cumFunding": { "allTime": "1.2", "sinceOpen": "0.4", "sinceChange": "0.4" }
+                }
+            })
+        };
+        let summary = json!({
+            "accountValue": "25000.0",
+            "totalNtlPos": "60605.0",
+            "totalRawUsd": "-5000.0",
+            "totalMarginUsed": "6060.5"
+        });
+        [
+            (
+                "clearinghouseState",
+                json!({
+                    "marginSummary": summary,
+                    "crossMarginSummary": summary,
+                    "crossMaintenanceMarginUsed": "1515.1",
+                    "withdrawable": "18939.5",
+                    "assetPositions": [
+                        position("BTC", "0.5", "60000.0", "30605.0", "605.0", "0.2016", "21000.5", "3060.5"),
+                        position("ETH", "-10.0", "3050.0", "30000.0", "500.0", "0.1667", "4890.2", "3000.0"),
+                        position("SOL", "0.0", "150.0", "0.0", "0.0", "0.0", "0.0", "0.0"),
+                    ],
+                    "time": 1_760_000_000_000u64
+                }),
+            ),
+            (
+                "spotClearinghouseState",
+                json!({
+                    "balances": [
+                        { "coin": "USDC", "token": 0, "hold": "0.0", "total": "1000.0", "entryNtl": "0.0" },
+                        { "coin": "HYPE", "token": 150, "hold": "2.5", "total": "12.5", "entryNtl": "400.0" },
+                        { "coin": "PURR", "token": 1, "hold": "0.0", "total": "0.0", "entryNtl": "0.0" }
+                    ]
+                }),
+            ),
+            (
+                "metaAndAssetCtxs",
+                json!([
+                    {
+                        "universe": [
+                            { "name": "BTC", "szDecimals": 5, "maxLeverage": 40, "marginTableId": 56 },
+                            { "name": "ETH", "szDecimals": 4, "maxLeverage": 25, "marginTableId": 25 }
+                        ],
+                        "marginTables": [
+                            [56, {
+                                "description": "tiered 40x",
+                                "marginTiers": [
+                                    { "lowerBound": "0.0", "maxLeverage": 40 },
+                                    { "lowerBound": "150000000.0", "maxLeverage": 20 }
+                                ]
+                            }]
+                        ]
+                    },
+                    [
+                        { "funding": "0.0000125", "openInterest": "28134.55", "prevDayPx": "61050.0", "dayNtlVlm": "1523456789.12",
+                          "premium": "0.0001", "oraclePx": "61200.0", "markPx": "61210.0", "midPx": "61209.5",
+                          "impactPxs": ["61205.0", "61215.0"] },
+                        { "funding": "-0.000003", "openInterest": "512345.6", "prevDayPx": "3010.1", "dayNtlVlm": "812345678.9",
+                          "premium": null, "oraclePx": "3001.0", "markPx": "3001.2", "midPx": null, "impactPxs": null }
+                    ]
+                ]),
+            ),
+            (
+                "predictedFundings",
+                json!([
+                    ["BTC", [["HlPerp", { "fundingRate": "0.0000125", "nextFundingTime": 1_760_004_000_000u64 }]]],
+                    ["ETH", [["BinPerp", { "fundingRate": "0.0001", "nextFundingTime": 1_760_004_000_000u64 }],
+                             ["HlPerp", { "fundingRate": "-0.000003", "nextFundingTime": 1_760_004_000_000u64 }]]]
+                ]),
+            ),
+            (
+                "orderStatus",
+                json!({
+                    "status": "order",
+                    "order": {
+                        "status": "open",
+                        "statusTimestamp": 1_760_000_000_000u64,
+                        "order": {
+                            "timestamp": 1_759_999_000_000u64,
+                            "coin": "BTC",
+                            "side": "B",
+                            "limitPx": "59000.0",
+                            "sz": "0.2",
+                            "oid": 42,
+                            "origSz": "0.25",
+                            "cloid": null
+                        }
+                    }
+                }),
+            ),
+            (
+                "spotMetaAndAssetCtxs",
+                json!([
+                    {
+                        "universe": [
+                            { "name": "PURR/USDC", "tokens": [1, 0], "index": 0, "isCanonical": true },
+                            { "name": "@107", "tokens": [150, 0], "index": 107, "isCanonical": false }
+                        ],
+                        "tokens": [
+                            { "name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0, "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true, "evmContract": null },
+                            { "name": "PURR", "szDecimals": 0, "weiDecimals": 5, "index": 1, "tokenId": "0xc1fb593aeffbeb02f85e0308e9956a90", "isCanonical": true, "evmContract": null },
+                            { "name": "HYPE", "szDecimals": 2, "weiDecimals": 8, "index": 150, "tokenId": "0x0d01dc56dcaaca66ad901c959b4011ec", "isCanonical": false, "evmContract": null }
+                        ]
+                    },
+                    [
+                        { "coin": "PURR/USDC", "prevDayPx": "0.2", "dayNtlVlm": "150000.0", "markPx": "0.21", "midPx": "0.2101", "circulatingSupply": "600000000.0" },
+                        { "coin": "@107", "prevDayPx": "40.0", "dayNtlVlm": "95000000.0", "markPx": "42.5", "midPx": "42.51", "circulatingSupply": "330000000.0" }
+                    ]
+                ]),
+            ),
+        ]
+        .into_iter()
+        .map(|(kind, response)| (kind.to_string(), response))
+        .collect()
+    }
+}
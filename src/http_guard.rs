@@ -0,0 +1,198 @@
+//! Request checks for the streamable HTTP transport.
+//!
+//! The HTTP transport serves every tool, including the ones that sign, so a
+//! request must prove it comes from the local machine or carry the bearer
+//! token. On a loopback address the Host and Origin headers must name that
+//! loopback address, which stops web pages from reaching the server through
+//! DNS rebinding. Any other address requires `HYPERLIQUID_HTTP_TOKEN`.
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+pub struct HttpGuard {
+    port: u16,
+    loopback: bool,
+    token: Option<String>,
+}
+
+impl HttpGuard {
+    /// Refuses to guard a non-loopback address without a token.
+    pub fn new(addr: SocketAddr, token: Option<String>) -> anyhow::Result<Self> {
+        let loopback = addr.ip().is_loopback();
+        if !loopback && token.is_none() {
+            anyhow::bail!(
+                "Refusing to serve HTTP on {addr} without HYPERLIQUID_HTTP_TOKEN: every tool, \
+                 including order placement and transfers, would be open to the network. Set a \
+                 token or bind to 127.0.0.1."
+            );
+        }
+        Ok(Self {
+            port: addr.port(),
+            loopback,
+            token,
+        })
+    }
+
+    /// Whether a request with these headers may reach the MCP service.
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), (StatusCode, &'static str)> {
+        if let Some(token) = &self.token {
+            let presented = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .unwrap_or_default();
+            if !constant_time_eq(presented.as_bytes(), token.as_bytes()) {
+                return Err((StatusCode::UNAUTHORIZED, "Missing or wrong bearer token"));
+            }
+        }
+        if !self.loopback {
+            return Ok(());
+        }
+
+        let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+        if !host.is_some_and(|h| self.is_local_authority(h)) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Host is not this server's loopback address",
+            ));
+        }
+        // Clients other than browsers send no Origin; browsers always do on
+        // the cross-origin requests a rebinding page would make.
+        if let Some(origin) = headers.get(header::ORIGIN) {
+            let authority = origin.to_str().ok().and_then(|o| {
+                o.strip_prefix("http://")
+                    .or_else(|| o.strip_prefix("https://"))
+            });
+            if !authority.is_some_and(|a| self.is_local_authority(a)) {
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    "Origin is not this server's loopback address",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `localhost`, `127.0.0.1` or `[::1]` with the bound port.
+    fn is_local_authority(&self, authority: &str) -> bool {
+        let Some((host, port)) = authority.rsplit_once(':') else {
+            return false;
+        };
+        port.parse() == Ok(self.port)
+            && (host.eq_ignore_ascii_case("localhost") || host == "127.0.0.1" || host == "[::1]")
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Axum middleware applying [`HttpGuard::check`].
+pub async fn guard(State(guard): State<Arc<HttpGuard>>, request: Request, next: Next) -> Response {
+    match guard.check(request.headers()) {
+        Ok(()) => next.run(request).await,
+        Err((status, reason)) => {
+            tracing::warn!(%status, reason, "Rejected HTTP request");
+            (status, reason).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn loopback_requires_local_host_and_origin() {
+        let guard = HttpGuard::new("127.0.0.1:8000".parse().unwrap(), None).unwrap();
+        let status = |pairs: &[(header::HeaderName, &str)]| {
+            guard.check(&headers(pairs)).err().map(|(s, _)| s)
+        };
+
+        assert_eq!(status(&[(header::HOST, "127.0.0.1:8000")]), None);
+        assert_eq!(status(&[(header::HOST, "localhost:8000")]), None);
+        assert_eq!(
+            status(&[
+                (header::HOST, "localhost:8000"),
+                (header::ORIGIN, "http://localhost:8000")
+            ]),
+            None
+        );
+        assert_eq!(status(&[]), Some(StatusCode::FORBIDDEN));
+        // A rebinding page resolves its own name to 127.0.0.1.
+        assert_eq!(
+            status(&[(header::HOST, "evil.example:8000")]),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(&[(header::HOST, "localhost:9000")]),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(&[
+                (header::HOST, "localhost:8000"),
+                (header::ORIGIN, "https://evil.example")
+            ]),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(&[(header::HOST, "localhost:8000"), (header::ORIGIN, "null")]),
+            Some(StatusCode::FORBIDDEN)
+        );
+    }
+
+    #[test]
+    fn other_addresses_require_a_token() {
+        assert!(HttpGuard::new("0.0.0.0:8000".parse().unwrap(), None).is_err());
+
+        let guard = HttpGuard::new("0.0.0.0:8000".parse().unwrap(), Some("s3cret".into())).unwrap();
+        let status = |pairs: &[(header::HeaderName, &str)]| {
+            guard.check(&headers(pairs)).err().map(|(s, _)| s)
+        };
+        assert_eq!(
+            status(&[
+                (header::HOST, "mcp.lan:8000"),
+                (header::AUTHORIZATION, "Bearer s3cret")
+            ]),
+            None
+        );
+        assert_eq!(
+            status(&[(header::HOST, "mcp.lan:8000")]),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(&[(header::AUTHORIZATION, "Bearer s3cre")]),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn loopback_with_a_token_checks_both() {
+        let guard =
+            HttpGuard::new("127.0.0.1:8000".parse().unwrap(), Some("s3cret".into())).unwrap();
+        assert!(
+            guard
+                .check(&headers(&[(header::HOST, "127.0.0.1:8000")]))
+                .is_err()
+        );
+        assert!(
+            guard
+                .check(&headers(&[
+                    (header::HOST, "127.0.0.1:8000"),
+                    (header::AUTHORIZATION, "Bearer s3cret")
+                ]))
+                .is_ok()
+        );
+    }
+}
@@ -4,16 +4,26 @@
 //! - Order placement with builder fee
 //! - Builder fee approval (EIP-712 user-signed action)
 //! - Leverage updates (RMP-based L1 action)
+//! - Sub-account creation and transfers (RMP-based L1 actions)
 //! - Raw info requests (POST to /info)
+//! - Vault listing (stats-data endpoint used by the web UI)
+//! - HyperEVM balances and transfers back to HyperCore
 use alloy::dyn_abi::{Eip712Types, Resolver, TypedData};
+use alloy::network::TransactionBuilder;
 use alloy::primitives::{Address, B256, keccak256};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
 use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
 use alloy::sol;
 use alloy::sol_types::SolStruct;
-use hypersdk::hypercore::{Chain, OrderGrouping, OrderRequest};
+use hypersdk::hypercore::{Chain, OrderGrouping, OrderRequest, SpotToken};
+use hypersdk::hyperevm::ERC20;
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::exchange::ExchangeApi;
+
 sol! {
     struct Agent {
         string source;
@@ -39,6 +49,8 @@ pub struct BuilderInfo {
 enum RmpAction {
     Order(OrderPayload),
     UpdateLeverage(UpdateLeveragePayload),
+    CreateSubAccount(CreateSubAccountPayload),
+    SubAccountTransfer(SubAccountTransferPayload),
 }
 
 #[derive(Serialize)]
@@ -57,6 +69,20 @@ pub struct UpdateLeveragePayload {
     pub leverage: u32,
 }
 
+#[derive(Serialize)]
+pub struct CreateSubAccountPayload {
+    pub name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAccountTransferPayload {
+    pub sub_account_user: String,
+    pub is_deposit: bool,
+    /// USDC amount in micro-units (6 decimals).
+    pub usd: u64,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ExchangeRequest<A: Serialize> {
@@ -80,6 +106,23 @@ fn base_url(chain: Chain) -> &'static str {
     }
 }
 
+/// The Arbitrum bridge contract that credits USDC deposits to the sender's
+/// Hyperliquid account.
+pub fn bridge_address(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Mainnet => "0x2Df1c51E09aECF9cacB7bc98cB1742757f163dF7",
+        Chain::Testnet => "0x08cfc1B6b2dCF36A1480b99353A354AA8AC56f89",
+    }
+}
+
+/// JSON-RPC endpoint of the HyperEVM paired with `chain`.
+pub fn evm_rpc_url(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Mainnet => hypersdk::hyperevm::DEFAULT_RPC_URL,
+        Chain::Testnet => "https://rpc.hyperliquid-testnet.xyz/evm",
+    }
+}
+
 fn chain_source(chain: Chain) -> &'static str {
     match chain {
         Chain::Mainnet => "a",
@@ -144,7 +187,7 @@ fn sign_rmp_action<S: SignerSync>(
 
 #[allow(clippy::too_many_arguments)]
 pub async fn place_order_with_builder<S: SignerSync>(
-    http: &reqwest::Client,
+    api: &dyn ExchangeApi,
     chain: Chain,
     signer: &S,
     orders: Vec<OrderRequest>,
@@ -168,10 +211,7 @@ pub async fn place_order_with_builder<S: SignerSync>(
         vault_address,
     };
 
-    let url = format!("{}/exchange", base_url(chain));
-    let resp = http.post(&url).json(&request).send().await?;
-    let body: Value = resp.json().await?;
-    Ok(body)
+    api.exchange(serde_json::to_value(&request)?).await
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -206,19 +246,82 @@ pub async fn update_leverage<S: SignerSync>(
     Ok(body)
 }
 
-/// Approve builder fees. Uses EIP-712 user-signed action with
-pub async fn approve_builder_fee<S: SignerSync>(
+/// Create a sub-account under the signer's (master) account. On success the
+/// response `data` holds the new sub-account address.
+pub async fn create_sub_account<S: SignerSync>(
     http: &reqwest::Client,
     chain: Chain,
     signer: &S,
-    builder_address: Address,
-    max_fee_rate: &str,
+    name: &str,
     nonce: u64,
 ) -> anyhow::Result<Value> {
-    let hyperliquid_chain = match chain {
+    let action = RmpAction::CreateSubAccount(CreateSubAccountPayload {
+        name: name.to_string(),
+    });
+
+    let signature = sign_rmp_action(signer, &action, nonce, chain, None)?;
+
+    let request = ExchangeRequest {
+        action,
+        nonce,
+        signature,
+        vault_address: None,
+    };
+
+    let url = format!("{}/exchange", base_url(chain));
+    let resp = http.post(&url).json(&request).send().await?;
+    let body: Value = resp.json().await?;
+    Ok(body)
+}
+
+/// Move USDC between the master account and a sub-account's perp balance.
+pub async fn sub_account_transfer<S: SignerSync>(
+    http: &reqwest::Client,
+    chain: Chain,
+    signer: &S,
+    sub_account: Address,
+    is_deposit: bool,
+    usd: u64,
+    nonce: u64,
+) -> anyhow::Result<Value> {
+    let action = RmpAction::SubAccountTransfer(SubAccountTransferPayload {
+        sub_account_user: format!("{:#x}", sub_account),
+        is_deposit,
+        usd,
+    });
+
+    let signature = sign_rmp_action(signer, &action, nonce, chain, None)?;
+
+    let request = ExchangeRequest {
+        action,
+        nonce,
+        signature,
+        vault_address: None,
+    };
+
+    let url = format!("{}/exchange", base_url(chain));
+    let resp = http.post(&url).json(&request).send().await?;
+    let body: Value = resp.json().await?;
+    Ok(body)
+}
+
+fn hyperliquid_chain_name(chain: Chain) -> &'static str {
+    match chain {
         Chain::Mainnet => "Mainnet",
         Chain::Testnet => "Testnet",
-    };
+    }
+}
+
+/// EIP-712 hash a builder fee approval is signed over. The domain chain id is
+/// `signature_chain_id`, which the action repeats as `signatureChainId`.
+fn approve_builder_fee_signing_hash(
+    chain: Chain,
+    signature_chain_id: u64,
+    builder_address: Address,
+    max_fee_rate: &str,
+    nonce: u64,
+) -> anyhow::Result<B256> {
+    let hyperliquid_chain = hyperliquid_chain_name(chain);
 
     // Build the EIP-712 message value
     let message = serde_json::json!({
@@ -242,7 +345,7 @@ pub async fn approve_builder_fee<S: SignerSync>(
     let domain = alloy::sol_types::eip712_domain! {
         name: "HyperliquidSignTransaction",
         version: "1",
-        chain_id: 421614,
+        chain_id: signature_chain_id,
         verifying_contract: Address::ZERO,
     };
 
@@ -253,9 +356,32 @@ pub async fn approve_builder_fee<S: SignerSync>(
         message,
     };
 
-    let signing_hash = typed_data
+    typed_data
         .eip712_signing_hash()
-        .map_err(|e| anyhow::anyhow!("EIP-712 hash failed: {e}"))?;
+        .map_err(|e| anyhow::anyhow!("EIP-712 hash failed: {e}"))
+}
+
+/// Approve builder fees. Uses EIP-712 user-signed action with
+/// `signature_chain_id` as the domain chain id; the action's
+/// `signatureChainId` is set from the same value, since the exchange
+/// recovers the signer from the domain it names.
+pub async fn approve_builder_fee<S: SignerSync>(
+    http: &reqwest::Client,
+    chain: Chain,
+    signature_chain_id: u64,
+    signer: &S,
+    builder_address: Address,
+    max_fee_rate: &str,
+    nonce: u64,
+) -> anyhow::Result<Value> {
+    let hyperliquid_chain = hyperliquid_chain_name(chain);
+    let signing_hash = approve_builder_fee_signing_hash(
+        chain,
+        signature_chain_id,
+        builder_address,
+        max_fee_rate,
+        nonce,
+    )?;
 
     let sig = signer
         .sign_hash_sync(&signing_hash)
@@ -270,7 +396,7 @@ pub async fn approve_builder_fee<S: SignerSync>(
     let action = serde_json::json!({
         "type": "approveBuilderFee",
         "hyperliquidChain": hyperliquid_chain,
-        "signatureChainId": "0x66eee",
+        "signatureChainId": format!("{signature_chain_id:#x}"),
         "maxFeeRate": max_fee_rate,
         "builder": format!("{:#x}", builder_address),
         "nonce": nonce,
@@ -303,3 +429,205 @@ pub async fn raw_info_request(
     let body: Value = resp.json().await?;
     Ok(body)
 }
+
+pub async fn raw_exchange_request(
+    http: &reqwest::Client,
+    chain: Chain,
+    request: Value,
+) -> anyhow::Result<Value> {
+    let url = format!("{}/exchange", base_url(chain));
+    let resp = http.post(&url).json(&request).send().await?;
+    let body: Value = resp.json().await?;
+    Ok(body)
+}
+
+/// Perp account value of `user` on `chain`, from `clearinghouseState`.
+pub async fn account_value(
+    http: &reqwest::Client,
+    chain: Chain,
+    user: Address,
+) -> anyhow::Result<hypersdk::Decimal> {
+    let body = raw_info_request(
+        http,
+        chain,
+        serde_json::json!({ "type": "clearinghouseState", "user": format!("{user:#x}") }),
+    )
+    .await?;
+    body.pointer("/marginSummary/accountValue")
+        .and_then(|v| v.as_str())
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("clearinghouseState response carried no account value"))
+}
+
+/// The exchange's clock in milliseconds, from `exchangeStatus`. Falls back to
+/// the HTTP `Date` header (second precision) if the body has no time.
+pub async fn server_time(http: &reqwest::Client, chain: Chain) -> anyhow::Result<u64> {
+    let url = format!("{}/info", base_url(chain));
+    let resp = http
+        .post(&url)
+        .json(&serde_json::json!({ "type": "exchangeStatus" }))
+        .send()
+        .await?;
+    let date = resp
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|d| d.to_str().ok())
+        .and_then(|d| chrono::DateTime::parse_from_rfc2822(d).ok())
+        .map(|d| d.timestamp_millis() as u64);
+    let body: Value = resp.json().await.unwrap_or_default();
+    body.get("time")
+        .and_then(|t| t.as_u64())
+        .or(date)
+        .ok_or_else(|| anyhow::anyhow!("Exchange response carried no server time"))
+}
+
+/// Fetch every vault with its TVL, APR and PnL history. The info API has no
+/// listing endpoint, so this uses the stats-data feed behind the web UI.
+pub async fn vault_listing(http: &reqwest::Client, chain: Chain) -> anyhow::Result<Value> {
+    let network = match chain {
+        Chain::Mainnet => "Mainnet",
+        Chain::Testnet => "Testnet",
+    };
+    let url = format!("https://stats-data.hyperliquid.xyz/{network}/vaults");
+    let resp = http.get(&url).send().await?.error_for_status()?;
+    let body: Value = resp.json().await?;
+    Ok(body)
+}
+
+/// Balance of `token` held by `owner` on the HyperEVM. HYPE is the native
+/// gas token there; every other linked token is an ERC-20.
+pub async fn evm_balance(
+    chain: Chain,
+    owner: Address,
+    token: &SpotToken,
+) -> anyhow::Result<hypersdk::Decimal> {
+    let provider = hypersdk::hyperevm::mainnet_with_url(evm_rpc_url(chain)).await?;
+    let wei = if token.name == "HYPE" {
+        provider.get_balance(owner).await?
+    } else {
+        let contract = token
+            .evm_contract
+            .ok_or_else(|| anyhow::anyhow!("{} is not linked to the HyperEVM", token.name))?;
+        ERC20::new(contract, provider)
+            .balanceOf(owner)
+            .call()
+            .await?
+    };
+    Ok(token.from_wei(wei))
+}
+
+/// Move `amount` of `token` from the signer's HyperEVM address to its
+/// HyperCore spot balance by sending it to the token's system address.
+/// Returns the EVM transaction hash once the transaction is mined.
+pub async fn transfer_from_evm(
+    chain: Chain,
+    signer: PrivateKeySigner,
+    token: &SpotToken,
+    amount: hypersdk::Decimal,
+) -> anyhow::Result<B256> {
+    let system = token
+        .cross_chain_address
+        .ok_or_else(|| anyhow::anyhow!("{} has no HyperCore system address", token.name))?;
+    let from = signer.address();
+    let provider =
+        hypersdk::hyperevm::mainnet_with_signer_and_url(evm_rpc_url(chain), signer).await?;
+    let wei = token.to_wei(amount);
+    let pending = if token.name == "HYPE" {
+        let tx = TransactionRequest::default()
+            .with_from(from)
+            .with_to(system)
+            .with_value(wei);
+        provider.send_transaction(tx).await?
+    } else {
+        let contract = token
+            .evm_contract
+            .ok_or_else(|| anyhow::anyhow!("{} is not linked to the HyperEVM", token.name))?;
+        ERC20::new(contract, provider)
+            .transfer(system, wei)
+            .send()
+            .await?
+    };
+    let receipt = pending.get_receipt().await?;
+    if !receipt.status() {
+        anyhow::bail!("EVM transaction {} reverted", receipt.transaction_hash);
+    }
+    Ok(receipt.transaction_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{U256, address, b256};
+
+    /// EIP-712 encoding of an `ApproveBuilderFee` written out by hand, so
+    /// the resolver-based path is checked against the spec rather than
+    /// against itself.
+    fn hand_encoded_hash(
+        hyperliquid_chain: &str,
+        signature_chain_id: u64,
+        builder: Address,
+        max_fee_rate: &str,
+        nonce: u64,
+    ) -> B256 {
+        let domain_type = keccak256(
+            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+        );
+        let mut domain = domain_type.to_vec();
+        domain.extend_from_slice(keccak256("HyperliquidSignTransaction").as_slice());
+        domain.extend_from_slice(keccak256("1").as_slice());
+        domain.extend_from_slice(&U256::from(signature_chain_id).to_be_bytes::<32>());
+        domain.extend_from_slice(B256::left_padding_from(Address::ZERO.as_slice()).as_slice());
+
+        let struct_type = keccak256(
+            "HyperliquidTransaction:ApproveBuilderFee(string hyperliquidChain,string maxFeeRate,address builder,uint64 nonce)",
+        );
+        let mut message = struct_type.to_vec();
+        message.extend_from_slice(keccak256(hyperliquid_chain).as_slice());
+        message.extend_from_slice(keccak256(max_fee_rate).as_slice());
+        message.extend_from_slice(B256::left_padding_from(builder.as_slice()).as_slice());
+        message.extend_from_slice(&U256::from(nonce).to_be_bytes::<32>());
+
+        let mut digest = vec![0x19, 0x01];
+        digest.extend_from_slice(keccak256(domain).as_slice());
+        digest.extend_from_slice(keccak256(message).as_slice());
+        keccak256(digest)
+    }
+
+    #[test]
+    fn builder_fee_hash_matches_known_vector() {
+        let builder = address!("0x1234567890abcdef1234567890abcdef12345678");
+        let nonce = 1_700_000_000_000;
+
+        let hash = approve_builder_fee_signing_hash(Chain::Mainnet, 42161, builder, "0.01%", nonce)
+            .unwrap();
+        assert_eq!(
+            hash,
+            hand_encoded_hash("Mainnet", 42161, builder, "0.01%", nonce)
+        );
+        assert_eq!(
+            hash,
+            b256!("0xcbd08a21d07a5d977334eaa56a937d574b61c2928c01be9dd25e42778644e71b")
+        );
+
+        let testnet =
+            approve_builder_fee_signing_hash(Chain::Testnet, 421614, builder, "0.01%", nonce)
+                .unwrap();
+        assert_eq!(
+            testnet,
+            hand_encoded_hash("Testnet", 421614, builder, "0.01%", nonce)
+        );
+    }
+
+    #[test]
+    fn builder_fee_hash_follows_signature_chain_id() {
+        let builder = address!("0x1234567890abcdef1234567890abcdef12345678");
+        let hash = |chain_id| {
+            approve_builder_fee_signing_hash(Chain::Mainnet, chain_id, builder, "0.01%", 1).unwrap()
+        };
+
+        // The domain chain id is what the signature commits to; the same
+        // action signed for another chain id must not verify.
+        assert_ne!(hash(42161), hash(421614));
+        assert_ne!(hash(42161), hash(1));
+    }
+}
@@ -0,0 +1,328 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use hypersdk::Decimal;
+use hypersdk::hypercore::Side;
+
+use crate::tools::format::{fmt_price, fmt_qty};
+
+/// A wall that fired is not reported again for this long, so one stepping
+/// along the book a tick at a time doesn't flood the digest.
+const ALERT_COOLDOWN_MS: u64 = 5 * 60 * 1000;
+
+/// A book level large enough to trip an alert.
+#[derive(Debug, Clone, Copy)]
+pub struct Wall {
+    pub side: Side,
+    pub px: Decimal,
+    pub sz: Decimal,
+}
+
+/// A `set_liquidity_alert` registration: report a level of at least
+/// `min_size` appearing within `band` of mid on the watched side.
+#[derive(Debug, Clone)]
+pub struct LiquidityAlert {
+    pub id: u64,
+    pub coin: String,
+    pub symbol: String,
+    /// None watches both sides.
+    pub side: Option<Side>,
+    pub min_size: Decimal,
+    /// Distance from mid as a fraction, e.g. 0.01 for 1%.
+    pub band: Decimal,
+    pub expires_at: DateTime<Utc>,
+    pub triggers: u32,
+    /// When it last fired, in milliseconds, and what it reported.
+    pub last_trigger: Option<(u64, String)>,
+    /// Walls in the last book seen; only levels not among them fire.
+    walls: Vec<(Side, Decimal)>,
+    /// Whether `walls` holds a book yet. Until it does, the first book only
+    /// records what is already there.
+    seeded: bool,
+}
+
+impl LiquidityAlert {
+    pub fn new(
+        coin: String,
+        symbol: String,
+        side: Option<Side>,
+        min_size: Decimal,
+        band: Decimal,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: 0,
+            coin,
+            symbol,
+            side,
+            min_size,
+            band,
+            expires_at,
+            triggers: 0,
+            last_trigger: None,
+            walls: Vec::new(),
+            seeded: false,
+        }
+    }
+
+    /// "bids", "asks" or "both sides".
+    pub fn describe_side(&self) -> &'static str {
+        match self.side {
+            Some(Side::Bid) => "bids",
+            Some(Side::Ask) => "asks",
+            None => "both sides",
+        }
+    }
+
+    /// Levels on the watched sides of at least `min_size` within the band
+    /// of mid. Books are (price, size), best first.
+    pub fn find_walls(
+        &self,
+        bids: &[(Decimal, Decimal)],
+        asks: &[(Decimal, Decimal)],
+    ) -> Vec<Wall> {
+        let (Some(&(bid, _)), Some(&(ask, _))) = (bids.first(), asks.first()) else {
+            return Vec::new();
+        };
+        let mid = (bid + ask) / Decimal::TWO;
+        if mid.is_zero() {
+            return Vec::new();
+        }
+        [(Side::Bid, bids), (Side::Ask, asks)]
+            .into_iter()
+            .filter(|(side, _)| self.side.is_none_or(|s| s == *side))
+            .flat_map(|(side, levels)| {
+                levels
+                    .iter()
+                    .filter(move |(px, sz)| {
+                        *sz >= self.min_size && ((px - mid) / mid).abs() <= self.band
+                    })
+                    .map(move |&(px, sz)| Wall { side, px, sz })
+            })
+            .collect()
+    }
+
+    /// Remember `walls` as already on the book, so they don't fire.
+    pub fn seed(&mut self, walls: &[Wall]) {
+        self.walls = walls.iter().map(|w| (w.side, w.px)).collect();
+        self.seeded = true;
+    }
+
+    /// e.g. "BTC bid wall 512 @ $61,200 (0.40% from mid)".
+    pub fn describe_wall(&self, wall: &Wall, mid: Decimal) -> String {
+        let distance = if mid.is_zero() {
+            Decimal::ZERO
+        } else {
+            ((wall.px - mid) / mid * Decimal::ONE_HUNDRED).abs()
+        };
+        format!(
+            "{} {} wall {} @ {} ({distance:.2}% from mid)",
+            self.symbol,
+            if wall.side == Side::Bid { "bid" } else { "ask" },
+            fmt_qty(wall.sz),
+            fmt_price(wall.px)
+        )
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    alerts: Vec<LiquidityAlert>,
+}
+
+/// Liquidity alerts for one account's feed. Books for their coins arrive
+/// over the WebSocket and are scanned as they come in.
+#[derive(Default)]
+pub struct LiquidityAlerts {
+    inner: Mutex<Inner>,
+}
+
+impl LiquidityAlerts {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register `alert`, returning its id and whether its coin was already
+    /// watched by another alert.
+    pub fn add(&self, mut alert: LiquidityAlert) -> (u64, bool) {
+        let mut inner = self.lock();
+        inner.next_id += 1;
+        alert.id = inner.next_id;
+        let watched = inner.alerts.iter().any(|a| a.coin == alert.coin);
+        inner.alerts.push(alert);
+        (inner.next_id, watched)
+    }
+
+    /// Live alerts, oldest first.
+    pub fn list(&self) -> Vec<LiquidityAlert> {
+        self.lock().alerts.clone()
+    }
+
+    /// Drop alerts past their expiry, returning the coins no alert watches
+    /// any more.
+    pub fn prune(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut inner = self.lock();
+        let mut expired: Vec<String> = Vec::new();
+        inner.alerts.retain(|a| {
+            let live = a.expires_at > now;
+            if !live {
+                expired.push(a.coin.clone());
+            }
+            live
+        });
+        expired.sort();
+        expired.dedup();
+        expired.retain(|coin| !inner.alerts.iter().any(|a| &a.coin == coin));
+        expired
+    }
+
+    /// Check a book for `coin`, returning a line for each wall that was not
+    /// there in the previous one.
+    pub fn scan(
+        &self,
+        coin: &str,
+        bids: &[(Decimal, Decimal)],
+        asks: &[(Decimal, Decimal)],
+        now_ms: u64,
+    ) -> Vec<String> {
+        let mid = match (bids.first(), asks.first()) {
+            (Some((bid, _)), Some((ask, _))) => (bid + ask) / Decimal::TWO,
+            _ => return Vec::new(),
+        };
+        let mut lines = Vec::new();
+        for alert in self.lock().alerts.iter_mut().filter(|a| a.coin == coin) {
+            let walls = alert.find_walls(bids, asks);
+            let cooling = alert
+                .last_trigger
+                .as_ref()
+                .is_some_and(|(t, _)| now_ms.saturating_sub(*t) < ALERT_COOLDOWN_MS);
+            let new: Vec<&Wall> = walls
+                .iter()
+                .filter(|w| !alert.walls.contains(&(w.side, w.px)))
+                .collect();
+            if alert.seeded && !cooling && !new.is_empty() {
+                let text = format!(
+                    "{} (liquidity alert #{})",
+                    new.iter()
+                        .map(|w| alert.describe_wall(w, mid))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    alert.id
+                );
+                alert.triggers += 1;
+                alert.last_trigger = Some((now_ms, text.clone()));
+                lines.push(text);
+            }
+            alert.seed(&walls);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn book(levels: &[(&str, &str)]) -> Vec<(Decimal, Decimal)> {
+        levels.iter().map(|(px, sz)| (d(px), d(sz))).collect()
+    }
+
+    fn alert(coin: &str, side: Option<Side>, expires_at: DateTime<Utc>) -> LiquidityAlert {
+        LiquidityAlert::new(
+            coin.into(),
+            coin.into(),
+            side,
+            d("100"),
+            d("0.01"),
+            expires_at,
+        )
+    }
+
+    #[test]
+    fn find_walls_keeps_large_levels_within_the_band_on_watched_sides() {
+        let bids = book(&[("100", "5"), ("99.5", "150"), ("98", "500")]);
+        let asks = book(&[("100.2", "120"), ("101", "3")]);
+        let expires = Utc::now();
+
+        let both = alert("BTC", None, expires).find_walls(&bids, &asks);
+        // 98 is 2% from mid, outside the 1% band.
+        let found: Vec<_> = both.iter().map(|w| (w.side, w.px)).collect();
+        assert_eq!(found, [(Side::Bid, d("99.5")), (Side::Ask, d("100.2"))]);
+
+        let asks_only = alert("BTC", Some(Side::Ask), expires).find_walls(&bids, &asks);
+        assert_eq!(asks_only.len(), 1);
+        assert_eq!(asks_only[0].side, Side::Ask);
+
+        assert!(
+            alert("BTC", None, expires)
+                .find_walls(&[], &asks)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn scan_seeds_then_fires_once_per_new_wall_outside_the_cooldown() {
+        let alerts = LiquidityAlerts::default();
+        alerts.add(alert("BTC", None, Utc::now()));
+        let asks = book(&[("100.2", "1")]);
+        let start = 1_000_000;
+
+        // A wall already on the first book only seeds.
+        let first = book(&[("100", "150")]);
+        assert!(alerts.scan("BTC", &first, &asks, start).is_empty());
+        assert!(alerts.scan("BTC", &first, &asks, start + 1).is_empty());
+
+        let added = book(&[("100", "150"), ("99.8", "200")]);
+        let lines = alerts.scan("BTC", &added, &asks, start + 2);
+        assert_eq!(lines.len(), 1);
+        assert!(
+            lines[0].starts_with("BTC bid wall 200 @ $99.8"),
+            "{}",
+            lines[0]
+        );
+        assert!(lines[0].ends_with("(liquidity alert #1)"));
+        // Still there on the next book: not new.
+        assert!(alerts.scan("BTC", &added, &asks, start + 3).is_empty());
+
+        // Another wall within the cooldown is suppressed.
+        let within = book(&[("100", "150"), ("99.8", "200"), ("99.6", "300")]);
+        assert!(alerts.scan("BTC", &within, &asks, start + 4).is_empty());
+
+        let after = book(&[("100", "150"), ("99.7", "400")]);
+        let lines = alerts.scan("BTC", &after, &asks, start + 2 + ALERT_COOLDOWN_MS);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("@ $99.7"), "{}", lines[0]);
+
+        let alert = &alerts.list()[0];
+        assert_eq!(alert.triggers, 2);
+        assert_eq!(
+            alert.last_trigger.as_ref().map(|(t, _)| *t),
+            Some(start + 2 + ALERT_COOLDOWN_MS)
+        );
+        // Books for other coins are ignored.
+        assert!(alerts.scan("ETH", &within, &asks, start).is_empty());
+    }
+
+    #[test]
+    fn prune_returns_only_coins_left_unwatched() {
+        let alerts = LiquidityAlerts::default();
+        let now = Utc::now();
+        let past = now - chrono::Duration::minutes(1);
+        let future = now + chrono::Duration::hours(1);
+        let (first, watched) = alerts.add(alert("BTC", None, past));
+        assert_eq!((first, watched), (1, false));
+        assert_eq!(alerts.add(alert("BTC", Some(Side::Ask), future)), (2, true));
+        alerts.add(alert("ETH", None, past));
+        alerts.add(alert("ETH", Some(Side::Bid), past));
+
+        assert_eq!(alerts.prune(now), ["ETH"]);
+        let left: Vec<_> = alerts.list().iter().map(|a| a.id).collect();
+        assert_eq!(left, [2]);
+        assert!(alerts.prune(now).is_empty());
+    }
+}
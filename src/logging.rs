@@ -0,0 +1,126 @@
+//! Tracing setup: stderr or rotating log files, behind a filter that can be
+//! changed at runtime via the `set_log_level` tool.
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, fmt, reload};
+
+const DEFAULT_MAX_LOG_FILES: usize = 7;
+
+struct FilterState {
+    handle: reload::Handle<EnvFilter, Registry>,
+    base: String,
+}
+
+static FILTER: OnceLock<FilterState> = OnceLock::new();
+/// Bumped on every filter change so a pending revert doesn't undo a newer one.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Install the global subscriber. With `log_file` set, logs go to a rotating
+/// file (and to stderr too when `HYPERLIQUID_LOG_STDERR=true`). The returned
+/// guard must be held for the lifetime of the process to flush the file.
+pub fn init(log_file: Option<&Path>) -> Result<Option<WorkerGuard>> {
+    let base = std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default();
+    let (filter, handle) = reload::Layer::new(EnvFilter::from_default_env());
+
+    let Some(path) = log_file else {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer().with_writer(std::io::stderr))
+            .init();
+        let _ = FILTER.set(FilterState { handle, base });
+        return Ok(None);
+    };
+
+    let rotation = match std::env::var("HYPERLIQUID_LOG_ROTATION")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "hourly" => Rotation::HOURLY,
+        "never" => Rotation::NEVER,
+        _ => Rotation::DAILY,
+    };
+    let max_files = std::env::var("HYPERLIQUID_LOG_MAX_FILES")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_LOG_FILES);
+
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let prefix = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Log file path must end in a file name")?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .max_log_files(max_files)
+        .filename_prefix(prefix)
+        .build(dir)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let also_stderr = std::env::var("HYPERLIQUID_LOG_STDERR")
+        .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes" | "on"))
+        .unwrap_or(false);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_ansi(false).with_writer(writer))
+        .with(also_stderr.then(|| fmt::layer().with_writer(std::io::stderr)))
+        .init();
+    let _ = FILTER.set(FilterState { handle, base });
+
+    Ok(Some(guard))
+}
+
+/// Replace the active filter with `directives` (e.g. `"debug"` or
+/// `"hyperliquid_mcp=debug"`). When `revert_after` is set, the startup filter
+/// is restored after that duration unless the filter is changed again first.
+pub fn set_filter(directives: &str, revert_after: Option<Duration>) -> Result<()> {
+    let state = FILTER.get().context("Logging is not initialized")?;
+    let filter = EnvFilter::try_new(directives)
+        .with_context(|| format!("Invalid log filter '{directives}'"))?;
+    state.handle.reload(filter)?;
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    tracing::info!(filter = directives, "Log filter changed");
+
+    if let Some(delay) = revert_after {
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let Some(state) = FILTER.get() else { return };
+            match EnvFilter::try_new(&state.base) {
+                Ok(filter) => {
+                    if state.handle.reload(filter).is_ok() {
+                        tracing::info!(filter = %state.base, "Log filter reverted");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to restore startup log filter"),
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// The filter active at startup (from `RUST_LOG`), empty if none.
+pub fn base_filter() -> String {
+    FILTER
+        .get()
+        .map(|state| state.base.clone())
+        .unwrap_or_default()
+}
@@ -1,29 +1,97 @@
-use anyhow::Result;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
 use rmcp::{ServiceExt, transport::stdio};
-use tracing_subscriber::{self, EnvFilter};
 
 pub mod agent;
+mod breaker;
 mod cache;
+mod call_log;
+mod cloids;
 mod config;
+mod events;
+mod exchange;
+mod http_guard;
 mod hyperliquid;
+mod liquidity;
+mod logging;
+mod meta;
+mod nonce;
+mod paper;
+mod persist;
 mod server;
 mod state;
 mod tools;
+mod trading_hours;
 mod ws;
 
+/// Command-line arguments. Each one overrides the equivalent environment setting.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Network to connect to: "mainnet" or "testnet" (overrides HYPERLIQUID_NETWORK)
+    #[arg(long, value_name = "NETWORK")]
+    pub network: Option<String>,
+
+    /// Never sign anything; keys are only used to derive account addresses
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Validate write actions and report what would be sent, without submitting
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Load settings from this file instead of ~/.config/hyperliquid-mcp/.env
+    #[arg(long, value_name = "PATH")]
+    pub env_file: Option<PathBuf>,
+
+    /// MCP transport to serve on
+    #[arg(long, value_enum, default_value_t = Transport::Stdio)]
+    pub transport: Transport,
+
+    /// Listen address for the HTTP transport (served at /mcp)
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8000")]
+    pub http_addr: SocketAddr,
+
+    /// Write logs to this file (rotated) instead of stderr; overrides HYPERLIQUID_LOG_FILE
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Transport {
+    Stdio,
+    Http,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_writer(std::io::stderr)
-        .init();
+    let cli = Cli::parse();
+
+    let env_file = config::load_env_file(cli.env_file.as_deref())?;
+    let log_file = cli
+        .log_file
+        .clone()
+        .or_else(|| std::env::var_os("HYPERLIQUID_LOG_FILE").map(PathBuf::from));
+    let _log_guard = logging::init(log_file.as_deref())?;
 
     tracing::info!("Starting Hyperliquid MCP server");
+    env_file.log();
 
-    let mut config = config::Config::from_env()?;
+    let mut config = config::Config::load(&cli)?;
+    tools::format::set_plain_numbers(config.plain_numbers);
+    if let Some(tz) = config.display_tz {
+        tools::timestamps::set_display_tz(tz);
+    }
 
     if config.main_wallet.is_some() && config.wallet.is_none() && config.vault_address.is_none() {
-        run_setup(&mut config).await?;
+        if config.dry_run {
+            tracing::warn!("Skipping first-time setup in dry-run mode");
+        } else {
+            run_setup(&mut config).await?;
+        }
     }
 
     if config.main_wallet.is_some() && config.wallet.is_some() && config.vault_address.is_none() {
@@ -33,8 +101,20 @@ async fn main() -> Result<()> {
         );
     }
 
+    let realtime = config.realtime;
+    let http_token = config.http_token.take();
+    let profile_configs = std::mem::take(&mut config.profiles);
     let state = state::ServerState::new(config).await?;
 
+    let mut profiles = std::collections::HashMap::new();
+    for profile in profile_configs {
+        let profile_state = state.with_profile(profile, realtime);
+        if profile_state.user_address.is_some() {
+            profile_state.check_and_cache_builder_approval().await;
+        }
+        profiles.insert(profile_state.profile.clone(), profile_state);
+    }
+
     // Check builder fee approval status at startup
     if state.user_address.is_some() {
         let approved = state.check_and_cache_builder_approval().await;
@@ -49,10 +129,54 @@ async fn main() -> Result<()> {
         }
     }
 
-    let server = server::HyperliquidMcp::new(state);
+    // The session baseline for the equity footer on destructive tools.
+    for account in std::iter::once(&state).chain(profiles.values()) {
+        if account.hide_equity_footer || account.user_address.is_none() {
+            continue;
+        }
+        let account = account.clone();
+        tokio::spawn(async move {
+            tools::account::equity_snapshot(&account).await;
+        });
+    }
+
+    let server = server::HyperliquidMcp::new(state, profiles);
+
+    match cli.transport {
+        Transport::Stdio => {
+            let service = server.serve(stdio()).await?;
+            service.waiting().await?;
+        }
+        Transport::Http => serve_http(server, cli.http_addr, http_token).await?,
+    }
+
+    Ok(())
+}
 
-    let service = server.serve(stdio()).await?;
-    service.waiting().await?;
+async fn serve_http(
+    server: server::HyperliquidMcp,
+    addr: SocketAddr,
+    token: Option<String>,
+) -> Result<()> {
+    use rmcp::transport::streamable_http_server::{
+        StreamableHttpService, session::local::LocalSessionManager,
+    };
+
+    let guard = std::sync::Arc::new(http_guard::HttpGuard::new(addr, token)?);
+    let service = StreamableHttpService::new(
+        move || Ok(server.clone()),
+        LocalSessionManager::default().into(),
+        Default::default(),
+    );
+    let router = axum::Router::new().nest_service("/mcp", service).layer(
+        axum::middleware::from_fn_with_state(guard, http_guard::guard),
+    );
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind {addr}"))?;
+    tracing::info!(%addr, "Serving MCP over streamable HTTP at /mcp");
+    axum::serve(listener, router).await?;
 
     Ok(())
 }
@@ -90,15 +214,20 @@ async fn run_setup(config: &mut config::Config) -> Result<()> {
     let fee_status = hyperliquid::approve_builder_fee(
         &http,
         config.chain,
+        config.signature_chain_id,
         &main_wallet,
         builder_addr,
-        "0.01%",
+        &config::fee_to_percent(config.builder_max_fee),
         nonce.next(),
     )
     .await?;
     tracing::info!(status = ?fee_status, "Builder fee approval");
 
-    let env_path = agent::save_agent_key_to_env(&agent_key_hex)?;
+    let env_path = agent::save_agent_key_to_env(
+        config::AGENT_KEY_VAR,
+        &agent_key_hex,
+        config.env_file.as_deref(),
+    )?;
     tracing::info!(path = %env_path.display(), "Setup complete — agent key saved");
 
     config.agent_address = Some(agent_address);
@@ -0,0 +1,638 @@
+//! Typed `metaAndAssetCtxs` and `spotMetaAndAssetCtxs` responses.
+//!
+//! Every field is optional and anything unrecognized lands in `extra`, so an
+//! API change never fails a parse. Instead, each response is checked for the
+//! fields the tools rely on, and a field that disappears (or is renamed) is
+//! logged once with the unrecognized fields that may have replaced it.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use hypersdk::Decimal;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::state::MarketKind;
+
+/// Universe fields every market is expected to have.
+const UNIVERSE_FIELDS: &[&str] = &["name", "szDecimals"];
+/// Asset context fields the perp tools read.
+const PERP_CTX_FIELDS: &[&str] = &[
+    "markPx",
+    "midPx",
+    "oraclePx",
+    "prevDayPx",
+    "dayNtlVlm",
+    "funding",
+    "premium",
+    "openInterest",
+    "impactPxs",
+];
+/// Asset context fields the spot tools read.
+const SPOT_CTX_FIELDS: &[&str] = &[
+    "markPx",
+    "midPx",
+    "prevDayPx",
+    "dayNtlVlm",
+    "circulatingSupply",
+];
+const TOKEN_FIELDS: &[&str] = &["name", "index", "szDecimals"];
+
+/// Missing fields already warned about, as "section.field".
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// One market in the universe of either response.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UniverseEntry {
+    /// "BTC" for perps, "PURR/USDC" or "@107" for spot.
+    pub name: String,
+    #[serde(deserialize_with = "lenient_u64")]
+    pub sz_decimals: Option<u64>,
+    #[serde(deserialize_with = "lenient_u64")]
+    pub max_leverage: Option<u64>,
+    /// Key into `MarketMeta::margin_tables` (perps only).
+    #[serde(deserialize_with = "lenient_u64")]
+    pub margin_table_id: Option<u64>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// One bracket of a margin table: positions of at least `lower_bound`
+/// notional may use at most `max_leverage`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginTier {
+    pub lower_bound: Decimal,
+    pub max_leverage: u32,
+}
+
+impl MarginTier {
+    /// Margin needed to open at max leverage, as a fraction of notional.
+    pub fn initial_rate(&self) -> Decimal {
+        Decimal::ONE / Decimal::from(self.max_leverage.max(1))
+    }
+
+    /// Half the initial rate; below this the position is liquidated.
+    pub fn maintenance_rate(&self) -> Decimal {
+        self.initial_rate() / Decimal::TWO
+    }
+}
+
+/// A perp's leverage schedule. Max leverage drops as the position grows, so
+/// the headline figure only holds for the first tier.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarginTable {
+    pub description: String,
+    /// Sorted by `lower_bound`; the first starts at zero.
+    pub tiers: Vec<MarginTier>,
+}
+
+impl MarginTable {
+    /// A single tier allowing `max_leverage` at any size.
+    pub fn flat(max_leverage: u32) -> Self {
+        MarginTable {
+            description: String::new(),
+            tiers: vec![MarginTier {
+                lower_bound: Decimal::ZERO,
+                max_leverage,
+            }],
+        }
+    }
+
+    /// Parse a `marginTables` entry. None without a usable tier.
+    fn parse(value: &Value) -> Option<Self> {
+        let mut tiers: Vec<MarginTier> = value
+            .get("marginTiers")?
+            .as_array()?
+            .iter()
+            .filter_map(|tier| {
+                Some(MarginTier {
+                    lower_bound: parse_decimal(tier.get("lowerBound")?)?,
+                    max_leverage: u32::try_from(tier.get("maxLeverage")?.as_u64()?).ok()?,
+                })
+            })
+            .collect();
+        tiers.sort_by_key(|t| t.lower_bound);
+        (!tiers.is_empty()).then(|| MarginTable {
+            description: value
+                .get("description")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            tiers,
+        })
+    }
+
+    /// Index of the tier a position of `notional` falls in. A notional on a
+    /// bound belongs to the tier starting there.
+    pub fn tier_index(&self, notional: Decimal) -> usize {
+        self.tiers
+            .iter()
+            .rposition(|t| t.lower_bound <= notional.abs())
+            .unwrap_or(0)
+    }
+
+    pub fn tier_at(&self, notional: Decimal) -> &MarginTier {
+        &self.tiers[self.tier_index(notional)]
+    }
+
+    /// The most leverage a position of `notional` may use.
+    pub fn max_leverage_at(&self, notional: Decimal) -> u32 {
+        self.tier_at(notional).max_leverage
+    }
+
+    /// What tier `i` subtracts from `notional × rate`, so maintenance margin
+    /// is continuous across bounds.
+    pub fn maintenance_deduction(&self, i: usize) -> Decimal {
+        self.tiers
+            .windows(2)
+            .take(i)
+            .map(|w| w[1].lower_bound * (w[1].maintenance_rate() - w[0].maintenance_rate()))
+            .sum()
+    }
+
+    /// Maintenance margin for a position of `notional`.
+    pub fn maintenance_margin(&self, notional: Decimal) -> Decimal {
+        let notional = notional.abs();
+        let i = self.tier_index(notional);
+        notional * self.tiers[i].maintenance_rate() - self.maintenance_deduction(i)
+    }
+
+    /// The largest notional that may use `leverage`: the bound of the first
+    /// tier that allows less. None when every tier allows it.
+    pub fn max_notional_at(&self, leverage: u32) -> Option<Decimal> {
+        self.tiers
+            .iter()
+            .find(|t| t.max_leverage < leverage)
+            .map(|t| t.lower_bound)
+    }
+}
+
+/// A spot token from `spotMetaAndAssetCtxs`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SpotToken {
+    pub name: String,
+    #[serde(deserialize_with = "lenient_u64")]
+    pub index: Option<u64>,
+    pub full_name: Option<String>,
+    #[serde(deserialize_with = "lenient_u64")]
+    pub sz_decimals: Option<u64>,
+    /// Set when the token is linked to a HyperEVM contract.
+    pub evm_contract: Option<Value>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A market's asset context. The exchange sends numbers as strings; values
+/// that don't parse are None rather than an error.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AssetCtx {
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub mark_px: Option<Decimal>,
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub mid_px: Option<Decimal>,
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub oracle_px: Option<Decimal>,
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub prev_day_px: Option<Decimal>,
+    /// Rolling 24h notional volume.
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub day_ntl_vlm: Option<Decimal>,
+    /// Hourly funding rate.
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub funding: Option<Decimal>,
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub premium: Option<Decimal>,
+    /// Open interest in coins.
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub open_interest: Option<Decimal>,
+    #[serde(deserialize_with = "lenient_decimal")]
+    pub circulating_supply: Option<Decimal>,
+    /// Bid and ask a standard impact notional would fill at.
+    #[serde(deserialize_with = "lenient_impact_pxs")]
+    pub impact_pxs: Option<(Decimal, Decimal)>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl AssetCtx {
+    /// Mark price, or the mid when there is no mark.
+    pub fn price(&self) -> Option<Decimal> {
+        self.mark_px.or(self.mid_px)
+    }
+}
+
+/// A whole `metaAndAssetCtxs` or `spotMetaAndAssetCtxs` response. `ctxs`
+/// lines up with `universe` by position; `tokens` is empty for perps.
+#[derive(Debug, Clone, Default)]
+pub struct MarketMeta {
+    pub universe: Vec<UniverseEntry>,
+    pub ctxs: Vec<AssetCtx>,
+    pub tokens: Vec<SpotToken>,
+    /// Perp margin tables by id; empty for spot.
+    pub margin_tables: HashMap<u64, MarginTable>,
+}
+
+impl MarketMeta {
+    /// Parse a `[meta, ctxs]` response. Anything unexpected is logged, once
+    /// per missing field, and parsed as far as possible.
+    pub fn parse(data: &Value, kind: MarketKind) -> Self {
+        let label = match kind {
+            MarketKind::Perp => "metaAndAssetCtxs",
+            MarketKind::Spot => "spotMetaAndAssetCtxs",
+        };
+        let meta = data.get(0);
+        let universe = section(meta.and_then(|m| m.get("universe")), label, "universe");
+        let ctxs = section(data.get(1), label, "asset contexts");
+        let tokens = match kind {
+            MarketKind::Perp => &[][..],
+            MarketKind::Spot => section(meta.and_then(|m| m.get("tokens")), label, "tokens"),
+        };
+
+        // [[id, {description, marginTiers}], ...]
+        let margin_tables = meta
+            .and_then(|m| m.get("marginTables"))
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some((entry.get(0)?.as_u64()?, MarginTable::parse(entry.get(1)?)?)))
+            .collect();
+
+        let meta = MarketMeta {
+            universe: universe.iter().map(parse_or_default).collect(),
+            ctxs: ctxs.iter().map(parse_or_default).collect(),
+            tokens: tokens.iter().map(parse_or_default).collect(),
+            margin_tables,
+        };
+
+        let ctx_fields = match kind {
+            MarketKind::Perp => PERP_CTX_FIELDS,
+            MarketKind::Spot => SPOT_CTX_FIELDS,
+        };
+        check_fields(
+            label,
+            "universe",
+            universe,
+            meta.universe.iter().map(|a| &a.extra),
+            UNIVERSE_FIELDS,
+        );
+        check_fields(
+            label,
+            "ctx",
+            ctxs,
+            meta.ctxs.iter().map(|c| &c.extra),
+            ctx_fields,
+        );
+        check_fields(
+            label,
+            "token",
+            tokens,
+            meta.tokens.iter().map(|t| &t.extra),
+            TOKEN_FIELDS,
+        );
+        meta
+    }
+
+    /// Universe entry and context of the market named `coin`.
+    pub fn find(&self, coin: &str) -> Option<(&UniverseEntry, &AssetCtx)> {
+        let i = self.universe.iter().position(|a| a.name == coin)?;
+        Some((&self.universe[i], self.ctxs.get(i)?))
+    }
+
+    /// The margin table of the perp named `coin`. Markets without a listed
+    /// table get a single tier at their max leverage.
+    pub fn margin_table(&self, coin: &str) -> Option<MarginTable> {
+        let (asset, _) = self.find(coin)?;
+        asset
+            .margin_table_id
+            .and_then(|id| self.margin_tables.get(&id).cloned())
+            .or_else(|| Some(MarginTable::flat(u32::try_from(asset.max_leverage?).ok()?)))
+    }
+
+    pub fn token(&self, name: &str) -> Option<&SpotToken> {
+        self.tokens.iter().find(|t| t.name == name)
+    }
+}
+
+fn parse_or_default<'a, T: Deserialize<'a> + Default>(value: &'a Value) -> T {
+    T::deserialize(value).unwrap_or_default()
+}
+
+/// The array at `value`, or empty (with a one-time warning) if it isn't one.
+fn section<'a>(value: Option<&'a Value>, label: &str, name: &str) -> &'a [Value] {
+    match value.and_then(Value::as_array) {
+        Some(items) => items,
+        None => {
+            warn_once(
+                format!("{label}.{name}"),
+                format!("{label} response has no {name} array; dependent columns will show N/A"),
+            );
+            &[]
+        }
+    }
+}
+
+/// Warn about each of `expected` that no entry carries, naming the fields
+/// that landed in `extra` as possible replacements. Fields present but null
+/// (e.g. `midPx` without a book) are fine.
+fn check_fields<'a>(
+    label: &str,
+    section: &str,
+    entries: &[Value],
+    extras: impl Iterator<Item = &'a HashMap<String, Value>>,
+    expected: &[&str],
+) {
+    let objects: Vec<_> = entries.iter().filter_map(Value::as_object).collect();
+    if objects.is_empty() {
+        return;
+    }
+    let missing: Vec<&str> = expected
+        .iter()
+        .copied()
+        .filter(|field| !objects.iter().any(|o| o.contains_key(*field)))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    let unknown: BTreeSet<&str> = extras
+        .flat_map(|extra| extra.keys())
+        .map(String::as_str)
+        .filter(|key| !UNUSED_FIELDS.contains(key))
+        .collect();
+    for field in missing {
+        let unknown = if unknown.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "; unrecognized fields: {}",
+                unknown.iter().copied().collect::<Vec<_>>().join(", ")
+            )
+        };
+        warn_once(
+            format!("{label}.{section}.{field}"),
+            format!(
+                "{label} {section} entries no longer include `{field}`; it will show as N/A{unknown}"
+            ),
+        );
+    }
+}
+
+/// Fields the API sends that no tool reads. Anything else in `extra` is
+/// reported as a possible rename.
+const UNUSED_FIELDS: &[&str] = &[
+    "onlyIsolated",
+    "isDelisted",
+    "marginMode",
+    "growthMode",
+    "lastGrowthModeChangeTime",
+    "tokens",
+    "index",
+    "isCanonical",
+    "weiDecimals",
+    "tokenId",
+    "deployerTradingFeeShare",
+    "dayBaseVlm",
+    "totalSupply",
+    "coin",
+];
+
+fn warn_once(key: String, message: String) {
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if warned.insert(key) {
+        tracing::warn!("{message}");
+    }
+}
+
+fn parse_decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+fn lenient_decimal<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Decimal>, D::Error> {
+    Ok(parse_decimal(&Value::deserialize(d)?))
+}
+
+fn lenient_u64<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    Ok(match Value::deserialize(d)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+fn lenient_impact_pxs<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<(Decimal, Decimal)>, D::Error> {
+    let value = Value::deserialize(d)?;
+    let pxs = value.as_array();
+    let px = |i: usize| pxs?.get(i).and_then(parse_decimal);
+    Ok(px(0).zip(px(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn perp_fixture() -> Value {
+        json!([
+            {
+                "universe": [
+                    {"name": "BTC", "szDecimals": 5, "maxLeverage": 40, "marginTableId": 56},
+                    {"name": "ETH", "szDecimals": 4, "maxLeverage": 25, "marginTableId": 55}
+                ],
+                "marginTables": []
+            },
+            [
+                {
+                    "funding": "0.0000125",
+                    "openInterest": "28134.55",
+                    "prevDayPx": "61050.0",
+                    "dayNtlVlm": "1523456789.12",
+                    "premium": "0.0001",
+                    "oraclePx": "61200.0",
+                    "markPx": "61210.0",
+                    "midPx": "61209.5",
+                    "impactPxs": ["61205.0", "61215.0"],
+                    "dayBaseVlm": "24890.1"
+                },
+                {
+                    "funding": "-0.000003",
+                    "openInterest": "512345.6",
+                    "prevDayPx": "3010.1",
+                    "dayNtlVlm": "812345678.9",
+                    "premium": null,
+                    "oraclePx": "3001.0",
+                    "markPx": "3001.2",
+                    "midPx": null,
+                    "impactPxs": null,
+                    "dayBaseVlm": "270000.0"
+                }
+            ]
+        ])
+    }
+
+    #[test]
+    fn parses_current_perp_payload() {
+        let meta = MarketMeta::parse(&perp_fixture(), MarketKind::Perp);
+        assert_eq!(meta.universe.len(), 2);
+        assert_eq!(meta.ctxs.len(), 2);
+        let (btc, ctx) = meta.find("BTC").unwrap();
+        assert_eq!(btc.sz_decimals, Some(5));
+        assert_eq!(btc.max_leverage, Some(40));
+        assert_eq!(ctx.funding, Some("0.0000125".parse().unwrap()));
+        assert_eq!(ctx.price(), Some("61210.0".parse().unwrap()));
+        assert_eq!(
+            ctx.impact_pxs,
+            Some(("61205.0".parse().unwrap(), "61215.0".parse().unwrap()))
+        );
+        let (_, eth) = meta.find("ETH").unwrap();
+        assert_eq!(eth.mid_px, None);
+        assert_eq!(eth.premium, None);
+        assert_eq!(eth.impact_pxs, None);
+    }
+
+    fn tiered_table() -> MarginTable {
+        MarginTable::parse(&json!({
+            "description": "tiered 20x",
+            "marginTiers": [
+                {"lowerBound": "20000000.0", "maxLeverage": 5},
+                {"lowerBound": "0.0", "maxLeverage": 20},
+                {"lowerBound": "3000000.0", "maxLeverage": 10}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_margin_tables() {
+        let mut data = perp_fixture();
+        data[0]["marginTables"] = json!([[56, {
+            "description": "tiered 40x",
+            "marginTiers": [
+                {"lowerBound": "0.0", "maxLeverage": 40},
+                {"lowerBound": "150000000.0", "maxLeverage": 20}
+            ]
+        }]]);
+        let meta = MarketMeta::parse(&data, MarketKind::Perp);
+        let btc = meta.margin_table("BTC").unwrap();
+        assert_eq!(btc.description, "tiered 40x");
+        assert_eq!(btc.tiers.len(), 2);
+        assert_eq!(btc.max_leverage_at("150000000".parse().unwrap()), 20);
+        // ETH's table 55 isn't listed, so its headline leverage applies throughout.
+        assert_eq!(meta.margin_table("ETH").unwrap(), MarginTable::flat(25));
+    }
+
+    #[test]
+    fn tier_lookup_at_bracket_boundaries() {
+        let table = tiered_table();
+        let tier = |n: &str| table.tier_index(n.parse().unwrap());
+        assert_eq!(tier("0"), 0);
+        assert_eq!(tier("2999999.99"), 0);
+        assert_eq!(tier("3000000"), 1);
+        assert_eq!(tier("19999999.99"), 1);
+        assert_eq!(tier("20000000"), 2);
+        assert_eq!(tier("-3000000"), 1);
+        assert_eq!(table.max_leverage_at("3000000".parse().unwrap()), 10);
+
+        assert_eq!(table.max_notional_at(25), Some(Decimal::ZERO));
+        assert_eq!(table.max_notional_at(20), Some("3000000".parse().unwrap()));
+        assert_eq!(table.max_notional_at(10), Some("20000000".parse().unwrap()));
+        assert_eq!(table.max_notional_at(5), None);
+    }
+
+    #[test]
+    fn maintenance_margin_is_continuous_at_bounds() {
+        let table = tiered_table();
+        let mm = |n: &str| table.maintenance_margin(n.parse().unwrap());
+        assert_eq!(table.maintenance_deduction(0), Decimal::ZERO);
+        assert_eq!(table.maintenance_deduction(1), "75000".parse().unwrap());
+        assert_eq!(table.maintenance_deduction(2), "1075000".parse().unwrap());
+        // 2.5% below 3M; 5% less the deduction from there.
+        assert_eq!(mm("1000000"), "25000".parse().unwrap());
+        assert_eq!(mm("2999999"), "74999.975".parse().unwrap());
+        assert_eq!(mm("3000000"), "75000".parse().unwrap());
+        assert_eq!(mm("19999999"), "924999.95".parse().unwrap());
+        assert_eq!(mm("20000000"), "925000".parse().unwrap());
+    }
+
+    #[test]
+    fn renamed_field_degrades_to_none() {
+        let mut data = perp_fixture();
+        let ctx = data[1][0].as_object_mut().unwrap();
+        let funding = ctx.remove("funding").unwrap();
+        ctx.insert("fundingRate".into(), funding);
+        let meta = MarketMeta::parse(&data, MarketKind::Perp);
+        let (_, ctx) = meta.find("BTC").unwrap();
+        assert_eq!(ctx.funding, None);
+        assert!(ctx.extra.contains_key("fundingRate"));
+        assert_eq!(ctx.mark_px, Some("61210.0".parse().unwrap()));
+    }
+
+    #[test]
+    fn retyped_values_parse_or_degrade() {
+        let mut data = perp_fixture();
+        data[1][0]["markPx"] = json!(61210.5);
+        data[1][0]["openInterest"] = json!({"coins": "1"});
+        data[1][0]["impactPxs"] = json!(["61205.0"]);
+        data[0]["universe"][0]["szDecimals"] = json!("5");
+        let meta = MarketMeta::parse(&data, MarketKind::Perp);
+        assert_eq!(meta.universe.len(), 2);
+        let (btc, ctx) = meta.find("BTC").unwrap();
+        assert_eq!(btc.name, "BTC");
+        assert_eq!(ctx.mark_px, Some("61210.5".parse().unwrap()));
+        assert_eq!(ctx.open_interest, None);
+        assert_eq!(ctx.impact_pxs, None);
+    }
+
+    #[test]
+    fn malformed_shape_is_empty() {
+        let meta = MarketMeta::parse(&json!({"error": "bad"}), MarketKind::Perp);
+        assert!(meta.universe.is_empty() && meta.ctxs.is_empty());
+        let meta = MarketMeta::parse(&json!([{"universe": []}]), MarketKind::Perp);
+        assert!(meta.find("BTC").is_none());
+    }
+
+    #[test]
+    fn parses_spot_payload() {
+        let data = json!([
+            {
+                "universe": [{"name": "@107", "tokens": [150, 0], "index": 107, "isCanonical": false}],
+                "tokens": [
+                    {"name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0, "evmContract": null},
+                    {"name": "HYPE", "szDecimals": 2, "weiDecimals": 8, "index": 150,
+                     "fullName": "Hyperliquid", "evmContract": null}
+                ]
+            },
+            [{"prevDayPx": "38.1", "dayNtlVlm": "12345.0", "markPx": "38.9", "midPx": "38.91",
+              "circulatingSupply": "333000000.0", "coin": "@107"}]
+        ]);
+        let meta = MarketMeta::parse(&data, MarketKind::Spot);
+        let (_, ctx) = meta.find("@107").unwrap();
+        assert_eq!(ctx.circulating_supply, Some("333000000.0".parse().unwrap()));
+        let hype = meta.token("HYPE").unwrap();
+        assert_eq!(hype.sz_decimals, Some(2));
+        assert_eq!(hype.full_name.as_deref(), Some("Hyperliquid"));
+        assert!(hype.evm_contract.is_none());
+    }
+
+    #[test]
+    fn missing_fields_are_detected_once() {
+        let entries = vec![json!({"name": "BTC"})];
+        check_fields(
+            "test",
+            "universe",
+            &entries,
+            std::iter::empty(),
+            &["name", "szDecimalsX"],
+        );
+        let warned = WARNED.lock().unwrap();
+        assert!(warned.contains("test.universe.szDecimalsX"));
+        assert!(!warned.contains("test.universe.name"));
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+use hypersdk::hypercore::Chain;
+
+use crate::hyperliquid;
+
+/// Recent action rejections kept for `get_nonce_health`.
+const MAX_ACTION_ERRORS: usize = 50;
+
+/// Local clock drift from the exchange beyond this is compensated for. The
+/// exchange accepts nonces within a wide window, so small drift is harmless.
+pub const CLOCK_SKEW_THRESHOLD_MS: i64 = 2_000;
+
+/// Exchange round trips per clock check. The one with the shortest round
+/// trip is used, since it leaves the least room for network asymmetry.
+pub const CLOCK_SAMPLES: usize = 5;
+
+/// Same scheme as the SDK's nonce handler: timestamps in milliseconds,
+/// bumped by one when two actions land in the same millisecond. The clock is
+/// shifted by `offset_ms` once drift from the exchange has been measured.
+pub struct NonceClock {
+    last: AtomicU64,
+    offset_ms: AtomicI64,
+    issued: AtomicU64,
+    errors: Mutex<VecDeque<ActionError>>,
+    measurement: Mutex<Option<SkewMeasurement>>,
+}
+
+/// One exchange round trip: the exchange clock minus the local clock at the
+/// midpoint of the request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClockSample {
+    pub skew_ms: i64,
+    pub round_trip_ms: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SkewMeasurement {
+    /// Local time the check finished, in milliseconds.
+    pub time: u64,
+    pub samples: Vec<ClockSample>,
+    /// Skew of the sample with the shortest round trip.
+    pub skew_ms: i64,
+}
+
+impl SkewMeasurement {
+    pub fn from_samples(time: u64, samples: Vec<ClockSample>) -> Option<Self> {
+        let best = samples.iter().min_by_key(|s| s.round_trip_ms)?.skew_ms;
+        Some(Self {
+            time,
+            samples,
+            skew_ms: best,
+        })
+    }
+
+    /// Largest minus smallest skew across samples; a wide spread means the
+    /// network, not the clock, dominates the reading.
+    pub fn spread_ms(&self) -> i64 {
+        let skews = self.samples.iter().map(|s| s.skew_ms);
+        skews.clone().max().unwrap_or(0) - skews.min().unwrap_or(0)
+    }
+
+    pub fn exceeds_threshold(&self) -> bool {
+        self.skew_ms.abs() > CLOCK_SKEW_THRESHOLD_MS
+    }
+}
+
+pub struct ActionError {
+    pub time: u64,
+    pub nonce: u64,
+    pub is_nonce_error: bool,
+    pub message: String,
+}
+
+/// Nonce-window rejections, e.g. "Invalid nonce: duplicate nonce" or nonces
+/// too far from the exchange clock.
+pub fn is_nonce_error(message: &str) -> bool {
+    message.to_lowercase().contains("nonce")
+}
+
+impl Default for NonceClock {
+    fn default() -> Self {
+        Self {
+            last: AtomicU64::new(0),
+            offset_ms: AtomicI64::new(0),
+            issued: AtomicU64::new(0),
+            errors: Mutex::new(VecDeque::new()),
+            measurement: Mutex::new(None),
+        }
+    }
+}
+
+impl NonceClock {
+    /// A clock for another profile that keeps the drift already measured.
+    pub fn carry_over(&self) -> Self {
+        let clock = Self::default();
+        clock.offset_ms.store(self.offset_ms(), Ordering::Relaxed);
+        *clock.measurement.lock().unwrap_or_else(|e| e.into_inner()) = self.measurement();
+        clock
+    }
+
+    /// Local time adjusted by the measured drift, in milliseconds.
+    pub fn now_ms(&self) -> u64 {
+        (chrono::Utc::now().timestamp_millis() + self.offset_ms.load(Ordering::Relaxed)) as u64
+    }
+
+    pub fn next(&self) -> u64 {
+        self.issued.fetch_add(1, Ordering::Relaxed);
+        let now = self.now_ms();
+        let mut last = self.last.load(Ordering::Relaxed);
+        loop {
+            let next = now.max(last + 1);
+            match self
+                .last
+                .compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return next,
+                Err(current) => last = current,
+            }
+        }
+    }
+
+    /// The most recently issued nonce, or zero before the first action.
+    pub fn last(&self) -> u64 {
+        self.last.load(Ordering::Relaxed)
+    }
+
+    pub fn issued(&self) -> u64 {
+        self.issued.load(Ordering::Relaxed)
+    }
+
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn record_error(&self, nonce: u64, message: &str) {
+        let mut errors = self.errors.lock().unwrap_or_else(|e| e.into_inner());
+        if errors.len() >= MAX_ACTION_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(ActionError {
+            time: chrono::Utc::now().timestamp_millis() as u64,
+            nonce,
+            is_nonce_error: is_nonce_error(message),
+            message: message.to_string(),
+        });
+    }
+
+    /// Run `f` over the recorded rejections, oldest first.
+    pub fn with_errors<T>(&self, f: impl FnOnce(&VecDeque<ActionError>) -> T) -> T {
+        f(&self.errors.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// The most recent clock check, if one has succeeded.
+    pub fn measurement(&self) -> Option<SkewMeasurement> {
+        self.measurement
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Measure drift against the exchange clock over `CLOCK_SAMPLES` round
+    /// trips and compensate when it is beyond the threshold. Failed samples
+    /// are dropped; the check fails only if none succeed.
+    pub async fn sync(
+        &self,
+        http: &reqwest::Client,
+        chain: Chain,
+    ) -> anyhow::Result<SkewMeasurement> {
+        let mut samples = Vec::with_capacity(CLOCK_SAMPLES);
+        let mut last_error = None;
+        for _ in 0..CLOCK_SAMPLES {
+            let before = chrono::Utc::now().timestamp_millis();
+            match hyperliquid::server_time(http, chain).await {
+                Ok(server) => {
+                    let after = chrono::Utc::now().timestamp_millis();
+                    samples.push(ClockSample {
+                        skew_ms: server as i64 - (before + after) / 2,
+                        round_trip_ms: after - before,
+                    });
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        let time = chrono::Utc::now().timestamp_millis() as u64;
+        let Some(measurement) = SkewMeasurement::from_samples(time, samples) else {
+            return Err(
+                last_error.unwrap_or_else(|| anyhow::anyhow!("No clock samples were taken"))
+            );
+        };
+
+        let offset = if measurement.exceeds_threshold() {
+            tracing::warn!(
+                skew_ms = measurement.skew_ms,
+                "LOCAL CLOCK IS {:.1}s {} THE EXCHANGE; shifting nonces to compensate. \
+                 Sync the system clock (NTP) to avoid intermittent invalid-nonce rejections",
+                measurement.skew_ms.abs() as f64 / 1000.0,
+                if measurement.skew_ms > 0 {
+                    "BEHIND"
+                } else {
+                    "AHEAD OF"
+                },
+            );
+            measurement.skew_ms
+        } else {
+            0
+        };
+        self.offset_ms.store(offset, Ordering::Relaxed);
+        *self.measurement.lock().unwrap_or_else(|e| e.into_inner()) = Some(measurement.clone());
+        Ok(measurement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurement_uses_the_fastest_round_trip() {
+        let samples = vec![
+            ClockSample {
+                skew_ms: 3_400,
+                round_trip_ms: 900,
+            },
+            ClockSample {
+                skew_ms: 2_950,
+                round_trip_ms: 40,
+            },
+            ClockSample {
+                skew_ms: 2_700,
+                round_trip_ms: 350,
+            },
+        ];
+        let m = SkewMeasurement::from_samples(0, samples).unwrap();
+        assert_eq!(m.skew_ms, 2_950);
+        assert_eq!(m.spread_ms(), 700);
+        assert!(m.exceeds_threshold());
+        assert!(SkewMeasurement::from_samples(0, Vec::new()).is_none());
+    }
+}
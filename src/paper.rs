@@ -0,0 +1,210 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use hypersdk::Decimal;
+
+use crate::config::BASE_TAKER_FEE;
+
+/// Paper fills kept for display; the totals cover all of them.
+const MAX_PAPER_FILLS: usize = 200;
+
+/// A virtual perp position. `szi` is signed: positive long, negative short.
+#[derive(Debug, Clone, Copy)]
+pub struct PaperPosition {
+    pub szi: Decimal,
+    pub entry_px: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaperFill {
+    pub time: DateTime<Utc>,
+    pub coin: String,
+    pub is_buy: bool,
+    pub sz: Decimal,
+    pub px: Decimal,
+    pub fee: Decimal,
+    /// PnL realized by the part of the fill that reduced a position.
+    pub closed_pnl: Decimal,
+}
+
+/// The virtual portfolio dry-run orders trade against: a USDC balance and
+/// perp positions, marked to real mids. Every fill pays the base taker fee.
+/// No margin or liquidation is modelled.
+#[derive(Debug, Clone)]
+pub struct PaperAccount {
+    pub starting_balance: Decimal,
+    /// Starting balance plus realized PnL, less fees.
+    pub balance: Decimal,
+    pub positions: BTreeMap<String, PaperPosition>,
+    pub fills: VecDeque<PaperFill>,
+    pub fees_paid: Decimal,
+    pub realized_pnl: Decimal,
+    pub started_at: DateTime<Utc>,
+}
+
+impl PaperAccount {
+    pub fn new(starting_balance: Decimal) -> Self {
+        Self {
+            starting_balance,
+            balance: starting_balance,
+            positions: BTreeMap::new(),
+            fills: VecDeque::new(),
+            fees_paid: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            started_at: Utc::now(),
+        }
+    }
+
+    pub fn position(&self, coin: &str) -> Decimal {
+        self.positions.get(coin).map_or(Decimal::ZERO, |p| p.szi)
+    }
+
+    /// Apply a fill at `px`. Adding to a position averages the entry; reducing
+    /// realizes PnL against it, and a fill larger than the position flips it
+    /// at `px`.
+    pub fn fill(&mut self, coin: &str, is_buy: bool, sz: Decimal, px: Decimal) -> PaperFill {
+        let delta = if is_buy { sz } else { -sz };
+        let current = self.positions.get(coin).copied();
+        let mut closed_pnl = Decimal::ZERO;
+        let next = match current {
+            None => Some(PaperPosition {
+                szi: delta,
+                entry_px: px,
+            }),
+            Some(pos) if pos.szi.is_sign_positive() == is_buy => {
+                let szi = pos.szi + delta;
+                Some(PaperPosition {
+                    szi,
+                    entry_px: (pos.szi.abs() * pos.entry_px + sz * px) / szi.abs(),
+                })
+            }
+            Some(pos) => {
+                let closed = sz.min(pos.szi.abs());
+                let direction = if pos.szi.is_sign_positive() {
+                    Decimal::ONE
+                } else {
+                    -Decimal::ONE
+                };
+                closed_pnl = closed * (px - pos.entry_px) * direction;
+                let szi = pos.szi + delta;
+                if szi.is_zero() {
+                    None
+                } else if szi.is_sign_positive() == pos.szi.is_sign_positive() {
+                    Some(PaperPosition { szi, ..pos })
+                } else {
+                    Some(PaperPosition { szi, entry_px: px })
+                }
+            }
+        };
+        match next {
+            Some(pos) => self.positions.insert(coin.to_string(), pos),
+            None => self.positions.remove(coin),
+        };
+
+        let fee = (sz * px * BASE_TAKER_FEE).round_dp(6);
+        self.balance += closed_pnl - fee;
+        self.realized_pnl += closed_pnl;
+        self.fees_paid += fee;
+        let fill = PaperFill {
+            time: Utc::now(),
+            coin: coin.to_string(),
+            is_buy,
+            sz,
+            px,
+            fee,
+            closed_pnl,
+        };
+        self.fills.push_back(fill.clone());
+        while self.fills.len() > MAX_PAPER_FILLS {
+            self.fills.pop_front();
+        }
+        fill
+    }
+
+    /// Unrealized PnL of a position at `mark`.
+    pub fn unrealized(&self, coin: &str, mark: Decimal) -> Decimal {
+        self.positions
+            .get(coin)
+            .map_or(Decimal::ZERO, |p| p.szi * (mark - p.entry_px))
+    }
+
+    /// Balance plus unrealized PnL, marking each position at its mid or, when
+    /// no mid is known, at entry.
+    pub fn equity(&self, mids: &HashMap<String, Decimal>) -> Decimal {
+        self.balance
+            + self
+                .positions
+                .iter()
+                .map(|(coin, p)| {
+                    self.unrealized(coin, mids.get(coin).copied().unwrap_or(p.entry_px))
+                })
+                .sum::<Decimal>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn long_adds_average_and_reduces_realize() {
+        let mut account = PaperAccount::new(d("10000"));
+
+        account.fill("BTC", true, d("1"), d("100"));
+        let add = account.fill("BTC", true, d("1"), d("110"));
+        assert_eq!(add.closed_pnl, Decimal::ZERO);
+        let pos = account.positions["BTC"];
+        assert_eq!((pos.szi, pos.entry_px), (d("2"), d("105")));
+
+        let reduce = account.fill("BTC", false, d("0.5"), d("115"));
+        assert_eq!(reduce.closed_pnl, d("5"));
+        let pos = account.positions["BTC"];
+        assert_eq!((pos.szi, pos.entry_px), (d("1.5"), d("105")));
+
+        let close = account.fill("BTC", false, d("1.5"), d("100"));
+        assert_eq!(close.closed_pnl, d("-7.5"));
+        assert!(!account.positions.contains_key("BTC"));
+        assert_eq!(account.position("BTC"), Decimal::ZERO);
+
+        // 0.045% of 100 + 110 + 57.5 + 150 notional.
+        assert_eq!(account.fees_paid, d("0.187875"));
+        assert_eq!(account.realized_pnl, d("-2.5"));
+        assert_eq!(account.balance, d("10000") - d("2.5") - d("0.187875"));
+        assert_eq!(account.fills.len(), 4);
+    }
+
+    #[test]
+    fn short_flips_to_long_at_the_fill_price() {
+        let mut account = PaperAccount::new(d("1000"));
+
+        let open = account.fill("ETH", false, d("2"), d("50"));
+        assert_eq!(open.fee, d("0.045"));
+        assert_eq!(account.position("ETH"), d("-2"));
+
+        // Buying 3 closes the 2 short at a 10 profit each and opens 1 long.
+        let flip = account.fill("ETH", true, d("3"), d("40"));
+        assert_eq!(flip.closed_pnl, d("20"));
+        assert_eq!(flip.fee, d("0.054"));
+        let pos = account.positions["ETH"];
+        assert_eq!((pos.szi, pos.entry_px), (d("1"), d("40")));
+        assert_eq!(account.balance, d("1000") + d("20") - d("0.099"));
+    }
+
+    #[test]
+    fn equity_marks_to_mid_or_entry() {
+        let mut account = PaperAccount::new(d("1000"));
+        account.fill("ETH", false, d("2"), d("50"));
+        account.fill("BTC", true, d("0.1"), d("100"));
+        let balance = account.balance;
+
+        assert_eq!(account.unrealized("ETH", d("45")), d("10"));
+        let mids = HashMap::from([("ETH".to_string(), d("45"))]);
+        // BTC has no mid, so it counts at entry: no unrealized PnL.
+        assert_eq!(account.equity(&mids), balance + d("10"));
+        assert_eq!(account.equity(&HashMap::new()), balance);
+    }
+}
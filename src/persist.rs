@@ -0,0 +1,277 @@
+//! Background constructs that outlive the server process.
+//!
+//! Some things the server arms keep running on the exchange after a restart,
+//! which desktop clients do constantly. Their tracking is saved to
+//! `~/.config/hyperliquid-mcp/state.json` whenever it changes and restored at
+//! startup, keyed by network and account so profiles and networks don't mix.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use hypersdk::Address;
+use hypersdk::hypercore::Chain;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::tools::timestamps::fmt_instant;
+
+/// Bumped when a field changes meaning. New fields are added with defaults
+/// instead, so older files keep loading.
+pub const STATE_VERSION: u32 = 1;
+
+/// Serializes writes from this process; profiles share the file.
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// The whole state file. Fields this version doesn't know are kept in
+/// `extra` and written back, so an older server never strips what a newer
+/// one saved.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateFile {
+    pub version: u32,
+    pub accounts: BTreeMap<String, AccountState>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What is tracked for one account on one network.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountState {
+    /// When the last `schedule_cancel` fires, in ms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_cancel_ms: Option<i64>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AccountState {
+    pub fn scheduled_cancel(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_cancel_ms
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.scheduled_cancel_ms.is_none() && self.extra.is_empty()
+    }
+
+    /// Drop entries that no longer refer to anything live: a scheduled
+    /// cancel whose time has passed has already fired. One still ahead is
+    /// kept even if the account has no open orders, since the exchange
+    /// keeps it armed and it will cancel whatever is placed before then.
+    pub fn reconcile(&mut self, now: DateTime<Utc>) {
+        if self.scheduled_cancel().is_some_and(|when| when <= now) {
+            self.scheduled_cancel_ms = None;
+        }
+    }
+
+    /// One line per restored construct, for the startup log and
+    /// `get_server_health`.
+    pub fn describe(&self) -> Vec<String> {
+        self.scheduled_cancel()
+            .map(|when| format!("scheduled cancel at {}", fmt_instant(when)))
+            .into_iter()
+            .collect()
+    }
+}
+
+pub fn default_state_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(config::STATE_FILE_PATH))
+}
+
+/// Entry key for an account, e.g. "mainnet:0xabc…".
+pub fn account_key(chain: Chain, address: Address) -> String {
+    let network = match chain {
+        Chain::Mainnet => "mainnet",
+        Chain::Testnet => "testnet",
+    };
+    format!("{network}:{address:#x}")
+}
+
+/// Read the state file. A missing file is empty; an unreadable one is
+/// logged and treated as empty rather than stopping the server.
+pub fn load(path: &Path) -> StateFile {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return StateFile::default(),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Could not read state file");
+            return StateFile::default();
+        }
+    };
+    match serde_json::from_str::<StateFile>(&text) {
+        Ok(file) => {
+            if file.version > STATE_VERSION {
+                tracing::warn!(
+                    path = %path.display(),
+                    version = file.version,
+                    supported = STATE_VERSION,
+                    "State file was written by a newer server; fields this version doesn't know are kept but ignored"
+                );
+            }
+            file
+        }
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring unparseable state file");
+            StateFile::default()
+        }
+    }
+}
+
+/// The account's entry, minus anything that has lapsed since it was saved.
+/// Lapsed entries are removed from the file too.
+pub fn restore(path: &Path, key: &str) -> AccountState {
+    let Some(mut account) = load(path).accounts.remove(key) else {
+        return AccountState::default();
+    };
+    let before = account.scheduled_cancel_ms;
+    account.reconcile(Utc::now());
+    if account.scheduled_cancel_ms != before
+        && let Err(e) = update_account(path, key, |saved| saved.reconcile(Utc::now()))
+    {
+        tracing::warn!(path = %path.display(), error = %e, "Could not update state file");
+    }
+    account
+}
+
+/// Apply `change` to one account's entry and write the file atomically. The
+/// file is re-read first, so concurrent server processes only touch their
+/// own accounts, and fields this version doesn't know survive.
+pub fn update_account(
+    path: &Path,
+    key: &str,
+    change: impl FnOnce(&mut AccountState),
+) -> Result<()> {
+    use std::io::Write;
+
+    let _guard = SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load(path);
+    file.version = file.version.max(STATE_VERSION);
+    let mut account = file.accounts.remove(key).unwrap_or_default();
+    change(&mut account);
+    if !account.is_empty() {
+        file.accounts.insert(key.to_string(), account);
+    }
+
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let tmp_path = dir.join(format!(".state.json.{}.tmp", std::process::id()));
+    {
+        let mut f = std::fs::File::create(&tmp_path)?;
+        f.write_all(
+            serde_json::to_string_pretty(&file)
+                .context("Could not serialize state")?
+                .as_bytes(),
+        )?;
+        f.write_all(b"\n")?;
+        f.sync_all()?;
+    }
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A state file path unique to this test and process, removed on drop.
+    struct TempState(PathBuf);
+
+    impl TempState {
+        fn new(name: &str, contents: &serde_json::Value) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "hyperliquid-mcp-{name}-{}.json",
+                std::process::id()
+            ));
+            std::fs::write(&path, contents.to_string()).unwrap();
+            Self(path)
+        }
+
+        fn read(&self) -> serde_json::Value {
+            serde_json::from_str(&std::fs::read_to_string(&self.0).unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for TempState {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn future_ms() -> i64 {
+        (Utc::now() + chrono::Duration::hours(1)).timestamp_millis()
+    }
+
+    #[test]
+    fn update_account_keeps_fields_it_does_not_know() {
+        let when = future_ms();
+        let file = TempState::new(
+            "unknown-fields",
+            &json!({
+                "version": 1,
+                "watchlists": { "default": ["BTC"] },
+                "accounts": {
+                    "mainnet:0xaa": { "scheduled_cancel_ms": when, "trailing_stops": [7] },
+                    "mainnet:0xbb": { "scheduled_cancel_ms": when },
+                }
+            }),
+        );
+
+        update_account(&file.0, "mainnet:0xaa", |a| a.scheduled_cancel_ms = None).unwrap();
+        update_account(&file.0, "mainnet:0xbb", |a| a.scheduled_cancel_ms = None).unwrap();
+
+        let saved = file.read();
+        assert_eq!(saved["watchlists"], json!({ "default": ["BTC"] }));
+        // The unknown field keeps the entry alive; the emptied one is gone.
+        assert_eq!(
+            saved["accounts"],
+            json!({ "mainnet:0xaa": { "trailing_stops": [7] } })
+        );
+    }
+
+    #[test]
+    fn newer_versions_still_load_and_keep_their_version() {
+        let when = future_ms();
+        let file = TempState::new(
+            "newer-version",
+            &json!({
+                "version": STATE_VERSION + 1,
+                "accounts": { "testnet:0xaa": { "scheduled_cancel_ms": when } }
+            }),
+        );
+
+        let restored = restore(&file.0, "testnet:0xaa");
+        assert_eq!(restored.scheduled_cancel_ms, Some(when));
+        update_account(&file.0, "testnet:0xbb", |a| {
+            a.scheduled_cancel_ms = Some(when)
+        })
+        .unwrap();
+        assert_eq!(file.read()["version"], json!(STATE_VERSION + 1));
+    }
+
+    #[test]
+    fn restore_drops_and_saves_a_lapsed_scheduled_cancel() {
+        let past = (Utc::now() - chrono::Duration::minutes(1)).timestamp_millis();
+        let file = TempState::new(
+            "lapsed",
+            &json!({
+                "version": 1,
+                "accounts": { "mainnet:0xaa": { "scheduled_cancel_ms": past } }
+            }),
+        );
+
+        let restored = restore(&file.0, "mainnet:0xaa");
+        assert_eq!(restored.scheduled_cancel(), None);
+        assert!(restored.describe().is_empty());
+        assert_eq!(file.read()["accounts"], json!({}));
+        assert_eq!(restore(&file.0, "mainnet:0xcc").scheduled_cancel_ms, None);
+    }
+}
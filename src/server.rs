@@ -1,24 +1,95 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use rmcp::{
-    ErrorData as McpError, ServerHandler,
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
+    ErrorData as McpError, RoleServer, ServerHandler,
+    handler::server::{router::tool::ToolRouter, tool::ToolCallContext, wrapper::Parameters},
     model::*,
-    tool, tool_handler, tool_router,
+    service::RequestContext,
+    tool, tool_router,
 };
+use tracing::Instrument;
 
-use crate::state::ServerState;
-use crate::tools::{account, market, trading, transfer, vault};
+use crate::call_log::{self, FailedCall, Failure};
+use crate::config;
+use crate::state::{ServerState, VaultLeader, mcp_err};
+use crate::tools::{account, analysis, diagnostics, market, trading, transfer, vault};
 
 #[derive(Clone)]
 pub struct HyperliquidMcp {
     state: ServerState,
+    profiles: Arc<HashMap<String, ServerState>>,
     tool_router: ToolRouter<Self>,
 }
 
+impl HyperliquidMcp {
+    /// Resolve the state for an account profile; `None` selects the default profile.
+    fn profile_state(&self, profile: Option<&str>) -> Result<&ServerState, McpError> {
+        let name = match profile.map(str::trim) {
+            None | Some("") => return Ok(&self.state),
+            Some(name) => name.to_lowercase(),
+        };
+        if name == config::DEFAULT_PROFILE {
+            return Ok(&self.state);
+        }
+        self.profiles.get(&name).ok_or_else(|| {
+            let mut known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            known.insert(0, config::DEFAULT_PROFILE);
+            mcp_err(&format!(
+                "Unknown profile '{name}'. Configured profiles: {}",
+                known.join(", ")
+            ))
+        })
+    }
+
+    /// Rewrite `place_order`'s order type and TIF descriptions to name the
+    /// defaults configured via env, so the model sees the effective fallback.
+    fn with_configured_defaults(&self, mut tool: Tool) -> Tool {
+        let state = &self.state;
+        if tool.name != "place_order"
+            || (state.default_order_type == config::DEFAULT_ORDER_TYPE
+                && state.default_tif == config::DEFAULT_TIF)
+        {
+            return tool;
+        }
+
+        let mut schema = (*tool.input_schema).clone();
+        if let Some(props) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+            let descriptions = [
+                (
+                    "order_type",
+                    format!(
+                        "Order type: \"limit\" or \"market\" (default on this server: \"{}\"). \
+                         A price without an order type is a limit order.",
+                        state.default_order_type
+                    ),
+                ),
+                (
+                    "time_in_force",
+                    format!(
+                        "Time in force: \"Gtc\", \"Ioc\", or \"Alo\" (post-only) (default on this server: \"{}\")",
+                        state.default_tif
+                    ),
+                ),
+            ];
+            for (name, description) in descriptions {
+                if let Some(prop) = props.get_mut(name).and_then(|p| p.as_object_mut()) {
+                    prop.insert("description".into(), description.into());
+                }
+            }
+        }
+        tool.input_schema = Arc::new(schema);
+        tool
+    }
+}
+
 #[tool_router]
 impl HyperliquidMcp {
-    pub fn new(state: ServerState) -> Self {
+    pub fn new(state: ServerState, profiles: HashMap<String, ServerState>) -> Self {
         Self {
             state,
+            profiles: Arc::new(profiles),
             tool_router: Self::tool_router(),
         }
     }
@@ -45,6 +116,17 @@ impl HyperliquidMcp {
         market::get_market_summary(&self.state, req).await
     }
 
+    #[tool(
+        name = "get_margin_tiers",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_margin_tiers(
+        &self,
+        Parameters(req): Parameters<market::GetMarginTiersRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        market::get_margin_tiers(&self.state, req).await
+    }
+
     #[tool(
         name = "get_order_book",
         annotations(read_only_hint = true, destructive_hint = false)
@@ -56,6 +138,25 @@ impl HyperliquidMcp {
         market::get_order_book(&self.state, req).await
     }
 
+    #[tool(
+        name = "set_liquidity_alert",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn set_liquidity_alert(
+        &self,
+        Parameters(req): Parameters<market::SetLiquidityAlertRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        market::set_liquidity_alert(&self.state, req).await
+    }
+
+    #[tool(
+        name = "list_liquidity_alerts",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn list_liquidity_alerts(&self) -> Result<CallToolResult, McpError> {
+        market::list_liquidity_alerts(&self.state).await
+    }
+
     #[tool(
         name = "get_candles",
         annotations(read_only_hint = true, destructive_hint = false)
@@ -78,28 +179,121 @@ impl HyperliquidMcp {
         market::get_funding_rates(&self.state, req).await
     }
 
+    #[tool(
+        name = "compare_funding",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn compare_funding(
+        &self,
+        Parameters(req): Parameters<market::CompareFundingRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        market::compare_funding(&self.state, req).await
+    }
+
+    #[tool(
+        name = "estimate_carry",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn estimate_carry(
+        &self,
+        Parameters(req): Parameters<market::EstimateCarryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        market::estimate_carry(state, req).await
+    }
+
+    #[tool(
+        name = "get_spread_history",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_spread_history(
+        &self,
+        Parameters(req): Parameters<market::GetSpreadHistoryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        market::get_spread_history(&self.state, req).await
+    }
+
+    #[tool(
+        name = "get_spot_deploy_state",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_spot_deploy_state(
+        &self,
+        Parameters(req): Parameters<market::GetSpotDeployStateRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        market::get_spot_deploy_state(&self.state, req).await
+    }
+
     #[tool(
         name = "get_wallet_address",
         annotations(read_only_hint = true, destructive_hint = false)
     )]
-    async fn get_wallet_address(&self) -> Result<CallToolResult, McpError> {
-        account::get_wallet_address(&self.state).await
+    async fn get_wallet_address(
+        &self,
+        Parameters(req): Parameters<account::GetWalletAddressRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_wallet_address(state).await
     }
 
     #[tool(
         name = "get_positions",
         annotations(read_only_hint = true, destructive_hint = false)
     )]
-    async fn get_positions(&self) -> Result<CallToolResult, McpError> {
-        account::get_positions(&self.state).await
+    async fn get_positions(
+        &self,
+        Parameters(req): Parameters<account::GetPositionsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_positions(state).await
     }
 
     #[tool(
         name = "get_balances",
         annotations(read_only_hint = true, destructive_hint = false)
     )]
-    async fn get_balances(&self) -> Result<CallToolResult, McpError> {
-        account::get_balances(&self.state).await
+    async fn get_balances(
+        &self,
+        Parameters(req): Parameters<account::GetBalancesRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_balances(state).await
+    }
+
+    #[tool(
+        name = "get_account_overview",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_account_overview(
+        &self,
+        Parameters(req): Parameters<account::GetAccountOverviewRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_account_overview(state).await
+    }
+
+    #[tool(
+        name = "get_risk_status",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_risk_status(
+        &self,
+        Parameters(req): Parameters<account::GetRiskStatusRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_risk_status(state).await
+    }
+
+    #[tool(
+        name = "get_account_permissions",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_account_permissions(
+        &self,
+        Parameters(req): Parameters<account::GetAccountPermissionsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_account_permissions(state).await
     }
 
     #[tool(
@@ -110,7 +304,8 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<account::GetOpenOrdersRequest>,
     ) -> Result<CallToolResult, McpError> {
-        account::get_open_orders(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_open_orders(state, req).await
     }
 
     #[tool(
@@ -121,7 +316,20 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<account::GetTradeHistoryRequest>,
     ) -> Result<CallToolResult, McpError> {
-        account::get_trade_history(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_trade_history(state, req).await
+    }
+
+    #[tool(
+        name = "get_recent_fills",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_recent_fills(
+        &self,
+        Parameters(req): Parameters<account::GetRecentFillsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_recent_fills(state, req).await
     }
 
     #[tool(
@@ -132,7 +340,163 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<account::GetOrderStatusRequest>,
     ) -> Result<CallToolResult, McpError> {
-        account::get_order_status(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_order_status(state, req).await
+    }
+
+    #[tool(
+        name = "watch_order",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn watch_order(
+        &self,
+        Parameters(req): Parameters<account::WatchOrderRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::watch_order(state, req).await
+    }
+
+    #[tool(
+        name = "get_watched_orders",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_watched_orders(
+        &self,
+        Parameters(req): Parameters<account::GetWatchedOrdersRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_watched_orders(state).await
+    }
+
+    #[tool(
+        name = "get_events",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_events(
+        &self,
+        Parameters(req): Parameters<account::GetEventsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_events(state, req).await
+    }
+
+    #[tool(
+        name = "get_staking_yield",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_staking_yield(
+        &self,
+        Parameters(req): Parameters<account::GetStakingYieldRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_staking_yield(state).await
+    }
+
+    #[tool(
+        name = "get_deposit_status",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_deposit_status(
+        &self,
+        Parameters(req): Parameters<account::GetDepositStatusRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::get_deposit_status(state, req).await
+    }
+
+    #[tool(
+        name = "snapshot_account",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn snapshot_account(
+        &self,
+        Parameters(req): Parameters<account::SnapshotAccountRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::snapshot_account(state, req).await
+    }
+
+    #[tool(
+        name = "diff_account_snapshot",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn diff_account_snapshot(
+        &self,
+        Parameters(req): Parameters<account::DiffAccountSnapshotRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        account::diff_account_snapshot(state, req).await
+    }
+
+    #[tool(
+        name = "get_pnl_curve",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_pnl_curve(
+        &self,
+        Parameters(req): Parameters<analysis::GetPnlCurveRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        analysis::get_pnl_curve(state, req).await
+    }
+
+    #[tool(
+        name = "get_true_performance",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_true_performance(
+        &self,
+        Parameters(req): Parameters<analysis::GetTruePerformanceRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        analysis::get_true_performance(state).await
+    }
+
+    #[tool(
+        name = "get_execution_quality",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_execution_quality(
+        &self,
+        Parameters(req): Parameters<analysis::GetExecutionQualityRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        analysis::get_execution_quality(state, req).await
+    }
+
+    #[tool(
+        name = "get_coin_stats",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_coin_stats(
+        &self,
+        Parameters(req): Parameters<analysis::GetCoinStatsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        analysis::get_coin_stats(state, req).await
+    }
+
+    #[tool(
+        name = "get_position_timeline",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_position_timeline(
+        &self,
+        Parameters(req): Parameters<analysis::GetPositionTimelineRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        analysis::get_position_timeline(state, req).await
+    }
+
+    #[tool(
+        name = "get_trend_snapshot",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_trend_snapshot(
+        &self,
+        Parameters(req): Parameters<analysis::GetTrendSnapshotRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        analysis::get_trend_snapshot(&self.state, req).await
     }
 
     /// WARNING: Executes a real trade with real funds.
@@ -144,7 +508,32 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::PlaceOrderRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::place_order(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::place_order(state, req).await
+    }
+
+    #[tool(
+        name = "place_batch_orders",
+        annotations(read_only_hint = false, destructive_hint = true)
+    )]
+    async fn place_batch_orders(
+        &self,
+        Parameters(req): Parameters<trading::PlaceBatchOrdersRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::place_batch_orders(state, req).await
+    }
+
+    #[tool(
+        name = "place_pair_trade",
+        annotations(read_only_hint = false, destructive_hint = true)
+    )]
+    async fn place_pair_trade(
+        &self,
+        Parameters(req): Parameters<trading::PlacePairTradeRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::place_pair_trade(state, req).await
     }
 
     #[tool(
@@ -155,7 +544,20 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::CancelOrderRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::cancel_order(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::cancel_order(state, req).await
+    }
+
+    #[tool(
+        name = "cancel_orders",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn cancel_orders(
+        &self,
+        Parameters(req): Parameters<trading::CancelOrdersRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::cancel_orders(state, req).await
     }
 
     #[tool(
@@ -166,7 +568,8 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::CancelAllOrdersRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::cancel_all_orders(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::cancel_all_orders(state, req).await
     }
 
     #[tool(
@@ -177,7 +580,8 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::ModifyOrderRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::modify_order(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::modify_order(state, req).await
     }
 
     #[tool(
@@ -188,7 +592,20 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::SetLeverageRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::set_leverage(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::set_leverage(state, req).await
+    }
+
+    #[tool(
+        name = "set_leverage_all",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn set_leverage_all(
+        &self,
+        Parameters(req): Parameters<trading::SetLeverageAllRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::set_leverage_all(state, req).await
     }
 
     /// WARNING: Immediately closes your full position at market price.
@@ -200,7 +617,60 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::ClosePositionRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::close_position(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::close_position(state, req).await
+    }
+
+    /// WARNING: Immediately closes the selected positions at market price.
+    #[tool(
+        name = "close_positions",
+        annotations(read_only_hint = false, destructive_hint = true)
+    )]
+    async fn close_positions(
+        &self,
+        Parameters(req): Parameters<trading::ClosePositionsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::close_positions(state, req).await
+    }
+
+    /// Clear the dry-run paper account: positions, fills and PnL.
+    #[tool(
+        name = "reset_paper_account",
+        annotations(read_only_hint = false, destructive_hint = true)
+    )]
+    async fn reset_paper_account(
+        &self,
+        Parameters(req): Parameters<trading::ResetPaperAccountRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::reset_paper_account(state, req).await
+    }
+
+    #[tool(
+        name = "flatten_coin",
+        annotations(read_only_hint = false, destructive_hint = true)
+    )]
+    async fn flatten_coin(
+        &self,
+        Parameters(req): Parameters<trading::FlattenCoinRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::flatten_coin(state, req).await
+    }
+
+    /// WARNING: Market-sells small spot balances into USDC when confirm is true.
+    /// Only balances worth at least $10 can be sold.
+    #[tool(
+        name = "consolidate_dust",
+        annotations(read_only_hint = false, destructive_hint = true)
+    )]
+    async fn consolidate_dust(
+        &self,
+        Parameters(req): Parameters<trading::ConsolidateDustRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::consolidate_dust(state, req).await
     }
 
     #[tool(
@@ -211,7 +681,20 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<trading::ScheduleCancelRequest>,
     ) -> Result<CallToolResult, McpError> {
-        trading::schedule_cancel(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::schedule_cancel(state, req).await
+    }
+
+    #[tool(
+        name = "check_spot_readiness",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn check_spot_readiness(
+        &self,
+        Parameters(req): Parameters<trading::CheckSpotReadinessRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        trading::check_spot_readiness(state, req).await
     }
 
     #[tool(
@@ -222,7 +705,32 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<transfer::TransferSpotPerpsRequest>,
     ) -> Result<CallToolResult, McpError> {
-        transfer::transfer_between_spot_perps(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::transfer_between_spot_perps(state, req).await
+    }
+
+    #[tool(
+        name = "transfer_to_evm",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn transfer_to_evm(
+        &self,
+        Parameters(req): Parameters<transfer::TransferEvmRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::transfer_to_evm(state, req).await
+    }
+
+    #[tool(
+        name = "transfer_from_evm",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn transfer_from_evm(
+        &self,
+        Parameters(req): Parameters<transfer::TransferEvmRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::transfer_from_evm(state, req).await
     }
 
     #[tool(
@@ -233,20 +741,52 @@ impl HyperliquidMcp {
         transfer::create_agent_wallet(&self.state).await
     }
 
+    #[tool(
+        name = "renew_agent",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn renew_agent(
+        &self,
+        Parameters(req): Parameters<transfer::RenewAgentRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::renew_agent(state).await
+    }
+
+    #[tool(
+        name = "create_subaccount",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn create_subaccount(
+        &self,
+        Parameters(req): Parameters<transfer::CreateSubAccountRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::create_sub_account(state, req).await
+    }
+
     #[tool(
         name = "approve_builder_fee",
         annotations(read_only_hint = false, destructive_hint = false)
     )]
-    async fn approve_builder_fee(&self) -> Result<CallToolResult, McpError> {
-        transfer::approve_builder_fee(&self.state).await
+    async fn approve_builder_fee(
+        &self,
+        Parameters(req): Parameters<transfer::ApproveBuilderFeeRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::approve_builder_fee(state, req).await
     }
 
     #[tool(
         name = "check_builder_fee",
         annotations(read_only_hint = true, destructive_hint = false)
     )]
-    async fn check_builder_fee(&self) -> Result<CallToolResult, McpError> {
-        transfer::check_builder_fee(&self.state).await
+    async fn check_builder_fee(
+        &self,
+        Parameters(req): Parameters<transfer::CheckBuilderFeeRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        transfer::check_builder_fee(state).await
     }
 
     #[tool(
@@ -260,6 +800,17 @@ impl HyperliquidMcp {
         vault::get_vault_details(&self.state, req).await
     }
 
+    #[tool(
+        name = "list_vaults",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn list_vaults(
+        &self,
+        Parameters(req): Parameters<vault::ListVaultsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        vault::list_vaults(&self.state, req).await
+    }
+
     /// WARNING: Closes ALL positions and cancels ALL orders immediately.
     #[tool(
         name = "emergency_close_all",
@@ -269,12 +820,236 @@ impl HyperliquidMcp {
         &self,
         Parameters(req): Parameters<vault::EmergencyCloseAllRequest>,
     ) -> Result<CallToolResult, McpError> {
-        vault::emergency_close_all(&self.state, req).await
+        let state = self.profile_state(req.profile.as_deref())?;
+        vault::emergency_close_all(state, req).await
+    }
+
+    #[tool(
+        name = "diagnose_config",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn diagnose_config(&self) -> Result<CallToolResult, McpError> {
+        diagnostics::diagnose_config(&self.state).await
+    }
+
+    #[tool(
+        name = "get_trading_window",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_trading_window(&self) -> Result<CallToolResult, McpError> {
+        diagnostics::get_trading_window(&self.state).await
+    }
+
+    #[tool(
+        name = "get_nonce_health",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_nonce_health(
+        &self,
+        Parameters(req): Parameters<diagnostics::GetNonceHealthRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        diagnostics::get_nonce_health(state).await
+    }
+
+    #[tool(
+        name = "check_clock_skew",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn check_clock_skew(
+        &self,
+        Parameters(req): Parameters<diagnostics::CheckClockSkewRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        diagnostics::check_clock_skew(state).await
+    }
+
+    #[tool(
+        name = "get_server_health",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_server_health(
+        &self,
+        Parameters(req): Parameters<diagnostics::GetServerHealthRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.profile_state(req.profile.as_deref())?;
+        diagnostics::get_server_health(state).await
+    }
+
+    #[tool(
+        name = "get_recent_errors",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn get_recent_errors(
+        &self,
+        Parameters(req): Parameters<diagnostics::GetRecentErrorsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        diagnostics::get_recent_errors(&self.state, req).await
+    }
+
+    #[tool(
+        name = "raw_info_query",
+        annotations(read_only_hint = true, destructive_hint = false)
+    )]
+    async fn raw_info_query(
+        &self,
+        Parameters(req): Parameters<diagnostics::RawInfoQueryRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        diagnostics::raw_info_query(&self.state, req).await
+    }
+
+    #[tool(
+        name = "set_log_level",
+        annotations(read_only_hint = false, destructive_hint = false)
+    )]
+    async fn set_log_level(
+        &self,
+        Parameters(req): Parameters<diagnostics::SetLogLevelRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        diagnostics::set_log_level(req).await
     }
 }
 
-#[tool_handler]
 impl ServerHandler for HyperliquidMcp {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let request_id = call_log::request_id();
+        let tool = request.name.to_string();
+        let span = tracing::info_span!("tool_call", tool = %request.name, request_id = %request_id);
+        // Write tools get a notice appended when their profile's agent
+        // approval is about to lapse.
+        let writes = self
+            .tool_router
+            .get(&request.name)
+            .and_then(|tool| tool.annotations.as_ref())
+            .and_then(|annotations| annotations.read_only_hint)
+            == Some(false);
+        // Destructive tools end with the account's equity change.
+        let destructive = self
+            .tool_router
+            .get(&request.name)
+            .and_then(|tool| tool.annotations.as_ref())
+            .and_then(|annotations| annotations.destructive_hint)
+            == Some(true);
+        let profile = request
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get("profile"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        // get_events shows the unread events itself.
+        let digest = request.name != "get_events";
+        async {
+            let footer_state = self
+                .profile_state(profile.as_deref())
+                .ok()
+                .filter(|state| destructive && !state.hide_equity_footer && !state.dry_run);
+            let equity_before = match footer_state {
+                Some(state) => account::equity_snapshot(state).await,
+                None => None,
+            };
+            let started = std::time::Instant::now();
+            let tcc = ToolCallContext::new(self, request, context);
+            let mut result = self.tool_router.call(tcc).await;
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            let failure = match &result {
+                Ok(r) if r.is_error == Some(true) => {
+                    tracing::info!(elapsed_ms, "Tool returned an error result");
+                    let detail = r
+                        .content
+                        .iter()
+                        .find_map(|c| c.as_text())
+                        .map(|t| t.text.clone())
+                        .unwrap_or_default();
+                    Some((Failure::Refused, detail))
+                }
+                Ok(_) => {
+                    tracing::debug!(elapsed_ms, "Tool call completed");
+                    None
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e.message, "Tool call failed");
+                    Some((Failure::Error, e.message.to_string()))
+                }
+            };
+            if let Some((failure, detail)) = failure {
+                self.state.call_log.record(FailedCall {
+                    request_id: request_id.clone(),
+                    tool,
+                    profile: profile.clone(),
+                    at: chrono::Utc::now(),
+                    elapsed_ms,
+                    failure,
+                    detail,
+                });
+            }
+            // Errors name the request so it can be found in the log and in
+            // get_recent_errors.
+            if let Err(e) = &mut result {
+                e.message = format!("{} (request id: {request_id})", e.message).into();
+            }
+            // Events from the feed since the last call lead the response,
+            // since stdio has no way to push them.
+            if digest
+                && let Ok(r) = &mut result
+                && let Ok(state) = self.profile_state(profile.as_deref())
+                && let Some(digest) = state.cache.events.digest()
+            {
+                r.content.insert(0, Content::text(digest));
+            }
+            // A pending order hasn't changed the account yet, so there is
+            // nothing to report and no reason to read it again.
+            if let (Some(state), Some(before)) = (footer_state, equity_before)
+                && let Ok(r) = &mut result
+                && r.is_error != Some(true)
+                && !r.content.iter().any(|c| {
+                    c.as_text()
+                        .is_some_and(|t| t.text.contains(trading::CONFIRMATION_PENDING))
+                })
+                && let Some(footer) = account::equity_footer(state, before).await
+            {
+                r.content.push(Content::text(footer));
+            }
+            if writes
+                && let Ok(r) = &mut result
+                && let Ok(state) = self.profile_state(profile.as_deref())
+                && let Some(notice) = state.agent_expiry_notice()
+            {
+                r.content.push(Content::text(notice));
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            tools: self
+                .tool_router
+                .list_all()
+                .into_iter()
+                .map(|tool| self.with_configured_defaults(tool))
+                .collect(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.tool_router
+            .get(name)
+            .cloned()
+            .map(|tool| self.with_configured_defaults(tool))
+    }
+
     fn get_info(&self) -> ServerInfo {
         let instructions = if let Some(vault) = self.state.vault_address {
             format!(
@@ -283,8 +1058,20 @@ impl ServerHandler for HyperliquidMcp {
                  Use get_vault_details to check vault state. \
                  Use emergency_close_all to close all positions in an emergency. \
                  WARNING: Trading tools execute real trades with real vault funds. \
-                 Always confirm trade details before executing.",
+                 Always confirm trade details before executing.{}",
                 vault,
+                match &*self
+                    .state
+                    .vault_leader
+                    .read()
+                    .unwrap_or_else(|e| e.into_inner())
+                {
+                    Some(VaultLeader::Mismatch(reason)) => format!(
+                        " VAULT LEADER MISMATCH: {reason}. Vault trades are refused until the \
+                         configuration is fixed; tell the user and suggest diagnose_config."
+                    ),
+                    _ => String::new(),
+                },
             )
         } else {
             "Hyperliquid MCP Server — trade perpetual futures and spot assets on Hyperliquid. \
@@ -296,6 +1083,29 @@ impl ServerHandler for HyperliquidMcp {
                 .to_string()
         };
 
+        let instructions = match self.state.nonce.measurement() {
+            Some(m) if m.exceeds_threshold() => format!(
+                "{instructions} CLOCK SKEW: the server clock is off from the exchange by {:+} ms. \
+                 Nonces are adjusted, but tell the user to sync the system clock; \
+                 check_clock_skew re-measures it.",
+                m.skew_ms
+            ),
+            _ => instructions,
+        };
+
+        let instructions = if self.profiles.is_empty() {
+            instructions
+        } else {
+            let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            format!(
+                "{instructions} Multiple account profiles are configured ({}, {}); \
+                 pass `profile` to account and trading tools to select one.",
+                config::DEFAULT_PROFILE,
+                names.join(", "),
+            )
+        };
+
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
             capabilities: ServerCapabilities::builder().enable_tools().build(),
@@ -1,56 +1,389 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use alloy::signers::local::PrivateKeySigner;
 use anyhow::Result;
-use hypersdk::Address;
-use hypersdk::hypercore::{Chain, HttpClient, NonceHandler};
+use hypersdk::hypercore::{Chain, Cloid, HttpClient};
+use hypersdk::{Address, Decimal};
+use rust_decimal::RoundingStrategy;
 use serde_json::json;
+use tokio::sync::broadcast;
 
-use crate::cache::WsCache;
-use crate::config::{self, Config};
+use crate::breaker::CircuitBreaker;
+use crate::cache::{CachedValue, WsCache};
+use crate::call_log::CallLog;
+use crate::cloids::{CloidRegistry, Submission};
+use crate::config::{self, Config, ProfileConfig};
+#[cfg(test)]
+use crate::exchange::mock::MOCK_USER;
+use crate::exchange::{ExchangeApi, HttpExchange};
 use crate::hyperliquid;
+use crate::nonce::{NonceClock, is_nonce_error};
+use crate::paper::PaperAccount;
+use crate::persist;
+use crate::tools::account::AccountSnapshot;
+use crate::tools::format::integer_digits;
+use crate::tools::timestamps::fmt_time;
+use crate::trading_hours::TradingHours;
+
+/// Spot asset indices start here; perps are numbered from zero.
+const SPOT_ASSET_OFFSET: usize = 10_000;
+
+/// Identical orders submitted within this window are treated as client retries.
+const ORDER_DEDUPE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Which instrument family a symbol refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketKind {
+    Perp,
+    Spot,
+}
+
+impl MarketKind {
+    /// Parse a `market_kind` tool parameter.
+    pub fn parse(s: &str) -> Result<Self, rmcp::model::ErrorData> {
+        match s.trim().to_lowercase().as_str() {
+            "perp" | "perps" | "perpetual" => Ok(MarketKind::Perp),
+            "spot" => Ok(MarketKind::Spot),
+            other => Err(mcp_err(&format!(
+                "Invalid market_kind '{other}'. Use \"perp\" or \"spot\"."
+            ))),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MarketKind::Perp => "perp",
+            MarketKind::Spot => "spot",
+        }
+    }
+}
+
+/// Which account a trading action hits when a vault is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountScope {
+    Vault,
+    Personal,
+}
+
+impl AccountScope {
+    /// Parse an `account_scope` tool parameter.
+    pub fn parse(s: &str) -> Result<Self, rmcp::model::ErrorData> {
+        match s.trim().to_lowercase().as_str() {
+            "vault" => Ok(AccountScope::Vault),
+            "personal" | "self" | "main" => Ok(AccountScope::Personal),
+            other => Err(mcp_err(&format!(
+                "Invalid account_scope '{other}'. Use \"vault\" or \"personal\"."
+            ))),
+        }
+    }
+}
+
+/// Log a warning at startup when the agent approval lapses within this long.
+const AGENT_EXPIRY_WARN_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+/// Append a notice to write tool results within this long of expiry.
+const AGENT_EXPIRY_NOTICE_MS: u64 = 48 * 60 * 60 * 1000;
+
+/// The active agent's approval, from `extraAgents` at startup.
+#[derive(Debug, Clone, Default)]
+pub struct AgentExpiry {
+    /// False until the startup lookup succeeded.
+    pub checked: bool,
+    /// `validUntil` in ms; None when the approval doesn't expire.
+    pub valid_until: Option<u64>,
+    /// Saved by `renew_agent`; signs once the server restarts.
+    pub replacement: Option<Address>,
+}
+
+/// A spot pair with its API name ("PURR/USDC" or "@107") and token names.
+#[derive(Debug, Clone)]
+pub struct SpotPair {
+    pub name: String,
+    pub base: String,
+    pub quote: String,
+    pub index: usize,
+}
+
+/// A user-supplied coin resolved to a concrete market.
+#[derive(Debug, Clone)]
+pub struct ResolvedMarket {
+    /// Name the API expects (`coin` in info requests, key in `allMids`).
+    pub coin: String,
+    /// Human-readable symbol: "BTC" for perps, "BASE/QUOTE" for spot.
+    pub symbol: String,
+    pub asset: usize,
+    pub kind: MarketKind,
+    /// Size decimals (of the base token for spot), which also bound price decimals.
+    pub sz_decimals: u32,
+}
+
+impl ResolvedMarket {
+    /// e.g. "HYPE/USDC (spot, @107)" or "BTC (perp)".
+    pub fn describe(&self) -> String {
+        if self.coin == self.symbol {
+            format!("{} ({})", self.symbol, self.kind.label())
+        } else {
+            format!("{} ({}, {})", self.symbol, self.kind.label(), self.coin)
+        }
+    }
+
+    /// Round a price to what the exchange accepts: at most 5 significant
+    /// figures and `6 - szDecimals` decimals (`8 - szDecimals` for spot).
+    /// Buys round down and sells round up, so rounding never makes an order
+    /// more aggressive than requested. A buy below the smallest tick would
+    /// round to zero, which is an error rather than a zero-priced order.
+    pub fn round_price(&self, px: Decimal, is_buy: bool) -> Result<Decimal, String> {
+        if px <= Decimal::ZERO {
+            return Ok(px);
+        }
+        let dp = self.price_decimals(px);
+        let strategy = if is_buy {
+            RoundingStrategy::ToNegativeInfinity
+        } else {
+            RoundingStrategy::ToPositiveInfinity
+        };
+        let rounded = px.round_dp_with_strategy(dp, strategy).normalize();
+        if rounded.is_zero() {
+            return Err(format!(
+                "Price ${} is below the smallest price step of {} (1e-{dp}) and would round to zero.",
+                px.normalize(),
+                self.symbol
+            ));
+        }
+        Ok(rounded)
+    }
+
+    /// Decimals a price of this magnitude may carry.
+    fn price_decimals(&self, px: Decimal) -> u32 {
+        let max_decimals = match self.kind {
+            MarketKind::Perp => 6,
+            MarketKind::Spot => 8,
+        };
+        (5 - integer_digits(px))
+            .max(0)
+            .min(max_decimals - self.sz_decimals as i32)
+            .max(0) as u32
+    }
+
+    /// The smallest price step at `px`, e.g. 1 for BTC at 61,210.
+    pub fn price_tick(&self, px: Decimal) -> Decimal {
+        Decimal::new(1, self.price_decimals(px))
+    }
+}
+
+/// Guidance for a coin asked for as a perp that only trades spot.
+fn no_perp_message(spot: &ResolvedMarket) -> String {
+    let base = spot.symbol.split('/').next().unwrap_or(&spot.symbol);
+    format!(
+        "{base} has a spot market ({}) but no perp — you cannot short it or trade it \
+         with leverage.",
+        spot.symbol
+    )
+}
+
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = s.len().checked_sub(suffix.len())?;
+    let (head, tail) = (s.get(..split)?, s.get(split..)?);
+    (tail.eq_ignore_ascii_case(suffix) && !head.is_empty()).then_some(head)
+}
+
+/// Builder attached to orders, with the per-order fee in tenths of a basis point.
+#[derive(Clone, Copy)]
+pub struct ActiveBuilder {
+    pub address: Address,
+    pub fee: u64,
+}
+
+impl Default for ActiveBuilder {
+    fn default() -> Self {
+        ActiveBuilder {
+            address: config::BUILDER_ADDRESS
+                .parse()
+                .expect("BUILDER_ADDRESS is a valid address"),
+            fee: config::BUILDER_FEE,
+        }
+    }
+}
+
+/// Whether the configured main wallet leads the configured vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultLeader {
+    Verified,
+    /// Another wallet leads it, or it is not a vault. The exchange would
+    /// reject every vault action after the nonce is spent, so they are
+    /// refused locally instead.
+    Mismatch(String),
+    /// `vaultDetails` could not be fetched; vault actions are let through.
+    Unchecked(String),
+}
+
+impl VaultLeader {
+    /// Compare the leader in a `vaultDetails` response with `main`.
+    pub fn from_details(
+        vault: Address,
+        details: &serde_json::Value,
+        main: Option<Address>,
+    ) -> Self {
+        let leader = details
+            .get("leader")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse::<Address>().ok());
+        match (leader, main) {
+            (None, _) => VaultLeader::Mismatch(format!("`{vault:#x}` is not a known vault")),
+            (Some(leader), Some(main)) if leader == main => VaultLeader::Verified,
+            (Some(leader), Some(main)) => VaultLeader::Mismatch(format!(
+                "Vault `{vault:#x}` is led by `{leader:#x}`, not the configured main wallet `{main:#x}`"
+            )),
+            (Some(leader), None) => VaultLeader::Mismatch(format!(
+                "Vault `{vault:#x}` is led by `{leader:#x}` and no main wallet is configured"
+            )),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            VaultLeader::Verified => "verified".to_string(),
+            VaultLeader::Mismatch(reason) => {
+                format!("MISMATCH: {reason}; vault writes are refused")
+            }
+            VaultLeader::Unchecked(e) => format!("not verified ({e})"),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ServerState {
     pub client: Arc<HttpClient>,
     pub http: reqwest::Client,
+    /// Info and exchange requests go through here, so tests can swap in
+    /// canned responses.
+    pub api: Arc<dyn ExchangeApi>,
     pub chain: Chain,
     pub agent_signer: Option<Arc<PrivateKeySigner>>,
     pub main_signer: Option<Arc<PrivateKeySigner>>,
     pub user_address: Option<Address>,
     pub agent_address: Option<Address>,
     pub asset_map: HashMap<String, usize>,
-    pub nonce: Arc<NonceHandler>,
+    pub spot_pairs: Arc<Vec<SpotPair>>,
+    /// Size decimals by asset index.
+    pub sz_decimals: Arc<HashMap<usize, u32>>,
+    pub nonce: Arc<NonceClock>,
+    /// Pauses signed actions while the exchange keeps failing.
+    pub breaker: Arc<CircuitBreaker>,
+    /// Every cloid issued, with its intent and outcome.
+    pub cloids: Arc<CloidRegistry>,
+    /// Recent failed tool calls, shared by all profiles.
+    pub call_log: Arc<CallLog>,
+    /// Write tools other than cancels refuse outside this schedule.
+    pub trading_hours: Option<Arc<TradingHours>>,
+    pub builder: Arc<RwLock<ActiveBuilder>>,
     pub builder_fee_approved: Arc<AtomicBool>,
+    /// Master account whose builder approval was last checked.
+    pub builder_fee_account: Arc<Mutex<Option<Address>>>,
     pub nudge_shown: Arc<AtomicBool>,
+    /// Set by HYPERLIQUID_HIDE_BUILDER_NUDGE.
+    pub hide_builder_nudge: bool,
+    /// Set by HYPERLIQUID_HIDE_EQUITY_FOOTER.
+    pub hide_equity_footer: bool,
+    /// Set by HYPERLIQUID_ENABLE_WITHDRAWALS; gates moving funds off HyperCore.
+    pub withdrawals_enabled: bool,
+    /// Set by HYPERLIQUID_ENABLE_RAW_QUERIES.
+    pub raw_queries_enabled: bool,
+    /// Info request types `raw_info_query` may forward.
+    pub raw_query_types: Arc<Vec<String>>,
+    /// Set by HYPERLIQUID_AUTO_CANCEL_ORPHAN_STOPS.
+    pub auto_cancel_orphan_stops: bool,
+    /// Set by HYPERLIQUID_EMERGENCY_PHRASE; never shown in tool output.
+    pub emergency_phrase: Option<Arc<str>>,
     pub cache: Arc<WsCache>,
     pub vault_address: Option<Address>,
+    pub profile: String,
+    pub read_only: bool,
+    pub dry_run: bool,
+    /// The virtual portfolio dry-run orders fill against. Only in dry-run
+    /// mode; each profile has its own.
+    pub paper: Option<Arc<Mutex<PaperAccount>>>,
+    pub paper_slippage_bps: u32,
+    pub env_file: Option<PathBuf>,
+    /// Max builder fee to approve, in tenths of a basis point.
+    pub builder_max_fee: u64,
+    pub signature_chain_id: u64,
+    pub market_buffer_bps: u32,
+    pub default_order_type: String,
+    pub default_tif: String,
+    pub max_open_orders: usize,
+    pub max_open_orders_per_coin: Option<usize>,
+    pub max_position_notional: Option<Decimal>,
+    pub max_position_notional_by_coin: Arc<BTreeMap<String, Decimal>>,
+    pub position_warn_multiple: Decimal,
+    pub impact_guard_multiple: Decimal,
+    /// Width of the accepted limit price band around the mark, in percent.
+    pub price_band_pct: Decimal,
+    /// `get_positions` flags next funding payments of at least this, in USD.
+    pub funding_alert_usd: Decimal,
+    /// `place_order` stops waiting for the exchange after this.
+    pub order_latency_budget: Option<Duration>,
+    /// Recently submitted orders, keyed by parameter fingerprint.
+    pub recent_orders: Arc<Mutex<HashMap<u64, CachedValue<Cloid>>>>,
+    /// Set when the account is empty on the configured network but not on
+    /// the other one, which usually means HYPERLIQUID_NETWORK is wrong.
+    pub network_notice: Arc<RwLock<Option<String>>>,
+    /// Set when account queries fall back to an agent wallet with no equity
+    /// or fills, i.e. the wallet address setting is missing.
+    pub address_notice: Arc<RwLock<Option<String>>>,
+    pub agent_expiry: Arc<RwLock<AgentExpiry>>,
+    /// Leadership of `vault_address`, checked at startup and by
+    /// `diagnose_config`. None without a vault.
+    pub vault_leader: Arc<RwLock<Option<VaultLeader>>>,
+    /// When the last `schedule_cancel` from this server fires.
+    pub scheduled_cancel: Arc<Mutex<Option<chrono::DateTime<chrono::Utc>>>>,
+    /// Where `scheduled_cancel` is saved across restarts; None without a
+    /// home directory.
+    pub state_file: Option<Arc<PathBuf>>,
+    /// What was restored from the state file at startup, one line each.
+    pub restored_state: Arc<Vec<String>>,
+    /// Named account snapshots from `snapshot_account`.
+    pub snapshots: Arc<Mutex<HashMap<String, AccountSnapshot>>>,
 }
 
 impl ServerState {
     pub async fn new(config: Config) -> Result<Self> {
         let client = HttpClient::new(config.chain);
         let http = reqwest::Client::new();
-        let nonce = NonceHandler::default();
+        let nonce = NonceClock::default();
+        if let Err(e) = nonce.sync(&http, config.chain).await {
+            tracing::warn!(error = %e, "Could not compare the local clock with the exchange");
+        }
 
         let mut asset_map = HashMap::new();
+        let mut sz_decimals = HashMap::new();
 
         match client.perps().await {
             Ok(perps) => {
                 for market in &perps {
                     asset_map.insert(market.name.clone(), market.index);
+                    sz_decimals.insert(market.index, market.sz_decimals.clamp(0, 6) as u32);
                 }
                 tracing::info!(count = perps.len(), "Loaded perp markets");
             }
             Err(e) => tracing::warn!(error = %e, "Failed to load perp markets for asset map"),
         }
 
+        let mut spot_pairs = Vec::new();
         match client.spot().await {
             Ok(spots) => {
                 for market in &spots {
                     asset_map.insert(market.name.clone(), market.index);
+                    sz_decimals.insert(market.index, market.base().sz_decimals.clamp(0, 8) as u32);
+                    spot_pairs.push(SpotPair {
+                        name: market.name.clone(),
+                        base: market.base().name.clone(),
+                        quote: market.quote().name.clone(),
+                        index: market.index,
+                    });
                 }
                 tracing::info!(count = spots.len(), "Loaded spot markets");
             }
@@ -68,7 +401,7 @@ impl ServerState {
             );
         }
 
-        if let Some(vault) = config.vault_address {
+        let vault_leader = if let Some(vault) = config.vault_address {
             let main_addr = config.main_address.ok_or_else(|| {
                 anyhow::anyhow!(
                     "HYPERLIQUID_VAULT_ADDRESS is set but no main wallet configured. \
@@ -76,7 +409,7 @@ impl ServerState {
                 )
             })?;
 
-            let details = hyperliquid::raw_info_request(
+            let leadership = match hyperliquid::raw_info_request(
                 &http,
                 config.chain,
                 json!({
@@ -84,23 +417,27 @@ impl ServerState {
                     "vaultAddress": format!("{:#x}", vault),
                 }),
             )
-            .await?;
-
-            let leader = details
-                .get("leader")
-                .and_then(|v| v.as_str())
-                .and_then(|s| s.parse::<Address>().ok());
-
-            anyhow::ensure!(
-                leader == Some(main_addr),
-                "Wallet {:#x} is not the leader of vault {:#x}. \
-                 Check HYPERLIQUID_VAULT_ADDRESS and HYPERLIQUID_PRIVATE_KEY configuration.",
-                main_addr,
-                vault,
-            );
-
-            tracing::info!(vault = %vault, leader = %main_addr, "Vault leader verified");
-        }
+            .await
+            {
+                Ok(details) => VaultLeader::from_details(vault, &details, Some(main_addr)),
+                Err(e) => VaultLeader::Unchecked(e.to_string()),
+            };
+            match &leadership {
+                VaultLeader::Verified => {
+                    tracing::info!(vault = %vault, leader = %main_addr, "Vault leader verified")
+                }
+                VaultLeader::Mismatch(reason) => tracing::error!(
+                    "{reason}. Vault trades will be refused. \
+                     Check HYPERLIQUID_VAULT_ADDRESS and HYPERLIQUID_PRIVATE_KEY configuration."
+                ),
+                VaultLeader::Unchecked(e) => {
+                    tracing::warn!(vault = %vault, error = %e, "Could not verify vault leader")
+                }
+            }
+            Some(leadership)
+        } else {
+            None
+        };
 
         let cache = if config.realtime {
             crate::ws::spawn(config.chain, user_address, http.clone())
@@ -108,8 +445,22 @@ impl ServerState {
             crate::ws::cache_only()
         };
 
-        Ok(ServerState {
-            client: Arc::new(client),
+        let state_file = persist::default_state_path();
+        let restored = restore_account(
+            state_file.as_deref(),
+            config.chain,
+            config.vault_address.or(user_address),
+            config::DEFAULT_PROFILE,
+        );
+
+        let client = Arc::new(client);
+        let state = ServerState {
+            api: Arc::new(HttpExchange::new(
+                Arc::clone(&client),
+                http.clone(),
+                config.chain,
+            )),
+            client,
             http,
             chain: config.chain,
             agent_signer: config.wallet.map(Arc::new),
@@ -117,12 +468,592 @@ impl ServerState {
             user_address,
             agent_address: config.agent_address,
             asset_map,
+            spot_pairs: Arc::new(spot_pairs),
+            sz_decimals: Arc::new(sz_decimals),
             nonce: Arc::new(nonce),
+            breaker: Arc::new(CircuitBreaker::new(config.breaker_failures)),
+            cloids: Arc::default(),
+            call_log: Arc::default(),
+            trading_hours: config.trading_hours.map(Arc::new),
+            builder: Arc::default(),
             builder_fee_approved: Arc::new(AtomicBool::new(false)),
+            builder_fee_account: Arc::default(),
             nudge_shown: Arc::new(AtomicBool::new(false)),
+            hide_builder_nudge: config.hide_builder_nudge,
+            hide_equity_footer: config.hide_equity_footer,
+            withdrawals_enabled: config.withdrawals_enabled,
+            raw_queries_enabled: config.raw_queries_enabled,
+            raw_query_types: Arc::new(config.raw_query_types),
+            auto_cancel_orphan_stops: config.auto_cancel_orphan_stops,
+            emergency_phrase: config.emergency_phrase.map(Arc::from),
             cache,
             vault_address: config.vault_address,
-        })
+            profile: config::DEFAULT_PROFILE.to_string(),
+            read_only: config.read_only,
+            dry_run: config.dry_run,
+            paper: config
+                .dry_run
+                .then(|| Arc::new(Mutex::new(PaperAccount::new(config.paper_balance)))),
+            paper_slippage_bps: config.paper_slippage_bps,
+            env_file: config.env_file,
+            builder_max_fee: config.builder_max_fee,
+            signature_chain_id: config.signature_chain_id,
+            market_buffer_bps: config.market_buffer_bps,
+            default_order_type: config.default_order_type,
+            default_tif: config.default_tif,
+            max_open_orders: config.max_open_orders,
+            max_open_orders_per_coin: config.max_open_orders_per_coin,
+            max_position_notional: config.max_position_notional,
+            max_position_notional_by_coin: Arc::new(config.max_position_notional_by_coin),
+            position_warn_multiple: config.position_warn_multiple,
+            impact_guard_multiple: config.impact_guard_multiple,
+            price_band_pct: config.price_band_pct,
+            funding_alert_usd: config.funding_alert_usd,
+            order_latency_budget: config.order_latency_budget,
+            recent_orders: Arc::default(),
+            snapshots: Arc::default(),
+            scheduled_cancel: Arc::new(Mutex::new(restored.scheduled_cancel())),
+            state_file: state_file.map(Arc::new),
+            restored_state: Arc::new(restored.describe()),
+            network_notice: Arc::default(),
+            address_notice: Arc::default(),
+            agent_expiry: Arc::default(),
+            vault_leader: Arc::new(RwLock::new(vault_leader)),
+        };
+        state.spawn_network_check();
+        state.spawn_orphan_watch();
+
+        if state.cache.live && !config.sample_books.is_empty() {
+            let coins = config
+                .sample_books
+                .iter()
+                .filter_map(|name| match state.resolve_market(name, None) {
+                    Ok(market) => Some(market.coin),
+                    Err(e) => {
+                        tracing::warn!(coin = %name, error = %e.message, "Skipping order book sampling");
+                        None
+                    }
+                })
+                .collect();
+            crate::ws::spawn_book_sampler(
+                state.http.clone(),
+                state.chain,
+                Arc::clone(&state.cache),
+                coins,
+                std::time::Duration::from_secs(config.sample_interval_secs),
+            );
+        }
+
+        Ok(state)
+    }
+
+    /// A read-only state for `MOCK_USER` on mainnet, answered by `api`. Knows
+    /// the BTC and ETH perps and the PURR/USDC and HYPE/USDC (@107) spot
+    /// pairs, with default limits and no live feed.
+    #[cfg(test)]
+    pub fn mock(api: Arc<dyn ExchangeApi>) -> Self {
+        let spot_pairs = vec![
+            SpotPair {
+                name: "PURR/USDC".into(),
+                base: "PURR".into(),
+                quote: "USDC".into(),
+                index: SPOT_ASSET_OFFSET,
+            },
+            SpotPair {
+                name: "@107".into(),
+                base: "HYPE".into(),
+                quote: "USDC".into(),
+                index: SPOT_ASSET_OFFSET + 107,
+            },
+        ];
+        let mut asset_map = HashMap::from([("BTC".to_string(), 0), ("ETH".to_string(), 1)]);
+        asset_map.extend(spot_pairs.iter().map(|p| (p.name.clone(), p.index)));
+        let sz_decimals = HashMap::from([
+            (0, 5),
+            (1, 4),
+            (SPOT_ASSET_OFFSET, 0),
+            (SPOT_ASSET_OFFSET + 107, 2),
+        ]);
+
+        ServerState {
+            client: Arc::new(HttpClient::new(Chain::Mainnet)),
+            http: reqwest::Client::new(),
+            api,
+            chain: Chain::Mainnet,
+            agent_signer: None,
+            main_signer: None,
+            user_address: Some(MOCK_USER),
+            agent_address: None,
+            asset_map,
+            spot_pairs: Arc::new(spot_pairs),
+            sz_decimals: Arc::new(sz_decimals),
+            nonce: Arc::default(),
+            breaker: Arc::new(CircuitBreaker::new(config::DEFAULT_BREAKER_FAILURES)),
+            cloids: Arc::default(),
+            call_log: Arc::default(),
+            trading_hours: None,
+            builder: Arc::default(),
+            builder_fee_approved: Arc::new(AtomicBool::new(false)),
+            builder_fee_account: Arc::default(),
+            nudge_shown: Arc::new(AtomicBool::new(false)),
+            hide_builder_nudge: true,
+            hide_equity_footer: false,
+            withdrawals_enabled: false,
+            raw_queries_enabled: false,
+            raw_query_types: Arc::default(),
+            auto_cancel_orphan_stops: false,
+            emergency_phrase: None,
+            cache: crate::ws::cache_only(),
+            vault_address: None,
+            profile: config::DEFAULT_PROFILE.to_string(),
+            read_only: true,
+            dry_run: false,
+            paper: None,
+            paper_slippage_bps: config::DEFAULT_PAPER_SLIPPAGE_BPS,
+            env_file: None,
+            builder_max_fee: 0,
+            signature_chain_id: 0,
+            market_buffer_bps: config::DEFAULT_MARKET_BUFFER_BPS,
+            default_order_type: config::DEFAULT_ORDER_TYPE.to_string(),
+            default_tif: config::DEFAULT_TIF.to_string(),
+            max_open_orders: config::DEFAULT_MAX_OPEN_ORDERS,
+            max_open_orders_per_coin: None,
+            max_position_notional: None,
+            max_position_notional_by_coin: Arc::default(),
+            position_warn_multiple: config::DEFAULT_POSITION_WARN_MULTIPLE,
+            impact_guard_multiple: config::DEFAULT_IMPACT_GUARD_MULTIPLE,
+            price_band_pct: config::DEFAULT_PRICE_BAND_PCT,
+            funding_alert_usd: config::DEFAULT_FUNDING_ALERT_USD,
+            order_latency_budget: None,
+            recent_orders: Arc::default(),
+            snapshots: Arc::default(),
+            scheduled_cancel: Arc::default(),
+            state_file: None,
+            restored_state: Arc::default(),
+            network_notice: Arc::default(),
+            address_notice: Arc::default(),
+            agent_expiry: Arc::default(),
+            vault_leader: Arc::default(),
+        }
+    }
+
+    /// Build the state for an additional account profile. Market metadata and
+    /// HTTP clients are shared; signers, nonces and user caches are not.
+    pub fn with_profile(&self, profile: ProfileConfig, realtime: bool) -> Self {
+        let user_address = profile.main_address.or(profile.agent_address);
+
+        if profile.main_address.is_none() && profile.agent_address.is_some() {
+            tracing::warn!(
+                profile = %profile.name,
+                "No wallet address configured for profile. Account queries will use the agent wallet address."
+            );
+        }
+
+        let cache = if realtime {
+            crate::ws::spawn(self.chain, user_address, self.http.clone())
+        } else {
+            crate::ws::cache_only()
+        };
+        let restored = restore_account(
+            self.state_file.as_deref().map(PathBuf::as_path),
+            self.chain,
+            user_address,
+            &profile.name,
+        );
+
+        let state = ServerState {
+            client: Arc::clone(&self.client),
+            http: self.http.clone(),
+            api: Arc::clone(&self.api),
+            chain: self.chain,
+            agent_signer: profile.wallet.map(Arc::new),
+            main_signer: None,
+            user_address,
+            agent_address: profile.agent_address,
+            asset_map: self.asset_map.clone(),
+            spot_pairs: Arc::clone(&self.spot_pairs),
+            sz_decimals: Arc::clone(&self.sz_decimals),
+            nonce: Arc::new(self.nonce.carry_over()),
+            breaker: Arc::clone(&self.breaker),
+            cloids: Arc::clone(&self.cloids),
+            call_log: Arc::clone(&self.call_log),
+            trading_hours: self.trading_hours.clone(),
+            builder: Arc::default(),
+            builder_fee_approved: Arc::new(AtomicBool::new(false)),
+            builder_fee_account: Arc::default(),
+            nudge_shown: Arc::new(AtomicBool::new(false)),
+            hide_builder_nudge: self.hide_builder_nudge,
+            hide_equity_footer: self.hide_equity_footer,
+            withdrawals_enabled: self.withdrawals_enabled,
+            raw_queries_enabled: self.raw_queries_enabled,
+            raw_query_types: Arc::clone(&self.raw_query_types),
+            auto_cancel_orphan_stops: self.auto_cancel_orphan_stops,
+            emergency_phrase: self.emergency_phrase.clone(),
+            cache,
+            vault_address: None,
+            profile: profile.name,
+            read_only: self.read_only,
+            dry_run: self.dry_run,
+            paper: self.paper.as_ref().map(|paper| {
+                let starting = paper
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .starting_balance;
+                Arc::new(Mutex::new(PaperAccount::new(starting)))
+            }),
+            paper_slippage_bps: self.paper_slippage_bps,
+            env_file: self.env_file.clone(),
+            builder_max_fee: self.builder_max_fee,
+            signature_chain_id: self.signature_chain_id,
+            market_buffer_bps: self.market_buffer_bps,
+            default_order_type: self.default_order_type.clone(),
+            default_tif: self.default_tif.clone(),
+            max_open_orders: self.max_open_orders,
+            max_open_orders_per_coin: self.max_open_orders_per_coin,
+            max_position_notional: self.max_position_notional,
+            max_position_notional_by_coin: Arc::clone(&self.max_position_notional_by_coin),
+            position_warn_multiple: self.position_warn_multiple,
+            impact_guard_multiple: self.impact_guard_multiple,
+            price_band_pct: self.price_band_pct,
+            funding_alert_usd: self.funding_alert_usd,
+            order_latency_budget: self.order_latency_budget,
+            recent_orders: Arc::default(),
+            snapshots: Arc::default(),
+            scheduled_cancel: Arc::new(Mutex::new(restored.scheduled_cancel())),
+            state_file: self.state_file.clone(),
+            restored_state: Arc::new(restored.describe()),
+            network_notice: Arc::default(),
+            address_notice: Arc::default(),
+            agent_expiry: Arc::default(),
+            vault_leader: Arc::default(),
+        };
+        state.spawn_network_check();
+        state.spawn_orphan_watch();
+        state
+    }
+
+    /// Compare account value on both networks. Returns a notice when the
+    /// configured network shows zero equity but the other one does not.
+    pub async fn check_network(&self) -> Result<Option<String>> {
+        let Some(user) = self.vault_address.or(self.user_address) else {
+            return Ok(None);
+        };
+        let (configured, other) = match self.chain {
+            Chain::Mainnet => ("mainnet", Chain::Testnet),
+            Chain::Testnet => ("testnet", Chain::Mainnet),
+        };
+        let (here, there) = tokio::try_join!(
+            hyperliquid::account_value(&self.http, self.chain, user),
+            hyperliquid::account_value(&self.http, other, user),
+        )?;
+        if !here.is_zero() || there.is_zero() {
+            return Ok(None);
+        }
+        let other_name = if other == Chain::Mainnet {
+            "mainnet"
+        } else {
+            "testnet"
+        };
+        Ok(Some(format!(
+            "`{user:#x}` has no equity on {configured} but ${there:.2} on {other_name}. \
+             HYPERLIQUID_NETWORK may be set to the wrong network; set it to `{other_name}` \
+             if that is the account you meant to use."
+        )))
+    }
+
+    /// Without a wallet address, account queries use the agent wallet, which
+    /// holds nothing: trades land on its master account. Returns a notice
+    /// when the agent has neither equity nor fills.
+    pub async fn check_agent_address(&self) -> Result<Option<String>> {
+        let Some(agent) = self.agent_address else {
+            return Ok(None);
+        };
+        if self.user_address != Some(agent) || self.vault_address.is_some() {
+            return Ok(None);
+        }
+        let (equity, fills) = tokio::try_join!(
+            hyperliquid::account_value(&self.http, self.chain, agent),
+            hyperliquid::raw_info_request(
+                &self.http,
+                self.chain,
+                json!({ "type": "userFills", "user": format!("{agent:#x}") }),
+            ),
+        )?;
+        let has_fills = fills.as_array().is_some_and(|f| !f.is_empty());
+        if !equity.is_zero() || has_fills {
+            return Ok(None);
+        }
+
+        let var = if self.profile == config::DEFAULT_PROFILE {
+            "HYPERLIQUID_WALLET_ADDRESS".to_string()
+        } else {
+            format!(
+                "{}{}_WALLET_ADDRESS",
+                config::PROFILE_PREFIX,
+                self.profile.to_uppercase()
+            )
+        };
+        let master = self
+            .resolve_builder_fee_account()
+            .await
+            .filter(|master| *master != agent);
+        let fix = match master {
+            Some(master) => format!(
+                "Trades signed by the agent go to its master account `{master:#x}`; set `{var}={master:#x}` and restart."
+            ),
+            None => format!("Set `{var}` to your funded account's address and restart."),
+        };
+        Ok(Some(format!(
+            "`{var}` is not set, so account queries use the agent wallet `{agent:#x}`, \
+             which has no equity or fills. Positions, balances and trade history below are for \
+             the agent, not the funded account. {fix}"
+        )))
+    }
+
+    /// `validUntil` of the agent's approval, from `extraAgents` on its master
+    /// account. None without a separate agent or when it doesn't expire.
+    pub async fn fetch_agent_valid_until(&self) -> Result<Option<u64>> {
+        let Some(agent) = self.agent_address else {
+            return Ok(None);
+        };
+        let Some(master) = self
+            .resolve_builder_fee_account()
+            .await
+            .filter(|master| *master != agent)
+        else {
+            return Ok(None);
+        };
+        let agents = hyperliquid::raw_info_request(
+            &self.http,
+            self.chain,
+            json!({ "type": "extraAgents", "user": format!("{master:#x}") }),
+        )
+        .await?;
+        Ok(agents
+            .as_array()
+            .and_then(|list| {
+                list.iter().find(|a| {
+                    a.get("address")
+                        .and_then(|v| v.as_str())
+                        .and_then(|v| v.parse::<Address>().ok())
+                        == Some(agent)
+                })
+            })
+            .and_then(|a| a.get("validUntil"))
+            .and_then(|v| v.as_u64()))
+    }
+
+    /// Env var holding this profile's agent key.
+    pub fn agent_key_var(&self) -> String {
+        if self.profile == config::DEFAULT_PROFILE {
+            config::AGENT_KEY_VAR.to_string()
+        } else {
+            format!(
+                "{}{}{}",
+                config::PROFILE_PREFIX,
+                self.profile.to_uppercase(),
+                config::PROFILE_AGENT_KEY_SUFFIX
+            )
+        }
+    }
+
+    /// "expires 2026-10-20 08:00 UTC (in 3d 4h)", or None without an agent
+    /// or before the startup lookup finished.
+    pub fn agent_expiry_summary(&self) -> Option<String> {
+        self.agent_address?;
+        let expiry = self
+            .agent_expiry
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if !expiry.checked {
+            return None;
+        }
+        let mut summary = match expiry.valid_until {
+            None => "no expiry".to_string(),
+            Some(until) => {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                if until <= now {
+                    format!("expired {}", fmt_time(until))
+                } else {
+                    let mins = (until - now) / 60_000;
+                    let left = if mins < 24 * 60 {
+                        format!("{}h {}m", mins / 60, mins % 60)
+                    } else {
+                        format!("{}d {}h", mins / (24 * 60), mins % (24 * 60) / 60)
+                    };
+                    format!("expires {} (in {left})", fmt_time(until))
+                }
+            }
+        };
+        if let Some(replacement) = expiry.replacement {
+            summary.push_str(&format!(
+                "; replacement `{replacement:#x}` saved, active after restart"
+            ));
+        }
+        Some(summary)
+    }
+
+    /// Markdown notice for write tool results once the agent approval is
+    /// within 48 hours of lapsing, after which the exchange rejects
+    /// everything the agent signs.
+    pub fn agent_expiry_notice(&self) -> Option<String> {
+        let agent = self.agent_address?;
+        let expiry = self
+            .agent_expiry
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let until = expiry.valid_until?;
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if until.saturating_sub(now) > AGENT_EXPIRY_NOTICE_MS {
+            return None;
+        }
+        let action = match expiry.replacement {
+            Some(replacement) => format!(
+                "A replacement agent `{replacement:#x}` is saved; restart the server to sign with it."
+            ),
+            None => "Run `renew_agent` to approve and save a replacement.".to_string(),
+        };
+        Some(format!(
+            "> **Agent wallet:** `{agent:#x}` {}. Once it lapses, the exchange rejects every \
+             action it signs. {action}",
+            self.agent_expiry_summary()?
+        ))
+    }
+
+    fn spawn_network_check(&self) {
+        if self.user_address.is_none() {
+            return;
+        }
+        let state = self.clone();
+        tokio::spawn(async move {
+            match state.check_agent_address().await {
+                Ok(Some(notice)) => {
+                    tracing::warn!(profile = %state.profile, "{}", notice.replace('`', ""));
+                    *state
+                        .address_notice
+                        .write()
+                        .unwrap_or_else(|e| e.into_inner()) = Some(notice);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!(error = %e, "Agent address check failed"),
+            }
+            match state.fetch_agent_valid_until().await {
+                Ok(valid_until) => {
+                    let now = chrono::Utc::now().timestamp_millis() as u64;
+                    if let Some(until) = valid_until
+                        && until.saturating_sub(now) < AGENT_EXPIRY_WARN_MS
+                    {
+                        tracing::warn!(
+                            profile = %state.profile,
+                            expires = %fmt_time(until),
+                            "Agent wallet approval expires soon; run renew_agent to replace it"
+                        );
+                    }
+                    let mut expiry = state
+                        .agent_expiry
+                        .write()
+                        .unwrap_or_else(|e| e.into_inner());
+                    expiry.checked = true;
+                    expiry.valid_until = valid_until;
+                }
+                Err(e) => tracing::debug!(error = %e, "Agent expiry check failed"),
+            }
+            match state.check_network().await {
+                Ok(Some(notice)) => {
+                    tracing::warn!(profile = %state.profile, "{}", notice.replace('`', ""));
+                    *state
+                        .network_notice
+                        .write()
+                        .unwrap_or_else(|e| e.into_inner()) = Some(notice);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!(error = %e, "Network mismatch check failed"),
+            }
+        });
+    }
+
+    /// Cancel trigger orders left behind when a fill from the WebSocket feed
+    /// closes a position. Only runs with HYPERLIQUID_AUTO_CANCEL_ORPHAN_STOPS.
+    fn spawn_orphan_watch(&self) {
+        if !self.auto_cancel_orphan_stops || !self.cache.live || self.require_signer().is_err() {
+            return;
+        }
+        let state = self.clone();
+        let mut closes = self.cache.position_closes.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let coin = match closes.recv().await {
+                    Ok(coin) => coin,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                match crate::tools::trading::cancel_orphaned_triggers(&state, &coin).await {
+                    Ok(oids) if !oids.is_empty() => tracing::info!(
+                        profile = %state.profile,
+                        coin,
+                        ?oids,
+                        "Cancelled orphaned trigger orders after position close"
+                    ),
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!(coin, error = %e.message, "Orphaned trigger cancel failed")
+                    }
+                }
+            }
+        });
+    }
+
+    /// Save this account's background constructs to the state file, so a
+    /// restart picks them up again. Failures are logged, not returned: the
+    /// action itself already went through.
+    pub fn persist_state(&self) {
+        let (Some(path), Ok(address)) = (&self.state_file, self.query_address()) else {
+            return;
+        };
+        let scheduled = *self
+            .scheduled_cancel
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let key = persist::account_key(self.chain, address);
+        if let Err(e) = persist::update_account(path, &key, |account| {
+            account.scheduled_cancel_ms = scheduled.map(|when| when.timestamp_millis());
+        }) {
+            tracing::warn!(path = %path.display(), error = %e, "Could not save state file");
+        }
+    }
+
+    /// Notional cap for a perp position on `coin`: its
+    /// `HYPERLIQUID_MAX_POSITION_NOTIONAL_<COIN>` override, else the global one.
+    pub fn position_notional_limit(&self, coin: &str) -> Option<Decimal> {
+        self.max_position_notional_by_coin
+            .get(&config::notional_limit_key(coin))
+            .copied()
+            .or(self.max_position_notional)
+    }
+
+    /// Markdown notices to put ahead of account output, if the startup
+    /// checks found the wrong network or a missing wallet address.
+    pub fn account_notices(&self) -> String {
+        let mut out = String::new();
+        if let Some(n) = self
+            .address_notice
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            out.push_str(&format!("> **Wallet address missing:** {n}\n\n"));
+        }
+        if let Some(n) = self
+            .network_notice
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            out.push_str(&format!("> **Network check:** {n}\n\n"));
+        }
+        out
     }
 
     pub fn require_address(&self) -> Result<Address, rmcp::model::ErrorData> {
@@ -137,7 +1068,23 @@ impl ServerState {
     /// - Vault mode → main wallet
     /// - Normal mode → agent wallet, falling back to main wallet
     pub fn require_signer(&self) -> Result<&Arc<PrivateKeySigner>, rmcp::model::ErrorData> {
-        if self.is_vault_mode() {
+        let scope = if self.is_vault_mode() {
+            AccountScope::Vault
+        } else {
+            AccountScope::Personal
+        };
+        self.scoped_signer(scope)
+    }
+
+    /// Signer for an action on `scope`: vault actions need the leader's main
+    /// wallet, personal ones use the agent wallet, falling back to main.
+    pub fn scoped_signer(
+        &self,
+        scope: AccountScope,
+    ) -> Result<&Arc<PrivateKeySigner>, rmcp::model::ErrorData> {
+        self.ensure_writable()?;
+        if scope == AccountScope::Vault {
+            self.check_vault_leader()?;
             self.require_main_signer()
         } else {
             self.agent_signer
@@ -153,27 +1100,205 @@ impl ServerState {
     }
 
     pub fn require_main_signer(&self) -> Result<&Arc<PrivateKeySigner>, rmcp::model::ErrorData> {
+        self.ensure_writable()?;
         self.main_signer.as_ref().ok_or_else(|| {
-            mcp_err(
-                "Main wallet required for this operation. \
-                 Set HYPERLIQUID_PRIVATE_KEY in your ~/.config/hyperliquid-mcp/.env file.",
-            )
+            if self.profile == config::DEFAULT_PROFILE {
+                mcp_err(
+                    "Main wallet required for this operation. \
+                     Set HYPERLIQUID_PRIVATE_KEY in your ~/.config/hyperliquid-mcp/.env file.",
+                )
+            } else {
+                mcp_err(&format!(
+                    "Main wallet required for this operation, and profile `{}` only has an \
+                     agent key. Run it from the account's own wallet instead.",
+                    self.profile
+                ))
+            }
         })
     }
 
-    pub fn resolve_asset(&self, coin: &str) -> Result<usize, rmcp::model::ErrorData> {
-        self.asset_map.get(coin).copied().ok_or_else(|| {
-            mcp_err(&format!(
-                "Unknown market '{}'. Use get_markets to see available markets.",
-                coin
-            ))
+    /// Refuse vault actions when the signer doesn't lead the vault.
+    fn check_vault_leader(&self) -> Result<(), rmcp::model::ErrorData> {
+        match &*self.vault_leader.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(VaultLeader::Mismatch(reason)) => Err(mcp_err(&format!(
+                "Vault actions are disabled. {reason}, so the exchange would reject them. \
+                 Fix HYPERLIQUID_VAULT_ADDRESS or HYPERLIQUID_PRIVATE_KEY and restart, \
+                 or pass account_scope: \"personal\" to act on your own account."
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn ensure_writable(&self) -> Result<(), rmcp::model::ErrorData> {
+        if self.read_only {
+            return Err(mcp_err(
+                "Server is running in read-only mode (--read-only). \
+                 Restart without --read-only to sign actions.",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse actions that add risk or move funds while the circuit breaker
+    /// is open. Cancels, closes and `emergency_close_all` don't call this, so
+    /// they stay available while the exchange is flaky.
+    pub fn check_breaker(&self) -> Result<(), rmcp::model::ErrorData> {
+        self.breaker.check().map_err(|msg| mcp_err(&msg))
+    }
+
+    /// Refuse write actions outside `HYPERLIQUID_TRADING_HOURS`. Cancels and
+    /// `emergency_close_all` don't call this, so they work at any time.
+    pub fn check_trading_window(&self) -> Result<(), rmcp::model::ErrorData> {
+        let Some(hours) = &self.trading_hours else {
+            return Ok(());
+        };
+        let now = chrono::Utc::now();
+        if hours.is_open(now) {
+            return Ok(());
+        }
+        let reopens = hours
+            .next_change(now)
+            .map(|t| format!(" Trading reopens {}.", hours.format_time(t)))
+            .unwrap_or_default();
+        Err(mcp_err(&format!(
+            "Outside the trading window ({}, set by HYPERLIQUID_TRADING_HOURS).{reopens} \
+             Cancels and emergency_close_all remain available.",
+            hours.describe()
+        )))
+    }
+
+    /// Resolve a user-supplied coin to a market. Every tool that takes a coin
+    /// goes through this. Perp names (also as "BTC-PERP" or "BTC-USD"), spot
+    /// pairs ("PURR/USDC" or "PURR-USDC"), spot indices ("@107") and bare spot
+    /// tokens ("HYPE", which prefers the USDC pair) are accepted,
+    /// case-insensitively. Without `kind`, perps take precedence over spot
+    /// tokens of the same name.
+    pub fn resolve_market(
+        &self,
+        coin: &str,
+        kind: Option<MarketKind>,
+    ) -> Result<ResolvedMarket, rmcp::model::ErrorData> {
+        let input = coin.trim();
+        // "BTC-PERP" and "BTC-USD" are common ways of naming the perpetual.
+        let (input, kind) = match ["-PERP", "-USD"]
+            .iter()
+            .find_map(|suffix| strip_suffix_ignore_case(input, suffix))
+        {
+            Some(base) => (base, kind.or(Some(MarketKind::Perp))),
+            None => (input, kind),
+        };
+
+        let perp = || {
+            self.asset_map
+                .iter()
+                .filter(|(_, index)| **index < SPOT_ASSET_OFFSET)
+                .find(|(name, _)| name.eq_ignore_ascii_case(input))
+                .map(|(name, index)| ResolvedMarket {
+                    coin: name.clone(),
+                    symbol: name.clone(),
+                    asset: *index,
+                    kind: MarketKind::Perp,
+                    sz_decimals: self.sz_decimals.get(index).copied().unwrap_or(0),
+                })
+        };
+
+        let spot = || {
+            let pair = if let Some((base, quote)) = input.split_once(['/', '-']) {
+                self.spot_pairs.iter().find(|p| {
+                    p.base.eq_ignore_ascii_case(base.trim())
+                        && p.quote.eq_ignore_ascii_case(quote.trim())
+                })
+            } else if input.starts_with('@') {
+                self.spot_pairs.iter().find(|p| p.name == input)
+            } else {
+                let matches: Vec<_> = self
+                    .spot_pairs
+                    .iter()
+                    .filter(|p| p.base.eq_ignore_ascii_case(input))
+                    .collect();
+                matches
+                    .iter()
+                    .find(|p| p.quote == "USDC")
+                    .or_else(|| matches.first())
+                    .copied()
+            };
+            pair.map(|p| ResolvedMarket {
+                coin: p.name.clone(),
+                symbol: format!("{}/{}", p.base, p.quote),
+                asset: p.index,
+                kind: MarketKind::Spot,
+                sz_decimals: self.sz_decimals.get(&p.index).copied().unwrap_or(0),
+            })
+        };
+
+        let resolved = match kind {
+            Some(MarketKind::Perp) => perp(),
+            Some(MarketKind::Spot) => spot(),
+            None => perp().or_else(spot),
+        };
+        if let Some(market) = resolved {
+            return Ok(market);
+        }
+
+        // The coin may exist in the other namespace; say which instead of
+        // calling it unknown.
+        let guidance = match kind {
+            Some(MarketKind::Perp) => spot().map(|spot| {
+                format!(
+                    "{} For a spot trade, pass market_kind \"spot\".",
+                    no_perp_message(&spot)
+                )
+            }),
+            Some(MarketKind::Spot) => perp().map(|perp| {
+                format!(
+                    "{} is perp-only; there is no spot market to buy or sell. \
+                     Trade the perp instead (market_kind \"perp\").",
+                    perp.symbol
+                )
+            }),
+            None => None,
+        };
+        Err(mcp_err(&guidance.unwrap_or_else(|| {
+            let kind_str = kind.map(|k| format!("{} ", k.label())).unwrap_or_default();
+            format!("Unknown {kind_str}market '{input}'. Use get_markets to see available markets.")
+        })))
+    }
+
+    /// Refusal for side "short" on a spot market, where selling only
+    /// disposes of tokens held. Shorting takes a perp, if the coin has one.
+    pub fn spot_short_refusal(&self, market: &ResolvedMarket, side: &str) -> Option<String> {
+        if market.kind != MarketKind::Spot || !side.trim().eq_ignore_ascii_case("short") {
+            return None;
+        }
+        let base = market.symbol.split('/').next().unwrap_or(&market.symbol);
+        Some(match self.resolve_market(base, Some(MarketKind::Perp)) {
+            Ok(perp) => format!(
+                "{} is a spot market, where \"short\" could only sell tokens you hold. \
+                 To short {base} with leverage, pass market_kind \"perp\" for {}; \
+                 for a spot sell, use side \"sell\".",
+                market.describe(),
+                perp.describe()
+            ),
+            Err(_) => format!(
+                "{} Did you mean a spot sell (side \"sell\")?",
+                no_perp_message(market)
+            ),
         })
     }
 
+    pub fn active_builder(&self) -> ActiveBuilder {
+        *self.builder.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_active_builder(&self, builder: ActiveBuilder) {
+        *self.builder.write().unwrap_or_else(|e| e.into_inner()) = builder;
+    }
+
     pub fn builder_info(&self) -> hyperliquid::BuilderInfo {
+        let builder = self.active_builder();
         hyperliquid::BuilderInfo {
-            b: config::BUILDER_ADDRESS.to_lowercase(),
-            f: config::BUILDER_FEE,
+            b: format!("{:#x}", builder.address),
+            f: builder.fee,
         }
     }
 
@@ -181,6 +1306,113 @@ impl ServerState {
         self.nonce.next()
     }
 
+    /// Record a rejected exchange action for `get_nonce_health`. A nonce
+    /// rejection triggers a clock re-check in the background.
+    pub fn record_action_error(&self, nonce: u64, message: &str) {
+        self.nonce.record_error(nonce, message);
+        if is_nonce_error(message) {
+            tracing::warn!(nonce, error = message, "Exchange rejected action nonce");
+            let clock = Arc::clone(&self.nonce);
+            let http = self.http.clone();
+            let chain = self.chain;
+            tokio::spawn(async move {
+                if let Err(e) = clock.sync(&http, chain).await {
+                    tracing::warn!(error = %e, "Clock re-check failed");
+                }
+            });
+        }
+    }
+
+    /// Map a failed exchange call to a tool error, recording it. Failures
+    /// other than nonce rejections count towards the circuit breaker.
+    pub fn action_failed(
+        &self,
+        nonce: u64,
+        context: &str,
+        error: impl std::fmt::Display,
+    ) -> rmcp::model::ErrorData {
+        let message = error.to_string();
+        self.record_action_error(nonce, &message);
+        if !is_nonce_error(&message) && self.breaker.record_failure(&message) {
+            self.spawn_breaker_probe();
+        }
+        mcp_err(&format!("{context}: {message}"))
+    }
+
+    /// Probe the exchange every `PROBE_INTERVAL` while the breaker is open,
+    /// closing it on the first answer.
+    fn spawn_breaker_probe(&self) {
+        let breaker = Arc::clone(&self.breaker);
+        let http = self.http.clone();
+        let chain = self.chain;
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(crate::breaker::PROBE_INTERVAL).await;
+                if !breaker.begin_probe() {
+                    break;
+                }
+                match hyperliquid::server_time(&http, chain).await {
+                    Ok(_) => {
+                        breaker.record_success();
+                        break;
+                    }
+                    Err(e) => breaker.probe_failed(&e.to_string()),
+                }
+            }
+        });
+    }
+
+    /// Record a raw `/exchange` response if the whole action was rejected.
+    /// Any response means the exchange is reachable, which resets the
+    /// circuit breaker.
+    pub fn record_response(&self, nonce: u64, response: &serde_json::Value) {
+        self.breaker.record_success();
+        if response.get("status").and_then(|s| s.as_str()) == Some("err") {
+            let message = response
+                .get("response")
+                .and_then(|r| r.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| response.to_string());
+            self.record_action_error(nonce, &message);
+        }
+    }
+
+    /// Record `cloid` as the order for `fingerprint`. If an identical order was
+    /// already submitted within the dedupe window, is still waiting for the
+    /// exchange to answer, or got no answer at all, returns its cloid and age
+    /// instead.
+    pub fn claim_order(&self, fingerprint: u64, cloid: Cloid) -> Option<(Cloid, Duration)> {
+        let mut recent = self.recent_orders.lock().unwrap_or_else(|e| e.into_inner());
+        recent.retain(|_, entry| {
+            entry.is_fresh(ORDER_DEDUPE_WINDOW)
+                || self.cloids.lookup(&entry.value).is_some_and(|r| {
+                    matches!(r.outcome, Submission::Pending | Submission::Unknown(_))
+                })
+        });
+        if let Some(entry) = recent.get(&fingerprint) {
+            return Some((entry.value, entry.inserted_at.elapsed()));
+        }
+        recent.insert(fingerprint, CachedValue::new(cloid));
+        None
+    }
+
+    /// Record what a status lookup found for a cloid whose submission got no
+    /// answer, and drop the duplicate claim it held. Other outcomes are left
+    /// as the exchange reported them. Returns whether anything changed.
+    pub fn resolve_unanswered(&self, cloid: Cloid, outcome: Submission) -> bool {
+        let unanswered = self
+            .cloids
+            .lookup(&cloid)
+            .is_some_and(|r| matches!(r.outcome, Submission::Unknown(_)));
+        if !unanswered {
+            return false;
+        }
+        self.cloids.record(cloid, outcome);
+        let mut recent = self.recent_orders.lock().unwrap_or_else(|e| e.into_inner());
+        recent.retain(|_, entry| entry.value != cloid);
+        true
+    }
+
     pub fn vault_addr(&self) -> Option<Address> {
         self.vault_address
     }
@@ -198,28 +1430,128 @@ impl ServerState {
         self.vault_address.is_some()
     }
 
+    /// Resolve an `account_scope` parameter. Defaults to the vault in vault
+    /// mode and the personal account otherwise.
+    pub fn account_scope(
+        &self,
+        requested: Option<&str>,
+    ) -> Result<AccountScope, rmcp::model::ErrorData> {
+        let scope = match requested {
+            Some(s) => AccountScope::parse(s)?,
+            None if self.is_vault_mode() => AccountScope::Vault,
+            None => AccountScope::Personal,
+        };
+        if scope == AccountScope::Vault && !self.is_vault_mode() {
+            return Err(mcp_err(
+                "account_scope \"vault\" needs a configured vault. Set HYPERLIQUID_VAULT_ADDRESS, \
+                 or omit account_scope to act on your personal account.",
+            ));
+        }
+        Ok(scope)
+    }
+
+    /// The `vault_address` to sign actions on `scope` with.
+    pub fn scoped_vault(&self, scope: AccountScope) -> Option<Address> {
+        match scope {
+            AccountScope::Vault => self.vault_address,
+            AccountScope::Personal => None,
+        }
+    }
+
+    /// The account whose positions and orders an action on `scope` affects.
+    pub fn scoped_address(&self, scope: AccountScope) -> Result<Address, rmcp::model::ErrorData> {
+        match scope {
+            AccountScope::Vault => self.query_address(),
+            AccountScope::Personal => self.require_address(),
+        }
+    }
+
+    /// "vault `0x…`" or "personal account `0x…`", for naming the account an
+    /// action hit.
+    pub fn describe_scope(&self, scope: AccountScope) -> String {
+        let address = self
+            .scoped_address(scope)
+            .map(|a| format!(" `{a:#x}`"))
+            .unwrap_or_default();
+        match scope {
+            AccountScope::Vault => format!("vault{address}"),
+            AccountScope::Personal => format!("personal account{address}"),
+        }
+    }
+
+    /// The master account builder approvals are stored against. Without a
+    /// configured main address the agent is mapped to its master via
+    /// `userRole`. The result is cached.
+    pub async fn resolve_builder_fee_account(&self) -> Option<Address> {
+        if let Some(addr) = *self
+            .builder_fee_account
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+        {
+            return Some(addr);
+        }
+
+        let mut address = self.user_address?;
+        if Some(address) == self.agent_address {
+            let role = hyperliquid::raw_info_request(
+                &self.http,
+                self.chain,
+                json!({ "type": "userRole", "user": format!("{:#x}", address) }),
+            )
+            .await;
+            match role {
+                Ok(val) if val.get("role").and_then(|r| r.as_str()) == Some("agent") => {
+                    match val
+                        .get("data")
+                        .and_then(|d| d.get("user"))
+                        .and_then(|u| u.as_str())
+                        .and_then(|u| u.parse::<Address>().ok())
+                    {
+                        Some(master) => {
+                            tracing::info!(agent = %address, master = %master, "Resolved master account for agent wallet");
+                            address = master;
+                        }
+                        None => tracing::warn!(
+                            "userRole response for agent is missing the master address"
+                        ),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to resolve master account for agent wallet");
+                    return Some(address);
+                }
+            }
+        }
+
+        *self
+            .builder_fee_account
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(address);
+        Some(address)
+    }
+
     pub async fn check_and_cache_builder_approval(&self) -> bool {
-        let address = match self.user_address {
+        let address = match self.resolve_builder_fee_account().await {
             Some(addr) => addr,
             None => return false,
         };
 
+        let builder = self.active_builder();
         let result = hyperliquid::raw_info_request(
             &self.http,
             self.chain,
             json!({
                 "type": "maxBuilderFee",
                 "user": format!("{:#x}", address),
-                "builder": config::BUILDER_ADDRESS,
+                "builder": format!("{:#x}", builder.address),
             }),
         )
         .await;
 
+        // Orders carrying a fee above the approved max are rejected.
         let approved = match result {
-            Ok(val) => {
-                let max_fee = val.as_str().unwrap_or("0");
-                max_fee != "0" && max_fee != "0%"
-            }
+            Ok(val) => config::parse_max_builder_fee(&val) >= builder.fee,
             Err(_) => false,
         };
 
@@ -231,12 +1563,31 @@ impl ServerState {
         &self,
         request: serde_json::Value,
     ) -> Result<serde_json::Value, rmcp::model::ErrorData> {
-        hyperliquid::raw_info_request(&self.http, self.chain, request)
+        self.api
+            .info(request)
             .await
             .map_err(|e| mcp_err(&format!("API request failed: {e}")))
     }
 }
 
+/// Load an account's saved constructs at startup, dropping any that have
+/// lapsed, and log what was restored.
+fn restore_account(
+    path: Option<&Path>,
+    chain: Chain,
+    address: Option<Address>,
+    profile: &str,
+) -> persist::AccountState {
+    let (Some(path), Some(address)) = (path, address) else {
+        return persist::AccountState::default();
+    };
+    let restored = persist::restore(path, &persist::account_key(chain, address));
+    for line in restored.describe() {
+        tracing::info!(profile, "Restored from state file: {line}");
+    }
+    restored
+}
+
 pub fn mcp_err(msg: &str) -> rmcp::model::ErrorData {
     rmcp::model::ErrorData::new(
         rmcp::model::ErrorCode::INTERNAL_ERROR,
@@ -244,3 +1595,234 @@ pub fn mcp_err(msg: &str) -> rmcp::model::ErrorData {
         None::<serde_json::Value>,
     )
 }
+
+/// Result returned by write tools in dry-run mode instead of submitting `action`.
+pub fn dry_run_result(action: &str) -> rmcp::model::CallToolResult {
+    rmcp::model::CallToolResult::success(vec![rmcp::model::Content::text(format!(
+        "**Dry run** — nothing was submitted to the exchange.\n\nWould {action}"
+    ))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::MockExchange;
+
+    fn mock_state() -> ServerState {
+        ServerState::mock(Arc::new(MockExchange::new()))
+    }
+
+    fn resolve_error(state: &ServerState, coin: &str, kind: Option<MarketKind>) -> String {
+        state
+            .resolve_market(coin, kind)
+            .unwrap_err()
+            .message
+            .to_string()
+    }
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn market(symbol: &str, kind: MarketKind, sz_decimals: u32) -> ResolvedMarket {
+        ResolvedMarket {
+            coin: symbol.into(),
+            symbol: symbol.into(),
+            asset: 0,
+            kind,
+            sz_decimals,
+        }
+    }
+
+    #[test]
+    fn round_price_handles_sub_cent_assets() {
+        let btc = market("BTC", MarketKind::Perp, 5);
+        assert_eq!(btc.round_price(d("61234.56"), true), Ok(d("61234")));
+        assert_eq!(btc.round_price(d("61234.56"), false), Ok(d("61235")));
+
+        // kPEPE-class perp: 6 - szDecimals caps the decimals below 5 figures.
+        let kpepe = market("kPEPE", MarketKind::Perp, 0);
+        assert_eq!(
+            kpepe.round_price(d("0.0041234567"), true),
+            Ok(d("0.004123"))
+        );
+        assert_eq!(
+            kpepe.round_price(d("0.0041234567"), false),
+            Ok(d("0.004124"))
+        );
+        assert_eq!(kpepe.price_tick(d("0.004123")), d("0.000001"));
+
+        // SHIB-class spot: 8 decimals.
+        let shib = market("SHIB/USDC", MarketKind::Spot, 0);
+        assert_eq!(
+            shib.round_price(d("0.00001234567"), true),
+            Ok(d("0.00001234"))
+        );
+        assert_eq!(
+            shib.round_price(d("0.00001234567"), false),
+            Ok(d("0.00001235"))
+        );
+        assert_eq!(shib.price_tick(d("0.00001234")), d("0.00000001"));
+
+        // Below the smallest tick a buy would round to zero; a sell rounds up.
+        assert_eq!(
+            shib.round_price(d("0.000000004"), true),
+            Err(
+                "Price $0.000000004 is below the smallest price step of SHIB/USDC (1e-8) \
+                 and would round to zero."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            shib.round_price(d("0.000000004"), false),
+            Ok(d("0.00000001"))
+        );
+        assert_eq!(shib.round_price(Decimal::ZERO, true), Ok(Decimal::ZERO));
+    }
+
+    #[test]
+    fn resolve_market_accepts_each_naming_form() {
+        let mut state = mock_state();
+        // A HYPE pair quoted in USDH listed ahead of HYPE/USDC, and a token
+        // only quoted in USDH.
+        let mut pairs = vec![SpotPair {
+            name: "@200".into(),
+            base: "HYPE".into(),
+            quote: "USDH".into(),
+            index: SPOT_ASSET_OFFSET + 200,
+        }];
+        pairs.extend(state.spot_pairs.iter().cloned());
+        pairs.push(SpotPair {
+            name: "@300".into(),
+            base: "JEFF".into(),
+            quote: "USDH".into(),
+            index: SPOT_ASSET_OFFSET + 300,
+        });
+        state.spot_pairs = Arc::new(pairs);
+
+        use MarketKind::{Perp, Spot};
+        for (input, kind, coin, symbol, resolved_kind) in [
+            ("BTC", None, "BTC", "BTC", Perp),
+            ("btc-perp", None, "BTC", "BTC", Perp),
+            ("ETH-USD", None, "ETH", "ETH", Perp),
+            (" eth-Usd ", Some(Perp), "ETH", "ETH", Perp),
+            ("PURR/USDC", None, "PURR/USDC", "PURR/USDC", Spot),
+            ("purr-usdc", None, "PURR/USDC", "PURR/USDC", Spot),
+            ("HYPE / USDH", None, "@200", "HYPE/USDH", Spot),
+            ("@107", None, "@107", "HYPE/USDC", Spot),
+            ("@200", Some(Spot), "@200", "HYPE/USDH", Spot),
+            ("hype", None, "@107", "HYPE/USDC", Spot),
+            ("JEFF", None, "@300", "JEFF/USDH", Spot),
+        ] {
+            let market = state.resolve_market(input, kind).unwrap();
+            assert_eq!(
+                (market.coin.as_str(), market.symbol.as_str(), market.kind),
+                (coin, symbol, resolved_kind),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            resolve_error(&state, "@999", None),
+            "Unknown market '@999'. Use get_markets to see available markets."
+        );
+    }
+
+    #[test]
+    fn unanswered_orders_stay_claimed_past_the_dedupe_window() {
+        let state = mock_state();
+        let age = |fingerprint: u64| {
+            let mut recent = state.recent_orders.lock().unwrap();
+            let entry = recent.get_mut(&fingerprint).unwrap();
+            entry.inserted_at -= ORDER_DEDUPE_WINDOW * 2;
+        };
+
+        for (fingerprint, outcome, still_claimed) in [
+            (1, Submission::Pending, true),
+            (2, Submission::Unknown("timed out".into()), true),
+            (3, Submission::Resting { oid: 7 }, false),
+            (4, Submission::Rejected("bad price".into()), false),
+        ] {
+            let cloid = state.cloids.issue_new("test");
+            assert!(state.claim_order(fingerprint, cloid).is_none());
+            state.cloids.record(cloid, outcome);
+            age(fingerprint);
+            let retry = state.cloids.issue_new("test");
+            assert_eq!(
+                state.claim_order(fingerprint, retry).map(|(c, _)| c),
+                still_claimed.then_some(cloid),
+                "{fingerprint}"
+            );
+        }
+    }
+
+    #[test]
+    fn perp_request_for_spot_only_coin_points_to_spot() {
+        let state = mock_state();
+        let expected = "HYPE has a spot market (HYPE/USDC) but no perp — you cannot short it \
+                        or trade it with leverage. For a spot trade, pass market_kind \"spot\".";
+        assert_eq!(
+            resolve_error(&state, "hype", Some(MarketKind::Perp)),
+            expected
+        );
+        assert_eq!(resolve_error(&state, "HYPE-PERP", None), expected);
+    }
+
+    #[test]
+    fn spot_request_for_perp_only_coin_points_to_perp() {
+        let state = mock_state();
+        assert_eq!(
+            resolve_error(&state, "btc", Some(MarketKind::Spot)),
+            "BTC is perp-only; there is no spot market to buy or sell. \
+             Trade the perp instead (market_kind \"perp\")."
+        );
+    }
+
+    #[test]
+    fn coin_missing_from_both_namespaces_is_unknown() {
+        let state = mock_state();
+        assert_eq!(
+            resolve_error(&state, "DOGE", Some(MarketKind::Perp)),
+            "Unknown perp market 'DOGE'. Use get_markets to see available markets."
+        );
+        assert_eq!(
+            resolve_error(&state, "DOGE", Some(MarketKind::Spot)),
+            "Unknown spot market 'DOGE'. Use get_markets to see available markets."
+        );
+        assert_eq!(
+            resolve_error(&state, "DOGE", None),
+            "Unknown market 'DOGE'. Use get_markets to see available markets."
+        );
+    }
+
+    #[test]
+    fn short_on_spot_market_is_refused() {
+        let mut state = mock_state();
+        let hype = state.resolve_market("HYPE", None).unwrap();
+        assert_eq!(hype.kind, MarketKind::Spot);
+        assert_eq!(
+            state.spot_short_refusal(&hype, "short").as_deref(),
+            Some(
+                "HYPE has a spot market (HYPE/USDC) but no perp — you cannot short it or trade \
+                 it with leverage. Did you mean a spot sell (side \"sell\")?"
+            )
+        );
+        assert_eq!(state.spot_short_refusal(&hype, "sell"), None);
+
+        // With a HYPE perp listed too, the refusal points at it.
+        state.asset_map.insert("HYPE".into(), 2);
+        let hype_spot = state
+            .resolve_market("HYPE", Some(MarketKind::Spot))
+            .unwrap();
+        assert_eq!(
+            state.spot_short_refusal(&hype_spot, "Short").as_deref(),
+            Some(
+                "HYPE/USDC (spot, @107) is a spot market, where \"short\" could only sell \
+                 tokens you hold. To short HYPE with leverage, pass market_kind \"perp\" for \
+                 HYPE (perp); for a spot sell, use side \"sell\"."
+            )
+        );
+
+        let btc = state.resolve_market("BTC", None).unwrap();
+        assert_eq!(state.spot_short_refusal(&btc, "short"), None);
+    }
+}
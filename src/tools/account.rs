@@ -1,13 +1,62 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use either::Either;
+use hypersdk::Decimal;
+use hypersdk::hypercore::Cloid;
+use hypersdk::hypercore::types::{AssetPosition, BasicOrder, OrderStatus, OrderType};
 use rmcp::{model::*, schemars};
+use serde_json::json;
 
-use crate::cache::CachedValue;
+use crate::cache::{CachedValue, FillSource, MAX_RECENT_FILLS, RecentFill, WatchedOrder};
+use crate::cloids::Submission;
+use crate::config::{self, MAX_POSITION_NOTIONAL_VAR};
 use crate::state::{ServerState, mcp_err};
+use crate::tools::format::{fmt_pct, fmt_price, fmt_qty, fmt_usd, round_sig};
+use crate::tools::market;
+use crate::tools::timestamps::{fmt_instant, fmt_recent, fmt_time, fmt_time_secs, fmt_until};
 
 const POSITIONS_TTL: Duration = Duration::from_secs(3);
 const OPEN_ORDERS_TTL: Duration = Duration::from_secs(2);
+/// How long the equity footer may wait for each clearinghouse read before
+/// it is left out, so a slow info endpoint doesn't hold up the action.
+const EQUITY_FOOTER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetWalletAddressRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetPositionsRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetBalancesRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetAccountOverviewRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetRiskStatusRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetAccountPermissionsRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetOpenOrdersRequest {
@@ -15,6 +64,9 @@ pub struct GetOpenOrdersRequest {
         description = "Filter by coin symbol, e.g. \"BTC\" (optional, returns all if omitted)"
     )]
     pub coin: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -22,19 +74,115 @@ pub struct GetTradeHistoryRequest {
     #[schemars(description = "Filter by coin symbol (optional)")]
     pub coin: Option<String>,
 
-    #[schemars(description = "Number of trades to return (default 50, max 200)")]
+    #[schemars(
+        description = "Number of trades to return (default 50, max 200; max 2000 with export_csv)"
+    )]
+    pub limit: Option<usize>,
+
+    #[schemars(
+        description = "Set to true to return the trades as CSV (ISO timestamps, plain decimals) for spreadsheets"
+    )]
+    pub export_csv: Option<bool>,
+
+    #[schemars(
+        description = "Set to true to show the nearest 1-minute candle close for each trade and how the fill compared to it (newest 50 trades)"
+    )]
+    pub with_market_context: Option<bool>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetRecentFillsRequest {
+    #[schemars(description = "Filter by coin symbol (optional)")]
+    pub coin: Option<String>,
+
+    #[schemars(description = "Number of fills to return (default 20, max 200)")]
     pub limit: Option<usize>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetOrderStatusRequest {
     #[schemars(description = "The numeric order ID to look up")]
-    pub order_id: u64,
+    pub order_id: Option<u64>,
+
+    #[schemars(description = "Client order ID (0x-prefixed hex), as an alternative to order_id")]
+    pub cloid: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WatchOrderRequest {
+    #[schemars(description = "The numeric order ID to watch")]
+    pub order_id: Option<u64>,
+
+    #[schemars(description = "Client order ID (0x-prefixed hex), as an alternative to order_id")]
+    pub cloid: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetWatchedOrdersRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetEventsRequest {
+    #[schemars(description = "Number of recent events to show (default 50, max 200)")]
+    pub limit: Option<u32>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetStakingYieldRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetDepositStatusRequest {
+    #[schemars(description = "Hours of ledger history to scan for deposits (default 24, max 720)")]
+    pub lookback_hours: Option<u64>,
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SnapshotAccountRequest {
+    #[schemars(
+        description = "Name to store the snapshot under, e.g. \"before-rebalance\"; reusing a name replaces it"
+    )]
+    pub name: String,
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiffAccountSnapshotRequest {
+    #[schemars(description = "Name of a snapshot taken earlier with snapshot_account")]
+    pub name: String,
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 pub async fn get_wallet_address(state: &ServerState) -> Result<CallToolResult, ErrorData> {
     let address = state.require_address()?;
-    let mut output = format!("Main wallet (account owner): {:#x}", address);
+    let mut output = String::new();
+    if state.profile != crate::config::DEFAULT_PROFILE {
+        output.push_str(&format!("Profile: {}\n", state.profile));
+    }
+    output.push_str(&format!("Main wallet (account owner): {:#x}", address));
 
     if let Some(agent_addr) = state.agent_address {
         if agent_addr != address {
@@ -42,6 +190,9 @@ pub async fn get_wallet_address(state: &ServerState) -> Result<CallToolResult, E
                 "\nAgent wallet (trade signing): {:#x}",
                 agent_addr
             ));
+            if let Some(expiry) = state.agent_expiry_summary() {
+                output.push_str(&format!("\nAgent approval: {expiry}"));
+            }
         }
     }
 
@@ -55,8 +206,102 @@ pub async fn get_wallet_address(state: &ServerState) -> Result<CallToolResult, E
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
+/// Paper fills shown under the paper positions.
+const PAPER_FILLS_SHOWN: usize = 10;
+
+/// The dry-run paper account marked to current mids, empty outside dry-run.
+async fn paper_summary(state: &ServerState) -> String {
+    let Some(paper) = &state.paper else {
+        return String::new();
+    };
+    let account = paper.lock().unwrap_or_else(|e| e.into_inner()).clone();
+
+    let mut mids = state.cache.all_mids.borrow().clone();
+    if account.positions.keys().any(|c| !mids.contains_key(c)) {
+        match state.api.all_mids().await {
+            Ok(fetched) => mids.extend(fetched),
+            Err(e) => tracing::warn!(error = %e, "Mid lookup for paper positions failed"),
+        }
+    }
+    let equity = account.equity(&mids);
+
+    let mut output = format!(
+        "## Paper Account (dry-run)\n\n\
+         | Metric | Value |\n\
+         |--------|-------|\n\
+         | Starting Balance | {} |\n\
+         | Balance | {} |\n\
+         | Unrealized PnL | {} |\n\
+         | Equity | {} |\n\
+         | Realized PnL | {} |\n\
+         | Fees Paid | {} |\n\
+         | Started | {} |\n",
+        fmt_usd(account.starting_balance),
+        fmt_usd(account.balance),
+        fmt_usd(equity - account.balance),
+        fmt_usd(equity),
+        fmt_usd(account.realized_pnl),
+        fmt_usd(account.fees_paid),
+        fmt_instant(account.started_at),
+    );
+
+    if !account.positions.is_empty() {
+        output.push_str(
+            "\n### Paper Positions\n\n\
+             | Market | Side | Size | Entry Price | Mark | Unrealized PnL |\n\
+             |--------|------|------|-------------|------|----------------|\n",
+        );
+        for (coin, p) in &account.positions {
+            let mark = mids.get(coin).copied();
+            output.push_str(&format!(
+                "| {coin} | {} | {} | {} | {} | {} |\n",
+                if p.szi.is_sign_positive() {
+                    "Long"
+                } else {
+                    "Short"
+                },
+                fmt_qty(p.szi.abs()),
+                fmt_price(round_sig(p.entry_px, 8)),
+                mark.map(fmt_price).unwrap_or_else(|| "N/A".into()),
+                mark.map(|m| fmt_usd(account.unrealized(coin, m)))
+                    .unwrap_or_else(|| "N/A".into()),
+            ));
+        }
+    }
+
+    if !account.fills.is_empty() {
+        output.push_str(
+            "\n### Recent Paper Fills\n\n\
+             | Time | Market | Side | Size | Price | Fee | Closed PnL |\n\
+             |------|--------|------|------|-------|-----|------------|\n",
+        );
+        for f in account.fills.iter().rev().take(PAPER_FILLS_SHOWN) {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\n",
+                fmt_instant(f.time),
+                f.coin,
+                if f.is_buy { "Buy" } else { "Sell" },
+                fmt_qty(f.sz),
+                fmt_price(round_sig(f.px, 8)),
+                fmt_usd(f.fee),
+                fmt_usd(f.closed_pnl),
+            ));
+        }
+    }
+    output.push('\n');
+    output
+}
+
 pub async fn get_positions(state: &ServerState) -> Result<CallToolResult, ErrorData> {
-    let address = state.query_address()?;
+    let paper = paper_summary(state).await;
+    let address = match state.query_address() {
+        Ok(address) => address,
+        // Paper trading needs no wallet; show what there is.
+        Err(_) if !paper.is_empty() => {
+            return Ok(CallToolResult::success(vec![Content::text(paper)]));
+        }
+        Err(e) => return Err(e),
+    };
 
     let user_state = get_cached_clearinghouse(state, address).await?;
 
@@ -66,13 +311,14 @@ pub async fn get_positions(state: &ServerState) -> Result<CallToolResult, ErrorD
         .filter(|p| !p.position.szi.is_zero())
         .collect();
 
+    let notice = format!("{}{paper}", state.account_notices());
     if positions.is_empty() {
-        return Ok(CallToolResult::success(vec![Content::text(
-            "No open positions.",
-        )]));
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "{notice}No open positions."
+        ))]));
     }
 
-    let mut output = format!("## Open Positions ({})\n\n", positions.len());
+    let mut output = format!("{notice}## Open Positions ({})\n\n", positions.len());
     output.push_str(
         "| Market | Side | Size | Entry Price | Mark Value | Unrealized PnL | ROE | Liq. Price | Leverage | Margin Used |\n",
     );
@@ -88,32 +334,56 @@ pub async fn get_positions(state: &ServerState) -> Result<CallToolResult, ErrorD
             "Short"
         };
         let size = p.szi.abs();
-        let entry = p
-            .entry_px
-            .map(|px| format!("${px}"))
-            .unwrap_or_else(|| "N/A".into());
+        let entry = p.entry_px.map(fmt_price).unwrap_or_else(|| "N/A".into());
         let liq = p
             .liquidation_px
-            .map(|px| format!("${px}"))
+            .map(fmt_price)
             .unwrap_or_else(|| "N/A".into());
         let lev = format!("{}x {}", p.leverage.value, p.leverage.leverage_type);
-        let roe_pct = format!(
-            "{:.2}",
-            p.return_on_equity * rust_decimal::Decimal::from(100)
-        );
 
         output.push_str(&format!(
-            "| {} | {} | {} | {} | ${} | ${} | {}% | {} | {} | ${} |\n",
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
             p.coin,
             side,
-            size,
+            fmt_qty(size),
             entry,
-            p.position_value,
-            p.unrealized_pnl,
-            roe_pct,
+            fmt_usd(p.position_value),
+            fmt_usd(p.unrealized_pnl),
+            fmt_pct(p.return_on_equity * rust_decimal::Decimal::from(100)),
             liq,
             lev,
-            p.margin_used,
+            fmt_usd(p.margin_used),
+        ));
+    }
+
+    // Longs pay positive funding, shorts negative.
+    let mut funding_alerts = Vec::new();
+    for ap in &positions {
+        let p = &ap.position;
+        let Some(next) = market::next_funding(state, &p.coin, None).await else {
+            continue;
+        };
+        let payment = if p.szi.is_sign_positive() {
+            next.rate * p.position_value
+        } else {
+            -next.rate * p.position_value
+        };
+        if payment >= state.funding_alert_usd {
+            funding_alerts.push(format!(
+                "- **{}:** about to pay {} of funding {} ({}, {:+.4}%/h)\n",
+                p.coin,
+                fmt_usd(payment),
+                fmt_until(next.next_time_ms),
+                fmt_time(next.next_time_ms),
+                next.rate * Decimal::ONE_HUNDRED
+            ));
+        }
+    }
+    if !funding_alerts.is_empty() {
+        output.push_str(&format!(
+            "\n**Upcoming funding** (payments of {} or more, HYPERLIQUID_FUNDING_ALERT_USD):\n\n{}",
+            fmt_usd(state.funding_alert_usd),
+            funding_alerts.concat()
         ));
     }
 
@@ -123,45 +393,57 @@ pub async fn get_positions(state: &ServerState) -> Result<CallToolResult, ErrorD
         "\n## Account Summary\n\n\
          | Metric | Value |\n\
          |--------|-------|\n\
-         | Account Value | ${} |\n\
-         | Total Position Notional | ${} |\n\
-         | Total Margin Used | ${} |\n\
-         | Available Margin | ${:.2} |\n\
-         | Withdrawable | ${} |\n",
-        ms.account_value,
-        ms.total_ntl_pos,
-        ms.total_margin_used,
-        available,
-        user_state.withdrawable,
+         | Account Value | {} |\n\
+         | Total Position Notional | {} |\n\
+         | Total Margin Used | {} |\n\
+         | Available Margin | {} |\n\
+         | Withdrawable | {} |\n",
+        fmt_usd(ms.account_value),
+        fmt_usd(ms.total_ntl_pos),
+        fmt_usd(ms.total_margin_used),
+        fmt_usd(available),
+        fmt_usd(user_state.withdrawable),
     ));
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
 pub async fn get_balances(state: &ServerState) -> Result<CallToolResult, ErrorData> {
-    let address = state.query_address()?;
+    let paper = paper_summary(state).await;
+    let address = match state.query_address() {
+        Ok(address) => address,
+        Err(_) if !paper.is_empty() => {
+            return Ok(CallToolResult::success(vec![Content::text(paper)]));
+        }
+        Err(e) => return Err(e),
+    };
 
     let user_state = get_cached_clearinghouse(state, address).await?;
 
     let ms = &user_state.margin_summary;
     let available = ms.account_value - ms.total_margin_used;
 
-    let mut output = "## Perpetual Account\n\n".to_string();
+    let mut output = state.account_notices();
+    output.push_str(&paper);
+    output.push_str("## Perpetual Account\n\n");
     output.push_str("| Metric | Value |\n");
     output.push_str("|--------|-------|\n");
-    output.push_str(&format!("| Account Value | ${} |\n", ms.account_value));
     output.push_str(&format!(
-        "| Total Margin Used | ${} |\n",
-        ms.total_margin_used
+        "| Account Value | {} |\n",
+        fmt_usd(ms.account_value)
     ));
-    output.push_str(&format!("| Available Margin | ${:.2} |\n", available));
     output.push_str(&format!(
-        "| Withdrawable | ${} |\n",
-        user_state.withdrawable
+        "| Total Margin Used | {} |\n",
+        fmt_usd(ms.total_margin_used)
+    ));
+    output.push_str(&format!("| Available Margin | {} |\n", fmt_usd(available)));
+    output.push_str(&format!(
+        "| Withdrawable | {} |\n",
+        fmt_usd(user_state.withdrawable)
     ));
 
     let token_balances = state
-        .client
+        .api
         .user_balances(address)
         .await
         .map_err(|e| mcp_err(&format!("Failed to fetch spot balances: {e}")))?;
@@ -180,7 +462,10 @@ pub async fn get_balances(state: &ServerState) -> Result<CallToolResult, ErrorDa
             let available = b.total - b.hold;
             output.push_str(&format!(
                 "| {} | {} | {} | {} |\n",
-                b.coin, b.total, available, b.hold,
+                b.coin,
+                fmt_qty(b.total),
+                fmt_qty(available),
+                fmt_qty(b.hold),
             ));
         }
     }
@@ -188,131 +473,953 @@ pub async fn get_balances(state: &ServerState) -> Result<CallToolResult, ErrorDa
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn get_open_orders(
-    state: &ServerState,
-    req: GetOpenOrdersRequest,
-) -> Result<CallToolResult, ErrorData> {
+/// The WebSocket feed is reported stale when nothing arrived for this long;
+/// mids alone stream about once a second.
+const WS_STALE_MS: u64 = 30_000;
+
+pub async fn get_account_overview(state: &ServerState) -> Result<CallToolResult, ErrorData> {
     let address = state.query_address()?;
+    let (user_state, orders, balances) = tokio::try_join!(
+        get_cached_clearinghouse(state, address),
+        get_cached_open_orders(state, address),
+        async {
+            state
+                .api
+                .user_balances(address)
+                .await
+                .map_err(|e| mcp_err(&format!("Failed to fetch spot balances: {e}")))
+        },
+    )?;
 
-    let orders = get_cached_open_orders(state, address).await?;
+    let mut output = state.account_notices();
+    output.push_str("## Account Overview\n\n");
+    let ms = &user_state.margin_summary;
+    output.push_str(&format!(
+        "Equity ${:.2} · Available margin ${:.2} · Withdrawable ${:.2}\n",
+        ms.account_value,
+        ms.account_value - ms.total_margin_used,
+        user_state.withdrawable
+    ));
+    let spot: Vec<String> = balances
+        .iter()
+        .filter(|b| !b.total.is_zero())
+        .map(|b| format!("{} {}", b.total.normalize(), b.coin))
+        .collect();
+    if !spot.is_empty() {
+        output.push_str(&format!("Spot: {}\n", spot.join(", ")));
+    }
 
-    let filtered: Vec<_> = if let Some(ref coin) = req.coin {
-        orders
-            .iter()
-            .filter(|o| o.coin.eq_ignore_ascii_case(coin))
-            .collect()
+    let positions: Vec<_> = user_state
+        .asset_positions
+        .iter()
+        .map(|p| &p.position)
+        .filter(|p| !p.szi.is_zero())
+        .collect();
+    if positions.is_empty() {
+        output.push_str("\nNo open positions.\n");
     } else {
-        orders.iter().collect()
-    };
-
-    if filtered.is_empty() {
-        let msg = match &req.coin {
-            Some(c) => format!("No open orders for {c}."),
-            None => "No open orders.".into(),
-        };
-        return Ok(CallToolResult::success(vec![Content::text(msg)]));
+        output.push_str(&format!("\n### Positions ({})\n\n", positions.len()));
+        output.push_str("| Market | Side | Size | Entry | uPnL | Liq. | Leverage |\n");
+        output.push_str("|--------|------|------|-------|------|------|----------|\n");
+        for p in positions {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | ${:.2} | {} | {}x |\n",
+                p.coin,
+                if p.szi.is_sign_positive() {
+                    "Long"
+                } else {
+                    "Short"
+                },
+                p.szi.abs(),
+                p.entry_px
+                    .map(|px| format!("${px}"))
+                    .unwrap_or_else(|| "—".into()),
+                p.unrealized_pnl,
+                p.liquidation_px
+                    .map(|px| format!("${px}"))
+                    .unwrap_or_else(|| "—".into()),
+                p.leverage.value,
+            ));
+        }
     }
 
-    let mut output = format!("## Open Orders ({})\n\n", filtered.len());
-    output.push_str("| Market | Side | Price | Size | Order ID |\n");
-    output.push_str("|--------|------|-------|------|----------|\n");
+    if orders.is_empty() {
+        output.push_str("\nNo open orders.\n");
+    } else {
+        let mut per_coin: BTreeMap<&str, usize> = BTreeMap::new();
+        for o in &orders {
+            *per_coin.entry(o.coin.as_str()).or_default() += 1;
+        }
+        let counts: Vec<String> = per_coin.iter().map(|(c, n)| format!("{c} {n}")).collect();
+        output.push_str(&format!(
+            "\nOpen orders ({}): {}\n",
+            orders.len(),
+            counts.join(", ")
+        ));
+    }
 
-    for o in &filtered {
-        let side = match o.side {
-            hypersdk::hypercore::Side::Bid => "Buy",
-            hypersdk::hypercore::Side::Ask => "Sell",
-        };
+    let now = chrono::Utc::now();
+    let scheduled = *state
+        .scheduled_cancel
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(when) = scheduled.filter(|w| *w > now) {
         output.push_str(&format!(
-            "| {} | {} | ${} | {} | {} |\n",
-            o.coin, side, o.limit_px, o.sz, o.oid,
+            "Scheduled cancel armed: all orders cancel at {} (in {}s).\n",
+            fmt_instant(when),
+            (when - now).num_seconds()
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    if state.read_only {
+        warnings.push("Trading is disabled: the server runs in read-only mode.".to_string());
+    } else if state.require_signer().is_err() {
+        warnings.push("Trading is disabled: no signing key is configured.".to_string());
+    } else if state.dry_run {
+        warnings.push("Dry-run mode: write actions are not submitted.".to_string());
+    }
+    if let Err(e) = state.check_trading_window() {
+        warnings.push(e.message.to_string());
+    }
+    if !state.hide_builder_nudge
+        && state.agent_signer.is_some()
+        && !state
+            .builder_fee_approved
+            .load(std::sync::atomic::Ordering::Relaxed)
+    {
+        warnings.push("Builder fee is not approved; run `check_builder_fee`.".to_string());
+    }
+    if state.cache.live {
+        let last = state
+            .cache
+            .last_message_ms
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let age = (now.timestamp_millis() as u64).saturating_sub(last);
+        if last == 0 {
+            warnings.push("The WebSocket feed has not delivered any data yet.".to_string());
+        } else if age > WS_STALE_MS {
+            warnings.push(format!(
+                "The WebSocket feed is stale: last message {}s ago.",
+                age / 1000
+            ));
+        }
+    }
+    let orphaned = orphaned_trigger_orders(&orders, &user_state.asset_positions);
+    if !orphaned.is_empty() {
+        let mut coins: Vec<&str> = orphaned.iter().map(|o| o.coin.as_str()).collect();
+        coins.sort_unstable();
+        coins.dedup();
+        warnings.push(format!(
+            "{} orphaned trigger order(s) on coins with no position ({}); see `get_open_orders`.",
+            orphaned.len(),
+            coins.join(", ")
         ));
     }
+    if !warnings.is_empty() {
+        output.push_str("\n### Warnings\n\n");
+        for w in &warnings {
+            output.push_str(&format!("- {w}\n"));
+        }
+    }
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn get_trade_history(
-    state: &ServerState,
-    req: GetTradeHistoryRequest,
-) -> Result<CallToolResult, ErrorData> {
-    let address = state.query_address()?;
+/// Trigger orders (stops and take-profits) on perps where the account holds
+/// no position, typically left behind when a position was closed some other
+/// way. Spot markets are skipped since their triggers don't track a position.
+pub(crate) fn orphaned_trigger_orders<'a>(
+    orders: &'a [BasicOrder],
+    positions: &[AssetPosition],
+) -> Vec<&'a BasicOrder> {
+    orders
+        .iter()
+        .filter(|o| !matches!(o.order_type, OrderType::Limit | OrderType::Market))
+        .filter(|o| !o.coin.starts_with('@') && !o.coin.contains('/'))
+        .filter(|o| {
+            !positions
+                .iter()
+                .any(|p| p.position.coin == o.coin && !p.position.szi.is_zero())
+        })
+        .collect()
+}
 
-    let fills = state
-        .client
-        .user_fills(address)
-        .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch trade history: {e}")))?;
+/// Configured risk limits and how much of each the account is using:
+/// position notional per perp and open order counts.
+pub async fn get_risk_status(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    let (user_state, orders) = tokio::try_join!(
+        get_cached_clearinghouse(state, address),
+        get_cached_open_orders(state, address),
+    )?;
 
-    let limit = req.limit.unwrap_or(50).min(200);
+    let mut output = format!("{}## Risk Status\n\n", state.account_notices());
+    output.push_str("### Position Notional Limits\n\n");
+    match state.max_position_notional {
+        Some(limit) => output.push_str(&format!(
+            "Global limit: {} per position (`{MAX_POSITION_NOTIONAL_VAR}`)\n",
+            fmt_usd(limit)
+        )),
+        None => output.push_str(&format!(
+            "Global limit: not set (`{MAX_POSITION_NOTIONAL_VAR}`)\n"
+        )),
+    }
+    for (coin, limit) in state.max_position_notional_by_coin.iter() {
+        output.push_str(&format!(
+            "- {coin}: {} (`{MAX_POSITION_NOTIONAL_VAR}_{coin}`)\n",
+            fmt_usd(*limit)
+        ));
+    }
 
-    let filtered: Vec<_> = if let Some(ref coin) = req.coin {
-        fills
+    // Open positions, then coins with an override but no position.
+    let mut rows: Vec<(String, Decimal, Decimal)> = user_state
+        .asset_positions
+        .iter()
+        .filter(|p| !p.position.szi.is_zero())
+        .map(|p| {
+            (
+                p.position.coin.clone(),
+                p.position.szi,
+                p.position.position_value,
+            )
+        })
+        .collect();
+    for coin in state.max_position_notional_by_coin.keys() {
+        if !rows
             .iter()
-            .filter(|f| f.coin.eq_ignore_ascii_case(coin))
-            .take(limit)
-            .collect()
+            .any(|(held, _, _)| config::notional_limit_key(held) == *coin)
+        {
+            rows.push((coin.clone(), Decimal::ZERO, Decimal::ZERO));
+        }
+    }
+
+    if rows.is_empty() {
+        output.push_str("\nNo open positions.\n");
     } else {
-        fills.iter().take(limit).collect()
-    };
+        output.push_str("\n| Coin | Position | Notional | Limit | Utilization | Headroom |\n");
+        output.push_str("|------|----------|----------|-------|-------------|----------|\n");
+        for (coin, szi, notional) in &rows {
+            let (limit, utilization, headroom) = match state.position_notional_limit(coin) {
+                Some(limit) => {
+                    let used = notional / limit * Decimal::from(100);
+                    let headroom = if *notional > limit {
+                        format!("**over by {}**", fmt_usd(notional - limit))
+                    } else {
+                        fmt_usd(limit - notional)
+                    };
+                    (fmt_usd(limit), format!("{used:.1}%"), headroom)
+                }
+                None => ("—".to_string(), "—".to_string(), "—".to_string()),
+            };
+            output.push_str(&format!(
+                "| {coin} | {} | {} | {limit} | {utilization} | {headroom} |\n",
+                fmt_qty(*szi),
+                fmt_usd(*notional),
+            ));
+        }
+        output.push_str(
+            "\nNotional is at the mark price. Orders that would take a position past its limit \
+             are refused unless they are reduce-only.\n",
+        );
+    }
 
-    if filtered.is_empty() {
-        return Ok(CallToolResult::success(vec![Content::text(
-            "No recent trades.",
-        )]));
+    output.push_str("\n### Open Orders\n\n");
+    output.push_str(&format!(
+        "{} of {} open orders account-wide (`HYPERLIQUID_MAX_OPEN_ORDERS`)\n",
+        orders.len(),
+        state.max_open_orders
+    ));
+    if let Some(limit) = state.max_open_orders_per_coin {
+        let mut per_coin: BTreeMap<&str, usize> = BTreeMap::new();
+        for order in &orders {
+            *per_coin.entry(order.coin.as_str()).or_default() += 1;
+        }
+        output.push_str(&format!(
+            "Per-coin limit: {limit} (`HYPERLIQUID_MAX_OPEN_ORDERS_PER_COIN`)\n"
+        ));
+        for (coin, count) in per_coin {
+            output.push_str(&format!("- {coin}: {count} of {limit}\n"));
+        }
     }
 
-    let mut output = format!("## Recent Trades ({})\n\n", filtered.len());
-    output.push_str("| Time | Market | Side | Price | Size | Direction | Fee | Closed PnL |\n");
-    output.push_str("|------|--------|------|-------|------|-----------|-----|------------|\n");
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
 
-    for f in &filtered {
-        let time = chrono_from_ms(f.time);
-        let side = match f.side {
-            hypersdk::hypercore::Side::Bid => "Buy",
-            hypersdk::hypercore::Side::Ask => "Sell",
-        };
-        let pnl_str = if f.closed_pnl.is_zero() {
-            "—".into()
-        } else {
-            format!("${}", f.closed_pnl)
+/// An address from an info response field.
+fn address_field(value: &serde_json::Value, key: &str) -> Option<hypersdk::Address> {
+    value.get(key)?.as_str()?.parse().ok()
+}
+
+pub async fn get_account_permissions(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    // Agents, builder approvals and referrals belong to the master account,
+    // not to an agent signing for it.
+    let account = match state.resolve_builder_fee_account().await {
+        Some(account) => account,
+        None => state.query_address()?,
+    };
+    let user = format!("{account:#x}");
+    let (agents, referral, sub_accounts, fills) = tokio::join!(
+        state.raw_info_request(json!({ "type": "extraAgents", "user": user })),
+        state.raw_info_request(json!({ "type": "referral", "user": user })),
+        state.raw_info_request(json!({ "type": "subAccounts", "user": user })),
+        state.raw_info_request(json!({ "type": "userFills", "user": user })),
+    );
+
+    let mut output = format!("## Account Permissions: `{user}`\n\n");
+    output.push_str(
+        "Everything with signing power or a fee claim over this account. \
+         Anything you don't recognise can be revoked from the Hyperliquid app.\n\n",
+    );
+
+    output.push_str("### Agent Wallets\n\n");
+    match agents {
+        Ok(agents) => {
+            let agents = agents.as_array().cloned().unwrap_or_default();
+            if agents.is_empty() {
+                output.push_str("No approved agents.\n\n");
+            } else {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                output.push_str("| Name | Address | Valid Until | Note |\n");
+                output.push_str("|------|---------|-------------|------|\n");
+                for agent in &agents {
+                    let address = address_field(agent, "address");
+                    let valid_until = agent.get("validUntil").and_then(|v| v.as_u64());
+                    let mut notes = Vec::new();
+                    if address.is_some() && address == state.agent_address {
+                        notes.push("this server's signer");
+                    }
+                    if valid_until.is_some_and(|t| t <= now) {
+                        notes.push("expired");
+                    }
+                    output.push_str(&format!(
+                        "| {} | `{}` | {} | {} |\n",
+                        agent
+                            .get("name")
+                            .and_then(|v| v.as_str())
+                            .filter(|n| !n.is_empty())
+                            .unwrap_or("(unnamed)"),
+                        address.map(|a| format!("{a:#x}")).unwrap_or_default(),
+                        valid_until.map(fmt_time).unwrap_or_else(|| "—".into()),
+                        notes.join(", "),
+                    ));
+                }
+                output.push('\n');
+            }
+        }
+        Err(e) => output.push_str(&format!("Could not fetch agents: {}\n\n", e.message)),
+    }
+
+    // Builders this server knows of, plus any named in recent fills. Fills
+    // that paid a builder fee without naming the builder are counted.
+    let mut builders: Vec<(hypersdk::Address, &str)> = vec![(
+        config::BUILDER_ADDRESS
+            .parse()
+            .expect("BUILDER_ADDRESS is a valid address"),
+        "this server (default)",
+    )];
+    let active = state.active_builder().address;
+    if builders.iter().all(|(b, _)| *b != active) {
+        builders.push((active, "this server (active)"));
+    }
+    let mut unnamed_builder_fills = 0;
+    if let Ok(fills) = &fills {
+        for fill in fills.as_array().into_iter().flatten() {
+            let paid = fill
+                .get("builderFee")
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<Decimal>().ok())
+                .is_some_and(|fee| !fee.is_zero());
+            match address_field(fill, "builder") {
+                Some(builder) if builders.iter().all(|(b, _)| *b != builder) => {
+                    builders.push((builder, "seen in fills"));
+                }
+                Some(_) => {}
+                None if paid => unnamed_builder_fills += 1,
+                None => {}
+            }
+        }
+    }
+    output.push_str("### Builder Fee Approvals\n\n");
+    output.push_str("| Builder | Max Fee | Source |\n");
+    output.push_str("|---------|---------|--------|\n");
+    for (builder, source) in &builders {
+        let max_fee = match state
+            .raw_info_request(json!({
+                "type": "maxBuilderFee",
+                "user": user,
+                "builder": format!("{builder:#x}"),
+            }))
+            .await
+        {
+            Ok(value) => match config::parse_max_builder_fee(&value) {
+                0 => "not approved".to_string(),
+                fee => config::fee_to_percent(fee),
+            },
+            Err(e) => format!("unknown ({})", e.message),
         };
-        output.push_str(&format!(
-            "| {} | {} | {} | ${} | {} | {} | ${} | {} |\n",
-            time, f.coin, side, f.px, f.sz, f.dir, f.fee, pnl_str,
-        ));
+        output.push_str(&format!("| `{builder:#x}` | {max_fee} | {source} |\n"));
+    }
+    match &fills {
+        Ok(_) if unnamed_builder_fills > 0 => output.push_str(&format!(
+            "\n{unnamed_builder_fills} recent fill(s) paid a builder fee without naming the builder; \
+             the exchange does not list approvals for builders it isn't asked about.\n"
+        )),
+        Ok(_) => {}
+        Err(e) => output.push_str(&format!(
+            "\nCould not scan recent fills for other builders: {}\n",
+            e.message
+        )),
+    }
+    output.push('\n');
+
+    output.push_str("### Referrer\n\n");
+    match referral {
+        Ok(referral) => match referral.get("referredBy").filter(|r| !r.is_null()) {
+            Some(referred_by) => output.push_str(&format!(
+                "Referred by `{}`{}\n\n",
+                referred_by
+                    .get("referrer")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown"),
+                referred_by
+                    .get("code")
+                    .and_then(|v| v.as_str())
+                    .map(|code| format!(" (code {code})"))
+                    .unwrap_or_default()
+            )),
+            None => output.push_str("No referrer.\n\n"),
+        },
+        Err(e) => output.push_str(&format!("Could not fetch referral: {}\n\n", e.message)),
+    }
+
+    output.push_str("### Sub-Accounts\n\n");
+    match sub_accounts {
+        Ok(sub_accounts) => {
+            let sub_accounts = sub_accounts.as_array().cloned().unwrap_or_default();
+            if sub_accounts.is_empty() {
+                output.push_str("No sub-accounts.\n");
+            } else {
+                output.push_str("| Name | Address |\n");
+                output.push_str("|------|---------|\n");
+                for sub in &sub_accounts {
+                    output.push_str(&format!(
+                        "| {} | `{}` |\n",
+                        sub.get("name").and_then(|v| v.as_str()).unwrap_or("—"),
+                        sub.get("subAccountUser")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("?"),
+                    ));
+                }
+                output.push_str(
+                    "\nSub-accounts are controlled by this account's keys and its agents.\n",
+                );
+            }
+        }
+        Err(e) => output.push_str(&format!("Could not fetch sub-accounts: {}\n", e.message)),
     }
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn get_order_status(
+pub async fn get_open_orders(
     state: &ServerState,
-    req: GetOrderStatusRequest,
+    req: GetOpenOrdersRequest,
 ) -> Result<CallToolResult, ErrorData> {
     let address = state.query_address()?;
 
-    let update = state
-        .client
-        .order_status(address, Either::Left(req.order_id))
-        .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch order status: {e}")))?;
+    let (orders, user_state) = tokio::try_join!(
+        get_cached_open_orders(state, address),
+        get_cached_clearinghouse(state, address),
+    )?;
+    let orphaned: Vec<u64> = orphaned_trigger_orders(&orders, &user_state.asset_positions)
+        .iter()
+        .map(|o| o.oid)
+        .collect();
 
-    let update = match update {
-        Some(u) => u,
-        None => {
+    let market = req
+        .coin
+        .as_deref()
+        .map(|c| state.resolve_market(c, None))
+        .transpose()?;
+    let filtered: Vec<_> = if let Some(ref market) = market {
+        orders.iter().filter(|o| o.coin == market.coin).collect()
+    } else {
+        orders.iter().collect()
+    };
+
+    if filtered.is_empty() {
+        let msg = match &market {
+            Some(m) => format!("No open orders for {}.", m.describe()),
+            None => "No open orders.".into(),
+        };
+        return Ok(CallToolResult::success(vec![Content::text(msg)]));
+    }
+
+    let mut output = match &market {
+        Some(m) => format!("## Open Orders: {} ({})\n\n", m.describe(), filtered.len()),
+        None => format!("## Open Orders ({})\n\n", filtered.len()),
+    };
+    output.push_str(&format!(
+        "{} of {} open orders account-wide",
+        orders.len(),
+        state.max_open_orders
+    ));
+    if let (Some(m), Some(limit)) = (&market, state.max_open_orders_per_coin) {
+        output.push_str(&format!(", {} of {limit} on {}", filtered.len(), m.symbol));
+    }
+    output.push_str(".\n\n");
+    output.push_str("| Market | Side | Price | Size | Placed | Order ID | Cloid | Placed By |\n");
+    output.push_str("|--------|------|-------|------|--------|----------|-------|-----------|\n");
+
+    for o in &filtered {
+        let side = match o.side {
+            hypersdk::hypercore::Side::Bid => "Buy",
+            hypersdk::hypercore::Side::Ask => "Sell",
+        };
+        let flag = if orphaned.contains(&o.oid) {
+            " (orphaned trigger)"
+        } else {
+            ""
+        };
+        // Orders this server placed name the tool and intent behind them.
+        let record = o.cloid.and_then(|c| state.cloids.lookup(&c));
+        output.push_str(&format!(
+            "| {} | {} | ${} | {} | {} | {}{flag} | {} | {} |\n",
+            o.coin,
+            side,
+            o.limit_px,
+            o.sz,
+            fmt_recent(o.timestamp),
+            o.oid,
+            o.cloid
+                .map(|c| format!("{c:#x}"))
+                .unwrap_or_else(|| "—".into()),
+            record
+                .map(|r| format!("{} (intent #{})", r.purpose, r.intent.id()))
+                .unwrap_or_else(|| "—".into()),
+        ));
+    }
+
+    let shown: Vec<&&BasicOrder> = filtered
+        .iter()
+        .filter(|o| orphaned.contains(&o.oid))
+        .collect();
+    if !shown.is_empty() {
+        let unguarded = shown.iter().filter(|o| !o.reduce_only).count();
+        output.push_str(&format!(
+            "\n**Orphaned triggers:** {} stop/take-profit order(s) on coins with no open position. ",
+            shown.len()
+        ));
+        if unguarded > 0 {
+            output.push_str(&format!(
+                "{unguarded} of them are not reduce-only and would open a new position when \
+                 triggered. "
+            ));
+        }
+        output.push_str(
+            "Cancel them unless they are intended entries, or set \
+             HYPERLIQUID_AUTO_CANCEL_ORPHAN_STOPS=true to cancel them when a position closes.\n",
+        );
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+const MAX_TRADE_HISTORY: usize = 200;
+/// `userFills` returns at most the 2000 most recent fills.
+const MAX_CSV_TRADES: usize = 2000;
+const MAX_CSV_CHARS: usize = 400_000;
+/// Trades annotated by `with_market_context`, newest first.
+const MAX_CONTEXT_TRADES: usize = 50;
+
+/// Quote a CSV field when it holds a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Fills as CSV with a header row: RFC 3339 UTC times and the exchange's own
+/// decimal strings, with no currency symbols or placeholders.
+fn trades_csv(fills: &[&hypersdk::hypercore::types::Fill]) -> String {
+    let mut csv = String::from(
+        "time,coin,side,price,size,direction,fee,fee_token,closed_pnl,order_id,trade_id,hash\n",
+    );
+    for f in fills {
+        let time = chrono::DateTime::from_timestamp_millis(f.time as i64)
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_default();
+        let side = match f.side {
+            hypersdk::hypercore::Side::Bid => "buy",
+            hypersdk::hypercore::Side::Ask => "sell",
+        };
+        let fields = [
+            time,
+            csv_field(&f.coin),
+            side.to_string(),
+            f.px.to_string(),
+            f.sz.to_string(),
+            csv_field(&f.dir),
+            f.fee.to_string(),
+            csv_field(&f.fee_token),
+            f.closed_pnl.to_string(),
+            f.oid.to_string(),
+            f.tid.to_string(),
+            csv_field(&f.hash),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub async fn get_trade_history(
+    state: &ServerState,
+    req: GetTradeHistoryRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+
+    let fills = state
+        .api
+        .user_fills(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch trade history: {e}")))?;
+
+    let export_csv = req.export_csv.unwrap_or(false);
+    let max_limit = if export_csv {
+        MAX_CSV_TRADES
+    } else {
+        MAX_TRADE_HISTORY
+    };
+    let limit = req.limit.unwrap_or(50).min(max_limit);
+
+    let market = req
+        .coin
+        .as_deref()
+        .map(|c| state.resolve_market(c, None))
+        .transpose()?;
+    let filtered: Vec<_> = if let Some(ref market) = market {
+        fills
+            .iter()
+            .filter(|f| f.coin == market.coin)
+            .take(limit)
+            .collect()
+    } else {
+        fills.iter().take(limit).collect()
+    };
+
+    let notice = state.account_notices();
+    if filtered.is_empty() {
+        let msg = match &market {
+            Some(m) => format!("{notice}No recent trades for {}.", m.describe()),
+            None => format!("{notice}No recent trades."),
+        };
+        return Ok(CallToolResult::success(vec![Content::text(msg)]));
+    }
+
+    if export_csv {
+        let csv = trades_csv(&filtered);
+        if csv.len() > MAX_CSV_CHARS {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "{} trades make {} KB of CSV, over the {} KB output limit. \
+                 Narrow the range with a lower limit or a coin filter.",
+                filtered.len(),
+                csv.len() / 1000,
+                MAX_CSV_CHARS / 1000
+            ))]));
+        }
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "{notice}{} trades, newest first.\n\n```csv\n{csv}```\n",
+            filtered.len()
+        ))]));
+    }
+
+    let mut output = match &market {
+        Some(m) => format!(
+            "{notice}## Recent Trades: {} ({})\n\n",
+            m.describe(),
+            filtered.len()
+        ),
+        None => format!("{notice}## Recent Trades ({})\n\n", filtered.len()),
+    };
+    let with_context = req.with_market_context.unwrap_or(false);
+    let mut closes = Vec::new();
+    let mut unpriced = 0;
+    let mut context_errors = Vec::new();
+    if with_context {
+        let annotated = &filtered[..filtered.len().min(MAX_CONTEXT_TRADES)];
+        let mut times_by_coin: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        for f in annotated {
+            times_by_coin.entry(&f.coin).or_default().push(f.time);
+        }
+        let fetched = futures::future::join_all(
+            times_by_coin
+                .iter()
+                .map(|(coin, times)| market::get_minute_candles_at(state, coin, times)),
+        )
+        .await;
+        let mut candles_by_coin = BTreeMap::new();
+        for (coin, candles) in times_by_coin.keys().zip(fetched) {
+            match candles {
+                Ok(candles) => {
+                    candles_by_coin.insert(*coin, candles);
+                }
+                Err(e) => context_errors.push(e.message.to_string()),
+            }
+        }
+        closes = annotated
+            .iter()
+            .map(|f| {
+                let candles = candles_by_coin.get(f.coin.as_str())?;
+                market::nearest_minute_close(candles, f.time)
+            })
+            .collect();
+        unpriced = annotated
+            .iter()
+            .zip(&closes)
+            .filter(|(f, close)| close.is_none() && candles_by_coin.contains_key(f.coin.as_str()))
+            .count();
+        output.push_str(
+            "| Time | Market | Side | Price | Size | Direction | Fee | Closed PnL | 1m Close | vs Market |\n",
+        );
+        output.push_str(
+            "|------|--------|------|-------|------|-----------|-----|------------|----------|-----------|\n",
+        );
+    } else {
+        output.push_str("| Time | Market | Side | Price | Size | Direction | Fee | Closed PnL |\n");
+        output.push_str("|------|--------|------|-------|------|-----------|-----|------------|\n");
+    }
+
+    for (i, f) in filtered.iter().enumerate() {
+        let time = fmt_time_secs(f.time);
+        let side = match f.side {
+            hypersdk::hypercore::Side::Bid => "Buy",
+            hypersdk::hypercore::Side::Ask => "Sell",
+        };
+        let pnl_str = if f.closed_pnl.is_zero() {
+            "—".into()
+        } else {
+            format!("${}", f.closed_pnl)
+        };
+        output.push_str(&format!(
+            "| {} | {} | {} | ${} | {} | {} | ${} | {} |",
+            time, f.coin, side, f.px, f.sz, f.dir, f.fee, pnl_str,
+        ));
+        if with_context {
+            let is_buy = matches!(f.side, hypersdk::hypercore::Side::Bid);
+            match closes.get(i).copied().flatten() {
+                Some(close) => output.push_str(&format!(
+                    " {} | {} |",
+                    fmt_price(close),
+                    fmt_fill_edge(is_buy, f.px, close)
+                )),
+                None => output.push_str(" — | — |"),
+            }
+        }
+        output.push('\n');
+    }
+
+    if with_context {
+        output.push_str(
+            "\n*vs Market compares each fill with the nearest 1m candle close: \
+             positive means the fill beat it (bought below or sold above).*\n",
+        );
+        if unpriced > 0 {
+            output.push_str(&format!(
+                "\n*No 1m candle closed within a minute of {unpriced} of these fills; \
+                 1m candles only reach back about 3.5 days.*\n"
+            ));
+        }
+        if filtered.len() > MAX_CONTEXT_TRADES {
+            output.push_str(&format!(
+                "\n*Market context skipped beyond the newest {MAX_CONTEXT_TRADES} of {} trades.*\n",
+                filtered.len()
+            ));
+        }
+        for error in context_errors {
+            output.push_str(&format!("\n*{error}*\n"));
+        }
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// How far a fill beat the market close, in basis points: positive when a buy
+/// filled below it or a sell above it.
+fn fmt_fill_edge(is_buy: bool, px: Decimal, close: Decimal) -> String {
+    if close.is_zero() {
+        return "—".into();
+    }
+    let edge = if is_buy { close - px } else { px - close };
+    format!(
+        "{:+.1} bps",
+        (edge / close * Decimal::from(10_000)).round_dp(1)
+    )
+}
+
+pub async fn get_recent_fills(
+    state: &ServerState,
+    req: GetRecentFillsRequest,
+) -> Result<CallToolResult, ErrorData> {
+    if !state.cache.live {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Recent fills are buffered from the WebSocket feed. Set REALTIME_ENABLED=true and \
+             restart, or use get_trade_history.",
+        )]));
+    }
+    let limit = req.limit.unwrap_or(20).clamp(1, MAX_RECENT_FILLS);
+    let market = req
+        .coin
+        .as_deref()
+        .map(|c| state.resolve_market(c, None))
+        .transpose()?;
+
+    let recent = state.cache.recent_fills.read().await;
+    let filtered: Vec<_> = recent
+        .fills
+        .iter()
+        .rev()
+        .filter(|f| market.as_ref().is_none_or(|m| f.fill.coin == m.coin))
+        .take(limit)
+        .collect();
+
+    let mut output = String::new();
+    if !recent.backfilled {
+        output.push_str(
+            "*The startup backfill hasn't completed; fills from before the feed connected may be missing.*\n\n",
+        );
+    }
+    if filtered.is_empty() {
+        output.push_str(&match &market {
+            Some(m) => format!("No recent fills for {}.", m.describe()),
+            None => "No recent fills.".into(),
+        });
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    output.push_str(&match &market {
+        Some(m) => format!("## Recent Fills: {} ({})\n\n", m.describe(), filtered.len()),
+        None => format!("## Recent Fills ({})\n\n", filtered.len()),
+    });
+    output.push_str(
+        "| Time | Market | Side | Price | Size | Direction | Fee | Closed PnL | Source |\n",
+    );
+    output.push_str(
+        "|------|--------|------|-------|------|-----------|-----|------------|--------|\n",
+    );
+    for RecentFill {
+        fill: f,
+        source,
+        received_at,
+    } in filtered
+    {
+        let pnl_str = if f.closed_pnl.is_zero() {
+            "—".into()
+        } else {
+            format!("${}", f.closed_pnl)
+        };
+        output.push_str(&format!(
+            "| {} | {} | {} | ${} | {} | {} | ${} | {} | {} |\n",
+            fmt_recent(f.time),
+            f.coin,
+            side_label(f.side),
+            f.px,
+            f.sz,
+            f.dir,
+            f.fee,
+            pnl_str,
+            match source {
+                // Delivery delay shows how far behind the feed is running.
+                FillSource::WebSocket => format!(
+                    "live (+{:.1}s)",
+                    received_at.saturating_sub(f.time) as f64 / 1000.0
+                ),
+                FillSource::Rest => "backfill".into(),
+            },
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn get_order_status(
+    state: &ServerState,
+    req: GetOrderStatusRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    let id = match (req.order_id, req.cloid.as_deref()) {
+        (Some(oid), None) => Either::Left(oid),
+        (None, Some(cloid)) => Either::Right(
+            cloid
+                .parse::<Cloid>()
+                .map_err(|e| mcp_err(&format!("Invalid cloid '{cloid}': {e}")))?,
+        ),
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Provide exactly one of order_id or cloid.",
+            )]));
+        }
+    };
+
+    let update = state
+        .api
+        .order_status(address, id)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch order status: {e}")))?;
+
+    let update = match (update, id) {
+        (Some(u), _) => u,
+        (None, Either::Left(oid)) => {
             return Ok(CallToolResult::error(vec![Content::text(format!(
-                "Order {} not found.",
-                req.order_id
+                "Order {oid} not found."
+            ))]));
+        }
+        // A submission still in flight is not an error: the exchange has
+        // simply not answered yet.
+        (None, Either::Right(cloid)) => {
+            if state
+                .cloids
+                .lookup(&cloid)
+                .is_some_and(|r| r.outcome == Submission::Pending)
+            {
+                return Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Order {cloid} was submitted and the exchange has not answered yet. \
+                     Check again shortly; don't resend it."
+                ))]));
+            }
+            let issued = state
+                .cloids
+                .describe(&cloid)
+                .map(|issued| format!(" This server issued that cloid as {issued}."))
+                .unwrap_or_default();
+            // A submission that got no answer never reached the book, so an
+            // identical order may be placed again.
+            let released = if state.resolve_unanswered(cloid, Submission::NotSent) {
+                " It never reached the exchange, so an identical order can be placed again."
+            } else {
+                ""
+            };
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Order {cloid} not found.{issued}{released}"
             ))]));
         }
     };
+    if let Either::Right(cloid) = id {
+        state.resolve_unanswered(
+            cloid,
+            Submission::from_order_status(update.status, update.order.oid),
+        );
+    }
 
     let o = &update.order;
     let side = match o.side {
         hypersdk::hypercore::Side::Bid => "Buy",
         hypersdk::hypercore::Side::Ask => "Sell",
     };
-    let time = chrono_from_ms(update.status_timestamp);
+    let time = fmt_time_secs(update.status_timestamp);
 
     let output = format!(
         "## Order {} — {}\n\n\
@@ -330,11 +1437,663 @@ pub async fn get_order_status(
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-async fn get_cached_clearinghouse(
+pub async fn watch_order(
+    state: &ServerState,
+    req: WatchOrderRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    if !state.cache.live {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Order watching needs the WebSocket feed. Set REALTIME_ENABLED=true and restart.",
+        )]));
+    }
+
+    let id = match (req.order_id, req.cloid.as_deref()) {
+        (Some(oid), None) => Either::Left(oid),
+        (None, Some(cloid)) => Either::Right(
+            cloid
+                .parse::<Cloid>()
+                .map_err(|e| mcp_err(&format!("Invalid cloid '{cloid}': {e}")))?,
+        ),
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Provide exactly one of order_id or cloid.",
+            )]));
+        }
+    };
+
+    let issued = match id {
+        Either::Right(cloid) => state.cloids.describe(&cloid),
+        Either::Left(_) => None,
+    };
+    let Some(update) = state
+        .api
+        .order_status(address, id)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch order status: {e}")))?
+    else {
+        // A cloid this server issued but the exchange never saw, e.g. a
+        // rejected or unsent attempt, is worth explaining.
+        return match issued {
+            Some(issued) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Order not found. This server issued that cloid as {issued}."
+            ))])),
+            None => Err(mcp_err("Order not found.")),
+        };
+    };
+    let o = &update.order;
+    let issued = issued.or_else(|| o.cloid.and_then(|c| state.cloids.describe(&c)));
+
+    let mut watched = WatchedOrder {
+        oid: o.oid,
+        coin: o.coin.clone(),
+        side: o.side,
+        limit_px: o.limit_px,
+        orig_sz: o.orig_sz,
+        status: OrderStatus::Open,
+        fills: Vec::new(),
+        finished_at: None,
+    };
+    watched.set_status(update.status);
+
+    // Seed with fills that happened before the order was registered, from
+    // the recent-fills buffer when it reaches back far enough.
+    let buffered = state
+        .cache
+        .recent_fills
+        .read()
+        .await
+        .covering(o.timestamp)
+        .map(|fills| {
+            fills
+                .into_iter()
+                .filter(|f| f.oid == o.oid)
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+    let fills = match buffered {
+        Some(fills) => fills,
+        None => state
+            .api
+            .user_fills(address)
+            .await
+            .map_err(|e| mcp_err(&format!("Failed to fetch fills: {e}")))?,
+    };
+    for fill in fills.iter().filter(|f| f.oid == o.oid) {
+        watched.record_fill(fill);
+    }
+
+    let progress = watch_progress(&watched);
+    state.cache.prune_watched_orders().await;
+    state
+        .cache
+        .watched_orders
+        .write()
+        .await
+        .insert(o.oid, watched);
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Watching order {} ({} {}): {progress}.{} Use get_watched_orders to check fill progress.",
+        o.oid,
+        side_label(o.side),
+        o.coin,
+        issued
+            .map(|issued| format!(" Issued by this server: {issued}."))
+            .unwrap_or_default(),
+    ))]))
+}
+
+pub async fn get_watched_orders(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    state.cache.prune_watched_orders().await;
+    let watched = state.cache.watched_orders.read().await;
+    if watched.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "No watched orders. Use watch_order to register one.",
+        )]));
+    }
+
+    let mut orders: Vec<_> = watched.values().collect();
+    orders.sort_by_key(|o| std::cmp::Reverse(o.oid));
+
+    let mut output = format!("## Watched Orders ({})\n\n", orders.len());
+    output
+        .push_str("| Order ID | Market | Side | Limit | Size | Progress | Status | Last Fill |\n");
+    output
+        .push_str("|----------|--------|------|-------|------|----------|--------|-----------|\n");
+    for o in orders {
+        let last_fill = o
+            .fills
+            .iter()
+            .map(|f| f.time)
+            .max()
+            .map(fmt_recent)
+            .unwrap_or_else(|| "—".into());
+        output.push_str(&format!(
+            "| {} | {} | {} | ${} | {} | {} | {} | {} |\n",
+            o.oid,
+            o.coin,
+            side_label(o.side),
+            o.limit_px,
+            o.orig_sz,
+            watch_progress(o),
+            o.status,
+            last_fill,
+        ));
+    }
+    output.push_str("\nFinished orders are dropped an hour after they complete or cancel.\n");
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn get_events(
+    state: &ServerState,
+    req: GetEventsRequest,
+) -> Result<CallToolResult, ErrorData> {
+    if !state.cache.live {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Account events come from the WebSocket feed. Set REALTIME_ENABLED=true and restart.",
+        )]));
+    }
+    let limit = req.limit.unwrap_or(50).clamp(1, 200) as usize;
+    let events = state.cache.events.recent(limit);
+    if events.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "No account events since the server started. Fills of resting orders, \
+             triggered stops and liquidations show up here.",
+        )]));
+    }
+
+    let mut output = format!("## Account Events ({})\n\n", events.len());
+    output.push_str("| Time | Type | Event | New |\n");
+    output.push_str("|------|------|-------|-----|\n");
+    for (event, unread) in &events {
+        output.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            fmt_recent(event.time),
+            event.kind.label(),
+            event.text,
+            if *unread { "yes" } else { "" },
+        ));
+    }
+    output.push_str(
+        "\nFills the account took as a taker are left out unless a trigger order sent them; \
+         the response to the order already reported them.\n",
+    );
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+fn side_label(side: hypersdk::hypercore::Side) -> &'static str {
+    match side {
+        hypersdk::hypercore::Side::Bid => "Buy",
+        hypersdk::hypercore::Side::Ask => "Sell",
+    }
+}
+
+/// e.g. "38% filled, VWAP 3012.4".
+fn watch_progress(order: &WatchedOrder) -> String {
+    let filled = order.filled_sz();
+    if filled.is_zero() || order.orig_sz.is_zero() {
+        return "0% filled".into();
+    }
+    let pct = (filled / order.orig_sz * Decimal::ONE_HUNDRED).round_dp(0);
+    match order.vwap() {
+        Some(vwap) => format!("{pct}% filled, VWAP {}", round_sig(vwap, 6).normalize()),
+        None => format!("{pct}% filled"),
+    }
+}
+
+/// Staking withdrawals unlock this long after they are initiated.
+const UNSTAKING_QUEUE_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+pub async fn get_staking_yield(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    let user = format!("{:#x}", address);
+
+    let summary = state
+        .raw_info_request(json!({ "type": "delegatorSummary", "user": user }))
+        .await?;
+    let rewards = state
+        .raw_info_request(json!({ "type": "delegatorRewards", "user": user }))
+        .await?;
+    let history = state
+        .raw_info_request(json!({ "type": "delegatorHistory", "user": user }))
+        .await?;
+
+    let num = |v: &serde_json::Value, key: &str| {
+        v.get(key)
+            .and_then(|x| x.as_str())
+            .and_then(|x| x.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+
+    let delegated = num(&summary, "delegated");
+    let undelegated = num(&summary, "undelegated");
+    let pending = num(&summary, "totalPendingWithdrawal");
+
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let rewards_since = |days: u64| -> f64 {
+        let cutoff = now.saturating_sub(days * DAY_MS);
+        rewards
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter(|r| r.get("time").and_then(|t| t.as_u64()).unwrap_or(0) >= cutoff)
+                    .map(|r| num(r, "totalAmount"))
+                    .sum()
+            })
+            .unwrap_or(0.0)
+    };
+    let rewards_7d = rewards_since(7);
+    let rewards_30d = rewards_since(30);
+
+    let apr = |earned: f64, days: f64| {
+        if delegated > 0.0 {
+            format!("{:.2}%", earned / delegated * 365.0 / days * 100.0)
+        } else {
+            "—".to_string()
+        }
+    };
+
+    let mut output = "## HYPE Staking\n\n".to_string();
+    output.push_str("| Metric | Value |\n");
+    output.push_str("|--------|-------|\n");
+    output.push_str(&format!("| Staked (delegated) | {delegated:.4} HYPE |\n"));
+    output.push_str(&format!(
+        "| Staking balance (undelegated) | {undelegated:.4} HYPE |\n"
+    ));
+    output.push_str(&format!("| Pending withdrawals | {pending:.4} HYPE |\n"));
+    output.push_str(&format!("| Rewards (7d) | {rewards_7d:.6} HYPE |\n"));
+    output.push_str(&format!("| Rewards (30d) | {rewards_30d:.6} HYPE |\n"));
+    output.push_str(&format!(
+        "| Implied APR (7d) | {} |\n",
+        apr(rewards_7d, 7.0)
+    ));
+    output.push_str(&format!(
+        "| Implied APR (30d) | {} |\n",
+        apr(rewards_30d, 30.0)
+    ));
+
+    // Withdrawals initiated within the queue window that haven't finalized yet.
+    let cutoff = now.saturating_sub(UNSTAKING_QUEUE_MS);
+    let withdrawals: Vec<(u64, f64)> = history
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|h| {
+                    let time = h.get("time")?.as_u64()?;
+                    let w = h.get("delta")?.get("withdrawal")?;
+                    (w.get("phase")?.as_str()? == "initiated" && time >= cutoff)
+                        .then(|| (time, num(w, "amount")))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !withdrawals.is_empty() {
+        output.push_str("\n## Pending Withdrawals\n\n");
+        output.push_str("| Amount | Initiated | Unlocks |\n");
+        output.push_str("|--------|-----------|---------|\n");
+        for (time, amount) in &withdrawals {
+            output.push_str(&format!(
+                "| {amount:.4} HYPE | {} | {} |\n",
+                fmt_time(*time),
+                fmt_time(time + UNSTAKING_QUEUE_MS),
+            ));
+        }
+    }
+
+    if delegated == 0.0 && undelegated == 0.0 && pending == 0.0 {
+        output.push_str("\nNo HYPE staked on this account.\n");
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn get_deposit_status(
+    state: &ServerState,
+    req: GetDepositStatusRequest,
+) -> Result<CallToolResult, ErrorData> {
+    // Deposits are credited to the address that sent them, never the vault.
+    let address = state.require_address()?;
+    let hours = req.lookback_hours.unwrap_or(24).clamp(1, 720);
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let start = now.saturating_sub(hours * 60 * 60 * 1000);
+
+    let updates = state
+        .raw_info_request(json!({
+            "type": "userNonFundingLedgerUpdates",
+            "user": format!("{:#x}", address),
+            "startTime": start,
+        }))
+        .await?;
+
+    let deposits: Vec<(u64, String, String)> = updates
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|u| {
+                    let delta = u.get("delta")?;
+                    if delta.get("type")?.as_str()? != "deposit" {
+                        return None;
+                    }
+                    Some((
+                        u.get("time")?.as_u64()?,
+                        delta.get("usdc")?.as_str()?.to_string(),
+                        u.get("hash")
+                            .and_then(|h| h.as_str())
+                            .unwrap_or("?")
+                            .to_string(),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut output = "## Deposits\n\n".to_string();
+    output.push_str(&format!(
+        "Bridge address ({}): `{}`\n\n",
+        if state.chain == hypersdk::hypercore::Chain::Mainnet {
+            "Arbitrum"
+        } else {
+            "Arbitrum Sepolia"
+        },
+        crate::hyperliquid::bridge_address(state.chain)
+    ));
+    output.push_str(&format!(
+        "Send native USDC from `{:#x}` on Arbitrum to the bridge address; \
+         deposits below 5 USDC are lost. Deposits are normally credited within a minute.\n\n",
+        address
+    ));
+
+    if deposits.is_empty() {
+        output.push_str(&format!(
+            "No deposits credited in the last {hours} hours. A deposit that is still \
+             confirming on Arbitrum will not appear until the bridge credits it.\n"
+        ));
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    output.push_str(&format!("### Last {hours} Hours ({})\n\n", deposits.len()));
+    output.push_str("| Time | Amount | Status | Hash |\n");
+    output.push_str("|------|--------|--------|------|\n");
+    for (time, usdc, hash) in deposits.iter().rev() {
+        output.push_str(&format!(
+            "| {} | {usdc} USDC | Credited | `{hash}` |\n",
+            fmt_time_secs(*time)
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Named snapshots kept per profile; the oldest is dropped beyond this.
+const MAX_SNAPSHOTS: usize = 20;
+
+/// Point-in-time account state stored by `snapshot_account`.
+#[derive(Debug, Clone)]
+pub struct AccountSnapshot {
+    pub taken_at: u64,
+    pub equity: Decimal,
+    pub withdrawable: Decimal,
+    /// Coin to (signed size, entry price).
+    pub positions: BTreeMap<String, (Decimal, Option<Decimal>)>,
+    /// Spot token to total balance.
+    pub balances: BTreeMap<String, Decimal>,
+    pub open_orders: BTreeMap<u64, String>,
+}
+
+async fn take_snapshot(state: &ServerState) -> Result<AccountSnapshot, ErrorData> {
+    let address = state.query_address()?;
+    let user_state = get_cached_clearinghouse(state, address).await?;
+    let orders = get_cached_open_orders(state, address).await?;
+    let balances = state
+        .api
+        .user_balances(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch spot balances: {e}")))?;
+
+    Ok(AccountSnapshot {
+        taken_at: chrono::Utc::now().timestamp_millis() as u64,
+        equity: user_state.margin_summary.account_value,
+        withdrawable: user_state.withdrawable,
+        positions: user_state
+            .asset_positions
+            .iter()
+            .filter(|p| !p.position.szi.is_zero())
+            .map(|p| {
+                (
+                    p.position.coin.clone(),
+                    (p.position.szi, p.position.entry_px),
+                )
+            })
+            .collect(),
+        balances: balances
+            .iter()
+            .filter(|b| !b.total.is_zero())
+            .map(|b| (b.coin.clone(), b.total))
+            .collect(),
+        open_orders: orders
+            .iter()
+            .map(|o| {
+                (
+                    o.oid,
+                    format!(
+                        "{} {} {} @ {}",
+                        side_label(o.side),
+                        o.sz,
+                        o.coin,
+                        o.limit_px
+                    ),
+                )
+            })
+            .collect(),
+    })
+}
+
+pub async fn snapshot_account(
+    state: &ServerState,
+    req: SnapshotAccountRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Snapshot name must not be empty",
+        )]));
+    }
+    let snapshot = take_snapshot(state).await?;
+
+    let output = format!(
+        "Saved snapshot \"{name}\" at {}: equity ${:.2}, {} positions, {} spot balances, \
+         {} open orders. Compare later with diff_account_snapshot. Snapshots are kept in \
+         memory and lost on restart.",
+        fmt_time_secs(snapshot.taken_at),
+        snapshot.equity,
+        snapshot.positions.len(),
+        snapshot.balances.len(),
+        snapshot.open_orders.len(),
+    );
+
+    let mut snapshots = state.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+    if !snapshots.contains_key(name)
+        && snapshots.len() >= MAX_SNAPSHOTS
+        && let Some(oldest) = snapshots
+            .iter()
+            .min_by_key(|(_, s)| s.taken_at)
+            .map(|(k, _)| k.clone())
+    {
+        snapshots.remove(&oldest);
+    }
+    snapshots.insert(name.to_string(), snapshot);
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn diff_account_snapshot(
+    state: &ServerState,
+    req: DiffAccountSnapshotRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let name = req.name.trim();
+    let before = {
+        let snapshots = state.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        match snapshots.get(name) {
+            Some(s) => s.clone(),
+            None => {
+                let mut known: Vec<_> = snapshots.keys().map(|k| format!("\"{k}\"")).collect();
+                known.sort();
+                let hint = if known.is_empty() {
+                    "No snapshots have been taken yet; use snapshot_account first.".to_string()
+                } else {
+                    format!("Available: {}.", known.join(", "))
+                };
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "No snapshot named \"{name}\". {hint}"
+                ))]));
+            }
+        }
+    };
+    let after = take_snapshot(state).await?;
+
+    let address = state.query_address()?;
+    let fills = state
+        .raw_info_request(json!({
+            "type": "userFillsByTime",
+            "user": format!("{:#x}", address),
+            "startTime": before.taken_at,
+            "endTime": after.taken_at,
+        }))
+        .await?;
+    let fills = fills.as_array().cloned().unwrap_or_default();
+    let sum = |field: &str| -> Decimal {
+        fills
+            .iter()
+            .filter_map(|f| f.get(field)?.as_str()?.parse::<Decimal>().ok())
+            .sum()
+    };
+    let fees = sum("fee");
+    let closed_pnl = sum("closedPnl");
+
+    let elapsed_min = (after.taken_at - before.taken_at) / 60_000;
+    let mut output = format!(
+        "## Changes Since \"{name}\" ({}, {}h {}m ago)\n\n",
+        fmt_time_secs(before.taken_at),
+        elapsed_min / 60,
+        elapsed_min % 60
+    );
+    output.push_str("| Metric | Then | Now | Change |\n");
+    output.push_str("|--------|------|-----|--------|\n");
+    output.push_str(&format!(
+        "| Equity | ${:.2} | ${:.2} | {:+.2} |\n",
+        before.equity,
+        after.equity,
+        after.equity - before.equity
+    ));
+    output.push_str(&format!(
+        "| Withdrawable | ${:.2} | ${:.2} | {:+.2} |\n",
+        before.withdrawable,
+        after.withdrawable,
+        after.withdrawable - before.withdrawable
+    ));
+    output.push_str(&format!(
+        "| Fills | | | {} |\n| Fees Paid | | | ${fees:.2} |\n| Realized PnL | | | {closed_pnl:+.2} |\n",
+        fills.len()
+    ));
+
+    let mut changes = Vec::new();
+    for (coin, (szi, entry)) in &after.positions {
+        let entry = entry.map(|e| format!(" @ {e}")).unwrap_or_default();
+        match before.positions.get(coin) {
+            None => changes.push(format!("| {coin} | Opened | — | {szi}{entry} |")),
+            Some((old, _)) if old.is_sign_positive() != szi.is_sign_positive() => {
+                changes.push(format!("| {coin} | Flipped | {old} | {szi}{entry} |"))
+            }
+            Some((old, _)) if old != szi => {
+                let kind = if szi.abs() > old.abs() {
+                    "Increased"
+                } else {
+                    "Reduced"
+                };
+                changes.push(format!("| {coin} | {kind} | {old} | {szi}{entry} |"));
+            }
+            Some(_) => {}
+        }
+    }
+    for (coin, (szi, _)) in &before.positions {
+        if !after.positions.contains_key(coin) {
+            changes.push(format!("| {coin} | Closed | {szi} | — |"));
+        }
+    }
+    output.push_str("\n### Positions\n\n");
+    if changes.is_empty() {
+        output.push_str("No position changes.\n");
+    } else {
+        output.push_str("| Coin | Change | Size Then | Size Now |\n");
+        output.push_str("|------|--------|-----------|----------|\n");
+        output.push_str(&changes.join("\n"));
+        output.push('\n');
+    }
+
+    let mut balance_changes = Vec::new();
+    let tokens: std::collections::BTreeSet<_> = before
+        .balances
+        .keys()
+        .chain(after.balances.keys())
+        .collect();
+    for token in tokens {
+        let old = before.balances.get(token).copied().unwrap_or_default();
+        let new = after.balances.get(token).copied().unwrap_or_default();
+        if old != new {
+            balance_changes.push(format!("| {token} | {old} | {new} | {:+} |", new - old));
+        }
+    }
+    if !balance_changes.is_empty() {
+        output.push_str("\n### Spot Balances\n\n");
+        output.push_str("| Token | Then | Now | Change |\n");
+        output.push_str("|-------|------|-----|--------|\n");
+        output.push_str(&balance_changes.join("\n"));
+        output.push('\n');
+    }
+
+    let gone: Vec<_> = before
+        .open_orders
+        .iter()
+        .filter(|(oid, _)| !after.open_orders.contains_key(oid))
+        .collect();
+    let placed: Vec<_> = after
+        .open_orders
+        .iter()
+        .filter(|(oid, _)| !before.open_orders.contains_key(oid))
+        .collect();
+    output.push_str(&format!(
+        "\n### Open Orders\n\n{} then, {} now.\n",
+        before.open_orders.len(),
+        after.open_orders.len()
+    ));
+    for (oid, desc) in &gone {
+        output.push_str(&format!("- Filled or cancelled: {desc} (oid {oid})\n"));
+    }
+    for (oid, desc) in &placed {
+        output.push_str(&format!("- New: {desc} (oid {oid})\n"));
+    }
+
+    if fills.len() >= 2000 {
+        output.push_str(
+            "\nMore than 2000 fills since the snapshot; fees and realized PnL cover the first 2000 only.\n",
+        );
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub(crate) async fn get_cached_clearinghouse(
     state: &ServerState,
     address: hypersdk::Address,
 ) -> Result<hypersdk::hypercore::ClearinghouseState, ErrorData> {
-    {
+    // The cache holds the queried account only; personal-scope lookups in
+    // vault mode go straight to the API.
+    let cacheable = state.query_address().ok() == Some(address);
+    if cacheable {
         let guard = state.cache.clearinghouse_cache.read().await;
         if let Some(cached) = guard.as_ref() {
             if cached.is_fresh(POSITIONS_TTL) {
@@ -345,20 +2104,63 @@ async fn get_cached_clearinghouse(
     }
 
     let user_state = state
-        .client
-        .clearinghouse_state(address, None)
+        .api
+        .clearinghouse_state(address)
         .await
         .map_err(|e| mcp_err(&format!("Failed to fetch positions: {e}")))?;
 
-    *state.cache.clearinghouse_cache.write().await = Some(CachedValue::new(user_state.clone()));
+    if cacheable {
+        *state.cache.clearinghouse_cache.write().await = Some(CachedValue::new(user_state.clone()));
+    }
     Ok(user_state)
 }
 
-async fn get_cached_open_orders(
+/// Account value of the queried account, for the equity footer on
+/// destructive tools. The first value seen is the session baseline. `None`
+/// when the read fails or takes longer than `EQUITY_FOOTER_TIMEOUT`.
+pub(crate) async fn equity_snapshot(state: &ServerState) -> Option<Decimal> {
+    let address = state.query_address().ok()?;
+    let equity = tokio::time::timeout(
+        EQUITY_FOOTER_TIMEOUT,
+        get_cached_clearinghouse(state, address),
+    )
+    .await
+    .ok()?
+    .ok()?
+    .margin_summary
+    .account_value;
+    let _ = state.cache.session_equity.set(equity);
+    Some(equity)
+}
+
+/// "> **Equity:** $10,412.00 → $10,396.00 (-$16.00 this action, -$88.00
+/// this session)", re-reading the account after the action.
+pub(crate) async fn equity_footer(state: &ServerState, before: Decimal) -> Option<String> {
+    state.cache.invalidate_user_data().await;
+    let now = equity_snapshot(state).await?;
+    let session = state.cache.session_equity.get().copied().unwrap_or(before);
+    let change = |delta: Decimal| {
+        if delta.is_sign_negative() && !delta.is_zero() {
+            fmt_usd(delta)
+        } else {
+            format!("+{}", fmt_usd(delta))
+        }
+    };
+    Some(format!(
+        "> **Equity:** {} → {} ({} this action, {} this session)",
+        fmt_usd(before),
+        fmt_usd(now),
+        change(now - before),
+        change(now - session)
+    ))
+}
+
+pub(crate) async fn get_cached_open_orders(
     state: &ServerState,
     address: hypersdk::Address,
 ) -> Result<Vec<hypersdk::hypercore::types::BasicOrder>, ErrorData> {
-    {
+    let cacheable = state.query_address().ok() == Some(address);
+    if cacheable {
         let guard = state.cache.open_orders_cache.read().await;
         if let Some(cached) = guard.as_ref() {
             if cached.is_fresh(OPEN_ORDERS_TTL) {
@@ -369,19 +2171,295 @@ async fn get_cached_open_orders(
     }
 
     let orders = state
-        .client
-        .open_orders(address, None)
+        .api
+        .open_orders(address)
         .await
         .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
 
-    *state.cache.open_orders_cache.write().await = Some(CachedValue::new(orders.clone()));
+    if cacheable {
+        *state.cache.open_orders_cache.write().await = Some(CachedValue::new(orders.clone()));
+    }
     Ok(orders)
 }
 
-fn chrono_from_ms(ms: u64) -> String {
-    let secs = (ms / 1000) as i64;
-    let nanos = ((ms % 1000) * 1_000_000) as u32;
-    chrono::DateTime::from_timestamp(secs, nanos)
-        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
-        .unwrap_or_else(|| ms.to_string())
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::exchange::ExchangeApi;
+    use crate::exchange::mock::{MockExchange, text};
+
+    fn mock_state() -> (Arc<MockExchange>, ServerState) {
+        let mock = Arc::new(MockExchange::new());
+        (Arc::clone(&mock), ServerState::mock(mock))
+    }
+
+    #[tokio::test]
+    async fn get_positions_renders_open_positions_and_summary() {
+        let (_, state) = mock_state();
+        let result = get_positions(&state).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## Open Positions (2)\n\n\
+             | Market | Side | Size | Entry Price | Mark Value | Unrealized PnL | ROE | Liq. Price | Leverage | Margin Used |\n\
+             |--------|------|------|-------------|------------|----------------|-----|------------|----------|-------------|\n\
+             | BTC | Long | 0.5 | $60,000 | $30,605.00 | $605.00 | +20.16% | $21,000.5 | 10x cross | $3,060.50 |\n\
+             | ETH | Short | 10 | $3,050 | $30,000.00 | $500.00 | +16.67% | $4,890.2 | 10x cross | $3,000.00 |\n\
+             \n\
+             ## Account Summary\n\n\
+             | Metric | Value |\n\
+             |--------|-------|\n\
+             | Account Value | $25,000.00 |\n\
+             | Total Position Notional | $60,605.00 |\n\
+             | Total Margin Used | $6,060.50 |\n\
+             | Available Margin | $18,939.50 |\n\
+             | Withdrawable | $18,939.50 |\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_positions_prints_plain_numbers_when_asked() {
+        crate::tools::format::set_plain_numbers_for_test(true);
+        let (_, state) = mock_state();
+        let result = get_positions(&state).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## Open Positions (2)\n\n\
+             | Market | Side | Size | Entry Price | Mark Value | Unrealized PnL | ROE | Liq. Price | Leverage | Margin Used |\n\
+             |--------|------|------|-------------|------------|----------------|-----|------------|----------|-------------|\n\
+             | BTC | Long | 0.5 | $60000 | $30605.00 | $605.00 | +20.16% | $21000.5 | 10x cross | $3060.50 |\n\
+             | ETH | Short | 10 | $3050 | $30000.00 | $500.00 | +16.67% | $4890.2 | 10x cross | $3000.00 |\n\
+             \n\
+             ## Account Summary\n\n\
+             | Metric | Value |\n\
+             |--------|-------|\n\
+             | Account Value | $25000.00 |\n\
+             | Total Position Notional | $60605.00 |\n\
+             | Total Margin Used | $6060.50 |\n\
+             | Available Margin | $18939.50 |\n\
+             | Withdrawable | $18939.50 |\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_balances_lists_nonzero_spot_balances() {
+        let (_, state) = mock_state();
+        let result = get_balances(&state).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## Perpetual Account\n\n\
+             | Metric | Value |\n\
+             |--------|-------|\n\
+             | Account Value | $25,000.00 |\n\
+             | Total Margin Used | $6,060.50 |\n\
+             | Available Margin | $18,939.50 |\n\
+             | Withdrawable | $18,939.50 |\n\
+             \n\
+             ## Spot Balances\n\n\
+             | Token | Total | Available | Held |\n\
+             |-------|-------|-----------|------|\n\
+             | USDC | 1,000 | 1,000 | 0 |\n\
+             | HYPE | 12.5 | 10 | 2.5 |\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_order_status_looks_up_order_id() {
+        let (mock, state) = mock_state();
+        let req = GetOrderStatusRequest {
+            order_id: Some(42),
+            cloid: None,
+            profile: None,
+        };
+        let result = get_order_status(&state, req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## Order 42 — Open\n\n\
+             | Field | Value |\n\
+             |-------|-------|\n\
+             | Coin | BTC |\n\
+             | Side | Buy |\n\
+             | Price | $59000.0 |\n\
+             | Remaining Size | 0.2 |\n\
+             | Original Size | 0.25 |\n\
+             | Status Time | 2025-10-09 08:53:20 UTC |\n"
+        );
+        assert_eq!(
+            mock.requests(),
+            vec![json!({
+                "type": "orderStatus",
+                "user": "0x00000000000000000000000000000000000000aa",
+                "oid": 42
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_order_status_reports_unknown_order() {
+        let (mock, state) = mock_state();
+        mock.respond("orderStatus", json!({ "status": "unknownOid" }));
+        let req = GetOrderStatusRequest {
+            order_id: Some(7),
+            cloid: None,
+            profile: None,
+        };
+        let result = get_order_status(&state, req).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(text(&result), "Order 7 not found.");
+    }
+
+    #[tokio::test]
+    async fn get_trade_history_annotates_fills_with_minute_closes() {
+        let (mock, state) = mock_state();
+        let fill = |coin: &str, side: &str, px: &str, time: u64| {
+            json!({
+                "coin": coin, "px": px, "sz": "0.1", "side": side, "time": time,
+                "startPosition": "0", "dir": "Open Long", "closedPnl": "0",
+                "hash": "0x0", "oid": 1, "crossed": true, "fee": "1.5", "tid": time,
+                "cloid": null, "feeToken": "USDC"
+            })
+        };
+        mock.respond(
+            "userFills",
+            json!([
+                fill("ETH", "A", "3000", 1_760_000_500_000),
+                fill("BTC", "A", "60100", 1_760_000_115_000),
+                fill("BTC", "B", "60010", 1_760_000_055_000),
+            ]),
+        );
+        let candle = |open: u64, close: &str| {
+            json!({
+                "t": open, "T": open + 59_999, "s": "BTC", "i": "1m", "o": close,
+                "h": close, "l": close, "c": close, "v": "1", "n": 1
+            })
+        };
+        mock.respond(
+            "candleSnapshot",
+            json!([
+                candle(1_760_000_000_000, "60000"),
+                candle(1_760_000_060_000, "60050")
+            ]),
+        );
+        let req = GetTradeHistoryRequest {
+            coin: None,
+            limit: None,
+            export_csv: None,
+            with_market_context: Some(true),
+            profile: None,
+        };
+        let result = get_trade_history(&state, req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## Recent Trades (3)\n\n\
+             | Time | Market | Side | Price | Size | Direction | Fee | Closed PnL | 1m Close | vs Market |\n\
+             |------|--------|------|-------|------|-----------|-----|------------|----------|-----------|\n\
+             | 2025-10-09 09:01:40 UTC | ETH | Sell | $3000 | 0.1 | Open Long | $1.5 | — | — | — |\n\
+             | 2025-10-09 08:55:15 UTC | BTC | Sell | $60100 | 0.1 | Open Long | $1.5 | — | $60,050 | +8.3 bps |\n\
+             | 2025-10-09 08:54:15 UTC | BTC | Buy | $60010 | 0.1 | Open Long | $1.5 | — | $60,000 | -1.7 bps |\n\
+             \n\
+             *vs Market compares each fill with the nearest 1m candle close: \
+             positive means the fill beat it (bought below or sold above).*\n\
+             \n\
+             *No 1m candle closed within a minute of 1 of these fills; \
+             1m candles only reach back about 3.5 days.*\n"
+        );
+        // One candle request per coin, not per fill.
+        let candle_requests = mock
+            .requests()
+            .into_iter()
+            .filter(|r| r["type"] == "candleSnapshot")
+            .count();
+        assert_eq!(candle_requests, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn equity_footer_is_left_out_when_the_account_read_stalls() {
+        let (mock, state) = mock_state();
+        let before = equity_snapshot(&state).await.unwrap();
+
+        mock.stall("clearinghouseState");
+        assert_eq!(equity_footer(&state, before).await, None);
+    }
+
+    #[tokio::test]
+    async fn equity_footer_reports_action_and_session_change() {
+        let (mock, state) = mock_state();
+        state
+            .cache
+            .session_equity
+            .set(Decimal::from(25_088))
+            .unwrap();
+        let before = equity_snapshot(&state).await.unwrap();
+        assert_eq!(before, Decimal::from(25_000));
+
+        let mut after = mock
+            .info(json!({ "type": "clearinghouseState" }))
+            .await
+            .unwrap();
+        after["marginSummary"]["accountValue"] = json!("24984.5");
+        mock.respond("clearinghouseState", after);
+        assert_eq!(
+            equity_footer(&state, before).await.as_deref(),
+            Some(
+                "> **Equity:** $25,000.00 → $24,984.50 (-$15.50 this action, -$103.50 this session)"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn orphaned_triggers_are_perp_stops_without_a_position() {
+        let (_, state) = mock_state();
+        let address = state.query_address().unwrap();
+        // The mock account is long BTC and short ETH.
+        let positions = state
+            .api
+            .clearinghouse_state(address)
+            .await
+            .unwrap()
+            .asset_positions;
+        let order = |oid: u64, coin: &str, order_type: &str| -> BasicOrder {
+            serde_json::from_value(json!({
+                "timestamp": 0,
+                "coin": coin,
+                "side": "A",
+                "limitPx": "100",
+                "sz": "1",
+                "oid": oid,
+                "origSz": "1",
+                "cloid": null,
+                "orderType": order_type,
+                "tif": null,
+                "reduceOnly": true,
+            }))
+            .unwrap()
+        };
+        let orders = vec![
+            order(1, "BTC", "Stop Market"),
+            order(2, "SOL", "Stop Market"),
+            order(3, "SOL", "Take Profit Limit"),
+            order(4, "SOL", "Limit"),
+            order(5, "@107", "Stop Limit"),
+            order(6, "PURR/USDC", "Take Profit Market"),
+            order(7, "ETH", "Take Profit Market"),
+        ];
+
+        let orphaned: Vec<u64> = orphaned_trigger_orders(&orders, &positions)
+            .iter()
+            .map(|o| o.oid)
+            .collect();
+        assert_eq!(orphaned, vec![2, 3]);
+
+        // A flat position entry leaves its triggers orphaned too.
+        let mut flat = positions.clone();
+        flat.retain(|p| p.position.coin == "BTC");
+        flat[0].position.szi = Decimal::ZERO;
+        let orphaned: Vec<u64> = orphaned_trigger_orders(&orders, &flat)
+            .iter()
+            .map(|o| o.oid)
+            .collect();
+        assert_eq!(orphaned, vec![1, 2, 3, 7]);
+    }
 }
@@ -0,0 +1,1868 @@
+use std::collections::BTreeMap;
+
+use hypersdk::Decimal;
+use hypersdk::hypercore::types::Candle;
+use rmcp::{model::*, schemars};
+use serde_json::json;
+
+use crate::state::{MarketKind, ServerState};
+use crate::tools::account::get_cached_clearinghouse;
+use crate::tools::format::{fmt_price, fmt_qty, fmt_usd, round_sig};
+use crate::tools::market::{get_cached_candles, get_minute_candles_at, nearest_minute_close};
+use crate::tools::timestamps::{fmt_ago, fmt_date, fmt_time_secs};
+use crate::tools::vault::{history, portfolio_period};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+/// `userFillsByTime` returns at most this many fills per request.
+const FILLS_PAGE_SIZE: usize = 2000;
+/// `userFunding` returns at most this many entries per request.
+const FUNDING_PAGE_SIZE: usize = 500;
+/// `userNonFundingLedgerUpdates` returns at most this many entries per request.
+const LEDGER_PAGE_SIZE: usize = 2000;
+const MAX_PAGES: usize = 10;
+const MINUTE_MS: u64 = 60 * 1000;
+/// Only the most recent 5000 1m candles exist, so mids reach back about 3.5
+/// days.
+const MAX_EXECUTION_LOOKBACK_HOURS: u64 = 72;
+const TREND_TIMEFRAMES: [&str; 4] = ["15m", "1h", "4h", "1d"];
+/// Enough history for EMA(50) to settle.
+const TREND_CANDLES: u64 = 200;
+const SWING_LOOKBACK: usize = 50;
+const RSI_PERIOD: usize = 14;
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Timeline rows shown: the opening step and the most recent ones.
+const MAX_TIMELINE_ROWS: usize = 50;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetPnlCurveRequest {
+    #[schemars(description = "Days of history to include (default 7, max 90)")]
+    pub lookback_days: Option<u64>,
+
+    #[schemars(
+        description = "Bucket size: \"hour\" or \"day\" (default \"hour\" up to 3 days, \"day\" beyond)"
+    )]
+    pub bucket: Option<String>,
+
+    #[schemars(description = "Include funding payments in the curve (default true)")]
+    pub include_funding: Option<bool>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetTruePerformanceRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetExecutionQualityRequest {
+    #[schemars(
+        description = "Hours of fills to analyze (default 24, max 72; 1m candles only reach back about 3.5 days)"
+    )]
+    pub lookback_hours: Option<u64>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetCoinStatsRequest {
+    #[schemars(description = "Symbol, e.g. \"SOL\", \"PURR/USDC\"")]
+    pub coin: String,
+
+    #[schemars(description = "\"perp\" or \"spot\" (optional)")]
+    pub market_kind: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetPositionTimelineRequest {
+    #[schemars(description = "Perp symbol, e.g. \"ETH\"")]
+    pub coin: String,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetTrendSnapshotRequest {
+    #[schemars(description = "Symbol, e.g. \"BTC\", \"ETH\", \"PURR/USDC\"")]
+    pub coin: String,
+
+    #[schemars(description = "\"perp\" or \"spot\" (optional)")]
+    pub market_kind: Option<String>,
+}
+
+/// Sum `(timestamp_ms, amount)` events into fixed buckets covering
+/// `[start, end)`. Buckets are aligned to multiples of `bucket_ms` and empty
+/// buckets are kept so the curve has an even time axis.
+fn bucket_events(events: &[(u64, f64)], start: u64, end: u64, bucket_ms: u64) -> Vec<(u64, f64)> {
+    if bucket_ms == 0 || end <= start {
+        return Vec::new();
+    }
+    let first = start - start % bucket_ms;
+    let count = (end - first).div_ceil(bucket_ms) as usize;
+    let mut buckets: Vec<(u64, f64)> = (0..count)
+        .map(|i| (first + i as u64 * bucket_ms, 0.0))
+        .collect();
+    for &(time, amount) in events {
+        if time < start || time >= end {
+            continue;
+        }
+        let idx = ((time - first) / bucket_ms) as usize;
+        if let Some(bucket) = buckets.get_mut(idx) {
+            bucket.1 += amount;
+        }
+    }
+    buckets
+}
+
+/// Running total of bucket values.
+fn cumulative(buckets: &[(u64, f64)]) -> Vec<(u64, f64)> {
+    let mut total = 0.0;
+    buckets
+        .iter()
+        .map(|&(time, amount)| {
+            total += amount;
+            (time, total)
+        })
+        .collect()
+}
+
+/// Largest fall from a running peak of a cumulative curve, in curve units.
+/// The curve starts from zero, so an initial loss counts as a drawdown.
+fn max_drawdown(curve: &[(u64, f64)]) -> f64 {
+    let mut peak: f64 = 0.0;
+    let mut max_dd: f64 = 0.0;
+    for &(_, value) in curve {
+        peak = peak.max(value);
+        max_dd = max_dd.max(peak - value);
+    }
+    max_dd
+}
+
+/// Net external flow into the account from one ledger update: deposits,
+/// withdrawals, transfers to or from other accounts and vault moves. Spot ↔
+/// perp transfers stay within the account and count as zero.
+fn ledger_flow(update: &serde_json::Value, user: &str) -> Option<(u64, f64)> {
+    let time = update.get("time")?.as_u64()?;
+    let delta = update.get("delta")?;
+    let usdc = str_f64(delta.get("usdc"));
+    let is_sender = delta
+        .get("user")
+        .and_then(|u| u.as_str())
+        .is_some_and(|u| u.eq_ignore_ascii_case(user));
+    let amount = match delta.get("type")?.as_str()? {
+        "deposit" | "vaultWithdraw" => usdc,
+        "withdraw" => -(usdc + str_f64(delta.get("fee"))),
+        "vaultDeposit" => -usdc,
+        "internalTransfer" | "subAccountTransfer" if is_sender => -usdc,
+        "internalTransfer" | "subAccountTransfer" => usdc,
+        "spotTransfer" | "send" => {
+            let value = str_f64(delta.get("usdcValue"));
+            if is_sender { -value } else { value }
+        }
+        _ => return None,
+    };
+    Some((time, amount))
+}
+
+/// Chain sub-period returns of an equity series with external flows removed
+/// (time-weighted return). Flows landing in `(t0, t1]` are taken out of the
+/// end value before comparing with the start, so a deposit is not a gain.
+/// Returns a growth index starting at 1.0 at the first point; periods
+/// starting from zero equity are skipped.
+fn time_weighted_index(equity: &[(u64, f64)], flows: &[(u64, f64)]) -> Vec<(u64, f64)> {
+    let Some(&(first, _)) = equity.first() else {
+        return Vec::new();
+    };
+    let mut index = vec![(first, 1.0)];
+    let mut growth = 1.0;
+    for pair in equity.windows(2) {
+        let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+        let flow: f64 = flows
+            .iter()
+            .filter(|(t, _)| *t > t0 && *t <= t1)
+            .map(|(_, amount)| amount)
+            .sum();
+        if v0 > f64::EPSILON {
+            growth *= (v1 - flow) / v0;
+        }
+        index.push((t1, growth));
+    }
+    index
+}
+
+/// Largest fall from a running peak of a growth index, as a fraction.
+fn max_drawdown_pct(index: &[(u64, f64)]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_dd: f64 = 0.0;
+    for &(_, value) in index {
+        peak = peak.max(value);
+        if peak > 0.0 {
+            max_dd = max_dd.max((peak - value) / peak);
+        }
+    }
+    max_dd
+}
+
+/// One block character per value, scaled between the series min and max.
+fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    values
+        .iter()
+        .map(|v| {
+            if range <= f64::EPSILON {
+                SPARKLINE_CHARS[SPARKLINE_CHARS.len() / 2]
+            } else {
+                let idx = ((v - min) / range * (SPARKLINE_CHARS.len() - 1) as f64).round();
+                SPARKLINE_CHARS[idx as usize]
+            }
+        })
+        .collect()
+}
+
+fn str_decimal(v: Option<&serde_json::Value>) -> Option<Decimal> {
+    v?.as_str()?.parse().ok()
+}
+
+fn str_f64(v: Option<&serde_json::Value>) -> f64 {
+    v.and_then(|x| x.as_str())
+        .and_then(|x| x.parse::<f64>().ok())
+        .unwrap_or(0.0)
+}
+
+/// Identity of an entry for de-duplicating across pages: the fill's `tid`,
+/// or the whole entry for funding and ledger updates.
+fn entry_key(entry: &serde_json::Value) -> String {
+    match entry.get("tid").and_then(|t| t.as_u64()) {
+        Some(tid) => format!("tid:{tid}"),
+        None => entry.to_string(),
+    }
+}
+
+/// Add a page fetched from `cursor` to `entries` and return where the next
+/// page starts, or None when this was the last one. The next page restarts
+/// at the last timestamp rather than after it, since a full page can end
+/// partway through entries sharing that timestamp; entries seen already are
+/// dropped. A full page that is all one timestamp cannot be split further,
+/// so the cursor then moves past it.
+fn absorb_page(
+    entries: &mut Vec<serde_json::Value>,
+    page: Vec<serde_json::Value>,
+    cursor: u64,
+    end: u64,
+    page_size: usize,
+) -> Option<u64> {
+    let time = |e: &serde_json::Value| e.get("time").and_then(|t| t.as_u64());
+    let full = page.len() >= page_size;
+    let last_time = page.iter().filter_map(time).max();
+    let seen: std::collections::HashSet<String> = entries
+        .iter()
+        .filter(|e| time(e) == Some(cursor))
+        .map(entry_key)
+        .collect();
+    entries.extend(
+        page.into_iter()
+            .filter(|e| time(e) != Some(cursor) || !seen.contains(&entry_key(e))),
+    );
+    match last_time {
+        Some(t) if full && t > cursor && t < end => Some(t),
+        Some(t) if full && t == cursor && t + 1 < end => Some(t + 1),
+        _ => None,
+    }
+}
+
+/// Fetch a time-ranged info endpoint page by page, restarting `startTime` at
+/// the last entry's time until a short page comes back.
+async fn fetch_paged(
+    state: &ServerState,
+    request_type: &str,
+    user: &str,
+    start: u64,
+    end: u64,
+    page_size: usize,
+) -> Result<Vec<serde_json::Value>, ErrorData> {
+    let mut entries = Vec::new();
+    let mut cursor = start;
+    for _ in 0..MAX_PAGES {
+        let page = state
+            .raw_info_request(json!({
+                "type": request_type,
+                "user": user,
+                "startTime": cursor,
+                "endTime": end,
+            }))
+            .await?;
+        let page = page.as_array().cloned().unwrap_or_default();
+        match absorb_page(&mut entries, page, cursor, end, page_size) {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+    Ok(entries)
+}
+
+pub async fn get_pnl_curve(
+    state: &ServerState,
+    req: GetPnlCurveRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    let user = format!("{:#x}", address);
+
+    let days = req.lookback_days.unwrap_or(7).clamp(1, 90);
+    let bucket_ms = match req.bucket.as_deref().map(str::to_lowercase).as_deref() {
+        Some("hour" | "hourly" | "1h") => HOUR_MS,
+        Some("day" | "daily" | "1d") => DAY_MS,
+        None if days <= 3 => HOUR_MS,
+        None => DAY_MS,
+        Some(other) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid bucket '{other}'. Use \"hour\" or \"day\"."
+            ))]));
+        }
+    };
+    let include_funding = req.include_funding.unwrap_or(true);
+
+    let end = chrono::Utc::now().timestamp_millis() as u64;
+    let start = end.saturating_sub(days * DAY_MS);
+
+    // Realized PnL net of fees. Fees paid in other tokens (spot buys) are skipped.
+    let fills = fetch_paged(state, "userFillsByTime", &user, start, end, FILLS_PAGE_SIZE).await?;
+    let mut events: Vec<(u64, f64)> = fills
+        .iter()
+        .filter_map(|f| {
+            let time = f.get("time")?.as_u64()?;
+            let fee = if f.get("feeToken").and_then(|t| t.as_str()) == Some("USDC") {
+                str_f64(f.get("fee"))
+            } else {
+                0.0
+            };
+            Some((time, str_f64(f.get("closedPnl")) - fee))
+        })
+        .collect();
+    let trading_total: f64 = events.iter().map(|e| e.1).sum();
+
+    let mut funding_total = 0.0;
+    if include_funding {
+        let funding =
+            fetch_paged(state, "userFunding", &user, start, end, FUNDING_PAGE_SIZE).await?;
+        for entry in &funding {
+            let Some(time) = entry.get("time").and_then(|t| t.as_u64()) else {
+                continue;
+            };
+            let usdc = str_f64(entry.get("delta").and_then(|d| d.get("usdc")));
+            funding_total += usdc;
+            events.push((time, usdc));
+        }
+    }
+
+    if events.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "No fills{} in the last {days} days.",
+            if include_funding { " or funding" } else { "" }
+        ))]));
+    }
+
+    let buckets = bucket_events(&events, start, end, bucket_ms);
+    let curve = cumulative(&buckets);
+    let values: Vec<f64> = curve.iter().map(|c| c.1).collect();
+    let total = values.last().copied().unwrap_or(0.0);
+    let drawdown = max_drawdown(&curve);
+
+    let (bucket_name, time_fmt) = if bucket_ms == HOUR_MS {
+        ("hour", "%m-%d %H:00")
+    } else {
+        ("day", "%Y-%m-%d")
+    };
+
+    let mut output = format!("## Realized PnL — last {days} days (by {bucket_name})\n\n");
+    output.push_str(&format!("`{}`\n\n", sparkline(&values)));
+    output.push_str("| Metric | Value |\n|--------|-------|\n");
+    output.push_str(&format!("| Total | ${total:.2} |\n"));
+    output.push_str(&format!(
+        "| Trading (net of fees) | ${trading_total:.2} |\n"
+    ));
+    if include_funding {
+        output.push_str(&format!("| Funding | ${funding_total:.2} |\n"));
+    }
+    output.push_str(&format!("| Max Drawdown | ${drawdown:.2} |\n"));
+    output.push_str(&format!("| Fills | {} |\n", fills.len()));
+
+    output.push_str(&format!(
+        "\n| {} | PnL | Cumulative |\n|------|-----|------------|\n",
+        if bucket_ms == HOUR_MS {
+            "Hour (UTC)"
+        } else {
+            "Day (UTC)"
+        }
+    ));
+    for ((time, pnl), (_, cum)) in buckets.iter().zip(&curve) {
+        // Skip empty hours to keep long hourly tables readable.
+        if bucket_ms == HOUR_MS && *pnl == 0.0 {
+            continue;
+        }
+        let label = chrono::DateTime::from_timestamp_millis(*time as i64)
+            .map(|dt| dt.format(time_fmt).to_string())
+            .unwrap_or_else(|| time.to_string());
+        output.push_str(&format!("| {label} | ${pnl:.2} | ${cum:.2} |\n"));
+    }
+
+    if fills.len() >= FILLS_PAGE_SIZE * MAX_PAGES {
+        output.push_str("\n*Fill history was truncated; totals may be incomplete.*\n");
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn get_true_performance(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    let user = format!("{:#x}", address);
+
+    let portfolio = state
+        .raw_info_request(json!({ "type": "portfolio", "user": user }))
+        .await?;
+    let series = |period: &str| {
+        portfolio_period(&portfolio, period)
+            .map(|data| history(data, "accountValueHistory"))
+            .unwrap_or_default()
+    };
+    let periods = [
+        ("7d", series("week")),
+        ("30d", series("month")),
+        ("All time", series("allTime")),
+    ];
+    let Some(start) = periods
+        .iter()
+        .filter_map(|(_, equity)| equity.first().map(|p| p.0))
+        .min()
+    else {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "No equity history for this account yet.",
+        )]));
+    };
+
+    let end = chrono::Utc::now().timestamp_millis() as u64;
+    let ledger = fetch_paged(
+        state,
+        "userNonFundingLedgerUpdates",
+        &user,
+        start,
+        end,
+        LEDGER_PAGE_SIZE,
+    )
+    .await?;
+    let flows: Vec<(u64, f64)> = ledger
+        .iter()
+        .filter_map(|u| ledger_flow(u, &user))
+        .collect();
+
+    let mut output = "## True Performance (deposits and withdrawals removed)\n\n".to_string();
+    output.push_str(
+        "| Period | Start Equity | End Equity | Net Deposits | Raw Change | Time-Weighted Return | Max Drawdown |\n",
+    );
+    output.push_str(
+        "|--------|--------------|------------|--------------|------------|----------------------|--------------|\n",
+    );
+    for (label, equity) in &periods {
+        let (Some(&(t0, v0)), Some(&(t1, v1))) = (equity.first(), equity.last()) else {
+            output.push_str(&format!(
+                "| {label} | N/A | N/A | N/A | N/A | N/A | N/A |\n"
+            ));
+            continue;
+        };
+        let net: f64 = flows
+            .iter()
+            .filter(|(t, _)| *t > t0 && *t <= t1)
+            .map(|(_, amount)| amount)
+            .sum();
+        let raw = if v0 > f64::EPSILON {
+            format!("{:+.2}%", (v1 - v0) / v0 * 100.0)
+        } else {
+            "N/A".into()
+        };
+        let index = time_weighted_index(equity, &flows);
+        let twr = index.last().map_or(0.0, |p| p.1) - 1.0;
+        output.push_str(&format!(
+            "| {label} | ${v0:.2} | ${v1:.2} | ${net:+.2} | {raw} | {:+.2}% | {:.2}% |\n",
+            twr * 100.0,
+            max_drawdown_pct(&index) * 100.0,
+        ));
+    }
+
+    let all_time = time_weighted_index(&periods[2].1, &flows);
+    if all_time.len() > 1 {
+        let values: Vec<f64> = all_time.iter().map(|p| p.1).collect();
+        output.push_str(&format!(
+            "\nAll-time growth of $1, deposits removed: `{}`\n",
+            sparkline(&values)
+        ));
+    }
+    output.push_str(
+        "\nEach step between equity points is measured after taking out the deposits, withdrawals, \
+         transfers and vault moves in it, and the steps are chained, so adding or removing money \
+         does not count as performance. Raw change compares start and end equity directly.\n",
+    );
+    if ledger.len() >= LEDGER_PAGE_SIZE * MAX_PAGES {
+        output.push_str("\n*Ledger history was truncated; older flows may be missing.*\n");
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+struct ExecFill {
+    time: u64,
+    px: Decimal,
+    sz: Decimal,
+    is_buy: bool,
+    crossed: bool,
+    /// Fee in USDC.
+    fee: Decimal,
+}
+
+#[derive(Default)]
+struct CoinExecution {
+    fills: usize,
+    maker_fills: usize,
+    notional: Decimal,
+    /// Taker fills with a mid to compare against.
+    priced_taker_fills: usize,
+    /// Mid-price notional of priced taker fills.
+    taker_mid_notional: Decimal,
+    /// USD paid versus mid on priced taker fills; negative means price improvement.
+    slippage_cost: Decimal,
+    fees: Decimal,
+}
+
+impl CoinExecution {
+    fn add(&mut self, other: &CoinExecution) {
+        self.fills += other.fills;
+        self.maker_fills += other.maker_fills;
+        self.notional += other.notional;
+        self.priced_taker_fills += other.priced_taker_fills;
+        self.taker_mid_notional += other.taker_mid_notional;
+        self.slippage_cost += other.slippage_cost;
+        self.fees += other.fees;
+    }
+
+    fn slippage_bps(&self) -> Option<Decimal> {
+        (!self.taker_mid_notional.is_zero())
+            .then(|| self.slippage_cost / self.taker_mid_notional * Decimal::from(10_000))
+    }
+
+    fn fee_bps(&self) -> Option<Decimal> {
+        (!self.notional.is_zero()).then(|| self.fees / self.notional * Decimal::from(10_000))
+    }
+}
+
+/// A `userFillsByTime` entry and its coin, or `None` when a field is missing.
+fn exec_fill(f: &serde_json::Value) -> Option<(&str, ExecFill)> {
+    let coin = f.get("coin")?.as_str()?;
+    let px = str_decimal(f.get("px"))?;
+    let fee = str_decimal(f.get("fee")).unwrap_or_default();
+    // Spot buys pay the fee in the base token; value it at the fill price.
+    let fee = if f.get("feeToken").and_then(|t| t.as_str()) == Some("USDC") {
+        fee
+    } else {
+        fee * px
+    };
+    let fill = ExecFill {
+        time: f.get("time")?.as_u64()?,
+        px,
+        sz: str_decimal(f.get("sz"))?,
+        is_buy: f.get("side").and_then(|s| s.as_str()) == Some("B"),
+        crossed: f.get("crossed").and_then(|c| c.as_bool()).unwrap_or(false),
+        fee,
+    };
+    Some((coin, fill))
+}
+
+fn summarize_execution(fills: &[ExecFill], candles: &[Candle]) -> CoinExecution {
+    let mut stats = CoinExecution::default();
+    for fill in fills {
+        stats.fills += 1;
+        stats.notional += fill.px * fill.sz;
+        stats.fees += fill.fee;
+        if !fill.crossed {
+            stats.maker_fills += 1;
+            continue;
+        }
+        let Some(mid) = nearest_minute_close(candles, fill.time) else {
+            continue;
+        };
+        stats.priced_taker_fills += 1;
+        stats.taker_mid_notional += mid * fill.sz;
+        stats.slippage_cost += if fill.is_buy {
+            (fill.px - mid) * fill.sz
+        } else {
+            (mid - fill.px) * fill.sz
+        };
+    }
+    stats
+}
+
+fn fmt_bps(bps: Option<Decimal>) -> String {
+    bps.map(|b| format!("{:.1} bps", b.round_dp(1)))
+        .unwrap_or_else(|| "—".into())
+}
+
+pub async fn get_execution_quality(
+    state: &ServerState,
+    req: GetExecutionQualityRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let address = state.query_address()?;
+    let user = format!("{:#x}", address);
+
+    let hours = req
+        .lookback_hours
+        .unwrap_or(24)
+        .clamp(1, MAX_EXECUTION_LOOKBACK_HOURS);
+    let end = chrono::Utc::now().timestamp_millis() as u64;
+    let start = end.saturating_sub(hours * HOUR_MS);
+
+    let raw = fetch_paged(state, "userFillsByTime", &user, start, end, FILLS_PAGE_SIZE).await?;
+    let mut by_coin: BTreeMap<String, Vec<ExecFill>> = BTreeMap::new();
+    for (coin, fill) in raw.iter().filter_map(exec_fill) {
+        by_coin.entry(coin.to_string()).or_default().push(fill);
+    }
+
+    if by_coin.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "No fills in the last {hours} hours."
+        ))]));
+    }
+
+    let candles = futures::future::join_all(by_coin.iter().map(|(coin, fills)| {
+        let fills = fills.as_slice();
+        async move {
+            // Maker-only coins need no mids.
+            if fills.iter().all(|f| !f.crossed) {
+                return Ok(Vec::new());
+            }
+            let times: Vec<u64> = fills.iter().map(|f| f.time).collect();
+            get_minute_candles_at(state, coin, &times).await
+        }
+    }))
+    .await;
+
+    let mut output = format!("## Execution Quality — last {hours} hours\n\n");
+    output.push_str(
+        "| Market | Fills | Notional | Maker Fills | Taker Slippage | Slippage Cost | Fees | Fee Drag |\n",
+    );
+    output.push_str(
+        "|--------|-------|----------|-------------|----------------|---------------|------|----------|\n",
+    );
+
+    let mut total = CoinExecution::default();
+    let mut candle_errors = Vec::new();
+    for ((coin, fills), candles) in by_coin.iter().zip(candles) {
+        let candles = candles.unwrap_or_else(|e| {
+            candle_errors.push(e.message.to_string());
+            Vec::new()
+        });
+        let stats = summarize_execution(fills, &candles);
+        let symbol = state
+            .resolve_market(coin, None)
+            .map(|m| m.symbol)
+            .unwrap_or_else(|_| coin.clone());
+        output.push_str(&format!(
+            "| {symbol} | {} | ${:.2} | {}% | {} | ${:.2} | ${:.2} | {} |\n",
+            stats.fills,
+            stats.notional,
+            stats.maker_fills * 100 / stats.fills,
+            fmt_bps(stats.slippage_bps()),
+            stats.slippage_cost,
+            stats.fees,
+            fmt_bps(stats.fee_bps()),
+        ));
+        total.add(&stats);
+    }
+    output.push_str(&format!(
+        "| **Total** | {} | ${:.2} | {}% | {} | ${:.2} | ${:.2} | {} |\n",
+        total.fills,
+        total.notional,
+        total.maker_fills * 100 / total.fills,
+        fmt_bps(total.slippage_bps()),
+        total.slippage_cost,
+        total.fees,
+        fmt_bps(total.fee_bps()),
+    ));
+
+    let taker_fills = total.fills - total.maker_fills;
+    output.push_str(&format!(
+        "\nTaker slippage compares {} of {taker_fills} taker fills against the nearest 1m candle close \
+         (positive = paid more than mid). Maker fills pay no spread. \
+         Execution cost = slippage + fees = ${:.2}.\n",
+        total.priced_taker_fills,
+        total.slippage_cost + total.fees,
+    ));
+    if raw.len() >= FILLS_PAGE_SIZE * MAX_PAGES {
+        output.push_str("\n*Fill history was truncated; totals may be incomplete.*\n");
+    }
+    for error in candle_errors {
+        output.push_str(&format!("\n*{error}*\n"));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// A fill reduced to what lifetime stats need.
+struct StatFill {
+    time: u64,
+    oid: u64,
+    px: Decimal,
+    sz: Decimal,
+    is_buy: bool,
+    /// Position before this fill, signed.
+    start_position: Decimal,
+    closed_pnl: Decimal,
+    /// Fee in USDC.
+    fee: Decimal,
+}
+
+/// A position held from flat (or a flip) until it returns to flat or flips.
+struct RoundTrip {
+    /// `None` when the position was already open before the first fill seen.
+    opened: Option<u64>,
+    closed: u64,
+    /// Closed PnL net of fees paid while the position was open.
+    net_pnl: Decimal,
+}
+
+#[derive(Default)]
+struct CoinStats {
+    fills: usize,
+    volume: Decimal,
+    realized_pnl: Decimal,
+    fees: Decimal,
+    round_trips: Vec<RoundTrip>,
+    first_fill: Option<u64>,
+    last_fill: Option<u64>,
+    /// Position after the last fill.
+    position: Decimal,
+}
+
+impl CoinStats {
+    fn avg_hold_ms(&self) -> Option<u64> {
+        let holds: Vec<u64> = self
+            .round_trips
+            .iter()
+            .filter_map(|t| Some(t.closed.saturating_sub(t.opened?)))
+            .collect();
+        (!holds.is_empty()).then(|| holds.iter().sum::<u64>() / holds.len() as u64)
+    }
+
+    fn best(&self) -> Option<&RoundTrip> {
+        self.round_trips
+            .iter()
+            .filter(|t| t.net_pnl.is_sign_positive() && !t.net_pnl.is_zero())
+            .max_by_key(|t| t.net_pnl)
+    }
+
+    fn worst(&self) -> Option<&RoundTrip> {
+        self.round_trips
+            .iter()
+            .filter(|t| t.net_pnl.is_sign_negative())
+            .min_by_key(|t| t.net_pnl)
+    }
+
+    fn wins(&self) -> usize {
+        self.round_trips
+            .iter()
+            .filter(|t| t.net_pnl.is_sign_positive() && !t.net_pnl.is_zero())
+            .count()
+    }
+}
+
+/// Aggregate fills (sorted by time) into lifetime stats. Partial fills just
+/// move the running position; a round trip ends when the position returns to
+/// flat or crosses zero, and a crossing fill opens the next one.
+fn coin_stats(fills: &[StatFill]) -> CoinStats {
+    let mut stats = CoinStats::default();
+    let Some(first) = fills.first() else {
+        return stats;
+    };
+    let mut position = first.start_position;
+    let mut opened = position.is_zero().then_some(first.time);
+    // Only a position opened within the history has a known open time.
+    let mut open_known = position.is_zero();
+    let mut trip_pnl = Decimal::ZERO;
+
+    for f in fills {
+        stats.fills += 1;
+        stats.volume += f.px * f.sz;
+        stats.realized_pnl += f.closed_pnl;
+        stats.fees += f.fee;
+        stats.first_fill.get_or_insert(f.time);
+        stats.last_fill = Some(f.time);
+
+        if position.is_zero() {
+            opened = Some(f.time);
+            open_known = true;
+            trip_pnl = Decimal::ZERO;
+        }
+        trip_pnl += f.closed_pnl - f.fee;
+
+        let next = if f.is_buy {
+            position + f.sz
+        } else {
+            position - f.sz
+        };
+        let crossed = !position.is_zero()
+            && !next.is_zero()
+            && position.is_sign_positive() != next.is_sign_positive();
+        if !position.is_zero() && (next.is_zero() || crossed) {
+            stats.round_trips.push(RoundTrip {
+                opened: opened.filter(|_| open_known),
+                closed: f.time,
+                net_pnl: trip_pnl,
+            });
+            trip_pnl = Decimal::ZERO;
+            opened = Some(f.time);
+            open_known = true;
+        }
+        position = next;
+    }
+    stats.position = position;
+    stats
+}
+
+/// The fills on `coin` from raw `userFillsByTime` entries, oldest first.
+fn stat_fills(raw: &[serde_json::Value], coin: &str) -> Vec<StatFill> {
+    let mut fills: Vec<StatFill> = raw
+        .iter()
+        .filter(|f| f.get("coin").and_then(|c| c.as_str()) == Some(coin))
+        .filter_map(|f| {
+            let px = str_decimal(f.get("px"))?;
+            let fee = str_decimal(f.get("fee")).unwrap_or_default();
+            // Spot buys pay the fee in the base token; value it at the fill price.
+            let fee = if f.get("feeToken").and_then(|t| t.as_str()) == Some("USDC") {
+                fee
+            } else {
+                fee * px
+            };
+            Some(StatFill {
+                time: f.get("time")?.as_u64()?,
+                oid: f.get("oid").and_then(|o| o.as_u64()).unwrap_or_default(),
+                px,
+                sz: str_decimal(f.get("sz"))?,
+                is_buy: f.get("side").and_then(|s| s.as_str()) == Some("B"),
+                start_position: str_decimal(f.get("startPosition")).unwrap_or_default(),
+                closed_pnl: str_decimal(f.get("closedPnl")).unwrap_or_default(),
+                fee,
+            })
+        })
+        .collect();
+    fills.sort_by_key(|f| f.time);
+    fills
+}
+
+/// Merge consecutive partial fills of the same order into one fill at their
+/// average price.
+fn merge_order_fills(fills: Vec<StatFill>) -> Vec<StatFill> {
+    let mut merged: Vec<StatFill> = Vec::with_capacity(fills.len());
+    for f in fills {
+        match merged.last_mut() {
+            Some(last) if last.oid == f.oid && last.is_buy == f.is_buy => {
+                let sz = last.sz + f.sz;
+                last.px = (last.px * last.sz + f.px * f.sz) / sz;
+                last.sz = sz;
+                last.time = f.time;
+                last.closed_pnl += f.closed_pnl;
+                last.fee += f.fee;
+            }
+            _ => merged.push(f),
+        }
+    }
+    merged
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepKind {
+    Open,
+    /// Opened by a fill that closed the opposite position first.
+    Flip,
+    Add,
+    Reduce,
+}
+
+impl StepKind {
+    fn label(self) -> &'static str {
+        match self {
+            StepKind::Open => "Open",
+            StepKind::Flip => "Flip",
+            StepKind::Add => "Add",
+            StepKind::Reduce => "Reduce",
+        }
+    }
+}
+
+struct TimelineStep {
+    time: u64,
+    kind: StepKind,
+    /// Signed change to the position: positive buys, negative sells.
+    delta: Decimal,
+    px: Decimal,
+    /// Signed position after the fill.
+    position: Decimal,
+    /// Average entry after the fill; `None` when the position was opened
+    /// before the available history.
+    avg_entry: Option<Decimal>,
+    /// PnL realized by a reduce.
+    realized: Decimal,
+}
+
+struct PositionTimeline {
+    steps: Vec<TimelineStep>,
+    /// When the position was opened, if within the available history.
+    opened: Option<u64>,
+    realized: Decimal,
+    fees: Decimal,
+}
+
+impl PositionTimeline {
+    fn position(&self) -> Decimal {
+        self.steps.last().map_or(Decimal::ZERO, |s| s.position)
+    }
+
+    fn count(&self, kind: StepKind) -> usize {
+        self.steps.iter().filter(|s| s.kind == kind).count()
+    }
+}
+
+/// Rebuild the current position from fills sorted by time, with average-cost
+/// accounting. The position starts at the last fill that opened it from flat
+/// or flipped it; only the opening part of a flip counts, at the fill price.
+/// Adds average into the entry, reduces realize `size * (px - entry)` for a
+/// long and the negative of that for a short and leave the entry unchanged.
+/// When no opening fill is in `fills`, the entry is unknown and the
+/// exchange's closed PnL is used for reduces. `None` when the position ends
+/// flat.
+fn position_timeline(fills: &[StatFill]) -> Option<PositionTimeline> {
+    let signed = |f: &StatFill| if f.is_buy { f.sz } else { -f.sz };
+    let opens = |f: &StatFill| {
+        let next = f.start_position + signed(f);
+        !next.is_zero()
+            && (f.start_position.is_zero()
+                || f.start_position.is_sign_positive() != next.is_sign_positive())
+    };
+    let start = fills.iter().rposition(opens);
+    let mut timeline = PositionTimeline {
+        steps: Vec::new(),
+        opened: start.map(|i| fills[i].time),
+        realized: Decimal::ZERO,
+        fees: Decimal::ZERO,
+    };
+    let mut position = fills.get(start.unwrap_or(0))?.start_position;
+    let mut entry: Option<Decimal> = None;
+    for (i, f) in fills.iter().enumerate().skip(start.unwrap_or(0)) {
+        let delta = signed(f);
+        let next = position + delta;
+        let (kind, realized) = if Some(i) == start {
+            entry = Some(f.px);
+            let kind = if position.is_zero() {
+                StepKind::Open
+            } else {
+                StepKind::Flip
+            };
+            // The closing part of a flip belongs to the previous position.
+            (kind, Decimal::ZERO)
+        } else if position.is_sign_positive() == delta.is_sign_positive() {
+            entry = entry.map(|e| (position.abs() * e + f.sz * f.px) / next.abs());
+            (StepKind::Add, Decimal::ZERO)
+        } else {
+            let realized = match entry {
+                Some(e) if position.is_sign_positive() => f.sz * (f.px - e),
+                Some(e) => f.sz * (e - f.px),
+                None => f.closed_pnl,
+            };
+            (StepKind::Reduce, realized)
+        };
+        timeline.realized += realized;
+        timeline.fees += f.fee;
+        timeline.steps.push(TimelineStep {
+            time: f.time,
+            kind,
+            delta,
+            px: f.px,
+            position: next,
+            avg_entry: entry,
+            realized,
+        });
+        position = next;
+    }
+    (!position.is_zero()).then_some(timeline)
+}
+
+fn fmt_duration(ms: u64) -> String {
+    let mins = ms / MINUTE_MS;
+    if mins < 60 {
+        format!("{mins}m")
+    } else if mins < 24 * 60 {
+        format!("{}h {}m", mins / 60, mins % 60)
+    } else {
+        format!("{}d {}h", mins / (24 * 60), mins % (24 * 60) / 60)
+    }
+}
+
+pub async fn get_coin_stats(
+    state: &ServerState,
+    req: GetCoinStatsRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+    let address = state.query_address()?;
+    let user = format!("{:#x}", address);
+
+    let end = chrono::Utc::now().timestamp_millis() as u64;
+    let raw = fetch_paged(state, "userFillsByTime", &user, 0, end, FILLS_PAGE_SIZE).await?;
+    let fills = stat_fills(&raw, &market.coin);
+
+    if fills.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "No fills on {} in the available history.",
+            market.describe()
+        ))]));
+    }
+
+    let stats = coin_stats(&fills);
+    let net = stats.realized_pnl - stats.fees;
+    let trips = stats.round_trips.len();
+
+    let mut output = format!("## {} Lifetime Stats\n\n", market.describe());
+    output.push_str("| Metric | Value |\n");
+    output.push_str("|--------|-------|\n");
+    output.push_str(&format!(
+        "| History | {} to {} |\n",
+        fmt_date(stats.first_fill.unwrap_or_default()),
+        fmt_date(stats.last_fill.unwrap_or_default())
+    ));
+    output.push_str(&format!("| Fills | {} |\n", stats.fills));
+    output.push_str(&format!("| Volume | ${:.2} |\n", stats.volume));
+    output.push_str(&format!("| Closed Trades | {trips} |\n"));
+    if trips > 0 {
+        output.push_str(&format!(
+            "| Win Rate | {:.0}% |\n",
+            stats.wins() as f64 / trips as f64 * 100.0
+        ));
+    }
+    output.push_str(&format!("| Realized PnL | ${:.2} |\n", stats.realized_pnl));
+    output.push_str(&format!("| Fees | ${:.2} |\n", stats.fees));
+    output.push_str(&format!("| Net PnL | ${net:.2} |\n"));
+    output.push_str(&format!(
+        "| Avg Hold Time | {} |\n",
+        stats
+            .avg_hold_ms()
+            .map(fmt_duration)
+            .unwrap_or_else(|| "—".into())
+    ));
+    for (label, trip) in [
+        ("Biggest Win", stats.best()),
+        ("Biggest Loss", stats.worst()),
+    ] {
+        let value = trip
+            .map(|t| format!("${:.2} (closed {})", t.net_pnl, fmt_date(t.closed)))
+            .unwrap_or_else(|| "—".into());
+        output.push_str(&format!("| {label} | {value} |\n"));
+    }
+    if !stats.position.is_zero() {
+        output.push_str(&format!(
+            "| Open Position | {} (not counted as a trade) |\n",
+            stats.position
+        ));
+    }
+
+    output.push_str(
+        "\nA trade runs from flat until the position is flat again or flips; \
+         win/loss figures are net of fees.\n",
+    );
+    if raw.len() >= FILLS_PAGE_SIZE * MAX_PAGES {
+        output
+            .push_str("\n*Fill history was truncated; stats cover the most recent fills only.*\n");
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn get_position_timeline(
+    state: &ServerState,
+    req: GetPositionTimelineRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+    let address = state.query_address()?;
+    let user = format!("{:#x}", address);
+
+    let user_state = get_cached_clearinghouse(state, address).await?;
+    let Some(live) = user_state
+        .asset_positions
+        .iter()
+        .map(|ap| &ap.position)
+        .find(|p| p.coin == market.coin && !p.szi.is_zero())
+    else {
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "No open position on {}.",
+            market.describe()
+        ))]));
+    };
+
+    let end = chrono::Utc::now().timestamp_millis() as u64;
+    let raw = fetch_paged(state, "userFillsByTime", &user, 0, end, FILLS_PAGE_SIZE).await?;
+    let fills = merge_order_fills(stat_fills(&raw, &market.coin));
+    let Some(timeline) = position_timeline(&fills) else {
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "{} {} is open, but the fills in the available history don't add up to it.",
+            market.describe(),
+            live.szi
+        ))]));
+    };
+
+    let side = if live.szi.is_sign_positive() {
+        "Long"
+    } else {
+        "Short"
+    };
+    let times = |n: usize| match n {
+        1 => "once".to_string(),
+        2 => "twice".to_string(),
+        n => format!("{n} times"),
+    };
+    let mut summary = match (timeline.opened, timeline.steps.first()) {
+        (Some(opened), Some(first)) => format!(
+            "{side} {} {} opened {} at {}",
+            fmt_qty(live.szi.abs()),
+            market.coin,
+            fmt_ago(opened),
+            fmt_price(first.px)
+        ),
+        _ => format!(
+            "{side} {} {} opened before the available history",
+            fmt_qty(live.szi.abs()),
+            market.coin
+        ),
+    };
+    let mut changes = Vec::new();
+    for (kind, verb) in [(StepKind::Add, "added"), (StepKind::Reduce, "reduced")] {
+        let n = timeline.count(kind);
+        if n > 0 {
+            changes.push(format!("{verb} {}", times(n)));
+        }
+    }
+    if !changes.is_empty() {
+        summary.push_str(&format!(", {}", changes.join(", ")));
+    }
+    if let Some(entry) = live.entry_px {
+        summary.push_str(&format!(", {} avg entry now", fmt_price(entry)));
+    }
+    summary.push_str(&format!(
+        ", net realized {} after {} in fees.",
+        fmt_usd(timeline.realized - timeline.fees),
+        fmt_usd(timeline.fees)
+    ));
+
+    let mut output = format!(
+        "## {} Position Timeline
+
+{summary}
+
+",
+        market.describe()
+    );
+    output.push_str(
+        "| Time | Action | Size | Price | Position | Avg Entry | Realized |
+",
+    );
+    output.push_str(
+        "|------|--------|------|-------|----------|-----------|----------|
+",
+    );
+    let steps = &timeline.steps;
+    let hidden = steps.len().saturating_sub(MAX_TIMELINE_ROWS);
+    for (i, step) in steps.iter().enumerate() {
+        if i > 0 && i <= hidden {
+            if i == 1 {
+                output.push_str(&format!("| … | {hidden} more | | | | | |\n"));
+            }
+            continue;
+        }
+        output.push_str(&format!(
+            "| {} | {} | {:+} | {} | {} | {} | {} |\n",
+            fmt_time_secs(step.time),
+            step.kind.label(),
+            step.delta,
+            fmt_price(round_sig(step.px, 6)),
+            step.position,
+            step.avg_entry
+                .map(|e| fmt_price(round_sig(e, 6)))
+                .unwrap_or_else(|| "—".into()),
+            if step.realized.is_zero() {
+                "—".to_string()
+            } else {
+                fmt_usd(step.realized)
+            },
+        ));
+    }
+    output.push_str(&format!(
+        "| Now | Live | | | {} | {} | {} unrealized |\n",
+        live.szi,
+        live.entry_px.map(fmt_price).unwrap_or_else(|| "—".into()),
+        fmt_usd(live.unrealized_pnl)
+    ));
+
+    if timeline.position() != live.szi {
+        output.push_str(&format!(
+            "\n*The fills add up to {}, not the live {}; some changes (e.g. a transfer or \
+             auto-deleverage) are not in the fill history.*\n",
+            timeline.position(),
+            live.szi
+        ));
+    }
+    if timeline.opened.is_none() {
+        output.push_str(
+            "\n*The opening fill is older than the available history, so average entries \
+             and realized PnL before it are unknown; realized PnL uses the exchange's figure.*\n",
+        );
+    }
+    if raw.len() >= FILLS_PAGE_SIZE * MAX_PAGES {
+        output.push_str("\n*Fill history was truncated; the timeline may be incomplete.*\n");
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Exponential moving average of `values`, seeded with the simple average of
+/// the first `period` values.
+fn ema(values: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || values.len() < period {
+        return None;
+    }
+    let k = 2.0 / (period as f64 + 1.0);
+    let seed = values[..period].iter().sum::<f64>() / period as f64;
+    Some(
+        values[period..]
+            .iter()
+            .fold(seed, |ema, v| v * k + ema * (1.0 - k)),
+    )
+}
+
+/// Wilder's RSI over `period` closes.
+fn rsi(closes: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || closes.len() <= period {
+        return None;
+    }
+    let changes: Vec<f64> = closes.windows(2).map(|w| w[1] - w[0]).collect();
+    let mut gain = changes[..period].iter().map(|c| c.max(0.0)).sum::<f64>() / period as f64;
+    let mut loss = changes[..period].iter().map(|c| (-c).max(0.0)).sum::<f64>() / period as f64;
+    for c in &changes[period..] {
+        gain = (gain * (period - 1) as f64 + c.max(0.0)) / period as f64;
+        loss = (loss * (period - 1) as f64 + (-c).max(0.0)) / period as f64;
+    }
+    if loss == 0.0 {
+        return Some(if gain == 0.0 { 50.0 } else { 100.0 });
+    }
+    Some(100.0 - 100.0 / (1.0 + gain / loss))
+}
+
+struct TrendRow {
+    close: f64,
+    ema20: f64,
+    ema50: f64,
+    rsi: f64,
+    swing_high: f64,
+    swing_low: f64,
+}
+
+impl TrendRow {
+    fn from_candles(candles: &[Candle]) -> Option<Self> {
+        use rust_decimal::prelude::ToPrimitive;
+
+        let closes: Vec<f64> = candles.iter().filter_map(|c| c.close.to_f64()).collect();
+        let recent = &candles[candles.len().saturating_sub(SWING_LOOKBACK)..];
+        Some(Self {
+            close: *closes.last()?,
+            ema20: ema(&closes, 20)?,
+            ema50: ema(&closes, 50)?,
+            rsi: rsi(&closes, RSI_PERIOD)?,
+            swing_high: recent
+                .iter()
+                .filter_map(|c| c.high.to_f64())
+                .fold(f64::MIN, f64::max),
+            swing_low: recent
+                .iter()
+                .filter_map(|c| c.low.to_f64())
+                .fold(f64::MAX, f64::min),
+        })
+    }
+
+    /// "up" when close > EMA(20) > EMA(50) and RSI >= 50, "down" when
+    /// close < EMA(20) < EMA(50) and RSI <= 50, otherwise "range".
+    fn classify(&self) -> &'static str {
+        if self.close > self.ema20 && self.ema20 > self.ema50 && self.rsi >= 50.0 {
+            "up"
+        } else if self.close < self.ema20 && self.ema20 < self.ema50 && self.rsi <= 50.0 {
+            "down"
+        } else {
+            "range"
+        }
+    }
+}
+
+/// Format a price to 5 significant figures.
+/// Five significant figures, so sub-cent prices keep their digits.
+fn fmt_px(px: f64) -> String {
+    let decimals = if px > 0.0 {
+        (4 - px.log10().floor() as i32).clamp(0, 20) as usize
+    } else {
+        2
+    };
+    format!("{px:.decimals$}")
+}
+
+pub async fn get_trend_snapshot(
+    state: &ServerState,
+    req: GetTrendSnapshotRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+
+    let series = futures::future::try_join_all(
+        TREND_TIMEFRAMES
+            .iter()
+            .map(|tf| get_cached_candles(state, &market.coin, tf, TREND_CANDLES)),
+    )
+    .await?;
+
+    let mut output = format!("## {} Trend Snapshot\n\n", market.describe());
+    output.push_str(
+        "| Timeframe | Close | EMA(20) | EMA(50) | RSI(14) | From Swing High | From Swing Low | Trend |\n",
+    );
+    output.push_str(
+        "|-----------|-------|---------|---------|---------|-----------------|----------------|-------|\n",
+    );
+    for (tf, candles) in TREND_TIMEFRAMES.iter().zip(&series) {
+        let Some(row) = TrendRow::from_candles(candles) else {
+            output.push_str(&format!(
+                "| {tf} | — | — | — | — | — | — | not enough history ({} candles) |\n",
+                candles.len()
+            ));
+            continue;
+        };
+        output.push_str(&format!(
+            "| {tf} | {} | {} | {} | {:.1} | {:+.2}% | {:+.2}% | **{}** |\n",
+            fmt_px(row.close),
+            fmt_px(row.ema20),
+            fmt_px(row.ema50),
+            row.rsi,
+            (row.close / row.swing_high - 1.0) * 100.0,
+            (row.close / row.swing_low - 1.0) * 100.0,
+            row.classify(),
+        ));
+    }
+    output.push_str(&format!(
+        "\nSwing high/low span the last {SWING_LOOKBACK} candles; the latest candle may still be open.\n\
+         Trend is **up** when close > EMA(20) > EMA(50) and RSI ≥ 50, **down** when \
+         close < EMA(20) < EMA(50) and RSI ≤ 50, and **range** otherwise.\n"
+    ));
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(tid: u64, time: u64) -> serde_json::Value {
+        json!({ "tid": tid, "time": time, "coin": "BTC" })
+    }
+
+    #[test]
+    fn paging_restarts_at_the_last_timestamp_without_duplicates() {
+        let mut entries = Vec::new();
+        // A full page ending partway through the fills at t=200.
+        let next = absorb_page(
+            &mut entries,
+            vec![fill(1, 100), fill(2, 200), fill(3, 200)],
+            0,
+            1_000,
+            3,
+        );
+        assert_eq!(next, Some(200));
+
+        let next = absorb_page(
+            &mut entries,
+            vec![fill(2, 200), fill(3, 200), fill(4, 200)],
+            200,
+            1_000,
+            3,
+        );
+        // Only one timestamp in a full page: move past it.
+        assert_eq!(next, Some(201));
+
+        let next = absorb_page(&mut entries, vec![fill(5, 300)], 201, 1_000, 3);
+        assert_eq!(next, None);
+        let tids: Vec<u64> = entries.iter().map(|e| e["tid"].as_u64().unwrap()).collect();
+        assert_eq!(tids, [1, 2, 3, 4, 5]);
+
+        // Funding entries have no tid; identical entries are the same one.
+        let funding = |coin: &str| json!({ "time": 500, "delta": { "coin": coin } });
+        let mut entries = vec![funding("BTC")];
+        absorb_page(
+            &mut entries,
+            vec![funding("BTC"), funding("ETH")],
+            500,
+            1_000,
+            10,
+        );
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn bucket_events_keeps_empty_buckets_on_an_even_axis() {
+        let events = [
+            (5, 1.0),
+            (12, 2.0),
+            (14, -0.5),
+            (35, 4.0),
+            (40, 9.0),
+            (2, 9.0),
+        ];
+        assert_eq!(
+            bucket_events(&events, 3, 40, 10),
+            vec![(0, 1.0), (10, 1.5), (20, 0.0), (30, 4.0)]
+        );
+        assert!(bucket_events(&events, 40, 40, 10).is_empty());
+        assert!(bucket_events(&events, 0, 40, 0).is_empty());
+    }
+
+    #[test]
+    fn cumulative_curve_and_drawdown() {
+        let buckets = [(0, 3.0), (10, -5.0), (20, 4.0), (30, -1.0)];
+        let curve = cumulative(&buckets);
+        assert_eq!(curve, vec![(0, 3.0), (10, -2.0), (20, 2.0), (30, 1.0)]);
+        assert_eq!(max_drawdown(&curve), 5.0);
+        // Losing from the start counts against the zero baseline.
+        assert_eq!(max_drawdown(&cumulative(&[(0, -2.0), (10, -1.0)])), 3.0);
+        assert_eq!(max_drawdown(&[]), 0.0);
+    }
+
+    fn assert_index(actual: Vec<(u64, f64)>, expected: &[(u64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for ((t, v), (et, ev)) in actual.iter().zip(expected) {
+            assert_eq!(t, et);
+            assert!((v - ev).abs() < 1e-9, "{actual:?}");
+        }
+    }
+
+    #[test]
+    fn time_weighted_index_removes_deposits_and_withdrawals() {
+        let equity = [(0, 1_000.0), (10, 1_100.0), (20, 2_210.0), (30, 1_000.0)];
+        // +10%, then +10% on 1,100 plus a 1,000 deposit, then flat while
+        // 1,210 is withdrawn.
+        let flows = [(15, 1_000.0), (25, -1_210.0)];
+        let index = time_weighted_index(&equity, &flows);
+        assert_index(
+            index.clone(),
+            &[(0, 1.0), (10, 1.1), (20, 1.21), (30, 1.21)],
+        );
+        assert_eq!(max_drawdown_pct(&index), 0.0);
+
+        // A flow exactly at a point belongs to the period ending there.
+        let index = time_weighted_index(&[(0, 100.0), (10, 150.0), (20, 120.0)], &[(10, 50.0)]);
+        assert_index(index.clone(), &[(0, 1.0), (10, 1.0), (20, 0.8)]);
+        assert!((max_drawdown_pct(&index) - 0.2).abs() < 1e-9);
+
+        // Periods starting from an empty account are skipped.
+        let index = time_weighted_index(&[(0, 0.0), (10, 500.0), (20, 550.0)], &[(5, 500.0)]);
+        assert_index(index, &[(0, 1.0), (10, 1.0), (20, 1.1)]);
+        assert!(time_weighted_index(&[], &flows).is_empty());
+    }
+
+    #[test]
+    fn ledger_flow_signs_external_moves() {
+        let user = "0xabc0000000000000000000000000000000000001";
+        let update =
+            |delta: serde_json::Value| json!({ "time": 42, "hash": "0x01", "delta": delta });
+        let flow = |delta| ledger_flow(&update(delta), user).map(|(_, amount)| amount);
+
+        assert_eq!(
+            flow(json!({ "type": "deposit", "usdc": "500.0" })),
+            Some(500.0)
+        );
+        assert_eq!(
+            flow(json!({ "type": "withdraw", "usdc": "200.0", "fee": "1.0" })),
+            Some(-201.0)
+        );
+        assert_eq!(
+            flow(json!({ "type": "vaultDeposit", "usdc": "50.0" })),
+            Some(-50.0)
+        );
+        assert_eq!(
+            flow(json!({ "type": "vaultWithdraw", "usdc": "55.0" })),
+            Some(55.0)
+        );
+        assert_eq!(
+            flow(
+                json!({ "type": "internalTransfer", "usdc": "10.0", "user": user.to_uppercase() })
+            ),
+            Some(-10.0)
+        );
+        assert_eq!(
+            flow(json!({ "type": "subAccountTransfer", "usdc": "10.0", "user": "0xdef" })),
+            Some(10.0)
+        );
+        assert_eq!(
+            flow(json!({ "type": "spotTransfer", "usdcValue": "25.5", "user": user })),
+            Some(-25.5)
+        );
+        assert_eq!(
+            flow(json!({ "type": "accountClassTransfer", "usdc": "100.0" })),
+            None
+        );
+        assert_eq!(
+            ledger_flow(&update(json!({ "type": "deposit", "usdc": "1.0" })), user),
+            Some((42, 1.0))
+        );
+    }
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    /// A fill of `sz` at `px` from signed position `start`.
+    fn stat_fill(time: u64, oid: u64, is_buy: bool, px: &str, sz: &str, start: &str) -> StatFill {
+        StatFill {
+            time,
+            oid,
+            px: d(px),
+            sz: d(sz),
+            is_buy,
+            start_position: d(start),
+            closed_pnl: Decimal::ZERO,
+            fee: Decimal::ZERO,
+        }
+    }
+
+    fn with_pnl(mut fill: StatFill, closed_pnl: &str, fee: &str) -> StatFill {
+        fill.closed_pnl = d(closed_pnl);
+        fill.fee = d(fee);
+        fill
+    }
+
+    #[test]
+    fn coin_stats_merges_partials_and_splits_round_trips_at_flips() {
+        let fills = merge_order_fills(vec![
+            // Two partial fills of one buy order.
+            with_pnl(stat_fill(1, 10, true, "100", "0.4", "0"), "0", "0.04"),
+            with_pnl(stat_fill(2, 10, true, "101", "0.6", "0.4"), "0", "0.06"),
+            with_pnl(stat_fill(3, 11, false, "110", "1", "1"), "9.4", "0.1"),
+            with_pnl(stat_fill(4, 12, false, "112", "0.5", "0"), "0", "0.05"),
+            // Closes the short and opens a long in one fill.
+            with_pnl(stat_fill(5, 13, true, "108", "1.5", "-0.5"), "2", "0.2"),
+            with_pnl(stat_fill(6, 14, false, "100", "1", "1"), "-8", "0.1"),
+        ]);
+        assert_eq!(fills.len(), 5);
+        assert_eq!(
+            (fills[0].time, fills[0].px, fills[0].sz),
+            (2, d("100.6"), d("1.0"))
+        );
+        assert_eq!(fills[0].fee, d("0.10"));
+
+        let stats = coin_stats(&fills);
+        assert_eq!(stats.fills, 5);
+        assert_eq!(stats.volume, d("528.6"));
+        assert_eq!(stats.realized_pnl, d("3.4"));
+        assert_eq!(stats.fees, d("0.55"));
+        assert_eq!(stats.position, Decimal::ZERO);
+        assert_eq!((stats.first_fill, stats.last_fill), (Some(2), Some(6)));
+        let trips: Vec<_> = stats
+            .round_trips
+            .iter()
+            .map(|t| (t.opened, t.closed, t.net_pnl))
+            .collect();
+        assert_eq!(
+            trips,
+            [
+                (Some(2), 3, d("9.2")),
+                (Some(4), 5, d("1.75")),
+                (Some(5), 6, d("-8.1")),
+            ]
+        );
+        assert_eq!(stats.wins(), 2);
+        assert_eq!(stats.best().map(|t| t.net_pnl), Some(d("9.2")));
+        assert_eq!(stats.worst().map(|t| t.net_pnl), Some(d("-8.1")));
+        assert_eq!(stats.avg_hold_ms(), Some(1));
+    }
+
+    #[test]
+    fn coin_stats_leaves_pre_history_opens_unknown() {
+        let fills = [
+            with_pnl(stat_fill(10, 1, false, "50", "1", "3"), "4", "0"),
+            with_pnl(stat_fill(20, 2, false, "55", "2", "2"), "18", "0"),
+        ];
+        let stats = coin_stats(&fills);
+        assert_eq!(stats.round_trips.len(), 1);
+        assert_eq!(stats.round_trips[0].opened, None);
+        assert_eq!(stats.round_trips[0].net_pnl, d("22"));
+        assert_eq!(stats.avg_hold_ms(), None);
+
+        // Same order id on the other side is a separate fill.
+        let merged = merge_order_fills(vec![
+            stat_fill(1, 7, true, "10", "1", "0"),
+            stat_fill(2, 7, false, "11", "1", "1"),
+        ]);
+        assert_eq!(merged.len(), 2);
+    }
+
+    fn steps(timeline: &PositionTimeline) -> Vec<(StepKind, Decimal, Option<Decimal>, Decimal)> {
+        timeline
+            .steps
+            .iter()
+            .map(|s| (s.kind, s.position, s.avg_entry, s.realized))
+            .collect()
+    }
+
+    #[test]
+    fn position_timeline_averages_adds_and_realizes_reduces() {
+        let timeline = position_timeline(&[
+            stat_fill(1, 1, true, "100", "1", "0"),
+            stat_fill(2, 2, true, "110", "1", "1"),
+            stat_fill(3, 3, false, "120", "0.5", "2"),
+        ])
+        .unwrap();
+        assert_eq!(
+            steps(&timeline),
+            [
+                (StepKind::Open, d("1"), Some(d("100")), d("0")),
+                (StepKind::Add, d("2"), Some(d("105")), d("0")),
+                (StepKind::Reduce, d("1.5"), Some(d("105")), d("7.5")),
+            ]
+        );
+        assert_eq!(timeline.opened, Some(1));
+        assert_eq!(timeline.realized, d("7.5"));
+        assert_eq!(timeline.position(), d("1.5"));
+        assert_eq!(timeline.count(StepKind::Add), 1);
+    }
+
+    #[test]
+    fn position_timeline_starts_at_the_last_flip_or_reopen() {
+        // A short flipped long: the short's history is not part of it.
+        let timeline = position_timeline(&[
+            stat_fill(1, 1, false, "100", "1", "0"),
+            stat_fill(2, 2, true, "90", "3", "-1"),
+            stat_fill(3, 3, false, "95", "1", "2"),
+        ])
+        .unwrap();
+        assert_eq!(
+            steps(&timeline),
+            [
+                (StepKind::Flip, d("2"), Some(d("90")), d("0")),
+                (StepKind::Reduce, d("1"), Some(d("90")), d("5")),
+            ]
+        );
+        assert_eq!(timeline.opened, Some(2));
+
+        // Closed to flat and reopened short.
+        let timeline = position_timeline(&[
+            stat_fill(1, 1, true, "100", "1", "0"),
+            stat_fill(2, 2, false, "110", "1", "1"),
+            stat_fill(3, 3, false, "105", "2", "0"),
+            stat_fill(4, 4, true, "100", "1", "-2"),
+        ])
+        .unwrap();
+        assert_eq!(
+            steps(&timeline),
+            [
+                (StepKind::Open, d("-2"), Some(d("105")), d("0")),
+                (StepKind::Reduce, d("-1"), Some(d("105")), d("5")),
+            ]
+        );
+        assert_eq!(timeline.opened, Some(3));
+
+        assert!(
+            position_timeline(&[
+                stat_fill(1, 1, true, "100", "1", "0"),
+                stat_fill(2, 2, false, "110", "1", "1"),
+            ])
+            .is_none()
+        );
+        assert!(position_timeline(&[]).is_none());
+    }
+
+    #[test]
+    fn position_timeline_without_an_opening_fill_uses_closed_pnl() {
+        let timeline = position_timeline(&[with_pnl(
+            stat_fill(5, 1, false, "100", "1", "3"),
+            "12",
+            "0.1",
+        )])
+        .unwrap();
+        assert_eq!(
+            steps(&timeline),
+            [(StepKind::Reduce, d("2"), None, d("12"))]
+        );
+        assert_eq!(timeline.opened, None);
+        assert_eq!(timeline.fees, d("0.1"));
+    }
+
+    /// Hourly candles closing at `closes`, each spanning ±1 around its close.
+    fn candles(closes: &[f64]) -> Vec<Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let open = i as u64 * HOUR_MS;
+                serde_json::from_value(json!({
+                    "t": open, "T": open + HOUR_MS - 1, "s": "BTC", "i": "1h",
+                    "o": close.to_string(), "c": close.to_string(),
+                    "h": (close + 1.0).to_string(), "l": (close - 1.0).to_string(),
+                    "v": "10", "n": 5
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ema_and_rsi_match_hand_computed_values() {
+        assert_eq!(ema(&[1.0, 2.0, 3.0, 4.0, 5.0], 3), Some(4.0));
+        assert_eq!(ema(&[1.0, 2.0], 3), None);
+        assert_eq!(rsi(&[1.0, 2.0, 1.0, 2.0, 1.0], 2), Some(37.5));
+        assert_eq!(rsi(&[1.0, 2.0, 3.0, 4.0], 2), Some(100.0));
+        assert_eq!(rsi(&[3.0, 3.0, 3.0], 2), Some(50.0));
+        assert_eq!(rsi(&[1.0, 2.0], 2), None);
+    }
+
+    #[test]
+    fn trend_rows_classify_up_down_and_range() {
+        let rising: Vec<f64> = (0..120).map(|i| 100.0 + i as f64).collect();
+        let row = TrendRow::from_candles(&candles(&rising)).unwrap();
+        assert_eq!(row.classify(), "up");
+        assert_eq!((row.swing_high, row.swing_low), (220.0, 169.0));
+
+        let falling: Vec<f64> = (0..120).map(|i| 300.0 - i as f64).collect();
+        assert_eq!(
+            TrendRow::from_candles(&candles(&falling))
+                .unwrap()
+                .classify(),
+            "down"
+        );
+
+        // A sharp bounce in a downtrend: above the fast average, which is
+        // still below the slow one.
+        let mut bounce = falling.clone();
+        bounce.extend([190.0, 200.0, 210.0]);
+        let row = TrendRow::from_candles(&candles(&bounce)).unwrap();
+        assert!(row.close > row.ema20 && row.ema20 < row.ema50);
+        assert_eq!(row.classify(), "range");
+
+        // A rally that just rolled over: above the slow average, below the fast.
+        let mut pullback = rising.clone();
+        pullback.extend((1..=8).map(|i| 219.0 - 3.0 * i as f64));
+        let row = TrendRow::from_candles(&candles(&pullback)).unwrap();
+        assert!(row.close < row.ema20 && row.ema20 > row.ema50);
+        assert_eq!(row.classify(), "range");
+
+        // Not enough history for EMA(50).
+        assert!(TrendRow::from_candles(&candles(&rising[..40])).is_none());
+    }
+
+    #[test]
+    fn sparkline_scales_between_min_and_max() {
+        assert_eq!(sparkline(&[0.0, 1.0, 2.0, 3.0, 7.0]), "▁▂▃▄█");
+        assert_eq!(sparkline(&[-4.0, 4.0, 0.0]), "▁█▅");
+        assert_eq!(sparkline(&[2.0, 2.0]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    fn minute_candle(close_time: u64, close: &str) -> Candle {
+        serde_json::from_value(json!({
+            "t": close_time - MINUTE_MS + 1, "T": close_time, "s": "BTC", "i": "1m",
+            "o": close, "c": close, "h": close, "l": close, "v": "1", "n": 1
+        }))
+        .unwrap()
+    }
+
+    fn exec(time: u64, is_buy: bool, crossed: bool, px: &str, sz: &str) -> ExecFill {
+        ExecFill {
+            time,
+            px: d(px),
+            sz: d(sz),
+            is_buy,
+            crossed,
+            fee: d("0.05"),
+        }
+    }
+
+    #[test]
+    fn execution_slippage_is_signed_against_the_nearest_minute_close() {
+        let candles = [minute_candle(60_000, "100"), minute_candle(120_000, "200")];
+        let fills = [
+            // Paying above mid on a buy and receiving below it on a sell
+            // both cost.
+            exec(60_500, true, true, "101", "1"),
+            exec(61_000, false, true, "99", "2"),
+            // A buy below mid is a gain.
+            exec(119_000, true, true, "199", "1"),
+            // Makers pay no spread.
+            exec(60_000, true, false, "90", "1"),
+            // The nearest close is more than a minute away.
+            exec(400_000, true, true, "500", "1"),
+        ];
+        let stats = summarize_execution(&fills, &candles);
+
+        assert_eq!(stats.fills, 5);
+        assert_eq!(stats.maker_fills, 1);
+        assert_eq!(stats.priced_taker_fills, 3);
+        assert_eq!(stats.taker_mid_notional, d("500"));
+        assert_eq!(stats.slippage_cost, d("2"));
+        assert_eq!(stats.slippage_bps(), Some(d("40")));
+        assert_eq!(stats.notional, d("1088"));
+        assert_eq!(stats.fees, d("0.25"));
+    }
+
+    #[test]
+    fn execution_bps_need_a_notional() {
+        let empty = CoinExecution::default();
+        assert_eq!(empty.slippage_bps(), None);
+        assert_eq!(empty.fee_bps(), None);
+
+        // Maker-only fills have fees to compare but no slippage.
+        let makers = summarize_execution(&[exec(60_000, true, false, "100", "5")], &[]);
+        assert_eq!(makers.slippage_bps(), None);
+        assert_eq!(makers.fee_bps(), Some(d("1")));
+    }
+
+    #[test]
+    fn exec_fill_values_non_usdc_fees_at_the_fill_price() {
+        let raw = |fee_token: &str| {
+            json!({
+                "coin": "@107", "time": 1_000, "px": "20", "sz": "3", "side": "B",
+                "crossed": true, "fee": "0.01", "feeToken": fee_token
+            })
+        };
+        let spot = raw("HYPE");
+        let (coin, fill) = exec_fill(&spot).unwrap();
+        assert_eq!(coin, "@107");
+        assert_eq!(fill.fee, d("0.2"));
+        assert!(fill.is_buy && fill.crossed);
+
+        let usdc = raw("USDC");
+        assert_eq!(exec_fill(&usdc).unwrap().1.fee, d("0.01"));
+
+        assert!(exec_fill(&json!({ "coin": "BTC", "time": 1_000, "sz": "1" })).is_none());
+    }
+}
@@ -0,0 +1,818 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use alloy::signers::local::PrivateKeySigner;
+use hypersdk::Address;
+use rmcp::{model::*, schemars};
+use serde_json::json;
+
+use crate::breaker::{BreakerState, FAILURE_WINDOW, PROBE_INTERVAL};
+use crate::logging;
+use crate::nonce::{CLOCK_SKEW_THRESHOLD_MS, SkewMeasurement};
+use crate::state::{ServerState, VaultLeader, mcp_err};
+use crate::tools::timestamps::{fmt_instant, fmt_time_secs};
+
+const DEFAULT_LOG_LEVEL_MINUTES: u64 = 15;
+/// Longest temporary log filter, one week. Longer ones can use 0 instead.
+const MAX_LOG_LEVEL_MINUTES: u64 = 7 * 24 * 60;
+
+/// Every environment variable the server reads, besides per-profile ones.
+const KNOWN_VARS: &[&str] = &[
+    "HYPERLIQUID_PRIVATE_KEY",
+    "HYPERLIQUID_AGENT_PRIVATE_KEY",
+    "HYPERLIQUID_WALLET_ADDRESS",
+    "HYPERLIQUID_VAULT_ADDRESS",
+    "HYPERLIQUID_NETWORK",
+    "HYPERLIQUID_BUILDER_MAX_FEE",
+    "HYPERLIQUID_SIGNATURE_CHAIN_ID",
+    "HYPERLIQUID_MARKET_BUFFER_BPS",
+    "HYPERLIQUID_DEFAULT_ORDER_TYPE",
+    "HYPERLIQUID_DEFAULT_TIF",
+    "HYPERLIQUID_MAX_OPEN_ORDERS",
+    "HYPERLIQUID_MAX_OPEN_ORDERS_PER_COIN",
+    "HYPERLIQUID_MAX_POSITION_NOTIONAL",
+    "HYPERLIQUID_SAMPLE_BOOKS",
+    "HYPERLIQUID_SAMPLE_INTERVAL_SECS",
+    "HYPERLIQUID_POSITION_WARN_MULTIPLE",
+    "HYPERLIQUID_IMPACT_GUARD_MULTIPLE",
+    "HYPERLIQUID_PRICE_BAND_PCT",
+    "HYPERLIQUID_FUNDING_ALERT_USD",
+    "HYPERLIQUID_ORDER_LATENCY_BUDGET_SECS",
+    "HYPERLIQUID_HIDE_BUILDER_NUDGE",
+    "HYPERLIQUID_HIDE_EQUITY_FOOTER",
+    "HYPERLIQUID_PLAIN_NUMBERS",
+    "HYPERLIQUID_ENABLE_WITHDRAWALS",
+    "HYPERLIQUID_ENABLE_RAW_QUERIES",
+    "HYPERLIQUID_AUTO_CANCEL_ORPHAN_STOPS",
+    "HYPERLIQUID_EMERGENCY_PHRASE",
+    "HYPERLIQUID_BREAKER_FAILURES",
+    "HYPERLIQUID_PAPER_BALANCE",
+    "HYPERLIQUID_PAPER_SLIPPAGE_BPS",
+    "HYPERLIQUID_TRADING_HOURS",
+    "HYPERLIQUID_DISPLAY_TZ",
+    "HYPERLIQUID_RAW_QUERY_TYPES",
+    "HYPERLIQUID_LOG_FILE",
+    "HYPERLIQUID_LOG_ROTATION",
+    "HYPERLIQUID_LOG_MAX_FILES",
+    "HYPERLIQUID_LOG_STDERR",
+    "REALTIME_ENABLED",
+    "RUST_LOG",
+];
+
+/// Longest `raw_info_query` response returned before truncating.
+const MAX_RAW_QUERY_CHARS: usize = 20_000;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RawInfoQueryRequest {
+    #[schemars(
+        description = "Info request body, e.g. {\"type\": \"userRateLimit\", \"user\": \"0x...\"}. The type must be on the safelist."
+    )]
+    pub request: serde_json::Map<String, serde_json::Value>,
+}
+
+pub async fn raw_info_query(
+    state: &ServerState,
+    req: RawInfoQueryRequest,
+) -> Result<CallToolResult, ErrorData> {
+    if !state.raw_queries_enabled {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "raw_info_query is disabled. Set HYPERLIQUID_ENABLE_RAW_QUERIES=true to enable it.",
+        )]));
+    }
+    let Some(kind) = req.request.get("type").and_then(|t| t.as_str()) else {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "The request needs a string \"type\" field, e.g. \"userRateLimit\".",
+        )]));
+    };
+    if !state.raw_query_types.iter().any(|t| t == kind) {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Info type '{kind}' is not on the safelist. Allowed: {}. \
+             Add read-only types with HYPERLIQUID_RAW_QUERY_TYPES.",
+            state.raw_query_types.join(", ")
+        ))]));
+    }
+    // Signed exchange payloads have no business on the info endpoint.
+    if let Some(key) = ["action", "signature", "nonce"]
+        .into_iter()
+        .find(|k| req.request.contains_key(*k))
+    {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "The request carries a '{key}' field, which belongs to exchange actions. \
+             raw_info_query only forwards plain info queries."
+        ))]));
+    }
+
+    let response = state
+        .raw_info_request(serde_json::Value::Object(req.request.clone()))
+        .await?;
+    let mut body = serde_json::to_string_pretty(&response)
+        .map_err(|e| mcp_err(&format!("Failed to format response: {e}")))?;
+    let total = body.len();
+    if total > MAX_RAW_QUERY_CHARS {
+        let mut cut = MAX_RAW_QUERY_CHARS;
+        while !body.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        body.truncate(cut);
+        body.push_str(&format!(
+            "\n... truncated ({total} characters in full; narrow the query to see the rest)"
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "## Info: {kind}\n\n```json\n{body}\n```"
+    ))]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetRecentErrorsRequest {
+    #[schemars(description = "Number of recent failed calls to show (default 20, max 100)")]
+    pub limit: Option<u32>,
+}
+
+pub async fn get_recent_errors(
+    state: &ServerState,
+    req: GetRecentErrorsRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let limit = req.limit.unwrap_or(20).clamp(1, 100) as usize;
+    let failed = state.call_log.recent(limit);
+    if failed.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "No failed tool calls since the server started.",
+        )]));
+    }
+
+    let mut output = format!("## Recent Failed Tool Calls ({})\n\n", failed.len());
+    output.push_str("| Time | Request ID | Tool | Profile | Kind | Elapsed | Detail |\n");
+    output.push_str("|------|------------|------|---------|------|---------|--------|\n");
+    for call in &failed {
+        output.push_str(&format!(
+            "| {} | `{}` | {} | {} | {} | {} ms | {} |\n",
+            fmt_instant(call.at),
+            call.request_id,
+            call.tool,
+            call.profile.as_deref().unwrap_or("default"),
+            call.failure.label(),
+            call.elapsed_ms,
+            call.detail.replace('|', "\\|"),
+        ));
+    }
+    output.push_str(
+        "\nErrors are failed calls; refused are tool answers marking invalid input or a blocked action. \
+         Log lines for a call carry its request_id. Hex strings of 64+ digits are redacted.\n",
+    );
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetLogLevelRequest {
+    #[schemars(
+        description = "Log level or filter directive, e.g. \"debug\", \"hyperliquid_mcp=trace\". \"reset\" restores the startup filter."
+    )]
+    pub level: String,
+
+    #[schemars(
+        description = "Minutes before reverting to the startup filter (default 15, at most 10080, 0 keeps it until restart)"
+    )]
+    pub duration_minutes: Option<u64>,
+}
+
+pub async fn set_log_level(req: SetLogLevelRequest) -> Result<CallToolResult, ErrorData> {
+    let level = req.level.trim();
+    if level.eq_ignore_ascii_case("reset") {
+        let base = logging::base_filter();
+        logging::set_filter(&base, None).map_err(|e| mcp_err(&format!("{e:#}")))?;
+        let shown = if base.is_empty() { "(none)" } else { &base };
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "Log filter restored to startup value: {shown}"
+        ))]));
+    }
+
+    let minutes = req.duration_minutes.unwrap_or(DEFAULT_LOG_LEVEL_MINUTES);
+    if minutes > MAX_LOG_LEVEL_MINUTES {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "duration_minutes can be at most {MAX_LOG_LEVEL_MINUTES} (one week); \
+             use 0 to keep the filter until restart."
+        ))]));
+    }
+    let revert_after = (minutes > 0).then(|| Duration::from_secs(minutes * 60));
+    logging::set_filter(level, revert_after).map_err(|e| mcp_err(&format!("{e:#}")))?;
+
+    let output = match revert_after {
+        Some(_) => format!("Log filter set to `{level}` for {minutes} minutes."),
+        None => format!("Log filter set to `{level}` until restart."),
+    };
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetNonceHealthRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetServerHealthRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+pub async fn get_nonce_health(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let clock = &state.nonce;
+    let skew = clock.sync(&state.http, state.chain).await;
+
+    let mut output = "## Nonce Health\n\n".to_string();
+    output.push_str("| Metric | Value |\n");
+    output.push_str("|--------|-------|\n");
+    output.push_str(&format!(
+        "| Last nonce | {} |\n",
+        match clock.last() {
+            0 => "none issued yet".to_string(),
+            n => n.to_string(),
+        }
+    ));
+    output.push_str(&format!("| Actions signed | {} |\n", clock.issued()));
+    output.push_str(&format!(
+        "| Local clock | {} |\n",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f UTC")
+    ));
+    match &skew {
+        Ok(m) => output.push_str(&format!(
+            "| Exchange clock skew | {:+} ms (exchange minus local) |\n",
+            m.skew_ms
+        )),
+        Err(e) => output.push_str(&format!("| Exchange clock skew | unavailable: {e} |\n")),
+    }
+    output.push_str(&format!(
+        "| Nonce clock adjustment | {} |\n",
+        match clock.offset_ms() {
+            0 => format!("none (drift within {CLOCK_SKEW_THRESHOLD_MS} ms)"),
+            offset => format!("{offset:+} ms"),
+        }
+    ));
+
+    let (total, nonce_errors) = clock.with_errors(|errors| {
+        let recent: Vec<String> = errors
+            .iter()
+            .rev()
+            .filter(|e| e.is_nonce_error)
+            .take(10)
+            .map(|e| {
+                let time = fmt_time_secs(e.time);
+                format!("| {time} | {} | {} |\n", e.nonce, e.message)
+            })
+            .collect();
+        (errors.len(), recent)
+    });
+    output.push_str(&format!("| Recent rejected actions | {total} |\n"));
+
+    output.push_str("\n### Nonce Rejections\n\n");
+    if nonce_errors.is_empty() {
+        output.push_str("None recorded since startup.\n");
+    } else {
+        output.push_str("| Time | Nonce | Error |\n");
+        output.push_str("|------|-------|-------|\n");
+        for row in &nonce_errors {
+            output.push_str(row);
+        }
+        output.push_str(
+            "\nNonce rejections trigger a clock re-check; drift beyond the threshold is compensated automatically.\n",
+        );
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckClockSkewRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+/// "3.2 s behind the exchange" for a skew of +3200 ms (exchange minus local).
+fn describe_skew(skew_ms: i64) -> String {
+    let direction = if skew_ms > 0 { "behind" } else { "ahead of" };
+    format!(
+        "{:.1} s {direction} the exchange",
+        skew_ms.abs() as f64 / 1000.0
+    )
+}
+
+/// One-line clock verdict for `get_server_health`.
+fn clock_summary(m: &SkewMeasurement, offset_ms: i64) -> String {
+    if m.exceeds_threshold() {
+        format!(
+            "**{}**; nonces shifted {offset_ms:+} ms (checked {})",
+            describe_skew(m.skew_ms),
+            fmt_time_secs(m.time)
+        )
+    } else {
+        format!(
+            "{:+} ms, within tolerance (checked {})",
+            m.skew_ms,
+            fmt_time_secs(m.time)
+        )
+    }
+}
+
+/// Re-measure local clock drift against the exchange, sample by sample.
+pub async fn check_clock_skew(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let clock = &state.nonce;
+    let m = match clock.sync(&state.http, state.chain).await {
+        Ok(m) => m,
+        Err(e) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Could not read the exchange clock: {e}. The previous nonce adjustment ({:+} ms) stays in place.",
+                clock.offset_ms()
+            ))]));
+        }
+    };
+
+    let mut output = "## Clock Skew\n\n".to_string();
+    output.push_str("| Sample | Round Trip | Skew (exchange − local) |\n");
+    output.push_str("|--------|------------|-------------------------|\n");
+    for (i, sample) in m.samples.iter().enumerate() {
+        output.push_str(&format!(
+            "| {} | {} ms | {:+} ms |\n",
+            i + 1,
+            sample.round_trip_ms,
+            sample.skew_ms
+        ));
+    }
+    output.push_str(&format!(
+        "\n**Skew:** {:+} ms, from the sample with the shortest round trip (spread {} ms across {} samples).\n",
+        m.skew_ms,
+        m.spread_ms(),
+        m.samples.len()
+    ));
+    output.push_str(&format!(
+        "**Nonce adjustment:** {}\n\n",
+        match clock.offset_ms() {
+            0 => "none".to_string(),
+            offset => format!("{offset:+} ms"),
+        }
+    ));
+
+    if m.exceeds_threshold() {
+        output.push_str(&format!(
+            "**Warning:** the local clock is {}. Nonces are shifted to the exchange clock, so actions should go through, \
+             but the system clock should be synced (NTP). Drift that changes between checks can still cause intermittent invalid-nonce rejections.\n",
+            describe_skew(m.skew_ms)
+        ));
+    } else {
+        output.push_str(&format!(
+            "The local clock is within {CLOCK_SKEW_THRESHOLD_MS} ms of the exchange; nonces need no adjustment.\n"
+        ));
+    }
+    if m.spread_ms() > CLOCK_SKEW_THRESHOLD_MS {
+        output.push_str(
+            "\nSamples disagree by more than the threshold; network latency is unstable and the reading may be off.\n",
+        );
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Whether signed actions can go through right now: the exchange circuit
+/// breaker, and how fresh the WebSocket feed is.
+pub async fn get_server_health(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let breaker = state.breaker.status();
+    let mut output = "## Server Health\n\n".to_string();
+    output.push_str("| Metric | Value |\n");
+    output.push_str("|--------|-------|\n");
+    output.push_str(&format!(
+        "| Mode | {} |\n",
+        if state.read_only {
+            "read-only"
+        } else if state.dry_run {
+            "dry run"
+        } else {
+            "trading"
+        }
+    ));
+    output.push_str(&format!(
+        "| Exchange circuit breaker | {} |\n",
+        if state.breaker.threshold() == 0 {
+            "disabled (HYPERLIQUID_BREAKER_FAILURES=0)"
+        } else {
+            breaker.state.label()
+        }
+    ));
+    if let Some(opened) = breaker.opened_at {
+        output.push_str(&format!("| Degraded since | {} |\n", fmt_instant(opened)));
+    }
+    output.push_str(&format!(
+        "| Consecutive exchange failures | {} (opens at {} within {} s of each other) |\n",
+        breaker.consecutive_failures,
+        state.breaker.threshold(),
+        FAILURE_WINDOW.as_secs(),
+    ));
+    if let Some(error) = &breaker.last_error {
+        output.push_str(&format!("| Last exchange error | {error} |\n"));
+    }
+    let ws = if !state.cache.live {
+        "disabled (HTTP only)".to_string()
+    } else {
+        match state
+            .cache
+            .last_message_ms
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            0 => "connected, no data yet".to_string(),
+            last => {
+                let age = (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(last);
+                format!("last message {:.1}s ago", age as f64 / 1000.0)
+            }
+        }
+    };
+    output.push_str(&format!("| WebSocket feed | {ws} |\n"));
+    output.push_str(&format!(
+        "| Clock vs exchange | {} |\n",
+        match state.nonce.measurement() {
+            Some(m) => clock_summary(&m, state.nonce.offset_ms()),
+            None => "not measured (exchange clock unreachable)".to_string(),
+        }
+    ));
+    if let Some(expiry) = state.agent_expiry_summary() {
+        output.push_str(&format!("| Agent approval | {expiry} |\n"));
+    }
+    if let Some(leader) = &*state.vault_leader.read().unwrap_or_else(|e| e.into_inner()) {
+        output.push_str(&format!("| Vault leader | {} |\n", leader.describe()));
+    }
+    output.push_str(&format!(
+        "| Restored at startup | {} |\n",
+        if state.restored_state.is_empty() {
+            "nothing".to_string()
+        } else {
+            state.restored_state.join("; ")
+        }
+    ));
+
+    if breaker.state != BreakerState::Closed {
+        output.push_str(&format!(
+            "\nSigned actions are paused; read-only tools still work. The exchange is probed every {} s and trading resumes on the first answer.\n",
+            PROBE_INTERVAL.as_secs()
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// The `HYPERLIQUID_TRADING_HOURS` schedule and whether write tools are
+/// allowed right now.
+pub async fn get_trading_window(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let Some(hours) = &state.trading_hours else {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "## Trading Window\n\nNo schedule is configured (HYPERLIQUID_TRADING_HOURS is not set); \
+             write tools are allowed at any time.",
+        )]));
+    };
+
+    let now = chrono::Utc::now();
+    let open = hours.is_open(now);
+    let mut output = "## Trading Window\n\n".to_string();
+    output.push_str("| Metric | Value |\n");
+    output.push_str("|--------|-------|\n");
+    output.push_str(&format!("| Setting | `{}` |\n", hours.spec()));
+    output.push_str(&format!("| Schedule | {} |\n", hours.describe()));
+    output.push_str(&format!("| Now | {} |\n", hours.format_time(now)));
+    output.push_str(&format!(
+        "| Status | {} |\n",
+        if open { "open" } else { "closed" }
+    ));
+    match hours.next_change(now) {
+        Some(at) => output.push_str(&format!(
+            "| {} | {} |\n",
+            if open { "Closes" } else { "Reopens" },
+            hours.format_time(at)
+        )),
+        None => output.push_str("| Closes | never (open around the clock) |\n"),
+    }
+    output.push_str(
+        "\nOutside the window every write tool is refused except `cancel_order`, \
+         `cancel_all_orders`, `schedule_cancel` and `emergency_close_all`. \
+         Read-only tools are unaffected.\n",
+    );
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Where a variable's value came from. Values already in the process
+/// environment win over the env file.
+#[derive(Default)]
+struct VarSource {
+    in_file: Option<String>,
+    in_process: Option<String>,
+}
+
+impl VarSource {
+    fn value(&self) -> Option<&str> {
+        self.in_process.as_deref().or(self.in_file.as_deref())
+    }
+
+    fn describe(&self) -> &'static str {
+        match (&self.in_file, &self.in_process) {
+            (Some(file), Some(process)) if file != process => {
+                "env file, overridden by process environment"
+            }
+            (Some(_), _) => "env file",
+            (None, Some(_)) => "process environment",
+            (None, None) => "not set",
+        }
+    }
+}
+
+fn is_profile_var(name: &str) -> bool {
+    name.strip_prefix(crate::config::PROFILE_PREFIX)
+        .is_some_and(|rest| {
+            [
+                crate::config::PROFILE_AGENT_KEY_SUFFIX,
+                crate::config::PROFILE_WALLET_ADDRESS_SUFFIX,
+            ]
+            .iter()
+            .any(|suffix| rest.len() > suffix.len() && rest.ends_with(suffix))
+        })
+}
+
+/// `HYPERLIQUID_MAX_POSITION_NOTIONAL_<COIN>` overrides.
+fn is_position_limit_var(name: &str) -> bool {
+    name.strip_prefix(crate::config::MAX_POSITION_NOTIONAL_VAR)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .is_some_and(|coin| !coin.is_empty())
+}
+
+/// Levenshtein distance, for suggesting the variable a typo was meant to be.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = (prev + usize::from(ca != *cb))
+                .min(row[j] + 1)
+                .min(current + 1);
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+fn closest_known(name: &str) -> Option<&'static str> {
+    KNOWN_VARS
+        .iter()
+        .map(|known| (edit_distance(name, known), *known))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+fn parse_key(value: &str) -> Result<Address, String> {
+    value
+        .trim()
+        .trim_start_matches("0x")
+        .parse::<PrivateKeySigner>()
+        .map(|signer| signer.address())
+        .map_err(|e| e.to_string())
+}
+
+pub async fn diagnose_config(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    let mut problems: Vec<String> = Vec::new();
+    let mut vars: BTreeMap<String, VarSource> = BTreeMap::new();
+
+    let mut output = "## Configuration Diagnosis\n\n".to_string();
+    output.push_str(&format!(
+        "Network: {}\n",
+        if state.chain == hypersdk::hypercore::Chain::Mainnet {
+            "mainnet"
+        } else {
+            "testnet"
+        }
+    ));
+
+    // Re-read the env file. Keys are scanned line by line as well, since a key
+    // with stray whitespace is otherwise silently ignored.
+    match &state.env_file {
+        None => output.push_str("Env file: none (no home directory)\n"),
+        Some(path) => match std::fs::read_to_string(path) {
+            Err(_) => output.push_str(&format!("Env file: `{}` (not found)\n", path.display())),
+            Ok(contents) => {
+                output.push_str(&format!("Env file: `{}`\n", path.display()));
+                for (n, line) in contents.lines().enumerate() {
+                    let line = line.trim_start();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let line = line.strip_prefix("export ").unwrap_or(line);
+                    let Some((raw_key, _)) = line.split_once('=') else {
+                        problems.push(format!("Line {} has no `=`: it is ignored.", n + 1));
+                        continue;
+                    };
+                    if raw_key != raw_key.trim() {
+                        problems.push(format!(
+                            "Line {}: `{}` has whitespace around the name. Remove it so the variable is recognized.",
+                            n + 1,
+                            raw_key.trim()
+                        ));
+                    }
+                }
+                match dotenvy::from_path_iter(path) {
+                    Ok(iter) => {
+                        for item in iter {
+                            match item {
+                                Ok((key, value)) => {
+                                    vars.entry(key).or_default().in_file = Some(value);
+                                }
+                                Err(e) => problems.push(format!("Env file parse error: {e}")),
+                            }
+                        }
+                    }
+                    Err(e) => problems.push(format!("Env file could not be read: {e}")),
+                }
+            }
+        },
+    }
+
+    for (key, value) in std::env::vars() {
+        if key.starts_with("HYPERLIQUID_") || KNOWN_VARS.contains(&key.as_str()) {
+            vars.entry(key).or_default().in_process = Some(value);
+        }
+    }
+
+    output.push_str("\n### Variables\n\n");
+    output.push_str("| Variable | Source | Value |\n");
+    output.push_str("|----------|--------|-------|\n");
+    for (name, source) in &vars {
+        let known = KNOWN_VARS.contains(&name.as_str())
+            || is_profile_var(name)
+            || is_position_limit_var(name);
+        if !known && !name.starts_with("HYPERLIQUID_") {
+            continue;
+        }
+        let value = match source.value() {
+            _ if name.ends_with("PRIVATE_KEY") || name == "HYPERLIQUID_EMERGENCY_PHRASE" => {
+                "(hidden)".to_string()
+            }
+            Some(v) => format!("`{v}`"),
+            None => "—".to_string(),
+        };
+        output.push_str(&format!("| {name} | {} | {value} |\n", source.describe()));
+        if !known {
+            problems.push(match closest_known(name) {
+                Some(suggestion) => {
+                    format!("`{name}` is not a recognized variable. Did you mean `{suggestion}`?")
+                }
+                None => format!("`{name}` is not a recognized variable and is ignored."),
+            });
+        }
+    }
+
+    output.push_str("\n### Keys and Addresses\n\n");
+    let mut main_address = None;
+    for name in ["HYPERLIQUID_PRIVATE_KEY", "HYPERLIQUID_AGENT_PRIVATE_KEY"] {
+        let Some(value) = vars.get(name).and_then(VarSource::value) else {
+            output.push_str(&format!("- {name}: not set\n"));
+            continue;
+        };
+        match parse_key(value) {
+            Ok(address) => {
+                output.push_str(&format!("- {name}: valid, address `{address:#x}`\n"));
+                if name == "HYPERLIQUID_PRIVATE_KEY" {
+                    main_address = Some(address);
+                }
+            }
+            Err(e) => {
+                output.push_str(&format!("- {name}: **invalid**\n"));
+                problems.push(format!("{name} is not a valid hex private key ({e})."));
+            }
+        }
+    }
+    if let Some(value) = vars
+        .get("HYPERLIQUID_WALLET_ADDRESS")
+        .and_then(VarSource::value)
+    {
+        match value.trim().parse::<Address>() {
+            Ok(address) => {
+                output.push_str(&format!("- HYPERLIQUID_WALLET_ADDRESS: `{address:#x}`\n"));
+                main_address = main_address.or(Some(address));
+            }
+            Err(_) => problems.push(format!(
+                "HYPERLIQUID_WALLET_ADDRESS `{value}` is not a valid address."
+            )),
+        }
+    }
+
+    // An agent key only works once the main account has approved it.
+    if let (Some(main), Some(agent)) = (main_address, state.agent_address)
+        && main != agent
+    {
+        let agents = state
+            .raw_info_request(json!({ "type": "extraAgents", "user": format!("{main:#x}") }))
+            .await?;
+        let approved = agents.as_array().and_then(|list| {
+            list.iter().find(|a| {
+                a.get("address")
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| v.parse::<Address>().ok())
+                    == Some(agent)
+            })
+        });
+        match approved {
+            Some(entry) => output.push_str(&format!(
+                "- Agent `{agent:#x}` is approved for `{main:#x}`{}\n",
+                entry
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .filter(|n| !n.is_empty())
+                    .map(|n| format!(" as \"{n}\""))
+                    .unwrap_or_default()
+            )),
+            None => problems.push(format!(
+                "Agent `{agent:#x}` is not an approved agent of `{main:#x}`. Orders signed by it will be rejected. \
+                 Approve it from the main wallet or run `create_agent_wallet`."
+            )),
+        }
+    }
+
+    if let Some(value) = vars
+        .get("HYPERLIQUID_VAULT_ADDRESS")
+        .and_then(VarSource::value)
+    {
+        output.push_str("\n### Vault\n\n");
+        match value.trim().parse::<Address>() {
+            Err(_) => problems.push(format!(
+                "HYPERLIQUID_VAULT_ADDRESS `{value}` is not a valid address."
+            )),
+            Ok(vault) => {
+                let details = state
+                    .raw_info_request(json!({
+                        "type": "vaultDetails",
+                        "vaultAddress": format!("{vault:#x}"),
+                    }))
+                    .await?;
+                // The running server keeps its own verdict, against the
+                // wallet it signs with, which gates vault writes.
+                if Some(vault) == state.vault_address {
+                    *state
+                        .vault_leader
+                        .write()
+                        .unwrap_or_else(|e| e.into_inner()) = Some(VaultLeader::from_details(
+                        vault,
+                        &details,
+                        state.user_address,
+                    ));
+                }
+                match VaultLeader::from_details(vault, &details, main_address) {
+                    VaultLeader::Verified => output.push_str(&format!(
+                        "`{vault:#x}` is led by the configured main wallet.\n"
+                    )),
+                    VaultLeader::Mismatch(reason) | VaultLeader::Unchecked(reason) => {
+                        problems.push(format!(
+                            "{reason}. Vault trades are refused until this is fixed. \
+                             Set HYPERLIQUID_PRIVATE_KEY to the leader's key or correct HYPERLIQUID_VAULT_ADDRESS."
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    match state.check_network().await {
+        Ok(Some(notice)) => problems.push(notice),
+        Ok(None) => {}
+        Err(e) => output.push_str(&format!("\nCould not compare networks: {e}\n")),
+    }
+
+    output.push_str("\n### Problems\n\n");
+    if problems.is_empty() {
+        output.push_str("No problems found.\n");
+    } else {
+        for problem in &problems {
+            output.push_str(&format!("- {problem}\n"));
+        }
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::text;
+
+    #[tokio::test]
+    async fn set_log_level_refuses_durations_past_a_week() {
+        let req = SetLogLevelRequest {
+            level: "debug".into(),
+            duration_minutes: Some(u64::MAX),
+        };
+        let result = set_log_level(req).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            text(&result),
+            "duration_minutes can be at most 10080 (one week); use 0 to keep the filter until restart."
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Number formatting shared by tool output: thousands separators, compact
+//! dollar amounts for volume-like columns and fixed decimals per column type.
+//! `HYPERLIQUID_PLAIN_NUMBERS` turns separators and abbreviations off for
+//! clients that parse the text.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use hypersdk::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+
+static PLAIN_NUMBERS: AtomicBool = AtomicBool::new(false);
+
+/// Set once at startup from `HYPERLIQUID_PLAIN_NUMBERS`.
+pub fn set_plain_numbers(plain: bool) {
+    PLAIN_NUMBERS.store(plain, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+thread_local! {
+    static PLAIN_NUMBERS_OVERRIDE: std::cell::Cell<Option<bool>> = const { std::cell::Cell::new(None) };
+}
+
+/// Switch plain numbers for the calling test's thread only, so tests of both
+/// modes can run in parallel.
+#[cfg(test)]
+pub fn set_plain_numbers_for_test(plain: bool) {
+    PLAIN_NUMBERS_OVERRIDE.with(|o| o.set(Some(plain)));
+}
+
+fn plain() -> bool {
+    #[cfg(test)]
+    if let Some(plain) = PLAIN_NUMBERS_OVERRIDE.with(std::cell::Cell::get) {
+        return plain;
+    }
+    PLAIN_NUMBERS.load(Ordering::Relaxed)
+}
+
+/// Insert thousands separators into the integer part of a formatted number.
+fn group_thousands(number: &str) -> String {
+    if plain() {
+        return number.to_string();
+    }
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", number),
+    };
+    let (int, frac) = match unsigned.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (unsigned, None),
+    };
+    let mut grouped = String::with_capacity(int.len() + int.len() / 3);
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    match frac {
+        Some(frac) => format!("{sign}{grouped}.{frac}"),
+        None => format!("{sign}{grouped}"),
+    }
+}
+
+/// Put the dollar sign after any minus sign: "-$1,234.50".
+fn dollars(number: String) -> String {
+    match number.strip_prefix('-') {
+        Some(rest) => format!("-${rest}"),
+        None => format!("${number}"),
+    }
+}
+
+/// Dollar amount with cents: "$1,234,567.89".
+pub fn fmt_usd(value: Decimal) -> String {
+    dollars(group_thousands(&format!("{value:.2}")))
+}
+
+/// Dollar amount abbreviated for volume, open interest and market cap
+/// columns: "$12.4M", "$532.1K", "$87".
+pub fn fmt_usd_compact(value: impl ToPrimitive) -> String {
+    let value = value.to_f64().unwrap_or(0.0);
+    if plain() {
+        return dollars(format!("{value:.0}"));
+    }
+    let abs = value.abs();
+    let scaled = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "K")]
+        .iter()
+        .find(|(unit, _)| abs >= *unit)
+        .map(|(unit, suffix)| format!("{:.1}{suffix}", value / unit));
+    dollars(scaled.unwrap_or_else(|| format!("{value:.0}")))
+}
+
+/// Price at the exchange's own precision: "$67,432.5".
+pub fn fmt_price(value: Decimal) -> String {
+    dollars(group_thousands(&value.normalize().to_string()))
+}
+
+/// Digits before the decimal point, counted exactly: 5 for 67432.5, 0 for
+/// 0.5, -4 for 0.0000123. Zero has one.
+pub fn integer_digits(value: Decimal) -> i32 {
+    if value.is_zero() {
+        return 1;
+    }
+    let mantissa_digits = value.mantissa().unsigned_abs().to_string().len() as i32;
+    mantissa_digits - value.scale() as i32
+}
+
+/// Round to `figures` significant figures. A nonzero value never becomes
+/// zero, however small: 0.0000123456 to 5 figures is 0.000012346.
+pub fn round_sig(value: Decimal, figures: u32) -> Decimal {
+    let dp = (figures as i32 - integer_digits(value)).clamp(0, 28) as u32;
+    value.round_dp(dp)
+}
+
+/// Token or contract quantity at full precision: "12,500.25".
+pub fn fmt_qty(value: Decimal) -> String {
+    group_thousands(&value.normalize().to_string())
+}
+
+/// Signed percentage with two decimals: "+1.23%".
+pub fn fmt_pct(value: impl ToPrimitive) -> String {
+    format!("{:+.2}%", value.to_f64().unwrap_or(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn numbers_are_grouped_and_abbreviated() {
+        set_plain_numbers_for_test(false);
+        assert_eq!(fmt_usd(d("1234567.891")), "$1,234,567.89");
+        assert_eq!(fmt_usd(d("-1234.5")), "-$1,234.50");
+        assert_eq!(fmt_usd(d("999")), "$999.00");
+        assert_eq!(fmt_price(d("67432.50")), "$67,432.5");
+        assert_eq!(fmt_price(d("0.000012346")), "$0.000012346");
+        assert_eq!(fmt_qty(d("12500.250")), "12,500.25");
+        assert_eq!(fmt_qty(d("-1000000")), "-1,000,000");
+        assert_eq!(fmt_pct(1.234), "+1.23%");
+        assert_eq!(fmt_pct(-0.5), "-0.50%");
+        assert_eq!(fmt_usd_compact(12_400_000.0), "$12.4M");
+        assert_eq!(fmt_usd_compact(532_149.0), "$532.1K");
+        assert_eq!(fmt_usd_compact(2.5e12), "$2.5T");
+        assert_eq!(fmt_usd_compact(-3_100_000_000.0), "-$3.1B");
+        assert_eq!(fmt_usd_compact(87.4), "$87");
+    }
+
+    #[test]
+    fn plain_numbers_drop_separators_and_abbreviations() {
+        set_plain_numbers_for_test(true);
+        assert_eq!(fmt_usd(d("1234567.891")), "$1234567.89");
+        assert_eq!(fmt_usd(d("-1234.5")), "-$1234.50");
+        assert_eq!(fmt_price(d("67432.50")), "$67432.5");
+        assert_eq!(fmt_qty(d("12500.250")), "12500.25");
+        assert_eq!(fmt_pct(1.234), "+1.23%");
+        assert_eq!(fmt_usd_compact(12_400_000.0), "$12400000");
+        assert_eq!(fmt_usd_compact(-3_100_000_000.0), "-$3100000000");
+    }
+
+    #[test]
+    fn round_sig_keeps_sub_cent_digits() {
+        assert_eq!(integer_digits(d("67432.5")), 5);
+        assert_eq!(integer_digits(d("0.5")), 0);
+        assert_eq!(integer_digits(d("0.0000123")), -4);
+        assert_eq!(integer_digits(d("0.00001230")), -4);
+        assert_eq!(integer_digits(Decimal::ZERO), 1);
+
+        assert_eq!(round_sig(d("67432.56"), 5), d("67433"));
+        assert_eq!(round_sig(d("0.0041234567"), 5), d("0.0041235"));
+        assert_eq!(round_sig(d("0.0000123456"), 5), d("0.000012346"));
+        assert_eq!(round_sig(d("0.000000001"), 5), d("0.000000001"));
+        assert_eq!(round_sig(d("-0.0000123456"), 3), d("-0.0000123"));
+    }
+}
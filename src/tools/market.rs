@@ -1,42 +1,80 @@
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
+use hypersdk::Decimal;
+use hypersdk::hypercore::types::{Candle, FundingRate};
 use rmcp::{model::*, schemars};
 use serde_json::json;
 
-use crate::cache::CachedValue;
-use crate::state::{ServerState, mcp_err};
+use crate::cache::{
+    BookSample, CachedValue, CandleSeries, CtxPoint, FundingHistory, PredictedFunding,
+};
+use crate::config;
+use crate::meta::{AssetCtx, MarginTable, MarketMeta, UniverseEntry};
+use crate::state::{MarketKind, ResolvedMarket, ServerState, mcp_err};
+use crate::tools::format::{fmt_pct, fmt_price, fmt_qty, fmt_usd, fmt_usd_compact, round_sig};
+use crate::tools::timestamps::{fmt_date, fmt_instant, fmt_time, fmt_time_secs, fmt_until};
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetMarketsRequest {
     #[schemars(description = "Filter by market type: \"perp\", \"spot\", or \"all\"")]
     pub market_type: Option<String>,
+
+    #[schemars(
+        description = "Sort by \"volume\" (default), \"open_interest\", \"funding\", \"change\" (24h %), \"market_cap\", \"oi_change\" (1h), \"volume_change\" (1h) or \"funding_drift\" (1h). Spot markets sort by volume for the perp-only keys; perps ignore market_cap."
+    )]
+    pub sort_by: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetMarketSummaryRequest {
     #[schemars(description = "Trading pair symbol, e.g. \"BTC\", \"ETH\", \"PURR/USDC\"")]
     pub coin: String,
+    #[schemars(
+        description = "\"perp\" or \"spot\" (optional; perps take precedence when a name exists in both)"
+    )]
+    pub market_kind: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetMarginTiersRequest {
+    #[schemars(description = "Perp symbol, e.g. \"BTC\"")]
+    pub coin: String,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetOrderBookRequest {
-    #[schemars(description = "Trading pair symbol, e.g. \"BTC\", \"ETH\"")]
+    #[schemars(
+        description = "Symbol, e.g. \"BTC\", \"HYPE\", \"PURR/USDC\" or a spot index like \"@107\""
+    )]
     pub coin: String,
 
     #[schemars(description = "Number of price levels per side (default 10, max 20)")]
     pub depth: Option<usize>,
+
+    #[schemars(
+        description = "\"perp\" or \"spot\" (optional; perps take precedence when a name exists in both)"
+    )]
+    pub market_kind: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetCandlesRequest {
-    #[schemars(description = "Trading pair symbol, e.g. \"BTC\", \"ETH\"")]
+    #[schemars(description = "Trading pair symbol, e.g. \"BTC\", \"ETH\", \"PURR/USDC\"")]
     pub coin: String,
 
     #[schemars(description = "Candle interval: \"1m\", \"5m\", \"15m\", \"1h\", \"4h\", \"1d\"")]
     pub interval: String,
 
-    #[schemars(description = "Number of candles to return (default 100, max 5000)")]
+    #[schemars(
+        description = "Number of candles to return (default depends on the interval, e.g. 120 for 1m, 168 for 1h, 90 for 1d; max 5000)"
+    )]
     pub count: Option<u64>,
+    #[schemars(
+        description = "\"perp\" or \"spot\" (optional; perps take precedence when a name exists in both)"
+    )]
+    pub market_kind: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -44,232 +82,605 @@ pub struct GetFundingRatesRequest {
     #[schemars(description = "Perpetual market symbol, e.g. \"BTC\", \"ETH\"")]
     pub coin: String,
 
-    #[schemars(description = "Hours of funding history to return (default 24)")]
+    #[schemars(description = "Hours of funding history to return (default 24, max 720)")]
+    pub lookback_hours: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CompareFundingRequest {
+    #[schemars(description = "First perpetual market, e.g. \"BTC\"")]
+    pub coin_a: String,
+
+    #[schemars(description = "Second perpetual market, e.g. \"ETH\"")]
+    pub coin_b: String,
+
+    #[schemars(
+        description = "Hours of funding history to compare (default 168, one week; max 720)"
+    )]
+    pub lookback_hours: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct EstimateCarryRequest {
+    #[schemars(description = "Perpetual market symbol, e.g. \"BTC\", \"ETH\"")]
+    pub coin: String,
+
+    #[schemars(description = "\"long\" or \"short\"")]
+    pub side: String,
+
+    #[schemars(description = "Position size in coin units (or use size_usd)")]
+    pub size: Option<f64>,
+
+    #[schemars(description = "Position size in USD notional, as an alternative to size")]
+    pub size_usd: Option<f64>,
+
+    #[schemars(description = "How long the position would be held, in hours")]
+    pub hours: f64,
+
+    #[schemars(
+        description = "Hours of funding history to average (default 168, one week; max 720)"
+    )]
+    pub lookback_hours: Option<u64>,
+
+    #[schemars(
+        description = "Account profile whose fee tier to use (optional, defaults to the default profile)"
+    )]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetSpotDeployStateRequest {
+    #[schemars(
+        description = "Deployer address (0x...) whose pending spot deploy to show (optional; defaults to the configured account)"
+    )]
+    pub deployer: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetSpreadHistoryRequest {
+    #[schemars(description = "Symbol listed in HYPERLIQUID_SAMPLE_BOOKS, e.g. \"BTC\", \"ETH\"")]
+    pub coin: String,
+    #[schemars(description = "\"perp\" or \"spot\" (optional)")]
+    pub market_kind: Option<String>,
+    #[schemars(
+        description = "Only use samples from the last N hours (optional, defaults to everything sampled)"
+    )]
     pub lookback_hours: Option<u64>,
 }
 
 const MARKET_SUMMARY_TTL: Duration = Duration::from_secs(5);
 
+/// Predicted funding follows the premium, so it is refetched often.
+const PREDICTED_FUNDING_TTL: Duration = Duration::from_secs(30);
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Lookbacks for the deltas in `get_market_summary`.
+const DELTA_LOOKBACKS: [(Duration, &str); 3] = [
+    (Duration::from_secs(5 * 60), "5m"),
+    (HOUR, "1h"),
+    (Duration::from_secs(4 * 60 * 60), "4h"),
+];
+/// Candle rows rendered by `get_candles`; longer windows are summarized only.
+const MAX_CANDLE_ROWS: usize = 200;
+/// `candleSnapshot` only serves the most recent 5000 candles of an interval.
+const MAX_CANDLES: u64 = 5000;
+const MINUTE_MS: u64 = 60 * 1000;
+const FUNDING_TTL: Duration = Duration::from_secs(5 * 60);
+/// `fundingHistory` returns at most this many entries per request.
+const FUNDING_PAGE_SIZE: usize = 500;
+/// Longest funding lookback the tools accept, 30 days of hourly entries.
+const MAX_FUNDING_LOOKBACK_HOURS: u64 = 720;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MarketSort {
+    Volume,
+    OpenInterest,
+    Funding,
+    Change,
+    MarketCap,
+    OiChange,
+    VolumeChange,
+    FundingDrift,
+}
+
+/// How a perp's context moved between an earlier snapshot and now.
+#[derive(Clone, Copy)]
+struct CtxDelta {
+    /// Open interest change in percent, counted in coins.
+    oi_pct: Option<f64>,
+    /// Change of the rolling 24h volume in percent. Positive means the
+    /// recent stretch traded more than the one that rolled out of the day.
+    volume_pct: Option<f64>,
+    /// Funding change in percentage points of APR.
+    funding_apr: f64,
+}
+
+impl CtxDelta {
+    fn between(then: &CtxPoint, now: &CtxPoint) -> Self {
+        let pct = |then: f64, now: f64| (then > 0.0).then(|| (now - then) / then * 100.0);
+        Self {
+            oi_pct: pct(then.open_interest, now.open_interest),
+            volume_pct: pct(then.day_volume, now.day_volume),
+            // Funding is an hourly rate
+            funding_apr: (now.funding - then.funding) * 24.0 * 365.0 * 100.0,
+        }
+    }
+}
+
+fn fmt_drift(points: f64) -> String {
+    format!("{points:+.2} pts")
+}
+
+/// Why delta columns are empty: the snapshot history is too short.
+async fn history_note(state: &ServerState) -> String {
+    match state.cache.meta_history_start().await {
+        Some(start) => format!(
+            "\nOI, volume and funding deltas fill in as snapshot history builds up (history starts {}).\n",
+            fmt_time_secs(start)
+        ),
+        None => "\nOI, volume and funding deltas need snapshot history, which is recorded \
+                 from the metaAndAssetCtxs poll (REALTIME_ENABLED=true).\n"
+            .to_string(),
+    }
+}
+
+struct MarketRow<'a> {
+    name: &'a str,
+    price: String,
+    /// Whether `price` is a live mid rather than the asset context's price.
+    live_mid: bool,
+    volume: f64,
+    /// Open interest in USD notional (perps only)
+    open_interest: Option<f64>,
+    /// Current funding, annualized percent (perps only)
+    funding_apr: Option<f64>,
+    change_pct: Option<f64>,
+    /// Circulating supply times price (spot only)
+    market_cap: Option<f64>,
+    /// Change over the last hour (perps with history only)
+    delta: Option<CtxDelta>,
+}
+
+impl MarketRow<'_> {
+    fn sort_key(&self, sort: MarketSort) -> f64 {
+        match sort {
+            MarketSort::Volume => self.volume,
+            MarketSort::OpenInterest => self.open_interest.unwrap_or(f64::MIN),
+            MarketSort::Funding => self.funding_apr.unwrap_or(f64::MIN),
+            MarketSort::Change => self.change_pct.unwrap_or(f64::MIN),
+            MarketSort::MarketCap => self.market_cap.unwrap_or(f64::MIN),
+            MarketSort::OiChange => self.delta.and_then(|d| d.oi_pct).unwrap_or(f64::MIN),
+            MarketSort::VolumeChange => self.delta.and_then(|d| d.volume_pct).unwrap_or(f64::MIN),
+            MarketSort::FundingDrift => self.delta.map_or(f64::MIN, |d| d.funding_apr),
+        }
+    }
+}
+
+fn to_f64(value: Option<Decimal>) -> Option<f64> {
+    rust_decimal::prelude::ToPrimitive::to_f64(&value?)
+}
+
+fn fmt_opt(value: Option<f64>, fmt: impl Fn(f64) -> String) -> String {
+    value.map(fmt).unwrap_or_else(|| "N/A".into())
+}
+
+fn market_rows<'a>(
+    meta: &'a MarketMeta,
+    mids: &std::collections::HashMap<String, hypersdk::Decimal>,
+    perp: bool,
+    earlier: Option<&HashMap<String, CtxPoint>>,
+) -> Vec<MarketRow<'a>> {
+    meta.universe
+        .iter()
+        .enumerate()
+        .map(|(i, asset)| {
+            let name = asset.name.as_str();
+            let ctx = meta.ctxs.get(i);
+            let ctx_px = ctx.and_then(AssetCtx::price);
+            let px = to_f64(ctx_px);
+            let live_mid = mids.get(name);
+            let price = match live_mid {
+                Some(mid) => fmt_price(*mid),
+                None => ctx_px.map(fmt_price).unwrap_or_else(|| "N/A".into()),
+            };
+            let change_pct = match (px, to_f64(ctx.and_then(|c| c.prev_day_px))) {
+                (Some(px), Some(prev)) if prev > 0.0 => Some((px - prev) / prev * 100.0),
+                _ => None,
+            };
+            let (open_interest, funding_apr) = if perp {
+                (
+                    to_f64(ctx.and_then(|c| c.open_interest))
+                        .zip(px)
+                        .map(|(oi, px)| oi * px),
+                    // Funding is an hourly rate
+                    to_f64(ctx.and_then(|c| c.funding)).map(|f| f * 24.0 * 365.0 * 100.0),
+                )
+            } else {
+                (None, None)
+            };
+            let market_cap = if perp {
+                None
+            } else {
+                to_f64(ctx.and_then(|c| c.circulating_supply))
+                    .zip(px)
+                    .map(|(supply, px)| supply * px)
+            };
+            MarketRow {
+                name,
+                price,
+                live_mid: live_mid.is_some(),
+                volume: to_f64(ctx.and_then(|c| c.day_ntl_vlm)).unwrap_or(0.0),
+                open_interest,
+                funding_apr,
+                change_pct,
+                market_cap,
+                delta: earlier
+                    .and_then(|earlier| earlier.get(name))
+                    .zip(ctx.and_then(CtxPoint::from_ctx))
+                    .map(|(then, now)| CtxDelta::between(then, &now)),
+            }
+        })
+        .collect()
+}
+
 pub async fn get_markets(
     state: &ServerState,
     req: GetMarketsRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let market_type = req.market_type.as_deref().unwrap_or("all");
-
-    let ws_mids = state.cache.all_mids.borrow().clone();
-    let mids = if !ws_mids.is_empty() {
-        tracing::debug!("get_markets: using WS-cached mid prices");
-        ws_mids
-    } else {
-        state
-            .client
-            .all_mids(None)
-            .await
-            .map_err(|e| mcp_err(&format!("Failed to fetch mid prices: {e}")))?
+    let market_type = req
+        .market_type
+        .as_deref()
+        .map(str::to_lowercase)
+        .unwrap_or_else(|| "all".into());
+    let (want_perp, want_spot) = match market_type.as_str() {
+        "all" => (true, true),
+        "perp" | "perps" => (true, false),
+        "spot" => (false, true),
+        other => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid market_type '{other}'. Use \"perp\", \"spot\" or \"all\"."
+            ))]));
+        }
+    };
+    let sort = match req.sort_by.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("volume") => MarketSort::Volume,
+        Some("open_interest" | "oi") => MarketSort::OpenInterest,
+        Some("funding") => MarketSort::Funding,
+        Some("change" | "24h_change") => MarketSort::Change,
+        Some("market_cap" | "mcap") => MarketSort::MarketCap,
+        Some("oi_change") => MarketSort::OiChange,
+        Some("volume_change") => MarketSort::VolumeChange,
+        Some("funding_drift") => MarketSort::FundingDrift,
+        Some(other) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid sort_by '{other}'. Use \"volume\", \"open_interest\", \"funding\", \"change\", \"market_cap\", \"oi_change\", \"volume_change\" or \"funding_drift\"."
+            ))]));
+        }
     };
 
+    // Live mids when the WebSocket feed has them; otherwise each row uses the
+    // price already in its asset context, so no extra request is needed.
+    let mids = state.cache.all_mids.borrow().clone();
+
     let mut output = String::new();
 
-    if market_type == "all" || market_type == "perp" {
-        let meta_data = get_cached_meta(state).await?;
-        let (universe, ctxs) = parse_meta_and_ctxs(&meta_data);
-
-        let mut rows: Vec<(&str, String, f64)> = Vec::new();
-        if let Some(universe) = universe {
-            for (i, asset) in universe.iter().enumerate() {
-                let name = asset.get("name").and_then(|n| n.as_str()).unwrap_or("?");
-                let price = mids
-                    .get(name)
-                    .map(|p| format!("${p}"))
-                    .unwrap_or_else(|| "N/A".into());
-                let volume = ctxs
-                    .and_then(|c| c.get(i))
-                    .and_then(|ctx| ctx.get("dayNtlVlm"))
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| s.parse::<f64>().ok())
-                    .unwrap_or(0.0);
-                rows.push((name, price, volume));
-            }
-        }
-        rows.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    if want_perp {
+        let meta = get_cached_meta(state).await?;
+
+        let capped = get_capped_perps(state).await?;
+
+        let perp_sort = match sort {
+            MarketSort::MarketCap => MarketSort::Volume,
+            other => other,
+        };
+        let hour_ago = state.cache.meta_ago(HOUR).await;
+        let mut rows = market_rows(&meta, &mids, true, hour_ago.as_ref().map(|h| &h.1));
+        rows.sort_by(|a, b| b.sort_key(perp_sort).total_cmp(&a.sort_key(perp_sort)));
+        let price_note = price_source_note(&rows, "mark prices");
 
         output.push_str(&format!("## Perpetual Markets ({} total)\n\n", rows.len()));
-        output.push_str("| Market | Price | 24h Volume |\n");
-        output.push_str("|--------|-------|------------|\n");
-        for (name, price, volume) in &rows {
-            output.push_str(&format!("| {} | {} | ${:.0} |\n", name, price, volume));
+        output.push_str(
+            "| Market | Price | 24h Change | 24h Volume | Open Interest | Funding (APR) | OI 1h | Volume 1h | Funding Drift 1h | OI Cap |\n",
+        );
+        output.push_str(
+            "|--------|-------|------------|------------|---------------|---------------|-------|-----------|------------------|--------|\n",
+        );
+        for r in &rows {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+                r.name,
+                r.price,
+                fmt_opt(r.change_pct, fmt_pct),
+                fmt_usd_compact(r.volume),
+                fmt_opt(r.open_interest, fmt_usd_compact),
+                fmt_opt(r.funding_apr, fmt_pct),
+                fmt_opt(r.delta.and_then(|d| d.oi_pct), fmt_pct),
+                fmt_opt(r.delta.and_then(|d| d.volume_pct), fmt_pct),
+                fmt_opt(r.delta.map(|d| d.funding_apr), fmt_drift),
+                if capped.iter().any(|c| c == r.name) {
+                    "At cap"
+                } else {
+                    ""
+                },
+            ));
+        }
+        output.push_str(&price_note);
+        if hour_ago.is_none() {
+            output.push_str(&history_note(state).await);
+        }
+        if !capped.is_empty() {
+            output.push_str(&format!(
+                "\n{} market(s) at their open interest cap only accept orders that reduce open interest.\n",
+                capped.len()
+            ));
         }
         output.push('\n');
     }
 
-    if market_type == "all" || market_type == "spot" {
-        let spot_data = get_cached_spot_meta(state).await?;
-        let (universe, ctxs) = parse_meta_and_ctxs(&spot_data);
-
-        let mut rows: Vec<(&str, String, f64)> = Vec::new();
-        if let Some(universe) = universe {
-            for (i, asset) in universe.iter().enumerate() {
-                let name = asset.get("name").and_then(|n| n.as_str()).unwrap_or("?");
-                let price = mids
-                    .get(name)
-                    .map(|p| format!("${p}"))
-                    .unwrap_or_else(|| "N/A".into());
-                let volume = ctxs
-                    .and_then(|c| c.get(i))
-                    .and_then(|ctx| ctx.get("dayNtlVlm"))
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| s.parse::<f64>().ok())
-                    .unwrap_or(0.0);
-                rows.push((name, price, volume));
-            }
-        }
-        rows.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    if want_spot {
+        let spot_meta = get_cached_spot_meta(state).await?;
+
+        let spot_sort = match sort {
+            MarketSort::Change | MarketSort::MarketCap => sort,
+            _ => MarketSort::Volume,
+        };
+        let mut rows = market_rows(&spot_meta, &mids, false, None);
+        rows.sort_by(|a, b| b.sort_key(spot_sort).total_cmp(&a.sort_key(spot_sort)));
 
         output.push_str(&format!("## Spot Markets ({} total)\n\n", rows.len()));
-        output.push_str("| Market | Price | 24h Volume |\n");
-        output.push_str("|--------|-------|------------|\n");
-        for (name, price, volume) in &rows {
-            output.push_str(&format!("| {} | {} | ${:.0} |\n", name, price, volume));
+        output.push_str(
+            "| Market | Price | 24h Change | 24h Volume | Market Cap | Size Decimals | EVM |\n",
+        );
+        output.push_str(
+            "|--------|-------|------------|------------|------------|---------------|-----|\n",
+        );
+        for r in &rows {
+            let pair = state.spot_pairs.iter().find(|p| p.name == r.name);
+            let display = pair
+                .map(|p| format!("{}/{}", p.base, p.quote))
+                .unwrap_or_else(|| r.name.to_string());
+            let token = pair.and_then(|p| spot_meta.token(&p.base));
+            let sz_decimals = token
+                .and_then(|t| t.sz_decimals)
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "N/A".into());
+            // HYPE is native on the HyperEVM and has no linked contract.
+            let evm_linked = token.is_some_and(|t| {
+                t.evm_contract.is_some() || pair.is_some_and(|p| p.base == "HYPE")
+            });
+            output.push_str(&format!(
+                "| {display} | {} | {} | {} | {} | {sz_decimals} | {} |\n",
+                r.price,
+                fmt_opt(r.change_pct, fmt_pct),
+                fmt_usd_compact(r.volume),
+                fmt_opt(r.market_cap, fmt_usd_compact),
+                if evm_linked { "Yes" } else { "" },
+            ));
         }
+        output.push_str(&price_source_note(&rows, "context prices"));
         output.push('\n');
     }
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-fn parse_meta_and_ctxs(
-    data: &serde_json::Value,
-) -> (
-    Option<&Vec<serde_json::Value>>,
-    Option<&Vec<serde_json::Value>>,
-) {
-    let arr = data.as_array();
-    let universe = arr
-        .and_then(|a| a.first())
-        .and_then(|meta| meta.get("universe"))
-        .and_then(|u| u.as_array());
-    let ctxs = arr.and_then(|a| a.get(1)).and_then(|c| c.as_array());
-    (universe, ctxs)
+/// Where the Price column came from, for the line under a market table.
+fn price_source_note(rows: &[MarketRow], fallback: &str) -> String {
+    let live = rows.iter().filter(|r| r.live_mid).count();
+    if live == rows.len() {
+        "\nPrices: live mids from the WebSocket feed.\n".to_string()
+    } else if live == 0 {
+        format!("\nPrices: {fallback} from the cached asset contexts (no live mids).\n")
+    } else {
+        format!(
+            "\nPrices: live mids from the WebSocket feed; {} without a mid show {fallback} from the cached asset contexts.\n",
+            rows.len() - live
+        )
+    }
+}
+
+/// Universe entry and asset context for a market, from the cached
+/// `metaAndAssetCtxs` / `spotMetaAndAssetCtxs` responses.
+pub(crate) async fn asset_ctx(
+    state: &ServerState,
+    market: &ResolvedMarket,
+) -> Result<Option<(UniverseEntry, AssetCtx)>, ErrorData> {
+    let meta = match market.kind {
+        MarketKind::Perp => get_cached_meta(state).await?,
+        MarketKind::Spot => get_cached_spot_meta(state).await?,
+    };
+    Ok(meta
+        .find(&market.coin)
+        .map(|(asset, ctx)| (asset.clone(), ctx.clone())))
 }
 
 pub async fn get_market_summary(
     state: &ServerState,
     req: GetMarketSummaryRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let perp_data = get_cached_meta(state).await?;
-
-    if let Some(arr) = perp_data.as_array() {
-        if arr.len() == 2 {
-            let meta = &arr[0];
-            let ctxs = &arr[1];
-
-            if let (Some(universe), Some(ctx_arr)) = (
-                meta.get("universe").and_then(|u| u.as_array()),
-                ctxs.as_array(),
-            ) {
-                for (i, asset) in universe.iter().enumerate() {
-                    let name = asset.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                    if name.eq_ignore_ascii_case(&req.coin) {
-                        if let Some(ctx) = ctx_arr.get(i) {
-                            let mark_px =
-                                ctx.get("markPx").and_then(|v| v.as_str()).unwrap_or("N/A");
-                            let oracle_px = ctx
-                                .get("oraclePx")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("N/A");
-                            let funding =
-                                ctx.get("funding").and_then(|v| v.as_str()).unwrap_or("N/A");
-                            let open_interest = ctx
-                                .get("openInterest")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("N/A");
-                            let day_vlm = ctx
-                                .get("dayNtlVlm")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("N/A");
-                            let prev_day_px = ctx
-                                .get("prevDayPx")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("N/A");
-                            let premium =
-                                ctx.get("premium").and_then(|v| v.as_str()).unwrap_or("N/A");
-                            let max_leverage = asset
-                                .get("maxLeverage")
-                                .and_then(|v| v.as_u64())
-                                .unwrap_or(0);
-
-                            let output = format!(
-                                "## {name} Perpetual Market\n\n\
-                                 | Metric | Value |\n\
-                                 |--------|-------|\n\
-                                 | Mark Price | ${mark_px} |\n\
-                                 | Oracle Price | ${oracle_px} |\n\
-                                 | Funding Rate | {funding} |\n\
-                                 | Premium | {premium} |\n\
-                                 | Open Interest | ${open_interest} |\n\
-                                 | 24h Volume | ${day_vlm} |\n\
-                                 | Previous Day Price | ${prev_day_px} |\n\
-                                 | Max Leverage | {max_leverage}x |\n"
-                            );
-                            return Ok(CallToolResult::success(vec![Content::text(output)]));
-                        }
-                    }
-                }
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+
+    let Some((asset, ctx)) = asset_ctx(state, &market).await? else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "No market data for {}",
+            market.describe()
+        ))]));
+    };
+
+    let field =
+        |value: Option<Decimal>| value.map(|v| v.to_string()).unwrap_or_else(|| "N/A".into());
+    let price = |value: Option<Decimal>| value.map(fmt_price).unwrap_or_else(|| "N/A".into());
+    let volume = ctx
+        .day_ntl_vlm
+        .map(fmt_usd_compact)
+        .unwrap_or_else(|| "N/A".into());
+    let output = match market.kind {
+        MarketKind::Perp => {
+            let max_leverage = asset.max_leverage.unwrap_or(0);
+            let oi_cap = if get_capped_perps(state).await?.contains(&market.coin) {
+                "Reached (new position-increasing orders are rejected)"
+            } else {
+                "Below cap"
+            };
+            // Open interest is reported in coins; show its notional too.
+            let open_interest = match (ctx.open_interest, ctx.mark_px) {
+                (Some(oi), Some(px)) => format!("{} ({})", fmt_qty(oi), fmt_usd_compact(oi * px)),
+                (Some(oi), None) => fmt_qty(oi),
+                _ => "N/A".into(),
+            };
+            let now = CtxPoint::from_ctx(&ctx);
+            let mut deltas = Vec::with_capacity(DELTA_LOOKBACKS.len());
+            for (ago, label) in DELTA_LOOKBACKS {
+                let then = state
+                    .cache
+                    .meta_ago(ago)
+                    .await
+                    .and_then(|(_, ctxs)| ctxs.get(&market.coin).copied());
+                let delta = then
+                    .zip(now)
+                    .map(|(then, now)| CtxDelta::between(&then, &now));
+                deltas.push((label, delta));
             }
+            let delta_row = |f: &dyn Fn(&CtxDelta) -> Option<String>| {
+                deltas
+                    .iter()
+                    .map(|(label, d)| {
+                        let value = d.as_ref().and_then(f).unwrap_or_else(|| "N/A".into());
+                        format!("{label} {value}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" / ")
+            };
+            let oi_change = delta_row(&|d| d.oi_pct.map(fmt_pct));
+            let volume_change = delta_row(&|d| d.volume_pct.map(fmt_pct));
+            let funding_drift = delta_row(&|d| Some(fmt_drift(d.funding_apr)));
+            let note = if deltas.iter().all(|(_, d)| d.is_none()) {
+                history_note(state).await
+            } else {
+                String::new()
+            };
+            let next_funding = next_funding(state, &market.coin, ctx.funding)
+                .await
+                .map(|next| fmt_next_funding(&next))
+                .unwrap_or_else(|| "N/A".into());
+            format!(
+                "## {} Perpetual Market\n\n\
+                 | Metric | Value |\n\
+                 |--------|-------|\n\
+                 | Mark Price | {} |\n\
+                 | Oracle Price | {} |\n\
+                 | Funding Rate | {} |\n\
+                 | Next Funding | {next_funding} |\n\
+                 | Premium | {} |\n\
+                 | Open Interest | {open_interest} |\n\
+                 | OI Change | {oi_change} |\n\
+                 | Open Interest Cap | {oi_cap} |\n\
+                 | 24h Volume | {volume} |\n\
+                 | 24h Volume Change | {volume_change} |\n\
+                 | Funding Drift (APR) | {funding_drift} |\n\
+                 | Previous Day Price | {} |\n\
+                 | Max Leverage | {max_leverage}x |\n{note}",
+                market.symbol,
+                price(ctx.mark_px),
+                price(ctx.oracle_px),
+                field(ctx.funding),
+                field(ctx.premium),
+                price(ctx.prev_day_px),
+            )
         }
+        MarketKind::Spot => format!(
+            "## {} Spot Market ({})\n\n\
+             | Metric | Value |\n\
+             |--------|-------|\n\
+             | Mark Price | {} |\n\
+             | Mid Price | {} |\n\
+             | 24h Volume | {volume} |\n\
+             | Previous Day Price | {} |\n",
+            market.symbol,
+            market.coin,
+            price(ctx.mark_px),
+            price(ctx.mid_px),
+            price(ctx.prev_day_px),
+        ),
+    };
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// The margin table of a perp, from the cached `metaAndAssetCtxs`.
+pub(crate) async fn margin_table(
+    state: &ServerState,
+    market: &ResolvedMarket,
+) -> Result<Option<MarginTable>, ErrorData> {
+    if market.kind != MarketKind::Perp {
+        return Ok(None);
     }
+    Ok(get_cached_meta(state).await?.margin_table(&market.coin))
+}
 
-    let spot_data = get_cached_spot_meta(state).await?;
-
-    if let Some(arr) = spot_data.as_array() {
-        if arr.len() == 2 {
-            let meta = &arr[0];
-            let ctxs = &arr[1];
-
-            if let (Some(universe), Some(ctx_arr)) = (
-                meta.get("universe").and_then(|u| u.as_array()),
-                ctxs.as_array(),
-            ) {
-                for (i, pair) in universe.iter().enumerate() {
-                    let name = pair.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                    if name.eq_ignore_ascii_case(&req.coin) {
-                        if let Some(ctx) = ctx_arr.get(i) {
-                            let mark_px =
-                                ctx.get("markPx").and_then(|v| v.as_str()).unwrap_or("N/A");
-                            let mid_px = ctx.get("midPx").and_then(|v| v.as_str()).unwrap_or("N/A");
-                            let day_vlm = ctx
-                                .get("dayNtlVlm")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("N/A");
-                            let prev_day_px = ctx
-                                .get("prevDayPx")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("N/A");
-
-                            let output = format!(
-                                "## {name} Spot Market\n\n\
-                                 | Metric | Value |\n\
-                                 |--------|-------|\n\
-                                 | Mark Price | ${mark_px} |\n\
-                                 | Mid Price | ${mid_px} |\n\
-                                 | 24h Volume | ${day_vlm} |\n\
-                                 | Previous Day Price | ${prev_day_px} |\n"
-                            );
-                            return Ok(CallToolResult::success(vec![Content::text(output)]));
-                        }
-                    }
-                }
-            }
-        }
+pub async fn get_margin_tiers(
+    state: &ServerState,
+    req: GetMarginTiersRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+    let Some(table) = margin_table(state, &market).await? else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "No margin table for {}",
+            market.describe()
+        ))]));
+    };
+
+    let mut output = match table.description.as_str() {
+        "" => format!("## {} Margin Tiers\n\n", market.symbol),
+        description => format!("## {} Margin Tiers ({description})\n\n", market.symbol),
+    };
+    output.push_str(
+        "| Position Notional | Max Leverage | Initial Margin | Maintenance Margin | Maintenance Deduction |\n",
+    );
+    output.push_str(
+        "|-------------------|--------------|----------------|--------------------|-----------------------|\n",
+    );
+    for (i, tier) in table.tiers.iter().enumerate() {
+        let bracket = match table.tiers.get(i + 1) {
+            Some(next) => format!(
+                "{} – {}",
+                fmt_price(tier.lower_bound),
+                fmt_price(next.lower_bound)
+            ),
+            None => format!("{} and above", fmt_price(tier.lower_bound)),
+        };
+        output.push_str(&format!(
+            "| {bracket} | {}x | {:.2}% | {:.2}% | {} |\n",
+            tier.max_leverage,
+            tier.initial_rate() * Decimal::ONE_HUNDRED,
+            tier.maintenance_rate() * Decimal::ONE_HUNDRED,
+            fmt_usd(table.maintenance_deduction(i)),
+        ));
+    }
+
+    output.push_str("\n**Max position notional by leverage:**\n");
+    for tier in &table.tiers {
+        let limit = table
+            .max_notional_at(tier.max_leverage)
+            .map(fmt_price)
+            .unwrap_or_else(|| "no tier limit".into());
+        output.push_str(&format!("- {}x: {limit}\n", tier.max_leverage));
     }
+    output.push_str(
+        "\nA position's leverage is capped by the tier its notional falls in. Maintenance \
+         margin is notional × the tier's rate minus its deduction; the position is liquidated \
+         when equity falls below it.\n",
+    );
 
-    Ok(CallToolResult::error(vec![Content::text(format!(
-        "Market '{}' not found. Use get_markets to see available markets.",
-        req.coin
-    ))]))
+    Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-async fn get_cached_meta(state: &ServerState) -> Result<serde_json::Value, ErrorData> {
+async fn get_cached_meta(state: &ServerState) -> Result<Arc<MarketMeta>, ErrorData> {
     {
         let guard = state.cache.meta_cache.read().await;
         if let Some(cached) = guard.as_ref() {
@@ -283,219 +694,1887 @@ async fn get_cached_meta(state: &ServerState) -> Result<serde_json::Value, Error
     let data = state
         .raw_info_request(json!({"type": "metaAndAssetCtxs"}))
         .await?;
+    let meta = Arc::new(MarketMeta::parse(&data, MarketKind::Perp));
 
-    *state.cache.meta_cache.write().await = Some(CachedValue::new(data.clone()));
-    Ok(data)
+    state.cache.record_meta_snapshot(&meta).await;
+    *state.cache.meta_cache.write().await = Some(CachedValue::new(Arc::clone(&meta)));
+    Ok(meta)
 }
 
-async fn get_cached_spot_meta(state: &ServerState) -> Result<serde_json::Value, ErrorData> {
+/// Perps at their open interest cap, where the exchange rejects orders that
+/// would increase open interest.
+pub(crate) async fn get_capped_perps(state: &ServerState) -> Result<Vec<String>, ErrorData> {
     {
-        let guard = state.cache.spot_meta_cache.read().await;
+        let guard = state.cache.oi_cap_cache.read().await;
         if let Some(cached) = guard.as_ref() {
             if cached.is_fresh(MARKET_SUMMARY_TTL) {
-                tracing::debug!("get_market_summary: spot meta cache hit");
                 return Ok(cached.value.clone());
             }
         }
     }
 
     let data = state
-        .raw_info_request(json!({"type": "spotMetaAndAssetCtxs"}))
+        .raw_info_request(json!({"type": "perpsAtOpenInterestCap"}))
         .await?;
+    let capped: Vec<String> = data
+        .as_array()
+        .map(|coins| {
+            coins
+                .iter()
+                .filter_map(|c| c.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
 
-    *state.cache.spot_meta_cache.write().await = Some(CachedValue::new(data.clone()));
-    Ok(data)
+    *state.cache.oi_cap_cache.write().await = Some(CachedValue::new(capped.clone()));
+    Ok(capped)
 }
 
-pub async fn get_order_book(
+/// Hyperliquid's predicted next funding per perp, from `predictedFundings`.
+/// Other venues in the response are ignored.
+async fn get_predicted_fundings(
     state: &ServerState,
-    req: GetOrderBookRequest,
-) -> Result<CallToolResult, ErrorData> {
-    let depth = req.depth.unwrap_or(10).min(20);
-
-    let book = state
-        .raw_info_request(json!({
-            "type": "l2Book",
-            "coin": req.coin,
-        }))
-        .await?;
-
-    let levels = book.get("levels").and_then(|l| l.as_array());
-    let levels = match levels {
-        Some(l) if l.len() >= 2 => l,
-        _ => {
-            return Ok(CallToolResult::error(vec![Content::text(format!(
-                "No order book data for '{}'",
-                req.coin
-            ))]));
+) -> Result<HashMap<String, PredictedFunding>, ErrorData> {
+    {
+        let guard = state.cache.predicted_funding_cache.read().await;
+        if let Some(cached) = guard.as_ref() {
+            if cached.is_fresh(PREDICTED_FUNDING_TTL) {
+                return Ok(cached.value.clone());
+            }
         }
-    };
+    }
 
-    let bids = levels[0].as_array();
-    let asks = levels[1].as_array();
+    let data = state
+        .raw_info_request(json!({"type": "predictedFundings"}))
+        .await?;
+    // [[coin, [[venue, {fundingRate, nextFundingTime, ...} | null], ...]], ...]
+    let predicted: HashMap<String, PredictedFunding> = data
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let coin = entry.get(0)?.as_str()?;
+            let venue = entry
+                .get(1)?
+                .as_array()?
+                .iter()
+                .find_map(|v| (v.get(0)?.as_str()? == "HlPerp").then(|| v.get(1))?)?;
+            let rate = venue.get("fundingRate")?.as_str()?.parse().ok()?;
+            let next_time_ms = venue.get("nextFundingTime")?.as_u64()?;
+            Some((coin.to_string(), PredictedFunding { rate, next_time_ms }))
+        })
+        .collect();
 
-    let mut output = format!("## {} Order Book\n\n", req.coin);
+    *state.cache.predicted_funding_cache.write().await = Some(CachedValue::new(predicted.clone()));
+    Ok(predicted)
+}
 
-    // Asks (reversed so highest price is at top)
-    output.push_str("### Asks (Sells)\n");
-    output.push_str("| Price | Size | Orders |\n");
-    output.push_str("|-------|------|--------|\n");
-    if let Some(asks) = asks {
-        let ask_slice: Vec<_> = asks.iter().take(depth).collect();
-        for ask in ask_slice.iter().rev() {
-            let px = ask.get("px").and_then(|v| v.as_str()).unwrap_or("?");
-            let sz = ask.get("sz").and_then(|v| v.as_str()).unwrap_or("?");
-            let n = ask.get("n").and_then(|v| v.as_u64()).unwrap_or(0);
-            output.push_str(&format!("| ${px} | {sz} | {n} |\n"));
+/// The next funding settlement for `coin`. Uses the predicted funding
+/// endpoint, falling back to `current` (the asset context's rate) at the next
+/// top of the hour, since funding settles hourly.
+pub(crate) async fn next_funding(
+    state: &ServerState,
+    coin: &str,
+    current: Option<Decimal>,
+) -> Option<PredictedFunding> {
+    match get_predicted_fundings(state).await {
+        Ok(predicted) => {
+            if let Some(next) = predicted.get(coin) {
+                return Some(*next);
+            }
         }
+        Err(e) => tracing::debug!(error = %e.message, "Predicted funding lookup failed"),
     }
+    let hour_ms = HOUR.as_millis() as u64;
+    let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+    current.map(|rate| PredictedFunding {
+        rate,
+        next_time_ms: (now_ms / hour_ms + 1) * hour_ms,
+    })
+}
 
-    output.push_str("\n### Bids (Buys)\n");
-    output.push_str("| Price | Size | Orders |\n");
-    output.push_str("|-------|------|--------|\n");
-    if let Some(bids) = bids {
-        for bid in bids.iter().take(depth) {
-            let px = bid.get("px").and_then(|v| v.as_str()).unwrap_or("?");
-            let sz = bid.get("sz").and_then(|v| v.as_str()).unwrap_or("?");
-            let n = bid.get("n").and_then(|v| v.as_u64()).unwrap_or(0);
-            output.push_str(&format!("| ${px} | {sz} | {n} |\n"));
+/// "2026-10-16 15:00 UTC (in 14m 05s) at +0.0013%/h".
+pub(crate) fn fmt_next_funding(next: &PredictedFunding) -> String {
+    format!(
+        "{} ({}) at {:+.4}%/h",
+        fmt_time(next.next_time_ms),
+        fmt_until(next.next_time_ms),
+        next.rate * Decimal::ONE_HUNDRED
+    )
+}
+
+async fn get_cached_spot_meta(state: &ServerState) -> Result<Arc<MarketMeta>, ErrorData> {
+    {
+        let guard = state.cache.spot_meta_cache.read().await;
+        if let Some(cached) = guard.as_ref() {
+            if cached.is_fresh(MARKET_SUMMARY_TTL) {
+                tracing::debug!("get_market_summary: spot meta cache hit");
+                return Ok(cached.value.clone());
+            }
         }
     }
 
-    Ok(CallToolResult::success(vec![Content::text(output)]))
+    let data = state
+        .raw_info_request(json!({"type": "spotMetaAndAssetCtxs"}))
+        .await?;
+    let meta = Arc::new(MarketMeta::parse(&data, MarketKind::Spot));
+
+    *state.cache.spot_meta_cache.write().await = Some(CachedValue::new(Arc::clone(&meta)));
+    Ok(meta)
 }
 
-pub async fn get_candles(
+pub async fn get_spread_history(
     state: &ServerState,
-    req: GetCandlesRequest,
+    req: GetSpreadHistoryRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let valid_intervals = [
-        "1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "8h", "12h", "1d", "3d", "1w", "1M",
-    ];
-    if !valid_intervals.contains(&req.interval.as_str()) {
-        return Ok(CallToolResult::error(vec![Content::text(
-            "Invalid interval. Use: 1m, 3m, 5m, 15m, 30m, 1h, 2h, 4h, 8h, 12h, 1d, 3d, 1w, 1M",
-        )]));
-    }
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
 
-    let count = req.count.unwrap_or(100).min(5000);
-
-    let now_ms = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
-
-    let interval_ms: u64 = match req.interval.as_str() {
-        "1m" => 60_000,
-        "3m" => 180_000,
-        "5m" => 300_000,
-        "15m" => 900_000,
-        "30m" => 1_800_000,
-        "1h" => 3_600_000,
-        "2h" => 7_200_000,
-        "4h" => 14_400_000,
-        "8h" => 28_800_000,
-        "12h" => 43_200_000,
-        "1d" => 86_400_000,
-        "3d" => 259_200_000,
-        "1w" => 604_800_000,
-        "1M" => 2_592_000_000,
-        _ => 3_600_000,
-    };
-    let start_time = now_ms.saturating_sub(count * interval_ms);
-
-    let candle_interval = parse_candle_interval(&req.interval)
-        .ok_or_else(|| mcp_err(&format!("Unsupported candle interval: {}", req.interval)))?;
-
-    let candles = state
-        .client
-        .candle_snapshot(req.coin.clone(), candle_interval, start_time, now_ms)
+    let cutoff = req
+        .lookback_hours
+        .map(|h| (chrono::Utc::now().timestamp_millis() as u64).saturating_sub(h * 60 * 60 * 1000));
+    let samples: Vec<BookSample> = state
+        .cache
+        .book_samples
+        .read()
         .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch candles: {e}")))?;
+        .get(&market.coin)
+        .map(|ring| {
+            ring.iter()
+                .filter(|s| cutoff.is_none_or(|c| s.time >= c))
+                .copied()
+                .collect()
+        })
+        .unwrap_or_default();
 
-    let mut output = format!(
-        "## {} Candles ({}, {} periods)\n\n",
-        req.coin,
-        req.interval,
-        candles.len()
-    );
-    output.push_str("| Time | Open | High | Low | Close | Volume |\n");
-    output.push_str("|------|------|------|-----|-------|--------|\n");
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "No order book samples for {}. Add it to HYPERLIQUID_SAMPLE_BOOKS \
+             (e.g. HYPERLIQUID_SAMPLE_BOOKS=BTC,ETH) with REALTIME_ENABLED on, \
+             then ask again once samples have accumulated.",
+            market.describe()
+        ))]));
+    };
 
-    let display_count = candles.len().min(count as usize);
-    for candle in candles.iter().rev().take(display_count).rev() {
-        let time = chrono_from_ms(candle.open_time);
-        output.push_str(&format!(
-            "| {} | {} | {} | {} | {} | {} |\n",
-            time, candle.open, candle.high, candle.low, candle.close, candle.volume
-        ));
-    }
+    let mut spreads: Vec<f64> = samples.iter().map(BookSample::spread_bps).collect();
+    spreads.sort_by(f64::total_cmp);
+    let n = spreads.len();
+    let mean = |sum: f64| sum / n as f64;
+    let p95 = spreads[((n as f64 * 0.95).ceil() as usize).clamp(1, n) - 1];
+    let widest = samples
+        .iter()
+        .max_by(|a, b| a.spread_bps().total_cmp(&b.spread_bps()))
+        .unwrap_or(first);
+    let thinnest = samples
+        .iter()
+        .min_by(|a, b| (a.bid_depth + a.ask_depth).total_cmp(&(b.bid_depth + b.ask_depth)))
+        .unwrap_or(first);
+
+    let mut output = format!("## {} Spread History\n\n", market.describe());
+    output.push_str(&format!(
+        "{n} samples from {} to {}.\n\n",
+        fmt_time(first.time),
+        fmt_time(last.time)
+    ));
+    output.push_str("| Metric | Value |\n");
+    output.push_str("|--------|-------|\n");
+    output.push_str(&format!(
+        "| Mean spread | {:.2} bps |\n",
+        mean(spreads.iter().sum())
+    ));
+    output.push_str(&format!("| Median spread | {:.2} bps |\n", spreads[n / 2]));
+    output.push_str(&format!("| p95 spread | {p95:.2} bps |\n"));
+    output.push_str(&format!(
+        "| Widest spread | {:.2} bps at {} (bid ${}, ask ${}) |\n",
+        widest.spread_bps(),
+        fmt_time(widest.time),
+        widest.bid,
+        widest.ask
+    ));
+    output.push_str(&format!(
+        "| Mean bid depth (0.5%) | ${:.0} |\n",
+        mean(samples.iter().map(|s| s.bid_depth).sum())
+    ));
+    output.push_str(&format!(
+        "| Mean ask depth (0.5%) | ${:.0} |\n",
+        mean(samples.iter().map(|s| s.ask_depth).sum())
+    ));
+    output.push_str(&format!(
+        "| Thinnest book (0.5%) | ${:.0} at {} |\n",
+        thinnest.bid_depth + thinnest.ask_depth,
+        fmt_time(thinnest.time)
+    ));
+    output.push_str(&format!(
+        "| Latest spread | {:.2} bps |\n",
+        last.spread_bps()
+    ));
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn get_funding_rates(
+/// Number of most recently deployed spot tokens listed by `get_spot_deploy_state`.
+const RECENT_DEPLOYS: usize = 10;
+
+pub async fn get_spot_deploy_state(
     state: &ServerState,
-    req: GetFundingRatesRequest,
+    req: GetSpotDeployStateRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let hours = req.lookback_hours.unwrap_or(24);
-    let now_ms = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
-    let start_ms = now_ms.saturating_sub(hours * 3_600_000);
+    let deployer = match req.deployer.as_deref() {
+        Some(addr) => match addr.trim().parse::<hypersdk::Address>() {
+            Ok(addr) => Some(addr),
+            Err(_) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Invalid deployer address '{addr}'. Expected a 0x-prefixed address."
+                ))]));
+            }
+        },
+        None => state.query_address().ok(),
+    };
+    // The gas auction is global; any address works when there is no deployer.
+    let user = deployer.unwrap_or_default();
 
-    let rates = state
-        .client
-        .funding_history(req.coin.clone(), start_ms, None)
-        .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch funding history: {e}")))?;
+    let data = state
+        .raw_info_request(json!({
+            "type": "spotDeployState",
+            "user": format!("{user:#x}"),
+        }))
+        .await?;
 
-    let mut output = format!(
-        "## {} Funding Rates (last {} hours, {} entries)\n\n",
-        req.coin,
-        hours,
-        rates.len()
+    let mut output = "## Spot Deploy Auction\n\n".to_string();
+    output.push_str("| Field | Value |\n");
+    output.push_str("|-------|-------|\n");
+
+    let auction = data.get("gasAuction");
+    let gas = |key: &str| {
+        auction
+            .and_then(|a| a.get(key))
+            .and_then(|v| v.as_str())
+            .map(|v| format!("{v} HYPE"))
+    };
+    let start = auction
+        .and_then(|a| a.get("startTimeSeconds"))
+        .and_then(|v| v.as_u64());
+    let duration = auction
+        .and_then(|a| a.get("durationSeconds"))
+        .and_then(|v| v.as_u64());
+    let now_secs = chrono::Utc::now().timestamp() as u64;
+
+    output.push_str(&format!(
+        "| Current price | {} |\n",
+        gas("currentGas").unwrap_or_else(|| "— (no auction running)".into())
+    ));
+    output.push_str(&format!(
+        "| Starting price | {} |\n",
+        gas("startGas").unwrap_or_else(|| "N/A".into())
+    ));
+    output.push_str(&format!(
+        "| Last clearing price | {} |\n",
+        gas("endGas").unwrap_or_else(|| "N/A".into())
+    ));
+    if let Some(start) = start {
+        output.push_str(&format!(
+            "| Auction started | {} |\n",
+            fmt_time(start * 1000)
+        ));
+    }
+    if let (Some(start), Some(duration)) = (start, duration) {
+        let end = start + duration;
+        let remaining = if end > now_secs {
+            format!(
+                "{}h {}m remaining",
+                (end - now_secs) / 3600,
+                (end - now_secs) % 3600 / 60
+            )
+        } else {
+            "ended".to_string()
+        };
+        output.push_str(&format!(
+            "| Auction ends | {} ({remaining}) |\n",
+            fmt_time(end * 1000)
+        ));
+    }
+    output.push_str(
+        "\nThe deploy price starts high and decays over the auction; the first deployer pays the current price.\n",
     );
-    output.push_str("| Time | Funding Rate | Premium |\n");
-    output.push_str("|------|-------------|---------|\n");
 
-    for rate in rates.iter().rev().take(100) {
-        let time = chrono_from_ms(rate.time);
+    let spot_meta = get_cached_spot_meta(state).await?;
+    let tokens = &spot_meta.tokens;
+    if !tokens.is_empty() {
         output.push_str(&format!(
-            "| {} | {} | {} |\n",
-            time, rate.funding_rate, rate.premium
+            "\n### Recent Deploys (last {})\n\n",
+            RECENT_DEPLOYS.min(tokens.len())
         ));
+        output.push_str("| Index | Token | Full Name | Size Decimals |\n");
+        output.push_str("|-------|-------|-----------|---------------|\n");
+        for token in tokens.iter().rev().take(RECENT_DEPLOYS) {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                token.index.unwrap_or(0),
+                token.name,
+                token.full_name.as_deref().unwrap_or("—"),
+                token.sz_decimals.unwrap_or(0),
+            ));
+        }
+    }
+
+    if let Some(deployer) = deployer {
+        output.push_str(&format!("\n### Pending Deploys for `{deployer:#x}`\n\n"));
+        let states = data
+            .get("states")
+            .and_then(|s| s.as_array())
+            .filter(|s| !s.is_empty());
+        match states {
+            None => output.push_str("No deploy in progress.\n"),
+            Some(states) => {
+                output.push_str("| Token | Name | Full Name | Max Supply | Spot Pairs |\n");
+                output.push_str("|-------|------|-----------|------------|------------|\n");
+                for s in states {
+                    let spec = s.get("spec");
+                    let spots = s
+                        .get("spots")
+                        .and_then(|v| v.as_array())
+                        .map(|v| {
+                            v.iter()
+                                .filter_map(|i| i.as_u64())
+                                .map(|i| format!("@{i}"))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        })
+                        .filter(|v| !v.is_empty())
+                        .unwrap_or_else(|| "none yet".into());
+                    output.push_str(&format!(
+                        "| {} | {} | {} | {} | {} |\n",
+                        s.get("token").and_then(|v| v.as_u64()).unwrap_or(0),
+                        spec.and_then(|v| v.get("name"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("?"),
+                        s.get("fullName").and_then(|v| v.as_str()).unwrap_or("—"),
+                        s.get("maxSupply")
+                            .map(|v| v.to_string().trim_matches('"').to_string())
+                            .unwrap_or_else(|| "not set".into()),
+                        spots,
+                    ));
+                }
+            }
+        }
     }
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-fn parse_candle_interval(s: &str) -> Option<hypersdk::hypercore::CandleInterval> {
-    use hypersdk::hypercore::CandleInterval;
-    match s {
-        "1m" => Some(CandleInterval::OneMinute),
-        "3m" => Some(CandleInterval::ThreeMinutes),
-        "5m" => Some(CandleInterval::FiveMinutes),
-        "15m" => Some(CandleInterval::FifteenMinutes),
-        "30m" => Some(CandleInterval::ThirtyMinutes),
-        "1h" => Some(CandleInterval::OneHour),
-        "2h" => Some(CandleInterval::TwoHours),
-        "4h" => Some(CandleInterval::FourHours),
-        "8h" => Some(CandleInterval::EightHours),
-        "12h" => Some(CandleInterval::TwelveHours),
-        "1d" => Some(CandleInterval::OneDay),
-        "3d" => Some(CandleInterval::ThreeDays),
-        "1w" => Some(CandleInterval::OneWeek),
-        "1M" => Some(CandleInterval::OneMonth),
-        _ => None,
+/// A snapshot older than this is reported as stale.
+const STALE_BOOK_MS: u64 = 10_000;
+/// A book mid this far from the WebSocket mid is reported as out of line.
+const BOOK_MID_DIVERGENCE_BPS: i64 = 100;
+
+/// One price level: price, size and number of orders.
+pub(crate) type BookLevel = (Decimal, Decimal, u64);
+
+/// A validated `l2Book` snapshot, best levels first.
+pub(crate) struct L2Book {
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+    /// Data-quality problems that remained after one refetch.
+    pub issues: Vec<String>,
+}
+
+impl L2Book {
+    fn parse(book: &serde_json::Value) -> (Vec<BookLevel>, Vec<BookLevel>, Option<u64>) {
+        let side = |i: usize| -> Vec<BookLevel> {
+            book.get("levels")
+                .and_then(|l| l.get(i))
+                .and_then(|l| l.as_array())
+                .map(|levels| {
+                    levels
+                        .iter()
+                        .filter_map(|l| {
+                            Some((
+                                l.get("px")?.as_str()?.parse().ok()?,
+                                l.get("sz")?.as_str()?.parse().ok()?,
+                                l.get("n").and_then(|n| n.as_u64()).unwrap_or(0),
+                            ))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        (side(0), side(1), book.get("time").and_then(|t| t.as_u64()))
+    }
+
+    /// "Data quality" warnings to show ahead of anything derived from the book.
+    pub fn warning(&self) -> String {
+        self.issues
+            .iter()
+            .map(|issue| format!("> **Data quality:** {issue}\n"))
+            .chain((!self.issues.is_empty()).then(|| "\n".to_string()))
+            .collect()
     }
 }
 
-fn chrono_from_ms(ms: u64) -> String {
-    let secs = (ms / 1000) as i64;
-    let nanos = ((ms % 1000) * 1_000_000) as u32;
-    chrono::DateTime::from_timestamp(secs, nanos)
-        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
-        .unwrap_or_else(|| ms.to_string())
+/// Problems with a book snapshot: crossed or locked top of book, empty sides,
+/// a snapshot time far behind `now_ms`, or a mid out of line with the
+/// WebSocket mid.
+fn validate_book(
+    bids: &[BookLevel],
+    asks: &[BookLevel],
+    time: Option<u64>,
+    ws_mid: Option<Decimal>,
+    now_ms: u64,
+) -> Vec<String> {
+    let mut issues = Vec::new();
+    match (bids.first(), asks.first()) {
+        (None, None) => issues.push("the order book is empty.".to_string()),
+        (None, Some(_)) => issues.push("the book has no bids.".to_string()),
+        (Some(_), None) => issues.push("the book has no asks.".to_string()),
+        (Some((bid, ..)), Some((ask, ..))) => {
+            if ask <= bid {
+                issues.push(format!(
+                    "the book is crossed (best bid ${bid} ≥ best ask ${ask}); prices may be wrong."
+                ));
+            } else if let Some(ws_mid) = ws_mid.filter(|m| !m.is_zero()) {
+                let mid = (bid + ask) / Decimal::TWO;
+                let bps = ((mid - ws_mid) / ws_mid * Decimal::from(10_000)).abs();
+                if bps > Decimal::from(BOOK_MID_DIVERGENCE_BPS) {
+                    issues.push(format!(
+                        "the book mid ${} is {bps:.0} bps from the live mid ${ws_mid}; the snapshot may be stale.",
+                        mid.normalize()
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(time) = time
+        && now_ms.saturating_sub(time) > STALE_BOOK_MS
+    {
+        issues.push(format!(
+            "the snapshot is {}s old.",
+            now_ms.saturating_sub(time) / 1000
+        ));
+    }
+    issues
+}
+
+/// Fetch and validate the `l2Book` for `coin`, refetching once if the first
+/// snapshot looks wrong. Remaining problems are reported in `issues` rather
+/// than hidden.
+pub(crate) async fn fetch_l2_book(state: &ServerState, coin: &str) -> Result<L2Book, ErrorData> {
+    let ws_mid = if state.cache.live {
+        state.cache.all_mids.borrow().get(coin).copied()
+    } else {
+        None
+    };
+    let mut attempt = 0;
+    loop {
+        let book = state
+            .raw_info_request(json!({ "type": "l2Book", "coin": coin }))
+            .await?;
+        let (bids, asks, time) = L2Book::parse(&book);
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let issues = validate_book(&bids, &asks, time, ws_mid, now_ms);
+        attempt += 1;
+        if issues.is_empty() || attempt > 1 {
+            return Ok(L2Book { bids, asks, issues });
+        }
+        tracing::debug!(coin, ?issues, "Refetching inconsistent order book");
+    }
+}
+
+pub async fn get_order_book(
+    state: &ServerState,
+    req: GetOrderBookRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let depth = req.depth.unwrap_or(10).min(20);
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+
+    let book = fetch_l2_book(state, &market.coin).await?;
+    let bids = &book.bids[..book.bids.len().min(depth)];
+    let asks = &book.asks[..book.asks.len().min(depth)];
+
+    let mut output = format!("## {} Order Book\n\n", market.describe());
+    output.push_str(&book.warning());
+    output.push_str(&BookSummary::from_levels(bids, asks).render());
+
+    // Asks (reversed so highest price is at top)
+    output.push_str("### Asks (Sells)\n");
+    output.push_str("| Price | Size | Orders |\n");
+    output.push_str("|-------|------|--------|\n");
+    for (px, sz, n) in asks.iter().rev() {
+        output.push_str(&format!("| ${px} | {sz} | {n} |\n"));
+    }
+
+    output.push_str("\n### Bids (Buys)\n");
+    output.push_str("| Price | Size | Orders |\n");
+    output.push_str("|-------|------|--------|\n");
+    for (px, sz, n) in bids {
+        output.push_str(&format!("| ${px} | {sz} | {n} |\n"));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Top-of-book figures computed from the displayed levels, so callers don't
+/// have to derive them from the table.
+struct BookSummary {
+    best_bid: Option<(Decimal, Decimal)>,
+    best_ask: Option<(Decimal, Decimal)>,
+    bid_size: Decimal,
+    ask_size: Decimal,
+    bid_notional: Decimal,
+    ask_notional: Decimal,
+}
+
+impl BookSummary {
+    /// `bids` and `asks` are levels, best first.
+    fn from_levels(bids: &[BookLevel], asks: &[BookLevel]) -> Self {
+        Self {
+            best_bid: bids.first().map(|&(px, sz, _)| (px, sz)),
+            best_ask: asks.first().map(|&(px, sz, _)| (px, sz)),
+            bid_size: bids.iter().map(|(_, sz, _)| sz).sum(),
+            ask_size: asks.iter().map(|(_, sz, _)| sz).sum(),
+            bid_notional: bids.iter().map(|(px, sz, _)| px * sz).sum(),
+            ask_notional: asks.iter().map(|(px, sz, _)| px * sz).sum(),
+        }
+    }
+
+    fn mid(&self) -> Option<Decimal> {
+        let ((bid, _), (ask, _)) = (self.best_bid?, self.best_ask?);
+        Some((bid + ask) / Decimal::TWO)
+    }
+
+    fn spread(&self) -> Option<Decimal> {
+        let ((bid, _), (ask, _)) = (self.best_bid?, self.best_ask?);
+        Some(ask - bid)
+    }
+
+    fn spread_bps(&self) -> Option<Decimal> {
+        let mid = self.mid().filter(|m| !m.is_zero())?;
+        Some(self.spread()? / mid * Decimal::from(10_000))
+    }
+
+    /// Mid weighted toward the side with less size at the top of book, where
+    /// the price is more likely to move next.
+    fn microprice(&self) -> Option<Decimal> {
+        let ((bid, bid_sz), (ask, ask_sz)) = (self.best_bid?, self.best_ask?);
+        let total = bid_sz + ask_sz;
+        if total.is_zero() {
+            return self.mid();
+        }
+        Some((bid * ask_sz + ask * bid_sz) / total)
+    }
+
+    fn render(&self) -> String {
+        let side = |level: Option<(Decimal, Decimal)>| {
+            level
+                .map(|(px, sz)| format!("${px} ({sz})"))
+                .unwrap_or_else(|| "—".into())
+        };
+        let usd = |v: Option<Decimal>| v.map(|v| format!("${v}")).unwrap_or_else(|| "—".into());
+        let mut out = String::from("| Metric | Value |\n|--------|-------|\n");
+        out.push_str(&format!("| Best Bid | {} |\n", side(self.best_bid)));
+        out.push_str(&format!("| Best Ask | {} |\n", side(self.best_ask)));
+        out.push_str(&format!(
+            "| Spread | {}{} |\n",
+            usd(self.spread()),
+            self.spread_bps()
+                .map(|bps| format!(" ({:.2} bps)", bps))
+                .unwrap_or_default()
+        ));
+        out.push_str(&format!(
+            "| Mid | {} |\n",
+            usd(self.mid().map(|m| m.normalize()))
+        ));
+        out.push_str(&format!(
+            "| Microprice | {} |\n",
+            usd(self.microprice().map(|m| round_sig(m, 8).normalize()))
+        ));
+        out.push_str(&format!(
+            "| Displayed Bids | {} (${:.0}) |\n",
+            self.bid_size, self.bid_notional
+        ));
+        out.push_str(&format!(
+            "| Displayed Asks | {} (${:.0}) |\n",
+            self.ask_size, self.ask_notional
+        ));
+        out.push('\n');
+        out
+    }
+}
+
+pub async fn get_candles(
+    state: &ServerState,
+    req: GetCandlesRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let Some(period_ms) = interval_ms(&req.interval) else {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Invalid interval. Use: 1m, 3m, 5m, 15m, 30m, 1h, 2h, 4h, 8h, 12h, 1d, 3d, 1w, 1M",
+        )]));
+    };
+
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+    let requested = req
+        .count
+        .unwrap_or_else(|| default_candle_count(&req.interval));
+    let count = requested.min(MAX_CANDLES);
+
+    let candles = get_cached_candles(state, &market.coin, &req.interval, count).await?;
+    if candles.is_empty() {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "No {} candles for {} in the last {count} periods. Check the symbol \
+             (names are case-sensitive, e.g. \"kPEPE\"; get_markets lists them all) \
+             and that the market had listed within the window.",
+            req.interval,
+            market.describe()
+        ))]));
+    }
+
+    let mut output = format!(
+        "## {} Candles ({}, {} periods)\n\n",
+        market.describe(),
+        req.interval,
+        candles.len()
+    );
+    if requested > MAX_CANDLES {
+        output.push_str(&format!(
+            "Requested {requested} candles; the exchange serves at most the latest {MAX_CANDLES} per interval.\n\n"
+        ));
+    }
+    if (candles.len() as u64) < count {
+        output.push_str(&candle_shortfall(
+            &market.describe(),
+            &req.interval,
+            period_ms,
+            &candles,
+            count,
+        ));
+    }
+    let display_count = candles.len().min(count as usize).min(MAX_CANDLE_ROWS);
+    if display_count < candles.len() {
+        output.push_str(&format!(
+            "Showing the latest {display_count} of {} candles; the summary covers all of them.\n\n",
+            candles.len()
+        ));
+    }
+    output.push_str("| Time | Open | High | Low | Close | Volume | Volume (USD) |\n");
+    output.push_str("|------|------|------|-----|-------|--------|--------------|\n");
+
+    for candle in candles.iter().rev().take(display_count).rev() {
+        let time = fmt_time(candle.open_time);
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | ${:.0} |\n",
+            time,
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.volume,
+            candle.volume * candle.close
+        ));
+    }
+
+    if let (Some(first), Some(last)) = (candles.first(), candles.last()) {
+        let volume: Decimal = candles.iter().map(|c| c.volume).sum();
+        let notional: Decimal = candles.iter().map(|c| c.volume * c.close).sum();
+        let high = candles.iter().map(|c| c.high).max().unwrap_or_default();
+        let low = candles.iter().map(|c| c.low).min().unwrap_or_default();
+        let widest = candles
+            .iter()
+            .max_by_key(|c| c.high - c.low)
+            .unwrap_or(first);
+        let pct = |from: Decimal, to: Decimal| {
+            if from.is_zero() {
+                "—".to_string()
+            } else {
+                format!("{:+.2}%", (to - from) / from * Decimal::ONE_HUNDRED)
+            }
+        };
+
+        output.push_str("\n### Window Summary\n\n");
+        output.push_str("| Metric | Value |\n");
+        output.push_str("|--------|-------|\n");
+        output.push_str(&format!(
+            "| Total Volume | {} (${:.0}) |\n",
+            volume.normalize(),
+            notional
+        ));
+        output.push_str(&format!("| High | {high} |\n"));
+        output.push_str(&format!("| Low | {low} |\n"));
+        output.push_str(&format!(
+            "| Net Change | {} ({} → {}) |\n",
+            pct(first.open, last.close),
+            first.open,
+            last.close
+        ));
+        output.push_str(&format!(
+            "| Largest Range | {} ({}, {}) |\n",
+            widest.high - widest.low,
+            pct(widest.low, widest.high).trim_start_matches('+'),
+            fmt_time(widest.open_time)
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn get_funding_rates(
+    state: &ServerState,
+    req: GetFundingRatesRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+    let hours = req
+        .lookback_hours
+        .unwrap_or(24)
+        .clamp(1, MAX_FUNDING_LOOKBACK_HOURS);
+    let rates = get_cached_funding(
+        state,
+        &market.coin,
+        Duration::from_secs(hours.saturating_mul(3600)),
+    )
+    .await?;
+
+    let mut output = format!(
+        "## {} Funding Rates (last {} hours, {} entries)\n\n",
+        market.symbol,
+        hours,
+        rates.len()
+    );
+    output.push_str("| Time | Funding Rate | Premium |\n");
+    output.push_str("|------|-------------|---------|\n");
+
+    for rate in rates.iter().rev().take(100) {
+        let time = fmt_time(rate.time);
+        output.push_str(&format!(
+            "| {} | {} | {} |\n",
+            time, rate.funding_rate, rate.premium
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Hourly funding periods in a year, for annualizing rates.
+const HOURS_PER_YEAR: Decimal = Decimal::from_parts(8760, 0, 0, false, 0);
+
+/// Mean, min and max of a series, or `None` when it is empty.
+fn series_stats(values: &[Decimal]) -> Option<(Decimal, Decimal, Decimal)> {
+    let min = values.iter().min()?;
+    let max = values.iter().max()?;
+    let mean = values.iter().sum::<Decimal>() / Decimal::from(values.len());
+    Some((mean, *min, *max))
+}
+
+pub async fn compare_funding(
+    state: &ServerState,
+    req: CompareFundingRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let a = state.resolve_market(&req.coin_a, Some(MarketKind::Perp))?;
+    let b = state.resolve_market(&req.coin_b, Some(MarketKind::Perp))?;
+    if a.coin == b.coin {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Pick two different markets to compare.",
+        )]));
+    }
+    let hours = req
+        .lookback_hours
+        .unwrap_or(168)
+        .clamp(1, MAX_FUNDING_LOOKBACK_HOURS);
+    let window = Duration::from_secs(hours.saturating_mul(3600));
+    let (rates_a, rates_b) = tokio::try_join!(
+        get_cached_funding(state, &a.coin, window),
+        get_cached_funding(state, &b.coin, window),
+    )?;
+
+    // Funding settles hourly; entries can be a few milliseconds apart
+    // between coins, so align on the hour.
+    const HOUR_MS: u64 = 3_600_000;
+    let by_hour: HashMap<u64, Decimal> = rates_b
+        .iter()
+        .map(|r| (r.time / HOUR_MS, r.funding_rate))
+        .collect();
+    let aligned: Vec<(u64, Decimal, Decimal)> = rates_a
+        .iter()
+        .filter_map(|r| Some((r.time, r.funding_rate, *by_hour.get(&(r.time / HOUR_MS))?)))
+        .collect();
+
+    let pct = |r: Decimal| r * Decimal::ONE_HUNDRED;
+    let mut output = format!(
+        "## Funding: {} vs {} (last {hours} hours)\n\n",
+        a.symbol, b.symbol
+    );
+    output.push_str("| Market | Entries | Mean | Min | Max | Mean APR |\n");
+    output.push_str("|--------|---------|------|-----|-----|----------|\n");
+    for (market, rates) in [(&a, &rates_a), (&b, &rates_b)] {
+        let values: Vec<Decimal> = rates.iter().map(|r| r.funding_rate).collect();
+        match series_stats(&values) {
+            Some((mean, min, max)) => output.push_str(&format!(
+                "| {} | {} | {:.4}% | {:.4}% | {:.4}% | {:+.2}% |\n",
+                market.symbol,
+                values.len(),
+                pct(mean),
+                pct(min),
+                pct(max),
+                pct(mean) * HOURS_PER_YEAR,
+            )),
+            None => output.push_str(&format!(
+                "| {} | 0 | N/A | N/A | N/A | N/A |\n",
+                market.symbol
+            )),
+        }
+    }
+
+    let spreads: Vec<Decimal> = aligned.iter().map(|(_, ra, rb)| ra - rb).collect();
+    let Some((mean, min, max)) = series_stats(&spreads) else {
+        output.push_str("\nNo overlapping funding hours to compare.\n");
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    };
+    let (last_time, last_a, last_b) = aligned[aligned.len() - 1];
+    let a_higher = spreads
+        .iter()
+        .filter(|s| s.is_sign_positive() && !s.is_zero())
+        .count();
+    let cumulative: Decimal = spreads.iter().sum();
+
+    output.push_str(&format!(
+        "\n### Spread ({} − {}, {} aligned hours)\n\n",
+        a.symbol,
+        b.symbol,
+        spreads.len()
+    ));
+    output.push_str("| Metric | Value |\n|--------|-------|\n");
+    output.push_str(&format!(
+        "| Current ({}) | {:+.4}% ({:+.2}% APR) |\n",
+        fmt_time(last_time),
+        pct(last_a - last_b),
+        pct(last_a - last_b) * HOURS_PER_YEAR,
+    ));
+    output.push_str(&format!(
+        "| Mean | {:+.4}% ({:+.2}% APR) |\n",
+        pct(mean),
+        pct(mean) * HOURS_PER_YEAR
+    ));
+    output.push_str(&format!("| Min | {:+.4}% |\n", pct(min)));
+    output.push_str(&format!("| Max | {:+.4}% |\n", pct(max)));
+    output.push_str(&format!(
+        "| {} paid more | {a_higher} of {} hours |\n",
+        a.symbol,
+        spreads.len()
+    ));
+    output.push_str(&format!("| Cumulative | {:+.4}% |\n", pct(cumulative)));
+
+    let (short, long) = if cumulative.is_sign_negative() {
+        (&b, &a)
+    } else {
+        (&a, &b)
+    };
+    output.push_str(&format!(
+        "\nOver the window, short {} / long {} would have collected {:.4}% of notional \
+         in net funding (before fees and price moves).\n",
+        short.symbol,
+        long.symbol,
+        pct(cumulative.abs()),
+    ));
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Taker and maker fee rates for the account, after its referral discount,
+/// from `userFees`. Base rates without an account or when the lookup fails.
+async fn account_fee_rates(state: &ServerState) -> (Decimal, Decimal, &'static str) {
+    let base = (config::BASE_TAKER_FEE, config::BASE_MAKER_FEE, "base rates");
+    let Some(address) = state.user_address else {
+        return base;
+    };
+    let fees = match state
+        .raw_info_request(json!({"type": "userFees", "user": format!("{address:#x}")}))
+        .await
+    {
+        Ok(fees) => fees,
+        Err(e) => {
+            tracing::warn!(error = %e.message, "Fee rate lookup failed; using base rates");
+            return base;
+        }
+    };
+    let rate = |key: &str| {
+        fees.get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<Decimal>().ok())
+    };
+    let (Some(taker), Some(maker)) = (rate("userCrossRate"), rate("userAddRate")) else {
+        return base;
+    };
+    // The referral discount applies to fees paid, not to maker rebates.
+    let discount = rate("activeReferralDiscount").unwrap_or_default();
+    let discounted = |r: Decimal| {
+        if r.is_sign_positive() {
+            r * (Decimal::ONE - discount)
+        } else {
+            r
+        }
+    };
+    (discounted(taker), discounted(maker), "account tier")
+}
+
+pub async fn estimate_carry(
+    state: &ServerState,
+    req: EstimateCarryRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+    let is_long = match req.side.to_lowercase().as_str() {
+        "long" | "buy" | "b" => true,
+        "short" | "sell" | "s" => false,
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Invalid side. Use \"long\" or \"short\".",
+            )]));
+        }
+    };
+    if !(req.hours.is_finite() && req.hours > 0.0) {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "hours must be a positive number.",
+        )]));
+    }
+    let Ok(hours) = Decimal::try_from(req.hours) else {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "hours is out of range.",
+        )]));
+    };
+
+    let Some((_, ctx)) = asset_ctx(state, &market).await? else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "No market data for {}",
+            market.describe()
+        ))]));
+    };
+    let (Some(mid), Some(current)) = (ctx.mid_px.or(ctx.mark_px), ctx.funding) else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "No price or funding rate for {}.",
+            market.symbol
+        ))]));
+    };
+
+    let size = match (req.size, req.size_usd) {
+        (Some(size), None) if size > 0.0 => Decimal::try_from(size).ok(),
+        (None, Some(usd)) if usd > 0.0 => Decimal::try_from(usd).ok().map(|usd| usd / mid),
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Provide exactly one of size or size_usd, as a positive number.",
+            )]));
+        }
+    };
+    let Some(size) = size else {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Size is out of range.",
+        )]));
+    };
+    let notional = size * mid;
+
+    let lookback = req
+        .lookback_hours
+        .unwrap_or(168)
+        .clamp(1, MAX_FUNDING_LOOKBACK_HOURS);
+    let (rates, (taker, maker, fee_source)) = tokio::join!(
+        get_cached_funding(
+            state,
+            &market.coin,
+            Duration::from_secs(lookback.saturating_mul(3600))
+        ),
+        account_fee_rates(state),
+    );
+    let rates: Vec<Decimal> = rates?.iter().map(|r| r.funding_rate).collect();
+    let mean = series_stats(&rates).map(|(mean, _, _)| mean);
+    // Every order from this server carries the builder fee, in tenths of a
+    // basis point.
+    let builder = Decimal::from(state.active_builder().fee) / Decimal::from(100_000);
+
+    // Positive funding is paid by longs to shorts; a negative cost is a credit.
+    let direction = if is_long { Decimal::ONE } else { -Decimal::ONE };
+    let funding_cost = |rate: Decimal| rate * notional * hours * direction;
+    let pct = |r: Decimal| r * Decimal::ONE_HUNDRED;
+    let side_label = if is_long { "long" } else { "short" };
+
+    let mut output = format!(
+        "## Carry Estimate: {side_label} {} {} for {}h\n\n",
+        fmt_qty(round_sig(size, 6)),
+        market.symbol,
+        hours.normalize()
+    );
+    output.push_str("| Metric | Value |\n|--------|-------|\n");
+    output.push_str(&format!(
+        "| Notional | {} at mid {} |\n",
+        fmt_usd(notional),
+        fmt_price(mid)
+    ));
+    output.push_str(&format!(
+        "| Current Funding | {:+.4}%/h ({:+.2}% APR) |\n",
+        pct(current),
+        pct(current) * HOURS_PER_YEAR
+    ));
+    if let Some(next) = next_funding(state, &market.coin, Some(current)).await {
+        output.push_str(&format!(
+            "| Next Funding | {}, {} for this position |\n",
+            fmt_next_funding(&next),
+            fmt_usd(next.rate * notional * direction)
+        ));
+    }
+    match mean {
+        Some(mean) => output.push_str(&format!(
+            "| Mean Funding ({lookback}h, {} entries) | {:+.4}%/h ({:+.2}% APR) |\n",
+            rates.len(),
+            pct(mean),
+            pct(mean) * HOURS_PER_YEAR
+        )),
+        None => output.push_str(&format!("| Mean Funding ({lookback}h) | N/A |\n")),
+    }
+    output.push_str(&format!(
+        "| Funding Over Period (current) | {} |\n",
+        fmt_usd(funding_cost(current))
+    ));
+    if let Some(mean) = mean {
+        output.push_str(&format!(
+            "| Funding Over Period (mean) | {} |\n",
+            fmt_usd(funding_cost(mean))
+        ));
+    }
+    output.push_str(&format!(
+        "| Fee Rates ({fee_source}) | taker {:.4}%, maker {:.4}%, builder {:.4}% |\n",
+        pct(taker),
+        pct(maker),
+        pct(builder)
+    ));
+
+    output.push_str(
+        "\n### Scenarios\n\n\
+         | Entry / Exit | Fees | Total (current funding) | Break-even (current) | Total (mean funding) | Break-even (mean) |\n\
+         |--------------|------|-------------------------|----------------------|----------------------|-------------------|\n",
+    );
+    let move_word = if is_long { "up" } else { "down" };
+    let break_even = |total: Decimal| {
+        if total.is_sign_negative() || total.is_zero() {
+            "none, funding covers it".to_string()
+        } else {
+            let per_coin = total / size;
+            format!(
+                "{move_word} {} ({:.3}%)",
+                fmt_price(round_sig(per_coin, 5)),
+                pct(per_coin / mid)
+            )
+        }
+    };
+    for (label, entry, exit) in [
+        ("Taker / taker", taker, taker),
+        ("Maker / taker", maker, taker),
+        ("Maker / maker", maker, maker),
+    ] {
+        let fees = notional * (entry + exit + builder * Decimal::TWO);
+        let at_current = fees + funding_cost(current);
+        let at_mean = mean.map(|m| fees + funding_cost(m));
+        output.push_str(&format!(
+            "| {label} | {} | {} | {} | {} | {} |\n",
+            fmt_usd(fees),
+            fmt_usd(at_current),
+            break_even(at_current),
+            at_mean.map(fmt_usd).unwrap_or_else(|| "N/A".into()),
+            at_mean.map(break_even).unwrap_or_else(|| "N/A".into()),
+        ));
+    }
+    output.push_str(
+        "\nCosts are positive, credits negative. Exit fees assume the price is unchanged; \
+         maker legs assume the order rests and fills. Funding is charged hourly on notional, \
+         so the estimate drifts as price and rates move.\n",
+    );
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Funding history for a perp over the last `window`, oldest first. Cached per
+/// coin for five minutes. A longer window fetches only the older range it is
+/// missing, and a stale entry fetches only the entries after its last one.
+pub(crate) async fn get_cached_funding(
+    state: &ServerState,
+    coin: &str,
+    window: Duration,
+) -> Result<Vec<FundingRate>, ErrorData> {
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let start_ms = now_ms.saturating_sub(window.as_millis() as u64);
+
+    let cached = state.cache.funding_cache.read().await.get(coin).map(|c| {
+        (
+            c.value.start_ms,
+            c.value.rates.clone(),
+            c.inserted_at,
+            c.is_fresh(FUNDING_TTL),
+        )
+    });
+
+    let entry = match cached {
+        Some((cached_start, _, _, true)) if cached_start <= start_ms => None,
+        Some((cached_start, mut rates, inserted_at, fresh)) => {
+            let mut history_start = cached_start;
+            if start_ms < cached_start {
+                let mut older =
+                    fetch_funding(state, coin, start_ms, Some(cached_start - 1)).await?;
+                older.append(&mut rates);
+                rates = older;
+                history_start = start_ms;
+            }
+            let mut entry = CachedValue {
+                value: FundingHistory {
+                    start_ms: history_start,
+                    rates,
+                },
+                inserted_at,
+            };
+            if !fresh {
+                let since = entry
+                    .value
+                    .rates
+                    .last()
+                    .map_or(history_start, |r| r.time + 1);
+                let newer = fetch_funding(state, coin, since, None).await?;
+                entry.value.rates.extend(newer);
+                entry.inserted_at = std::time::Instant::now();
+            }
+            Some(entry)
+        }
+        None => Some(CachedValue::new(FundingHistory {
+            start_ms,
+            rates: fetch_funding(state, coin, start_ms, None).await?,
+        })),
+    };
+
+    let mut cache = state.cache.funding_cache.write().await;
+    if let Some(mut entry) = entry {
+        entry.value.rates.sort_by_key(|r| r.time);
+        entry.value.rates.dedup_by_key(|r| r.time);
+        cache.insert(coin.to_string(), entry);
+    }
+    Ok(cache
+        .get(coin)
+        .map(|c| {
+            c.value
+                .rates
+                .iter()
+                .filter(|r| r.time >= start_ms)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Funding entries from `start_ms` through `end_ms` (or now). A full page
+/// is followed by another starting after its last entry, so a range longer
+/// than one page comes back whole.
+async fn fetch_funding(
+    state: &ServerState,
+    coin: &str,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> Result<Vec<FundingRate>, ErrorData> {
+    let mut rates = Vec::new();
+    let mut cursor = start_ms;
+    loop {
+        let page = state
+            .api
+            .funding_history(coin.to_string(), cursor, end_ms)
+            .await
+            .map_err(|e| mcp_err(&format!("Failed to fetch funding history: {e}")))?;
+        let full = page.len() >= FUNDING_PAGE_SIZE;
+        let last_time = page.iter().map(|r| r.time).max();
+        rates.extend(page);
+        match last_time {
+            Some(t) if full && t >= cursor && end_ms.is_none_or(|end| t < end) => cursor = t + 1,
+            _ => break,
+        }
+    }
+    Ok(rates)
+}
+
+/// The most recent `count` candles for a coin. A cached series is reused for
+/// up to one interval, and serves any request no longer than itself.
+pub(crate) async fn get_cached_candles(
+    state: &ServerState,
+    coin: &str,
+    interval: &str,
+    count: u64,
+) -> Result<Vec<Candle>, ErrorData> {
+    let candle_interval = parse_candle_interval(interval)
+        .ok_or_else(|| mcp_err(&format!("Unsupported candle interval: {interval}")))?;
+    let period_ms = interval_ms(interval)
+        .ok_or_else(|| mcp_err(&format!("Unsupported candle interval: {interval}")))?;
+    let ttl = Duration::from_millis(period_ms);
+
+    if let Some(candles) = state.cache.cached_candles(coin, interval, count, ttl).await {
+        return Ok(candles);
+    }
+
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let start_time = candle_window_start(interval, period_ms, count, now_ms);
+
+    let candles = state
+        .api
+        .candle_snapshot(coin.to_string(), candle_interval, start_time, now_ms)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch candles: {e}")))?;
+
+    state
+        .cache
+        .store_candles(
+            coin,
+            interval,
+            CandleSeries {
+                count,
+                candles: candles.clone(),
+            },
+        )
+        .await;
+    Ok(candles)
+}
+
+/// 1m candles covering every time in `times`, sorted by open time. A fresh
+/// cached 1m series is used when it reaches back far enough; otherwise one
+/// request is made per 5000-minute window that actually contains a time.
+pub(crate) async fn get_minute_candles_at(
+    state: &ServerState,
+    coin: &str,
+    times: &[u64],
+) -> Result<Vec<Candle>, ErrorData> {
+    let mut times = times.to_vec();
+    times.sort_unstable();
+    let Some(&first) = times.first() else {
+        return Ok(Vec::new());
+    };
+    let ttl = Duration::from_millis(MINUTE_MS);
+    let since = first.saturating_sub(MINUTE_MS);
+    if let Some(candles) = state
+        .cache
+        .cached_candles_since(coin, "1m", since, ttl)
+        .await
+    {
+        return Ok(candles);
+    }
+
+    let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+    let mut candles = Vec::new();
+    let mut covered_until = 0;
+    for time in times {
+        if time <= covered_until {
+            continue;
+        }
+        let start = time.saturating_sub(MINUTE_MS);
+        let end = start + MAX_CANDLES * MINUTE_MS;
+        let batch = state
+            .api
+            .candle_snapshot(
+                coin.to_string(),
+                hypersdk::hypercore::CandleInterval::OneMinute,
+                start,
+                end,
+            )
+            .await
+            .map_err(|e| mcp_err(&format!("Failed to fetch candles for {coin}: {e}")))?;
+        // A window reaching the present is also the latest series.
+        if end >= now_ms {
+            let series = CandleSeries {
+                count: (now_ms - start) / MINUTE_MS,
+                candles: batch.clone(),
+            };
+            state.cache.store_candles(coin, "1m", series).await;
+        }
+        candles.extend(batch);
+        covered_until = end - MINUTE_MS;
+    }
+    candles.sort_by_key(|c| c.open_time);
+    candles.dedup_by_key(|c| c.open_time);
+    Ok(candles)
+}
+
+/// Close of the 1m candle whose close time is nearest `time`, if one closed
+/// within a minute of it. `candles` must be sorted by open time.
+pub(crate) fn nearest_minute_close(candles: &[Candle], time: u64) -> Option<Decimal> {
+    let idx = candles.partition_point(|c| c.close_time < time);
+    [idx.checked_sub(1), Some(idx)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| candles.get(i))
+        .map(|c| (c.close_time.abs_diff(time), c.close))
+        .filter(|(distance, _)| *distance <= MINUTE_MS)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, close)| close)
+}
+
+/// Start of a window holding `count` candles of `period_ms` up to `now_ms`.
+/// Months are counted on the calendar; windows reaching before 1970 start at
+/// zero.
+fn candle_window_start(interval: &str, period_ms: u64, count: u64, now_ms: u64) -> u64 {
+    if interval == "1M" {
+        return chrono::DateTime::from_timestamp_millis(now_ms as i64)
+            .and_then(|now| now.checked_sub_months(chrono::Months::new(count as u32)))
+            .map(|start| start.timestamp_millis().max(0) as u64)
+            .unwrap_or(0);
+    }
+    now_ms.saturating_sub(count.saturating_mul(period_ms))
+}
+
+/// Candles to return when `get_candles` is called without a count: about a
+/// couple of hours of 1m candles up to two years of months.
+fn default_candle_count(interval: &str) -> u64 {
+    match interval {
+        "1m" => 120,
+        "3m" | "5m" => 144,
+        "15m" | "30m" => 96,
+        "1h" => 168,
+        "2h" => 84,
+        "4h" => 180,
+        "8h" => 90,
+        "12h" => 60,
+        "1d" => 90,
+        "3d" => 60,
+        "1w" => 52,
+        "1M" => 24,
+        _ => 100,
+    }
+}
+
+/// Why `get_candles` returned fewer candles than asked for. `candles` is
+/// not empty; an empty response is an error.
+fn candle_shortfall(
+    market: &str,
+    interval: &str,
+    period_ms: u64,
+    candles: &[Candle],
+    count: u64,
+) -> String {
+    let returned = candles.len();
+    match candles.first() {
+        None => String::new(),
+        // One short is just the current, still-open period not being counted.
+        Some(_) if returned as u64 + 1 >= count => String::new(),
+        // A first candle well after the window start means the market's
+        // history starts there, usually because it listed later.
+        Some(first) => {
+            let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+            let start = candle_window_start(interval, period_ms, count, now_ms);
+            if first.open_time > start.saturating_add(2 * period_ms) {
+                let begins = fmt_date(first.open_time);
+                format!(
+                    "{market} {interval} history begins {begins}; returning {returned} of {count} requested.\n\n"
+                )
+            } else {
+                format!(
+                    "Returning {returned} of {count} requested; periods without trades have no candle.\n\n"
+                )
+            }
+        }
+    }
+}
+
+fn interval_ms(s: &str) -> Option<u64> {
+    match s {
+        "1m" => Some(60_000),
+        "3m" => Some(180_000),
+        "5m" => Some(300_000),
+        "15m" => Some(900_000),
+        "30m" => Some(1_800_000),
+        "1h" => Some(3_600_000),
+        "2h" => Some(7_200_000),
+        "4h" => Some(14_400_000),
+        "8h" => Some(28_800_000),
+        "12h" => Some(43_200_000),
+        "1d" => Some(86_400_000),
+        "3d" => Some(259_200_000),
+        "1w" => Some(604_800_000),
+        "1M" => Some(2_592_000_000),
+        _ => None,
+    }
+}
+
+fn parse_candle_interval(s: &str) -> Option<hypersdk::hypercore::CandleInterval> {
+    use hypersdk::hypercore::CandleInterval;
+    match s {
+        "1m" => Some(CandleInterval::OneMinute),
+        "3m" => Some(CandleInterval::ThreeMinutes),
+        "5m" => Some(CandleInterval::FiveMinutes),
+        "15m" => Some(CandleInterval::FifteenMinutes),
+        "30m" => Some(CandleInterval::ThirtyMinutes),
+        "1h" => Some(CandleInterval::OneHour),
+        "2h" => Some(CandleInterval::TwoHours),
+        "4h" => Some(CandleInterval::FourHours),
+        "8h" => Some(CandleInterval::EightHours),
+        "12h" => Some(CandleInterval::TwelveHours),
+        "1d" => Some(CandleInterval::OneDay),
+        "3d" => Some(CandleInterval::ThreeDays),
+        "1w" => Some(CandleInterval::OneWeek),
+        "1M" => Some(CandleInterval::OneMonth),
+        _ => None,
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetLiquidityAlertRequest {
+    #[schemars(description = "Symbol, e.g. \"BTC\" or \"PURR/USDC\"")]
+    pub coin: String,
+
+    #[schemars(description = "\"bid\", \"ask\" or \"both\" (default \"both\")")]
+    pub side: Option<String>,
+
+    #[schemars(description = "Smallest level size that counts as a wall, in coin units")]
+    pub min_size: f64,
+
+    #[schemars(description = "How far from mid to look, in percent (default 1, max 10)")]
+    pub band_pct: Option<f64>,
+
+    #[schemars(description = "Minutes until the alert expires (default 60, max 1440)")]
+    pub expires_minutes: Option<u64>,
+
+    #[schemars(
+        description = "\"perp\" or \"spot\" (optional; perps take precedence when a name exists in both)"
+    )]
+    pub market_kind: Option<String>,
+}
+
+/// Default and widest `band_pct` for liquidity alerts.
+const DEFAULT_LIQUIDITY_BAND_PCT: f64 = 1.0;
+const MAX_LIQUIDITY_BAND_PCT: f64 = 10.0;
+/// Default and longest lifetime of a liquidity alert, in minutes.
+const DEFAULT_LIQUIDITY_ALERT_MINUTES: u64 = 60;
+const MAX_LIQUIDITY_ALERT_MINUTES: u64 = 24 * 60;
+
+/// Watch a book over the WebSocket for large levels appearing near mid.
+/// Walls already on the book when the alert is set are listed, not
+/// reported again.
+pub async fn set_liquidity_alert(
+    state: &ServerState,
+    req: SetLiquidityAlertRequest,
+) -> Result<CallToolResult, ErrorData> {
+    use hypersdk::hypercore::Side;
+
+    use crate::liquidity::LiquidityAlert;
+
+    if !state.cache.live {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Liquidity alerts need the WebSocket feed. Set REALTIME_ENABLED=true and restart.",
+        )]));
+    }
+    let side = match req
+        .side
+        .as_deref()
+        .map(|s| s.trim().to_lowercase())
+        .as_deref()
+    {
+        None | Some("both") => None,
+        Some("bid" | "bids" | "buy") => Some(Side::Bid),
+        Some("ask" | "asks" | "sell") => Some(Side::Ask),
+        Some(other) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid side '{other}'. Use \"bid\", \"ask\" or \"both\"."
+            ))]));
+        }
+    };
+    let min_size = match Decimal::try_from(req.min_size) {
+        Ok(size) if size > Decimal::ZERO => size,
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "min_size must be a positive size in coin units.",
+            )]));
+        }
+    };
+    let band_pct = req.band_pct.unwrap_or(DEFAULT_LIQUIDITY_BAND_PCT);
+    if !(band_pct > 0.0 && band_pct <= MAX_LIQUIDITY_BAND_PCT) {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "band_pct must be above 0 and at most {MAX_LIQUIDITY_BAND_PCT}."
+        ))]));
+    }
+    let minutes = req
+        .expires_minutes
+        .unwrap_or(DEFAULT_LIQUIDITY_ALERT_MINUTES)
+        .clamp(1, MAX_LIQUIDITY_ALERT_MINUTES);
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+
+    let band = Decimal::try_from(band_pct / 100.0)
+        .map_err(|e| mcp_err(&format!("Invalid band_pct: {e}")))?;
+    let expires_at = chrono::Utc::now() + chrono::Duration::minutes(minutes as i64);
+    let mut alert = LiquidityAlert::new(
+        market.coin.clone(),
+        market.symbol.clone(),
+        side,
+        min_size,
+        band,
+        expires_at,
+    );
+
+    let book = fetch_l2_book(state, &market.coin).await?;
+    let levels = |levels: &[BookLevel]| -> Vec<(Decimal, Decimal)> {
+        levels.iter().map(|&(px, sz, _)| (px, sz)).collect()
+    };
+    let (bids, asks) = (levels(&book.bids), levels(&book.asks));
+    let walls = alert.find_walls(&bids, &asks);
+    alert.seed(&walls);
+    let mid = match (bids.first(), asks.first()) {
+        (Some((bid, _)), Some((ask, _))) => (bid + ask) / Decimal::TWO,
+        _ => Decimal::ZERO,
+    };
+    let existing: Vec<String> = walls.iter().map(|w| alert.describe_wall(w, mid)).collect();
+    let watched_side = alert.describe_side();
+
+    state.cache.prune_liquidity_alerts();
+    let (id, already_watched) = state.cache.liquidity_alerts.add(alert);
+    if !already_watched {
+        state.cache.watch_book(&market.coin);
+    }
+
+    let mut output = format!(
+        "## Liquidity Alert #{id}: {}\n\n\
+         Watching {watched_side} within {band_pct}% of mid for levels of at least {} {}. \
+         Expires {} ({}).\n\n",
+        market.describe(),
+        fmt_qty(min_size),
+        market.symbol,
+        fmt_instant(expires_at),
+        fmt_until(expires_at.timestamp_millis() as u64),
+    );
+    output.push_str(&book.warning());
+    if existing.is_empty() {
+        output.push_str("No level that size is on the book now.\n");
+    } else {
+        output.push_str(&format!(
+            "Already on the book (not reported again unless they move): {}.\n",
+            existing.join("; ")
+        ));
+    }
+    output.push_str(
+        "\nNew walls are reported at the top of the next response and in get_events. \
+         A triggered alert stays quiet for 5 minutes.\n",
+    );
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Liquidity alerts that haven't expired, with how often each fired.
+pub async fn list_liquidity_alerts(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    state.cache.prune_liquidity_alerts();
+    let alerts = state.cache.liquidity_alerts.list();
+    if alerts.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "No liquidity alerts are set. Use set_liquidity_alert to watch a book for large levels.",
+        )]));
+    }
+
+    let mut output = format!("## Liquidity Alerts ({})\n\n", alerts.len());
+    output
+        .push_str("| # | Market | Side | Min Size | Band | Expires | Triggers | Last Trigger |\n");
+    output
+        .push_str("|---|--------|------|----------|------|---------|----------|--------------|\n");
+    for alert in &alerts {
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {}% | {} | {} | {} |\n",
+            alert.id,
+            alert.symbol,
+            alert.describe_side(),
+            fmt_qty(alert.min_size),
+            (alert.band * Decimal::ONE_HUNDRED).normalize(),
+            fmt_until(alert.expires_at.timestamp_millis() as u64),
+            alert.triggers,
+            alert
+                .last_trigger
+                .as_ref()
+                .map(|(time, text)| format!("{}: {text}", fmt_time_secs(*time)))
+                .unwrap_or_else(|| "—".to_string()),
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::exchange::ExchangeApi;
+    use crate::exchange::mock::{MockExchange, text};
+
+    fn mock_state() -> ServerState {
+        ServerState::mock(Arc::new(MockExchange::new()))
+    }
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    /// Hourly `fundingHistory` entries in the requested range, at most
+    /// `FUNDING_PAGE_SIZE` per answer like the real endpoint.
+    fn hourly_funding(mock: &MockExchange) {
+        mock.respond_with("fundingHistory", |request| {
+            const HOUR_MS: u64 = 3_600_000;
+            let start = request["startTime"].as_u64().unwrap();
+            let end = request["endTime"].as_u64().unwrap_or(u64::MAX);
+            let first = start.div_ceil(HOUR_MS) * HOUR_MS;
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            let rates: Vec<_> = (first..=end.min(now))
+                .step_by(HOUR_MS as usize)
+                .take(FUNDING_PAGE_SIZE)
+                .map(|time| {
+                    json!({
+                        "coin": request["coin"],
+                        "fundingRate": "0.0000125",
+                        "premium": "0.0001",
+                        "time": time,
+                    })
+                })
+                .collect();
+            json!(rates)
+        });
+    }
+
+    fn assert_hourly_without_gaps(rates: &[FundingRate], hours: u64) {
+        assert!(rates.len() as u64 >= hours - 1, "{} entries", rates.len());
+        assert!(rates.windows(2).all(|w| w[1].time - w[0].time == 3_600_000));
+    }
+
+    #[tokio::test]
+    async fn funding_history_longer_than_a_page_has_no_gaps() {
+        let mock = Arc::new(MockExchange::new());
+        hourly_funding(&mock);
+        let state = ServerState::mock(mock.clone());
+
+        // A first request longer than one page.
+        let rates = get_cached_funding(&state, "ETH", Duration::from_secs(720 * 3600))
+            .await
+            .unwrap();
+        assert_hourly_without_gaps(&rates, 720);
+
+        // Extending a cached window back by more than one page.
+        let rates = get_cached_funding(&state, "BTC", Duration::from_secs(24 * 3600))
+            .await
+            .unwrap();
+        assert_hourly_without_gaps(&rates, 24);
+        let rates = get_cached_funding(&state, "BTC", Duration::from_secs(720 * 3600))
+            .await
+            .unwrap();
+        assert_hourly_without_gaps(&rates, 720);
+    }
+
+    fn level(px: &str, sz: &str) -> BookLevel {
+        (d(px), d(sz), 1)
+    }
+
+    #[test]
+    fn validate_book_flags_malformed_snapshots() {
+        let now = 1_760_000_000_000;
+        let bids = [level("100", "2"), level("99.5", "1")];
+        let asks = [level("100.5", "1"), level("101", "3")];
+        assert!(validate_book(&bids, &asks, Some(now - 500), Some(d("100.2")), now).is_empty());
+
+        assert_eq!(
+            validate_book(&[level("101", "1")], &asks, Some(now), None, now),
+            ["the book is crossed (best bid $101 ≥ best ask $100.5); prices may be wrong."]
+        );
+        assert_eq!(
+            validate_book(&[level("100.5", "1")], &asks, None, None, now),
+            ["the book is crossed (best bid $100.5 ≥ best ask $100.5); prices may be wrong."]
+        );
+        assert_eq!(
+            validate_book(&[], &asks, None, None, now),
+            ["the book has no bids."]
+        );
+        assert_eq!(
+            validate_book(&bids, &[], None, None, now),
+            ["the book has no asks."]
+        );
+        assert_eq!(
+            validate_book(&[], &[], Some(now - 42_000), None, now),
+            ["the order book is empty.", "the snapshot is 42s old."]
+        );
+        assert_eq!(
+            validate_book(&bids, &asks, Some(now), Some(d("97")), now),
+            ["the book mid $100.25 is 335 bps from the live mid $97; the snapshot may be stale."]
+        );
+        // A zero live mid (no data yet) is ignored rather than divided by.
+        assert!(validate_book(&bids, &asks, Some(now), Some(Decimal::ZERO), now).is_empty());
+    }
+
+    #[test]
+    fn book_summary_handles_crossed_and_empty_books() {
+        let summary = BookSummary::from_levels(
+            &[level("100", "3"), level("99", "1")],
+            &[level("101", "1"), level("102", "2")],
+        );
+        assert_eq!(summary.mid(), Some(d("100.5")));
+        assert_eq!(
+            summary.spread_bps().map(|b| b.round_dp(2)),
+            Some(d("99.50"))
+        );
+        // Three to one on the bid: the price leans toward the ask.
+        assert_eq!(summary.microprice(), Some(d("100.75")));
+
+        let crossed = BookSummary::from_levels(&[level("101", "1")], &[level("100", "1")]);
+        assert_eq!(crossed.spread(), Some(d("-1")));
+        assert_eq!(
+            crossed.spread_bps().map(|b| b.round_dp(2)),
+            Some(d("-99.50"))
+        );
+        assert_eq!(crossed.microprice(), Some(d("100.5")));
+
+        let empty_sizes = BookSummary::from_levels(&[level("100", "0")], &[level("101", "0")]);
+        assert_eq!(empty_sizes.microprice(), Some(d("100.5")));
+
+        let one_sided = BookSummary::from_levels(&[level("100", "3")], &[]);
+        assert_eq!(
+            (
+                one_sided.mid(),
+                one_sided.spread_bps(),
+                one_sided.microprice()
+            ),
+            (None, None, None)
+        );
+        assert_eq!(
+            BookSummary::from_levels(&[], &[]).render(),
+            "| Metric | Value |\n|--------|-------|\n\
+             | Best Bid | — |\n\
+             | Best Ask | — |\n\
+             | Spread | — |\n\
+             | Mid | — |\n\
+             | Microprice | — |\n\
+             | Displayed Bids | 0 ($0) |\n\
+             | Displayed Asks | 0 ($0) |\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_markets_lists_spot_pairs_by_volume() {
+        let req = GetMarketsRequest {
+            market_type: Some("spot".into()),
+            sort_by: None,
+        };
+        let result = get_markets(&mock_state(), req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## Spot Markets (2 total)\n\n\
+             | Market | Price | 24h Change | 24h Volume | Market Cap | Size Decimals | EVM |\n\
+             |--------|-------|------------|------------|------------|---------------|-----|\n\
+             | HYPE/USDC | $42.5 | +6.25% | $95.0M | $14.0B | 2 | Yes |\n\
+             | PURR/USDC | $0.21 | +5.00% | $150.0K | $126.0M | 0 |  |\n\
+             \n\
+             Prices: context prices from the cached asset contexts (no live mids).\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_markets_fetches_only_what_each_market_type_needs() {
+        for (market_type, expected) in [
+            ("perp", vec!["metaAndAssetCtxs", "perpsAtOpenInterestCap"]),
+            ("spot", vec!["spotMetaAndAssetCtxs"]),
+            (
+                "all",
+                vec![
+                    "metaAndAssetCtxs",
+                    "perpsAtOpenInterestCap",
+                    "spotMetaAndAssetCtxs",
+                ],
+            ),
+            ("options", vec![]),
+        ] {
+            let mock = Arc::new(MockExchange::new());
+            mock.respond("perpsAtOpenInterestCap", json!([]));
+            let state = ServerState::mock(mock.clone());
+            let req = GetMarketsRequest {
+                market_type: Some(market_type.into()),
+                sort_by: None,
+            };
+            get_markets(&state, req).await.unwrap();
+            let kinds: Vec<String> = mock
+                .requests()
+                .iter()
+                .map(|r| r["type"].as_str().unwrap_or_default().to_string())
+                .collect();
+            // Prices come from the asset contexts, never an allMids request.
+            assert_eq!(kinds, expected, "{market_type}");
+        }
+    }
+
+    #[tokio::test]
+    async fn get_market_summary_resolves_spot_base_token() {
+        let req = GetMarketSummaryRequest {
+            coin: "HYPE".into(),
+            market_kind: None,
+        };
+        let result = get_market_summary(&mock_state(), req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## HYPE/USDC Spot Market (@107)\n\n\
+             | Metric | Value |\n\
+             |--------|-------|\n\
+             | Mark Price | $42.5 |\n\
+             | Mid Price | $42.51 |\n\
+             | 24h Volume | $95.0M |\n\
+             | Previous Day Price | $40 |\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_margin_tiers_renders_tier_table() {
+        let req = GetMarginTiersRequest { coin: "BTC".into() };
+        let result = get_margin_tiers(&mock_state(), req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "## BTC Margin Tiers (tiered 40x)\n\n\
+             | Position Notional | Max Leverage | Initial Margin | Maintenance Margin | Maintenance Deduction |\n\
+             |-------------------|--------------|----------------|--------------------|-----------------------|\n\
+             | $0 – $150,000,000 | 40x | 2.50% | 1.25% | $0.00 |\n\
+             | $150,000,000 and above | 20x | 5.00% | 2.50% | $1,875,000.00 |\n\
+             \n\
+             **Max position notional by leverage:**\n\
+             - 40x: $150,000,000\n\
+             - 20x: no tier limit\n\
+             \n\
+             A position's leverage is capped by the tier its notional falls in. Maintenance \
+             margin is notional × the tier's rate minus its deduction; the position is liquidated \
+             when equity falls below it.\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_market_summary_reports_missing_market_data() {
+        let mock = Arc::new(MockExchange::new());
+        mock.respond(
+            "spotMetaAndAssetCtxs",
+            json!([{ "universe": [], "tokens": [] }, []]),
+        );
+        let state = ServerState::mock(Arc::clone(&mock) as Arc<dyn ExchangeApi>);
+        let req = GetMarketSummaryRequest {
+            coin: "@107".into(),
+            market_kind: None,
+        };
+        let result = get_market_summary(&state, req).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(text(&result), "No market data for HYPE/USDC (spot, @107)");
+        assert_eq!(
+            mock.requests(),
+            vec![json!({ "type": "spotMetaAndAssetCtxs" })]
+        );
+    }
 }
@@ -1,5 +1,9 @@
 pub mod account;
+pub mod analysis;
+pub mod diagnostics;
+pub mod format;
 pub mod market;
+pub mod timestamps;
 pub mod trading;
 pub mod transfer;
 pub mod vault;
@@ -0,0 +1,100 @@
+//! Timestamp formatting shared by tool output. Times always name their zone:
+//! UTC, or `HYPERLIQUID_DISPLAY_TZ` when set. Fills and order events carry
+//! seconds, and live views show very recent items as "4m ago".
+
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+static DISPLAY_TZ: OnceLock<Tz> = OnceLock::new();
+
+/// Items younger than this are shown relative to now in live views.
+const RECENT_SECS: i64 = 60 * 60;
+
+/// Set once at startup from `HYPERLIQUID_DISPLAY_TZ`.
+pub fn set_display_tz(tz: Tz) {
+    let _ = DISPLAY_TZ.set(tz);
+}
+
+fn from_ms(ms: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(i64::try_from(ms).ok()?)
+}
+
+fn render(at: DateTime<Utc>, pattern: &str) -> String {
+    match DISPLAY_TZ.get() {
+        Some(tz) => at
+            .with_timezone(tz)
+            .format(&format!("{pattern} %Z"))
+            .to_string(),
+        None => format!("{} UTC", at.format(pattern)),
+    }
+}
+
+/// "2026-10-16 14:05 UTC", for candles, funding and other periodic data.
+pub fn fmt_time(ms: u64) -> String {
+    from_ms(ms)
+        .map(|at| render(at, "%Y-%m-%d %H:%M"))
+        .unwrap_or_else(|| ms.to_string())
+}
+
+/// "2026-10-16 14:05:32 UTC", for fills and order events.
+pub fn fmt_time_secs(ms: u64) -> String {
+    from_ms(ms)
+        .map(fmt_instant)
+        .unwrap_or_else(|| ms.to_string())
+}
+
+/// A `DateTime` with seconds: "2026-10-16 14:05:32 UTC".
+pub fn fmt_instant(at: DateTime<Utc>) -> String {
+    render(at, "%Y-%m-%d %H:%M:%S")
+}
+
+/// Time of day only, for moments within the last day: "14:05 UTC".
+pub fn fmt_clock(at: DateTime<Utc>) -> String {
+    render(at, "%H:%M")
+}
+
+/// Calendar date in the display zone: "2026-10-16".
+pub fn fmt_date(ms: u64) -> String {
+    from_ms(ms)
+        .map(|at| match DISPLAY_TZ.get() {
+            Some(tz) => at.with_timezone(tz).format("%Y-%m-%d").to_string(),
+            None => at.format("%Y-%m-%d").to_string(),
+        })
+        .unwrap_or_else(|| ms.to_string())
+}
+
+/// "45s ago", "4m ago", "3h ago", "2d ago".
+pub fn fmt_ago(ms: u64) -> String {
+    let now = Utc::now().timestamp_millis().max(0) as u64;
+    let secs = now.saturating_sub(ms) / 1000;
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Time left until `ms`: "in 14m 05s", "in 2h 10m", or "now" once past.
+pub fn fmt_until(ms: u64) -> String {
+    let now = Utc::now().timestamp_millis().max(0) as u64;
+    let secs = ms.saturating_sub(now) / 1000;
+    match secs {
+        0 => "now".to_string(),
+        1..3600 => format!("in {}m {:02}s", secs / 60, secs % 60),
+        _ => format!("in {}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Relative for items from the last hour, absolute with seconds otherwise.
+/// For live views such as open orders and recent fills.
+pub fn fmt_recent(ms: u64) -> String {
+    let age_secs = (Utc::now().timestamp_millis() - ms as i64) / 1000;
+    if (0..RECENT_SECS).contains(&age_secs) {
+        fmt_ago(ms)
+    } else {
+        fmt_time_secs(ms)
+    }
+}
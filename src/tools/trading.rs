@@ -1,41 +1,111 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::atomic::Ordering;
 
 use chrono::{Duration, Utc};
-use hypersdk::Decimal;
+use either::Either;
+use hypersdk::hypercore::types::BasicOrder;
 use hypersdk::hypercore::{
-    BatchCancel, BatchModify, Cancel, Modify, OrderGrouping, OrderRequest, OrderResponseStatus,
-    OrderTypePlacement, TimeInForce,
+    BatchCancel, BatchModify, Cancel, Cloid, Modify, OrderGrouping, OrderRequest,
+    OrderResponseStatus, OrderTypePlacement, TimeInForce, TpSl, UserBalance,
 };
+use hypersdk::{Address, Decimal};
 use rmcp::{model::*, schemars};
-use rust_decimal::prelude::ToPrimitive;
 
+use crate::cloids::Submission;
+use crate::config;
+use crate::events::EventKind;
 use crate::hyperliquid;
-use crate::state::{ServerState, mcp_err};
+use crate::meta::MarginTable;
+use crate::paper::PaperAccount;
+use crate::state::{MarketKind, ResolvedMarket, ServerState, dry_run_result, mcp_err};
+use crate::tools::account;
+use crate::tools::format::{fmt_price, fmt_qty, fmt_usd, round_sig};
+use crate::tools::market::{
+    asset_ctx, fetch_l2_book, get_cached_candles, get_capped_perps, margin_table,
+};
+use crate::tools::timestamps::fmt_instant;
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct PlaceOrderRequest {
-    #[schemars(description = "Trading pair symbol, e.g. \"BTC\", \"ETH\"")]
+    #[schemars(
+        description = "Trading pair symbol, e.g. \"BTC\", \"ETH\", or a spot pair like \"HYPE/USDC\""
+    )]
     pub coin: String,
 
+    #[schemars(
+        description = "\"perp\" or \"spot\" (optional; perps take precedence when a name exists in both)"
+    )]
+    pub market_kind: Option<String>,
+
     #[schemars(description = "Order side: \"buy\" or \"sell\"")]
     pub side: String,
 
-    #[schemars(description = "Order size in coin units (e.g. 0.01 for 0.01 BTC)")]
-    pub size: f64,
+    #[schemars(
+        description = "Order size in coin units (e.g. 0.01 for 0.01 BTC). Provide either size or size_usd."
+    )]
+    pub size: Option<f64>,
+
+    #[schemars(
+        description = "Order size as a USD (quote) amount instead of coin units, e.g. 100 to spend 100 USDC on a spot buy. Rounded down to the size step."
+    )]
+    pub size_usd: Option<f64>,
 
-    #[schemars(description = "Limit price in USD. Required for limit orders, omit for market.")]
+    #[schemars(
+        description = "Limit price in USD. Required for limit orders unless price_reference is given; omit for market."
+    )]
     pub price: Option<f64>,
 
-    #[schemars(description = "Order type: \"limit\" (default) or \"market\"")]
+    #[schemars(
+        description = "Price a limit order relative to \"mid\", \"best_bid\", \"best_ask\" or \"mark\" at submission, instead of an absolute price"
+    )]
+    pub price_reference: Option<String>,
+
+    #[schemars(
+        description = "Signed offset from price_reference in basis points, e.g. -5 for 5 bps below (default 0)"
+    )]
+    pub price_offset_bps: Option<f64>,
+
+    #[schemars(
+        description = "Order type: \"limit\" (default) or \"market\". A price without an order type is a limit order."
+    )]
     pub order_type: Option<String>,
 
     #[schemars(description = "Time in force: \"Gtc\" (default), \"Ioc\", or \"Alo\" (post-only)")]
     pub time_in_force: Option<String>,
 
+    #[schemars(
+        description = "Instead of order_type and time_in_force: \"passive\" (post-only, one tick inside the spread unless a price is given), \"normal\" (GTC at price, price_reference or the mid) or \"immediate\" (market order)"
+    )]
+    pub urgency: Option<String>,
+
     #[schemars(
         description = "If true, order can only reduce an existing position (default false)"
     )]
     pub reduce_only: Option<bool>,
+
+    #[schemars(
+        description = "Place the order even if an identical one was submitted in the last 10 seconds (default false)"
+    )]
+    pub allow_duplicate: Option<bool>,
+
+    #[schemars(
+        description = "Skip the reduce-only check against the current position, e.g. when a position is about to open, and the spot size and balance checks (default false)"
+    )]
+    pub skip_validation: Option<bool>,
+
+    #[schemars(
+        description = "Send a market order even if its estimated fill is far beyond the market's impact spread (default false)"
+    )]
+    pub force: Option<bool>,
+
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -45,6 +115,30 @@ pub struct CancelOrderRequest {
 
     #[schemars(description = "The numeric order ID to cancel")]
     pub order_id: u64,
+
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CancelOrdersRequest {
+    #[schemars(
+        description = "Orders to cancel, by order id (e.g. \"123456789\") or cloid (\"0x\" and 32 hex digits), mixed freely"
+    )]
+    pub orders: Vec<String>,
+
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -53,6 +147,14 @@ pub struct CancelAllOrdersRequest {
         description = "Cancel only orders for this coin (optional, cancels all if omitted)"
     )]
     pub coin: Option<String>,
+
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -71,6 +173,14 @@ pub struct ModifyOrderRequest {
 
     #[schemars(description = "New order size in coin units")]
     pub new_size: f64,
+
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -83,543 +193,5501 @@ pub struct SetLeverageRequest {
 
     #[schemars(description = "Margin mode: \"cross\" (default) or \"isolated\"")]
     pub mode: Option<String>,
+
+    #[schemars(
+        description = "Set to true to only report the margin impact without changing anything"
+    )]
+    pub preview: Option<bool>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetLeverageAllRequest {
+    #[schemars(
+        description = "Coins to set, e.g. [\"BTC\", \"ETH\"] (optional, defaults to every perp with a position or open order)"
+    )]
+    pub coins: Option<Vec<String>>,
+
+    #[schemars(description = "Leverage multiplier to apply to each coin (e.g. 3 for 3x)")]
+    pub leverage: u32,
+
+    #[schemars(description = "Margin mode: \"cross\" (default) or \"isolated\"")]
+    pub mode: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct ClosePositionRequest {
     #[schemars(description = "The coin to close position for, e.g. \"BTC\"")]
     pub coin: String,
+
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct ScheduleCancelRequest {
+pub struct ClosePositionsRequest {
+    #[schemars(description = "Coins whose positions to close, e.g. [\"ETH\", \"SOL\"]")]
+    pub coins: Option<Vec<String>>,
+
     #[schemars(
-        description = "Seconds from now to cancel all open orders (e.g. 300 for 5 minutes)"
+        description = "Instead of coins: close every position with negative unrealized PnL"
     )]
-    pub seconds_from_now: u64,
-}
+    pub all_losing: Option<bool>,
 
-fn format_order_response(statuses: &[OrderResponseStatus]) -> String {
-    let parts: Vec<String> = statuses
-        .iter()
-        .map(|s| match s {
-            OrderResponseStatus::Filled {
-                total_sz,
-                avg_px,
-                oid,
-            } => {
-                format!("Filled {total_sz} @ ${avg_px} (order ID: {oid})")
-            }
-            OrderResponseStatus::Resting { oid, .. } => {
-                format!("Resting (order ID: {oid})")
-            }
-            OrderResponseStatus::Error(msg) => format!("Error: {msg}"),
-            OrderResponseStatus::Success => "Success".into(),
-        })
-        .collect();
-    parts.join("; ")
-}
+    #[schemars(
+        description = "Instead of coins: close every position with positive unrealized PnL"
+    )]
+    pub all_winning: Option<bool>,
 
-fn format_exchange_response(response: &serde_json::Value) -> String {
-    if let Some(status) = response.get("status") {
-        if status.as_str() == Some("ok") {
-            if let Some(data) = response.get("response").and_then(|r| r.get("data")) {
-                if let Some(statuses) = data.get("statuses").and_then(|s| s.as_array()) {
-                    let parts: Vec<String> = statuses
-                        .iter()
-                        .map(|s| {
-                            if let Some(filled) = s.get("filled") {
-                                let sz = filled
-                                    .get("totalSz")
-                                    .and_then(|v| v.as_str())
-                                    .unwrap_or("?");
-                                let px =
-                                    filled.get("avgPx").and_then(|v| v.as_str()).unwrap_or("?");
-                                let oid = filled.get("oid").and_then(|v| v.as_u64()).unwrap_or(0);
-                                format!("Filled {sz} @ ${px} (order ID: {oid})")
-                            } else if let Some(resting) = s.get("resting") {
-                                let oid = resting.get("oid").and_then(|v| v.as_u64()).unwrap_or(0);
-                                format!("Resting (order ID: {oid})")
-                            } else if let Some(error) = s.get("error") {
-                                format!("Error: {}", error.as_str().unwrap_or("unknown"))
-                            } else {
-                                "Success".into()
-                            }
-                        })
-                        .collect();
-                    return parts.join("; ");
-                }
-            }
-            return format!(
-                "OK: {}",
-                response
-                    .get("response")
-                    .and_then(|r| r.get("type"))
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("success")
-            );
-        } else {
-            return format!(
-                "Error: {}",
-                response.as_str().unwrap_or(&response.to_string())
-            );
-        }
-    }
-    format!("{response}")
-}
+    #[schemars(
+        description = "Account to act on when a vault is configured: \"vault\" (the default in vault mode) or \"personal\""
+    )]
+    pub account_scope: Option<String>,
 
-fn maybe_append_builder_nudge(state: &ServerState, output: &mut String) {
-    if !state.builder_fee_approved.load(Ordering::Relaxed)
-        && !state.nudge_shown.swap(true, Ordering::Relaxed)
-    {
-        output.push_str(
-            "\n\n---\n\
-             **Note:** Builder fees (0.01%) are not yet approved for this account. \
-             To support development, add `HYPERLIQUID_PRIVATE_KEY=0x...` to your \
-             `~/.config/hyperliquid-mcp/.env` file (temporarily remove `HYPERLIQUID_AGENT_PRIVATE_KEY`) \
-             and restart — the server will handle agent creation and fee approval automatically. \
-             Run `check_builder_fee` for details.",
-        );
-    }
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
-fn parse_tif(s: &str) -> TimeInForce {
-    match s.to_lowercase().as_str() {
-        "gtc" => TimeInForce::Gtc,
-        "ioc" => TimeInForce::Ioc,
-        "alo" | "post_only" | "post-only" => TimeInForce::Alo,
-        _ => TimeInForce::Gtc,
-    }
-}
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ResetPaperAccountRequest {
+    #[schemars(
+        description = "USDC to start from (optional, defaults to the current starting balance)"
+    )]
+    pub starting_balance: Option<f64>,
 
-fn to_decimal(f: f64) -> Result<Decimal, ErrorData> {
-    Decimal::try_from(f).map_err(|e| mcp_err(&format!("Invalid decimal value: {e}")))
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
-/// Round a price to 5 significant figures
-fn round_price_5sf(price: Decimal) -> Decimal {
-    if price.is_zero() {
-        return price;
-    }
-    let abs_f = price.abs().to_f64().unwrap_or(0.0);
-    if abs_f == 0.0 {
-        return price;
-    }
-    let integer_digits = abs_f.log10().floor() as i32 + 1;
-    let dp = (5 - integer_digits).max(0) as u32;
-    price.round_dp(dp)
-}
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchOrderLeg {
+    #[schemars(description = "Trading pair symbol, e.g. \"BTC\"")]
+    pub coin: String,
 
-fn make_cloid() -> alloy::primitives::B128 {
-    let uuid = uuid::Uuid::new_v4();
-    alloy::primitives::B128::from_slice(uuid.as_bytes())
-}
+    #[schemars(description = "Order side: \"buy\" or \"sell\"")]
+    pub side: String,
 
-pub async fn place_order(
-    state: &ServerState,
-    req: PlaceOrderRequest,
-) -> Result<CallToolResult, ErrorData> {
-    let signer = state.require_signer()?;
-    let asset = state.resolve_asset(&req.coin)?;
+    #[schemars(description = "Order size in coin units")]
+    pub size: f64,
 
-    let is_buy = match req.side.to_lowercase().as_str() {
-        "buy" | "b" | "long" => true,
-        "sell" | "s" | "short" => false,
-        _ => {
-            return Ok(CallToolResult::error(vec![Content::text(
-                "Invalid side. Use \"buy\" or \"sell\".",
-            )]));
-        }
-    };
+    #[schemars(
+        description = "Limit price. Required for non-trigger legs; for trigger legs, omit to fill at market when triggered."
+    )]
+    pub price: Option<f64>,
 
-    let order_type_str = req.order_type.as_deref().unwrap_or("limit");
-    let reduce_only = req.reduce_only.unwrap_or(false);
-    let size = to_decimal(req.size)?;
+    #[schemars(description = "Trigger price. Makes this a take-profit or stop-loss leg.")]
+    pub trigger_price: Option<f64>,
 
-    let (limit_px, order_type) = if order_type_str == "market" {
-        // For market orders, use a very high/low limit price with IOC
-        // Fetch current mid price and apply 3% slippage
-        let mids = state
-            .client
-            .all_mids(None)
-            .await
-            .map_err(|e| mcp_err(&format!("Failed to fetch prices for market order: {e}")))?;
+    #[schemars(description = "\"tp\" or \"sl\". Required with trigger_price.")]
+    pub tpsl: Option<String>,
 
-        let mid_price = mids
-            .get(&req.coin)
-            .ok_or_else(|| mcp_err(&format!("No mid price available for {}", req.coin)))?;
+    #[schemars(
+        description = "Time in force for non-trigger legs: \"Gtc\" (default), \"Ioc\" or \"Alo\""
+    )]
+    pub time_in_force: Option<String>,
 
-        // 0.05 = 5%
-        let slippage = Decimal::new(5, 2);
-        let limit_px = if is_buy {
-            mid_price * (Decimal::ONE + slippage)
-        } else {
-            mid_price * (Decimal::ONE - slippage)
-        };
-        let limit_px = round_price_5sf(limit_px);
+    #[schemars(
+        description = "Only reduce an existing position (default false; trigger legs are always reduce-only)"
+    )]
+    pub reduce_only: Option<bool>,
+}
 
-        (
-            limit_px,
-            OrderTypePlacement::Limit {
-                tif: TimeInForce::Ioc,
-            },
-        )
-    } else {
-        // Limit order
-        let price = req.price.ok_or_else(|| {
-            mcp_err(
-                "Price is required for limit orders. Provide 'price' or use order_type: \"market\".",
-            )
-        })?;
-        let tif = parse_tif(req.time_in_force.as_deref().unwrap_or("Gtc"));
-        (to_decimal(price)?, OrderTypePlacement::Limit { tif })
-    };
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PlaceBatchOrdersRequest {
+    #[schemars(description = "Orders to place in one exchange action (max 20)")]
+    pub orders: Vec<BatchOrderLeg>,
 
-    let order = OrderRequest {
-        asset,
-        is_buy,
-        reduce_only,
-        limit_px,
-        sz: size,
-        cloid: make_cloid(),
-        order_type,
-    };
+    #[schemars(
+        description = "\"na\" (default, independent orders), \"normal_tpsl\" (one entry order followed by its TP/SL legs) or \"position_tpsl\" (TP/SL legs attached to the open position)"
+    )]
+    pub grouping: Option<String>,
 
-    let nonce = state.next_nonce();
-    let response = hyperliquid::place_order_with_builder(
-        &state.http,
-        state.chain,
-        signer.as_ref(),
-        vec![order],
-        OrderGrouping::Na,
-        Some(state.builder_info()),
-        nonce,
-        state.vault_addr(),
-    )
-    .await
-    .map_err(|e| mcp_err(&format!("Order placement failed: {e}")))?;
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
 
-    state.cache.invalidate_user_data().await;
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PlacePairTradeRequest {
+    #[schemars(description = "Perp to buy, e.g. \"ETH\"")]
+    pub long_coin: String,
 
-    let side_str = if is_buy { "Buy" } else { "Sell" };
-    let mut output = format!("## Order Result: {side_str} {} {}", req.size, req.coin);
-    if order_type_str == "market" {
-        output.push_str(" @ Market\n\n");
-    } else if let Some(price) = req.price {
-        output.push_str(&format!(" @ ${price}\n\n"));
-    } else {
-        output.push('\n');
-    }
+    #[schemars(description = "Perp to sell short, e.g. \"BTC\"")]
+    pub short_coin: String,
 
-    output.push_str(&format!(
-        "Status: {}\n",
-        format_exchange_response(&response)
-    ));
+    #[schemars(description = "Total notional across both legs, in USD")]
+    pub notional_usd: f64,
 
-    maybe_append_builder_nudge(state, &mut output);
+    #[schemars(
+        description = "How to split the notional: \"equal\" (default, 50/50) or \"beta\" (hedge the long leg's beta to the short leg, from 7 days of hourly returns)"
+    )]
+    pub weighting: Option<String>,
 
-    Ok(CallToolResult::success(vec![Content::text(output)]))
+    #[schemars(
+        description = "Send even if a leg's estimated fill would trip the impact guard (default false)"
+    )]
+    pub force: Option<bool>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
-pub async fn cancel_order(
-    state: &ServerState,
-    req: CancelOrderRequest,
-) -> Result<CallToolResult, ErrorData> {
-    let signer = state.require_signer()?;
-    let asset = state.resolve_asset(&req.coin)?;
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FlattenCoinRequest {
+    #[schemars(description = "The coin to exit entirely, e.g. \"SOL\"")]
+    pub coin: String,
 
-    let cancel = Cancel {
-        asset,
-        oid: req.order_id,
-    };
+    #[schemars(
+        description = "Close with a reduce-only GTC limit at this price instead of at market (optional)"
+    )]
+    pub limit_price: Option<f64>,
 
-    let nonce = state.next_nonce();
-    let response = state
-        .client
-        .cancel(
-            signer.as_ref(),
-            BatchCancel {
-                cancels: vec![cancel],
-            },
-            nonce,
-            state.vault_addr(),
-            None,
-        )
-        .await
-        .map_err(|e| mcp_err(&format!("Cancel failed: {e}")))?;
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
 
-    state.cache.invalidate_user_data().await;
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ScheduleCancelRequest {
+    #[schemars(
+        description = "Seconds from now to cancel all open orders (e.g. 300 for 5 minutes)"
+    )]
+    pub seconds_from_now: u64,
 
-    Ok(CallToolResult::success(vec![Content::text(format!(
-        "Cancel order {} on {}: {}",
-        req.order_id,
-        req.coin,
-        format_order_response(&response)
-    ))]))
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckSpotReadinessRequest {
+    #[schemars(description = "Spot pair, e.g. \"HYPE/USDC\" or \"PURR\"")]
+    pub coin: String,
+
+    #[schemars(description = "Order side: \"buy\" or \"sell\"")]
+    pub side: String,
+
+    #[schemars(description = "Order size in base token units")]
+    pub size: f64,
+
+    #[schemars(description = "Limit price to check against (optional, defaults to the mid)")]
+    pub price: Option<f64>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ConsolidateDustRequest {
+    #[schemars(
+        description = "Balances worth less than this many USD count as dust (default 1). The exchange only sells balances worth at least $10, so set this above 10 to convert anything; smaller balances are listed as unconvertible."
+    )]
+    pub threshold_usd: Option<f64>,
+
+    #[schemars(
+        description = "Set to true to sell the dust into USDC. Without it, only lists what would be sold."
+    )]
+    pub confirm: Option<bool>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+/// What the exchange did with one order in an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum OrderOutcomeStatus {
+    Filled,
+    Resting,
+    Success,
+    Error,
+}
+
+/// One order's result, parsed from an exchange response and, for orders
+/// this server built, joined with the request by position. The markdown
+/// status line, the resting orders table and the JSON content block are all
+/// rendered from it.
+#[derive(Debug, Clone, serde::Serialize)]
+struct PlacedOrderOutcome {
+    /// Exchange coin name, e.g. "BTC" or "@107".
+    coin: Option<String>,
+    /// Display name, e.g. "HYPE/USDC (spot, @107)".
+    market: Option<String>,
+    side: Option<&'static str>,
+    /// Size and price as given by the caller, before rounding.
+    requested_size: Option<Decimal>,
+    requested_price: Option<Decimal>,
+    /// Size and price as sent, after rounding to the market's precision.
+    size: Option<Decimal>,
+    price: Option<Decimal>,
+    tif: Option<String>,
+    reduce_only: Option<bool>,
+    status: OrderOutcomeStatus,
+    oid: Option<u64>,
+    cloid: Option<String>,
+    filled_size: Option<Decimal>,
+    avg_price: Option<Decimal>,
+    error: Option<String>,
+}
+
+impl PlacedOrderOutcome {
+    fn new(status: OrderOutcomeStatus) -> Self {
+        Self {
+            coin: None,
+            market: None,
+            side: None,
+            requested_size: None,
+            requested_price: None,
+            size: None,
+            price: None,
+            tif: None,
+            reduce_only: None,
+            status,
+            oid: None,
+            cloid: None,
+            filled_size: None,
+            avg_price: None,
+            error: None,
+        }
+    }
+
+    /// Fill in what was sent for this order.
+    fn with_order(mut self, market: &ResolvedMarket, order: &OrderRequest) -> Self {
+        self.coin = Some(market.coin.clone());
+        self.market = Some(market.describe());
+        self.side = Some(if order.is_buy { "buy" } else { "sell" });
+        self.size = Some(order.sz);
+        self.price = Some(order.limit_px);
+        self.tif = Some(match &order.order_type {
+            OrderTypePlacement::Limit { tif } => format!("{tif:?}"),
+            OrderTypePlacement::Trigger { tpsl, .. } => format!("{tpsl:?} trigger"),
+        });
+        self.reduce_only = Some(order.reduce_only);
+        self.cloid.get_or_insert_with(|| order.cloid.to_string());
+        self
+    }
+
+    fn describe(&self) -> String {
+        let oid = self.oid.unwrap_or(0);
+        match self.status {
+            OrderOutcomeStatus::Filled => format!(
+                "Filled {} @ ${} (order ID: {oid})",
+                self.filled_size.unwrap_or_default(),
+                self.avg_price.unwrap_or_default()
+            ),
+            OrderOutcomeStatus::Resting => format!("Resting (order ID: {oid})"),
+            OrderOutcomeStatus::Success => "Success".into(),
+            OrderOutcomeStatus::Error => match self.expiry() {
+                Some(expiry) => expiry.describe(self.price, self.side == Some("buy")),
+                None => format!("Error: {}", self.error.as_deref().unwrap_or("unknown")),
+            },
+        }
+    }
+
+    /// Whether the order ended without resting or filling because of its
+    /// time in force rather than a real rejection.
+    fn expiry(&self) -> Option<Expiry> {
+        match self.status {
+            OrderOutcomeStatus::Error => Expiry::parse(self.error.as_deref()?),
+            _ => None,
+        }
+    }
+}
+
+/// An IOC or post-only order that the exchange dropped on arrival. Both come
+/// back as errors with no order ID, and nothing is left working.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expiry {
+    /// IOC (including market orders) with nothing to match within the limit.
+    IocUnfilled,
+    /// Post-only that would have crossed; the exchange reports the best bid
+    /// and ask at the time.
+    AloWouldCross {
+        bid: Option<Decimal>,
+        ask: Option<Decimal>,
+    },
+}
+
+impl Expiry {
+    fn parse(error: &str) -> Option<Self> {
+        if error.starts_with("Order could not immediately match") {
+            return Some(Expiry::IocUnfilled);
+        }
+        if !error.starts_with("Post only order would have immediately matched") {
+            return None;
+        }
+        // "..., bbo was 3010.0@3010.5. asset=4"
+        let bbo = error
+            .split("bbo was ")
+            .nth(1)
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(|bbo| bbo.trim_end_matches('.'));
+        let (bid, ask) = match bbo.and_then(|bbo| bbo.split_once('@')) {
+            Some((bid, ask)) => (bid.parse().ok(), ask.parse().ok()),
+            None => (None, None),
+        };
+        Some(Expiry::AloWouldCross { bid, ask })
+    }
+
+    fn describe(self, price: Option<Decimal>, is_buy: bool) -> String {
+        match self {
+            Expiry::IocUnfilled => {
+                "Expired unfilled — no liquidity within your limit price; nothing is resting"
+                    .to_string()
+            }
+            Expiry::AloWouldCross { bid, ask } => {
+                let at = price
+                    .map(|p| format!(" at {}", fmt_price(p)))
+                    .unwrap_or_default();
+                let touch = if is_buy {
+                    ask.map(|a| format!(" (best ask {})", fmt_price(a)))
+                } else {
+                    bid.map(|b| format!(" (best bid {})", fmt_price(b)))
+                };
+                format!(
+                    "Rejected — post-only order would cross the spread{at}{}; nothing is resting",
+                    touch.unwrap_or_default()
+                )
+            }
+        }
+    }
+}
+
+/// Outcomes from the SDK's parsed statuses (cancel and modify).
+fn outcomes_from_statuses(statuses: &[OrderResponseStatus]) -> Vec<PlacedOrderOutcome> {
+    statuses
+        .iter()
+        .map(|s| match s {
+            OrderResponseStatus::Filled {
+                total_sz,
+                avg_px,
+                oid,
+            } => PlacedOrderOutcome {
+                oid: Some(*oid),
+                filled_size: Some(*total_sz),
+                avg_price: Some(*avg_px),
+                ..PlacedOrderOutcome::new(OrderOutcomeStatus::Filled)
+            },
+            OrderResponseStatus::Resting { oid, cloid } => PlacedOrderOutcome {
+                oid: Some(*oid),
+                cloid: cloid.map(|c| c.to_string()),
+                ..PlacedOrderOutcome::new(OrderOutcomeStatus::Resting)
+            },
+            OrderResponseStatus::Error(msg) => PlacedOrderOutcome {
+                error: Some(msg.clone()),
+                ..PlacedOrderOutcome::new(OrderOutcomeStatus::Error)
+            },
+            OrderResponseStatus::Success => PlacedOrderOutcome::new(OrderOutcomeStatus::Success),
+        })
+        .collect()
+}
+
+/// Outcomes from a raw `/exchange` response. An action rejected as a whole
+/// is an `Err` with the exchange's message; an accepted action without
+/// per-order statuses (e.g. a leverage update) has no outcomes.
+fn outcomes_from_response(response: &serde_json::Value) -> Result<Vec<PlacedOrderOutcome>, String> {
+    match response.get("status").and_then(|s| s.as_str()) {
+        Some("ok") => {}
+        Some(_) => {
+            return Err(response
+                .get("response")
+                .and_then(|r| r.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| response.to_string()));
+        }
+        None => return Err(response.to_string()),
+    }
+    let Some(statuses) = response
+        .pointer("/response/data/statuses")
+        .and_then(|s| s.as_array())
+    else {
+        return Ok(Vec::new());
+    };
+    let dec = |v: &serde_json::Value, key: &str| v.get(key)?.as_str()?.parse::<Decimal>().ok();
+    let ids = |v: &serde_json::Value| {
+        (
+            v.get("oid").and_then(|o| o.as_u64()),
+            v.get("cloid").and_then(|c| c.as_str()).map(String::from),
+        )
+    };
+    Ok(statuses
+        .iter()
+        .map(|s| {
+            if let Some(filled) = s.get("filled") {
+                let (oid, cloid) = ids(filled);
+                PlacedOrderOutcome {
+                    oid,
+                    cloid,
+                    filled_size: dec(filled, "totalSz"),
+                    avg_price: dec(filled, "avgPx"),
+                    ..PlacedOrderOutcome::new(OrderOutcomeStatus::Filled)
+                }
+            } else if let Some(resting) = s.get("resting") {
+                let (oid, cloid) = ids(resting);
+                PlacedOrderOutcome {
+                    oid,
+                    cloid,
+                    ..PlacedOrderOutcome::new(OrderOutcomeStatus::Resting)
+                }
+            } else if let Some(error) = s.get("error") {
+                PlacedOrderOutcome {
+                    error: Some(error.as_str().unwrap_or("unknown").to_string()),
+                    ..PlacedOrderOutcome::new(OrderOutcomeStatus::Error)
+                }
+            } else {
+                PlacedOrderOutcome::new(OrderOutcomeStatus::Success)
+            }
+        })
+        .collect())
+}
+
+/// Outcomes of orders this server submitted, joined with the requests by
+/// position, which is how the exchange orders its statuses.
+fn placed_order_outcomes(
+    response: &serde_json::Value,
+    placed: &[(ResolvedMarket, OrderRequest)],
+) -> Result<Vec<PlacedOrderOutcome>, String> {
+    Ok(outcomes_from_response(response)?
+        .into_iter()
+        .zip(placed)
+        .map(|(outcome, (market, order))| outcome.with_order(market, order))
+        .collect())
+}
+
+/// The one-line status summary, e.g. "Filled 1 @ $3000 (order ID: 1); Resting (order ID: 2)".
+fn describe_outcomes(outcomes: &Result<Vec<PlacedOrderOutcome>, String>) -> String {
+    match outcomes {
+        Ok(outcomes) => outcomes
+            .iter()
+            .map(PlacedOrderOutcome::describe)
+            .collect::<Vec<_>>()
+            .join("; "),
+        Err(msg) => format!("Error: {msg}"),
+    }
+}
+
+fn format_order_response(statuses: &[OrderResponseStatus]) -> String {
+    describe_outcomes(&Ok(outcomes_from_statuses(statuses)))
+}
+
+/// An order's state from `orderStatus`, read from the raw response so the
+/// time in force (missing from the SDK's order type) is available.
+struct OrderSnapshot {
+    oid: u64,
+    status: String,
+    limit_px: Decimal,
+    sz: Decimal,
+    orig_sz: Decimal,
+    tif: Option<String>,
+}
+
+/// Look up an order by oid or by client order ID (as a hex string). `None`
+/// when the order is unknown or the lookup fails; callers only use it to
+/// report on an action that already went through.
+async fn fetch_order_snapshot(
+    state: &ServerState,
+    address: Address,
+    oid: serde_json::Value,
+) -> Option<OrderSnapshot> {
+    let body = state
+        .raw_info_request(serde_json::json!({
+            "type": "orderStatus",
+            "user": format!("{address:#x}"),
+            "oid": oid,
+        }))
+        .await
+        .inspect_err(|e| tracing::warn!(error = %e.message, "Order status lookup failed"))
+        .ok()?;
+    let update = body.get("order")?;
+    let order = update.get("order")?;
+    let dec = |key: &str| order.get(key)?.as_str()?.parse::<Decimal>().ok();
+    Some(OrderSnapshot {
+        oid: order.get("oid")?.as_u64()?,
+        status: update.get("status")?.as_str()?.to_string(),
+        limit_px: dec("limitPx")?,
+        sz: dec("sz")?,
+        orig_sz: dec("origSz")?,
+        tif: order.get("tif").and_then(|t| t.as_str()).map(String::from),
+    })
+}
+
+/// Table of the submitted orders that came back resting, so the caller sees
+/// exactly what is on the book without a follow-up status lookup. Empty when
+/// nothing rested.
+async fn resting_orders_table(state: &ServerState, outcomes: &[PlacedOrderOutcome]) -> String {
+    let resting: Vec<&PlacedOrderOutcome> = outcomes
+        .iter()
+        .filter(|o| o.status == OrderOutcomeStatus::Resting && o.coin.is_some())
+        .collect();
+    if resting.is_empty() {
+        return String::new();
+    }
+
+    let mut mids = state.cache.all_mids.borrow().clone();
+    if resting
+        .iter()
+        .any(|o| o.coin.as_ref().is_some_and(|c| !mids.contains_key(c)))
+    {
+        match state.api.all_mids().await {
+            Ok(fetched) => mids.extend(fetched),
+            Err(e) => tracing::warn!(error = %e, "Mid lookup for resting orders failed"),
+        }
+    }
+
+    let mut output = String::from("\n### Resting Orders\n\n");
+    output.push_str(
+        "| Order ID | Market | Side | Price | Size | TIF | Reduce-Only | Client ID | From Mid |\n",
+    );
+    output.push_str(
+        "|----------|--------|------|-------|------|-----|-------------|-----------|----------|\n",
+    );
+    let text = |v: &Option<String>| v.clone().unwrap_or_default();
+    let num = |v: Option<Decimal>| v.map(|d| d.to_string()).unwrap_or_default();
+    for o in resting {
+        let from_mid = o
+            .coin
+            .as_ref()
+            .and_then(|c| mids.get(c))
+            .filter(|mid| !mid.is_zero())
+            .zip(o.price)
+            .map(|(mid, px)| format!("{:+.1} bps", (px - mid) / mid * Decimal::from(10_000)))
+            .unwrap_or_else(|| "N/A".into());
+        output.push_str(&format!(
+            "| {} | {} | {} | ${} | {} | {} | {} | {} | {from_mid} |\n",
+            o.oid.unwrap_or(0),
+            text(&o.market),
+            match o.side {
+                Some("buy") => "Buy",
+                Some(_) => "Sell",
+                None => "",
+            },
+            num(o.price),
+            num(o.size),
+            text(&o.tif),
+            if o.reduce_only == Some(true) {
+                "Yes"
+            } else {
+                "No"
+            },
+            text(&o.cloid),
+        ));
+    }
+    output
+}
+
+fn format_exchange_response(response: &serde_json::Value) -> String {
+    match outcomes_from_response(response) {
+        Ok(outcomes) if outcomes.is_empty() => format!(
+            "OK: {}",
+            response
+                .pointer("/response/type")
+                .and_then(|t| t.as_str())
+                .unwrap_or("success")
+        ),
+        outcomes => describe_outcomes(&outcomes),
+    }
+}
+
+async fn maybe_append_builder_nudge(state: &ServerState, output: &mut String) {
+    if state.hide_builder_nudge
+        || state.builder_fee_approved.load(Ordering::Relaxed)
+        || state.nudge_shown.load(Ordering::Relaxed)
+    {
+        return;
+    }
+    // Shown by an earlier process this week; don't repeat it on every restart.
+    if config::nudge_shown_recently() {
+        state.nudge_shown.store(true, Ordering::Relaxed);
+        return;
+    }
+    // Approval may have happened since startup (e.g. from the web UI).
+    if state.check_and_cache_builder_approval().await {
+        return;
+    }
+    if !state.nudge_shown.swap(true, Ordering::Relaxed) {
+        output.push_str(&format!(
+            "\n\n---\n\
+             **Note:** Builder fees ({}) are not yet approved for this account. \
+             To support development, add `HYPERLIQUID_PRIVATE_KEY=0x...` to your \
+             `~/.config/hyperliquid-mcp/.env` file (temporarily remove `HYPERLIQUID_AGENT_PRIVATE_KEY`) \
+             and restart — the server will handle agent creation and fee approval automatically. \
+             Run `check_builder_fee` for details. \
+             Set `HYPERLIQUID_HIDE_BUILDER_NUDGE=true` to stop seeing this note.",
+            config::fee_to_percent(state.active_builder().fee),
+        ));
+        if let Err(e) = config::record_nudge_shown() {
+            tracing::warn!(error = %e, "Failed to record builder fee nudge");
+        }
+    }
+}
+
+fn parse_tif(s: &str) -> TimeInForce {
+    match s.to_lowercase().as_str() {
+        "gtc" => TimeInForce::Gtc,
+        "ioc" => TimeInForce::Ioc,
+        "alo" | "post_only" | "post-only" => TimeInForce::Alo,
+        _ => TimeInForce::Gtc,
+    }
+}
+
+fn to_decimal(f: f64) -> Result<Decimal, ErrorData> {
+    Decimal::try_from(f).map_err(|e| mcp_err(&format!("Invalid decimal value: {e}")))
+}
+
+/// Round a price to 5 significant figures. Sub-cent prices keep their
+/// digits rather than rounding to zero.
+fn round_price_5sf(price: Decimal) -> Decimal {
+    round_sig(price, 5)
+}
+
+/// How urgently `place_order` should fill, for callers that would rather
+/// not pick an order type and time in force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Urgency {
+    /// Only as maker: post-only, rejected rather than crossing.
+    Passive,
+    /// A resting GTC limit.
+    Normal,
+    /// Fill now: a market order.
+    Immediate,
+}
+
+impl Urgency {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_lowercase().as_str() {
+            "passive" | "maker" => Ok(Urgency::Passive),
+            "normal" => Ok(Urgency::Normal),
+            "immediate" | "urgent" | "taker" => Ok(Urgency::Immediate),
+            other => Err(format!(
+                "Invalid urgency '{other}'. Use \"passive\", \"normal\" or \"immediate\"."
+            )),
+        }
+    }
+
+    /// What the urgency turned into and why, for the tool result.
+    fn explain(self) -> String {
+        let why = match self {
+            Urgency::Passive => {
+                "a post-only (ALO) limit, so it only ever rests as maker; \
+                 the exchange rejects it instead of letting it cross the spread"
+            }
+            Urgency::Normal => {
+                "a GTC limit, which fills at the price or better and rests until filled or cancelled"
+            }
+            Urgency::Immediate => {
+                "a market order (an IOC limit priced off the book), which fills now \
+                 and cancels anything left over"
+            }
+        };
+        format!("Urgency {}: {why}.\n\n", format!("{self:?}").to_lowercase())
+    }
+}
+
+/// The most aggressive price that still rests as maker: one tick below the
+/// best ask for a buy, one tick above the best bid for a sell. On a
+/// one-tick spread that joins the best bid or ask.
+async fn passive_price(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    is_buy: bool,
+) -> Result<Result<(Decimal, String), String>, ErrorData> {
+    let (name, touch) = if is_buy {
+        reference_price(state, market, "best_ask").await?
+    } else {
+        reference_price(state, market, "best_bid").await?
+    };
+    let tick = market.price_tick(touch);
+    let price = if is_buy { touch - tick } else { touch + tick };
+    if price <= Decimal::ZERO {
+        return Ok(Err(format!(
+            "The {name} ${touch} is a single tick; there is no maker price below it."
+        )));
+    }
+    let side = if is_buy { "below" } else { "above" };
+    Ok(market
+        .round_price(price, is_buy)
+        .map(|price| (price, format!("one tick {side} the {name} ${touch}"))))
+}
+
+/// The range the exchange accepts limit prices in: within
+/// `price_band_pct` of a reference price.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PriceBand {
+    reference_name: &'static str,
+    reference: Decimal,
+    pct: Decimal,
+}
+
+impl PriceBand {
+    pub(crate) fn new(
+        state: &ServerState,
+        reference_name: &'static str,
+        reference: Decimal,
+    ) -> Self {
+        PriceBand {
+            reference_name,
+            reference,
+            pct: state.price_band_pct,
+        }
+    }
+
+    /// Lowest and highest accepted prices, rounded inwards to the market's
+    /// precision.
+    fn bounds(&self, market: &ResolvedMarket) -> (Decimal, Decimal) {
+        let width = self.reference * self.pct / Decimal::ONE_HUNDRED;
+        let low = self.reference - width;
+        let high = self.reference + width;
+        (
+            market.round_price(low, false).unwrap_or(low),
+            market.round_price(high, true).unwrap_or(high),
+        )
+    }
+
+    /// Why a limit price of `px` would be rejected, if it's outside the band.
+    fn refusal(&self, market: &ResolvedMarket, px: Decimal) -> Option<String> {
+        if self.reference.is_zero() {
+            return None;
+        }
+        let (low, high) = self.bounds(market);
+        (px < low || px > high).then(|| {
+            format!(
+                "{} orders must be priced between {} and {} right now (within {}% of the {} \
+                 price {}); the exchange rejects limit price {} as out of band.",
+                market.symbol,
+                fmt_price(low),
+                fmt_price(high),
+                self.pct.normalize(),
+                self.reference_name,
+                fmt_price(self.reference),
+                fmt_price(px),
+            )
+        })
+    }
+}
+
+/// The band around the mark price, or around the mid when no mark is known.
+/// None when neither is available, in which case the exchange has the final
+/// word.
+async fn price_band(state: &ServerState, market: &ResolvedMarket) -> Option<PriceBand> {
+    for reference in ["mark", "mid"] {
+        if let Ok((name, px)) = reference_price(state, market, reference).await {
+            return Some(PriceBand::new(state, name, px));
+        }
+    }
+    None
+}
+
+/// Fit an order to the exchange's constraints before it is signed. Prices
+/// (and trigger prices) are rounded with `ResolvedMarket::round_price` and
+/// the size is truncated to the market's size decimals. An order that still
+/// can't be accepted is an error: a size below one step, a value under the
+/// $10 minimum (reduce-only orders are exempt, so small positions can be
+/// closed), or a limit price outside `band`, when known.
+/// Every code path that builds an `OrderRequest` goes through here. Returns
+/// a note describing any rounding, empty when nothing changed.
+pub(crate) fn normalize_order(
+    market: &ResolvedMarket,
+    order: &mut OrderRequest,
+    band: Option<PriceBand>,
+) -> Result<String, String> {
+    let mut changes = Vec::new();
+
+    let limit_px = market.round_price(order.limit_px, order.is_buy)?;
+    if limit_px != order.limit_px {
+        changes.push(format!("price {} → {limit_px}", order.limit_px.normalize()));
+        order.limit_px = limit_px;
+    }
+    if let OrderTypePlacement::Trigger { trigger_px, .. } = &mut order.order_type {
+        let rounded = market.round_price(*trigger_px, order.is_buy)?;
+        if rounded != *trigger_px {
+            changes.push(format!(
+                "trigger price {} → {rounded}",
+                trigger_px.normalize()
+            ));
+            *trigger_px = rounded;
+        }
+    }
+
+    let sz = order
+        .sz
+        .round_dp_with_strategy(market.sz_decimals, rust_decimal::RoundingStrategy::ToZero)
+        .normalize();
+    if sz <= Decimal::ZERO {
+        return Err(format!(
+            "Size {} is below the {} size step of 1e-{}.",
+            order.sz.normalize(),
+            market.symbol,
+            market.sz_decimals
+        ));
+    }
+    if sz != order.sz {
+        changes.push(format!(
+            "size {} → {sz} ({} decimals)",
+            order.sz.normalize(),
+            market.sz_decimals
+        ));
+        order.sz = sz;
+    }
+
+    let value = order.sz * order.limit_px;
+    if !order.reduce_only && value < MIN_ORDER_VALUE_USD {
+        return Err(format!(
+            "Order value ${value:.2} ({} {} @ ${}) is below the ${MIN_ORDER_VALUE_USD} minimum.",
+            order.sz, market.symbol, order.limit_px
+        ));
+    }
+
+    if let Some(refusal) = band.and_then(|b| b.refusal(market, order.limit_px)) {
+        return Err(refusal);
+    }
+
+    Ok(if changes.is_empty() {
+        String::new()
+    } else {
+        format!("Rounded to exchange precision: {}.\n\n", changes.join(", "))
+    })
+}
+
+const MAX_BATCH_ORDERS: usize = 20;
+/// Hourly candles behind a pair trade's beta: 7 days.
+const PAIR_BETA_CANDLES: u64 = 168;
+/// A pair trade warns when its legs' filled notionals differ by more than
+/// this fraction.
+const PAIR_IMBALANCE_WARN: Decimal = Decimal::from_parts(10, 0, 0, false, 2);
+/// Market trigger legs are sent with a limit this far beyond the trigger price.
+const TRIGGER_MARKET_SLIPPAGE: Decimal = Decimal::from_parts(10, 0, 0, false, 2);
+/// Orders closing several positions at once are limited this far beyond the
+/// mid.
+pub(crate) const CLOSE_ALL_SLIPPAGE: Decimal = Decimal::from_parts(5, 0, 0, false, 2);
+/// `close_positions` sends a close at most this many times per position.
+const MAX_CLOSE_ATTEMPTS: usize = 3;
+/// Pause after a round of closes before re-reading positions.
+const CLOSE_SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn parse_grouping(s: &str) -> Option<OrderGrouping> {
+    match s.trim().to_lowercase().replace('-', "_").as_str() {
+        "na" | "none" => Some(OrderGrouping::Na),
+        "normal_tpsl" | "normaltpsl" => Some(OrderGrouping::NormalTpsl),
+        "position_tpsl" | "positiontpsl" => Some(OrderGrouping::PositionTpsl),
+        _ => None,
+    }
+}
+
+/// Check a batch against the shapes the exchange accepts for its grouping:
+/// `normalTpsl` is one entry order followed by at most one TP and one SL on
+/// the same asset and opposite side, `positionTpsl` is only TP/SL legs for a
+/// single asset. Trigger legs in either TP/SL grouping must be reduce-only.
+fn validate_grouping(grouping: &OrderGrouping, orders: &[OrderRequest]) -> Result<(), String> {
+    let tpsl = |o: &OrderRequest| match o.order_type {
+        OrderTypePlacement::Trigger { tpsl, .. } => Some(tpsl),
+        OrderTypePlacement::Limit { .. } => None,
+    };
+    let check_legs = |legs: &[OrderRequest], grouping: &str| -> Result<(), String> {
+        if legs.is_empty() || legs.len() > 2 {
+            return Err(format!(
+                "{grouping}: expected one or two TP/SL legs, got {}.",
+                legs.len()
+            ));
+        }
+        if legs.iter().any(|o| tpsl(o).is_none()) {
+            return Err(format!(
+                "{grouping}: every TP/SL leg needs trigger_price and tpsl."
+            ));
+        }
+        if legs.len() == 2 && tpsl(&legs[0]) == tpsl(&legs[1]) {
+            return Err(format!(
+                "{grouping}: at most one \"tp\" and one \"sl\" leg."
+            ));
+        }
+        if legs
+            .iter()
+            .any(|o| o.asset != legs[0].asset || o.is_buy != legs[0].is_buy)
+        {
+            return Err(format!(
+                "{grouping}: TP/SL legs must be on the same market and side."
+            ));
+        }
+        Ok(())
+    };
+
+    match grouping {
+        OrderGrouping::Na => Ok(()),
+        OrderGrouping::NormalTpsl => {
+            let (entry, legs) = orders
+                .split_first()
+                .ok_or_else(|| "normal_tpsl needs an entry order.".to_string())?;
+            if tpsl(entry).is_some() {
+                return Err("normal_tpsl: the first order must be the non-trigger entry order, followed by its TP/SL legs.".into());
+            }
+            check_legs(legs, "normal_tpsl")?;
+            if legs[0].asset != entry.asset {
+                return Err(
+                    "normal_tpsl: TP/SL legs must be on the same market as the entry.".into(),
+                );
+            }
+            if legs[0].is_buy == entry.is_buy {
+                return Err(
+                    "normal_tpsl: TP/SL legs must be on the opposite side of the entry.".into(),
+                );
+            }
+            Ok(())
+        }
+        OrderGrouping::PositionTpsl => check_legs(orders, "position_tpsl"),
+    }
+}
+
+/// Fingerprint of the order parameters that matter for duplicate detection.
+/// Decimals are normalized so `0.1` and `0.10` hash the same.
+fn order_fingerprint(
+    coin: &str,
+    is_buy: bool,
+    size: Decimal,
+    price: Option<Decimal>,
+    tif: TimeInForce,
+    reduce_only: bool,
+    vault: Option<Address>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    coin.to_uppercase().hash(&mut hasher);
+    is_buy.hash(&mut hasher);
+    size.normalize().to_string().hash(&mut hasher);
+    price.map(|p| p.normalize().to_string()).hash(&mut hasher);
+    format!("{tif:?}").hash(&mut hasher);
+    reduce_only.hash(&mut hasher);
+    vault.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Limit price and fill estimate for a market (IOC) order.
+struct MarketPricing {
+    limit_px: Decimal,
+    mid: Decimal,
+    /// Average fill price walking the book, and whether the book was deep
+    /// enough for the full size. `None` when priced from the mid alone.
+    estimate: Option<(Decimal, bool)>,
+    /// Data-quality warnings about the book the estimate came from.
+    issues: String,
+}
+
+impl MarketPricing {
+    fn describe(&self) -> String {
+        let estimate = match self.estimate {
+            Some((avg, complete)) => {
+                let slippage = if self.mid.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    ((avg - self.mid) / self.mid * Decimal::ONE_HUNDRED).abs()
+                };
+                let mut line = format!(
+                    "Estimated fill: ${} avg (slippage {:.3}% vs mid ${}), limit ${}\n",
+                    round_price_5sf(avg),
+                    slippage,
+                    self.mid,
+                    self.limit_px
+                );
+                if !complete {
+                    line.push_str("Book depth is below the order size; expect a partial fill.\n");
+                }
+                line
+            }
+            None => format!(
+                "Priced from mid ${} ± 5% (order book unavailable), limit ${}\n",
+                self.mid, self.limit_px
+            ),
+        };
+        format!("{}{estimate}", self.issues)
+    }
+}
+
+/// Price a market order by walking the L2 book for `size` and setting the
+/// limit `market_buffer_bps` beyond the last level needed. Falls back to mid
+/// ± 5% when the book is unavailable.
+async fn market_pricing(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    is_buy: bool,
+    size: Decimal,
+) -> Result<MarketPricing, ErrorData> {
+    let coin = market.coin.as_str();
+    let book = fetch_l2_book(state, coin)
+        .await
+        .inspect_err(
+            |e| tracing::warn!(coin, error = %e.message, "Failed to fetch book for market order"),
+        )
+        .ok();
+    let mut issues = String::new();
+
+    // A crossed or one-sided book can't be walked; price off the mid instead.
+    if let Some(book) = book {
+        issues = book.warning();
+        let walkable =
+            !book.bids.is_empty() && !book.asks.is_empty() && book.bids[0].0 < book.asks[0].0;
+        if walkable {
+            let (bids, asks) = (book.bids, book.asks);
+            let mid = (bids[0].0 + asks[0].0) / Decimal::TWO;
+            let levels = if is_buy { &asks } else { &bids };
+
+            let mut remaining = size;
+            let mut notional = Decimal::ZERO;
+            let mut worst_px = levels[0].0;
+            for (px, sz, _) in levels {
+                if remaining.is_zero() {
+                    break;
+                }
+                let take = remaining.min(*sz);
+                notional += take * px;
+                remaining -= take;
+                worst_px = *px;
+            }
+            let filled = size - remaining;
+            let avg = if filled.is_zero() {
+                worst_px
+            } else {
+                notional / filled
+            };
+
+            let buffer = Decimal::from(state.market_buffer_bps) / Decimal::from(10_000);
+            let limit_px = if is_buy {
+                worst_px * (Decimal::ONE + buffer)
+            } else {
+                worst_px * (Decimal::ONE - buffer)
+            };
+
+            // Round away from the book so the buffer is never rounded off.
+            return Ok(MarketPricing {
+                limit_px: market
+                    .round_price(limit_px, !is_buy)
+                    .map_err(|e| mcp_err(&e))?,
+                mid,
+                estimate: Some((avg, remaining.is_zero())),
+                issues,
+            });
+        }
+    }
+
+    let mids = state
+        .api
+        .all_mids()
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch prices for market order: {e}")))?;
+    let mid = *mids
+        .get(coin)
+        .ok_or_else(|| mcp_err(&format!("No mid price available for {coin}")))?;
+
+    // 0.05 = 5%
+    let slippage = Decimal::new(5, 2);
+    let limit_px = if is_buy {
+        mid * (Decimal::ONE + slippage)
+    } else {
+        mid * (Decimal::ONE - slippage)
+    };
+
+    Ok(MarketPricing {
+        limit_px: market
+            .round_price(limit_px, !is_buy)
+            .map_err(|e| mcp_err(&e))?,
+        mid,
+        estimate: None,
+        issues,
+    })
+}
+
+/// Refusal message for a market order whose estimated fill strays from mid by
+/// more than `impact_guard_multiple` times the impact spread, the gap between
+/// the `impactPxs` a standard impact notional would fill at on each side.
+/// Markets without impact prices (spot) are not guarded.
+async fn impact_guard(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    pricing: &MarketPricing,
+) -> Result<Option<String>, ErrorData> {
+    let Some((avg, complete)) = pricing.estimate else {
+        return Ok(None);
+    };
+    let impact = asset_ctx(state, market)
+        .await?
+        .and_then(|(_, ctx)| ctx.impact_pxs);
+    let Some((impact_bid, impact_ask)) = impact else {
+        return Ok(None);
+    };
+    let impact_spread = impact_ask - impact_bid;
+    if impact_spread <= Decimal::ZERO || pricing.mid.is_zero() {
+        return Ok(None);
+    }
+
+    let bps = |v: Decimal| v / pricing.mid * Decimal::from(10_000);
+    let suggestion = "Split it into smaller clips spread over time (a TWAP), or pass \
+                      force: true to send it anyway.";
+    if !complete {
+        return Ok(Some(format!(
+            "Refused: the visible {} book is shallower than the order size, so a market order \
+             would sweep every level. {suggestion}",
+            market.symbol
+        )));
+    }
+    let deviation = (avg - pricing.mid).abs();
+    if deviation <= impact_spread * state.impact_guard_multiple {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "Refused: the estimated fill ${} is {:.1} bps from mid ${}, more than {}× the {} impact \
+         spread of {:.1} bps (HYPERLIQUID_IMPACT_GUARD_MULTIPLE). The order would move the market \
+         well beyond normal size. {suggestion}",
+        round_price_5sf(avg),
+        bps(deviation),
+        pricing.mid,
+        state.impact_guard_multiple.normalize(),
+        market.symbol,
+        bps(impact_spread),
+    )))
+}
+
+/// Fill a dry-run perp order against the paper account, as a taker
+/// `paper_slippage_bps` through the live mid. Limit orders fill only when
+/// they cross the mid, at no worse than their limit; orders that would rest
+/// are not simulated. Returns a note for the dry-run result, empty outside
+/// dry-run mode.
+async fn paper_fill(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    is_buy: bool,
+    size: Decimal,
+    reduce_only: bool,
+    limit_px: Option<Decimal>,
+) -> Result<String, ErrorData> {
+    let Some(paper) = &state.paper else {
+        return Ok(String::new());
+    };
+    if market.kind != MarketKind::Perp {
+        return Ok("\n\nPaper account: spot orders are not simulated.".to_string());
+    }
+    let (_, mid) = reference_price(state, market, "mid").await?;
+    let slippage = Decimal::from(state.paper_slippage_bps) / Decimal::from(10_000);
+    let taker_px = if is_buy {
+        mid * (Decimal::ONE + slippage)
+    } else {
+        mid * (Decimal::ONE - slippage)
+    };
+    let px = match limit_px {
+        None => taker_px,
+        Some(limit) if is_buy && limit >= mid => limit.min(taker_px),
+        Some(limit) if !is_buy && limit <= mid => limit.max(taker_px),
+        Some(_) => {
+            return Ok(format!(
+                "\n\nPaper account: the limit does not cross the mid {}, so nothing filled. \
+                 Resting orders are not simulated.",
+                fmt_price(mid)
+            ));
+        }
+    };
+
+    let mut paper = paper.lock().unwrap_or_else(|e| e.into_inner());
+    let position = paper.position(&market.coin);
+    let size = if reduce_only {
+        let reducible = if position.is_sign_negative() == is_buy {
+            position.abs()
+        } else {
+            Decimal::ZERO
+        };
+        if reducible.is_zero() {
+            return Ok(format!(
+                "\n\nPaper account: reduce-only, but there is no paper {} position to reduce; nothing filled.",
+                market.symbol
+            ));
+        }
+        size.min(reducible)
+    } else {
+        size
+    };
+    let fill = paper.fill(&market.coin, is_buy, size, px);
+    let position = paper.position(&market.coin);
+    Ok(format!(
+        "\n\n**Paper fill:** {} {} {} @ {} (mid {}, fee {}{}). Paper position: {}; balance {}.",
+        if is_buy { "bought" } else { "sold" },
+        fmt_qty(fill.sz),
+        market.symbol,
+        fmt_price(round_sig(fill.px, 8)),
+        fmt_price(mid),
+        fmt_usd(fill.fee),
+        if fill.closed_pnl.is_zero() {
+            String::new()
+        } else {
+            format!(", realized {}", fmt_usd(fill.closed_pnl))
+        },
+        if position.is_zero() {
+            "flat".to_string()
+        } else {
+            fmt_qty(position)
+        },
+        fmt_usd(paper.balance),
+    ))
+}
+
+pub async fn reset_paper_account(
+    state: &ServerState,
+    req: ResetPaperAccountRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let Some(paper) = &state.paper else {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "The paper account only exists in dry-run mode. Restart with --dry-run to paper trade.",
+        )]));
+    };
+    let starting = match req.starting_balance {
+        Some(balance) if balance > 0.0 => to_decimal(balance)?,
+        Some(_) => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "starting_balance must be positive.",
+            )]));
+        }
+        None => {
+            paper
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .starting_balance
+        }
+    };
+    let previous = std::mem::replace(
+        &mut *paper.lock().unwrap_or_else(|e| e.into_inner()),
+        PaperAccount::new(starting),
+    );
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Paper account reset to {} USDC with no positions. The previous run had {} fills, \
+         {} realized PnL and {} in fees since {}.",
+        fmt_usd(starting),
+        previous.fills.len(),
+        fmt_usd(previous.realized_pnl),
+        fmt_usd(previous.fees_paid),
+        fmt_instant(previous.started_at),
+    ))]))
+}
+
+/// Current price for a `price_reference`, with the reference's display name.
+async fn reference_price(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    reference: &str,
+) -> Result<(&'static str, Decimal), ErrorData> {
+    let unavailable =
+        |name: &str| mcp_err(&format!("No {name} available for {}", market.describe()));
+    match reference.trim().to_lowercase().as_str() {
+        "mid" => {
+            let ws_mid = state.cache.all_mids.borrow().get(&market.coin).copied();
+            let mid = match ws_mid {
+                Some(mid) => mid,
+                None => *state
+                    .api
+                    .all_mids()
+                    .await
+                    .map_err(|e| mcp_err(&format!("Failed to fetch mids: {e}")))?
+                    .get(&market.coin)
+                    .ok_or_else(|| unavailable("mid price"))?,
+            };
+            Ok(("mid", mid))
+        }
+        side @ ("best_bid" | "bid" | "best_ask" | "ask") => {
+            let is_bid = side.ends_with("bid");
+            let book = fetch_l2_book(state, &market.coin).await?;
+            // Don't anchor a limit price to a book that failed validation.
+            if let Some(issue) = book.issues.first() {
+                return Err(mcp_err(&format!(
+                    "Can't price from the {} book: {issue} Use an explicit price or \"mid\".",
+                    market.describe()
+                )));
+            }
+            let px = if is_bid {
+                book.bids.first()
+            } else {
+                book.asks.first()
+            }
+            .map(|l| l.0);
+            if is_bid {
+                Ok(("best bid", px.ok_or_else(|| unavailable("best bid"))?))
+            } else {
+                Ok(("best ask", px.ok_or_else(|| unavailable("best ask"))?))
+            }
+        }
+        "mark" => {
+            let mark = asset_ctx(state, market)
+                .await?
+                .and_then(|(_, ctx)| ctx.mark_px)
+                .ok_or_else(|| unavailable("mark price"))?;
+            Ok(("mark", mark))
+        }
+        other => Err(mcp_err(&format!(
+            "Invalid price_reference '{other}'. Use \"mid\", \"best_bid\", \"best_ask\" or \"mark\"."
+        ))),
+    }
+}
+
+/// "2.0 ETH long", "0.5 ETH short" or "flat".
+fn describe_position(szi: Decimal, symbol: &str) -> String {
+    if szi.is_zero() {
+        return "flat".into();
+    }
+    let side = if szi > Decimal::ZERO { "long" } else { "short" };
+    format!("{} {symbol} {side}", szi.abs().normalize())
+}
+
+/// Warning for a non-reduce-only perp order that grows the position past
+/// `multiple` times its current size, or trades against it.
+fn exposure_warning(
+    szi: Decimal,
+    size: Decimal,
+    is_buy: bool,
+    symbol: &str,
+    multiple: Decimal,
+) -> Option<String> {
+    if szi.is_zero() {
+        return None;
+    }
+    let side = if is_buy { "buy" } else { "sell" };
+    let projected = if is_buy { szi + size } else { szi - size };
+    let current = describe_position(szi, symbol);
+    let after = describe_position(projected, symbol);
+
+    let adds = (szi > Decimal::ZERO) == is_buy;
+    let message = if adds {
+        if projected.abs() <= szi.abs() * multiple {
+            return None;
+        }
+        format!("this {side} adds to your existing {current} → {after}")
+    } else if projected.is_zero() {
+        format!("this {side} closes your existing {current}")
+    } else if (projected > Decimal::ZERO) != (szi > Decimal::ZERO) {
+        format!("this {side} flips your existing {current} → {after}")
+    } else {
+        format!(
+            "this {side} trades against your existing {current} → {after}. \
+             Pass reduce_only: true if it is meant to reduce the position"
+        )
+    };
+    Some(format!("Warning: {message}.\n\n"))
+}
+
+/// Warn once resting orders reach this share of the account-wide limit.
+const OPEN_ORDER_WARN_RATIO: f64 = 0.9;
+
+/// Refuse placements that would push resting orders past the configured open
+/// order limits, and return a warning when they get close. `new_orders` counts
+/// the resting orders about to be added per coin.
+async fn check_open_order_limits(
+    state: &ServerState,
+    address: Address,
+    new_orders: &HashMap<String, usize>,
+) -> Result<String, ErrorData> {
+    let added: usize = new_orders.values().sum();
+    if added == 0 {
+        return Ok(String::new());
+    }
+    let open = account::get_cached_open_orders(state, address).await?;
+
+    let total = open.len() + added;
+    if total > state.max_open_orders {
+        return Err(mcp_err(&format!(
+            "Placing {added} more resting order(s) would bring the account to {total} open orders, \
+             above the limit of {}. Cancel some orders first (limit set by HYPERLIQUID_MAX_OPEN_ORDERS).",
+            state.max_open_orders
+        )));
+    }
+    if let Some(limit) = state.max_open_orders_per_coin {
+        for (coin, added) in new_orders {
+            let count = open.iter().filter(|o| &o.coin == coin).count() + added;
+            if count > limit {
+                return Err(mcp_err(&format!(
+                    "Placing {added} more resting order(s) on {coin} would bring it to {count} open orders, \
+                     above the per-coin limit of {limit} (HYPERLIQUID_MAX_OPEN_ORDERS_PER_COIN)."
+                )));
+            }
+        }
+    }
+
+    if total as f64 >= state.max_open_orders as f64 * OPEN_ORDER_WARN_RATIO {
+        Ok(format!(
+            "Warning: {total} of {} open orders account-wide after this placement.\n\n",
+            state.max_open_orders
+        ))
+    } else {
+        Ok(String::new())
+    }
+}
+
+/// Refusal for a non-reduce-only perp order whose fill would take the
+/// position's notional, valued at `px`, past its configured limit. `szi` is
+/// the position before this order. Orders that shrink the position are
+/// allowed even when it is already over the limit.
+fn position_limit_refusal(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    szi: Decimal,
+    size: Decimal,
+    is_buy: bool,
+    px: Decimal,
+) -> Option<String> {
+    let limit = state.position_notional_limit(&market.coin)?;
+    let projected = if is_buy { szi + size } else { szi - size };
+    let projected_notional = projected.abs() * px;
+    if projected_notional <= limit || projected.abs() <= szi.abs() || px.is_zero() {
+        return None;
+    }
+
+    let max_units = limit / px;
+    let headroom = if is_buy {
+        max_units - szi
+    } else {
+        szi + max_units
+    }
+    .max(Decimal::ZERO)
+    .round_dp_with_strategy(market.sz_decimals, rust_decimal::RoundingStrategy::ToZero);
+    let key = config::notional_limit_key(&market.coin);
+    let source = if state.max_position_notional_by_coin.contains_key(&key) {
+        format!("{}_{key}", config::MAX_POSITION_NOTIONAL_VAR)
+    } else {
+        config::MAX_POSITION_NOTIONAL_VAR.to_string()
+    };
+    let side = if is_buy { "buy" } else { "sell" };
+    Some(format!(
+        "Refused: this {side} of {size} {symbol} would take the position to {} notional \
+         ({projected} {symbol} at ${px}), above the {} limit set by {source}.\n\n\
+         Current exposure: {} ({szi} {symbol}).\n\
+         Maximum additional {side} size: {headroom} {symbol}.",
+        fmt_usd(projected_notional),
+        fmt_usd(limit),
+        fmt_usd(szi.abs() * px),
+        symbol = market.symbol,
+    ))
+}
+
+/// Warning for a perp order whose fill would take the position, valued at
+/// `px`, into a margin tier allowing less than the leverage in use
+/// (`leverage`, or the headline maximum with no position). `szi` is the
+/// position before this order.
+fn margin_tier_warning(
+    table: &MarginTable,
+    symbol: &str,
+    szi: Decimal,
+    leverage: Option<u32>,
+    size: Decimal,
+    is_buy: bool,
+    px: Decimal,
+) -> Option<String> {
+    let projected = if is_buy { szi + size } else { szi - size };
+    if projected.abs() <= szi.abs() {
+        return None;
+    }
+    let notional = projected.abs() * px;
+    let tier = table.tier_at(notional);
+    let headline = table.tiers.first()?.max_leverage;
+    let used = leverage.unwrap_or(headline);
+    if used <= tier.max_leverage {
+        return None;
+    }
+    let setting = match leverage {
+        Some(leverage) => format!("the position's {leverage}x"),
+        None => format!("the headline {headline}x"),
+    };
+    Some(format!(
+        "Warning: this order takes the {symbol} position to {} notional, in the margin tier \
+         from {} where leverage is capped at {}x, below {setting}. The exchange may reject it; \
+         lower leverage with set_leverage first or size down (see get_margin_tiers).\n\n",
+        fmt_usd(notional),
+        fmt_price(tier.lower_bound),
+        tier.max_leverage,
+    ))
+}
+
+/// Cancel the orphaned trigger orders on `coin`, returning their IDs. Run
+/// by the orphan watch after a fill closes a position there.
+pub(crate) async fn cancel_orphaned_triggers(
+    state: &ServerState,
+    coin: &str,
+) -> Result<Vec<u64>, ErrorData> {
+    let address = state.query_address()?;
+    state.cache.invalidate_user_data().await;
+    let (user_state, orders) = tokio::try_join!(
+        account::get_cached_clearinghouse(state, address),
+        account::get_cached_open_orders(state, address),
+    )?;
+    let cancels: Vec<Cancel> =
+        account::orphaned_trigger_orders(&orders, &user_state.asset_positions)
+            .into_iter()
+            .filter(|o| o.coin == coin)
+            .filter_map(|o| {
+                Some(Cancel {
+                    asset: *state.asset_map.get(&o.coin)?,
+                    oid: o.oid,
+                })
+            })
+            .collect();
+    if cancels.is_empty() {
+        return Ok(Vec::new());
+    }
+    let oids: Vec<u64> = cancels.iter().map(|c| c.oid).collect();
+    if state.dry_run {
+        tracing::info!(coin, ?oids, "Dry run: would cancel orphaned trigger orders");
+        return Ok(Vec::new());
+    }
+
+    let signer = state.require_signer()?;
+    let nonce = state.next_nonce();
+    state
+        .client
+        .cancel(
+            signer.as_ref(),
+            BatchCancel { cancels },
+            nonce,
+            state.vault_addr(),
+            None,
+        )
+        .await
+        .map_err(|e| state.action_failed(nonce, "Orphaned trigger cancel failed", e))?;
+    state.cache.invalidate_user_data().await;
+    Ok(oids)
+}
+
+/// Hyperliquid rejects orders worth less than this many USD.
+const MIN_ORDER_VALUE_USD: Decimal = Decimal::TEN;
+
+/// One pre-flight check on a spot order. `Ok` describes what passed, `Err`
+/// the concrete fix.
+struct SpotCheck {
+    name: &'static str,
+    outcome: Result<String, String>,
+}
+
+fn check_spot_size(market: &ResolvedMarket, size: Decimal) -> Result<String, String> {
+    if size <= Decimal::ZERO {
+        return Err("Use a positive size.".to_string());
+    }
+    let decimals = market.sz_decimals;
+    if size.normalize().scale() > decimals {
+        let rounded = size.round_dp_with_strategy(decimals, rust_decimal::RoundingStrategy::ToZero);
+        return Err(format!(
+            "{} trades in steps of 1e-{decimals}; use a size of {rounded}.",
+            market.symbol
+        ));
+    }
+    Ok(format!("{size} fits the {decimals}-decimal size step"))
+}
+
+fn check_spot_min_value(size: Decimal, px: Decimal) -> Result<String, String> {
+    let value = size * px;
+    if value < MIN_ORDER_VALUE_USD {
+        let min_size = (MIN_ORDER_VALUE_USD / px)
+            .round_dp_with_strategy(8, rust_decimal::RoundingStrategy::AwayFromZero);
+        return Err(format!(
+            "Order value ${value:.2} is below the ${MIN_ORDER_VALUE_USD} minimum; \
+             use a size of at least {min_size}."
+        ));
+    }
+    Ok(format!(
+        "${value:.2} meets the ${MIN_ORDER_VALUE_USD} minimum"
+    ))
+}
+
+/// Buys spend the quote token at `px`, sells spend the base token. USDC
+/// sitting on the perp side is pointed out, since it can't be spent on spot.
+fn check_spot_balance(
+    balances: &[UserBalance],
+    perp_withdrawable: Decimal,
+    market: &ResolvedMarket,
+    is_buy: bool,
+    size: Decimal,
+    px: Decimal,
+) -> Result<String, String> {
+    let (base, quote) = market
+        .symbol
+        .split_once('/')
+        .unwrap_or((market.symbol.as_str(), "USDC"));
+    let (token, needed) = if is_buy {
+        (quote, size * px)
+    } else {
+        (base, size)
+    };
+    let balance = balances.iter().find(|b| b.coin == token);
+    let available = balance.map(|b| b.available()).unwrap_or_default();
+    if available >= needed {
+        return Ok(format!("{available} {token} available, {needed} needed"));
+    }
+
+    let shortfall = needed - available;
+    let held = balance.map(|b| b.hold).unwrap_or_default();
+    let fix = if token == "USDC" && perp_withdrawable >= shortfall {
+        format!(
+            "Move at least {shortfall:.2} USDC from perps with transfer_between_spot_perps \
+             (direction: \"to_spot\"); {perp_withdrawable:.2} USDC is withdrawable there."
+        )
+    } else if held >= shortfall {
+        format!("{held} {token} is locked in open orders; cancel them to free it.")
+    } else if is_buy {
+        format!("Deposit or acquire {shortfall} more {token}.")
+    } else {
+        format!("Reduce the size to {available} or acquire {shortfall} more {token}.")
+    };
+    Err(format!(
+        "Only {available} {token} available on spot, {needed} needed. {fix}"
+    ))
+}
+
+/// Run every spot pre-flight check for an order of `size` at `px`. Shared by
+/// `place_order` and `check_spot_readiness` so the two never disagree.
+async fn spot_order_checks(
+    state: &ServerState,
+    address: Address,
+    market: &ResolvedMarket,
+    is_buy: bool,
+    size: Decimal,
+    px: Decimal,
+) -> Result<Vec<SpotCheck>, ErrorData> {
+    let balances = state
+        .api
+        .user_balances(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch spot balances: {e}")))?;
+    let perp_withdrawable = account::get_cached_clearinghouse(state, address)
+        .await?
+        .withdrawable;
+    Ok(vec![
+        SpotCheck {
+            name: "Size step",
+            outcome: check_spot_size(market, size),
+        },
+        SpotCheck {
+            name: "Minimum value",
+            outcome: check_spot_min_value(size, px),
+        },
+        SpotCheck {
+            name: "Balance",
+            outcome: check_spot_balance(&balances, perp_withdrawable, market, is_buy, size, px),
+        },
+    ])
+}
+
+/// Size that `usd` of quote buys at `px`, rounded down to the size step.
+fn size_for_notional(market: &ResolvedMarket, usd: Decimal, px: Decimal) -> Decimal {
+    if px <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+    (usd / px).round_dp_with_strategy(market.sz_decimals, rust_decimal::RoundingStrategy::ToZero)
+}
+
+/// Give each order a fresh cloid from the registry, one intent per order.
+/// Orders are built with `Cloid::ZERO` and get their cloids just before
+/// sending, so dry runs and refused orders don't use any up.
+fn issue_cloids(state: &ServerState, orders: &mut [OrderRequest], purpose: &str) -> Vec<Cloid> {
+    orders
+        .iter_mut()
+        .map(|order| {
+            order.cloid = state.cloids.issue_new(purpose);
+            order.cloid
+        })
+        .collect()
+}
+
+pub async fn place_order(
+    state: &ServerState,
+    req: PlaceOrderRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    state.check_trading_window()?;
+    state.check_breaker()?;
+    let signer = state.scoped_signer(scope)?;
+    let address = state.scoped_address(scope)?;
+    let kind = req
+        .market_kind
+        .as_deref()
+        .map(MarketKind::parse)
+        .transpose()?;
+    let market = state.resolve_market(&req.coin, kind)?;
+
+    let is_buy = match req.side.to_lowercase().as_str() {
+        "buy" | "b" | "long" => true,
+        "sell" | "s" | "short" => false,
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Invalid side. Use \"buy\" or \"sell\".",
+            )]));
+        }
+    };
+    if let Some(refusal) = state.spot_short_refusal(&market, &req.side) {
+        return Ok(CallToolResult::error(vec![Content::text(refusal)]));
+    }
+
+    let urgency = match req.urgency.as_deref().map(Urgency::parse).transpose() {
+        Ok(urgency) => urgency,
+        Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+    };
+    if urgency.is_some() && (req.order_type.is_some() || req.time_in_force.is_some()) {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "urgency picks the order type and time in force; pass either urgency or order_type/time_in_force.",
+        )]));
+    }
+    if urgency == Some(Urgency::Immediate) && (req.price.is_some() || req.price_reference.is_some())
+    {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "urgency \"immediate\" sends a market order priced off the book; drop price and price_reference, \
+             or use \"normal\" for a limit order.",
+        )]));
+    }
+
+    // An explicit price without an order type is always a limit order, even
+    // when the configured default is market.
+    let order_type_str = match (urgency, req.order_type.as_deref()) {
+        (Some(Urgency::Immediate), _) => "market".to_string(),
+        (Some(_), _) => "limit".to_string(),
+        (None, Some(order_type)) => order_type.to_lowercase(),
+        (None, None) if req.price.is_some() || req.price_reference.is_some() => "limit".to_string(),
+        (None, None) => state.default_order_type.clone(),
+    };
+    let reduce_only = req.reduce_only.unwrap_or(false);
+
+    // A USD size is provisionally converted at the mid for the checks below,
+    // then settled once the order's price is known.
+    let size_usd = match (req.size, req.size_usd) {
+        (Some(_), None) => None,
+        (None, Some(usd)) if usd > 0.0 => Some(to_decimal(usd)?),
+        (None, Some(_)) => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "size_usd must be positive.",
+            )]));
+        }
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Provide either size (coin units) or size_usd (quote amount).",
+            )]));
+        }
+    };
+    let mut size = match (req.size, size_usd) {
+        (Some(size), _) => to_decimal(size)?,
+        (None, Some(usd)) => {
+            let (_, mid) = reference_price(state, &market, "mid").await?;
+            size_for_notional(&market, usd, mid)
+        }
+        (None, None) => unreachable!("size or size_usd is checked above"),
+    };
+
+    // Check perp orders against the current position: the exchange silently
+    // rejects or zero-fills reduce-only orders with nothing to reduce, and
+    // other orders get a warning when they pile onto or flip the position.
+    let mut position_warning = String::new();
+    let mut position_szi = Decimal::ZERO;
+    let mut position_leverage = None;
+    if market.kind == MarketKind::Perp {
+        let user_state = account::get_cached_clearinghouse(state, address).await?;
+        let position = user_state
+            .asset_positions
+            .iter()
+            .map(|p| &p.position)
+            .find(|p| p.coin == market.coin && !p.szi.is_zero());
+        let szi = position.map(|p| p.szi).unwrap_or_default();
+        position_szi = szi;
+        position_leverage = position.map(|p| p.leverage.value);
+        // A reduce-only buy reduces a short, a sell reduces a long.
+        let (reducible, side_str, needed) = if is_buy {
+            (-szi, "buy", "short")
+        } else {
+            (szi, "sell", "long")
+        };
+        if !reduce_only {
+            position_warning = exposure_warning(
+                szi,
+                size,
+                is_buy,
+                &market.symbol,
+                state.position_warn_multiple,
+            )
+            .unwrap_or_default();
+        } else if reducible <= Decimal::ZERO {
+            if !req.skip_validation.unwrap_or(false) {
+                let current = if szi.is_zero() {
+                    String::new()
+                } else {
+                    format!(" (current position: {szi})")
+                };
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Reduce-only {side_str} on {} but you have no {needed}{current}. \
+                     Pass skip_validation: true to send it anyway.",
+                    market.symbol
+                ))]));
+            }
+        } else if size > reducible {
+            position_warning = format!(
+                "Warning: size {size} exceeds the {reducible} {} {needed}; \
+                 the excess {} will be cancelled by the exchange.\n\n",
+                market.symbol,
+                size - reducible
+            );
+        }
+    }
+
+    // Perps at their open interest cap reject orders that add to open
+    // interest, which otherwise surfaces as an opaque exchange error.
+    let mut oi_cap_warning = String::new();
+    if !reduce_only
+        && market.kind == MarketKind::Perp
+        && get_capped_perps(state).await?.contains(&market.coin)
+    {
+        oi_cap_warning = format!(
+            "Warning: {} is at its open interest cap. The exchange rejects orders that \
+             open or increase a position there; only orders that reduce open interest \
+             will go through.\n\n",
+            market.symbol
+        );
+    }
+
+    let pricing = if order_type_str == "market" {
+        Some(market_pricing(state, &market, is_buy, size).await?)
+    } else {
+        None
+    };
+    if let Some(pricing) = &pricing
+        && !req.force.unwrap_or(false)
+        && let Some(refusal) = impact_guard(state, &market, pricing).await?
+    {
+        return Ok(CallToolResult::error(vec![Content::text(refusal)]));
+    }
+
+    let mut resolved_price = String::new();
+    let (limit_px, order_type) = if let Some(pricing) = &pricing {
+        // Market orders are IOC limits priced off the book
+        (
+            pricing.limit_px,
+            OrderTypePlacement::Limit {
+                tif: TimeInForce::Ioc,
+            },
+        )
+    } else {
+        // Limit order
+        let tif = match urgency {
+            Some(Urgency::Passive) => TimeInForce::Alo,
+            Some(_) => TimeInForce::Gtc,
+            None => parse_tif(req.time_in_force.as_deref().unwrap_or(&state.default_tif)),
+        };
+        // Normal urgency without a price rests at the mid.
+        let price_reference = match (urgency, req.price, req.price_reference.as_deref()) {
+            (Some(Urgency::Normal), None, None) => Some("mid"),
+            (_, _, reference) => reference,
+        };
+        let price = match (req.price, price_reference) {
+            (Some(_), Some(_)) => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "Provide either price or price_reference, not both.",
+                )]));
+            }
+            (Some(price), None) => to_decimal(price)?,
+            (None, Some(reference)) => {
+                let offset_bps = req.price_offset_bps.unwrap_or(0.0);
+                let (reference, reference_px) = reference_price(state, &market, reference).await?;
+                let target =
+                    reference_px * (Decimal::ONE + to_decimal(offset_bps)? / Decimal::from(10_000));
+                let price = match market.round_price(target, is_buy) {
+                    Ok(price) => price,
+                    Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+                };
+                resolved_price = format!(
+                    "Resolved price: ${price} ({reference} ${reference_px} {offset_bps:+} bps)\n\n"
+                );
+                price
+            }
+            (None, None) if urgency == Some(Urgency::Passive) => {
+                match passive_price(state, &market, is_buy).await? {
+                    Ok((price, why)) => {
+                        resolved_price = format!("Resolved price: ${price} ({why})\n\n");
+                        price
+                    }
+                    Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+                }
+            }
+            (None, None) => {
+                return Err(mcp_err(
+                    "Price is required for limit orders. Provide 'price' or 'price_reference', \
+                     or use order_type: \"market\".",
+                ));
+            }
+        };
+        (price, OrderTypePlacement::Limit { tif })
+    };
+    if let Some(urgency) = urgency {
+        resolved_price.push_str(&urgency.explain());
+    }
+
+    if let Some(usd) = size_usd {
+        // Buys hold the quote at the limit price, so size them there to stay
+        // within the amount; sells are sized at the expected price.
+        let sizing_px = match &pricing {
+            Some(pricing) if !is_buy => pricing.mid,
+            _ => limit_px,
+        };
+        size = size_for_notional(&market, usd, sizing_px);
+        if size.is_zero() {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "size_usd ${usd} is less than one size step of {} at ${sizing_px}.",
+                market.symbol
+            ))]));
+        }
+        resolved_price.push_str(&format!(
+            "Resolved size: {size} ({usd} {} at ${sizing_px})\n\n",
+            market
+                .symbol
+                .split_once('/')
+                .map_or("USD", |(_, quote)| quote)
+        ));
+    }
+
+    if market.kind == MarketKind::Perp
+        && !reduce_only
+        && state.position_notional_limit(&market.coin).is_some()
+    {
+        let (_, mid) = reference_price(state, &market, "mid").await?;
+        if let Some(refusal) =
+            position_limit_refusal(state, &market, position_szi, size, is_buy, mid)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(refusal)]));
+        }
+    }
+
+    if market.kind == MarketKind::Spot && !req.skip_validation.unwrap_or(false) {
+        let failed: Vec<String> =
+            spot_order_checks(state, address, &market, is_buy, size, limit_px)
+                .await?
+                .into_iter()
+                .filter_map(|c| c.outcome.err().map(|fix| format!("- {}: {fix}", c.name)))
+                .collect();
+        if !failed.is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Spot order on {} would be rejected:\n{}\n\nPass skip_validation: true to send it anyway.",
+                market.symbol,
+                failed.join("\n")
+            ))]));
+        }
+    }
+
+    let mut order = OrderRequest {
+        asset: market.asset,
+        is_buy,
+        reduce_only,
+        limit_px,
+        sz: size,
+        // Issued below once the order is going to be sent.
+        cloid: Cloid::ZERO,
+        order_type,
+    };
+    let mid = match &pricing {
+        Some(pricing) => Some(pricing.mid),
+        None => reference_price(state, &market, "mid")
+            .await
+            .ok()
+            .map(|(_, mid)| mid),
+    };
+    let band = match &pricing {
+        Some(pricing) => Some(PriceBand::new(state, "mid", pricing.mid)),
+        None => price_band(state, &market).await,
+    };
+    match normalize_order(&market, &mut order, band) {
+        Ok(rounding) => resolved_price.push_str(&rounding),
+        Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+    }
+    let (size, limit_px) = (order.sz, order.limit_px);
+
+    let mut tier_warning = String::new();
+    if market.kind == MarketKind::Perp
+        && !reduce_only
+        && let Some(table) = margin_table(state, &market).await?
+    {
+        tier_warning = margin_tier_warning(
+            &table,
+            &market.symbol,
+            position_szi,
+            position_leverage,
+            size,
+            is_buy,
+            mid.unwrap_or(limit_px),
+        )
+        .unwrap_or_default();
+    }
+
+    let rests = !matches!(
+        order.order_type,
+        OrderTypePlacement::Limit {
+            tif: TimeInForce::Ioc
+        }
+    );
+    let limit_warning = if rests {
+        check_open_order_limits(state, address, &HashMap::from([(market.coin.clone(), 1)])).await?
+    } else {
+        String::new()
+    };
+
+    if state.dry_run {
+        let side_str = if is_buy { "buy" } else { "sell" };
+        let paper = paper_fill(
+            state,
+            &market,
+            is_buy,
+            size,
+            reduce_only,
+            (order_type_str != "market").then_some(limit_px),
+        )
+        .await?;
+        return Ok(dry_run_result(&format!(
+            "place {side_str} {} {} @ ${} ({:?}{}) on the {}.\n\n{resolved_price}{position_warning}{oi_cap_warning}{tier_warning}{limit_warning}{}{paper}",
+            order.sz,
+            market.describe(),
+            order.limit_px,
+            order.order_type,
+            if reduce_only { ", reduce-only" } else { "" },
+            state.describe_scope(scope),
+            pricing.as_ref().map(|p| p.describe()).unwrap_or_default(),
+        )));
+    }
+
+    // Market orders are fingerprinted without their slippage-adjusted price,
+    // which moves with the mid between retries.
+    let tif = match &order.order_type {
+        OrderTypePlacement::Limit { tif } => *tif,
+        _ => TimeInForce::Gtc,
+    };
+    let fingerprint = order_fingerprint(
+        &market.coin,
+        is_buy,
+        size,
+        (order_type_str != "market").then_some(order.limit_px),
+        tif,
+        reduce_only,
+        state.scoped_vault(scope),
+    );
+    // A resubmission after a failed attempt gets a fresh cloid under the
+    // same intent, so the two stay linked.
+    let intent = state.cloids.intent_for(fingerprint);
+    order.cloid = state.cloids.issue(
+        intent,
+        format!(
+            "place_order: {} {size} {}",
+            if is_buy { "buy" } else { "sell" },
+            market.symbol
+        ),
+    );
+    if !req.allow_duplicate.unwrap_or(false) {
+        if let Some((cloid, age)) = state.claim_order(fingerprint, order.cloid) {
+            state.cloids.record(order.cloid, Submission::NotSent);
+            return duplicate_order_result(state, address, cloid, age).await;
+        }
+    }
+
+    let placed = [(market.clone(), order.clone())];
+    let cloid = order.cloid;
+    let nonce = state.next_nonce();
+    // The request runs on its own task so a slow exchange can't hold the
+    // tool call past the client's timeout, which would invite a retry.
+    let mut submission = {
+        let state = state.clone();
+        let signer = std::sync::Arc::clone(signer);
+        let vault = state.scoped_vault(scope);
+        tokio::spawn(async move {
+            let result = hyperliquid::place_order_with_builder(
+                state.api.as_ref(),
+                state.chain,
+                signer.as_ref(),
+                vec![order],
+                OrderGrouping::Na,
+                Some(state.builder_info()),
+                nonce,
+                vault,
+            )
+            .await;
+            state.cloids.record_response(&[cloid], &result);
+            result
+        })
+    };
+    let joined = match state.order_latency_budget {
+        Some(budget) => match tokio::time::timeout(budget, &mut submission).await {
+            Ok(joined) => joined,
+            Err(_) => {
+                let summary = format!(
+                    "{} {size} {}",
+                    if is_buy { "Buy" } else { "Sell" },
+                    market.describe()
+                );
+                confirm_in_background(state, submission, cloid, nonce, &summary);
+                return Ok(pending_order_result(
+                    &summary,
+                    &state.describe_scope(scope),
+                    cloid,
+                    budget,
+                ));
+            }
+        },
+        None => submission.await,
+    };
+    let response = match submission_result(state, cloid, joined) {
+        Ok(response) => response,
+        // Same as a late failure in `confirm_in_background`: the order stays
+        // claimed until `get_order_status` shows what became of it.
+        Err(e) => {
+            let mut error = state.action_failed(nonce, "Order placement failed", e);
+            error.message = format!("{}; {}", error.message, unanswered_order_note(cloid)).into();
+            return Err(error);
+        }
+    };
+    state.record_response(nonce, &response);
+
+    state.cache.invalidate_user_data().await;
+
+    let side_str = if is_buy { "Buy" } else { "Sell" };
+    let mut output = format!("## Order Result: {side_str} {size} {}", market.describe());
+    if order_type_str == "market" {
+        output.push_str(" @ Market\n\n");
+    } else {
+        output.push_str(&format!(" @ ${limit_px}\n\n"));
+    }
+    output.push_str(&format!("Account: {}\n\n", state.describe_scope(scope)));
+    let attempts = state.cloids.attempts(intent);
+    if attempts.len() > 1 {
+        output.push_str(&format!(
+            "Retry of an earlier attempt (attempt {} of intent #{}; earlier: {}).\n\n",
+            attempts.len(),
+            intent.id(),
+            attempts[..attempts.len() - 1]
+                .iter()
+                .map(|(c, r)| format!("{c} {}", r.outcome.describe()))
+                .collect::<Vec<_>>()
+                .join("; ")
+        ));
+    }
+    output.push_str(&resolved_price);
+    output.push_str(&position_warning);
+    output.push_str(&oi_cap_warning);
+    output.push_str(&tier_warning);
+    output.push_str(&limit_warning);
+    if let Some(pricing) = &pricing {
+        output.push_str(&pricing.describe());
+    }
+    let outcomes = placed_order_outcomes(&response, &placed).map(|outcomes| {
+        outcomes
+            .into_iter()
+            .map(|o| PlacedOrderOutcome {
+                requested_size: req.size.and_then(|s| Decimal::try_from(s).ok()),
+                requested_price: req.price.and_then(|p| Decimal::try_from(p).ok()),
+                ..o
+            })
+            .collect::<Vec<_>>()
+    });
+    output.push_str(&format!("Status: {}\n", describe_outcomes(&outcomes)));
+    if let Ok(outcomes) = &outcomes {
+        if let Some(context) = match outcomes.first() {
+            Some(outcome) => expiry_context(state, &market, outcome).await,
+            None => None,
+        } {
+            output.push_str(&context);
+        }
+        output.push_str(&resting_orders_table(state, outcomes).await);
+    }
+
+    maybe_append_builder_nudge(state, &mut output).await;
+
+    order_result(output, &outcomes)
+}
+
+/// The exchange's answer from a submission task. A task that died without
+/// one leaves the cloid unknown rather than pending.
+fn submission_result(
+    state: &ServerState,
+    cloid: Cloid,
+    joined: Result<anyhow::Result<serde_json::Value>, tokio::task::JoinError>,
+) -> anyhow::Result<serde_json::Value> {
+    joined.unwrap_or_else(|e| {
+        let result = Err(anyhow::anyhow!("order submission task failed: {e}"));
+        state.cloids.record_response(&[cloid], &result);
+        result
+    })
+}
+
+/// Finish the bookkeeping for a submission `place_order` stopped waiting
+/// for, and report its outcome as an account event.
+fn confirm_in_background(
+    state: &ServerState,
+    submission: tokio::task::JoinHandle<anyhow::Result<serde_json::Value>>,
+    cloid: Cloid,
+    nonce: u64,
+    summary: &str,
+) {
+    let state = state.clone();
+    let summary = summary.to_string();
+    tokio::spawn(async move {
+        let text = match submission_result(&state, cloid, submission.await) {
+            Ok(response) => {
+                state.record_response(nonce, &response);
+                state.cache.invalidate_user_data().await;
+                let outcome = state
+                    .cloids
+                    .lookup(&cloid)
+                    .map(|r| r.outcome.describe())
+                    .unwrap_or_else(|| "answered".to_string());
+                format!("{summary} (cloid {cloid}) confirmed late: {outcome}")
+            }
+            Err(e) => {
+                let _ = state.action_failed(nonce, "Order placement failed", &e);
+                format!(
+                    "{summary} (cloid {cloid}) got no answer: {e}; {}",
+                    unanswered_order_note(cloid)
+                )
+            }
+        };
+        state.cache.events.record(EventKind::Order, text);
+    });
+}
+
+/// What to do about an order whose request failed without an answer. It may
+/// still have reached the exchange, so it stays claimed: an identical order
+/// is refused as a duplicate until `get_order_status` resolves the cloid.
+fn unanswered_order_note(cloid: Cloid) -> String {
+    format!(
+        "it may or may not have reached the exchange. Check it with `get_order_status` and \
+         cloid {cloid} before placing it again; an identical order is refused as a duplicate \
+         until then unless `allow_duplicate` is set."
+    )
+}
+
+/// Marks a result whose order is still awaiting the exchange's answer.
+pub(crate) const CONFIRMATION_PENDING: &str = "Submitted, confirmation pending";
+
+/// Result for an order the exchange hasn't answered within the latency
+/// budget. It may still rest or fill, so it must not be sent again.
+fn pending_order_result(
+    summary: &str,
+    account: &str,
+    cloid: Cloid,
+    budget: std::time::Duration,
+) -> CallToolResult {
+    CallToolResult::success(vec![Content::text(format!(
+        "## Order Submitted: {summary}\n\n\
+         Account: {account}\n\n\
+         {CONFIRMATION_PENDING}, cloid {cloid}. The exchange hasn't answered \
+         within {}s. The request carries on in the background and its outcome will lead \
+         a later response and show in `get_events`; `get_order_status` with this cloid \
+         resolves it sooner. Don't resend it: an identical order is refused as a \
+         duplicate until the answer arrives.\n",
+        budget.as_secs()
+    ))])
+}
+
+/// Markdown output plus, when the exchange accepted the action, the same
+/// outcomes as a JSON block for clients that want structured data. When
+/// every order expired on arrival (IOC unfilled, post-only would cross),
+/// nothing is working, so the result is an error.
+fn order_result(
+    output: String,
+    outcomes: &Result<Vec<PlacedOrderOutcome>, String>,
+) -> Result<CallToolResult, ErrorData> {
+    let mut content = vec![Content::text(output)];
+    if let Ok(outcomes) = outcomes {
+        content.push(Content::json(serde_json::json!({ "orders": outcomes }))?);
+        if !outcomes.is_empty() && outcomes.iter().all(|o| o.expiry().is_some()) {
+            return Ok(CallToolResult::error(content));
+        }
+    }
+    Ok(CallToolResult::success(content))
+}
+
+/// The book around an expired order's limit, e.g. "Book now: best bid
+/// $3,009.5, best ask $3,010 (your limit $3,005)". Post-only rejections
+/// already carry the best bid and ask, so the book is only fetched for IOC.
+async fn expiry_context(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    outcome: &PlacedOrderOutcome,
+) -> Option<String> {
+    let (bid, ask) = match outcome.expiry()? {
+        Expiry::AloWouldCross {
+            bid: Some(bid),
+            ask: Some(ask),
+        } => (bid, ask),
+        _ => {
+            let book = fetch_l2_book(state, &market.coin).await.ok()?;
+            (book.bids.first()?.0, book.asks.first()?.0)
+        }
+    };
+    let limit = outcome
+        .price
+        .map(|p| format!(" (your limit {})", fmt_price(p)))
+        .unwrap_or_default();
+    Some(format!(
+        "Book now: best bid {}, best ask {}{limit}.\n",
+        fmt_price(bid),
+        fmt_price(ask)
+    ))
+}
+
+/// Result for a `place_order` call that repeats one submitted moments ago:
+/// report the original order's current status instead of trading again.
+async fn duplicate_order_result(
+    state: &ServerState,
+    address: Address,
+    cloid: Cloid,
+    age: std::time::Duration,
+) -> Result<CallToolResult, ErrorData> {
+    let mut output = format!(
+        "## Duplicate Order Skipped\n\n\
+         An identical order was submitted {:.1}s ago, so this one was not sent. \
+         Pass `allow_duplicate: true` to place it anyway.\n\n",
+        age.as_secs_f64()
+    );
+    if let Some(original) = state.cloids.describe(&cloid) {
+        output.push_str(&format!("Original submission: {original}\n"));
+    }
+
+    match state.api.order_status(address, Either::Right(cloid)).await {
+        Ok(Some(update)) => output.push_str(&format!(
+            "Original order {}: {} ({} of {} remaining @ ${})\n",
+            update.order.oid,
+            update.status,
+            update.order.sz,
+            update.order.orig_sz,
+            update.order.limit_px,
+        )),
+        Ok(None) => output.push_str(&format!(
+            "Original order (client ID {cloid}) has no recorded status yet.\n"
+        )),
+        Err(e) => output.push_str(&format!("Could not fetch original order status: {e}\n")),
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn place_batch_orders(
+    state: &ServerState,
+    req: PlaceBatchOrdersRequest,
+) -> Result<CallToolResult, ErrorData> {
+    state.check_trading_window()?;
+    state.check_breaker()?;
+    let signer = state.require_signer()?;
+
+    let grouping_str = req.grouping.as_deref().unwrap_or("na");
+    let Some(grouping) = parse_grouping(grouping_str) else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Invalid grouping '{grouping_str}'. Use \"na\", \"normal_tpsl\" or \"position_tpsl\"."
+        ))]));
+    };
+    if req.orders.is_empty() || req.orders.len() > MAX_BATCH_ORDERS {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Provide between 1 and {MAX_BATCH_ORDERS} orders."
+        ))]));
+    }
+
+    let mut orders = Vec::with_capacity(req.orders.len());
+    let mut markets = Vec::with_capacity(req.orders.len());
+    let mut lines = Vec::with_capacity(req.orders.len());
+    // Resting (non-IOC) orders per coin, for the open order limit check.
+    let mut resting: HashMap<String, usize> = HashMap::new();
+    for (i, leg) in req.orders.iter().enumerate() {
+        let n = i + 1;
+        let market = state.resolve_market(&leg.coin, None)?;
+        let is_buy = match leg.side.to_lowercase().as_str() {
+            "buy" | "b" | "long" => true,
+            "sell" | "s" | "short" => false,
+            _ => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Order {n}: invalid side. Use \"buy\" or \"sell\"."
+                ))]));
+            }
+        };
+        if let Some(refusal) = state.spot_short_refusal(&market, &leg.side) {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Order {n}: {refusal}"
+            ))]));
+        }
+        let size = to_decimal(leg.size)?;
+
+        let (limit_px, order_type, reduce_only) = match (leg.trigger_price, leg.tpsl.as_deref()) {
+            (Some(trigger), Some(kind)) => {
+                let tpsl = match kind.to_lowercase().as_str() {
+                    "tp" | "take_profit" => TpSl::Tp,
+                    "sl" | "stop_loss" => TpSl::Sl,
+                    _ => {
+                        return Ok(CallToolResult::error(vec![Content::text(format!(
+                            "Order {n}: invalid tpsl '{kind}'. Use \"tp\" or \"sl\"."
+                        ))]));
+                    }
+                };
+                let trigger_px = to_decimal(trigger)?;
+                let limit_px = match leg.price {
+                    Some(price) => to_decimal(price)?,
+                    None if is_buy => {
+                        round_price_5sf(trigger_px * (Decimal::ONE + TRIGGER_MARKET_SLIPPAGE))
+                    }
+                    None => round_price_5sf(trigger_px * (Decimal::ONE - TRIGGER_MARKET_SLIPPAGE)),
+                };
+                let order_type = OrderTypePlacement::Trigger {
+                    is_market: leg.price.is_none(),
+                    trigger_px,
+                    tpsl,
+                };
+                (limit_px, order_type, true)
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Order {n}: trigger_price and tpsl must be given together."
+                ))]));
+            }
+            (None, None) => {
+                let Some(price) = leg.price else {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Order {n}: price is required for non-trigger orders."
+                    ))]));
+                };
+                let tif = parse_tif(leg.time_in_force.as_deref().unwrap_or(&state.default_tif));
+                (
+                    to_decimal(price)?,
+                    OrderTypePlacement::Limit { tif },
+                    leg.reduce_only.unwrap_or(false),
+                )
+            }
+        };
+
+        let mut order = OrderRequest {
+            asset: market.asset,
+            is_buy,
+            reduce_only,
+            limit_px,
+            sz: size,
+            cloid: Cloid::ZERO,
+            order_type,
+        };
+        let band = price_band(state, &market).await;
+        let rounding = match normalize_order(&market, &mut order, band) {
+            Ok(rounding) => rounding,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Order {n}: {e}"
+                ))]));
+            }
+        };
+
+        lines.push(format!(
+            "{n}. {} {} {} @ ${}{}{}",
+            if is_buy { "Buy" } else { "Sell" },
+            order.sz,
+            market.describe(),
+            order.limit_px,
+            match &order.order_type {
+                OrderTypePlacement::Trigger {
+                    trigger_px, tpsl, ..
+                } => format!(" ({tpsl:?} trigger ${trigger_px}, reduce-only)"),
+                OrderTypePlacement::Limit { tif } if reduce_only =>
+                    format!(" ({tif:?}, reduce-only)"),
+                OrderTypePlacement::Limit { tif } => format!(" ({tif:?})"),
+            },
+            if rounding.is_empty() {
+                String::new()
+            } else {
+                format!(" — {}", rounding.trim_end().trim_end_matches('.'))
+            }
+        ));
+        if !matches!(
+            order.order_type,
+            OrderTypePlacement::Limit {
+                tif: TimeInForce::Ioc
+            }
+        ) {
+            *resting.entry(market.coin.clone()).or_default() += 1;
+        }
+        orders.push(order);
+        markets.push(market);
+    }
+
+    if let Err(msg) = validate_grouping(&grouping, &orders) {
+        return Ok(CallToolResult::error(vec![Content::text(msg)]));
+    }
+
+    // Legs on the same coin add up, so each is checked against the position
+    // the earlier legs would leave.
+    if markets
+        .iter()
+        .any(|m| m.kind == MarketKind::Perp && state.position_notional_limit(&m.coin).is_some())
+    {
+        let user_state = account::get_cached_clearinghouse(state, state.query_address()?).await?;
+        let mut projected: HashMap<&str, Decimal> = user_state
+            .asset_positions
+            .iter()
+            .map(|p| (p.position.coin.as_str(), p.position.szi))
+            .collect();
+        for (i, (market, order)) in markets.iter().zip(&orders).enumerate() {
+            if market.kind != MarketKind::Perp {
+                continue;
+            }
+            if !order.reduce_only && state.position_notional_limit(&market.coin).is_some() {
+                let (_, mid) = reference_price(state, market, "mid").await?;
+                let szi = projected
+                    .get(market.coin.as_str())
+                    .copied()
+                    .unwrap_or_default();
+                if let Some(refusal) =
+                    position_limit_refusal(state, market, szi, order.sz, order.is_buy, mid)
+                {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Order {}: {refusal}",
+                        i + 1
+                    ))]));
+                }
+            }
+            *projected.entry(market.coin.as_str()).or_default() +=
+                if order.is_buy { order.sz } else { -order.sz };
+        }
+    }
+    let limit_warning = check_open_order_limits(state, state.query_address()?, &resting).await?;
+
+    let summary = format!(
+        "Grouping: {grouping:?}\n\n{}\n\n{limit_warning}",
+        lines.join("\n")
+    );
+    if state.dry_run {
+        let mut paper = String::new();
+        for (market, order) in markets.iter().zip(&orders) {
+            if let OrderTypePlacement::Limit { .. } = order.order_type {
+                paper.push_str(
+                    &paper_fill(
+                        state,
+                        market,
+                        order.is_buy,
+                        order.sz,
+                        order.reduce_only,
+                        Some(order.limit_px),
+                    )
+                    .await?,
+                );
+            }
+        }
+        return Ok(dry_run_result(&format!(
+            "place {} orders.\n\n{summary}{paper}",
+            orders.len()
+        )));
+    }
+
+    let cloids = issue_cloids(state, &mut orders, "place_batch_orders");
+    let placed: Vec<_> = markets.into_iter().zip(orders.iter().cloned()).collect();
+    let nonce = state.next_nonce();
+    let result = hyperliquid::place_order_with_builder(
+        state.api.as_ref(),
+        state.chain,
+        signer.as_ref(),
+        orders,
+        grouping,
+        Some(state.builder_info()),
+        nonce,
+        state.vault_addr(),
+    )
+    .await;
+    state.cloids.record_response(&cloids, &result);
+    let response =
+        result.map_err(|e| state.action_failed(nonce, "Batch order placement failed", e))?;
+    state.record_response(nonce, &response);
+
+    state.cache.invalidate_user_data().await;
+
+    let mut output = format!("## Batch Order Result\n\n{summary}\n");
+    let outcomes = placed_order_outcomes(&response, &placed);
+    output.push_str(&format!("Status: {}\n", describe_outcomes(&outcomes)));
+    if let Ok(outcomes) = &outcomes {
+        output.push_str(&resting_orders_table(state, outcomes).await);
+    }
+
+    maybe_append_builder_nudge(state, &mut output).await;
+
+    order_result(output, &outcomes)
+}
+
+pub async fn cancel_order(
+    state: &ServerState,
+    req: CancelOrderRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    let signer = state.scoped_signer(scope)?;
+    let address = state.scoped_address(scope)?;
+    let market = state.resolve_market(&req.coin, None)?;
+
+    let cancel = Cancel {
+        asset: market.asset,
+        oid: req.order_id,
+    };
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "cancel order {} on {} for the {}.",
+            req.order_id,
+            market.describe(),
+            state.describe_scope(scope)
+        )));
+    }
+
+    let nonce = state.next_nonce();
+    let response = state
+        .client
+        .cancel(
+            signer.as_ref(),
+            BatchCancel {
+                cancels: vec![cancel],
+            },
+            nonce,
+            state.scoped_vault(scope),
+            None,
+        )
+        .await
+        .map_err(|e| state.action_failed(nonce, "Cancel failed", e))?;
+
+    state.cache.invalidate_user_data().await;
+
+    let mut output = format!(
+        "Cancel order {} on {} ({}): {}\n",
+        req.order_id,
+        market.describe(),
+        state.describe_scope(scope),
+        format_order_response(&response)
+    );
+    if !response.iter().all(|s| s.is_ok()) {
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    output.push('\n');
+    if let Some(order) = fetch_order_snapshot(state, address, req.order_id.into()).await {
+        let filled = order.orig_sz - order.sz;
+        output.push_str(&format!("Status: {}\n", order.status));
+        if filled.is_zero() {
+            output.push_str(&format!(
+                "Nothing had filled; all {} was cancelled.\n",
+                order.orig_sz
+            ));
+        } else {
+            output.push_str(&format!(
+                "Filled {filled} of {} before cancellation ({} cancelled).\n",
+                order.orig_sz, order.sz
+            ));
+        }
+    }
+    match account::get_cached_open_orders(state, address).await {
+        Ok(open) if open.iter().any(|o| o.oid == req.order_id) => output.push_str(&format!(
+            "**Warning:** order {} still appears in open orders.\n",
+            req.order_id
+        )),
+        Ok(_) => output.push_str("Confirmed: no longer among open orders.\n"),
+        Err(e) => output.push_str(&format!("Could not re-check open orders: {}\n", e.message)),
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// An order reference given to `cancel_orders`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OrderRef {
+    Oid(u64),
+    Cloid(Cloid),
+}
+
+impl OrderRef {
+    fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.starts_with("0x") || s.starts_with("0X") {
+            s.parse::<Cloid>()
+                .map(OrderRef::Cloid)
+                .map_err(|_| format!("Invalid cloid '{s}': expected \"0x\" and 32 hex digits."))
+        } else {
+            s.parse::<u64>()
+                .map(OrderRef::Oid)
+                .map_err(|_| format!("Invalid order reference '{s}': use an order id or a cloid."))
+        }
+    }
+
+    fn matches(self, order: &BasicOrder) -> bool {
+        match self {
+            OrderRef::Oid(oid) => order.oid == oid,
+            OrderRef::Cloid(cloid) => order.cloid == Some(cloid),
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            OrderRef::Oid(oid) => format!("order {oid}"),
+            OrderRef::Cloid(cloid) => format!("cloid {cloid:#x}"),
+        }
+    }
+}
+
+pub async fn cancel_orders(
+    state: &ServerState,
+    req: CancelOrdersRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    let address = state.scoped_address(scope)?;
+    let signer = state.scoped_signer(scope)?;
+
+    if req.orders.is_empty() {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "List at least one order id or cloid to cancel.",
+        )]));
+    }
+    let mut refs: Vec<OrderRef> = Vec::with_capacity(req.orders.len());
+    for s in &req.orders {
+        match OrderRef::parse(s) {
+            Ok(r) if !refs.contains(&r) => refs.push(r),
+            Ok(_) => {}
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        }
+    }
+
+    let orders = state
+        .api
+        .open_orders(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
+
+    // Resolve every reference to an open order; an oid and a cloid naming
+    // the same order cancel it once.
+    let mut found: Vec<(OrderRef, &BasicOrder)> = Vec::new();
+    let mut missing: Vec<String> = Vec::new();
+    for r in refs {
+        match orders.iter().find(|o| r.matches(o)) {
+            Some(o) if found.iter().any(|(_, f)| f.oid == o.oid) => {}
+            Some(o) => found.push((r, o)),
+            None => {
+                let issued = match r {
+                    OrderRef::Cloid(cloid) => state
+                        .cloids
+                        .describe(&cloid)
+                        .map(|d| format!("; issued as {d}"))
+                        .unwrap_or_default(),
+                    OrderRef::Oid(_) => String::new(),
+                };
+                missing.push(format!(
+                    "{}: not open (already filled, cancelled or never placed){issued}",
+                    r.describe()
+                ));
+            }
+        }
+    }
+
+    let mut cancels = Vec::with_capacity(found.len());
+    let mut lines = Vec::with_capacity(found.len());
+    for (r, o) in &found {
+        let Some(&asset) = state.asset_map.get(&o.coin) else {
+            missing.push(format!("{}: unknown market {}", r.describe(), o.coin));
+            continue;
+        };
+        cancels.push(Cancel { asset, oid: o.oid });
+        let side = match o.side {
+            hypersdk::hypercore::Side::Bid => "buy",
+            hypersdk::hypercore::Side::Ask => "sell",
+        };
+        let via = match r {
+            OrderRef::Cloid(cloid) => format!(" (cloid {cloid:#x})"),
+            OrderRef::Oid(_) => String::new(),
+        };
+        lines.push(format!(
+            "{} {side} {} @ ${}, order {}{via}",
+            o.coin, o.sz, o.limit_px, o.oid
+        ));
+    }
+
+    let mut output = String::new();
+    if cancels.is_empty() {
+        output.push_str(&format!(
+            "Nothing to cancel for the {}.\n",
+            state.describe_scope(scope)
+        ));
+    } else if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "cancel {} orders for the {}:\n- {}\n{}",
+            cancels.len(),
+            state.describe_scope(scope),
+            lines.join("\n- "),
+            missing
+                .iter()
+                .map(|m| format!("\nNot found: {m}"))
+                .collect::<String>()
+        )));
+    } else {
+        let nonce = state.next_nonce();
+        let response = state
+            .client
+            .cancel(
+                signer.as_ref(),
+                BatchCancel { cancels },
+                nonce,
+                state.scoped_vault(scope),
+                None,
+            )
+            .await
+            .map_err(|e| state.action_failed(nonce, "Cancel failed", e))?;
+        state.cache.invalidate_user_data().await;
+
+        output.push_str(&format!(
+            "## Cancel Orders ({})\n\n",
+            state.describe_scope(scope)
+        ));
+        for (line, status) in lines.iter().zip(response.iter()) {
+            let status = match status {
+                OrderResponseStatus::Error(e) => format!("failed: {e}"),
+                _ => "cancelled".to_string(),
+            };
+            output.push_str(&format!("- {line}: {status}\n"));
+        }
+    }
+    if !missing.is_empty() {
+        output.push_str("\n**Not found:**\n");
+        for m in &missing {
+            output.push_str(&format!("- {m}\n"));
+        }
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
 pub async fn cancel_all_orders(
     state: &ServerState,
-    req: CancelAllOrdersRequest,
+    req: CancelAllOrdersRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    let address = state.scoped_address(scope)?;
+    let signer = state.scoped_signer(scope)?;
+
+    let orders = state
+        .api
+        .open_orders(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
+
+    let market = req
+        .coin
+        .as_deref()
+        .map(|c| state.resolve_market(c, None))
+        .transpose()?;
+    let to_cancel: Vec<_> = if let Some(ref market) = market {
+        orders.iter().filter(|o| o.coin == market.coin).collect()
+    } else {
+        orders.iter().collect()
+    };
+
+    if to_cancel.is_empty() {
+        let msg = match &market {
+            Some(m) => format!(
+                "No open orders to cancel on {} for the {}.",
+                m.describe(),
+                state.describe_scope(scope)
+            ),
+            None => format!(
+                "No open orders to cancel for the {}.",
+                state.describe_scope(scope)
+            ),
+        };
+        return Ok(CallToolResult::success(vec![Content::text(msg)]));
+    }
+
+    let cancels: Vec<Cancel> = to_cancel
+        .iter()
+        .filter_map(|o| {
+            state
+                .asset_map
+                .get(&o.coin)
+                .map(|&asset| Cancel { asset, oid: o.oid })
+        })
+        .collect();
+
+    let cancel_count = cancels.len();
+    if state.dry_run {
+        let oids: Vec<String> = cancels.iter().map(|c| c.oid.to_string()).collect();
+        return Ok(dry_run_result(&format!(
+            "cancel {cancel_count} orders for the {}: {}.",
+            state.describe_scope(scope),
+            oids.join(", ")
+        )));
+    }
+
+    let nonce = state.next_nonce();
+    let response = state
+        .client
+        .cancel(
+            signer.as_ref(),
+            BatchCancel { cancels },
+            nonce,
+            state.scoped_vault(scope),
+            None,
+        )
+        .await
+        .map_err(|e| state.action_failed(nonce, "Cancel all failed", e))?;
+
+    state.cache.invalidate_user_data().await;
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Cancelled {cancel_count} orders for the {}: {}",
+        state.describe_scope(scope),
+        format_order_response(&response)
+    ))]))
+}
+
+pub async fn modify_order(
+    state: &ServerState,
+    req: ModifyOrderRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    state.check_trading_window()?;
+    state.check_breaker()?;
+    let signer = state.scoped_signer(scope)?;
+    let address = state.scoped_address(scope)?;
+    let market = state.resolve_market(&req.coin, None)?;
+
+    let is_buy = match req.side.to_lowercase().as_str() {
+        "buy" | "b" | "long" => true,
+        "sell" | "s" | "short" => false,
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Invalid side. Use \"buy\" or \"sell\".",
+            )]));
+        }
+    };
+
+    let mut modify = Modify {
+        oid: either::Either::Left(req.order_id),
+        order: OrderRequest {
+            asset: market.asset,
+            is_buy,
+            reduce_only: false,
+            limit_px: to_decimal(req.new_price)?,
+            sz: to_decimal(req.new_size)?,
+            cloid: Cloid::ZERO,
+            order_type: OrderTypePlacement::Limit {
+                tif: TimeInForce::Gtc,
+            },
+        },
+    };
+
+    let band = price_band(state, &market).await;
+    let rounding = match normalize_order(&market, &mut modify.order, band) {
+        Ok(rounding) => rounding,
+        Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+    };
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "modify order {} on {} to {} @ ${} for the {}.\n\n{rounding}",
+            req.order_id,
+            market.describe(),
+            modify.order.sz,
+            modify.order.limit_px,
+            state.describe_scope(scope)
+        )));
+    }
+
+    // A modify replaces the order, which then carries the new client ID.
+    let before = fetch_order_snapshot(state, address, req.order_id.into()).await;
+    modify.order.cloid = state
+        .cloids
+        .issue_new(format!("modify_order: order {}", req.order_id));
+    let cloid = modify.order.cloid;
+
+    let nonce = state.next_nonce();
+    let result = state
+        .client
+        .modify(
+            signer.as_ref(),
+            BatchModify {
+                modifies: vec![modify],
+            },
+            nonce,
+            state.scoped_vault(scope),
+            None,
+        )
+        .await;
+    state.cloids.record(
+        cloid,
+        match &result {
+            Ok(statuses) => statuses
+                .first()
+                .map(Submission::from)
+                .unwrap_or(Submission::Accepted),
+            Err(e) => Submission::Unknown(e.to_string()),
+        },
+    );
+    let response = result.map_err(|e| state.action_failed(nonce, "Modify failed", e))?;
+
+    state.cache.invalidate_user_data().await;
+
+    let mut output = format!(
+        "Modify order {} on {} ({}): {}\n{}",
+        req.order_id,
+        market.describe(),
+        state.describe_scope(scope),
+        format_order_response(&response),
+        if rounding.is_empty() {
+            String::new()
+        } else {
+            format!("\n{rounding}")
+        }
+    );
+    if !response.iter().all(|s| s.is_ok()) {
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    let Some(after) = fetch_order_snapshot(state, address, format!("{cloid}").into()).await else {
+        output.push_str("\nCould not fetch the modified order to confirm it.\n");
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    };
+    let unit = market
+        .symbol
+        .split_once('/')
+        .map_or(market.symbol.as_str(), |(base, _)| base);
+    let change = |old: Option<String>, new: String| match old {
+        Some(old) if old != new => format!("{old} → {new}"),
+        _ => new,
+    };
+    output.push_str(&format!(
+        "\nPrice: {}\nSize: {} {unit}\nTIF: {}\nStatus: {} (order ID {})\n",
+        change(
+            before
+                .as_ref()
+                .map(|b| format!("${}", b.limit_px.normalize())),
+            format!("${}", after.limit_px.normalize())
+        ),
+        change(
+            before.as_ref().map(|b| b.sz.normalize().to_string()),
+            after.sz.normalize().to_string()
+        ),
+        change(
+            before.as_ref().and_then(|b| b.tif.clone()),
+            after.tif.clone().unwrap_or_else(|| "—".into())
+        ),
+        after.status,
+        after.oid,
+    ));
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// Margin impact of changing leverage on a coin: the before/after margin
+/// usage line, or the reason the exchange would reject the change.
+struct LeverageImpact {
+    summary: String,
+    refusal: Option<String>,
+}
+
+async fn leverage_impact(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    leverage: u32,
+    is_cross: bool,
+) -> Result<LeverageImpact, ErrorData> {
+    let user_state = account::get_cached_clearinghouse(state, state.query_address()?).await?;
+    let ms = &user_state.margin_summary;
+    let usage = |used: Decimal| {
+        if ms.account_value.is_zero() {
+            "N/A".to_string()
+        } else {
+            format!("{:.1}%", used / ms.account_value * Decimal::ONE_HUNDRED)
+        }
+    };
+
+    let Some(position) = user_state
+        .asset_positions
+        .iter()
+        .map(|p| &p.position)
+        .find(|p| p.coin == market.coin && !p.szi.is_zero())
+    else {
+        return Ok(LeverageImpact {
+            summary: format!(
+                "No open {} position; the new leverage applies to future orders. Margin usage stays at {}.\n",
+                market.symbol,
+                usage(ms.total_margin_used)
+            ),
+            refusal: None,
+        });
+    };
+
+    // Larger positions fall in margin tiers that allow less leverage.
+    let table = margin_table(state, market).await?;
+    let tier = table.as_ref().map(|t| t.tier_at(position.position_value));
+    let allowed = table.as_ref().map_or(position.max_leverage, |t| {
+        t.max_leverage_at(position.position_value)
+    });
+    if leverage > allowed {
+        let refusal = match tier {
+            Some(tier) if !tier.lower_bound.is_zero() => format!(
+                "{} allows at most {allowed}x leverage for a position of {} notional \
+                 (margin tier from {}). See get_margin_tiers.",
+                market.symbol,
+                fmt_usd(position.position_value),
+                fmt_price(tier.lower_bound)
+            ),
+            _ => format!("{} allows at most {allowed}x leverage.", market.symbol),
+        };
+        return Ok(LeverageImpact {
+            summary: String::new(),
+            refusal: Some(refusal),
+        });
+    }
+    if position.leverage.is_cross() != is_cross {
+        return Ok(LeverageImpact {
+            summary: String::new(),
+            refusal: Some(format!(
+                "The {} position is {}; the margin mode can't be changed while it is open. \
+                 Keep the current mode or close the position first.",
+                market.symbol, position.leverage.leverage_type
+            )),
+        });
+    }
+
+    let new_margin = position.position_value / Decimal::from(leverage);
+    let extra = new_margin - position.margin_used;
+    let available = ms.account_value - ms.total_margin_used;
+    let mut summary = format!(
+        "{} margin: ${:.2} at {}x → ${:.2} at {leverage}x\nMargin usage: {} → {}\n",
+        market.symbol,
+        position.margin_used,
+        position.leverage.value,
+        new_margin,
+        usage(ms.total_margin_used),
+        usage(ms.total_margin_used + extra),
+    );
+    if let (Some(table), Some(tier)) = (&table, tier) {
+        summary.push_str(&format!(
+            "Maintenance margin: ${:.2} (up to {}x tier at {} notional); unchanged by leverage\n",
+            table.maintenance_margin(position.position_value),
+            tier.max_leverage,
+            fmt_usd(position.position_value),
+        ));
+    }
+    let refusal = (extra > available).then(|| {
+        format!(
+            "Lowering {} leverage to {leverage}x needs ${extra:.2} more margin, but only ${:.2} is available \
+             (short ${:.2}). Add margin, reduce the position, or pick a higher leverage.",
+            market.symbol,
+            available.max(Decimal::ZERO),
+            extra - available.max(Decimal::ZERO),
+        )
+    });
+    Ok(LeverageImpact { summary, refusal })
+}
+
+pub async fn set_leverage(
+    state: &ServerState,
+    req: SetLeverageRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+    let is_cross = req.mode.as_deref().unwrap_or("cross") != "isolated";
+    let mode_str = if is_cross { "cross" } else { "isolated" };
+    if req.leverage == 0 {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Leverage must be at least 1.",
+        )]));
+    }
+
+    let impact = leverage_impact(state, &market, req.leverage, is_cross).await?;
+    if req.preview.unwrap_or(false) {
+        let verdict = match &impact.refusal {
+            Some(reason) => format!("Would be rejected: {reason}\n"),
+            None => "The change fits within available margin.\n".to_string(),
+        };
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "## Leverage Preview: {} {}x {mode_str}\n\n{}{verdict}",
+            market.symbol, req.leverage, impact.summary
+        ))]));
+    }
+    if let Some(reason) = impact.refusal {
+        return Ok(CallToolResult::error(vec![Content::text(reason)]));
+    }
+
+    state.check_trading_window()?;
+    state.check_breaker()?;
+    let signer = state.require_signer()?;
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "set {} leverage to {}x {mode_str}.\n\n{}",
+            market.symbol, req.leverage, impact.summary
+        )));
+    }
+
+    let nonce = state.next_nonce();
+    let response = hyperliquid::update_leverage(
+        &state.http,
+        state.chain,
+        signer.as_ref(),
+        market.asset,
+        is_cross,
+        req.leverage,
+        nonce,
+        state.vault_addr(),
+    )
+    .await
+    .map_err(|e| state.action_failed(nonce, "Update leverage failed", e))?;
+    state.record_response(nonce, &response);
+    state.cache.invalidate_user_data().await;
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Set {} leverage to {}x {}: {}\n\n{}",
+        market.symbol,
+        req.leverage,
+        mode_str,
+        format_exchange_response(&response),
+        impact.summary
+    ))]))
+}
+
+/// A coin's leverage setting from `activeAssetData`: the multiplier and
+/// whether it is cross.
+async fn current_leverage(
+    state: &ServerState,
+    user: &str,
+    coin: &str,
+) -> Result<Option<(u32, bool)>, ErrorData> {
+    let data = state
+        .raw_info_request(serde_json::json!({
+            "type": "activeAssetData",
+            "user": user,
+            "coin": coin,
+        }))
+        .await?;
+    Ok(data.get("leverage").and_then(|l| {
+        let value = u32::try_from(l.get("value")?.as_u64()?).ok()?;
+        Some((value, l.get("type")?.as_str()? == "cross"))
+    }))
+}
+
+pub async fn set_leverage_all(
+    state: &ServerState,
+    req: SetLeverageAllRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let is_cross = req.mode.as_deref().unwrap_or("cross") != "isolated";
+    let mode_str = if is_cross { "cross" } else { "isolated" };
+    if req.leverage == 0 {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Leverage must be at least 1.",
+        )]));
+    }
+    state.check_trading_window()?;
+    state.check_breaker()?;
+    let signer = state.require_signer()?;
+    let address = state.query_address()?;
+    let user = format!("{address:#x}");
+
+    let mut markets: Vec<ResolvedMarket> = Vec::new();
+    let selection = match req.coins.filter(|c| !c.is_empty()) {
+        Some(coins) => {
+            for coin in &coins {
+                let market = state.resolve_market(coin, Some(MarketKind::Perp))?;
+                if !markets.iter().any(|m| m.coin == market.coin) {
+                    markets.push(market);
+                }
+            }
+            "the named coins"
+        }
+        None => {
+            let user_state = account::get_cached_clearinghouse(state, address).await?;
+            let orders = state
+                .api
+                .open_orders(address)
+                .await
+                .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
+            let held = user_state
+                .asset_positions
+                .iter()
+                .filter(|p| !p.position.szi.is_zero())
+                .map(|p| p.position.coin.as_str());
+            let mut coins: Vec<&str> = held.chain(orders.iter().map(|o| o.coin.as_str())).collect();
+            coins.sort_unstable();
+            coins.dedup();
+            // Spot orders have no leverage to set.
+            markets.extend(
+                coins
+                    .into_iter()
+                    .filter_map(|c| state.resolve_market(c, Some(MarketKind::Perp)).ok()),
+            );
+            "every perp with a position or open order"
+        }
+    };
+
+    let mut output = format!(
+        "## Set Leverage: {}x {mode_str}\n\nSelected: {selection} ({}).\n\n",
+        req.leverage,
+        markets.len()
+    );
+    if markets.is_empty() {
+        output.push_str("No coins to update.\n");
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    output.push_str("| Coin | Before | Result |\n");
+    output.push_str("|------|--------|--------|\n");
+    let (mut changed, mut unchanged, mut skipped, mut failed) = (0, 0, 0, 0);
+    // One action at a time, so each coin gets its own status.
+    for market in &markets {
+        let current = match current_leverage(state, &user, &market.coin).await {
+            Ok(current) => current,
+            Err(e) => {
+                tracing::warn!(coin = %market.coin, error = %e.message, "activeAssetData failed");
+                None
+            }
+        };
+        let before = current
+            .map(|(value, cross)| format!("{value}x {}", if cross { "cross" } else { "isolated" }))
+            .unwrap_or_else(|| "—".into());
+        let result = if current == Some((req.leverage, is_cross)) {
+            unchanged += 1;
+            "Already set; skipped".to_string()
+        } else {
+            match leverage_impact(state, market, req.leverage, is_cross).await {
+                Err(e) => {
+                    failed += 1;
+                    format!("Error: {}", e.message)
+                }
+                Ok(LeverageImpact {
+                    refusal: Some(reason),
+                    ..
+                }) => {
+                    skipped += 1;
+                    format!("Skipped: {reason}")
+                }
+                Ok(_) if state.dry_run => {
+                    changed += 1;
+                    "Would change".to_string()
+                }
+                Ok(_) => {
+                    let nonce = state.next_nonce();
+                    match hyperliquid::update_leverage(
+                        &state.http,
+                        state.chain,
+                        signer.as_ref(),
+                        market.asset,
+                        is_cross,
+                        req.leverage,
+                        nonce,
+                        state.vault_addr(),
+                    )
+                    .await
+                    {
+                        Ok(response) => {
+                            state.record_response(nonce, &response);
+                            if outcomes_from_response(&response).is_ok() {
+                                changed += 1;
+                            } else {
+                                failed += 1;
+                            }
+                            format_exchange_response(&response)
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            let e = state.action_failed(nonce, "Update leverage failed", e);
+                            e.message.to_string()
+                        }
+                    }
+                }
+            }
+        };
+        output.push_str(&format!(
+            "| {} | {before} | {} |\n",
+            market.symbol,
+            result.replace('|', "\\|")
+        ));
+    }
+    if changed > 0 && !state.dry_run {
+        state.cache.invalidate_user_data().await;
+    }
+
+    let verb = if state.dry_run {
+        "To change"
+    } else {
+        "Changed"
+    };
+    output.push_str(&format!(
+        "\n{verb}: {changed}. Already set: {unchanged}. Skipped: {skipped}. Failed: {failed}.\n"
+    ));
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "set leverage as follows.\n\n{output}"
+        )));
+    }
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+pub async fn close_position(
+    state: &ServerState,
+    req: ClosePositionRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    state.check_trading_window()?;
+    let signer = state.scoped_signer(scope)?;
+    let address = state.scoped_address(scope)?;
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+
+    // In dry-run mode a paper position takes precedence over the real one.
+    let paper_szi = state.paper.as_ref().map_or(Decimal::ZERO, |paper| {
+        paper
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .position(&market.coin)
+    });
+    if !paper_szi.is_zero() {
+        let paper = paper_fill(
+            state,
+            &market,
+            paper_szi.is_sign_negative(),
+            paper_szi.abs(),
+            true,
+            None,
+        )
+        .await?;
+        return Ok(dry_run_result(&format!(
+            "close the paper {} position.{paper}",
+            market.symbol
+        )));
+    }
+
+    let user_state = state
+        .api
+        .clearinghouse_state(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch positions: {e}")))?;
+
+    let position = user_state
+        .asset_positions
+        .iter()
+        .find(|p| p.position.coin == market.coin && !p.position.szi.is_zero())
+        .ok_or_else(|| {
+            mcp_err(&format!(
+                "No open position for {} on the {}",
+                market.symbol,
+                state.describe_scope(scope)
+            ))
+        })?;
+
+    let szi = position.position.szi;
+    // Sell to close long, buy to close short
+    let is_buy = szi.is_sign_negative();
+    let size = szi.abs();
+
+    let pricing = market_pricing(state, &market, is_buy, size).await?;
+    let limit_px = pricing.limit_px;
+
+    let mut order = OrderRequest {
+        asset: market.asset,
+        is_buy,
+        reduce_only: true,
+        limit_px,
+        sz: size,
+        cloid: Cloid::ZERO,
+        order_type: OrderTypePlacement::Limit {
+            tif: TimeInForce::Ioc,
+        },
+    };
+    let band = PriceBand::new(state, "mid", pricing.mid);
+    if let Err(e) = normalize_order(&market, &mut order, Some(band)) {
+        return Ok(CallToolResult::error(vec![Content::text(e)]));
+    }
+
+    if state.dry_run {
+        let side_str = if is_buy { "buy" } else { "sell" };
+        return Ok(dry_run_result(&format!(
+            "close the {} position on the {}: reduce-only IOC {side_str} {} @ ${}.\n\n{}",
+            market.symbol,
+            state.describe_scope(scope),
+            order.sz,
+            order.limit_px,
+            pricing.describe()
+        )));
+    }
+
+    let cloids = issue_cloids(
+        state,
+        std::slice::from_mut(&mut order),
+        &format!("close_position: {}", market.symbol),
+    );
+    let nonce = state.next_nonce();
+    let result = hyperliquid::place_order_with_builder(
+        state.api.as_ref(),
+        state.chain,
+        signer.as_ref(),
+        vec![order],
+        OrderGrouping::Na,
+        Some(state.builder_info()),
+        nonce,
+        state.scoped_vault(scope),
+    )
+    .await;
+    state.cloids.record_response(&cloids, &result);
+    let response = result.map_err(|e| state.action_failed(nonce, "Close position failed", e))?;
+    state.record_response(nonce, &response);
+
+    state.cache.invalidate_user_data().await;
+
+    let mut output = format!(
+        "## Close {} Position\n\nAccount: {}\n\n{}Result: {}",
+        market.symbol,
+        state.describe_scope(scope),
+        pricing.describe(),
+        format_exchange_response(&response)
+    );
+
+    maybe_append_builder_nudge(state, &mut output).await;
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+/// A reduce-only IOC order closing `szi` of `market`, limited
+/// `CLOSE_ALL_SLIPPAGE` beyond `mid`. The caller issues its cloid.
+pub(crate) fn close_order(
+    state: &ServerState,
+    market: &ResolvedMarket,
+    szi: Decimal,
+    mid: Decimal,
+) -> Result<OrderRequest, String> {
+    let is_buy = szi.is_sign_negative();
+    let limit_px = if is_buy {
+        mid * (Decimal::ONE + CLOSE_ALL_SLIPPAGE)
+    } else {
+        mid * (Decimal::ONE - CLOSE_ALL_SLIPPAGE)
+    };
+    let mut order = OrderRequest {
+        asset: market.asset,
+        is_buy,
+        reduce_only: true,
+        limit_px,
+        sz: szi.abs(),
+        cloid: Cloid::ZERO,
+        order_type: OrderTypePlacement::Limit {
+            tif: TimeInForce::Ioc,
+        },
+    };
+    normalize_order(market, &mut order, Some(PriceBand::new(state, "mid", mid)))?;
+    Ok(order)
+}
+
+/// A position picked by `close_positions` and how closing it went.
+struct CloseTarget {
+    market: ResolvedMarket,
+    szi: Decimal,
+    pnl: Decimal,
+    remaining: Decimal,
+    attempts: usize,
+    /// The last order's outcome, or why none was sent.
+    last: String,
+    /// Set once another attempt can't help, e.g. the order was refused.
+    given_up: bool,
+}
+
+impl CloseTarget {
+    fn describe(&self) -> String {
+        let tries = if self.attempts > 1 {
+            format!(" after {} attempts", self.attempts)
+        } else {
+            String::new()
+        };
+        if self.remaining.is_zero() {
+            format!("Closed{tries}: {}", self.last)
+        } else if self.attempts == 0 {
+            format!("Not sent: {}", self.last)
+        } else if self.remaining != self.szi {
+            format!(
+                "Partially closed{tries}, {} left. Last: {}",
+                self.remaining.abs(),
+                self.last
+            )
+        } else {
+            format!("Not closed{tries}: {}", self.last)
+        }
+    }
+}
+
+/// Close the positions on several coins, picked by name or by the sign of
+/// their unrealized PnL, with reduce-only IOC orders. Positions still open
+/// afterwards, e.g. from a partial fill, get a fresh order at the new mid,
+/// up to `MAX_CLOSE_ATTEMPTS` in all.
+pub async fn close_positions(
+    state: &ServerState,
+    req: ClosePositionsRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let scope = state.account_scope(req.account_scope.as_deref())?;
+    state.check_trading_window()?;
+    let signer = state.scoped_signer(scope)?;
+    let address = state.scoped_address(scope)?;
+
+    let coins = req.coins.unwrap_or_default();
+    let losing = req.all_losing.unwrap_or(false);
+    let winning = req.all_winning.unwrap_or(false);
+    if [!coins.is_empty(), losing, winning]
+        .iter()
+        .filter(|s| **s)
+        .count()
+        != 1
+    {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Provide exactly one of coins, all_losing: true or all_winning: true.",
+        )]));
+    }
+    let mut named: Vec<ResolvedMarket> = Vec::with_capacity(coins.len());
+    for coin in &coins {
+        let market = state.resolve_market(coin, Some(MarketKind::Perp))?;
+        if !named.iter().any(|m| m.coin == market.coin) {
+            named.push(market);
+        }
+    }
+
+    let user_state = state
+        .api
+        .clearinghouse_state(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch positions: {e}")))?;
+    let positions: HashMap<&str, (Decimal, Decimal)> = user_state
+        .asset_positions
+        .iter()
+        .filter(|p| !p.position.szi.is_zero())
+        .map(|p| {
+            (
+                p.position.coin.as_str(),
+                (p.position.szi, p.position.unrealized_pnl),
+            )
+        })
+        .collect();
+
+    let selection;
+    let mut missing = Vec::new();
+    let mut targets = Vec::new();
+    let mut target = |market: ResolvedMarket, (szi, pnl): (Decimal, Decimal)| {
+        targets.push(CloseTarget {
+            market,
+            szi,
+            pnl,
+            remaining: szi,
+            attempts: 0,
+            last: String::new(),
+            given_up: false,
+        })
+    };
+    if named.is_empty() {
+        selection = if losing {
+            "positions with negative unrealized PnL"
+        } else {
+            "positions with positive unrealized PnL"
+        };
+        for ap in &user_state.asset_positions {
+            let p = &ap.position;
+            let picked = if losing {
+                p.unrealized_pnl.is_sign_negative() && !p.unrealized_pnl.is_zero()
+            } else {
+                p.unrealized_pnl.is_sign_positive() && !p.unrealized_pnl.is_zero()
+            };
+            if p.szi.is_zero() || !picked {
+                continue;
+            }
+            match state.resolve_market(&p.coin, Some(MarketKind::Perp)) {
+                Ok(market) => target(market, (p.szi, p.unrealized_pnl)),
+                Err(e) => tracing::warn!(coin = %p.coin, error = %e.message, "Skipping close"),
+            }
+        }
+    } else {
+        selection = "the named coins";
+        for market in named {
+            match positions.get(market.coin.as_str()) {
+                Some(&position) => target(market, position),
+                None => missing.push(market.symbol),
+            }
+        }
+    }
+
+    let mut output = format!(
+        "## Close Positions\n\nAccount: {}\n\nSelected: {selection}.\n\n",
+        state.describe_scope(scope)
+    );
+    if !missing.is_empty() {
+        output.push_str(&format!(
+            "No open position on {}; nothing to close there.\n\n",
+            missing.join(", ")
+        ));
+    }
+    if targets.is_empty() {
+        output.push_str("No positions to close.\n");
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    let describe_position = |t: &CloseTarget| {
+        format!(
+            "{} {}",
+            if t.szi.is_sign_negative() {
+                "Short"
+            } else {
+                "Long"
+            },
+            t.szi.abs()
+        )
+    };
+    if state.dry_run {
+        let lines: Vec<String> = targets
+            .iter()
+            .map(|t| {
+                format!(
+                    "- {} {} ({} unrealized)",
+                    t.market.symbol,
+                    describe_position(t),
+                    fmt_usd(t.pnl)
+                )
+            })
+            .collect();
+        return Ok(dry_run_result(&format!(
+            "close {} positions on the {} with reduce-only IOC orders:\n\n{}",
+            targets.len(),
+            state.describe_scope(scope),
+            lines.join("\n")
+        )));
+    }
+
+    for attempt in 1..=MAX_CLOSE_ATTEMPTS {
+        let open: Vec<usize> = (0..targets.len())
+            .filter(|&i| !targets[i].remaining.is_zero() && !targets[i].given_up)
+            .collect();
+        if open.is_empty() {
+            break;
+        }
+        let mids = state
+            .api
+            .all_mids()
+            .await
+            .map_err(|e| mcp_err(&format!("Failed to fetch prices: {e}")))?;
+        let mut sent = Vec::with_capacity(open.len());
+        let mut placed = Vec::with_capacity(open.len());
+        for i in open {
+            let t = &mut targets[i];
+            let Some(&mid) = mids.get(&t.market.coin) else {
+                t.last = "no mid price".to_string();
+                t.given_up = true;
+                continue;
+            };
+            match close_order(state, &t.market, t.remaining, mid) {
+                Ok(mut order) => {
+                    order.cloid = state
+                        .cloids
+                        .issue_new(format!("close_positions: {}", t.market.symbol));
+                    t.attempts = attempt;
+                    placed.push((t.market.clone(), order));
+                    sent.push(i);
+                }
+                Err(e) => {
+                    t.last = e;
+                    t.given_up = true;
+                }
+            }
+        }
+        if placed.is_empty() {
+            break;
+        }
+
+        let cloids: Vec<Cloid> = placed.iter().map(|(_, o)| o.cloid).collect();
+        let nonce = state.next_nonce();
+        let result = hyperliquid::place_order_with_builder(
+            state.api.as_ref(),
+            state.chain,
+            signer.as_ref(),
+            placed.iter().map(|(_, o)| o.clone()).collect(),
+            OrderGrouping::Na,
+            Some(state.builder_info()),
+            nonce,
+            state.scoped_vault(scope),
+        )
+        .await;
+        state.cloids.record_response(&cloids, &result);
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                let e = state.action_failed(nonce, "Close failed", e);
+                for &i in &sent {
+                    targets[i].last = e.message.to_string();
+                }
+                break;
+            }
+        };
+        state.record_response(nonce, &response);
+        match placed_order_outcomes(&response, &placed) {
+            Ok(outcomes) => {
+                for (&i, outcome) in sent.iter().zip(outcomes) {
+                    targets[i].last = outcome.describe();
+                }
+            }
+            Err(msg) => {
+                for &i in &sent {
+                    targets[i].last = format!("Rejected: {msg}");
+                }
+                break;
+            }
+        }
+
+        // IOC orders are done once the response arrives; the pause lets the
+        // account state catch up before it is re-read.
+        tokio::time::sleep(CLOSE_SETTLE_DELAY).await;
+        let after = match state.api.clearinghouse_state(address).await {
+            Ok(after) => after,
+            Err(e) => {
+                output.push_str(&format!(
+                    "Could not re-check positions after attempt {attempt}: {e}\n\n"
+                ));
+                break;
+            }
+        };
+        for &i in &sent {
+            let t = &mut targets[i];
+            t.remaining = after
+                .asset_positions
+                .iter()
+                .find(|p| p.position.coin == t.market.coin)
+                .map(|p| p.position.szi)
+                .unwrap_or_default();
+        }
+    }
+    state.cache.invalidate_user_data().await;
+
+    output.push_str("| Coin | Position | Unrealized PnL | Result |\n");
+    output.push_str("|------|----------|----------------|--------|\n");
+    for t in &targets {
+        output.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            t.market.symbol,
+            describe_position(t),
+            fmt_usd(t.pnl),
+            t.describe()
+        ));
+    }
+    let closed = targets.iter().filter(|t| t.remaining.is_zero()).count();
+    output.push_str(&format!(
+        "\nClosed {closed} of {} positions.\n",
+        targets.len()
+    ));
+    let still_open: Vec<&str> = targets
+        .iter()
+        .filter(|t| !t.remaining.is_zero())
+        .map(|t| t.market.symbol.as_str())
+        .collect();
+    if !still_open.is_empty() {
+        output.push_str(&format!(
+            "Still open: {}. Check get_positions before trying again.\n",
+            still_open.join(", ")
+        ));
+    }
+
+    maybe_append_builder_nudge(state, &mut output).await;
+
+    let content = vec![Content::text(output)];
+    Ok(if closed == 0 {
+        CallToolResult::error(content)
+    } else {
+        CallToolResult::success(content)
+    })
+}
+
+/// Beta of `long` to `short`: the slope of the long coin's hourly returns on
+/// the short coin's over the last 7 days. Shorting `beta` times the long
+/// notional leaves the pair flat to moves in the short coin.
+async fn pair_beta(
+    state: &ServerState,
+    long: &ResolvedMarket,
+    short: &ResolvedMarket,
+) -> Result<Result<f64, String>, ErrorData> {
+    use rust_decimal::prelude::ToPrimitive;
+
+    let (long_candles, short_candles) = tokio::try_join!(
+        get_cached_candles(state, &long.coin, "1h", PAIR_BETA_CANDLES),
+        get_cached_candles(state, &short.coin, "1h", PAIR_BETA_CANDLES),
+    )?;
+    let short_closes: HashMap<u64, f64> = short_candles
+        .iter()
+        .filter_map(|c| Some((c.open_time, c.close.to_f64()?)))
+        .collect();
+    let closes: Vec<(f64, f64)> = long_candles
+        .iter()
+        .filter_map(|c| Some((c.close.to_f64()?, *short_closes.get(&c.open_time)?)))
+        .collect();
+    let returns: Vec<(f64, f64)> = closes
+        .windows(2)
+        .filter(|w| w[0].0 > 0.0 && w[0].1 > 0.0)
+        .map(|w| (w[1].0 / w[0].0 - 1.0, w[1].1 / w[0].1 - 1.0))
+        .collect();
+    if returns.len() < 24 {
+        return Ok(Err(format!(
+            "Not enough overlapping hourly candles for {} and {} to estimate beta. Use weighting \"equal\".",
+            long.symbol, short.symbol
+        )));
+    }
+    let n = returns.len() as f64;
+    let mean_long = returns.iter().map(|r| r.0).sum::<f64>() / n;
+    let mean_short = returns.iter().map(|r| r.1).sum::<f64>() / n;
+    let cov = returns
+        .iter()
+        .map(|r| (r.0 - mean_long) * (r.1 - mean_short))
+        .sum::<f64>();
+    let var = returns
+        .iter()
+        .map(|r| (r.1 - mean_short).powi(2))
+        .sum::<f64>();
+    let beta = if var > 0.0 { cov / var } else { 0.0 };
+    if beta <= 0.0 {
+        return Ok(Err(format!(
+            "{} has a beta of {beta:.2} to {} over 7 days, so shorting {} does not hedge it. Use weighting \"equal\".",
+            long.symbol, short.symbol, short.symbol
+        )));
+    }
+    Ok(Ok(beta))
+}
+
+pub async fn place_pair_trade(
+    state: &ServerState,
+    req: PlacePairTradeRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let address = state.query_address()?;
+    state.check_trading_window()?;
+    state.check_breaker()?;
     let signer = state.require_signer()?;
+    let long = state.resolve_market(&req.long_coin, Some(MarketKind::Perp))?;
+    let short = state.resolve_market(&req.short_coin, Some(MarketKind::Perp))?;
+    if long.coin == short.coin {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "long_coin and short_coin must be different markets.",
+        )]));
+    }
+    if !(req.notional_usd.is_finite() && req.notional_usd > 0.0) {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "notional_usd must be a positive USD amount.",
+        )]));
+    }
+    let notional = to_decimal(req.notional_usd)?;
 
-    let orders = state
-        .client
-        .open_orders(address, None)
-        .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
+    let (long_share, weighting) = match req.weighting.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("equal") => (Decimal::new(5, 1), "equal notional (50/50)".to_string()),
+        Some("beta") => match pair_beta(state, &long, &short).await? {
+            Ok(beta) => (
+                to_decimal(1.0 / (1.0 + beta))?,
+                format!(
+                    "beta-weighted: {} has a beta of {beta:.2} to {} over 7 days of hourly returns, \
+                     so the short leg is {beta:.2}× the long",
+                    long.symbol, short.symbol
+                ),
+            ),
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        },
+        Some(other) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid weighting '{other}'. Use \"equal\" or \"beta\"."
+            ))]));
+        }
+    };
 
-    let to_cancel: Vec<_> = if let Some(ref coin) = req.coin {
-        orders
+    let limited = [&long, &short]
+        .iter()
+        .any(|m| state.position_notional_limit(&m.coin).is_some());
+    let positions: HashMap<String, Decimal> = if limited {
+        account::get_cached_clearinghouse(state, state.query_address()?)
+            .await?
+            .asset_positions
             .iter()
-            .filter(|o| o.coin.eq_ignore_ascii_case(coin))
+            .map(|p| (p.position.coin.clone(), p.position.szi))
             .collect()
     } else {
-        orders.iter().collect()
+        HashMap::new()
     };
 
-    if to_cancel.is_empty() {
-        return Ok(CallToolResult::success(vec![Content::text(
-            "No open orders to cancel.",
-        )]));
+    let legs = [
+        ("Long", &long, true, notional * long_share),
+        (
+            "Short",
+            &short,
+            false,
+            notional * (Decimal::ONE - long_share),
+        ),
+    ];
+    let mut orders = Vec::with_capacity(2);
+    let mut mids = Vec::with_capacity(2);
+    let mut lines = Vec::with_capacity(2);
+    for (label, market, is_buy, leg_notional) in legs {
+        let (_, mid) = reference_price(state, market, "mid").await?;
+        let size = leg_notional / mid;
+        let pricing = market_pricing(state, market, is_buy, size).await?;
+        if !req.force.unwrap_or(false)
+            && let Some(refusal) = impact_guard(state, market, &pricing).await?
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "{label} leg ({}): {refusal}",
+                market.symbol
+            ))]));
+        }
+        let mut order = OrderRequest {
+            asset: market.asset,
+            is_buy,
+            reduce_only: false,
+            limit_px: pricing.limit_px,
+            sz: size,
+            cloid: Cloid::ZERO,
+            order_type: OrderTypePlacement::Limit {
+                tif: TimeInForce::Ioc,
+            },
+        };
+        let band = PriceBand::new(state, "mid", mid);
+        if let Err(e) = normalize_order(market, &mut order, Some(band)) {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "{label} leg ({}): {e}",
+                market.symbol
+            ))]));
+        }
+        let szi = positions.get(&market.coin).copied().unwrap_or_default();
+        if let Some(refusal) = position_limit_refusal(state, market, szi, order.sz, is_buy, mid) {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "{label} leg ({}): {refusal}",
+                market.symbol
+            ))]));
+        }
+        lines.push(format!(
+            "- {label} {} {}: {} at mid {} (IOC, limit {})",
+            order.sz,
+            market.symbol,
+            fmt_usd(order.sz * mid),
+            fmt_price(mid),
+            fmt_price(order.limit_px)
+        ));
+        orders.push(order);
+        mids.push(mid);
     }
+    let planned_ratio = mids[0] / mids[1];
+    let summary = format!(
+        "Pair: long {} / short {}, {} total, {weighting}.\n\n{}\n\nPlanned entry ratio: {} ({} / {} mids).\n",
+        long.symbol,
+        short.symbol,
+        fmt_usd(notional),
+        lines.join("\n"),
+        round_sig(planned_ratio, 6),
+        long.symbol,
+        short.symbol
+    );
 
-    let cancels: Vec<Cancel> = to_cancel
+    if state.dry_run {
+        let mut paper = String::new();
+        for ((_, market, _, _), order) in legs.iter().zip(&orders) {
+            paper.push_str(
+                &paper_fill(
+                    state,
+                    market,
+                    order.is_buy,
+                    order.sz,
+                    false,
+                    Some(order.limit_px),
+                )
+                .await?,
+            );
+        }
+        return Ok(dry_run_result(&format!(
+            "place a pair trade.\n\n{summary}{paper}"
+        )));
+    }
+
+    // Both legs go in one action, so neither waits on the other's round trip.
+    let cloids = issue_cloids(state, &mut orders, "place_pair_trade");
+    let placed: Vec<_> = [long.clone(), short.clone()]
+        .into_iter()
+        .zip(orders.iter().cloned())
+        .collect();
+    let nonce = state.next_nonce();
+    let result = hyperliquid::place_order_with_builder(
+        state.api.as_ref(),
+        state.chain,
+        signer.as_ref(),
+        orders,
+        OrderGrouping::Na,
+        Some(state.builder_info()),
+        nonce,
+        state.vault_addr(),
+    )
+    .await;
+    state.cloids.record_response(&cloids, &result);
+    let response =
+        result.map_err(|e| state.action_failed(nonce, "Pair trade placement failed", e))?;
+    state.record_response(nonce, &response);
+    state.cache.invalidate_user_data().await;
+
+    let outcomes = match placed_order_outcomes(&response, &placed) {
+        Ok(outcomes) if outcomes.len() == 2 => outcomes,
+        Ok(_) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "## Pair Trade Result\n\n{summary}\nThe exchange did not return a status for both legs; \
+                 check get_positions before retrying."
+            ))]));
+        }
+        Err(msg) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "## Pair Trade Result\n\n{summary}\nRejected, nothing was placed: {msg}"
+            ))]));
+        }
+    };
+    let filled = |o: &PlacedOrderOutcome| o.filled_size.filter(|sz| !sz.is_zero()).zip(o.avg_price);
+
+    let mut output = String::from("## Pair Trade Result\n\n");
+    match (filled(&outcomes[0]), filled(&outcomes[1])) {
+        (Some(_), Some(_)) => {}
+        (None, None) => {
+            output.push_str(&format!(
+                "{summary}\nNeither leg filled; no position was opened.\n\n- Long: {}\n- Short: {}\n",
+                outcomes[0].describe(),
+                outcomes[1].describe()
+            ));
+            return Ok(CallToolResult::error(vec![Content::text(output)]));
+        }
+        (one, _) => {
+            let (done, done_market, failed, failed_market, failed_is_buy) = if one.is_some() {
+                (&outcomes[0], &long, &outcomes[1], &short, false)
+            } else {
+                (&outcomes[1], &short, &outcomes[0], &long, true)
+            };
+            let (sz, px) = filled(done).unwrap_or_default();
+            output.push_str(&format!(
+                "**⚠ UNHEDGED: only the {} leg filled.** You now hold {} {} ({} @ {}) with no offsetting {} position. \
+                 The {} leg failed: {}.\n\n\
+                 - To unwind, call `close_position` with coin \"{}\".\n\
+                 - To complete the pair instead, call `place_order` with coin \"{}\", side \"{}\", size \"{}\" and order_type \"market\".\n\n",
+                if failed_is_buy { "short" } else { "long" },
+                sz,
+                done_market.symbol,
+                if failed_is_buy { "short" } else { "long" },
+                fmt_price(round_sig(px, 6)),
+                failed_market.symbol,
+                if failed_is_buy { "long" } else { "short" },
+                failed.describe(),
+                done_market.symbol,
+                failed_market.symbol,
+                if failed_is_buy { "buy" } else { "sell" },
+                failed.size.unwrap_or_default(),
+            ));
+        }
+    }
+    output.push_str(&summary);
+    output.push_str(&format!(
+        "\n- Long {}: {}\n- Short {}: {}\n",
+        long.symbol,
+        outcomes[0].describe(),
+        short.symbol,
+        outcomes[1].describe()
+    ));
+    if let (Some((long_sz, long_px)), Some((short_sz, short_px))) =
+        (filled(&outcomes[0]), filled(&outcomes[1]))
+    {
+        let (long_ntl, short_ntl) = (long_sz * long_px, short_sz * short_px);
+        output.push_str(&format!(
+            "\nAchieved entry ratio: {} (planned {}). Filled notional: long {}, short {}.\n",
+            round_sig(long_px / short_px, 6),
+            round_sig(planned_ratio, 6),
+            fmt_usd(long_ntl),
+            fmt_usd(short_ntl)
+        ));
+        let target_short = long_ntl * (Decimal::ONE - long_share) / long_share;
+        if !target_short.is_zero()
+            && ((short_ntl - target_short) / target_short).abs() > PAIR_IMBALANCE_WARN
+        {
+            output.push_str(&format!(
+                "**Warning:** the legs are out of balance: the short leg is {} against {} targeted \
+                 for this long fill, likely a partial fill. Top up or trim one leg with place_order.\n",
+                fmt_usd(short_ntl),
+                fmt_usd(target_short)
+            ));
+        }
+    }
+
+    maybe_append_builder_nudge(state, &mut output).await;
+
+    order_result(output, &Ok(outcomes))
+}
+
+/// Cancel every resting order on a coin, then close its position. Orders are
+/// cancelled first so a resting order can't re-open the position afterwards.
+pub async fn flatten_coin(
+    state: &ServerState,
+    req: FlattenCoinRequest,
+) -> Result<CallToolResult, ErrorData> {
+    state.check_trading_window()?;
+    let signer = state.require_signer()?;
+    let address = state.query_address()?;
+    let market = state.resolve_market(&req.coin, Some(MarketKind::Perp))?;
+
+    let position_size = |user_state: &hypersdk::hypercore::ClearinghouseState| {
+        user_state
+            .asset_positions
+            .iter()
+            .find(|p| p.position.coin == market.coin)
+            .map(|p| p.position.szi)
+            .unwrap_or_default()
+    };
+
+    let orders = state
+        .api
+        .open_orders(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
+    let cancels: Vec<Cancel> = orders
         .iter()
-        .filter_map(|o| {
-            state
-                .asset_map
-                .get(&o.coin)
-                .map(|&asset| Cancel { asset, oid: o.oid })
+        .filter(|o| o.coin == market.coin)
+        .map(|o| Cancel {
+            asset: market.asset,
+            oid: o.oid,
         })
         .collect();
 
-    let cancel_count = cancels.len();
-    let nonce = state.next_nonce();
-    let response = state
-        .client
-        .cancel(
-            signer.as_ref(),
-            BatchCancel { cancels },
-            nonce,
-            state.vault_addr(),
-            None,
-        )
+    let before = state
+        .api
+        .clearinghouse_state(address)
         .await
-        .map_err(|e| mcp_err(&format!("Cancel all failed: {e}")))?;
+        .map_err(|e| mcp_err(&format!("Failed to fetch positions: {e}")))?;
+    let szi = position_size(&before);
+
+    if state.dry_run {
+        let close = if szi.is_zero() {
+            "no position to close".to_string()
+        } else {
+            match req.limit_price {
+                Some(price) => format!("close {szi} with a reduce-only limit at ${price}"),
+                None => format!("close {szi} at market"),
+            }
+        };
+        return Ok(dry_run_result(&format!(
+            "cancel {} {} orders, then {close}.",
+            cancels.len(),
+            market.symbol
+        )));
+    }
+
+    let mut output = format!("## Flatten {}\n\n### 1. Cancel orders\n", market.symbol);
+    if cancels.is_empty() {
+        output.push_str("No open orders.\n");
+    } else {
+        let cancel_count = cancels.len();
+        let nonce = state.next_nonce();
+        let response = state
+            .client
+            .cancel(
+                signer.as_ref(),
+                BatchCancel { cancels },
+                nonce,
+                state.vault_addr(),
+                None,
+            )
+            .await
+            .map_err(|e| state.action_failed(nonce, "Cancel failed", e))?;
+        output.push_str(&format!(
+            "Cancelled {cancel_count} orders: {}\n",
+            format_order_response(&response)
+        ));
+    }
+
+    output.push_str("\n### 2. Close position\n");
+    output.push_str(&format!("Position before: {szi}\n"));
+    if szi.is_zero() {
+        output.push_str("No open position.\n");
+        state.cache.invalidate_user_data().await;
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    let is_buy = szi.is_sign_negative();
+    let size = szi.abs();
+    let (limit_px, tif, band) = match req.limit_price {
+        Some(price) => (
+            to_decimal(price)?,
+            TimeInForce::Gtc,
+            price_band(state, &market).await,
+        ),
+        None => {
+            let pricing = market_pricing(state, &market, is_buy, size).await?;
+            output.push_str(&pricing.describe());
+            let band = PriceBand::new(state, "mid", pricing.mid);
+            (pricing.limit_px, TimeInForce::Ioc, Some(band))
+        }
+    };
+
+    let mut order = OrderRequest {
+        asset: market.asset,
+        is_buy,
+        reduce_only: true,
+        limit_px,
+        sz: size,
+        cloid: Cloid::ZERO,
+        order_type: OrderTypePlacement::Limit { tif },
+    };
+    match normalize_order(&market, &mut order, band) {
+        Ok(rounding) => output.push_str(&rounding),
+        Err(e) => {
+            output.push_str(&format!("Close not sent: {e}\n"));
+            state.cache.invalidate_user_data().await;
+            return Ok(CallToolResult::success(vec![Content::text(output)]));
+        }
+    }
+    order.cloid = state
+        .cloids
+        .issue_new(format!("flatten_coin: {}", market.symbol));
+    let cloid = order.cloid;
+    let nonce = state.next_nonce();
+    let result = hyperliquid::place_order_with_builder(
+        state.api.as_ref(),
+        state.chain,
+        signer.as_ref(),
+        vec![order],
+        OrderGrouping::Na,
+        Some(state.builder_info()),
+        nonce,
+        state.vault_addr(),
+    )
+    .await;
+    state.cloids.record_response(&[cloid], &result);
+    let response = result.map_err(|e| state.action_failed(nonce, "Close position failed", e))?;
+    state.record_response(nonce, &response);
+    output.push_str(&format!(
+        "Result: {}\n",
+        format_exchange_response(&response)
+    ));
 
     state.cache.invalidate_user_data().await;
+    match state.api.clearinghouse_state(address).await {
+        Ok(after) => output.push_str(&format!("Position after: {}\n", position_size(&after))),
+        Err(e) => output.push_str(&format!("Could not fetch position after close: {e}\n")),
+    }
 
-    Ok(CallToolResult::success(vec![Content::text(format!(
-        "Cancelled {cancel_count} orders: {}",
-        format_order_response(&response)
-    ))]))
+    maybe_append_builder_nudge(state, &mut output).await;
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn modify_order(
+pub async fn schedule_cancel(
     state: &ServerState,
-    req: ModifyOrderRequest,
+    req: ScheduleCancelRequest,
 ) -> Result<CallToolResult, ErrorData> {
     let signer = state.require_signer()?;
-    let asset = state.resolve_asset(&req.coin)?;
 
-    let is_buy = match req.side.to_lowercase().as_str() {
-        "buy" | "b" | "long" => true,
-        "sell" | "s" | "short" => false,
-        _ => {
-            return Ok(CallToolResult::error(vec![Content::text(
-                "Invalid side. Use \"buy\" or \"sell\".",
-            )]));
-        }
-    };
+    if req.seconds_from_now == 0 {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "seconds_from_now must be greater than 0.",
+        )]));
+    }
 
-    let modify = Modify {
-        oid: either::Either::Left(req.order_id),
-        order: OrderRequest {
-            asset,
-            is_buy,
-            reduce_only: false,
-            limit_px: to_decimal(req.new_price)?,
-            sz: to_decimal(req.new_size)?,
-            cloid: make_cloid(),
-            order_type: OrderTypePlacement::Limit {
-                tif: TimeInForce::Gtc,
-            },
-        },
-    };
+    let when = Utc::now()
+        + Duration::seconds(
+            i64::try_from(req.seconds_from_now)
+                .map_err(|_| mcp_err("seconds_from_now too large"))?,
+        );
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "schedule cancellation of all open orders at {}.",
+            fmt_instant(when)
+        )));
+    }
 
     let nonce = state.next_nonce();
-    let response = state
+    state
         .client
-        .modify(
-            signer.as_ref(),
-            BatchModify {
-                modifies: vec![modify],
-            },
-            nonce,
-            state.vault_addr(),
-            None,
-        )
+        .schedule_cancel(signer.as_ref(), nonce, when, state.vault_addr(), None)
         .await
-        .map_err(|e| mcp_err(&format!("Modify failed: {e}")))?;
+        .map_err(|e| state.action_failed(nonce, "Schedule cancel failed", e))?;
+    *state
+        .scheduled_cancel
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(when);
+    state.persist_state();
 
-    state.cache.invalidate_user_data().await;
+    let output = format!(
+        "Scheduled cancellation of all open orders at {} ({} seconds from now).",
+        fmt_instant(when),
+        req.seconds_from_now,
+    );
 
-    Ok(CallToolResult::success(vec![Content::text(format!(
-        "Modify order {}: {}",
-        req.order_id,
-        format_order_response(&response)
-    ))]))
+    Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn set_leverage(
+pub async fn check_spot_readiness(
     state: &ServerState,
-    req: SetLeverageRequest,
+    req: CheckSpotReadinessRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let signer = state.require_signer()?;
-    let asset = state.resolve_asset(&req.coin)?;
-    let is_cross = req.mode.as_deref().unwrap_or("cross") != "isolated";
+    let address = state.query_address()?;
+    let is_buy = match req.side.to_lowercase().as_str() {
+        "buy" | "b" => true,
+        "sell" | "s" => false,
+        _ => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Invalid side. Use \"buy\" or \"sell\".",
+            )]));
+        }
+    };
+    let size = to_decimal(req.size)?;
 
-    let nonce = state.next_nonce();
-    let response = hyperliquid::update_leverage(
-        &state.http,
-        state.chain,
-        signer.as_ref(),
-        asset,
-        is_cross,
-        req.leverage,
-        nonce,
-        state.vault_addr(),
-    )
-    .await
-    .map_err(|e| mcp_err(&format!("Update leverage failed: {e}")))?;
+    let mut output = format!(
+        "## Spot Readiness: {} {size} {}\n\n",
+        if is_buy { "Buy" } else { "Sell" },
+        req.coin.trim()
+    );
+    output.push_str("| Check | Result | Details |\n");
+    output.push_str("|-------|--------|---------|\n");
 
-    let mode_str = if is_cross { "cross" } else { "isolated" };
-    Ok(CallToolResult::success(vec![Content::text(format!(
-        "Set {} leverage to {}x {}: {}",
-        req.coin,
-        req.leverage,
-        mode_str,
-        format_exchange_response(&response)
-    ))]))
+    let market = match state.resolve_market(&req.coin, Some(MarketKind::Spot)) {
+        Ok(market) => market,
+        Err(e) => {
+            output.push_str(&format!("| Pair | FAIL | {} |\n", e.message));
+            return Ok(CallToolResult::success(vec![Content::text(output)]));
+        }
+    };
+    output.push_str(&format!(
+        "| Pair | PASS | {} (`{}`) |\n",
+        market.symbol, market.coin
+    ));
+
+    let (px, px_source) = match req.price {
+        Some(price) => (to_decimal(price)?, "limit price"),
+        None => {
+            let mids = state
+                .api
+                .all_mids()
+                .await
+                .map_err(|e| mcp_err(&format!("Failed to fetch prices: {e}")))?;
+            let Some(mid) = mids.get(&market.coin).copied() else {
+                output.push_str("| Price | FAIL | No mid price; pass a price to check against |\n");
+                return Ok(CallToolResult::success(vec![Content::text(output)]));
+            };
+            (mid, "mid")
+        }
+    };
+
+    let checks = spot_order_checks(state, address, &market, is_buy, size, px).await?;
+    let ready = checks.iter().all(|c| c.outcome.is_ok());
+    for check in checks {
+        let (result, details) = match check.outcome {
+            Ok(details) => ("PASS", details),
+            Err(fix) => ("FAIL", fix),
+        };
+        output.push_str(&format!("| {} | {result} | {details} |\n", check.name));
+    }
+
+    output.push_str(&format!("\nChecked at ${px} ({px_source}). "));
+    if ready {
+        output.push_str("Ready: place_order would accept this spot order.");
+    } else {
+        output.push_str("Not ready: fix the failing checks before placing the order.");
+    }
+    Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn close_position(
+struct DustBalance {
+    coin: String,
+    amount: Decimal,
+    value: Decimal,
+    /// Sell order for the balance, or why it can't be sold.
+    order: Result<OrderRequest, String>,
+}
+
+pub async fn consolidate_dust(
     state: &ServerState,
-    req: ClosePositionRequest,
+    req: ConsolidateDustRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    let signer = state.require_signer()?;
     let address = state.query_address()?;
-    let asset = state.resolve_asset(&req.coin)?;
+    let threshold = to_decimal(req.threshold_usd.unwrap_or(1.0))?;
+    let confirm = req.confirm.unwrap_or(false);
 
-    let user_state = state
+    let balances = state
+        .api
+        .user_balances(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch spot balances: {e}")))?;
+    let markets = state
         .client
-        .clearinghouse_state(address, None)
+        .spot()
         .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch positions: {e}")))?;
+        .map_err(|e| mcp_err(&format!("Failed to fetch spot markets: {e}")))?;
+    let mids = state
+        .api
+        .all_mids()
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch prices: {e}")))?;
 
-    let position = user_state
-        .asset_positions
+    let usdc_before = balances
         .iter()
-        .find(|p| p.position.coin.eq_ignore_ascii_case(&req.coin) && !p.position.szi.is_zero())
-        .ok_or_else(|| mcp_err(&format!("No open position for {}", req.coin)))?;
+        .find(|b| b.coin == "USDC")
+        .map(|b| b.total)
+        .unwrap_or_default();
 
-    let szi = position.position.szi;
-    // Sell to close long, buy to close short
-    let is_buy = szi.is_sign_negative();
-    let size = szi.abs();
+    let mut dust = Vec::new();
+    for b in &balances {
+        let amount = b.total - b.hold;
+        if b.coin == "USDC" || amount <= Decimal::ZERO {
+            continue;
+        }
+        let Some(market) = markets
+            .iter()
+            .find(|m| m.base().name == b.coin && m.quote().name == "USDC")
+        else {
+            continue;
+        };
+        let Some(mid) = mids.get(&market.name) else {
+            continue;
+        };
+        let value = amount * mid;
+        if value >= threshold {
+            continue;
+        }
 
-    // Get current mid price for slippage calculation
-    let mids = state
-        .client
-        .all_mids(None)
-        .await
-        .map_err(|e| mcp_err(&format!("Failed to fetch prices: {e}")))?;
+        let Ok(resolved) = state.resolve_market(&market.name, Some(MarketKind::Spot)) else {
+            continue;
+        };
+        // 5% below mid, fitted to the pair's precision.
+        let mut order = OrderRequest {
+            asset: market.index,
+            is_buy: false,
+            reduce_only: false,
+            limit_px: mid * (Decimal::ONE - Decimal::new(5, 2)),
+            sz: amount,
+            cloid: Cloid::ZERO,
+            order_type: OrderTypePlacement::Limit {
+                tif: TimeInForce::Ioc,
+            },
+        };
+        let band = PriceBand::new(state, "mid", *mid);
+        let order = normalize_order(&resolved, &mut order, Some(band)).map(|_| order);
 
-    let mid_price = mids
-        .get(&req.coin)
-        .ok_or_else(|| mcp_err(&format!("No mid price available for {}", req.coin)))?;
+        dust.push(DustBalance {
+            coin: b.coin.clone(),
+            amount,
+            value,
+            order,
+        });
+    }
 
-    // 5%
-    let slippage = Decimal::new(5, 2);
-    let limit_px = if is_buy {
-        mid_price * (Decimal::ONE + slippage)
-    } else {
-        mid_price * (Decimal::ONE - slippage)
-    };
-    let limit_px = round_price_5sf(limit_px);
+    if dust.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(format!(
+            "No spot balances below ${threshold}."
+        ))]));
+    }
 
-    let order = OrderRequest {
-        asset,
-        is_buy,
-        reduce_only: true,
-        limit_px,
-        sz: size,
-        cloid: make_cloid(),
-        order_type: OrderTypePlacement::Limit {
-            tif: TimeInForce::Ioc,
-        },
-    };
+    let mut output = format!("## Spot Dust (below ${threshold})\n\n");
+    output.push_str("| Token | Balance | Value | Status |\n");
+    output.push_str("|-------|---------|-------|--------|\n");
+    for d in &dust {
+        let status = match &d.order {
+            Ok(order) => format!("Sell {} @ ${} IOC", order.sz, order.limit_px),
+            Err(reason) => format!("Unconvertible: {reason}"),
+        };
+        output.push_str(&format!(
+            "| {} | {} | ${:.4} | {status} |\n",
+            d.coin, d.amount, d.value
+        ));
+    }
 
+    let mut orders: Vec<OrderRequest> = dust.into_iter().filter_map(|d| d.order.ok()).collect();
+    if orders.is_empty() {
+        output.push_str(&format!(
+            "\nNothing can be sold; every dust balance is below the tradable minimum. \
+             Spot orders must be worth at least ${MIN_ORDER_VALUE_USD}, so only a threshold_usd \
+             above that finds balances that can be converted."
+        ));
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    if !confirm {
+        output.push_str(&format!(
+            "\nCall again with confirm: true to sell {} balance(s) into USDC.",
+            orders.len()
+        ));
+        return Ok(CallToolResult::success(vec![Content::text(output)]));
+    }
+
+    state.check_trading_window()?;
+    let signer = state.require_signer()?;
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "sell {} dust balance(s) into USDC with IOC orders.\n\n{output}",
+            orders.len()
+        )));
+    }
+
+    let cloids = issue_cloids(state, &mut orders, "consolidate_dust");
     let nonce = state.next_nonce();
-    let response = hyperliquid::place_order_with_builder(
-        &state.http,
+    let result = hyperliquid::place_order_with_builder(
+        state.api.as_ref(),
         state.chain,
         signer.as_ref(),
-        vec![order],
+        orders,
         OrderGrouping::Na,
         Some(state.builder_info()),
         nonce,
         state.vault_addr(),
     )
-    .await
-    .map_err(|e| mcp_err(&format!("Close position failed: {e}")))?;
+    .await;
+    state.cloids.record_response(&cloids, &result);
+    let response = result.map_err(|e| state.action_failed(nonce, "Dust sell orders failed", e))?;
+    state.record_response(nonce, &response);
 
     state.cache.invalidate_user_data().await;
 
-    let mut output = format!(
-        "## Close {} Position\n\nResult: {}",
-        req.coin,
+    output.push_str(&format!(
+        "\n**Result:** {}\n",
         format_exchange_response(&response)
-    );
+    ));
+
+    match state.api.user_balances(address).await {
+        Ok(after) => {
+            output.push_str("\n## Balances After\n\n");
+            output.push_str("| Token | Before | After |\n");
+            output.push_str("|-------|--------|-------|\n");
+            for b in balances.iter().filter(|b| !b.total.is_zero()) {
+                let now = after
+                    .iter()
+                    .find(|a| a.coin == b.coin)
+                    .map(|a| a.total)
+                    .unwrap_or_default();
+                output.push_str(&format!("| {} | {} | {now} |\n", b.coin, b.total));
+            }
+            let usdc_after = after
+                .iter()
+                .find(|b| b.coin == "USDC")
+                .map(|b| b.total)
+                .unwrap_or_default();
+            output.push_str(&format!(
+                "\n**USDC recovered:** ${}\n",
+                usdc_after - usdc_before
+            ));
+        }
+        Err(e) => output.push_str(&format!("\nCould not fetch updated balances: {e}\n")),
+    }
 
-    maybe_append_builder_nudge(state, &mut output);
+    maybe_append_builder_nudge(state, &mut output).await;
 
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
-pub async fn schedule_cancel(
-    state: &ServerState,
-    req: ScheduleCancelRequest,
-) -> Result<CallToolResult, ErrorData> {
-    let signer = state.require_signer()?;
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
 
-    if req.seconds_from_now == 0 {
-        return Ok(CallToolResult::error(vec![Content::text(
-            "seconds_from_now must be greater than 0.",
-        )]));
+    use alloy::signers::local::PrivateKeySigner;
+    use serde_json::json;
+
+    use super::*;
+    use crate::exchange::mock::{MockExchange, text};
+
+    /// A dry-run state that can sign, so write tools get past their checks.
+    fn dry_run_state() -> (Arc<MockExchange>, ServerState) {
+        let mock = Arc::new(MockExchange::new());
+        let mut state = ServerState::mock(mock.clone());
+        state.read_only = false;
+        state.dry_run = true;
+        state.agent_signer = Some(Arc::new(PrivateKeySigner::random()));
+        (mock, state)
     }
 
-    let when = Utc::now()
-        + Duration::seconds(
-            i64::try_from(req.seconds_from_now)
-                .map_err(|_| mcp_err("seconds_from_now too large"))?,
+    #[tokio::test]
+    async fn set_leverage_all_skips_coins_already_at_target() {
+        let (mock, state) = dry_run_state();
+        mock.respond("frontendOpenOrders", json!([]));
+        mock.respond(
+            "activeAssetData",
+            json!({ "leverage": { "type": "cross", "value": 5 } }),
         );
 
-    let nonce = state.next_nonce();
-    state
-        .client
-        .schedule_cancel(signer.as_ref(), nonce, when, state.vault_addr(), None)
+        let req = SetLeverageAllRequest {
+            coins: None,
+            leverage: 3,
+            mode: None,
+            profile: None,
+        };
+        let result = set_leverage_all(&state, req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "**Dry run** — nothing was submitted to the exchange.\n\n\
+             Would set leverage as follows.\n\n\
+             ## Set Leverage: 3x cross\n\n\
+             Selected: every perp with a position or open order (2).\n\n\
+             | Coin | Before | Result |\n\
+             |------|--------|--------|\n\
+             | BTC | 5x cross | Would change |\n\
+             | ETH | 5x cross | Would change |\n\
+             \n\
+             To change: 2. Already set: 0. Skipped: 0. Failed: 0.\n"
+        );
+
+        let req = SetLeverageAllRequest {
+            coins: Some(vec!["eth".into(), "BTC".into(), "ETH".into()]),
+            leverage: 5,
+            mode: Some("cross".into()),
+            profile: None,
+        };
+        let result = set_leverage_all(&state, req).await.unwrap();
+        assert_eq!(
+            text(&result),
+            "**Dry run** — nothing was submitted to the exchange.\n\n\
+             Would set leverage as follows.\n\n\
+             ## Set Leverage: 5x cross\n\n\
+             Selected: the named coins (2).\n\n\
+             | Coin | Before | Result |\n\
+             |------|--------|--------|\n\
+             | ETH | 5x cross | Already set; skipped |\n\
+             | BTC | 5x cross | Already set; skipped |\n\
+             \n\
+             To change: 0. Already set: 2. Skipped: 0. Failed: 0.\n"
+        );
+        assert!(mock.requests().iter().all(|r| r.get("action").is_none()));
+    }
+
+    #[test]
+    fn normalize_order_refuses_prices_outside_the_band() {
+        let (_, state) = dry_run_state();
+        let btc = state.resolve_market("BTC", None).unwrap();
+        let band = PriceBand::new(&state, "mark", Decimal::from(61_220));
+        let order = |limit_px: i64| OrderRequest {
+            asset: btc.asset,
+            is_buy: true,
+            reduce_only: false,
+            limit_px: Decimal::from(limit_px),
+            sz: Decimal::new(1, 2),
+            cloid: Cloid::ZERO,
+            order_type: OrderTypePlacement::Limit {
+                tif: TimeInForce::Gtc,
+            },
+        };
+
+        assert_eq!(
+            normalize_order(&btc, &mut order(119_380), Some(band)),
+            Err(
+                "BTC orders must be priced between $3,061 and $119,379 right now (within 95% \
+                 of the mark price $61,220); the exchange rejects limit price $119,380 as out \
+                 of band."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            normalize_order(&btc, &mut order(119_379), Some(band)),
+            Ok(String::new())
+        );
+        assert!(normalize_order(&btc, &mut order(119_380), None).is_ok());
+    }
+
+    fn market_order(coin: &str, side: &str) -> PlaceOrderRequest {
+        PlaceOrderRequest {
+            coin: coin.into(),
+            market_kind: None,
+            side: side.into(),
+            size: None,
+            size_usd: None,
+            price: None,
+            price_reference: None,
+            price_offset_bps: None,
+            order_type: Some("market".into()),
+            time_in_force: None,
+            urgency: None,
+            reduce_only: None,
+            allow_duplicate: None,
+            skip_validation: None,
+            force: None,
+            account_scope: None,
+            profile: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn market_orders_take_the_spot_or_perp_branch() {
+        let (mock, state) = dry_run_state();
+        mock.respond("frontendOpenOrders", json!([]));
+        mock.respond("perpsAtOpenInterestCap", json!([]));
+        mock.respond(
+            "allMids",
+            json!({ "BTC": "61220", "ETH": "3000", "@107": "42.51" }),
+        );
+        let book = |bid: &str, ask: &str| {
+            json!({
+                "coin": "", "time": 0,
+                "levels": [
+                    [{ "px": bid, "sz": "1000", "n": 1 }],
+                    [{ "px": ask, "sz": "1000", "n": 1 }]
+                ]
+            })
+        };
+        // Info request types `place_order` sent for `req`, and its output.
+        let run = |req: PlaceOrderRequest| {
+            let (mock, state) = (mock.clone(), state.clone());
+            async move {
+                let before = mock.requests().len();
+                let result = place_order(&state, req).await.unwrap();
+                let kinds: Vec<String> = mock.requests()[before..]
+                    .iter()
+                    .filter_map(|r| r["type"].as_str().map(String::from))
+                    .collect();
+                (kinds, text(&result))
+            }
+        };
+
+        mock.respond("l2Book", book("61219", "61221"));
+        let (kinds, output) = run(PlaceOrderRequest {
+            size_usd: Some(1000.0),
+            ..market_order("BTC", "buy")
+        })
+        .await;
+        assert!(output.contains("Would place buy 0.01625 BTC (perp) @ $61528"));
+        assert!(output.contains("Resolved size: 0.01625 (1000 USD at $61528)"));
+        assert!(kinds.contains(&"perpsAtOpenInterestCap".to_string()));
+        assert!(!kinds.contains(&"spotClearinghouseState".to_string()));
+
+        // Spot buys are sized in the quote token and checked against the
+        // spot balance, with no open interest or margin checks.
+        mock.respond("l2Book", book("42.5", "42.52"));
+        let (kinds, output) = run(PlaceOrderRequest {
+            size_usd: Some(500.0),
+            ..market_order("HYPE", "buy")
+        })
+        .await;
+        assert!(output.contains("Would place buy 11.70 HYPE/USDC (spot, @107) @ $42.733"));
+        assert!(output.contains("Resolved size: 11.70 (500 USDC at $42.733)"));
+        assert!(kinds.contains(&"spotClearinghouseState".to_string()));
+        assert!(!kinds.contains(&"perpsAtOpenInterestCap".to_string()));
+        assert!(!output.contains("margin"));
+
+        let (_, output) = run(PlaceOrderRequest {
+            size_usd: Some(5000.0),
+            ..market_order("HYPE", "buy")
+        })
+        .await;
+        assert_eq!(
+            output,
+            "Spot order on HYPE/USDC would be rejected:\n\
+             - Balance: Only 1000.0 USDC available on spot, 4999.76100 needed. Move at least \
+             3999.76 USDC from perps with transfer_between_spot_perps (direction: \"to_spot\"); \
+             18939.50 USDC is withdrawable there.\n\n\
+             Pass skip_validation: true to send it anyway."
+        );
+
+        let (_, output) = run(PlaceOrderRequest {
+            size: Some(20.0),
+            ..market_order("HYPE", "sell")
+        })
+        .await;
+        assert_eq!(
+            output,
+            "Spot order on HYPE/USDC would be rejected:\n\
+             - Balance: Only 10.0 HYPE available on spot, 20 needed. Reduce the size to 10.0 \
+             or acquire 10.0 more HYPE.\n\n\
+             Pass skip_validation: true to send it anyway."
+        );
+        assert!(mock.requests().iter().all(|r| r.get("action").is_none()));
+    }
+
+    #[tokio::test]
+    async fn unanswered_order_is_held_until_its_status_is_checked() {
+        let (mock, mut state) = dry_run_state();
+        state.dry_run = false;
+        state.order_latency_budget = None;
+        mock.respond("frontendOpenOrders", json!([]));
+        mock.respond("perpsAtOpenInterestCap", json!([]));
+        mock.respond("orderStatus", json!({ "status": "unknownOid" }));
+        let order = || PlaceOrderRequest {
+            size: Some(0.01),
+            price: Some(61000.0),
+            order_type: Some("limit".into()),
+            ..market_order("BTC", "buy")
+        };
+
+        // With no exchange fixture the request fails without an answer.
+        let error = place_order(&state, order()).await.unwrap_err();
+        assert!(error.message.contains("Check it with `get_order_status`"));
+        let cloid = mock.requests().last().unwrap()["action"]["orders"][0]["c"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let retry = place_order(&state, order()).await.unwrap();
+        assert!(text(&retry).starts_with("## Duplicate Order Skipped"));
+
+        let status = account::get_order_status(
+            &state,
+            account::GetOrderStatusRequest {
+                order_id: None,
+                cloid: Some(cloid.clone()),
+                profile: None,
+            },
+        )
         .await
-        .map_err(|e| mcp_err(&format!("Schedule cancel failed: {e}")))?;
+        .unwrap();
+        assert!(text(&status).ends_with(
+            "It never reached the exchange, so an identical order can be placed again."
+        ));
+        assert_eq!(
+            state
+                .cloids
+                .lookup(&cloid.parse().unwrap())
+                .unwrap()
+                .outcome,
+            Submission::NotSent
+        );
 
-    let output = format!(
-        "Scheduled cancellation of all open orders at {} UTC ({} seconds from now).",
-        when.format("%Y-%m-%d %H:%M:%S"),
-        req.seconds_from_now,
-    );
+        mock.respond(
+            "exchange",
+            json!({
+                "status": "ok",
+                "response": { "type": "order", "data": { "statuses": [{ "resting": { "oid": 42 } }] } }
+            }),
+        );
+        let retry = place_order(&state, order()).await.unwrap();
+        assert!(text(&retry).starts_with("## Order Result: Buy 0.01 BTC"));
+    }
 
-    Ok(CallToolResult::success(vec![Content::text(output)]))
+    #[tokio::test]
+    async fn open_breaker_refuses_new_orders_but_not_cancels_or_closes() {
+        let (mock, state) = dry_run_state();
+        mock.respond("frontendOpenOrders", json!([]));
+        mock.respond("perpsAtOpenInterestCap", json!([]));
+        mock.respond(
+            "allMids",
+            json!({ "BTC": "61220", "ETH": "3000", "@107": "42.51" }),
+        );
+        mock.respond(
+            "l2Book",
+            json!({
+                "coin": "BTC", "time": 0,
+                "levels": [
+                    [{ "px": "61219", "sz": "10", "n": 1 }],
+                    [{ "px": "61221", "sz": "10", "n": 1 }]
+                ]
+            }),
+        );
+        for _ in 0..state.breaker.threshold() {
+            state.breaker.record_failure("429 Too Many Requests");
+        }
+
+        let refusal = place_order(
+            &state,
+            PlaceOrderRequest {
+                size: Some(0.01),
+                ..market_order("BTC", "buy")
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(refusal.message.starts_with("Exchange appears degraded"));
+
+        let cancel = cancel_order(
+            &state,
+            CancelOrderRequest {
+                coin: "BTC".into(),
+                order_id: 1,
+                account_scope: None,
+                profile: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(text(&cancel).starts_with("**Dry run**"));
+
+        let close = close_position(
+            &state,
+            ClosePositionRequest {
+                coin: "BTC".into(),
+                account_scope: None,
+                profile: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(text(&close).starts_with("**Dry run**"), "{}", text(&close));
+    }
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_price_5sf_never_zeroes_sub_cent_prices() {
+        assert_eq!(round_price_5sf(d("61234.56")), d("61235"));
+        assert_eq!(round_price_5sf(d("0.0000123456")), d("0.000012346"));
+        // A market trigger leg 10% through a kPEPE-class trigger.
+        assert_eq!(
+            round_price_5sf(d("0.0041234") * (Decimal::ONE + TRIGGER_MARKET_SLIPPAGE)),
+            d("0.0045357")
+        );
+    }
+
+    #[test]
+    fn spot_checks_report_each_fix() {
+        let (_, state) = dry_run_state();
+        let hype = state.resolve_market("HYPE", None).unwrap();
+
+        assert_eq!(
+            check_spot_size(&hype, Decimal::ZERO),
+            Err("Use a positive size.".to_string())
+        );
+        assert_eq!(
+            check_spot_size(&hype, d("1.234")),
+            Err("HYPE/USDC trades in steps of 1e-2; use a size of 1.23.".to_string())
+        );
+        assert_eq!(
+            check_spot_size(&hype, d("1.20")),
+            Ok("1.20 fits the 2-decimal size step".to_string())
+        );
+
+        assert_eq!(
+            check_spot_min_value(d("0.2"), d("42.5")),
+            Err(
+                "Order value $8.50 is below the $10 minimum; use a size of at least 0.23529412."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check_spot_min_value(d("1"), d("42.5")),
+            Ok("$42.50 meets the $10 minimum".to_string())
+        );
+
+        let balance = |coin: &str, total: &str, hold: &str| UserBalance {
+            coin: coin.into(),
+            token: 0,
+            hold: d(hold),
+            total: d(total),
+            entry_ntl: Decimal::ZERO,
+        };
+        let balances = [balance("USDC", "100", "50"), balance("HYPE", "12.5", "2.5")];
+        let check = |is_buy: bool, size: &str, perp_withdrawable: &str| {
+            check_spot_balance(
+                &balances,
+                d(perp_withdrawable),
+                &hype,
+                is_buy,
+                d(size),
+                d("10"),
+            )
+        };
+
+        assert_eq!(
+            check(true, "5", "0"),
+            Ok("50 USDC available, 50 needed".to_string())
+        );
+        assert_eq!(
+            check(true, "8", "100"),
+            Err(
+                "Only 50 USDC available on spot, 80 needed. Move at least 30.00 USDC from perps \
+                 with transfer_between_spot_perps (direction: \"to_spot\"); 100.00 USDC is \
+                 withdrawable there."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(true, "8", "0"),
+            Err(
+                "Only 50 USDC available on spot, 80 needed. 50 USDC is locked in open orders; \
+                 cancel them to free it."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(true, "20", "0"),
+            Err(
+                "Only 50 USDC available on spot, 200 needed. Deposit or acquire 150 more USDC."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(false, "12", "100"),
+            Err(
+                "Only 10.0 HYPE available on spot, 12 needed. 2.5 HYPE is locked in open \
+                 orders; cancel them to free it."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(false, "20", "100"),
+            Err(
+                "Only 10.0 HYPE available on spot, 20 needed. Reduce the size to 10.0 or \
+                 acquire 10.0 more HYPE."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn order_fingerprint_ignores_how_numbers_are_written() {
+        let fingerprint = |coin: &str, size: Decimal, price: Option<Decimal>| {
+            order_fingerprint(coin, true, size, price, TimeInForce::Gtc, false, None)
+        };
+        let base = fingerprint("BTC", d("0.1"), Some(d("61000")));
+
+        assert_eq!(fingerprint("btc", d("0.10"), Some(d("61000.0"))), base);
+        assert_eq!(
+            fingerprint(
+                "BTC",
+                to_decimal(0.1).unwrap(),
+                Some(to_decimal(61000.0).unwrap())
+            ),
+            base
+        );
+        assert_eq!(
+            fingerprint("BTC", Decimal::new(1000, 4), Some(Decimal::new(610_000, 1))),
+            base
+        );
+
+        assert_ne!(fingerprint("BTC", d("0.11"), Some(d("61000"))), base);
+        assert_ne!(fingerprint("BTC", d("0.1"), None), base);
+        assert_ne!(fingerprint("ETH", d("0.1"), Some(d("61000"))), base);
+        assert_ne!(
+            order_fingerprint(
+                "BTC",
+                false,
+                d("0.1"),
+                Some(d("61000")),
+                TimeInForce::Gtc,
+                false,
+                None
+            ),
+            base
+        );
+        assert_ne!(
+            order_fingerprint(
+                "BTC",
+                true,
+                d("0.1"),
+                Some(d("61000")),
+                TimeInForce::Alo,
+                false,
+                None
+            ),
+            base
+        );
+        assert_ne!(
+            order_fingerprint(
+                "BTC",
+                true,
+                d("0.1"),
+                Some(d("61000")),
+                TimeInForce::Gtc,
+                true,
+                None
+            ),
+            base
+        );
+        assert_ne!(
+            order_fingerprint(
+                "BTC",
+                true,
+                d("0.1"),
+                Some(d("61000")),
+                TimeInForce::Gtc,
+                false,
+                Some(Address::ZERO)
+            ),
+            base
+        );
+    }
+
+    /// A limit order on `market` as `place_order` would send it.
+    fn limit_order(market: &ResolvedMarket, is_buy: bool, px: &str, sz: &str) -> OrderRequest {
+        OrderRequest {
+            asset: market.asset,
+            is_buy,
+            reduce_only: false,
+            limit_px: d(px),
+            sz: d(sz),
+            cloid: Cloid::ZERO,
+            order_type: OrderTypePlacement::Limit {
+                tif: TimeInForce::Gtc,
+            },
+        }
+    }
+
+    fn trigger_leg(
+        market: &ResolvedMarket,
+        is_buy: bool,
+        trigger: &str,
+        tpsl: TpSl,
+    ) -> OrderRequest {
+        OrderRequest {
+            reduce_only: true,
+            order_type: OrderTypePlacement::Trigger {
+                is_market: true,
+                trigger_px: d(trigger),
+                tpsl,
+            },
+            ..limit_order(market, is_buy, trigger, "0.01")
+        }
+    }
+
+    #[tokio::test]
+    async fn groupings_serialize_to_the_exchange_action() {
+        let mock = Arc::new(MockExchange::new());
+        mock.respond("exchange", json!({ "status": "ok" }));
+        let state = ServerState::mock(mock.clone());
+        let btc = state.resolve_market("BTC", None).unwrap();
+        let signer = PrivateKeySigner::random();
+        let entry = limit_order(&btc, true, "61000", "0.01");
+        let tp = trigger_leg(&btc, false, "65000", TpSl::Tp);
+        let sl = trigger_leg(&btc, false, "58000", TpSl::Sl);
+
+        for (name, orders, wire) in [
+            ("na", vec![entry.clone()], "na"),
+            (
+                "normal_tpsl",
+                vec![entry.clone(), tp.clone(), sl.clone()],
+                "normalTpsl",
+            ),
+            ("positionTpsl", vec![tp.clone(), sl.clone()], "positionTpsl"),
+        ] {
+            let grouping = parse_grouping(name).unwrap();
+            validate_grouping(&grouping, &orders).unwrap();
+            hyperliquid::place_order_with_builder(
+                state.api.as_ref(),
+                state.chain,
+                &signer,
+                orders,
+                grouping,
+                None,
+                1,
+                None,
+            )
+            .await
+            .unwrap();
+            let request = mock.requests().pop().unwrap();
+            assert_eq!(request["action"]["grouping"], wire, "{name}");
+        }
+
+        let leg = |px: &str, tpsl: &str| {
+            json!({
+                "a": 0, "b": false, "p": px, "s": "0.01", "r": true,
+                "t": { "trigger": { "isMarket": true, "triggerPx": px, "tpsl": tpsl } },
+                "c": "0x00000000000000000000000000000000"
+            })
+        };
+        assert_eq!(
+            mock.requests()[1]["action"],
+            json!({
+                "type": "order",
+                "orders": [
+                    {
+                        "a": 0, "b": true, "p": "61000", "s": "0.01", "r": false,
+                        "t": { "limit": { "tif": "Gtc" } },
+                        "c": "0x00000000000000000000000000000000"
+                    },
+                    leg("65000", "tp"),
+                    leg("58000", "sl")
+                ],
+                "grouping": "normalTpsl"
+            })
+        );
+    }
+
+    #[test]
+    fn validate_grouping_rejects_shapes_the_exchange_refuses() {
+        let (_, state) = dry_run_state();
+        let btc = state.resolve_market("BTC", None).unwrap();
+        let eth = state.resolve_market("ETH", None).unwrap();
+        let entry = limit_order(&btc, true, "61000", "0.01");
+        let tp = trigger_leg(&btc, false, "65000", TpSl::Tp);
+        let sl = trigger_leg(&btc, false, "58000", TpSl::Sl);
+        let normal = OrderGrouping::NormalTpsl;
+        let position = OrderGrouping::PositionTpsl;
+
+        assert!(parse_grouping("Position-TPSL").is_some());
+        assert!(parse_grouping("oco").is_none());
+        assert_eq!(
+            validate_grouping(&OrderGrouping::Na, std::slice::from_ref(&tp)),
+            Ok(())
+        );
+
+        for (grouping, orders, error) in [
+            (&normal, vec![], "normal_tpsl needs an entry order."),
+            (
+                &normal,
+                vec![tp.clone(), sl.clone()],
+                "normal_tpsl: the first order must be the non-trigger entry order, followed by its TP/SL legs.",
+            ),
+            (
+                &normal,
+                vec![entry.clone()],
+                "normal_tpsl: expected one or two TP/SL legs, got 0.",
+            ),
+            (
+                &normal,
+                vec![entry.clone(), tp.clone(), sl.clone(), tp.clone()],
+                "normal_tpsl: expected one or two TP/SL legs, got 3.",
+            ),
+            (
+                &normal,
+                vec![entry.clone(), tp.clone(), tp.clone()],
+                "normal_tpsl: at most one \"tp\" and one \"sl\" leg.",
+            ),
+            (
+                &normal,
+                vec![entry.clone(), trigger_leg(&eth, false, "3600", TpSl::Tp)],
+                "normal_tpsl: TP/SL legs must be on the same market as the entry.",
+            ),
+            (
+                &normal,
+                vec![entry.clone(), trigger_leg(&btc, true, "65000", TpSl::Tp)],
+                "normal_tpsl: TP/SL legs must be on the opposite side of the entry.",
+            ),
+            (
+                &position,
+                vec![entry.clone(), sl.clone()],
+                "position_tpsl: every TP/SL leg needs trigger_price and tpsl.",
+            ),
+            (
+                &position,
+                vec![tp.clone(), trigger_leg(&eth, false, "3000", TpSl::Sl)],
+                "position_tpsl: TP/SL legs must be on the same market and side.",
+            ),
+        ] {
+            assert_eq!(validate_grouping(grouping, &orders), Err(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn placed_order_outcomes_parse_recorded_responses() {
+        let (_, state) = dry_run_state();
+        let btc = state.resolve_market("BTC", None).unwrap();
+        let eth = state.resolve_market("ETH", None).unwrap();
+        let placed = vec![
+            (btc.clone(), limit_order(&btc, true, "61000", "0.01")),
+            (eth.clone(), limit_order(&eth, false, "3450", "0.5")),
+            (btc.clone(), limit_order(&btc, true, "61500", "0.01")),
+            (eth.clone(), limit_order(&eth, true, "3460", "0.5")),
+        ];
+
+        let response = json!({
+            "status": "ok",
+            "response": { "type": "order", "data": { "statuses": [
+                { "resting": { "oid": 77738308, "cloid": "0x1234567890abcdef1234567890abcdef" } },
+                { "filled": { "totalSz": "0.5", "avgPx": "3451.2", "oid": 77738309 } },
+                { "error": "Post only order would have immediately matched, bbo was 61490.0@61491.0. asset=0" },
+                { "error": "Order could not immediately match against any resting orders. asset=1" }
+            ] } }
+        });
+        let outcomes = placed_order_outcomes(&response, &placed);
+        assert_eq!(
+            describe_outcomes(&outcomes),
+            "Resting (order ID: 77738308); Filled 0.5 @ $3451.2 (order ID: 77738309); \
+             Rejected — post-only order would cross the spread at $61,500 (best ask $61,491); \
+             nothing is resting; \
+             Expired unfilled — no liquidity within your limit price; nothing is resting"
+        );
+        let resting = serde_json::to_value(&outcomes.unwrap()[0]).unwrap();
+        assert_eq!(resting["coin"], "BTC");
+        assert_eq!(resting["side"], "buy");
+        assert_eq!(resting["status"], "resting");
+        assert_eq!(resting["cloid"], "0x1234567890abcdef1234567890abcdef");
+
+        let insufficient = json!({
+            "status": "ok",
+            "response": { "type": "order", "data": { "statuses": [
+                { "error": "Insufficient margin to place order. asset=0" }
+            ] } }
+        });
+        assert_eq!(
+            describe_outcomes(&placed_order_outcomes(&insufficient, &placed[..1])),
+            "Error: Insufficient margin to place order. asset=0"
+        );
+
+        let whole_action = json!({
+            "status": "err",
+            "response": "User or API Wallet 0x0d1e7cde53ba9467b783cb7c530ce054 does not exist."
+        });
+        assert_eq!(
+            placed_order_outcomes(&whole_action, &placed).unwrap_err(),
+            "User or API Wallet 0x0d1e7cde53ba9467b783cb7c530ce054 does not exist."
+        );
+        assert_eq!(
+            placed_order_outcomes(&json!({ "error": "bad gateway" }), &placed).unwrap_err(),
+            r#"{"error":"bad gateway"}"#
+        );
+
+        let no_statuses = json!({ "status": "ok", "response": { "type": "default" } });
+        assert!(
+            placed_order_outcomes(&no_statuses, &placed)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn normalize_order_applies_each_exchange_rule_per_asset() {
+        let (_, state) = dry_run_state();
+        let btc = state.resolve_market("BTC", None).unwrap();
+        let eth = state.resolve_market("ETH", None).unwrap();
+        let hype = state
+            .resolve_market("HYPE", Some(MarketKind::Spot))
+            .unwrap();
+        let kpepe = ResolvedMarket {
+            coin: "kPEPE".into(),
+            symbol: "kPEPE".into(),
+            asset: 99,
+            kind: MarketKind::Perp,
+            sz_decimals: 0,
+        };
+        let order = |market: &ResolvedMarket, is_buy: bool, px: &str, sz: &str| OrderRequest {
+            asset: market.asset,
+            is_buy,
+            reduce_only: false,
+            limit_px: d(px),
+            sz: d(sz),
+            cloid: Cloid::ZERO,
+            order_type: OrderTypePlacement::Limit {
+                tif: TimeInForce::Gtc,
+            },
+        };
+
+        // Tick rounding never makes the order more aggressive, and sizes
+        // truncate to the asset's step.
+        let mut o = order(&btc, true, "61234.56", "0.0012345678");
+        assert_eq!(
+            normalize_order(&btc, &mut o, None).unwrap(),
+            "Rounded to exchange precision: price 61234.56 → 61234, \
+             size 0.0012345678 → 0.00123 (5 decimals).\n\n"
+        );
+        assert_eq!((o.limit_px, o.sz), (d("61234"), d("0.00123")));
+
+        let mut o = order(&eth, false, "3456.789", "0.00311");
+        normalize_order(&eth, &mut o, None).unwrap();
+        assert_eq!((o.limit_px, o.sz), (d("3456.8"), d("0.0031")));
+
+        let mut o = order(&hype, true, "23.456789", "0.4567");
+        normalize_order(&hype, &mut o, None).unwrap();
+        assert_eq!((o.limit_px, o.sz), (d("23.456"), d("0.45")));
+
+        let mut o = order(&kpepe, true, "0.0041234567", "2500.7");
+        normalize_order(&kpepe, &mut o, None).unwrap();
+        assert_eq!((o.limit_px, o.sz), (d("0.004123"), d("2500")));
+
+        let mut o = order(&kpepe, true, "0.0000001", "5000000");
+        assert_eq!(
+            normalize_order(&kpepe, &mut o, None),
+            Err(
+                "Price $0.0000001 is below the smallest price step of kPEPE (1e-6) and \
+                 would round to zero."
+                    .to_string()
+            )
+        );
+
+        // Size step.
+        let mut o = order(&kpepe, true, "0.004123", "0.4");
+        assert_eq!(
+            normalize_order(&kpepe, &mut o, None),
+            Err("Size 0.4 is below the kPEPE size step of 1e-0.".to_string())
+        );
+
+        // Minimum notional, measured after rounding; reduce-only is exempt.
+        let mut o = order(&eth, false, "3456.8", "0.00289");
+        assert_eq!(
+            normalize_order(&eth, &mut o, None),
+            Err("Order value $9.67 (0.0028 ETH @ $3456.8) is below the $10 minimum.".to_string())
+        );
+        let mut o = order(&eth, false, "3456.8", "0.0028");
+        o.reduce_only = true;
+        assert!(normalize_order(&eth, &mut o, None).is_ok());
+
+        // Price band, checked on the rounded price.
+        let band = PriceBand::new(&state, "mark", d("24"));
+        let mut o = order(&hype, true, "46.8009", "1");
+        assert!(normalize_order(&hype, &mut o, Some(band)).is_ok());
+        assert_eq!(o.limit_px, d("46.8"));
+        let mut o = order(&hype, false, "46.81", "1");
+        let refusal = normalize_order(&hype, &mut o, Some(band)).unwrap_err();
+        assert_eq!(
+            refusal,
+            "HYPE/USDC orders must be priced between $1.2 and $46.8 right now (within 95% of \
+             the mark price $24); the exchange rejects limit price $46.81 as out of band."
+        );
+    }
 }
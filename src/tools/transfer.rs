@@ -1,8 +1,13 @@
+use std::sync::Arc;
+
+use alloy::signers::local::PrivateKeySigner;
+use hypersdk::hypercore::SpotToken;
 use rmcp::{model::*, schemars};
+use rust_decimal::Decimal;
 use serde_json::json;
 
 use crate::hyperliquid;
-use crate::state::{ServerState, mcp_err};
+use crate::state::{ServerState, dry_run_result, mcp_err};
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct TransferSpotPerpsRequest {
@@ -11,12 +16,17 @@ pub struct TransferSpotPerpsRequest {
 
     #[schemars(description = "Transfer direction: \"to_spot\" or \"to_perps\"")]
     pub direction: String,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 pub async fn transfer_between_spot_perps(
     state: &ServerState,
     req: TransferSpotPerpsRequest,
 ) -> Result<CallToolResult, ErrorData> {
+    state.check_trading_window()?;
+    state.check_breaker()?;
     let signer = state.require_signer()?;
 
     if req.amount <= 0.0 {
@@ -49,19 +59,31 @@ pub async fn transfer_between_spot_perps(
         .find(|t| t.name == "USDC" || t.name == "usdc")
         .ok_or_else(|| mcp_err("USDC token not found"))?;
 
+    if state.dry_run {
+        let dir_str = if to_perp {
+            "spot → perps"
+        } else {
+            "perps → spot"
+        };
+        return Ok(dry_run_result(&format!(
+            "transfer {} USDC ({dir_str}).",
+            req.amount
+        )));
+    }
+
     let nonce = state.next_nonce();
     if to_perp {
         state
             .client
             .transfer_to_perps(signer.as_ref(), usdc_token.clone(), amount, nonce)
             .await
-            .map_err(|e| mcp_err(&format!("Transfer to perps failed: {e}")))?;
+            .map_err(|e| state.action_failed(nonce, "Transfer to perps failed", e))?;
     } else {
         state
             .client
             .transfer_to_spot(signer.as_ref(), usdc_token.clone(), amount, nonce)
             .await
-            .map_err(|e| mcp_err(&format!("Transfer to spot failed: {e}")))?;
+            .map_err(|e| state.action_failed(nonce, "Transfer to spot failed", e))?;
     }
 
     let dir_str = if to_perp {
@@ -76,103 +98,604 @@ pub async fn transfer_between_spot_perps(
     ))]))
 }
 
-pub async fn create_agent_wallet(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TransferEvmRequest {
+    #[schemars(description = "Spot token to move, e.g. \"HYPE\" (must be linked to the HyperEVM)")]
+    pub token: String,
+
+    #[schemars(description = "Amount of the token to move")]
+    pub amount: f64,
+
+    #[schemars(description = "Must be true to submit the transfer")]
+    pub confirm: bool,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+/// Checks shared by both directions. Returns the token, the amount and the
+/// main wallet, or a user-facing error.
+async fn prepare_evm_transfer(
+    state: &ServerState,
+    req: &TransferEvmRequest,
+) -> Result<Result<(SpotToken, Decimal, Arc<PrivateKeySigner>), CallToolResult>, ErrorData> {
+    if !state.withdrawals_enabled {
+        return Ok(Err(CallToolResult::error(vec![Content::text(
+            "Transfers between HyperCore and the HyperEVM are disabled. \
+             Set HYPERLIQUID_ENABLE_WITHDRAWALS=true to allow them.",
+        )])));
+    }
+    if !req.confirm {
+        return Ok(Err(CallToolResult::error(vec![Content::text(
+            "Moving funds between HyperCore and the HyperEVM requires confirm: true.",
+        )])));
+    }
+    let amount = match Decimal::try_from(req.amount) {
+        Ok(amount) if amount > Decimal::ZERO => amount.normalize(),
+        _ => {
+            return Ok(Err(CallToolResult::error(vec![Content::text(
+                "Amount must be positive.",
+            )])));
+        }
+    };
+
+    let tokens = state
+        .client
+        .spot_tokens()
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch token info: {e}")))?;
+    let name = req.token.trim();
+    let Some(token) = tokens
+        .into_iter()
+        .find(|t| t.name.eq_ignore_ascii_case(name))
+    else {
+        return Ok(Err(CallToolResult::error(vec![Content::text(format!(
+            "Unknown spot token '{name}'."
+        ))])));
+    };
+    if token.cross_chain_address.is_none() {
+        return Ok(Err(CallToolResult::error(vec![Content::text(format!(
+            "{} is not linked to the HyperEVM and can only be held on HyperCore.",
+            token.name
+        ))])));
+    }
+    if amount.scale() > token.wei_decimals as u32 {
+        return Ok(Err(CallToolResult::error(vec![Content::text(format!(
+            "{} supports at most {} decimals; got {amount}.",
+            token.name, token.wei_decimals
+        ))])));
+    }
+
+    state.check_trading_window()?;
+    state.check_breaker()?;
+    let signer = state.require_main_signer()?.clone();
+    Ok(Ok((token, amount, signer)))
+}
+
+pub async fn transfer_to_evm(
+    state: &ServerState,
+    req: TransferEvmRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let (token, amount, signer) = match prepare_evm_transfer(state, &req).await? {
+        Ok(prepared) => prepared,
+        Err(result) => return Ok(result),
+    };
+    let address = signer.address();
+
+    let balances = state
+        .api
+        .user_balances(address)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch spot balances: {e}")))?;
+    let available = balances
+        .iter()
+        .find(|b| b.coin == token.name)
+        .map(|b| b.available())
+        .unwrap_or_default();
+    if available < amount {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Insufficient {name} on HyperCore spot: {available} available, {amount} requested.",
+            name = token.name
+        ))]));
+    }
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "move {amount} {} from HyperCore spot to the HyperEVM for {address:#x}.",
+            token.name
+        )));
+    }
+
+    let nonce = state.next_nonce();
+    state
+        .client
+        .transfer_to_evm(signer.as_ref(), token.clone(), amount, nonce)
+        .await
+        .map_err(|e| state.action_failed(nonce, "Transfer to HyperEVM failed", e))?;
+    tracing::info!(token = %token.name, %amount, address = %address, "Transferred to HyperEVM");
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Moved {amount} {} from HyperCore spot to the HyperEVM.\n\n\
+         Address: `{address:#x}`\n\
+         HyperCore balance before: {available} {0}",
+        token.name
+    ))]))
+}
+
+pub async fn transfer_from_evm(
+    state: &ServerState,
+    req: TransferEvmRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let (token, amount, signer) = match prepare_evm_transfer(state, &req).await? {
+        Ok(prepared) => prepared,
+        Err(result) => return Ok(result),
+    };
+    let address = signer.address();
+
+    let available = hyperliquid::evm_balance(state.chain, address, &token)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch HyperEVM balance: {e}")))?;
+    if available < amount {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Insufficient {name} on the HyperEVM: {available} available, {amount} requested.",
+            name = token.name
+        ))]));
+    }
+    let gas_note = if token.name == "HYPE" && available == amount {
+        "\n\n**Note:** this moves the whole HyperEVM HYPE balance, which also pays gas. \
+         The transaction will fail; leave a little HYPE behind."
+    } else {
+        ""
+    };
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "move {amount} {} from the HyperEVM to HyperCore spot for {address:#x}.{gas_note}",
+            token.name
+        )));
+    }
+    if !gas_note.is_empty() {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Cannot move the entire HyperEVM HYPE balance ({available}); \
+             some is needed for gas."
+        ))]));
+    }
+
+    let tx_hash = hyperliquid::transfer_from_evm(state.chain, (*signer).clone(), &token, amount)
+        .await
+        .map_err(|e| mcp_err(&format!("Transfer from HyperEVM failed: {e}")))?;
+    tracing::info!(token = %token.name, %amount, address = %address, tx = %tx_hash, "Transferred from HyperEVM");
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Moved {amount} {} from the HyperEVM to HyperCore spot.\n\n\
+         Address: `{address:#x}`\n\
+         EVM transaction: `{tx_hash:#x}`\n\n\
+         HyperCore credits the transfer within a few seconds of the transaction being mined.",
+        token.name
+    ))]))
+}
+
+/// A freshly approved agent, already written to the env file.
+struct NewAgent {
+    name: String,
+    address: hypersdk::Address,
+    approved_by: hypersdk::Address,
+    saved_to: std::path::PathBuf,
+}
+
+/// Approve a new agent with the main wallet and save its key under this
+/// profile's agent key variable.
+async fn approve_and_save_agent(state: &ServerState) -> Result<NewAgent, ErrorData> {
     use crate::agent;
 
     let main_signer = state.require_main_signer()?;
     let signing_address = main_signer.address();
     tracing::info!(address = %signing_address, "Creating agent wallet with main wallet");
 
-    let display_name = agent::agent_name_today();
-
     let nonce = state.next_nonce();
     let agent_key_hex = agent::create_agent_wallet(&state.client, main_signer.as_ref(), nonce)
         .await
         .map_err(|e| {
-            mcp_err(&format!(
-                "Agent wallet creation failed (signing address: {signing_address:#x}): {e}"
-            ))
+            state.action_failed(
+                nonce,
+                &format!("Agent wallet creation failed (signing address: {signing_address:#x})"),
+                e,
+            )
         })?;
 
     let (_agent_wallet, agent_address) = agent::wallet_and_address(&agent_key_hex)
         .map_err(|e| mcp_err(&format!("Failed to parse agent key: {e}")))?;
 
-    let env_path = agent::save_agent_key_to_env(&agent_key_hex)
-        .map_err(|e| mcp_err(&format!("Failed to save agent key: {e}")))?;
+    let saved_to = agent::save_agent_key_to_env(
+        &state.agent_key_var(),
+        &agent_key_hex,
+        state.env_file.as_deref(),
+    )
+    .map_err(|e| mcp_err(&format!("Failed to save agent key: {e}")))?;
+
+    Ok(NewAgent {
+        name: agent::agent_name_today(),
+        address: agent_address,
+        approved_by: signing_address,
+        saved_to,
+    })
+}
+
+pub async fn create_agent_wallet(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    state.check_trading_window()?;
+    let signing_address = state.require_main_signer()?.address();
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "create agent wallet '{}' signed by {signing_address:#x}.",
+            crate::agent::agent_name_today()
+        )));
+    }
 
+    let agent = approve_and_save_agent(state).await?;
     Ok(CallToolResult::success(vec![Content::text(format!(
         "New agent wallet created.\n\n\
-         Name: {display_name}\n\
-         Agent address: {agent_address:#x}\n\
-         Created by main wallet: {signing_address:#x}\n\
+         Name: {}\n\
+         Agent address: {:#x}\n\
+         Created by main wallet: {:#x}\n\
          Saved to: {}\n\n\
          Restart the MCP server to use the new agent wallet.",
-        env_path.display()
+        agent.name,
+        agent.address,
+        agent.approved_by,
+        agent.saved_to.display()
+    ))]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RenewAgentRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+/// Replace the active agent before its approval lapses: approve a new one
+/// and save it over the old key. The old agent keeps signing until restart.
+pub async fn renew_agent(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+    state.check_trading_window()?;
+    let signing_address = state.require_main_signer()?.address();
+    let current = match (state.agent_address, state.agent_expiry_summary()) {
+        (Some(agent), Some(summary)) => format!("Current agent `{agent:#x}` {summary}."),
+        (Some(agent), None) => format!("Current agent `{agent:#x}`."),
+        (None, _) => "No agent wallet is configured yet.".to_string(),
+    };
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "approve a replacement agent signed by {signing_address:#x} and save it as {}. {current}",
+            state.agent_key_var()
+        )));
+    }
+
+    let agent = approve_and_save_agent(state).await?;
+    state
+        .agent_expiry
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .replacement = Some(agent.address);
+
+    Ok(CallToolResult::success(vec![Content::text(format!(
+        "Agent wallet renewed.\n\n\
+         {current}\n\
+         New agent: {:#x} (\"{}\"), approved by {:#x}\n\
+         Saved to: {} as {}\n\n\
+         The current agent keeps signing until the MCP server restarts with the new key.",
+        agent.address,
+        agent.name,
+        agent.approved_by,
+        agent.saved_to.display(),
+        state.agent_key_var()
     ))]))
 }
 
-pub async fn approve_builder_fee(state: &ServerState) -> Result<CallToolResult, ErrorData> {
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateSubAccountRequest {
+    #[schemars(
+        description = "Sub-account name (1-16 characters: letters, digits, spaces, '-' or '_')"
+    )]
+    pub name: String,
+
+    #[schemars(
+        description = "USDC to move from the main account's perp balance into the new sub-account (optional)"
+    )]
+    pub initial_funding: Option<f64>,
+
+    #[schemars(description = "Must be true to create the sub-account")]
+    pub confirm: bool,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+const MAX_SUB_ACCOUNT_NAME_LEN: usize = 16;
+
+pub async fn create_sub_account(
+    state: &ServerState,
+    req: CreateSubAccountRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let name = req.name.trim();
+    if name.is_empty()
+        || name.chars().count() > MAX_SUB_ACCOUNT_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Invalid sub-account name '{name}'. Use 1-{MAX_SUB_ACCOUNT_NAME_LEN} characters: \
+             letters, digits, spaces, '-' or '_'."
+        ))]));
+    }
+
+    let funding = match req.initial_funding {
+        Some(amount) if !(amount.is_finite() && amount > 0.0) => {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "initial_funding must be a positive USDC amount.",
+            )]));
+        }
+        Some(amount) => Some((amount * 1_000_000.0).round() as u64),
+        None => None,
+    };
+
+    if !req.confirm {
+        return Ok(CallToolResult::error(vec![Content::text(
+            "Creating a sub-account requires confirm: true.",
+        )]));
+    }
+
+    state.check_trading_window()?;
+    let main_signer = state.require_main_signer()?;
+    let signing_address = main_signer.address();
+
+    if state.dry_run {
+        let funding_str = match req.initial_funding {
+            Some(amount) => format!(" and fund it with {amount} USDC"),
+            None => String::new(),
+        };
+        return Ok(dry_run_result(&format!(
+            "create sub-account '{name}' under {signing_address:#x}{funding_str}."
+        )));
+    }
+
+    let nonce = state.next_nonce();
+    let response = hyperliquid::create_sub_account(
+        &state.http,
+        state.chain,
+        main_signer.as_ref(),
+        name,
+        nonce,
+    )
+    .await
+    .map_err(|e| state.action_failed(nonce, "Sub-account creation failed", e))?;
+    state.record_response(nonce, &response);
+
+    let sub_account = response
+        .get("response")
+        .and_then(|r| r.get("data"))
+        .and_then(|d| d.as_str())
+        .and_then(|d| d.parse::<hypersdk::Address>().ok());
+    let Some(sub_account) = sub_account else {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "Sub-account creation failed: {response}"
+        ))]));
+    };
+    tracing::info!(name, address = %sub_account, "Created sub-account");
+
+    let mut output = format!(
+        "## Sub-Account Created\n\n\
+         | Field | Value |\n\
+         |-------|-------|\n\
+         | Name | {name} |\n\
+         | Address | `{sub_account:#x}` |\n\
+         | Master Account | `{signing_address:#x}` |\n"
+    );
+
+    if let (Some(usd), Some(amount)) = (funding, req.initial_funding) {
+        let nonce = state.next_nonce();
+        let result = hyperliquid::sub_account_transfer(
+            &state.http,
+            state.chain,
+            main_signer.as_ref(),
+            sub_account,
+            true,
+            usd,
+            nonce,
+        )
+        .await;
+        match result {
+            Ok(resp) if resp.get("status").and_then(|s| s.as_str()) == Some("ok") => {
+                output.push_str(&format!("| Initial Funding | {amount} USDC |\n"));
+            }
+            Ok(resp) => {
+                state.record_response(nonce, &resp);
+                output.push_str(&format!(
+                    "\n**Funding failed:** {resp}. The sub-account was created but is unfunded.\n"
+                ));
+            }
+            Err(e) => {
+                state.record_action_error(nonce, &e.to_string());
+                output.push_str(&format!(
+                    "\n**Funding failed:** {e}. The sub-account was created but is unfunded.\n"
+                ));
+            }
+        }
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApproveBuilderFeeRequest {
+    #[schemars(description = "Builder address to approve (defaults to this server's builder)")]
+    pub builder_address: Option<String>,
+
+    #[schemars(
+        description = "Max fee rate as a percent, e.g. \"0.01%\" (defaults to HYPERLIQUID_BUILDER_MAX_FEE or the server's fee; max 0.1%)"
+    )]
+    pub max_fee_rate: Option<String>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
+}
+
+pub async fn approve_builder_fee(
+    state: &ServerState,
+    req: ApproveBuilderFeeRequest,
+) -> Result<CallToolResult, ErrorData> {
     use crate::config;
+    use crate::state::ActiveBuilder;
 
+    state.check_trading_window()?;
     let main_signer = state.require_main_signer()?;
     let signing_address = main_signer.address();
-    tracing::info!(address = %signing_address, "Approving builder fee with main wallet");
 
-    let builder_addr: hypersdk::Address = config::BUILDER_ADDRESS
-        .parse()
-        .map_err(|_| mcp_err("Invalid builder address constant"))?;
+    let default_builder = ActiveBuilder::default();
+    let builder_addr: hypersdk::Address = match req.builder_address.as_deref() {
+        Some(addr) => addr
+            .trim()
+            .parse()
+            .map_err(|_| mcp_err(&format!("Invalid builder address '{addr}'")))?,
+        None => default_builder.address,
+    };
+    let custom = builder_addr != default_builder.address;
+
+    let max_fee = match req.max_fee_rate.as_deref() {
+        Some(rate) => config::percent_to_fee(rate)
+            .filter(|fee| *fee > 0)
+            .ok_or_else(|| {
+                mcp_err(&format!(
+                    "Invalid max_fee_rate '{rate}'. Use a positive percent with at most 3 decimals, e.g. \"0.01%\"."
+                ))
+            })?,
+        None => state.builder_max_fee,
+    };
+    if max_fee > config::MAX_BUILDER_FEE {
+        return Ok(CallToolResult::error(vec![Content::text(format!(
+            "max_fee_rate {} exceeds the exchange limit of {}.",
+            config::fee_to_percent(max_fee),
+            config::fee_to_percent(config::MAX_BUILDER_FEE),
+        ))]));
+    }
+    let max_fee_rate = config::fee_to_percent(max_fee);
+
+    // Orders routed to a custom builder pay its approved max; this server's
+    // own builder keeps its fixed per-order fee.
+    let active = ActiveBuilder {
+        address: builder_addr,
+        fee: if custom { max_fee } else { default_builder.fee },
+    };
+
+    tracing::info!(address = %signing_address, builder = %builder_addr, max_fee = %max_fee_rate, "Approving builder fee with main wallet");
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "approve builder {builder_addr:#x} for up to {max_fee_rate} signed by {signing_address:#x}."
+        )));
+    }
 
     let nonce = state.next_nonce();
     let status = hyperliquid::approve_builder_fee(
         &state.http,
         state.chain,
+        state.signature_chain_id,
         main_signer.as_ref(),
         builder_addr,
-        "0.01%",
+        &max_fee_rate,
         nonce,
     )
     .await
     .map_err(|e| {
-        mcp_err(&format!(
-            "Builder fee approval failed (signing address: {signing_address:#x}): {e}"
-        ))
+        state.action_failed(
+            nonce,
+            &format!("Builder fee approval failed (signing address: {signing_address:#x})"),
+            e,
+        )
     })?;
+    state.record_response(nonce, &status);
 
+    state.set_active_builder(active);
     state
         .builder_fee_approved
-        .store(true, std::sync::atomic::Ordering::Relaxed);
+        .store(max_fee >= active.fee, std::sync::atomic::Ordering::Relaxed);
 
-    Ok(CallToolResult::success(vec![Content::text(format!(
-        "Builder fees approved (0.01%) for account {signing_address:#x}. Status: {status}"
-    ))]))
+    let mut output = format!(
+        "## Builder Fee Approved\n\n\
+         | Field | Value |\n\
+         |-------|-------|\n\
+         | Account | `{signing_address:#x}` |\n\
+         | Builder | `{builder_addr:#x}` |\n\
+         | Max Fee Rate | {max_fee_rate} |\n\
+         | Per-Order Fee | {} |\n\
+         | Status | {status} |\n",
+        config::fee_to_percent(active.fee),
+    );
+    if custom {
+        output.push_str(
+            "\nOrders from this server now route the builder fee to this address \
+             until the server restarts.",
+        );
+    }
+    if max_fee < active.fee {
+        output.push_str(&format!(
+            "\n\n**Warning:** the approved max fee is below the per-order builder fee ({}). \
+             Orders will be rejected until a higher max fee is approved.",
+            config::fee_to_percent(active.fee)
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckBuilderFeeRequest {
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 pub async fn check_builder_fee(state: &ServerState) -> Result<CallToolResult, ErrorData> {
     use crate::config;
 
-    let fee_bps = config::BUILDER_FEE as f64 / 10.0;
-    let fee_pct = fee_bps / 100.0;
+    let builder = state.active_builder();
+    let fee_bps = builder.fee as f64 / 10.0;
+    let fee_pct = config::fee_to_percent(builder.fee);
 
     let mut output = format!(
         "## Builder Fee Information\n\n\
-         Builder Address: `{}`\n\
-         Fee Rate: {fee_bps} bps ({fee_pct}%)\n\n",
-        config::BUILDER_ADDRESS
+         Builder Address: `{:#x}`\n\
+         Fee Rate: {fee_bps} bps ({fee_pct})\n\
+         Max Fee to Approve: {}\n\n",
+        builder.address,
+        config::fee_to_percent(state.builder_max_fee),
     );
 
-    if let Some(address) = state.user_address {
+    if state.builder_max_fee < builder.fee {
+        output.push_str(
+            "**Warning:** `HYPERLIQUID_BUILDER_MAX_FEE` is below the per-order fee. \
+             Approving it would cause orders to be rejected.\n\n",
+        );
+    }
+
+    if let Some(address) = state.resolve_builder_fee_account().await {
+        output.push_str(&format!("Checked Account: `{address:#x}`\n\n"));
         let result = state
             .raw_info_request(json!({
                 "type": "maxBuilderFee",
                 "user": format!("{:#x}", address),
-                "builder": config::BUILDER_ADDRESS,
+                "builder": format!("{:#x}", builder.address),
             }))
             .await;
 
         match result {
             Ok(val) => {
-                let max_fee = val.as_str().unwrap_or("0");
-                if max_fee == "0" || max_fee == "0%" {
+                let approved = config::parse_max_builder_fee(&val);
+                state.builder_fee_approved.store(
+                    approved >= builder.fee,
+                    std::sync::atomic::Ordering::Relaxed,
+                );
+                if approved == 0 {
                     output.push_str(
                         "**Status: Not approved**\n\n\
                          You have not yet approved builder fees for this server. \
@@ -181,11 +704,20 @@ pub async fn check_builder_fee(state: &ServerState) -> Result<CallToolResult, Er
                          Requires `HYPERLIQUID_PRIVATE_KEY` (main wallet) in your \
                          `~/.config/hyperliquid-mcp/.env` file.",
                     );
+                } else if approved < builder.fee {
+                    output.push_str(&format!(
+                        "**Status: MISMATCH** (approved max fee rate: {})\n\n\
+                         **Warning:** the per-order builder fee ({fee_pct}) exceeds the approved \
+                         maximum, so orders will be rejected. Run `approve_builder_fee` again \
+                         with a max fee of at least {fee_pct}.",
+                        config::fee_to_percent(approved)
+                    ));
                 } else {
                     output.push_str(&format!(
-                        "**Status: Approved** (max fee rate: {max_fee})\n\n\
+                        "**Status: Approved** (max fee rate: {})\n\n\
                          Builder fees are active. A small fee of {fee_bps} bps is applied \
-                         to each trade to support this MCP server's development."
+                         to each trade to support this MCP server's development.",
+                        config::fee_to_percent(approved)
                     ));
                 }
             }
@@ -2,7 +2,7 @@ use rmcp::{model::*, schemars};
 use serde_json::json;
 
 use crate::hyperliquid;
-use crate::state::{ServerState, mcp_err};
+use crate::state::{ServerState, dry_run_result, mcp_err};
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetVaultDetailsRequest {
@@ -45,12 +45,11 @@ pub async fn get_vault_details(
         .get("description")
         .and_then(|v| v.as_str())
         .unwrap_or("");
-    let portfolio = details.get("portfolio").and_then(|v| v.as_object());
     let followers = details
         .get("followers")
         .and_then(|v| v.as_array())
-        .map(|a| a.len())
-        .unwrap_or(0);
+        .map(|a| a.as_slice())
+        .unwrap_or_default();
 
     let mut output = format!(
         "## Vault: {name}\n\n\
@@ -58,20 +57,69 @@ pub async fn get_vault_details(
          |-------|-------|\n\
          | Vault Address | `{:#x}` |\n\
          | Leader | `{leader}` |\n\
-         | Followers | {followers} |\n",
+         | Followers | {} |\n",
         vault_addr,
+        followers.len(),
     );
 
     if !description.is_empty() {
         output.push_str(&format!("| Description | {} |\n", description));
     }
 
-    if let Some(p) = portfolio {
-        if let Some(acv) = p.get("accountValue").and_then(|v| v.as_str()) {
-            output.push_str(&format!("| Account Value | ${acv} |\n"));
+    if let Some(apr) = details.get("apr").and_then(|v| v.as_f64()) {
+        output.push_str(&format!("| APR | {:.2}% |\n", apr * 100.0));
+    }
+
+    let portfolio = details.get("portfolio").unwrap_or(&serde_json::Value::Null);
+    if let Some(all_time) = portfolio_period(portfolio, "allTime") {
+        if let Some((_, acv)) = history(all_time, "accountValueHistory").last() {
+            output.push_str(&format!("| Account Value | ${acv:.2} |\n"));
         }
-        if let Some(pnl) = p.get("allTimePnl").and_then(|v| v.as_str()) {
-            output.push_str(&format!("| All-Time PnL | ${pnl} |\n"));
+        if let Some((_, pnl)) = history(all_time, "pnlHistory").last() {
+            output.push_str(&format!("| All-Time PnL | ${pnl:.2} |\n"));
+        }
+        if let Some(dd) = max_drawdown(all_time) {
+            output.push_str(&format!("| Max Drawdown | {:.2}% |\n", dd * 100.0));
+        }
+    }
+
+    if let Some(month) = portfolio_period(portfolio, "month") {
+        if let Some((_, pnl)) = history(month, "pnlHistory").last() {
+            output.push_str(&format!("| 30d PnL | ${pnl:.2} |\n"));
+        }
+    }
+
+    if !followers.is_empty() {
+        let num = |f: &serde_json::Value, key: &str| {
+            f.get(key)
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+        let total_equity: f64 = followers.iter().map(|f| num(f, "vaultEquity")).sum();
+        let total_pnl: f64 = followers.iter().map(|f| num(f, "allTimePnl")).sum();
+
+        output.push_str(&format!(
+            "\n## Followers\n\n\
+             Total follower equity: ${total_equity:.2} · Total follower all-time PnL: ${total_pnl:.2}\n\n\
+             | Follower | Equity | PnL | All-Time PnL | Days |\n\
+             |----------|--------|-----|--------------|------|\n"
+        ));
+
+        let mut sorted: Vec<_> = followers.iter().collect();
+        sorted.sort_by(|a, b| num(b, "vaultEquity").total_cmp(&num(a, "vaultEquity")));
+        for f in sorted.iter().take(10) {
+            let user = f.get("user").and_then(|v| v.as_str()).unwrap_or("?");
+            let days = f.get("daysFollowing").and_then(|v| v.as_u64()).unwrap_or(0);
+            output.push_str(&format!(
+                "| `{user}` | ${:.2} | ${:.2} | ${:.2} | {days} |\n",
+                num(f, "vaultEquity"),
+                num(f, "pnl"),
+                num(f, "allTimePnl"),
+            ));
+        }
+        if followers.len() > 10 {
+            output.push_str(&format!("\n…and {} more.\n", followers.len() - 10));
         }
     }
 
@@ -82,22 +130,248 @@ pub async fn get_vault_details(
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
 
+/// The data for one `portfolio` period ("day", "week", "month", "allTime").
+/// `portfolio` is a list of `[period, data]` pairs, as returned by the
+/// `portfolio` info request and inside `vaultDetails`.
+pub(crate) fn portfolio_period<'a>(
+    portfolio: &'a serde_json::Value,
+    period: &str,
+) -> Option<&'a serde_json::Value> {
+    portfolio
+        .as_array()?
+        .iter()
+        .find(|entry| entry.get(0).and_then(|p| p.as_str()) == Some(period))
+        .and_then(|entry| entry.get(1))
+}
+
+/// Parse a `[[timestamp, "value"], ...]` history series.
+pub(crate) fn history(data: &serde_json::Value, key: &str) -> Vec<(u64, f64)> {
+    data.get(key)
+        .and_then(|v| v.as_array())
+        .map(|points| {
+            points
+                .iter()
+                .filter_map(|p| {
+                    let t = p.get(0)?.as_u64()?;
+                    let v = p.get(1)?.as_str()?.parse::<f64>().ok()?;
+                    Some((t, v))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Largest peak-to-trough fall in cumulative PnL, relative to the account
+/// value at the peak. Using PnL rather than account value keeps deposits and
+/// withdrawals from showing up as drawdowns.
+fn max_drawdown(data: &serde_json::Value) -> Option<f64> {
+    let pnl = history(data, "pnlHistory");
+    let acv = history(data, "accountValueHistory");
+    if pnl.is_empty() || pnl.len() != acv.len() {
+        return None;
+    }
+
+    let mut peak_pnl = f64::MIN;
+    let mut peak_acv = 0.0;
+    let mut max_dd: f64 = 0.0;
+    for ((_, p), (_, a)) in pnl.iter().zip(&acv) {
+        if *p > peak_pnl {
+            peak_pnl = *p;
+            peak_acv = *a;
+        }
+        if peak_acv > 0.0 {
+            max_dd = max_dd.max((peak_pnl - p) / peak_acv);
+        }
+    }
+    Some(max_dd)
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListVaultsRequest {
+    #[schemars(description = "Sort by \"tvl\" (default) or \"return\" (30-day return)")]
+    pub sort_by: Option<String>,
+
+    #[schemars(description = "Only vaults whose name contains this text (case-insensitive)")]
+    pub search: Option<String>,
+
+    #[schemars(description = "Number of vaults to return (default 10, max 50)")]
+    pub limit: Option<usize>,
+}
+
+struct VaultRow {
+    name: String,
+    address: String,
+    leader: String,
+    tvl: f64,
+    apr: f64,
+    return_30d: Option<f64>,
+    created_ms: u64,
+}
+
+pub async fn list_vaults(
+    state: &ServerState,
+    req: ListVaultsRequest,
+) -> Result<CallToolResult, ErrorData> {
+    let sort_by_return = match req.sort_by.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("tvl") => false,
+        Some("return" | "returns" | "30d") => true,
+        Some(other) => {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid sort_by '{other}'. Use \"tvl\" or \"return\"."
+            ))]));
+        }
+    };
+    let limit = req.limit.unwrap_or(10).clamp(1, 50);
+    let search = req.search.as_deref().map(str::to_lowercase);
+
+    let listing = hyperliquid::vault_listing(&state.http, state.chain)
+        .await
+        .map_err(|e| mcp_err(&format!("Failed to fetch vault list: {e}")))?;
+
+    let num = |v: Option<&serde_json::Value>| {
+        v.and_then(|x| {
+            x.as_str()
+                .and_then(|s| s.parse::<f64>().ok())
+                .or(x.as_f64())
+        })
+        .unwrap_or(0.0)
+    };
+
+    let mut rows: Vec<VaultRow> = listing
+        .as_array()
+        .map(|a| a.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| {
+            let summary = v.get("summary")?;
+            if summary.get("isClosed").and_then(|c| c.as_bool()) == Some(true) {
+                return None;
+            }
+            let name = summary.get("name")?.as_str()?.to_string();
+            if let Some(ref q) = search {
+                if !name.to_lowercase().contains(q) {
+                    return None;
+                }
+            }
+            let tvl = num(summary.get("tvl"));
+            // `pnls` is a list of `[period, [pnl, ...]]` pairs.
+            let month_pnl = v
+                .get("pnls")
+                .and_then(|p| p.as_array())
+                .and_then(|periods| {
+                    periods
+                        .iter()
+                        .find(|p| p.get(0).and_then(|n| n.as_str()) == Some("month"))
+                })
+                .and_then(|p| p.get(1)?.as_array()?.last().cloned());
+            let return_30d = month_pnl
+                .filter(|_| tvl > 0.0)
+                .map(|pnl| num(Some(&pnl)) / tvl * 100.0);
+            Some(VaultRow {
+                name,
+                address: summary.get("vaultAddress")?.as_str()?.to_string(),
+                leader: summary
+                    .get("leader")
+                    .and_then(|l| l.as_str())
+                    .unwrap_or("?")
+                    .to_string(),
+                tvl,
+                apr: num(v.get("apr")) * 100.0,
+                return_30d,
+                created_ms: summary
+                    .get("createTimeMillis")
+                    .and_then(|t| t.as_u64())
+                    .unwrap_or(0),
+            })
+        })
+        .collect();
+
+    if rows.is_empty() {
+        return Ok(CallToolResult::success(vec![Content::text(
+            "No vaults match.",
+        )]));
+    }
+
+    if sort_by_return {
+        rows.sort_by(|a, b| {
+            b.return_30d
+                .unwrap_or(f64::MIN)
+                .total_cmp(&a.return_30d.unwrap_or(f64::MIN))
+        });
+    } else {
+        rows.sort_by(|a, b| b.tvl.total_cmp(&a.tvl));
+    }
+    let total = rows.len();
+    rows.truncate(limit);
+
+    // The listing has no follower counts; fetch details for the shown vaults.
+    let follower_counts = futures::future::join_all(rows.iter().map(|row| {
+        state.raw_info_request(json!({
+            "type": "vaultDetails",
+            "vaultAddress": row.address,
+        }))
+    }))
+    .await;
+
+    let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+    let mut output = format!(
+        "## Top Vaults by {} ({} of {total})\n\n",
+        if sort_by_return { "30d Return" } else { "TVL" },
+        rows.len(),
+    );
+    output.push_str("| Name | Vault | Leader | TVL | APR | 30d Return | Age | Followers |\n");
+    output.push_str("|------|-------|--------|-----|-----|------------|-----|-----------|\n");
+    for (row, details) in rows.iter().zip(&follower_counts) {
+        let followers = details
+            .as_ref()
+            .ok()
+            .and_then(|d| d.get("followers"))
+            .and_then(|f| f.as_array())
+            .map(|f| f.len().to_string())
+            .unwrap_or_else(|| "—".to_string());
+        let age_days = now_ms.saturating_sub(row.created_ms) / (24 * 60 * 60 * 1000);
+        let return_30d = row
+            .return_30d
+            .map(|r| format!("{r:.2}%"))
+            .unwrap_or_else(|| "—".to_string());
+        output.push_str(&format!(
+            "| {} | `{}` | `{}` | ${:.0} | {:.2}% | {return_30d} | {age_days}d | {followers} |\n",
+            row.name, row.address, row.leader, row.tvl, row.apr,
+        ));
+    }
+
+    Ok(CallToolResult::success(vec![Content::text(output)]))
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct EmergencyCloseAllRequest {
     #[schemars(
         description = "Must be true to confirm emergency close of ALL positions and orders"
     )]
     pub confirm: bool,
+
+    #[schemars(
+        description = "Confirmation phrase, required when the operator has configured one. Ask the user for it; never guess."
+    )]
+    pub confirmation_phrase: Option<String>,
+
+    #[schemars(
+        description = "Coins to leave untouched, e.g. [\"ETH\"] to keep an ETH hedge and its orders (optional)"
+    )]
+    pub exclude_coins: Option<Vec<String>>,
+
+    #[schemars(description = "Account profile to use (optional, defaults to the default profile)")]
+    pub profile: Option<String>,
 }
 
 pub async fn emergency_close_all(
     state: &ServerState,
     req: EmergencyCloseAllRequest,
 ) -> Result<CallToolResult, ErrorData> {
-    use hypersdk::Decimal;
-    use hypersdk::hypercore::{
-        BatchCancel, Cancel, OrderGrouping, OrderRequest, OrderTypePlacement, TimeInForce,
-    };
+    use hypersdk::hypercore::{BatchCancel, Cancel, OrderGrouping, OrderRequest};
+
+    use crate::state::MarketKind;
+    use crate::tools::trading::close_order;
 
     if !req.confirm {
         return Ok(CallToolResult::error(vec![Content::text(
@@ -105,16 +379,61 @@ pub async fn emergency_close_all(
              This will close ALL positions and cancel ALL orders.",
         )]));
     }
+    // The phrase is never echoed: the model should only get it from the user.
+    if let Some(phrase) = state.emergency_phrase.as_deref() {
+        match req.confirmation_phrase.as_deref().map(str::trim) {
+            None | Some("") => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "Emergency close requires confirmation_phrase: the operator has configured \
+                     a phrase (HYPERLIQUID_EMERGENCY_PHRASE). Ask the user for it.",
+                )]));
+            }
+            Some(given) if given != phrase => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "confirmation_phrase does not match. Nothing was cancelled or closed. \
+                     Ask the user for the exact phrase.",
+                )]));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let excluded: Vec<String> = req
+        .exclude_coins
+        .iter()
+        .flatten()
+        .map(|c| state.resolve_market(c, None).map(|m| m.coin))
+        .collect::<Result<_, _>>()?;
+    let keep = |coin: &str| excluded.iter().any(|c| c == coin);
+    let excluded_str = if excluded.is_empty() {
+        String::new()
+    } else {
+        format!(" except {}", excluded.join(", "))
+    };
 
     let address = state.query_address()?;
     let signer = state.require_signer()?;
+
+    if state.dry_run {
+        return Ok(dry_run_result(&format!(
+            "cancel all open orders and close all positions{excluded_str} with reduce-only IOC orders."
+        )));
+    }
+
     let mut output = "## Emergency Close All\n\n".to_string();
+    if !excluded.is_empty() {
+        output.push_str(&format!(
+            "Leaving {} untouched (positions and orders).\n",
+            excluded.join(", ")
+        ));
+    }
 
     let orders = state
-        .client
-        .open_orders(address, None)
+        .api
+        .open_orders(address)
         .await
         .map_err(|e| mcp_err(&format!("Failed to fetch open orders: {e}")))?;
+    let orders: Vec<_> = orders.into_iter().filter(|o| !keep(&o.coin)).collect();
 
     if !orders.is_empty() {
         let cancels: Vec<Cancel> = orders
@@ -139,7 +458,7 @@ pub async fn emergency_close_all(
                 None,
             )
             .await
-            .map_err(|e| mcp_err(&format!("Cancel all failed: {e}")))?;
+            .map_err(|e| state.action_failed(nonce, "Cancel all failed", e))?;
 
         output.push_str(&format!("Cancelled {cancel_count} orders.\n"));
     } else {
@@ -147,78 +466,90 @@ pub async fn emergency_close_all(
     }
 
     let user_state = state
-        .client
-        .clearinghouse_state(address, None)
+        .api
+        .clearinghouse_state(address)
         .await
         .map_err(|e| mcp_err(&format!("Failed to fetch positions: {e}")))?;
 
     let positions: Vec<_> = user_state
         .asset_positions
         .iter()
-        .filter(|p| !p.position.szi.is_zero())
+        .filter(|p| !p.position.szi.is_zero() && !keep(&p.position.coin))
         .collect();
 
+    let mut skipped: Vec<(String, String)> = Vec::new();
     if !positions.is_empty() {
         let mids = state
-            .client
-            .all_mids(None)
+            .api
+            .all_mids()
             .await
             .map_err(|e| mcp_err(&format!("Failed to fetch prices: {e}")))?;
 
-        // 5%
-        let slippage = Decimal::new(5, 2);
-
-        let close_orders: Vec<OrderRequest> = positions
-            .iter()
-            .filter_map(|ap| {
-                let p = &ap.position;
-                let asset = state.asset_map.get(&p.coin).copied()?;
-                let is_buy = p.szi.is_sign_negative();
-                let size = p.szi.abs();
-                let mid = mids.get(&p.coin)?;
-                let limit_px = if is_buy {
-                    mid * (Decimal::ONE + slippage)
-                } else {
-                    mid * (Decimal::ONE - slippage)
-                };
-                Some(OrderRequest {
-                    asset,
-                    is_buy,
-                    reduce_only: true,
-                    limit_px,
-                    sz: size,
-                    cloid: {
-                        let uuid = uuid::Uuid::new_v4();
-                        alloy::primitives::B128::from_slice(uuid.as_bytes())
-                    },
-                    order_type: OrderTypePlacement::Limit {
-                        tif: TimeInForce::Ioc,
-                    },
-                })
-            })
-            .collect();
+        let mut close_orders: Vec<OrderRequest> = Vec::new();
+        for ap in &positions {
+            let p = &ap.position;
+            let order = state
+                .resolve_market(&p.coin, Some(MarketKind::Perp))
+                .map_err(|e| e.to_string())
+                .and_then(|market| {
+                    let mid = mids.get(&p.coin).ok_or("no mid price")?;
+                    close_order(state, &market, p.szi, *mid)
+                });
+            match order {
+                Ok(mut order) => {
+                    order.cloid = state
+                        .cloids
+                        .issue_new(format!("emergency_close_all: {}", p.coin));
+                    close_orders.push(order);
+                }
+                Err(reason) => {
+                    tracing::warn!(coin = %p.coin, error = %reason, "Skipping emergency close");
+                    skipped.push((p.coin.clone(), reason));
+                }
+            }
+        }
 
-        let close_count = close_orders.len();
-        let nonce = state.next_nonce();
-        hyperliquid::place_order_with_builder(
-            &state.http,
-            state.chain,
-            signer.as_ref(),
-            close_orders,
-            OrderGrouping::Na,
-            Some(state.builder_info()),
-            nonce,
-            state.vault_addr(),
-        )
-        .await
-        .map_err(|e| mcp_err(&format!("Close positions failed: {e}")))?;
+        if !close_orders.is_empty() {
+            let close_count = close_orders.len();
+            let cloids: Vec<_> = close_orders.iter().map(|o| o.cloid).collect();
+            let nonce = state.next_nonce();
+            let result = hyperliquid::place_order_with_builder(
+                state.api.as_ref(),
+                state.chain,
+                signer.as_ref(),
+                close_orders,
+                OrderGrouping::Na,
+                Some(state.builder_info()),
+                nonce,
+                state.vault_addr(),
+            )
+            .await;
+            state.cloids.record_response(&cloids, &result);
+            let response =
+                result.map_err(|e| state.action_failed(nonce, "Close positions failed", e))?;
+            state.record_response(nonce, &response);
 
-        output.push_str(&format!("Closed {close_count} positions.\n"));
+            output.push_str(&format!("Closed {close_count} positions.\n"));
+        }
     } else {
         output.push_str("No open positions to close.\n");
     }
 
     state.cache.invalidate_user_data().await;
 
+    if !skipped.is_empty() {
+        output.push_str(&format!(
+            "\n**{} positions were NOT closed and are still open:**\n\n",
+            skipped.len()
+        ));
+        output.push_str("| Coin | Reason |\n");
+        output.push_str("|------|--------|\n");
+        for (coin, reason) in &skipped {
+            output.push_str(&format!("| {coin} | {reason} |\n"));
+        }
+        output.push_str("\nClose them manually with close_position or place_order.\n");
+        return Ok(CallToolResult::error(vec![Content::text(output)]));
+    }
+
     Ok(CallToolResult::success(vec![Content::text(output)]))
 }
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Timelike, Utc, Weekday};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// How far ahead to look for the window opening or closing. A week covers
+/// every schedule; the extra day covers overnight ranges.
+const LOOKAHEAD_MINUTES: i64 = 8 * MINUTES_PER_DAY as i64;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// When write tools may trade, from `HYPERLIQUID_TRADING_HOURS`, e.g.
+/// "08:00-22:00 UTC, Mon-Fri". A range whose end is before its start runs
+/// overnight; the hours after midnight belong to the day it started on.
+#[derive(Debug, Clone)]
+pub struct TradingHours {
+    /// Minutes after midnight, end exclusive. `end < start` crosses midnight.
+    ranges: Vec<(u32, u32)>,
+    /// Indexed Monday first.
+    days: [bool; 7],
+    offset: FixedOffset,
+    /// The setting as written, for display.
+    spec: String,
+}
+
+impl TradingHours {
+    /// Parse comma-separated parts: time ranges (`08:00-22:00`), each
+    /// optionally followed by a zone (`UTC`, `UTC+2`, `UTC-05:30`), and days
+    /// or day ranges (`Mon-Fri`, `Sat`). Without days, every day is allowed.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        let mut days = [false; 7];
+        let mut any_day = false;
+        let mut offset: Option<FixedOffset> = None;
+
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            if part.contains(':') {
+                let (range, zone) = match part.split_once(char::is_whitespace) {
+                    Some((range, zone)) => (range, Some(zone.trim())),
+                    None => (part, None),
+                };
+                ranges.push(parse_range(range)?);
+                if let Some(zone) = zone {
+                    let parsed = parse_zone(zone)?;
+                    if offset.is_some_and(|o| o != parsed) {
+                        return Err("all time ranges must use the same time zone".to_string());
+                    }
+                    offset = Some(parsed);
+                }
+            } else {
+                let (first, last) = match part.split_once('-') {
+                    Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+                    None => {
+                        let day = parse_day(part)?;
+                        (day, day)
+                    }
+                };
+                // Ranges like Fri-Mon wrap around the weekend.
+                let mut day = first;
+                loop {
+                    days[day] = true;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+                any_day = true;
+            }
+        }
+
+        if ranges.is_empty() {
+            return Err("expected at least one time range such as 08:00-22:00".to_string());
+        }
+        Ok(Self {
+            ranges,
+            days: if any_day { days } else { [true; 7] },
+            offset: offset.unwrap_or_else(|| FixedOffset::east_opt(0).expect("zero offset")),
+            spec: spec.trim().to_string(),
+        })
+    }
+
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.offset);
+        let minute = local.hour() * 60 + local.minute();
+        let today = local.weekday().num_days_from_monday() as usize;
+        let yesterday = (today + 6) % 7;
+        self.ranges.iter().any(|&(start, end)| {
+            if start < end {
+                self.days[today] && (start..end).contains(&minute)
+            } else {
+                (self.days[today] && minute >= start) || (self.days[yesterday] && minute < end)
+            }
+        })
+    }
+
+    /// The next minute at which the window opens or closes, whichever
+    /// applies. None when it never changes (open around the clock).
+    pub fn next_change(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let open = self.is_open(now);
+        let start = now.with_second(0)?.with_nanosecond(0)?;
+        (1..=LOOKAHEAD_MINUTES)
+            .map(|m| start + Duration::minutes(m))
+            .find(|t| self.is_open(*t) != open)
+    }
+
+    /// Format a time in the schedule's zone, e.g. "Mon 2026-10-19 08:00 UTC".
+    pub fn format_time(&self, at: DateTime<Utc>) -> String {
+        let local = at.with_timezone(&self.offset);
+        format!(
+            "{} {}",
+            local.format("%a %Y-%m-%d %H:%M"),
+            self.zone_label()
+        )
+    }
+
+    fn zone_label(&self) -> String {
+        let seconds = self.offset.local_minus_utc();
+        if seconds == 0 {
+            return "UTC".to_string();
+        }
+        let sign = if seconds < 0 { '-' } else { '+' };
+        let minutes = seconds.abs() / 60;
+        format!("UTC{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+
+    /// "08:00-22:00 UTC on Mon, Tue, Wed, Thu, Fri".
+    pub fn describe(&self) -> String {
+        let ranges: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|&(start, end)| match end {
+                0 => format!("{}-24:00", hhmm(start)),
+                end if end < start => format!("{}-{} (overnight)", hhmm(start), hhmm(end)),
+                end => format!("{}-{}", hhmm(start), hhmm(end)),
+            })
+            .collect();
+        let days = if self.days.iter().all(|d| *d) {
+            "every day".to_string()
+        } else {
+            let names: Vec<String> = WEEKDAYS
+                .iter()
+                .zip(self.days)
+                .filter(|(_, on)| *on)
+                .map(|(day, _)| day.to_string())
+                .collect();
+            format!("on {}", names.join(", "))
+        };
+        format!("{} {} {days}", ranges.join(", "), self.zone_label())
+    }
+}
+
+fn hhmm(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn parse_time(value: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid time '{value}', expected HH:MM");
+    let (hours, minutes) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 24 || minutes >= 60 || hours * 60 + minutes > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+fn parse_range(value: &str) -> Result<(u32, u32), String> {
+    let (start, end) = value
+        .split_once('-')
+        .ok_or_else(|| format!("invalid time range '{value}', expected HH:MM-HH:MM"))?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start == end || start == MINUTES_PER_DAY {
+        return Err(format!("time range '{value}' is empty"));
+    }
+    // 24:00 only makes sense as an end; treat it as midnight.
+    Ok((start, end % MINUTES_PER_DAY))
+}
+
+fn parse_day(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    value
+        .parse::<Weekday>()
+        .map(|day| day.num_days_from_monday() as usize)
+        .map_err(|_| format!("invalid day '{value}', expected a weekday such as Mon or Friday"))
+}
+
+/// "UTC", "GMT", "UTC+2", "UTC-05:30" or a bare "+02:00".
+fn parse_zone(value: &str) -> Result<FixedOffset, String> {
+    let invalid = || format!("invalid time zone '{value}', expected UTC or an offset like UTC+2");
+    let upper = value.trim().to_uppercase();
+    let rest = upper
+        .strip_prefix("UTC")
+        .or_else(|| upper.strip_prefix("GMT"))
+        .unwrap_or(&upper);
+    if rest.is_empty() {
+        return Ok(FixedOffset::east_opt(0).expect("zero offset"));
+    }
+    // U+2212 is the minus sign typeset offsets are often copied with.
+    let (sign, rest) = if let Some(rest) = rest.strip_prefix('+') {
+        (1, rest)
+    } else if let Some(rest) = rest.strip_prefix(['-', '\u{2212}']) {
+        (-1, rest)
+    } else {
+        return Err(invalid());
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // October 2026: the 12th is a Monday.
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_reads_ranges_zones_and_days() {
+        let hours = TradingHours::parse("08:00-22:00 UTC+2, Mon-Fri").unwrap();
+        assert_eq!(
+            hours.describe(),
+            "08:00-22:00 UTC+02:00 on Mon, Tue, Wed, Thu, Fri"
+        );
+
+        let hours = TradingHours::parse("22:00-24:00, 00:00-02:00 GMT, Fri-Mon").unwrap();
+        assert_eq!(
+            hours.describe(),
+            "22:00-24:00, 00:00-02:00 UTC on Mon, Fri, Sat, Sun"
+        );
+
+        let hours = TradingHours::parse("09:30-16:00 UTC-05:30").unwrap();
+        assert_eq!(hours.describe(), "09:30-16:00 UTC-05:30 every day");
+        let hours = TradingHours::parse("09:30-16:00 utc\u{2212}5").unwrap();
+        assert_eq!(hours.describe(), "09:30-16:00 UTC-05:00 every day");
+
+        for (spec, error) in [
+            (
+                "Mon-Fri",
+                "expected at least one time range such as 08:00-22:00",
+            ),
+            ("08:00-08:00", "time range '08:00-08:00' is empty"),
+            ("08:00-25:00", "invalid time '25:00', expected HH:MM"),
+            (
+                "08:00-22:00, Someday",
+                "invalid day 'Someday', expected a weekday such as Mon or Friday",
+            ),
+            (
+                "08:00-22:00 UTC+2, 23:00-23:30 UTC",
+                "all time ranges must use the same time zone",
+            ),
+            (
+                "08:00-22:00 UTC×5",
+                "invalid time zone 'UTC×5', expected UTC or an offset like UTC+2",
+            ),
+            (
+                "08:00-22:00 UTC+15",
+                "invalid time zone 'UTC+15', expected UTC or an offset like UTC+2",
+            ),
+        ] {
+            assert_eq!(TradingHours::parse(spec).unwrap_err(), error, "{spec}");
+        }
+    }
+
+    #[test]
+    fn is_open_respects_days_and_zone() {
+        let hours = TradingHours::parse("08:00-22:00 UTC+2, Mon-Fri").unwrap();
+        assert!(!hours.is_open(at(12, 5, 59)));
+        assert!(hours.is_open(at(12, 6, 0)));
+        assert!(hours.is_open(at(16, 19, 59)));
+        assert!(!hours.is_open(at(16, 20, 0)));
+        assert!(!hours.is_open(at(17, 12, 0)));
+        assert_eq!(hours.next_change(at(16, 19, 30)), Some(at(16, 20, 0)));
+        assert_eq!(hours.next_change(at(17, 12, 0)), Some(at(19, 6, 0)));
+    }
+
+    #[test]
+    fn overnight_ranges_belong_to_the_day_they_start() {
+        let hours = TradingHours::parse("22:00-06:00 UTC, Fri").unwrap();
+        assert_eq!(hours.describe(), "22:00-06:00 (overnight) UTC on Fri");
+        // Friday's session runs into Saturday morning.
+        assert!(hours.is_open(at(16, 23, 0)));
+        assert!(hours.is_open(at(17, 5, 59)));
+        assert!(!hours.is_open(at(17, 6, 0)));
+        assert!(!hours.is_open(at(17, 22, 30)));
+        // Friday before dawn is Thursday's session, which is off.
+        assert!(!hours.is_open(at(16, 5, 0)));
+
+        let always = TradingHours::parse("00:00-24:00").unwrap();
+        assert!(always.is_open(at(18, 23, 59)));
+        assert_eq!(always.next_change(at(18, 23, 59)), None);
+    }
+}
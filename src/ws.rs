@@ -3,18 +3,20 @@ use std::sync::Arc;
 
 use futures::StreamExt;
 use hypersdk::Address;
-use hypersdk::hypercore::types::{Incoming, Subscription};
+use hypersdk::hypercore::types::{Fill, Incoming, Subscription};
 use hypersdk::hypercore::ws::{ConnectionHandle, ConnectionStream, Event};
 use hypersdk::hypercore::{self, Chain};
 use rust_decimal::Decimal;
 use serde_json::json;
 use tokio::sync::watch;
 
-use crate::cache::{CachedValue, WsCache};
+use crate::cache::{BookSample, CachedValue, FillSource, MAX_RECENT_FILLS, WsCache};
+use crate::meta::MarketMeta;
+use crate::state::MarketKind;
 
 pub fn cache_only() -> Arc<WsCache> {
     let (_tx, rx) = watch::channel(HashMap::<String, Decimal>::new());
-    Arc::new(WsCache::new(rx))
+    Arc::new(WsCache::new(rx, None))
 }
 
 pub fn spawn(chain: Chain, user_address: Option<Address>, http: reqwest::Client) -> Arc<WsCache> {
@@ -26,7 +28,7 @@ pub fn spawn(chain: Chain, user_address: Option<Address>, http: reqwest::Client)
     let (handle, stream) = ws.split();
 
     let (mids_tx, mids_rx) = watch::channel(HashMap::<String, Decimal>::new());
-    let cache = Arc::new(WsCache::new(mids_rx));
+    let cache = Arc::new(WsCache::new(mids_rx, Some(handle.clone())));
 
     handle.subscribe(Subscription::AllMids { dex: None });
     if let Some(user) = user_address {
@@ -37,6 +39,15 @@ pub fn spawn(chain: Chain, user_address: Option<Address>, http: reqwest::Client)
     let event_cache = Arc::clone(&cache);
     tokio::spawn(event_loop(stream, handle, event_cache, mids_tx));
 
+    if let Some(user) = user_address {
+        tokio::spawn(backfill_fills(
+            http.clone(),
+            chain,
+            user,
+            Arc::clone(&cache),
+        ));
+    }
+
     let poll_cache = Arc::clone(&cache);
     tokio::spawn(poll_meta_loop(http, chain, poll_cache));
 
@@ -64,13 +75,23 @@ async fn handle_message(
     cache: &WsCache,
     mids_tx: &watch::Sender<HashMap<String, Decimal>>,
 ) {
+    cache.last_message_ms.store(
+        chrono::Utc::now().timestamp_millis() as u64,
+        std::sync::atomic::Ordering::Relaxed,
+    );
     match msg {
         Incoming::AllMids { mids, .. } => {
             let _ = mids_tx.send(mids);
         }
-        Incoming::OrderUpdates(_) | Incoming::UserFills { .. } => {
+        Incoming::OrderUpdates(updates) => {
+            cache.invalidate_user_data().await;
+            cache.record_order_updates(&updates).await;
+        }
+        Incoming::UserFills { fills, .. } => {
             cache.invalidate_user_data().await;
+            cache.record_fills(&fills).await;
         }
+        Incoming::L2Book(book) => cache.record_book(&book),
         _ => {}
     }
 }
@@ -96,6 +117,118 @@ async fn poll_meta_loop(http: reqwest::Client, chain: Chain, cache: Arc<WsCache>
     }
 }
 
+/// Seed the recent-fills buffer from `userFills`, so fills from before the
+/// feed connected are there too.
+async fn backfill_fills(http: reqwest::Client, chain: Chain, user: Address, cache: Arc<WsCache>) {
+    let base_url = match chain {
+        Chain::Mainnet => "https://api.hyperliquid.xyz",
+        Chain::Testnet => "https://api.hyperliquid-testnet.xyz",
+    };
+    let fills = match http
+        .post(format!("{base_url}/info"))
+        .json(&json!({"type": "userFills", "user": format!("{user:#x}")}))
+        .send()
+        .await
+    {
+        Ok(resp) => resp.json::<Vec<Fill>>().await,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to backfill recent fills");
+            return;
+        }
+    };
+    match fills {
+        Ok(mut fills) => {
+            fills.truncate(MAX_RECENT_FILLS);
+            cache.record_recent_fills(&fills, FillSource::Rest).await;
+            tracing::debug!(count = fills.len(), "Backfilled recent fills");
+        }
+        Err(e) => tracing::warn!(error = %e, "Failed to parse userFills response"),
+    }
+}
+
+/// Poll `l2Book` for each coin every `interval` and record its top of book.
+pub fn spawn_book_sampler(
+    http: reqwest::Client,
+    chain: Chain,
+    cache: Arc<WsCache>,
+    coins: Vec<String>,
+    interval: std::time::Duration,
+) {
+    let base_url = match chain {
+        Chain::Mainnet => "https://api.hyperliquid.xyz",
+        Chain::Testnet => "https://api.hyperliquid-testnet.xyz",
+    };
+    let url = format!("{base_url}/info");
+    tracing::info!(?coins, ?interval, "Sampling order books");
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for coin in &coins {
+                let book = match http
+                    .post(&url)
+                    .json(&json!({"type": "l2Book", "coin": coin}))
+                    .send()
+                    .await
+                {
+                    Ok(resp) => resp.json::<serde_json::Value>().await,
+                    Err(e) => {
+                        tracing::warn!(coin, error = %e, "Failed to sample order book");
+                        continue;
+                    }
+                };
+                match book.ok().as_ref().and_then(book_sample) {
+                    Some(sample) => cache.record_book_sample(coin, sample).await,
+                    None => tracing::debug!(coin, "Order book sample had no two-sided book"),
+                }
+            }
+        }
+    });
+}
+
+/// Best bid/ask and depth within 0.5% of mid from an `l2Book` response.
+fn book_sample(book: &serde_json::Value) -> Option<BookSample> {
+    let levels = book.get("levels")?.as_array()?;
+    let side = |i: usize| -> Vec<(f64, f64)> {
+        levels
+            .get(i)
+            .and_then(|l| l.as_array())
+            .map(|l| {
+                l.iter()
+                    .filter_map(|lvl| {
+                        let px = lvl.get("px")?.as_str()?.parse::<f64>().ok()?;
+                        let sz = lvl.get("sz")?.as_str()?.parse::<f64>().ok()?;
+                        Some((px, sz))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let (bids, asks) = (side(0), side(1));
+    let bid = bids.first()?.0;
+    let ask = asks.first()?.0;
+    // A crossed snapshot would record a negative spread.
+    if ask <= bid {
+        return None;
+    }
+    let mid = (bid + ask) / 2.0;
+    let depth = |levels: &[(f64, f64)]| -> f64 {
+        levels
+            .iter()
+            .filter(|(px, _)| (px - mid).abs() / mid <= 0.005)
+            .map(|(px, sz)| px * sz)
+            .sum()
+    };
+    Some(BookSample {
+        time: chrono::Utc::now().timestamp_millis() as u64,
+        bid,
+        ask,
+        bid_depth: depth(&bids),
+        ask_depth: depth(&asks),
+    })
+}
+
 async fn fetch_and_cache_meta(http: &reqwest::Client, url: &str, cache: &WsCache) {
     match http
         .post(url)
@@ -105,7 +238,9 @@ async fn fetch_and_cache_meta(http: &reqwest::Client, url: &str, cache: &WsCache
     {
         Ok(resp) => match resp.json::<serde_json::Value>().await {
             Ok(data) => {
-                *cache.meta_cache.write().await = Some(CachedValue::new(data));
+                let meta = MarketMeta::parse(&data, MarketKind::Perp);
+                cache.record_meta_snapshot(&meta).await;
+                *cache.meta_cache.write().await = Some(CachedValue::new(Arc::new(meta)));
                 tracing::debug!("Polled metaAndAssetCtxs");
             }
             Err(e) => tracing::warn!(error = %e, "Failed to parse metaAndAssetCtxs response"),
@@ -121,7 +256,8 @@ async fn fetch_and_cache_meta(http: &reqwest::Client, url: &str, cache: &WsCache
     {
         Ok(resp) => match resp.json::<serde_json::Value>().await {
             Ok(data) => {
-                *cache.spot_meta_cache.write().await = Some(CachedValue::new(data));
+                let meta = MarketMeta::parse(&data, MarketKind::Spot);
+                *cache.spot_meta_cache.write().await = Some(CachedValue::new(Arc::new(meta)));
                 tracing::debug!("Polled spotMetaAndAssetCtxs");
             }
             Err(e) => tracing::warn!(error = %e, "Failed to parse spotMetaAndAssetCtxs response"),